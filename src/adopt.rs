@@ -0,0 +1,286 @@
+use poise::serenity_prelude::*;
+use tokio::time::{Duration, sleep};
+
+use crate::join_menu::truncate_chars;
+use crate::reaction_emoji::ReactionEmojis;
+use crate::recruit_message::{RecruitMessage, extract_toml_block};
+use crate::storage::RecruitLocation;
+use crate::{Data, Error, bot_permissions, send_ephemeral};
+
+const DEFAULT_DEPTH: u16 = 200;
+/// 1 回の REST API で取得できるメッセージの数
+const PAGE_SIZE: u16 = 100;
+/// 履歴を取得する間隔 (チャンネルをまたいでも同じ間隔を空けます)
+const PAGE_INTERVAL: Duration = Duration::from_secs(1);
+/// 結果に表示する, 登録できなかった募集の件数
+const MAX_LISTED_FAILURES: usize = 10;
+const MAX_REASON_CHARS: usize = 80;
+
+/// チャンネルの履歴の, Bot が投稿したメッセージから推測した募集の状態
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inference {
+    /// toml ブロックがなく, 募集ではありません
+    NotRecruit,
+    /// 受付中の募集
+    Open,
+    /// 開始した, または受付を終了した募集
+    Started,
+    Cancelled,
+    /// 募集として扱えない理由
+    Broken(String),
+}
+
+/// 募集メッセージの本文と, Bot のリアクションが残っているかどうかから状態を推測します
+pub fn infer(content: &str, has_bot_reactions: bool, emojis: &ReactionEmojis) -> Inference {
+    if extract_toml_block(content).is_none() {
+        return Inference::NotRecruit;
+    }
+    let recruit_message = match RecruitMessage::parse(content) {
+        Ok(recruit_message) => recruit_message,
+        Err(err) => {
+            let err = truncate_chars(&err.replace('\n', " "), MAX_REASON_CHARS);
+            return Inference::Broken(format!("募集設定を読み取れません ({err})"));
+        }
+    };
+    let RecruitMessage { config, state } = recruit_message;
+    if state.cancelled {
+        return Inference::Cancelled;
+    }
+    if state.started || state.closed_entries.is_some() {
+        return Inference::Started;
+    }
+    if !has_bot_reactions && !config.reaction_types(emojis).is_empty() {
+        return Inference::Broken(
+            "Bot のリアクションがありません (`/repair_recruit` で付け直せます)".to_string(),
+        );
+    }
+    Inference::Open
+}
+
+/// 取り込んだ結果の件数
+#[derive(Debug, Default)]
+struct AdoptReport {
+    scanned: usize,
+    open: usize,
+    started: usize,
+    /// すでに登録されていた募集
+    tracked: usize,
+    cancelled: usize,
+    failures: Vec<String>,
+}
+
+impl AdoptReport {
+    fn render(&self, channels: usize) -> String {
+        let mut lines = vec![
+            format!(
+                "{channels} チャンネルの {} 件のメッセージを確認しました",
+                self.scanned
+            ),
+            format!(
+                "登録: {} 件 (受付中 {} 件、開始済み {} 件)",
+                self.open + self.started,
+                self.open,
+                self.started
+            ),
+            format!("登録済み: {} 件", self.tracked),
+            format!("対象外 (キャンセル済み): {} 件", self.cancelled),
+            format!("失敗: {} 件", self.failures.len()),
+        ];
+        lines.extend(
+            self.failures
+                .iter()
+                .take(MAX_LISTED_FAILURES)
+                .map(|failure| format!("- {failure}")),
+        );
+        if MAX_LISTED_FAILURES < self.failures.len() {
+            lines.push(format!(
+                "- 他 {} 件",
+                self.failures.len() - MAX_LISTED_FAILURES
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// チャンネルの新しい方から `depth` 件のメッセージを, 1 ページごとに間隔を空けて取得します
+async fn scan_history(
+    ctx: &Context,
+    channel_id: ChannelId,
+    depth: u16,
+) -> Result<Vec<Message>, Error> {
+    let mut messages: Vec<Message> = Vec::new();
+    while messages.len() < usize::from(depth) {
+        let remaining = depth - messages.len() as u16;
+        let mut request = GetMessages::new().limit(remaining.min(PAGE_SIZE) as u8);
+        if let Some(oldest) = messages.last() {
+            request = request.before(oldest.id);
+        }
+        sleep(PAGE_INTERVAL).await;
+        let page = channel_id.messages(ctx, request).await?;
+        let done = page.len() < usize::from(remaining.min(PAGE_SIZE));
+        messages.extend(page);
+        if done {
+            break;
+        }
+    }
+    Ok(messages)
+}
+
+async fn adopt_channel(
+    ctx: &Context,
+    data: &Data,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    depth: u16,
+    report: &mut AdoptReport,
+) {
+    let messages = match scan_history(ctx, channel_id, depth).await {
+        Ok(messages) => messages,
+        Err(err) => {
+            eprintln!("Failed to scan history of {channel_id}: {err}");
+            report.failures.push(format!(
+                "{}: 履歴を読み取れませんでした",
+                channel_id.mention()
+            ));
+            return;
+        }
+    };
+    let bot_id = ctx.cache.current_user().id;
+    for message in messages {
+        report.scanned += 1;
+        if message.author.id != bot_id {
+            continue;
+        }
+        let has_bot_reactions = message.reactions.iter().any(|reaction| reaction.me);
+        let inference = infer(&message.content, has_bot_reactions, &data.config.emojis);
+        let started = match inference {
+            Inference::NotRecruit => continue,
+            Inference::Cancelled => {
+                report.cancelled += 1;
+                continue;
+            }
+            Inference::Broken(reason) => {
+                report
+                    .failures
+                    .push(format!("{}: {reason}", message.link()));
+                continue;
+            }
+            Inference::Open => false,
+            Inference::Started => true,
+        };
+        if data.storage.has_recruit_location(message.id) {
+            report.tracked += 1;
+            continue;
+        }
+        let location = RecruitLocation {
+            guild_id: Some(guild_id),
+            channel_id,
+        };
+        if let Err(err) = data.storage.remember_recruit_location(message.id, location) {
+            eprintln!("Failed to save recruit location: {err}");
+            report
+                .failures
+                .push(format!("{}: 保存できませんでした", message.link()));
+            continue;
+        }
+        if started {
+            report.started += 1;
+        } else {
+            report.open += 1;
+        }
+    }
+}
+
+/// 以前のバージョンが投稿した募集を履歴から探して登録します
+///
+/// 登録した募集はサーバーを抜けたメンバーの処理や今日の募集の一覧などの対象になります。
+/// 登録済みの募集は数えるだけなので, 何度実行しても同じ結果になります。
+#[poise::command(
+    slash_command,
+    guild_only,
+    ephemeral,
+    default_member_permissions = "MANAGE_GUILD",
+    rename = "adopt_recruits"
+)]
+pub async fn adopt_recruits(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "探すチャンネル (省略するとこのチャンネル)"]
+    #[channel_types("Text")]
+    channel: Option<GuildChannel>,
+    #[description = "サーバーのすべてのテキストチャンネルを探す"] all_channels: Option<bool>,
+    #[description = "チャンネルごとに遡るメッセージの件数 (既定: 200)"]
+    #[min = 1]
+    #[max = 1000]
+    depth: Option<u16>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.defer_ephemeral().await?;
+    let channel_ids: Vec<ChannelId> = if all_channels == Some(true) {
+        let mut channels: Vec<GuildChannel> = guild_id
+            .channels(ctx)
+            .await?
+            .into_values()
+            .filter(|channel| channel.kind == ChannelType::Text)
+            // 読めないチャンネルは失敗として数えずに除きます
+            .filter(|channel| {
+                bot_permissions(ctx.serenity_context(), guild_id, channel.id).is_none_or(
+                    |permissions| permissions.view_channel() && permissions.read_message_history(),
+                )
+            })
+            .collect();
+        channels.sort_by_key(|channel| channel.position);
+        channels.into_iter().map(|channel| channel.id).collect()
+    } else {
+        vec![channel.map_or(ctx.channel_id(), |channel| channel.id)]
+    };
+
+    let data = ctx.data();
+    let mut report = AdoptReport::default();
+    for &channel_id in &channel_ids {
+        adopt_channel(
+            ctx.serenity_context(),
+            data,
+            guild_id,
+            channel_id,
+            depth.unwrap_or(DEFAULT_DEPTH),
+            &mut report,
+        )
+        .await;
+    }
+    if 0 < report.open + report.started {
+        data.summary_board.mark(guild_id);
+    }
+    send_ephemeral(ctx, &report.render(channel_ids.len())).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recruit_message::RecruitConfig;
+
+    #[test]
+    fn recruit_states_are_inferred_from_history() {
+        let emojis = ReactionEmojis::default();
+        let mut recruit_message = RecruitMessage::new(RecruitConfig::for_test("Apex", 3));
+        let open = recruit_message.render(&emojis);
+        assert_eq!(infer(&open, true, &emojis), Inference::Open);
+        assert!(matches!(
+            infer(&open, false, &emojis),
+            Inference::Broken(reason) if reason.contains("/repair_recruit")
+        ));
+
+        recruit_message.state.started = true;
+        let started = recruit_message.render(&emojis);
+        assert_eq!(infer(&started, false, &emojis), Inference::Started);
+        recruit_message.state.cancelled = true;
+        let cancelled = recruit_message.render(&emojis);
+        assert_eq!(infer(&cancelled, false, &emojis), Inference::Cancelled);
+
+        assert_eq!(infer("Apex やる人", true, &emojis), Inference::NotRecruit);
+        assert!(matches!(
+            infer("```toml\ngame_title = 1\n```", true, &emojis),
+            Inference::Broken(_)
+        ));
+    }
+}
@@ -0,0 +1,572 @@
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use hyper::server::conn::http1;
+use hyper_util::rt::{TokioIo, TokioTimer};
+use hyper_util::service::TowerToHyperService;
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use tokio::time::Duration;
+
+use crate::discord_api::DiscordApi;
+use crate::recruit_message::RecruitMessage;
+use crate::rejection::TtlCache;
+use crate::storage::RecruitRecord;
+use crate::{Data, participant_count};
+
+/// リクエストのヘッダーを読み終えるまでの時間の上限
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// リクエストの行とヘッダーを読み込むバッファの上限のバイト数
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+/// 1 回に返す受付中の募集の上限 (新しい募集から)
+const MAX_OPEN_RECRUITS: usize = 50;
+const DEFAULT_PER_PAGE: usize = 50;
+const MAX_PER_PAGE: usize = 200;
+/// 読み取れなかった募集メッセージを, 再び読み取らずに飛ばす時間
+const FAILED_LOOKUP_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// 受付中の募集
+#[derive(Debug, Serialize)]
+pub struct OpenRecruit {
+    pub game_title: String,
+    pub required_players: usize,
+    pub max_players: Option<usize>,
+    /// 最後に数えた参加人数 (10 分以内に数えていなければ `null`)
+    pub participants: Option<usize>,
+    pub organizer_id: Option<UserId>,
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub created_at: Timestamp,
+    /// 開始予定の日時
+    pub deadline: Option<Timestamp>,
+}
+
+/// 開始した募集の記録の 1 ページ
+#[derive(Debug, Serialize)]
+pub struct HistoryPage {
+    pub records: Vec<RecruitRecord>,
+    pub page: usize,
+    pub per_page: usize,
+    pub total: usize,
+}
+
+/// `/history` のクエリ
+#[derive(Debug, Default, Deserialize)]
+struct HistoryQuery {
+    days: Option<u64>,
+    page: Option<usize>,
+    per_page: Option<usize>,
+}
+
+/// API のエラー (`{"error": "..."}` を返します)
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({ "error": self.message }));
+        (self.status, body).into_response()
+    }
+}
+
+/// API のハンドラーが共有する状態
+struct ApiState<A> {
+    api: A,
+    data: Data,
+    token: String,
+    /// 読み取れなかった募集メッセージ (取得に失敗したか, 募集設定を読み取れなかった)
+    failed_lookups: Mutex<TtlCache<MessageId>>,
+}
+
+/// 環境変数 `JOINBELL_API_ADDR` と `JOINBELL_API_TOKEN` が設定されている場合, 読み取り専用の API を開始します
+pub fn spawn_from_env(ctx: Context, data: Data) {
+    let Ok(addr) = std::env::var("JOINBELL_API_ADDR") else {
+        return;
+    };
+    let Some(token) = std::env::var("JOINBELL_API_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+    else {
+        eprintln!("JOINBELL_API_TOKEN is not set; the HTTP API is disabled");
+        return;
+    };
+    let app = router(ctx, data, token);
+    tokio::spawn(async move {
+        let listener = match TcpListener::bind(&addr).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                eprintln!("Failed to bind HTTP API to {addr}: {err}");
+                return;
+            }
+        };
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    eprintln!("Failed to accept HTTP API connection: {err}");
+                    continue;
+                }
+            };
+            let service = TowerToHyperService::new(app.clone());
+            tokio::spawn(async move {
+                let served = http1::Builder::new()
+                    .timer(TokioTimer::new())
+                    .header_read_timeout(REQUEST_TIMEOUT)
+                    .max_buf_size(MAX_REQUEST_BYTES)
+                    .keep_alive(false)
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+                if let Err(err) = served {
+                    eprintln!("Failed to handle HTTP API request: {err}");
+                }
+            });
+        }
+    });
+}
+
+/// API のルーター
+///
+/// `api` は募集メッセージの取得に使います (本番では `Context`)。
+fn router<A>(api: A, data: Data, token: String) -> Router
+where
+    A: DiscordApi + Send + 'static,
+{
+    let state = Arc::new(ApiState {
+        api,
+        data,
+        token,
+        failed_lookups: Mutex::new(TtlCache::new(FAILED_LOOKUP_TTL)),
+    });
+    Router::new()
+        .route("/api/guilds/{guild_id}/recruits", get(recruits::<A>))
+        .route("/api/guilds/{guild_id}/history", get(history::<A>))
+        .fallback(not_found)
+        .method_not_allowed_fallback(method_not_allowed)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            authorize::<A>,
+        ))
+        .with_state(state)
+}
+
+/// `Authorization: Bearer <token>` がないリクエストを 401 にします
+async fn authorize<A>(
+    State(state): State<Arc<ApiState<A>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    if !is_authorized(authorization, &state.token) {
+        return ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized").into_response();
+    }
+    next.run(request).await
+}
+
+async fn not_found() -> ApiError {
+    ApiError::new(StatusCode::NOT_FOUND, "not found")
+}
+
+async fn method_not_allowed() -> ApiError {
+    ApiError::new(StatusCode::METHOD_NOT_ALLOWED, "method not allowed")
+}
+
+async fn recruits<A: DiscordApi>(
+    State(state): State<Arc<ApiState<A>>>,
+    Path(guild_id): Path<String>,
+) -> Result<Json<Vec<OpenRecruit>>, ApiError> {
+    let guild_id = parse_guild_id(&guild_id)?;
+    Ok(Json(open_recruits(&state, guild_id).await))
+}
+
+async fn history<A>(
+    State(state): State<Arc<ApiState<A>>>,
+    Path(guild_id): Path<String>,
+    query: Result<Query<HistoryQuery>, QueryRejection>,
+) -> Result<Json<HistoryPage>, ApiError> {
+    let guild_id = parse_guild_id(&guild_id)?;
+    let Query(query) =
+        query.map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "invalid query"))?;
+    let invalid = |key: &str| ApiError::new(StatusCode::BAD_REQUEST, format!("invalid {key}"));
+    let page = match query.page {
+        Some(0) => return Err(invalid("page")),
+        page => page.unwrap_or(1),
+    };
+    let per_page = match query.per_page {
+        Some(per_page) if !(1..=MAX_PER_PAGE).contains(&per_page) => {
+            return Err(invalid("per_page"));
+        }
+        per_page => per_page.unwrap_or(DEFAULT_PER_PAGE),
+    };
+    Ok(Json(history_page(
+        state.data.storage.recruit_history(guild_id),
+        query.days,
+        page,
+        per_page,
+        Timestamp::now(),
+    )))
+}
+
+fn parse_guild_id(guild_id: &str) -> Result<GuildId, ApiError> {
+    guild_id
+        .parse::<u64>()
+        .ok()
+        .filter(|&id| id != 0)
+        .map(GuildId::new)
+        .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "invalid guild_id"))
+}
+
+/// `Authorization: Bearer <token>` が一致するかどうか
+///
+/// 一致するまでの時間から推測されないよう, 長さが同じなら最後まで比べます。
+fn is_authorized(authorization: Option<&str>, token: &str) -> bool {
+    let Some(given) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// 開始した募集の記録を新しい順に並べ, `days` 日以内の `page` ページ目を返します
+fn history_page(
+    mut records: Vec<RecruitRecord>,
+    days: Option<u64>,
+    page: usize,
+    per_page: usize,
+    now: Timestamp,
+) -> HistoryPage {
+    if let Some(days) = days {
+        let since = now
+            .unix_timestamp()
+            .saturating_sub(days.saturating_mul(24 * 60 * 60).min(i64::MAX as u64) as i64);
+        records.retain(|record| since <= record.started_at.unix_timestamp());
+    }
+    records.sort_by_key(|record| std::cmp::Reverse(record.started_at.unix_timestamp()));
+    let total = records.len();
+    let records = records
+        .into_iter()
+        .skip((page - 1).saturating_mul(per_page))
+        .take(per_page)
+        .collect();
+    HistoryPage {
+        records,
+        page,
+        per_page,
+        total,
+    }
+}
+
+/// 場所を記録したサーバーの募集のうち, 開始して締め切っておらずキャンセルもされていない募集
+///
+/// 保存していない募集設定は募集メッセージから読み取り, 読み取れなかった募集は `FAILED_LOOKUP_TTL` の間は飛ばします。
+async fn open_recruits<A: DiscordApi>(state: &ApiState<A>, guild_id: GuildId) -> Vec<OpenRecruit> {
+    let data = &state.data;
+    state.failed_lookups.lock().unwrap().sweep(Instant::now());
+    let mut locations = data.storage.recruit_locations_in_guild(guild_id);
+    locations.reverse();
+    let mut recruits = Vec::new();
+    for (message_id, channel_id) in locations {
+        if MAX_OPEN_RECRUITS <= recruits.len() {
+            break;
+        }
+        let recruit_message = match data.storage.recruit_message(message_id) {
+            Some(recruit_message) => recruit_message,
+            None => {
+                if state
+                    .failed_lookups
+                    .lock()
+                    .unwrap()
+                    .contains(&message_id, Instant::now())
+                {
+                    continue;
+                }
+                let parsed = match state.api.message(channel_id, message_id).await {
+                    Ok(message) => RecruitMessage::parse(&message.content).ok(),
+                    Err(_) => None,
+                };
+                match parsed {
+                    Some(recruit_message) => recruit_message,
+                    None => {
+                        state
+                            .failed_lookups
+                            .lock()
+                            .unwrap()
+                            .insert_if_expired(message_id, Instant::now());
+                        continue;
+                    }
+                }
+            }
+        };
+        let RecruitMessage {
+            config,
+            state: recruit_state,
+        } = recruit_message;
+        if recruit_state.cancelled
+            || recruit_state.closed_entries.is_some()
+            || (recruit_state.started && config.closes_on_start())
+        {
+            continue;
+        }
+        recruits.push(OpenRecruit {
+            participants: participant_count::cached_count(data, message_id),
+            game_title: config.game_title,
+            required_players: config.required_players,
+            max_players: config.max_players,
+            organizer_id: config.organizer,
+            channel_id,
+            message_id,
+            created_at: message_id.created_at(),
+            deadline: config.start_time,
+        });
+    }
+    recruits
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use serde_json::{Value, json};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::mock_discord::{self, Call, MockDiscord};
+    use crate::recruit_message::RecruitConfig;
+    use crate::storage::RecruitLocation;
+
+    const TOKEN: &str = "secret";
+    const GUILD_ID: GuildId = GuildId::new(1);
+    const CHANNEL_ID: ChannelId = ChannelId::new(2);
+
+    async fn get(app: &Router, uri: &str, token: Option<&str>) -> (StatusCode, Value) {
+        let mut request = Request::get(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let response = app
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), MAX_REQUEST_BYTES * 64)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn record(message_id: u64, started_at: i64) -> RecruitRecord {
+        RecruitRecord {
+            game_title: format!("game{message_id}"),
+            category: None,
+            channel_id: CHANNEL_ID,
+            message_id: MessageId::new(message_id),
+            started_at: Timestamp::from_unix_timestamp(started_at).unwrap(),
+            participants: Vec::new(),
+            required_players: Some(4),
+            reacted_count: None,
+        }
+    }
+
+    fn remember(data: &Data, message_id: MessageId) {
+        let location = RecruitLocation {
+            guild_id: Some(GUILD_ID),
+            channel_id: CHANNEL_ID,
+        };
+        data.storage
+            .remember_recruit_location(message_id, location)
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn requests_without_the_token_are_rejected() {
+        let app = router(MockDiscord::new(), Data::for_test(), TOKEN.to_string());
+        for token in [None, Some("wrong"), Some("secre")] {
+            let (status, body) = get(&app, "/api/guilds/1/recruits", token).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            assert_eq!(body, json!({ "error": "unauthorized" }));
+        }
+        // 存在しないパスも, 認証する前には何があるかを返しません
+        let (status, _) = get(&app, "/api/unknown", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn unknown_routes_and_invalid_parameters_are_errors() {
+        let app = router(MockDiscord::new(), Data::for_test(), TOKEN.to_string());
+        let cases = [
+            ("/api/unknown", StatusCode::NOT_FOUND, "not found"),
+            (
+                "/api/guilds/x/recruits",
+                StatusCode::BAD_REQUEST,
+                "invalid guild_id",
+            ),
+            (
+                "/api/guilds/0/history",
+                StatusCode::BAD_REQUEST,
+                "invalid guild_id",
+            ),
+            (
+                "/api/guilds/1/history?page=0",
+                StatusCode::BAD_REQUEST,
+                "invalid page",
+            ),
+            (
+                "/api/guilds/1/history?per_page=201",
+                StatusCode::BAD_REQUEST,
+                "invalid per_page",
+            ),
+            (
+                "/api/guilds/1/history?days=-1",
+                StatusCode::BAD_REQUEST,
+                "invalid query",
+            ),
+        ];
+        for (uri, expected_status, expected_error) in cases {
+            let (status, body) = get(&app, uri, Some(TOKEN)).await;
+            assert_eq!(status, expected_status, "{uri}");
+            assert_eq!(body, json!({ "error": expected_error }), "{uri}");
+        }
+
+        let request = Request::post("/api/guilds/1/history")
+            .header(header::AUTHORIZATION, format!("Bearer {TOKEN}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn history_is_paged_newest_first_within_days() {
+        let data = Data::for_test();
+        let now = Timestamp::now().unix_timestamp();
+        for (message_id, days_ago) in [(10, 1), (11, 3), (12, 2), (13, 40)] {
+            data.storage
+                .record_recruit(GUILD_ID, record(message_id, now - days_ago * 24 * 60 * 60))
+                .unwrap();
+        }
+        let app = router(MockDiscord::new(), data, TOKEN.to_string());
+
+        let (status, body) = get(
+            &app,
+            "/api/guilds/1/history?days=30&page=1&per_page=2",
+            Some(TOKEN),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], 3);
+        let ids: Vec<&Value> = body["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|record| &record["message_id"])
+            .collect();
+        assert_eq!(ids, [&json!("10"), &json!("12")]);
+
+        let (_, body) = get(
+            &app,
+            "/api/guilds/1/history?days=30&page=2&per_page=2",
+            Some(TOKEN),
+        )
+        .await;
+        assert_eq!(body["records"][0]["message_id"], "11");
+
+        let (_, body) = get(&app, "/api/guilds/2/history", Some(TOKEN)).await;
+        assert_eq!(body["total"], 0);
+        assert_eq!(body["per_page"], DEFAULT_PER_PAGE);
+    }
+
+    #[tokio::test]
+    async fn open_recruits_are_read_from_recruit_messages() {
+        let data = Data::for_test();
+        let discord = MockDiscord::new();
+        let open = MessageId::new(20);
+        let cancelled = MessageId::new(21);
+        let mut cancelled_message = RecruitMessage::new(RecruitConfig::for_test("Cancelled", 2));
+        cancelled_message.state.cancelled = true;
+        for (message_id, recruit_message) in [
+            (
+                open,
+                RecruitMessage::new(RecruitConfig::for_test("Open", 4)),
+            ),
+            (cancelled, cancelled_message),
+        ] {
+            discord.insert_message(mock_discord::message(
+                CHANNEL_ID,
+                message_id,
+                &recruit_message.render(&data.config.emojis),
+            ));
+            remember(&data, message_id);
+        }
+        let app = router(discord, data, TOKEN.to_string());
+
+        let (status, body) = get(&app, "/api/guilds/1/recruits", Some(TOKEN)).await;
+        assert_eq!(status, StatusCode::OK);
+        let recruits = body.as_array().unwrap();
+        assert_eq!(recruits.len(), 1);
+        assert_eq!(recruits[0]["game_title"], "Open");
+        assert_eq!(recruits[0]["required_players"], 4);
+        assert_eq!(recruits[0]["message_id"], "20");
+        assert_eq!(recruits[0]["participants"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn failed_lookups_are_not_repeated() {
+        let data = Data::for_test();
+        let discord = MockDiscord::new();
+        let deleted = MessageId::new(30);
+        let unreadable = MessageId::new(31);
+        discord.insert_message(mock_discord::message(CHANNEL_ID, unreadable, "hello"));
+        remember(&data, deleted);
+        remember(&data, unreadable);
+        let app = router(discord.clone(), data, TOKEN.to_string());
+
+        for _ in 0..2 {
+            let (status, body) = get(&app, "/api/guilds/1/recruits", Some(TOKEN)).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body, json!([]));
+        }
+        let lookups: Vec<Call> = discord
+            .calls()
+            .into_iter()
+            .filter(|call| matches!(call, Call::Message { .. }))
+            .collect();
+        assert_eq!(
+            lookups,
+            [
+                Call::Message {
+                    message_id: unreadable
+                },
+                Call::Message {
+                    message_id: deleted
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,918 @@
+use poise::{CreateReply, serenity_prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::channel_check::{self, ChannelSuitability};
+use crate::discord_error::is_unknown_entity;
+use crate::i18n::{self, Language};
+use crate::join_menu::{self, Admission, truncate_chars};
+use crate::notification::{Mentions, NotificationPolicy};
+use crate::recruit_message::{
+    MAX_PLAYERS, OverMention, ParticipationMode, RecruitConfig, sanitize_text,
+};
+use crate::{
+    DEFAULT_DELETE_AFTER_MINUTES, Data, Error, author_permissions, confirm, format_progress,
+    game_alias, parse_bot_recruit_message, participant_age, post_recruit_message, recruit_actions,
+    remember_recruit_location, send_ephemeral,
+};
+
+const FORM_PREFIX: &str = "recruit_application_form:";
+const REVIEW_PREFIX: &str = "recruit_application:";
+const ANSWER_ID: &str = "answer";
+/// 質問の上限の文字数 (Discord のモーダルの入力欄のラベルの上限)
+pub const MAX_QUESTION_CHARS: usize = 45;
+/// 回答の上限の文字数
+pub const MAX_ANSWER_CHARS: usize = 200;
+/// モーダルのタイトルの上限の文字数
+const MAX_TITLE_CHARS: usize = 45;
+
+/// 応募の状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApplicationStatus {
+    /// 主催者の判断を待っている
+    Pending,
+    /// 承認されて参加者になった
+    Accepted,
+    /// 見送られた (同じ募集には応募し直せません)
+    Declined,
+}
+
+/// 主催者に送った, 承認と見送りのボタンのあるメッセージ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewMessage {
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+}
+
+/// 1 人の応募
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Application {
+    pub user_id: UserId,
+    pub answer: String,
+    pub status: ApplicationStatus,
+    pub applied_at: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<ReviewMessage>,
+}
+
+/// 応募した結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Applied {
+    New,
+    /// 判断を待っている応募の回答を書き換えた
+    Updated,
+}
+
+/// 主催者の判断
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Accept,
+    Decline,
+}
+
+/// 応募の状態を変えられなかった理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplicationError {
+    NotFound,
+    AlreadyDecided(ApplicationStatus),
+}
+
+/// 1 つの募集への応募 (応募した順)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Applications {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<Application>,
+}
+
+impl Applications {
+    pub fn new(guild_id: GuildId, channel_id: ChannelId) -> Self {
+        Self {
+            guild_id,
+            channel_id,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, user_id: UserId) -> Option<&Application> {
+        self.entries.iter().find(|entry| entry.user_id == user_id)
+    }
+
+    fn get_mut(&mut self, user_id: UserId) -> Option<&mut Application> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.user_id == user_id)
+    }
+
+    /// 応募を受け付けます
+    ///
+    /// 判断を待っている応募は回答だけを書き換え, 判断した後は受け付けません。
+    pub fn apply(
+        &mut self,
+        user_id: UserId,
+        answer: String,
+        at: Timestamp,
+    ) -> Result<Applied, ApplicationError> {
+        match self.get_mut(user_id) {
+            Some(entry) if entry.status == ApplicationStatus::Pending => {
+                entry.answer = answer;
+                Ok(Applied::Updated)
+            }
+            Some(entry) => Err(ApplicationError::AlreadyDecided(entry.status)),
+            None => {
+                self.entries.push(Application {
+                    user_id,
+                    answer,
+                    status: ApplicationStatus::Pending,
+                    applied_at: at,
+                    review: None,
+                });
+                Ok(Applied::New)
+            }
+        }
+    }
+
+    /// 判断を待っている応募を承認するか見送ります
+    pub fn decide(
+        &mut self,
+        user_id: UserId,
+        decision: Decision,
+    ) -> Result<ApplicationStatus, ApplicationError> {
+        let entry = self.get_mut(user_id).ok_or(ApplicationError::NotFound)?;
+        if entry.status != ApplicationStatus::Pending {
+            return Err(ApplicationError::AlreadyDecided(entry.status));
+        }
+        entry.status = match decision {
+            Decision::Accept => ApplicationStatus::Accepted,
+            Decision::Decline => ApplicationStatus::Declined,
+        };
+        Ok(entry.status)
+    }
+
+    pub fn set_review(&mut self, user_id: UserId, review: ReviewMessage) {
+        if let Some(entry) = self.get_mut(user_id) {
+            entry.review = Some(review);
+        }
+    }
+
+    /// 参加をやめたユーザーの応募を取り下げ, 取り下げた応募を返します
+    ///
+    /// 見送られた応募は, 応募し直せないよう残します。
+    pub fn withdraw(&mut self, user_id: UserId) -> Option<Application> {
+        let index = self.entries.iter().position(|entry| {
+            entry.user_id == user_id && entry.status != ApplicationStatus::Declined
+        })?;
+        Some(self.entries.remove(index))
+    }
+}
+
+/// 応募のフォームの送信かどうか
+pub fn is_application_form(interaction: &ModalInteraction) -> bool {
+    interaction.data.custom_id.starts_with(FORM_PREFIX)
+}
+
+/// 主催者に送った承認と見送りのボタンかどうか
+pub fn is_review_button(interaction: &ComponentInteraction) -> bool {
+    interaction.data.custom_id.starts_with(REVIEW_PREFIX)
+}
+
+fn review_custom_id(decision: Decision, message_id: MessageId, user_id: UserId) -> String {
+    let code = match decision {
+        Decision::Accept => "accept",
+        Decision::Decline => "decline",
+    };
+    format!("{REVIEW_PREFIX}{code}:{message_id}:{user_id}")
+}
+
+fn parse_review_custom_id(custom_id: &str) -> Option<(Decision, MessageId, UserId)> {
+    let mut parts = custom_id.strip_prefix(REVIEW_PREFIX)?.split(':');
+    let decision = match parts.next()? {
+        "accept" => Decision::Accept,
+        "decline" => Decision::Decline,
+        _ => return None,
+    };
+    let message_id = parts.next()?.parse::<u64>().ok().filter(|&id| id != 0)?;
+    let user_id = parts.next()?.parse::<u64>().ok().filter(|&id| id != 0)?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((decision, MessageId::new(message_id), UserId::new(user_id)))
+}
+
+async fn respond_ephemeral(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    content: impl Into<String>,
+) -> Result<(), Error> {
+    interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+/// 応募が必要な募集の「参加する」を押したユーザーに, 質問に答えるフォームを表示します
+///
+/// 判断を待っている応募があれば, 前の回答を入力した状態で表示します。
+pub async fn open_form(
+    ctx: &Context,
+    data: &Data,
+    interaction: &ComponentInteraction,
+    config: &RecruitConfig,
+) -> Result<(), Error> {
+    let Some(question) = &config.application_question else {
+        return Ok(());
+    };
+    let message_id = interaction.message.id;
+    let user_id = interaction.user.id;
+    if config.preset_participants.contains(&user_id)
+        || data
+            .storage
+            .menu_participants(message_id)
+            .contains(&user_id)
+    {
+        let content = format!("すでに {} に参加しています", config.game_title);
+        return respond_ephemeral(ctx, interaction, content).await;
+    }
+    let previous = data
+        .storage
+        .applications(message_id)
+        .and_then(|applications| applications.get(user_id).cloned());
+    if let Some(content) = previous
+        .as_ref()
+        .and_then(|application| decided_message(application.status, config))
+    {
+        return respond_ephemeral(ctx, interaction, content).await;
+    }
+    let title = format!(
+        "{} に応募",
+        truncate_chars(&config.game_title, MAX_TITLE_CHARS - 5)
+    );
+    let mut input = CreateInputText::new(InputTextStyle::Paragraph, question, ANSWER_ID)
+        .max_length(MAX_ANSWER_CHARS as u16)
+        .required(true);
+    if let Some(previous) = previous {
+        input = input.value(previous.answer);
+    }
+    let modal = CreateModal::new(format!("{FORM_PREFIX}{message_id}"), title)
+        .components(vec![CreateActionRow::InputText(input)]);
+    interaction
+        .create_response(ctx, CreateInteractionResponse::Modal(modal))
+        .await?;
+    Ok(())
+}
+
+fn decided_message(status: ApplicationStatus, config: &RecruitConfig) -> Option<String> {
+    match status {
+        ApplicationStatus::Pending => None,
+        ApplicationStatus::Accepted => Some(format!(
+            "すでに {} への参加が承認されています",
+            config.game_title
+        )),
+        ApplicationStatus::Declined => Some(format!(
+            "{} への応募は見送られたため、応募し直せません",
+            config.game_title
+        )),
+    }
+}
+
+/// 送信されたフォームの回答を応募として保存し, 主催者に知らせます
+pub async fn handle_form(
+    ctx: &Context,
+    data: &Data,
+    interaction: &ModalInteraction,
+) -> Result<(), Error> {
+    let Some(guild_id) = interaction.guild_id else {
+        return Ok(());
+    };
+    let Some(message_id) = interaction
+        .data
+        .custom_id
+        .strip_prefix(FORM_PREFIX)
+        .and_then(|id| id.parse::<u64>().ok())
+        .filter(|&id| id != 0)
+        .map(MessageId::new)
+    else {
+        return Ok(());
+    };
+    let answer = interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(input) if input.custom_id == ANSWER_ID => {
+                input.value.clone()
+            }
+            _ => None,
+        })
+        .unwrap_or_default();
+    interaction.defer_ephemeral(ctx).await?;
+    let content = submit(
+        ctx,
+        data,
+        guild_id,
+        interaction.channel_id,
+        message_id,
+        &interaction.user,
+        &answer,
+    )
+    .await?;
+    interaction
+        .create_followup(
+            ctx,
+            CreateInteractionResponseFollowup::new()
+                .content(content)
+                .ephemeral(true),
+        )
+        .await?;
+    Ok(())
+}
+
+/// 応募を保存し, 応募したユーザーに表示する結果を返します
+async fn submit(
+    ctx: &Context,
+    data: &Data,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    user: &User,
+    answer: &str,
+) -> Result<String, Error> {
+    let _guard = data.lock_message(message_id).await;
+    let message = match channel_id.message(ctx, message_id).await {
+        Ok(message) => message,
+        Err(err) if is_unknown_entity(&err).is_some() => {
+            return Ok("募集メッセージは削除されています".to_string());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let Some(mut recruit_message) = parse_bot_recruit_message(ctx, data, &message) else {
+        return Ok("このメッセージは募集ではありません".to_string());
+    };
+    if !join_menu::is_open(&recruit_message) {
+        return Ok("この募集はすでに開始または締め切られています".to_string());
+    }
+    remember_recruit_location(data, Some(guild_id), &message);
+    i18n::resolve_language(data, &mut recruit_message.config, Some(guild_id));
+    let config = &recruit_message.config;
+    let (Some(question), Some(organizer)) = (&config.application_question, config.organizer) else {
+        return Ok("この募集は応募を受け付けていません".to_string());
+    };
+    if let Some(reason) = participant_age::rejection(ctx, data, guild_id, user.id, None).await {
+        return Ok(reason.message(config));
+    }
+    let answer = sanitize_text(answer.trim());
+    if answer.is_empty() {
+        return Ok("回答を入力してください".to_string());
+    }
+    let answer = truncate_chars(&answer, MAX_ANSWER_CHARS);
+
+    let (applied, review) = match data.storage.update_applications(
+        message_id,
+        guild_id,
+        channel_id,
+        |applications| {
+            let applied = applications.apply(user.id, answer.clone(), Timestamp::now())?;
+            let review = applications.get(user.id).and_then(|entry| entry.review);
+            Ok((applied, review))
+        },
+    )? {
+        Ok(applied) => applied,
+        Err(ApplicationError::AlreadyDecided(status)) => {
+            return Ok(decided_message(status, config).unwrap_or_default());
+        }
+        Err(ApplicationError::NotFound) => return Ok(String::new()),
+    };
+    let content = review_content(config, question, user, &answer, &message.link());
+    let components = review_components(message_id, user.id);
+    // 回答を書き換えた応募は, 送った確認のメッセージも書き換えます
+    if let Some(review) = review {
+        let edited = review
+            .channel_id
+            .edit_message(
+                ctx,
+                review.message_id,
+                EditMessage::new()
+                    .content(&content)
+                    .components(components.clone()),
+            )
+            .await;
+        match edited {
+            Ok(_) => {
+                return Ok(format!(
+                    "{} への応募の回答を書き換えました",
+                    config.game_title
+                ));
+            }
+            Err(err) => eprintln!("Failed to edit application review: {err}"),
+        }
+    }
+    let review = send_review(ctx, organizer, channel_id, &content, components).await?;
+    data.storage
+        .update_applications(message_id, guild_id, channel_id, |applications| {
+            applications.set_review(user.id, review);
+        })?;
+    Ok(match applied {
+        Applied::New => format!(
+            "{} に応募しました。主催者が承認すると参加者になります",
+            config.game_title
+        ),
+        Applied::Updated => format!("{} への応募の回答を書き換えました", config.game_title),
+    })
+}
+
+fn review_content(
+    config: &RecruitConfig,
+    question: &str,
+    user: &User,
+    answer: &str,
+    link: &str,
+) -> String {
+    format!(
+        "🙋 **{}** に {} ({}) が応募しました\n> {question}\n{answer}\n{link}",
+        config.game_title,
+        user.mention(),
+        user.name
+    )
+}
+
+fn review_components(message_id: MessageId, user_id: UserId) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(review_custom_id(Decision::Accept, message_id, user_id))
+            .label("✅ 承認する")
+            .style(ButtonStyle::Success),
+        CreateButton::new(review_custom_id(Decision::Decline, message_id, user_id))
+            .label("❌ 見送る")
+            .style(ButtonStyle::Danger),
+    ])]
+}
+
+/// 主催者に DM で応募を知らせます (送れなければ募集のチャンネルでメンションします)
+async fn send_review(
+    ctx: &Context,
+    organizer: UserId,
+    channel_id: ChannelId,
+    content: &str,
+    components: Vec<CreateActionRow>,
+) -> Result<ReviewMessage, Error> {
+    let dm = CreateMessage::new()
+        .content(content)
+        .components(components.clone())
+        .allowed_mentions(Mentions::none().allowed_mentions());
+    let message = match organizer.direct_message(ctx, dm).await {
+        Ok(message) => message,
+        Err(err) => {
+            eprintln!("Failed to send application review by DM: {err}");
+            let message = CreateMessage::new()
+                .content(format!("{} {content}", organizer.mention()))
+                .components(components)
+                .allowed_mentions(Mentions::user(organizer).allowed_mentions());
+            channel_id.send_message(ctx, message).await?
+        }
+    };
+    Ok(ReviewMessage {
+        channel_id: message.channel_id,
+        message_id: message.id,
+    })
+}
+
+/// 主催者が押した承認か見送りのボタンで, 応募を判断します
+pub async fn handle_review_button(
+    ctx: &Context,
+    data: &Data,
+    interaction: &ComponentInteraction,
+) -> Result<(), Error> {
+    let Some((decision, message_id, user_id)) = parse_review_custom_id(&interaction.data.custom_id)
+    else {
+        return Ok(());
+    };
+    interaction.defer_ephemeral(ctx).await?;
+    let (content, settled) = decide(
+        ctx,
+        data,
+        message_id,
+        user_id,
+        decision,
+        interaction.user.id,
+    )
+    .await?;
+    // 判断が済んだ応募は, ボタンを消して結果を添えます
+    if settled {
+        let edited = interaction
+            .message
+            .channel_id
+            .edit_message(
+                ctx,
+                interaction.message.id,
+                EditMessage::new()
+                    .content(format!("{}\n→ {content}", interaction.message.content))
+                    .components(Vec::new()),
+            )
+            .await;
+        if let Err(err) = edited {
+            eprintln!("Failed to edit application review: {err}");
+        }
+    }
+    interaction
+        .create_followup(
+            ctx,
+            CreateInteractionResponseFollowup::new()
+                .content(content)
+                .ephemeral(true),
+        )
+        .await?;
+    Ok(())
+}
+
+/// 応募を判断し, 主催者に表示する結果と, 判断が済んだかどうかを返します
+async fn decide(
+    ctx: &Context,
+    data: &Data,
+    message_id: MessageId,
+    user_id: UserId,
+    decision: Decision,
+    actor: UserId,
+) -> Result<(String, bool), Error> {
+    let _guard = data.lock_message(message_id).await;
+    let Some(applications) = data.storage.applications(message_id) else {
+        return Ok(("この応募は取り下げられました".to_string(), true));
+    };
+    let (guild_id, channel_id) = (applications.guild_id, applications.channel_id);
+    let message = match channel_id.message(ctx, message_id).await {
+        Ok(message) => message,
+        Err(err) if is_unknown_entity(&err).is_some() => {
+            return Ok(("募集メッセージは削除されています".to_string(), true));
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let Some(mut recruit_message) = parse_bot_recruit_message(ctx, data, &message) else {
+        return Ok(("このメッセージは募集ではありません".to_string(), true));
+    };
+    i18n::resolve_language(data, &mut recruit_message.config, Some(guild_id));
+    let config = &recruit_message.config;
+    if config.organizer != Some(actor) {
+        return Ok((
+            "応募を判断できるのは募集の主催者のみです".to_string(),
+            false,
+        ));
+    }
+    match applications.get(user_id).map(|entry| entry.status) {
+        None => return Ok(("この応募は取り下げられました".to_string(), true)),
+        Some(ApplicationStatus::Pending) => {}
+        Some(ApplicationStatus::Accepted) => {
+            return Ok(("この応募はすでに承認しています".to_string(), true));
+        }
+        Some(ApplicationStatus::Declined) => {
+            return Ok(("この応募はすでに見送っています".to_string(), true));
+        }
+    }
+    if !join_menu::is_open(&recruit_message) {
+        return Ok((
+            "この募集はすでに開始または締め切られています".to_string(),
+            true,
+        ));
+    }
+    let policy = NotificationPolicy::new(config);
+    let link = message.link();
+    let content = match decision {
+        Decision::Accept => {
+            // 定員に達している間は, 判断を待ったままにします
+            let participants =
+                match join_menu::admit(ctx, data, guild_id, &message, config, user_id).await? {
+                    Admission::Full => {
+                        return Ok((format!("{} は定員に達しています", config.game_title), false));
+                    }
+                    Admission::AlreadyJoined => None,
+                    Admission::Joined {
+                        participants,
+                        role_failed,
+                    } => Some((participants, role_failed)),
+                };
+            data.storage
+                .update_applications(message_id, guild_id, channel_id, |applications| {
+                    applications.decide(user_id, Decision::Accept)
+                })?
+                .ok();
+            let notice = format!("{} への参加が承認されました", config.game_title);
+            if let Err(err) = user_id
+                .direct_message(
+                    ctx,
+                    policy.message(&format!("{notice}\n{link}"), &Mentions::none()),
+                )
+                .await
+            {
+                eprintln!("Failed to send application result by DM: {err}");
+            }
+            let mut content = format!("{} の参加を承認しました", user_id.mention());
+            if let Some((participants, role_failed)) = participants {
+                content += &format!(
+                    " {}",
+                    format_progress(Language::Ja, participants, config.required_players)
+                );
+                if role_failed {
+                    content += "\nロールの付与に失敗しました";
+                }
+            }
+            content
+        }
+        Decision::Decline => {
+            data.storage
+                .update_applications(message_id, guild_id, channel_id, |applications| {
+                    applications.decide(user_id, Decision::Decline)
+                })?
+                .ok();
+            let notice = format!(
+                "{} への応募は、今回は見送らせていただくことになりました。ご応募ありがとうございました",
+                config.game_title
+            );
+            if let Err(err) = user_id
+                .direct_message(
+                    ctx,
+                    policy.message(&format!("{notice}\n{link}"), &Mentions::none()),
+                )
+                .await
+            {
+                eprintln!("Failed to send application result by DM: {err}");
+            }
+            format!("{} の応募を見送りました", user_id.mention())
+        }
+    };
+    Ok((content, true))
+}
+
+/// 参加をやめたユーザーの応募を取り下げ, 主催者に送った確認のメッセージのボタンを消します
+pub async fn withdraw(
+    ctx: &Context,
+    data: &Data,
+    message_id: MessageId,
+    user_id: UserId,
+) -> Result<Option<ApplicationStatus>, Error> {
+    let Some(applications) = data.storage.applications(message_id) else {
+        return Ok(None);
+    };
+    let Some(withdrawn) = data.storage.update_applications(
+        message_id,
+        applications.guild_id,
+        applications.channel_id,
+        |applications| applications.withdraw(user_id),
+    )?
+    else {
+        return Ok(None);
+    };
+    if withdrawn.status == ApplicationStatus::Pending
+        && let Some(review) = withdrawn.review
+    {
+        let edited = review
+            .channel_id
+            .edit_message(
+                ctx,
+                review.message_id,
+                EditMessage::new()
+                    .content(format!(
+                        "{} が {} への応募を取り下げました",
+                        user_id.mention(),
+                        message_id.link(applications.channel_id, Some(applications.guild_id))
+                    ))
+                    .components(Vec::new()),
+            )
+            .await;
+        if let Err(err) = edited {
+            eprintln!("Failed to edit application review: {err}");
+        }
+    }
+    Ok(Some(withdrawn.status))
+}
+
+/// 応募を主催者が承認したユーザーを参加者にする募集を作成します
+///
+/// 参加のボタンを押すと質問に答えるフォームが開き, 承認した人数が揃うと開始します。リアクションでは参加できません。
+#[allow(clippy::too_many_arguments)]
+#[poise::command(slash_command, guild_only, rename = "apply")]
+pub async fn recruit_apply(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集するゲーム名"]
+    #[autocomplete = "game_alias::autocomplete_game_title"]
+    game_title: String,
+    #[description = "開始に必要な人数"]
+    #[min = 1]
+    #[max = 100]
+    required_players: usize,
+    #[description = "応募するときに答えてもらう質問 (45 文字まで)"] question: String,
+    #[description = "参加できる最大人数"]
+    #[min = 1]
+    #[max = 100]
+    max_players: Option<usize>,
+    #[description = "開始時にメンションするロール"] mention_role: Option<Role>,
+    #[description = "承認した参加者に mention_role を付与するかどうか"] assign_role: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let data = ctx.data();
+    let question = sanitize_text(question.trim());
+    if question.is_empty() || MAX_QUESTION_CHARS < question.chars().count() {
+        let content = format!("question は 1〜{MAX_QUESTION_CHARS} 文字で指定してください");
+        send_ephemeral(ctx, &content).await?;
+        return Ok(());
+    }
+    if !(1..=MAX_PLAYERS).contains(&required_players)
+        || max_players.is_some_and(|max_players| max_players < required_players)
+    {
+        send_ephemeral(
+            ctx,
+            "max_players は required_players 以上を指定してください",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let settings = data.storage.guild_settings(guild_id);
+    let member_roles = ctx
+        .author_member()
+        .await
+        .map(|member| member.roles.clone())
+        .unwrap_or_default();
+    let member_permissions = author_permissions(ctx).unwrap_or_default();
+    if !settings.can_create_recruit(&member_roles, member_permissions, mention_role.is_some()) {
+        send_ephemeral(
+            ctx,
+            "募集を作成する権限がありません。主催者ロールを持つメンバーのみ作成できます。",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    if let Some(channel) = ctx.guild_channel().await {
+        match channel_check::check_recruit_channel(ctx, guild_id, &channel).await? {
+            ChannelSuitability::Suitable => {}
+            ChannelSuitability::Refuse(reason) => {
+                send_ephemeral(ctx, reason).await?;
+                return Ok(());
+            }
+            ChannelSuitability::Warn(warnings) => {
+                let content = format!(
+                    "{}\nこのチャンネルに募集を投稿しますか?",
+                    warnings.join("\n")
+                );
+                if !confirm::confirm(ctx, &content, "投稿する").await? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    let config = RecruitConfig {
+        game_title: sanitize_text(game_title.trim()),
+        required_players,
+        max_players,
+        enforce_cap: false,
+        organizer: Some(ctx.author().id),
+        description: None,
+        mention_roles: mention_role.map(|role| role.id).into_iter().collect(),
+        notify_on_reaction: true,
+        auto_assign_role_on_reaction: assign_role.unwrap_or(false),
+        delete_after_minutes: DEFAULT_DELETE_AFTER_MINUTES,
+        start_delay_minutes: None,
+        count_voice_channel: None,
+        require_undeafened: false,
+        simulation: false,
+        extra_reactions: Vec::new(),
+        cleanup_after_start_minutes: settings.cleanup_after_start_minutes,
+        notify_organizer_when_close: false,
+        strict_order: false,
+        game_options: Vec::new(),
+        category: None,
+        start_time: None,
+        remind_before_minutes: None,
+        language: Some(settings.language),
+        roll_call_minutes: None,
+        priority_role: None,
+        priority_until: None,
+        over_mention: OverMention::default(),
+        multi_lobby: false,
+        override_quiet_hours: false,
+        preset_participants: Vec::new(),
+        participation_mode: ParticipationMode::Buttons,
+        require_voice_at_start: None,
+        voice_grace_minutes: None,
+        slots: Vec::new(),
+        reaction_emoji: None,
+        application_question: Some(question),
+    };
+    let posted = post_recruit_message(
+        ctx.serenity_context(),
+        data,
+        Some(guild_id),
+        ctx.channel_id(),
+        config,
+        None,
+        &Mentions::none(),
+    )
+    .await?;
+    let content = format!(
+        "募集メッセージを投稿しました {} {}\n応募は DM で届きます",
+        format_progress(Language::Ja, 0, required_players),
+        posted.link()
+    );
+    ctx.send(
+        CreateReply::default()
+            .content(content)
+            .ephemeral(true)
+            .components(recruit_actions::components(&posted, ctx.author().id, true)),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recruit_message::at;
+
+    const USER_ID: UserId = UserId::new(11);
+
+    fn applications() -> Applications {
+        Applications::new(GuildId::new(1), ChannelId::new(2))
+    }
+
+    #[test]
+    fn review_button_ids_are_read_back() {
+        for decision in [Decision::Accept, Decision::Decline] {
+            let custom_id = review_custom_id(decision, MessageId::new(3), USER_ID);
+            assert_eq!(
+                parse_review_custom_id(&custom_id),
+                Some((decision, MessageId::new(3), USER_ID))
+            );
+        }
+        for custom_id in [
+            "recruit_application:accept:3",
+            "recruit_application:accept:3:11:1",
+            "recruit_application:accept:0:11",
+            "recruit_application:ban:3:11",
+            "recruit_button:join",
+        ] {
+            assert_eq!(parse_review_custom_id(custom_id), None, "{custom_id}");
+        }
+    }
+
+    #[test]
+    fn pending_applications_can_be_updated_until_decided() {
+        let mut applications = applications();
+        assert_eq!(
+            applications.apply(USER_ID, "ランク".to_string(), at(0)),
+            Ok(Applied::New)
+        );
+        assert_eq!(
+            applications.apply(USER_ID, "カジュアル".to_string(), at(1)),
+            Ok(Applied::Updated)
+        );
+        let application = applications.get(USER_ID).unwrap();
+        assert_eq!(application.answer, "カジュアル");
+        assert_eq!(application.applied_at, at(0));
+
+        assert_eq!(
+            applications.decide(USER_ID, Decision::Accept),
+            Ok(ApplicationStatus::Accepted)
+        );
+        assert_eq!(
+            applications.decide(USER_ID, Decision::Decline),
+            Err(ApplicationError::AlreadyDecided(
+                ApplicationStatus::Accepted
+            ))
+        );
+        assert_eq!(
+            applications.apply(USER_ID, "ランク".to_string(), at(2)),
+            Err(ApplicationError::AlreadyDecided(
+                ApplicationStatus::Accepted
+            ))
+        );
+        assert_eq!(
+            applications.decide(UserId::new(12), Decision::Accept),
+            Err(ApplicationError::NotFound)
+        );
+    }
+
+    #[test]
+    fn declined_applications_are_kept_on_withdrawal() {
+        let mut applications = applications();
+        for user_id in [11, 12] {
+            applications
+                .apply(UserId::new(user_id), String::new(), at(0))
+                .unwrap();
+        }
+        applications
+            .decide(UserId::new(12), Decision::Decline)
+            .unwrap();
+        assert!(applications.withdraw(USER_ID).is_some());
+        // 見送った応募は, 応募し直せないよう残します
+        assert!(applications.withdraw(UserId::new(12)).is_none());
+        assert!(applications.get(USER_ID).is_none());
+        assert!(applications.get(UserId::new(12)).is_some());
+    }
+}
@@ -0,0 +1,88 @@
+use poise::serenity_prelude::*;
+
+use crate::close_reason::{self, CloseReason};
+use crate::discord_api::DiscordApi;
+use crate::recruit_message::RecruitMessage;
+use crate::{Data, Error, countdown, recruit_edit, roll_call, task_registry, voice_gate};
+
+/// 募集をキャンセルします
+///
+/// 募集メッセージにキャンセルしたことを表示してリアクションを外し, カウントダウンや点呼などの予定を取り消します。
+/// キャンセル済みの募集と, 開始して締め切った募集では何もせず `false` を返します。
+pub async fn cancel_recruit(
+    api: &impl DiscordApi,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Result<bool, Error> {
+    cancel_recruit_as(api, data, channel_id, message_id, CloseReason::Cancelled).await
+}
+
+/// 募集メッセージの状態をキャンセルにします
+///
+/// キャンセル済みの募集と, 開始して締め切った募集は変えずに `None` を返します。
+fn mark_cancelled(recruit_message: &mut RecruitMessage) -> Option<()> {
+    let state = &recruit_message.state;
+    if state.cancelled || (state.started && recruit_message.config.closes_on_start()) {
+        return None;
+    }
+    recruit_message.state.cancelled = true;
+    Some(())
+}
+
+/// 募集をキャンセルし, 終わった理由を `reason` として記録します
+pub async fn cancel_recruit_as(
+    api: &impl DiscordApi,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    reason: CloseReason,
+) -> Result<bool, Error> {
+    let _guard = data.lock_message(message_id).await;
+    let edited =
+        recruit_edit::edit_recruit_message(api, data, channel_id, message_id, mark_cancelled)
+            .await?;
+    let Some((recruit_message, ())) = edited else {
+        return Ok(false);
+    };
+    close_reason::close_recruit(data, message_id, Some(&recruit_message.config), reason);
+
+    countdown::cancel_countdown(api, data, message_id).await;
+    roll_call::cancel_roll_call(api, data, message_id).await;
+    voice_gate::release(data, message_id);
+    task_registry::cancel_all(data, message_id);
+    data.participant_counts.lock().unwrap().remove(&message_id);
+    // メッセージの管理権限がない場合は, 表示だけをキャンセルにします
+    if let Err(err) = api.delete_reactions(channel_id, message_id).await {
+        eprintln!("Failed to remove reactions of cancelled recruit {message_id}: {err}");
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recruit_message::RecruitConfig;
+
+    #[test]
+    fn only_open_recruits_are_cancelled() {
+        let open = RecruitMessage::new(RecruitConfig::for_test("Game", 4));
+        let mut cancelled = open.clone();
+        assert_eq!(mark_cancelled(&mut cancelled), Some(()));
+        assert!(cancelled.state.cancelled);
+        assert_eq!(mark_cancelled(&mut cancelled.clone()), None);
+
+        // 開始しても締め切らない募集は, 開始後もキャンセルできます
+        let mut started = open.clone();
+        started.state.started = true;
+        assert!(!started.config.closes_on_start());
+        assert_eq!(mark_cancelled(&mut started), Some(()));
+
+        let mut closed = open;
+        closed.state.started = true;
+        closed.config.start_time = Some(Timestamp::from_unix_timestamp(1_700_000_000).unwrap());
+        assert!(closed.config.closes_on_start());
+        assert_eq!(mark_cancelled(&mut closed), None);
+        assert!(!closed.state.cancelled);
+    }
+}
@@ -0,0 +1,75 @@
+use poise::serenity_prelude::*;
+
+/// Message Content Intent がないために機能を使えないときの案内
+pub const MESSAGE_CONTENT_REQUIRED: &str = "このコマンドには Message Content Intent が必要です";
+
+/// `JOINBELL_MESSAGE_CONTENT_INTENT` が `1` または `true` なら, Message Content Intent を使います
+///
+/// Message Content Intent は Developer Portal で有効にしていないと接続できないため, 既定では使いません。
+pub fn message_content_intent_enabled() -> bool {
+    std::env::var("JOINBELL_MESSAGE_CONTENT_INTENT")
+        .is_ok_and(|value| matches!(value.trim(), "1" | "true"))
+}
+
+/// Bot が使える Discord の機能
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Bot 以外が投稿したメッセージの本文を読み取れるか
+    pub message_content: bool,
+}
+
+impl Capabilities {
+    /// 接続に使った Intent と, Ready で受け取ったアプリケーションのフラグから判定します
+    pub fn detect(intents: GatewayIntents, flags: ApplicationFlags) -> Self {
+        let granted = flags.intersects(
+            ApplicationFlags::GATEWAY_MESSAGE_CONTENT
+                | ApplicationFlags::GATEWAY_MESSAGE_CONTENT_LIMITED,
+        );
+        Self {
+            message_content: intents.contains(GatewayIntents::MESSAGE_CONTENT) && granted,
+        }
+    }
+
+    /// Bot 以外が投稿したメッセージの本文を返します
+    ///
+    /// Message Content Intent がない場合, 本文は空で届くため本当に空のメッセージと区別できません。
+    /// そのため本文が空なら読み取れなかったものとして `None` を返します。
+    /// 本文が届いている場合 (メッセージのコンテキストメニューなど) はそのまま使います。
+    pub fn foreign_content(self, message: &Message) -> Option<&str> {
+        if self.message_content || !message.content.is_empty() {
+            Some(&message.content)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_content_needs_the_intent_and_the_flag() {
+        let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
+        let flags = ApplicationFlags::GATEWAY_MESSAGE_CONTENT_LIMITED;
+        assert!(Capabilities::detect(intents, flags).message_content);
+        assert!(!Capabilities::detect(intents, ApplicationFlags::empty()).message_content);
+        assert!(!Capabilities::detect(GatewayIntents::non_privileged(), flags).message_content);
+    }
+
+    #[test]
+    fn empty_content_is_unreadable_without_the_intent() {
+        let mut message = Message::default();
+        assert_eq!(Capabilities::default().foreign_content(&message), None);
+        let capabilities = Capabilities {
+            message_content: true,
+        };
+        assert_eq!(capabilities.foreign_content(&message), Some(""));
+        // 届いた本文は, Intent がなくても使います
+        message.content = "Apex".to_string();
+        assert_eq!(
+            Capabilities::default().foreign_content(&message),
+            Some("Apex")
+        );
+    }
+}
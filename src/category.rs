@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+
+use poise::serenity_prelude::{Mentionable, Role, RoleId};
+use serde::{Deserialize, Serialize};
+
+use crate::game_alias::normalize;
+use crate::i18n::Language;
+use crate::recruit_message::{OverMention, sanitize_text};
+use crate::time_input;
+use crate::{Data, Error, send_ephemeral};
+
+const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+
+/// 募集のカテゴリごとの既定値 (未指定の項目はサーバーの既定値または Bot の既定値を使います)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CategoryDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_on_reaction: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_assign_role_on_reaction: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delete_after_minutes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cleanup_after_start_minutes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remind_before_minutes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<Language>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roll_call: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roll_call_minutes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority_role: Option<RoleId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority_window_minutes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub over_mention: Option<OverMention>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multi_lobby: Option<bool>,
+}
+
+/// 募集の設定値を, コマンドでの指定 > カテゴリの既定値 > サーバーの既定値 > Bot の既定値 の順に決めます
+pub fn resolve<T>(explicit: Option<T>, category: Option<T>, guild: Option<T>, hardcoded: T) -> T {
+    explicit.or(category).or(guild).unwrap_or(hardcoded)
+}
+
+/// 大文字・小文字や全角・半角の違いを無視してカテゴリを探し, (登録した名前, 既定値) を返します
+pub fn find<'a>(
+    categories: &'a BTreeMap<String, CategoryDefaults>,
+    name: &str,
+) -> Option<(&'a str, &'a CategoryDefaults)> {
+    let key = normalize(name);
+    categories
+        .iter()
+        .find(|(category, _)| normalize(category) == key)
+        .map(|(category, defaults)| (category.as_str(), defaults))
+}
+
+/// カテゴリ名の入力候補
+pub async fn autocomplete_category(
+    ctx: poise::Context<'_, Data, Error>,
+    partial: &str,
+) -> impl Iterator<Item = String> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Vec::new().into_iter();
+    };
+    let partial = normalize(partial);
+    let names: Vec<String> = ctx
+        .data()
+        .storage
+        .guild_settings(guild_id)
+        .categories
+        .into_keys()
+        .filter(|name| normalize(name).contains(&partial))
+        .take(MAX_AUTOCOMPLETE_CHOICES)
+        .collect();
+    names.into_iter()
+}
+
+/// 募集のカテゴリと既定値を管理します
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "category",
+    subcommands("category_add", "category_list", "category_remove")
+)]
+pub async fn settings_category(_ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// 募集のカテゴリを追加します (同じ名前のカテゴリは置き換えます)
+#[allow(clippy::too_many_arguments)]
+#[poise::command(slash_command, guild_only, rename = "add")]
+async fn category_add(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "カテゴリ名"] name: String,
+    #[description = "参加通知を送るかどうかの既定値"] notify_on_reaction: Option<bool>,
+    #[description = "リアクション時にロールを自動付与するかどうかの既定値"]
+    auto_assign_role_on_reaction: Option<bool>,
+    #[description = "通知メッセージを削除するまでの時間の既定値 (例: 30分)"]
+    delete_after_minutes: Option<String>,
+    #[description = "開始後に募集メッセージを削除するまでの時間の既定値"]
+    cleanup_after_start_minutes: Option<String>,
+    #[description = "開始予定のどれだけ前に参加者へ DM でお知らせするかの既定値"]
+    remind_before_minutes: Option<String>,
+    #[description = "募集メッセージと通知の言語の既定値"] language: Option<Language>,
+    #[description = "人数が揃ったら開始前に参加者の点呼をとるかどうかの既定値"] roll_call: Option<
+        bool,
+    >,
+    #[description = "点呼の締切までの時間の既定値 (例: 10分)"] roll_call_minutes: Option<String>,
+    #[description = "募集の開始から優先して参加できるロール"] priority_role: Option<Role>,
+    #[description = "優先して参加できる時間 (既定: 30分)"] priority_window_minutes: Option<String>,
+    #[description = "定員より多く集まったときにメンションする参加者の既定値"] over_mention: Option<
+        OverMention,
+    >,
+    #[description = "開始に必要な人数ごとにロビーを分けて開始するかどうかの既定値"]
+    multi_lobby: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let name = sanitize_text(name.trim());
+    if name.is_empty() {
+        send_ephemeral(ctx, "カテゴリ名を入力してください").await?;
+        return Ok(());
+    }
+    let minutes = (|| {
+        let parse = |name, input: Option<String>, min| {
+            time_input::parse_optional_minutes(name, input.as_deref(), min)
+        };
+        Ok::<_, String>((
+            parse("delete_after_minutes", delete_after_minutes, 1)?,
+            parse(
+                "cleanup_after_start_minutes",
+                cleanup_after_start_minutes,
+                0,
+            )?,
+            parse("remind_before_minutes", remind_before_minutes, 1)?,
+            parse("roll_call_minutes", roll_call_minutes, 1)?,
+            parse("priority_window_minutes", priority_window_minutes, 1)?,
+        ))
+    })();
+    let (
+        delete_after_minutes,
+        cleanup_after_start_minutes,
+        remind_before_minutes,
+        roll_call_minutes,
+        priority_window_minutes,
+    ) = match minutes {
+        Ok(minutes) => minutes,
+        Err(err) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+    };
+    let defaults = CategoryDefaults {
+        notify_on_reaction,
+        auto_assign_role_on_reaction,
+        delete_after_minutes,
+        cleanup_after_start_minutes,
+        remind_before_minutes,
+        language,
+        roll_call,
+        roll_call_minutes,
+        priority_role: priority_role.map(|role| role.id),
+        priority_window_minutes,
+        over_mention,
+        multi_lobby,
+    };
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            let key = normalize(&name);
+            settings
+                .categories
+                .retain(|category, _| normalize(category) != key);
+            settings.categories.insert(name.clone(), defaults.clone());
+        })?;
+    let content = format!(
+        "カテゴリ {name} を設定しました\n{}",
+        render_defaults(&defaults)
+    );
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// 募集のカテゴリの一覧を表示します
+#[poise::command(slash_command, guild_only, rename = "list")]
+async fn category_list(ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let categories = ctx.data().storage.guild_settings(guild_id).categories;
+    if categories.is_empty() {
+        send_ephemeral(ctx, "募集のカテゴリはありません").await?;
+        return Ok(());
+    }
+    let lines: Vec<String> = categories
+        .iter()
+        .map(|(name, defaults)| format!("{name}: {}", render_defaults(defaults)))
+        .collect();
+    send_ephemeral(ctx, &lines.join("\n")).await?;
+    Ok(())
+}
+
+/// 募集のカテゴリを削除します
+#[poise::command(slash_command, guild_only, rename = "remove")]
+async fn category_remove(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "削除するカテゴリ名"]
+    #[autocomplete = "autocomplete_category"]
+    name: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let mut removed = false;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            let key = normalize(&name);
+            let before = settings.categories.len();
+            settings
+                .categories
+                .retain(|category, _| normalize(category) != key);
+            removed = settings.categories.len() < before;
+        })?;
+    let name = sanitize_text(&name);
+    let content = if removed {
+        format!("カテゴリ {name} を削除しました")
+    } else {
+        format!("{name} というカテゴリはありません")
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+fn render_defaults(defaults: &CategoryDefaults) -> String {
+    let mut items = Vec::new();
+    if let Some(notify_on_reaction) = defaults.notify_on_reaction {
+        items.push(format!("notify_on_reaction = {notify_on_reaction}"));
+    }
+    if let Some(auto_assign_role_on_reaction) = defaults.auto_assign_role_on_reaction {
+        items.push(format!(
+            "auto_assign_role_on_reaction = {auto_assign_role_on_reaction}"
+        ));
+    }
+    if let Some(delete_after_minutes) = defaults.delete_after_minutes {
+        items.push(format!("delete_after_minutes = {delete_after_minutes}"));
+    }
+    if let Some(cleanup_after_start_minutes) = defaults.cleanup_after_start_minutes {
+        items.push(format!(
+            "cleanup_after_start_minutes = {cleanup_after_start_minutes}"
+        ));
+    }
+    if let Some(remind_before_minutes) = defaults.remind_before_minutes {
+        items.push(format!("remind_before_minutes = {remind_before_minutes}"));
+    }
+    if let Some(language) = defaults.language {
+        items.push(format!("language = {}", language.code()));
+    }
+    if let Some(roll_call) = defaults.roll_call {
+        items.push(format!("roll_call = {roll_call}"));
+    }
+    if let Some(roll_call_minutes) = defaults.roll_call_minutes {
+        items.push(format!("roll_call_minutes = {roll_call_minutes}"));
+    }
+    if let Some(role_id) = defaults.priority_role {
+        items.push(format!("priority_role = {}", role_id.mention()));
+    }
+    if let Some(priority_window_minutes) = defaults.priority_window_minutes {
+        items.push(format!(
+            "priority_window_minutes = {priority_window_minutes}"
+        ));
+    }
+    if let Some(over_mention) = defaults.over_mention {
+        items.push(format!("over_mention = {}", over_mention.code()));
+    }
+    if let Some(multi_lobby) = defaults.multi_lobby {
+        items.push(format!("multi_lobby = {multi_lobby}"));
+    }
+    if items.is_empty() {
+        "既定値なし".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_values_win_over_defaults() {
+        assert_eq!(resolve(Some(1), Some(2), Some(3), 4), 1);
+        assert_eq!(resolve(None, Some(2), Some(3), 4), 2);
+        assert_eq!(resolve(None, None, Some(3), 4), 3);
+        assert_eq!(resolve(None, None, None, 4), 4);
+    }
+
+    #[test]
+    fn categories_are_found_ignoring_width_and_case() {
+        let defaults = CategoryDefaults {
+            delete_after_minutes: Some(30),
+            ..CategoryDefaults::default()
+        };
+        let categories = BTreeMap::from([("ランク".to_string(), defaults.clone())]);
+        assert_eq!(find(&categories, " らんく "), None);
+        assert_eq!(find(&categories, "ﾗﾝｸ"), Some(("ランク", &defaults)));
+        assert_eq!(find(&categories, "カジュアル"), None);
+    }
+}
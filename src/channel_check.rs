@@ -0,0 +1,200 @@
+use poise::serenity_prelude::*;
+
+use crate::{Data, Error};
+
+/// 募集を投稿するチャンネルの確認結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelSuitability {
+    Suitable,
+    /// 募集を見られるメンバーが少ないと考えられる理由
+    Warn(Vec<String>),
+    /// リアクションや通知が使えないため投稿できない理由
+    Refuse(&'static str),
+}
+
+/// 募集を投稿するチャンネルを確認します
+///
+/// スレッドの場合は, 閲覧権限などを親チャンネル `parent` で判定します。
+pub fn check_channel(
+    guild_id: GuildId,
+    everyone_permissions: Permissions,
+    channel: &GuildChannel,
+    parent: Option<&GuildChannel>,
+) -> ChannelSuitability {
+    match channel.kind {
+        ChannelType::Text
+        | ChannelType::News
+        | ChannelType::Voice
+        | ChannelType::Stage
+        | ChannelType::PublicThread
+        | ChannelType::PrivateThread
+        | ChannelType::NewsThread => {}
+        _ => {
+            return ChannelSuitability::Refuse(
+                "このチャンネルではリアクションによる募集ができません。",
+            );
+        }
+    }
+
+    let base = parent.unwrap_or(channel);
+    let mut warnings = Vec::new();
+    if !everyone_can_view(guild_id, everyone_permissions, &base.permission_overwrites) {
+        warnings.push(format!(
+            "@everyone はこのチャンネルを閲覧できません (閲覧を許可されたロール・メンバー: {})",
+            count_view_grants(guild_id, &base.permission_overwrites)
+        ));
+    }
+    if base.nsfw {
+        warnings.push("年齢制限チャンネルのため, 参加できるメンバーが限られます".to_string());
+    }
+    if matches!(channel.kind, ChannelType::Voice | ChannelType::Stage) {
+        warnings.push(
+            "ボイスチャンネルのテキストチャットは, 接続中のメンバー以外の目に留まりにくくなります"
+                .to_string(),
+        );
+    }
+    if warnings.is_empty() {
+        ChannelSuitability::Suitable
+    } else {
+        ChannelSuitability::Warn(warnings)
+    }
+}
+
+/// @everyone のサーバー全体の権限とチャンネルの @everyone の上書きから, 全員が閲覧できるかを判定します
+pub fn everyone_can_view(
+    guild_id: GuildId,
+    everyone_permissions: Permissions,
+    overwrites: &[PermissionOverwrite],
+) -> bool {
+    if everyone_permissions.administrator() {
+        return true;
+    }
+    let everyone_role = guild_id.everyone_role();
+    let mut can_view = everyone_permissions.view_channel();
+    for overwrite in overwrites {
+        if overwrite.kind == PermissionOverwriteType::Role(everyone_role) {
+            if overwrite.deny.view_channel() {
+                can_view = false;
+            }
+            if overwrite.allow.view_channel() {
+                can_view = true;
+            }
+        }
+    }
+    can_view
+}
+
+/// @everyone 以外で, チャンネルの閲覧を許可しているロールやメンバーの上書きの数
+pub fn count_view_grants(guild_id: GuildId, overwrites: &[PermissionOverwrite]) -> usize {
+    let everyone_role = guild_id.everyone_role();
+    overwrites
+        .iter()
+        .filter(|overwrite| overwrite.kind != PermissionOverwriteType::Role(everyone_role))
+        .filter(|overwrite| overwrite.allow.view_channel())
+        .count()
+}
+
+/// `/recruit create` を実行したチャンネルを, キャッシュまたは REST API から取得した権限で確認します
+pub async fn check_recruit_channel(
+    ctx: poise::Context<'_, Data, Error>,
+    guild_id: GuildId,
+    channel: &GuildChannel,
+) -> Result<ChannelSuitability, Error> {
+    let parent = match channel.thread_metadata.and(channel.parent_id) {
+        Some(parent_id) => parent_id.to_channel(ctx).await?.guild(),
+        None => None,
+    };
+    let everyone_role = guild_id.everyone_role();
+    let cached = ctx
+        .guild()
+        .and_then(|guild| guild.roles.get(&everyone_role).map(|role| role.permissions));
+    let everyone_permissions = match cached {
+        Some(permissions) => permissions,
+        None => guild_id
+            .roles(ctx)
+            .await?
+            .get(&everyone_role)
+            .map(|role| role.permissions)
+            .unwrap_or_default(),
+    };
+    Ok(check_channel(
+        guild_id,
+        everyone_permissions,
+        channel,
+        parent.as_ref(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD_ID: GuildId = GuildId::new(1);
+
+    fn overwrite(kind: PermissionOverwriteType, allow: bool) -> PermissionOverwrite {
+        let (allow, deny) = if allow {
+            (Permissions::VIEW_CHANNEL, Permissions::empty())
+        } else {
+            (Permissions::empty(), Permissions::VIEW_CHANNEL)
+        };
+        PermissionOverwrite { allow, deny, kind }
+    }
+
+    fn channel(kind: ChannelType, overwrites: Vec<PermissionOverwrite>) -> GuildChannel {
+        let mut channel = GuildChannel::default();
+        channel.kind = kind;
+        channel.permission_overwrites = overwrites;
+        channel
+    }
+
+    #[test]
+    fn everyone_overwrites_decide_who_can_view() {
+        let everyone = PermissionOverwriteType::Role(GUILD_ID.everyone_role());
+        let member = PermissionOverwriteType::Member(UserId::new(2));
+        assert!(everyone_can_view(GUILD_ID, Permissions::VIEW_CHANNEL, &[]));
+        assert!(!everyone_can_view(GUILD_ID, Permissions::empty(), &[]));
+        let hidden = [overwrite(everyone, false), overwrite(member, true)];
+        assert!(!everyone_can_view(
+            GUILD_ID,
+            Permissions::VIEW_CHANNEL,
+            &hidden
+        ));
+        assert!(everyone_can_view(
+            GUILD_ID,
+            Permissions::empty(),
+            &[overwrite(everyone, true)]
+        ));
+        assert!(everyone_can_view(
+            GUILD_ID,
+            Permissions::ADMINISTRATOR,
+            &hidden
+        ));
+        assert_eq!(count_view_grants(GUILD_ID, &hidden), 1);
+    }
+
+    #[test]
+    fn unsuitable_channels_are_warned_or_refused() {
+        let everyone = PermissionOverwriteType::Role(GUILD_ID.everyone_role());
+        let text = channel(ChannelType::Text, Vec::new());
+        assert_eq!(
+            check_channel(GUILD_ID, Permissions::VIEW_CHANNEL, &text, None),
+            ChannelSuitability::Suitable
+        );
+        let forum = channel(ChannelType::Forum, Vec::new());
+        assert!(matches!(
+            check_channel(GUILD_ID, Permissions::VIEW_CHANNEL, &forum, None),
+            ChannelSuitability::Refuse(_)
+        ));
+
+        // スレッドは親チャンネルの閲覧権限と年齢制限で判定します
+        let mut parent = channel(ChannelType::Text, vec![overwrite(everyone, false)]);
+        parent.nsfw = true;
+        let thread = channel(ChannelType::PublicThread, Vec::new());
+        let ChannelSuitability::Warn(warnings) =
+            check_channel(GUILD_ID, Permissions::VIEW_CHANNEL, &thread, Some(&parent))
+        else {
+            panic!("thread in a hidden channel is not warned");
+        };
+        assert_eq!(warnings.len(), 2);
+    }
+}
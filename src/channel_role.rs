@@ -0,0 +1,104 @@
+use poise::serenity_prelude::*;
+
+use crate::{Data, Error, send_ephemeral};
+
+/// `/recruit create` でメンションするロールを決めます
+///
+/// ロールの指定 (`mention_role` など) または `create_role` がなければ,
+/// 投稿するチャンネルに `/channel_settings channel_role` で設定したロールを使います。
+pub fn resolve(
+    explicit: &[RoleId],
+    create_role: bool,
+    channel_role: Option<RoleId>,
+) -> Option<RoleId> {
+    if !explicit.is_empty() || create_role {
+        return None;
+    }
+    channel_role
+}
+
+/// チャンネルごとに募集でメンションする既定のロールを管理します
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "channel_role",
+    subcommands("channel_role_set", "channel_role_list", "channel_role_remove")
+)]
+pub async fn settings_channel_role(_ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// チャンネルの募集でメンションするロールを設定します
+#[poise::command(slash_command, guild_only, rename = "set")]
+async fn channel_role_set(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集するチャンネル"] channel: GuildChannel,
+    #[description = "ロールの指定がないときにメンションするロール"] role: Role,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.channel_roles.insert(channel.id, role.id);
+        })?;
+    let content = format!(
+        "{} の募集でロールの指定がない場合は {} をメンションします",
+        channel.mention(),
+        role.mention()
+    );
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// チャンネルごとにメンションするロールの一覧を表示します
+#[poise::command(slash_command, guild_only, rename = "list")]
+async fn channel_role_list(ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let channel_roles = ctx.data().storage.guild_settings(guild_id).channel_roles;
+    if channel_roles.is_empty() {
+        send_ephemeral(ctx, "チャンネルごとのロールは設定されていません").await?;
+        return Ok(());
+    }
+    let lines: Vec<String> = channel_roles
+        .iter()
+        .map(|(channel_id, role_id)| format!("{}: {}", channel_id.mention(), role_id.mention()))
+        .collect();
+    send_ephemeral(ctx, &lines.join("\n")).await?;
+    Ok(())
+}
+
+/// チャンネルごとにメンションするロールの設定を解除します
+#[poise::command(slash_command, guild_only, rename = "remove")]
+async fn channel_role_remove(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "設定を解除するチャンネル"] channel: GuildChannel,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let mut removed = false;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            removed = settings.channel_roles.remove(&channel.id).is_some();
+        })?;
+    let content = if removed {
+        format!("{} のロールの設定を解除しました", channel.mention())
+    } else {
+        format!("{} にはロールが設定されていません", channel.mention())
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_role_is_used_only_without_other_roles() {
+        let channel_role = Some(RoleId::new(1));
+        assert_eq!(resolve(&[], false, channel_role), channel_role);
+        assert_eq!(resolve(&[RoleId::new(2)], false, channel_role), None);
+        assert_eq!(resolve(&[], true, channel_role), None);
+        assert_eq!(resolve(&[], false, None), None);
+    }
+}
@@ -0,0 +1,135 @@
+use std::io::Read;
+use std::path::PathBuf;
+
+use clap::{Arg, Command};
+
+use crate::Error;
+use crate::recruit_message::{RecruitMessage, extract_toml_block};
+use crate::storage_backend;
+
+/// コマンドラインで指定した動作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliCommand {
+    /// Bot を起動します (サブコマンドを省略した場合)
+    Run,
+    /// 募集メッセージの本文または toml を読み取り, 募集設定を表示します (`None` は標準入力)
+    CheckConfig(Option<PathBuf>),
+    /// 保存ファイルを現在の形式に移行して終了します
+    Migrate,
+}
+
+pub fn parse_args() -> CliCommand {
+    let matches = Command::new("joinbell")
+        .about("Discord のゲーム募集 Bot")
+        .subcommand(Command::new("run").about("Bot を起動します (既定)"))
+        .subcommand(
+            Command::new("check-config")
+                .about("募集メッセージの本文または toml を読み取り, 募集設定を表示します")
+                .arg(Arg::new("file").help("読み取るファイル (省略するか - で標準入力)")),
+        )
+        .subcommand(Command::new("migrate").about("保存ファイルを現在の形式に移行して終了します"))
+        .get_matches();
+    match matches.subcommand() {
+        Some(("check-config", matches)) => CliCommand::CheckConfig(
+            matches
+                .get_one::<String>("file")
+                .filter(|file| *file != "-")
+                .map(PathBuf::from),
+        ),
+        Some(("migrate", _)) => CliCommand::Migrate,
+        _ => CliCommand::Run,
+    }
+}
+
+/// `check-config`: 読み取った募集設定を表示します (読み取れなければエラーを返します)
+pub fn run_check_config(path: Option<PathBuf>) -> Result<(), Error> {
+    let input = match path {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input)?;
+            input
+        }
+    };
+    println!("{}", check_config(&input)?);
+    Ok(())
+}
+
+/// 募集メッセージの本文, または toml ブロックの中身だけを Bot と同じ方法で読み取ります
+///
+/// 読み取った募集設定と状態, 読み取るときに直した内容を返します。
+pub fn check_config(input: &str) -> Result<String, String> {
+    let content = if extract_toml_block(input).is_some() {
+        input.to_string()
+    } else {
+        format!("```toml\n{input}\n```")
+    };
+    let (recruit_message, warnings) = RecruitMessage::parse_with_warnings(&content)
+        .map_err(|err| format!("募集設定を読み取れませんでした: {err}"))?;
+    let mut report = toml::to_string(&recruit_message)
+        .map_err(|err| err.to_string())?
+        .trim_end()
+        .to_string();
+    for warning in warnings {
+        report += &format!("\nwarning: {warning}");
+    }
+    Ok(report)
+}
+
+/// `migrate`: `DATABASE_URL` (指定しない場合は `JOINBELL_DATA_PATH` の保存ファイル) の保存先を現在の形式で書き直します
+pub fn run_migrate() -> Result<(), Error> {
+    let storage = storage_backend::from_env()?;
+    if storage.migrate()? {
+        println!("保存ファイルを現在の形式に移行しました");
+    } else {
+        println!("移行が必要な項目はありません");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recruit_messages_and_bare_toml_are_read() {
+        let report = check_config("game_title = \"Apex\"\nrequired_players = 3").unwrap();
+        assert!(report.contains("game_title = \"Apex\""), "{report}");
+        assert!(!report.contains("warning:"), "{report}");
+
+        let message = "Apex 募集\n```toml\ngame_title = \"Apex\"\nrequired_players = 3\n```";
+        assert_eq!(check_config(message).unwrap(), report);
+    }
+
+    #[test]
+    fn fixed_values_are_reported_as_warnings() {
+        let report = check_config("game_title = \"\"\nrequired_players = 3").unwrap();
+        assert!(
+            report.contains("\nwarning: game_title が空のため"),
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn broken_blocks_are_errors() {
+        for input in [
+            "required_players = ",
+            "game_title = \"Apex\"",
+            "```toml\ngame_title = \"Apex\"\nrequired_players = \"three\"\n```",
+        ] {
+            let err = check_config(input).unwrap_err();
+            assert!(err.starts_with("募集設定を読み取れませんでした"), "{err}");
+        }
+    }
+
+    #[test]
+    fn check_config_reads_files() {
+        let path = std::env::temp_dir().join(format!("joinbell-{}-check.toml", std::process::id()));
+        std::fs::write(&path, "game_title = \"Apex\"\nrequired_players = 3").unwrap();
+        assert!(run_check_config(Some(path.clone())).is_ok());
+        std::fs::write(&path, "required_players = ").unwrap();
+        assert!(run_check_config(Some(path.clone())).is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(run_check_config(Some(path)).is_err());
+    }
+}
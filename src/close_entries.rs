@@ -0,0 +1,157 @@
+use poise::serenity_prelude::*;
+
+use crate::close_reason::{self, CloseReason};
+use crate::discord_api::DiscordApi;
+use crate::notification::NotificationPolicy;
+use crate::recruit_message::{ClosedEntries, RecruitConfig};
+use crate::scheduler::Job;
+use crate::storage::RecruitRecord;
+use crate::{
+    Data, Error, can_manage_recruit, collect_participants, countdown, i18n,
+    parse_bot_recruit_message, participant_order, recruit_edit, roll_call, send_ephemeral,
+    task_registry, voice_gate,
+};
+
+/// 募集の受付を終了し, 参加者を確定して募集メッセージに残します
+#[poise::command(slash_command, guild_only, rename = "close_entries")]
+pub async fn close_entries(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集メッセージのリンク"] message: Message,
+    #[description = "受付終了後のリアクションを外す"] remove_late_reactions: Option<bool>,
+    #[description = "募集メッセージをピン留めする"] pin: Option<bool>,
+) -> Result<(), Error> {
+    let data = ctx.data();
+    let serenity_ctx = ctx.serenity_context();
+    let _guard = data.lock_message(message.id).await;
+    let Some(recruit_message) = parse_bot_recruit_message(serenity_ctx, data, &message) else {
+        send_ephemeral(ctx, "指定されたメッセージは募集メッセージではありません。").await?;
+        return Ok(());
+    };
+
+    if !can_manage_recruit(ctx, &recruit_message.config) {
+        send_ephemeral(
+            ctx,
+            "主催者またはメッセージの管理権限を持つメンバーのみ受付を終了できます。",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let state = &recruit_message.state;
+    if state.cancelled || (state.started && recruit_message.config.closes_on_start()) {
+        send_ephemeral(ctx, "この募集はすでに終了しています。").await?;
+        return Ok(());
+    }
+    if state.closed_entries.is_some() {
+        send_ephemeral(ctx, "この募集はすでに受付を終了しています。").await?;
+        return Ok(());
+    }
+
+    let closed = close_recruit_entries(
+        serenity_ctx,
+        data,
+        &message,
+        &recruit_message.config,
+        ctx.guild_id(),
+        remove_late_reactions.unwrap_or(false),
+    )
+    .await?;
+    let Some(participants) = closed else {
+        send_ephemeral(ctx, "この募集はすでに終了しています。").await?;
+        return Ok(());
+    };
+
+    let mut reply = format!("受付を終了しました (参加者 {} 人)", participants.len());
+    // メッセージの管理権限がない場合は, ピン留めせずに受付だけを終了します
+    if pin.unwrap_or(false)
+        && let Err(err) = message.pin(ctx).await
+    {
+        eprintln!("Failed to pin closed recruit {}: {err}", message.id);
+        reply += "\nメッセージの管理権限がないため, ピン留めできませんでした";
+    }
+    send_ephemeral(ctx, &reply).await?;
+    Ok(())
+}
+
+/// 募集の受付を終了し, 確定した参加者を返します
+///
+/// すでに終わった募集や受付を終了した募集では何もせず `None` を返します。
+/// 呼び出す前に `Data::lock_message` でロックしてください。
+pub async fn close_recruit_entries(
+    api: &impl DiscordApi,
+    data: &Data,
+    message: &Message,
+    config: &RecruitConfig,
+    guild_id: Option<GuildId>,
+    remove_late_reactions: bool,
+) -> Result<Option<Vec<UserId>>, Error> {
+    let mut config = config.clone();
+    i18n::resolve_language(data, &mut config, guild_id);
+    let user_ids = collect_participants(api, data, message, &config, guild_id).await?;
+    // 順番を守る募集は参加した順に, 定員までを参加者として確定します
+    let participants = if config.strict_order {
+        let mut order =
+            participant_order::reconcile(&data.storage.participant_order(message.id), &user_ids);
+        if let Some(max_players) = config.max_players {
+            order.truncate(max_players);
+        }
+        order
+    } else {
+        let mut participants: Vec<UserId> = user_ids.into_iter().collect();
+        participants.sort();
+        participants
+    };
+
+    let channel_id = message.channel_id;
+    let edited =
+        recruit_edit::edit_recruit_message(api, data, channel_id, message.id, |recruit_message| {
+            let state = &recruit_message.state;
+            if state.cancelled
+                || (state.started && recruit_message.config.closes_on_start())
+                || state.closed_entries.is_some()
+            {
+                return None;
+            }
+            recruit_message.state.participant_count = Some(participants.len());
+            recruit_message.state.closed_entries = Some(ClosedEntries {
+                participants: participants.clone(),
+                remove_late_reactions,
+            });
+            Some(())
+        })
+        .await?;
+    if edited.is_none() {
+        return Ok(None);
+    }
+
+    countdown::cancel_countdown(api, data, message.id).await;
+    roll_call::cancel_roll_call(api, data, message.id).await;
+    voice_gate::release(data, message.id);
+    // 受付を終了した募集は記録として残すため, リマインドと開始した募集の削除は取り消しません
+    task_registry::cancel_matching(data, message.id, |job| {
+        matches!(
+            job,
+            Job::EndPriorityWindow { .. } | Job::RetryStartNotification { .. }
+        )
+    });
+
+    if let Some(guild_id) = guild_id
+        && NotificationPolicy::new(&config).has_side_effects()
+    {
+        let record = RecruitRecord {
+            game_title: config.game_title.clone(),
+            category: config.category.clone(),
+            channel_id,
+            message_id: message.id,
+            started_at: Timestamp::now(),
+            participants: participants.clone(),
+            required_players: Some(config.required_players),
+            reacted_count: Some(participants.len()),
+        };
+        if let Err(err) = data.storage.record_recruit(guild_id, record) {
+            eprintln!("Failed to record closed recruit: {err}");
+        }
+    }
+    close_reason::close_recruit(data, message.id, Some(&config), CloseReason::EntriesClosed);
+    Ok(Some(participants))
+}
@@ -0,0 +1,280 @@
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::recruit_log::{self, RecruitEventKind};
+use crate::recruit_message::RecruitConfig;
+use crate::{Data, mirror};
+
+/// 募集が終わった理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseReason {
+    /// 開始通知を送った (ロビーの開始を含みます)
+    Started,
+    /// `/close_entries` で受付を終了した
+    EntriesClosed,
+    /// 投稿したときの返信の「取り消す」で取り消した
+    Cancelled,
+    /// 開始・受付終了・取り消しのいずれもしないまま, 募集メッセージが削除された
+    Deleted,
+    /// 開始予定の日時までに人数が揃わなかった
+    Expired,
+    /// `/moderate_recruits` でまとめて終了した
+    ModeratorBulk,
+}
+
+impl CloseReason {
+    /// 集計で「成立」として数えるかどうか
+    pub fn is_filled(self) -> bool {
+        matches!(self, CloseReason::Started | CloseReason::EntriesClosed)
+    }
+}
+
+impl std::fmt::Display for CloseReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CloseReason::Started => "開始",
+            CloseReason::EntriesClosed => "受付終了",
+            CloseReason::Cancelled => "キャンセル",
+            CloseReason::Deleted => "削除",
+            CloseReason::Expired => "不成立",
+            CloseReason::ModeratorBulk => "一括終了",
+        })
+    }
+}
+
+/// 終わった募集の記録
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClosureRecord {
+    pub message_id: MessageId,
+    pub channel_id: ChannelId,
+    /// ゲーム名 (募集設定を読み取れないまま削除された募集にはありません)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    pub reason: CloseReason,
+    pub closed_at: Timestamp,
+    /// 人数が揃わなかった募集の, その時点の参加者
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub participants: Vec<UserId>,
+}
+
+/// 募集が終わったことを, 理由とともに記録します
+///
+/// 募集が終わる処理はすべてこの関数を通し, 理由を記録し忘れないようにします。
+/// 同じ募集は最初に終わった理由だけを記録するため, 開始を繰り返す募集の 2 回目以降の開始や,
+/// 開始した募集の削除は記録し直しません。場所を保存していない募集と動作確認用の募集は記録しません。
+pub fn close_recruit(
+    data: &Data,
+    message_id: MessageId,
+    config: Option<&RecruitConfig>,
+    reason: CloseReason,
+) {
+    record(data, message_id, config, reason, Vec::new());
+}
+
+/// 開始予定の日時までに人数が揃わなかった募集を, その時点の参加者とともに記録します
+pub fn close_expired(
+    data: &Data,
+    message_id: MessageId,
+    config: &RecruitConfig,
+    participants: Vec<UserId>,
+) {
+    record(
+        data,
+        message_id,
+        Some(config),
+        CloseReason::Expired,
+        participants,
+    );
+}
+
+fn record(
+    data: &Data,
+    message_id: MessageId,
+    config: Option<&RecruitConfig>,
+    reason: CloseReason,
+    participants: Vec<UserId>,
+) {
+    if config.is_some_and(|config| config.simulation) {
+        return;
+    }
+    // 開始しても締め切らない募集は, 受付中のままミラーします
+    if reason != CloseReason::Started || config.is_none_or(RecruitConfig::closes_on_start) {
+        mirror::close(data, message_id, reason);
+    }
+    let Some(location) = data.storage.recruit_location(message_id) else {
+        return;
+    };
+    let Some(guild_id) = location.guild_id else {
+        return;
+    };
+    // 開始は参加者を確定した時点で `recruit_log` に記録するため, ここでは開始以外を記録します
+    let snapshot = if reason == CloseReason::Started {
+        None
+    } else if participants.is_empty() {
+        Some(data.storage.participant_order(message_id))
+    } else {
+        Some(participants.clone())
+    };
+    let record = ClosureRecord {
+        message_id,
+        channel_id: location.channel_id,
+        game_title: config.map(|config| config.game_title.clone()),
+        category: config.and_then(|config| config.category.clone()),
+        reason,
+        closed_at: Timestamp::now(),
+        participants,
+    };
+    match data.storage.record_closure(guild_id, record) {
+        Ok(true) => {
+            if let Some(snapshot) = snapshot {
+                recruit_log::record(
+                    data,
+                    message_id,
+                    config,
+                    RecruitEventKind::Closed { reason },
+                    snapshot,
+                );
+            }
+        }
+        Ok(false) => {}
+        Err(err) => eprintln!("Failed to record close reason of {message_id}: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_discord::{self, MockDiscord};
+    use crate::recruit_message::RecruitMessage;
+    use crate::storage::RecruitLocation;
+    use crate::{cancel, close_entries, expiry, moderate};
+
+    const GUILD_ID: GuildId = GuildId::new(10);
+    const CHANNEL_ID: ChannelId = ChannelId::new(20);
+    const RECRUIT_ID: MessageId = MessageId::new(30);
+
+    /// 2 人で開始する募集を投稿し, 場所を記録します
+    fn post_recruit(discord: &MockDiscord, data: &Data) -> Message {
+        let config = RecruitConfig::for_test("Apex", 2);
+        let content = RecruitMessage::new(config).render(&data.config.emojis);
+        let message = mock_discord::message(CHANNEL_ID, RECRUIT_ID, &content);
+        discord.insert_message(message.clone());
+        let location = RecruitLocation {
+            guild_id: Some(GUILD_ID),
+            channel_id: CHANNEL_ID,
+        };
+        data.storage
+            .remember_recruit_location(RECRUIT_ID, location)
+            .unwrap();
+        message
+    }
+
+    fn reason(data: &Data) -> Option<CloseReason> {
+        data.storage
+            .closure(GUILD_ID, RECRUIT_ID)
+            .map(|closure| closure.reason)
+    }
+
+    #[tokio::test]
+    async fn cancelling_records_the_reason() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        post_recruit(&discord, &data);
+        let cancelled = cancel::cancel_recruit(&discord, &data, CHANNEL_ID, RECRUIT_ID).await;
+        assert!(cancelled.unwrap());
+        assert_eq!(reason(&data), Some(CloseReason::Cancelled));
+    }
+
+    #[tokio::test]
+    async fn closing_entries_records_the_reason() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        let message = post_recruit(&discord, &data);
+        let config = RecruitConfig::for_test("Apex", 2);
+        let closed = close_entries::close_recruit_entries(
+            &discord,
+            &data,
+            &message,
+            &config,
+            Some(GUILD_ID),
+            false,
+        )
+        .await;
+        assert_eq!(closed.unwrap(), Some(Vec::new()));
+        assert_eq!(reason(&data), Some(CloseReason::EntriesClosed));
+    }
+
+    #[tokio::test]
+    async fn expiry_records_the_reason_with_the_participants() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        post_recruit(&discord, &data);
+        discord.insert_member(mock_discord::member(GUILD_ID, 11));
+        discord.add_reaction(RECRUIT_ID, "✋", 11);
+        expiry::expire_recruit(&discord, &data, CHANNEL_ID, RECRUIT_ID, Some(GUILD_ID))
+            .await
+            .unwrap();
+        let closure = data.storage.closure(GUILD_ID, RECRUIT_ID).unwrap();
+        assert_eq!(closure.reason, CloseReason::Expired);
+        assert_eq!(closure.participants, [UserId::new(11)]);
+    }
+
+    #[tokio::test]
+    async fn starting_records_the_reason() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        let message = post_recruit(&discord, &data);
+        for user_id in [11, 12] {
+            discord.insert_member(mock_discord::member(GUILD_ID, user_id));
+            discord.add_reaction(RECRUIT_ID, "✋", user_id);
+            let reaction = mock_discord::reaction(&message, GUILD_ID, user_id, "✋");
+            crate::handle_reaction_add(&discord, &data, &reaction)
+                .await
+                .unwrap();
+        }
+        assert_eq!(reason(&data), Some(CloseReason::Started));
+    }
+
+    #[tokio::test]
+    async fn deleting_records_the_reason() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        let message = post_recruit(&discord, &data);
+        let recruit_message = crate::config_storage::load(&data, &message).unwrap();
+        data.storage
+            .set_recruit_message(RECRUIT_ID, recruit_message)
+            .unwrap();
+        data.forget_deleted_message(RECRUIT_ID);
+        assert_eq!(reason(&data), Some(CloseReason::Deleted));
+        // 場所の記録は消しても, 終わった理由は残します
+        assert_eq!(data.storage.recruit_location(RECRUIT_ID), None);
+    }
+
+    #[tokio::test]
+    async fn bulk_closing_records_the_reason() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        post_recruit(&discord, &data);
+        let report = moderate::close_all(&discord, &data, vec![(RECRUIT_ID, CHANNEL_ID)]).await;
+        assert_eq!(report.closed, 1);
+        assert_eq!(reason(&data), Some(CloseReason::ModeratorBulk));
+        assert!(data.storage.bulk_closes().is_empty());
+    }
+
+    #[tokio::test]
+    async fn only_the_first_reason_is_recorded() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        post_recruit(&discord, &data);
+        assert!(
+            cancel::cancel_recruit(&discord, &data, CHANNEL_ID, RECRUIT_ID)
+                .await
+                .unwrap()
+        );
+        data.forget_deleted_message(RECRUIT_ID);
+        assert_eq!(reason(&data), Some(CloseReason::Cancelled));
+    }
+}
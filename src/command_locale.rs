@@ -0,0 +1,170 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::i18n::Language;
+use crate::{Data, Error};
+
+/// 日本語 (既定の説明) のほかに説明を登録する言語
+const TRANSLATED_LANGUAGES: &[Language] = &[Language::En];
+
+/// 登録する前のコマンドに, `i18n` の説明と名前を追加します
+///
+/// 説明がないコマンドやパラメーターは, ログに出力して日本語のまま登録します。
+pub fn localize(commands: &mut [poise::Command<Data, Error>]) {
+    for &language in TRANSLATED_LANGUAGES {
+        let report = translate(commands, language);
+        for key in report.missing {
+            eprintln!("Missing {} description for command {key}", language.code());
+        }
+        for name in report.missing_choices {
+            eprintln!("Missing {} name for choice {name}", language.code());
+        }
+        for key in report.unused {
+            eprintln!("Unused {} description for command {key}", language.code());
+        }
+    }
+}
+
+/// 1 つの言語の説明を追加した結果
+#[derive(Debug, Default)]
+struct Report {
+    /// 説明がないコマンドとパラメーター
+    missing: Vec<String>,
+    /// 名前がない選択肢 (日本語の名前)
+    missing_choices: Vec<String>,
+    /// どのコマンドにも使わなかった説明
+    unused: Vec<&'static str>,
+}
+
+fn translate(commands: &mut [poise::Command<Data, Error>], language: Language) -> Report {
+    let mut used = HashSet::new();
+    let mut report = Report::default();
+    for command in commands {
+        translate_command(command, "", language, &mut used, &mut report);
+    }
+    report.unused = language
+        .command_description_keys()
+        .filter(|key| !used.contains(key))
+        .collect();
+    report
+}
+
+fn translate_command(
+    command: &mut poise::Command<Data, Error>,
+    parent: &str,
+    language: Language,
+    used: &mut HashSet<&'static str>,
+    report: &mut Report,
+) {
+    let key = if parent.is_empty() {
+        command.name.clone()
+    } else {
+        format!("{parent} {}", command.name)
+    };
+    // poise はコンテキストメニューのコマンドの翻訳を登録しないため, 日本語の名前のままにします
+    if command.context_menu_action.is_none() {
+        match description(language, &key, used) {
+            Some(description) => insert_locales(
+                &mut command.description_localizations,
+                language,
+                description,
+            ),
+            None => report.missing.push(key.clone()),
+        }
+    }
+
+    for parameter in &mut command.parameters {
+        // コンテキストメニューの対象のメッセージには説明がありません
+        if parameter.description.is_some() {
+            let parameter_key = format!("{key}.{}", parameter.name);
+            match description(language, &parameter_key, used) {
+                Some(description) => insert_locales(
+                    &mut parameter.description_localizations,
+                    language,
+                    description,
+                ),
+                None => report.missing.push(parameter_key),
+            }
+        }
+        for choice in &mut parameter.choices {
+            match language.choice_name(&choice.name) {
+                Some(name) => insert_locales(&mut choice.localizations, language, name),
+                // 「English」や「7d」はどの言語でもそのまま表示します
+                None if choice.name.is_ascii() => {}
+                None => report.missing_choices.push(choice.name.to_string()),
+            }
+        }
+    }
+    for subcommand in &mut command.subcommands {
+        translate_command(subcommand, &key, language, used, report);
+    }
+}
+
+fn description(
+    language: Language,
+    key: &str,
+    used: &mut HashSet<&'static str>,
+) -> Option<&'static str> {
+    let (key, description) = language.command_description(key)?;
+    used.insert(key);
+    Some(description)
+}
+
+fn insert_locales(
+    localizations: &mut HashMap<String, String>,
+    language: Language,
+    text: &'static str,
+) {
+    for locale in language.discord_locales() {
+        localizations.insert(locale.to_string(), text.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_command_is_translated() {
+        for &language in TRANSLATED_LANGUAGES {
+            let report = translate(&mut crate::commands(), language);
+            assert!(
+                report.missing.is_empty(),
+                "missing {} descriptions: {:?}",
+                language.code(),
+                report.missing
+            );
+            assert!(
+                report.missing_choices.is_empty(),
+                "missing {} choice names: {:?}",
+                language.code(),
+                report.missing_choices
+            );
+            assert!(
+                report.unused.is_empty(),
+                "unused {} descriptions: {:?}",
+                language.code(),
+                report.unused
+            );
+        }
+    }
+
+    #[test]
+    fn descriptions_are_registered_for_each_locale() {
+        let mut commands = crate::commands();
+        translate(&mut commands, Language::En);
+        let recruit = commands
+            .iter()
+            .find(|command| command.name == "recruit")
+            .unwrap();
+        for locale in ["en-US", "en-GB"] {
+            assert_eq!(
+                recruit
+                    .description_localizations
+                    .get(locale)
+                    .map(String::as_str),
+                Some("Create and manage recruits")
+            );
+        }
+        assert!(!recruit.description_localizations.contains_key("ja"));
+    }
+}
@@ -0,0 +1,138 @@
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::recruit_message::{ConfigWarning, RecruitMessage};
+use crate::{Data, Error, summary};
+
+/// 募集設定の保存先
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, poise::ChoiceParameter,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigStorage {
+    /// 募集メッセージの toml ブロック
+    #[default]
+    #[name = "募集メッセージ"]
+    Message,
+    /// Bot の保存ファイル (募集メッセージには説明文だけを表示します)
+    #[name = "保存ファイル"]
+    Database,
+}
+
+impl ConfigStorage {
+    pub fn code(self) -> &'static str {
+        match self {
+            ConfigStorage::Message => "message",
+            ConfigStorage::Database => "database",
+        }
+    }
+}
+
+/// 募集メッセージの募集設定と状態を, 保存ファイルに保存していればそこから, なければ toml ブロックから読み取ります
+///
+/// 保存ファイルに保存するようになる前に投稿された募集も, 引き続き toml ブロックから読み取ります。
+pub fn load(data: &Data, message: &Message) -> Result<RecruitMessage, String> {
+    match data.storage.recruit_message(message.id) {
+        Some(recruit_message) => Ok(recruit_message),
+        None => RecruitMessage::parse(&message.content),
+    }
+}
+
+/// `load` と同じように読み取り, toml ブロックの募集設定を直した内容も返します
+///
+/// 保存ファイルの募集設定は投稿時に直しているため, 直した内容は返しません。
+pub fn load_with_warnings(
+    data: &Data,
+    message: &Message,
+) -> Result<(RecruitMessage, Vec<ConfigWarning>), String> {
+    match data.storage.recruit_message(message.id) {
+        Some(recruit_message) => Ok((recruit_message, Vec::new())),
+        None => RecruitMessage::parse_with_warnings(&message.content),
+    }
+}
+
+/// 募集設定を保存ファイルに保存している募集かどうか
+pub fn is_stored(data: &Data, message_id: MessageId) -> bool {
+    data.storage.recruit_message(message_id).is_some()
+}
+
+/// 募集メッセージを編集したときの本文を, 保存せずに返します
+pub fn render(data: &Data, message_id: MessageId, recruit_message: &RecruitMessage) -> String {
+    if is_stored(data, message_id) {
+        recruit_message.render_without_config(&data.config.emojis)
+    } else {
+        recruit_message.render(&data.config.emojis)
+    }
+}
+
+/// 募集メッセージを編集する前に, 保存ファイルに保存している募集なら変更を保存し, 編集後の本文を返します
+///
+/// 今日の募集の一覧も, 編集した募集の状態で書き換えるよう予約します。
+pub fn store(
+    data: &Data,
+    message_id: MessageId,
+    recruit_message: &RecruitMessage,
+) -> Result<String, Error> {
+    summary::mark_message(data, message_id);
+    if !is_stored(data, message_id) {
+        return Ok(recruit_message.render(&data.config.emojis));
+    }
+    data.storage
+        .set_recruit_message(message_id, recruit_message.clone())?;
+    Ok(recruit_message.render_without_config(&data.config.emojis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_discord;
+    use crate::recruit_message::RecruitConfig;
+
+    const CHANNEL_ID: ChannelId = ChannelId::new(1);
+    const MESSAGE_ID: MessageId = MessageId::new(2);
+
+    fn recruit_message() -> RecruitMessage {
+        RecruitMessage::new(RecruitConfig::for_test("Apex", 3))
+    }
+
+    #[test]
+    fn legacy_recruits_are_read_from_the_toml_block() {
+        let data = Data::for_test();
+        let recruit_message = recruit_message();
+        let content = recruit_message.render(&data.config.emojis);
+        let message = mock_discord::message(CHANNEL_ID, MESSAGE_ID, &content);
+
+        assert!(!is_stored(&data, MESSAGE_ID));
+        assert_eq!(load(&data, &message), Ok(recruit_message.clone()));
+        assert_eq!(render(&data, MESSAGE_ID, &recruit_message), content);
+
+        let mut edited = recruit_message;
+        edited.config.required_players = 4;
+        let content = store(&data, MESSAGE_ID, &edited).unwrap();
+        assert_eq!(content, edited.render(&data.config.emojis));
+        // toml ブロックの募集は保存ファイルに移しません
+        assert!(!is_stored(&data, MESSAGE_ID));
+    }
+
+    #[test]
+    fn stored_recruits_are_read_from_the_storage() {
+        let data = Data::for_test();
+        let recruit_message = recruit_message();
+        data.storage
+            .set_recruit_message(MESSAGE_ID, recruit_message.clone())
+            .unwrap();
+        let content = recruit_message.render_without_config(&data.config.emojis);
+        let message = mock_discord::message(CHANNEL_ID, MESSAGE_ID, &content);
+
+        assert!(is_stored(&data, MESSAGE_ID));
+        assert_eq!(load(&data, &message), Ok(recruit_message.clone()));
+        assert_eq!(render(&data, MESSAGE_ID, &recruit_message), content);
+        assert!(!content.contains("```toml"));
+
+        let mut edited = recruit_message;
+        edited.config.required_players = 4;
+        let content = store(&data, MESSAGE_ID, &edited).unwrap();
+        assert_eq!(content, edited.render_without_config(&data.config.emojis));
+        assert_eq!(data.storage.recruit_message(MESSAGE_ID), Some(edited));
+    }
+}
@@ -0,0 +1,69 @@
+use poise::{CreateReply, serenity_prelude::*};
+use tokio::time::Duration;
+
+use crate::{Data, Error};
+
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 実行者にのみ確認ボタンを表示し, `confirm_label` のボタンが押されたら true を返します
+///
+/// キャンセルされた場合や 1 分以内に押されなかった場合は, その旨を表示して false を返します。
+pub async fn confirm(
+    ctx: poise::Context<'_, Data, Error>,
+    content: &str,
+    confirm_label: &str,
+) -> Result<bool, Error> {
+    let confirm_id = format!("{}:confirm", ctx.id());
+    let cancel_id = format!("{}:cancel", ctx.id());
+    let reply = ctx
+        .send(
+            CreateReply::default()
+                .content(content)
+                .ephemeral(true)
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new(&confirm_id)
+                        .label(confirm_label)
+                        .style(ButtonStyle::Danger),
+                    CreateButton::new(&cancel_id)
+                        .label("キャンセル")
+                        .style(ButtonStyle::Secondary),
+                ])]),
+        )
+        .await?;
+
+    let prefix = format!("{}:", ctx.id());
+    let interaction = ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .filter(move |interaction| interaction.data.custom_id.starts_with(&prefix))
+        .timeout(CONFIRM_TIMEOUT)
+        .await;
+    let Some(interaction) = interaction else {
+        reply
+            .edit(
+                ctx,
+                CreateReply::default()
+                    .content("時間内に確認されなかったためキャンセルしました")
+                    .components(Vec::new()),
+            )
+            .await?;
+        return Ok(false);
+    };
+
+    let confirmed = interaction.data.custom_id == confirm_id;
+    let content = if confirmed {
+        content.to_string()
+    } else {
+        "キャンセルしました".to_string()
+    };
+    interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .components(Vec::new()),
+            ),
+        )
+        .await?;
+    Ok(confirmed)
+}
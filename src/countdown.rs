@@ -0,0 +1,440 @@
+use std::collections::HashSet;
+
+use poise::serenity_prelude::*;
+use tokio::time::Duration;
+
+use crate::config_storage;
+use crate::discord_api::DiscordApi;
+use crate::i18n::{self, Language, Text};
+use crate::member_work::{self, MemberStrategy};
+use crate::notification::{Mentions, NotificationPolicy};
+use crate::recruit_message::RecruitConfig;
+use crate::scheduler::Job;
+use crate::{Data, Error, collect_participants, participant_order, send_start_notification};
+
+/// 延期ボタンの custom_id の接頭辞 (後ろに募集メッセージの ID を付けます)
+const SNOOZE_PREFIX: &str = "snooze_start:";
+/// `/settings snooze` の `minutes` を指定しなかった場合の 1 回の延期の分数
+const DEFAULT_SNOOZE_MINUTES: u64 = 15;
+/// `/settings snooze` の `max_minutes` を指定しなかった場合の延期できる合計の分数
+const DEFAULT_MAX_SNOOZE_MINUTES: u64 = 60;
+
+/// 開始までのカウントダウン中の募集
+pub struct PendingStart {
+    /// カウントダウンを始めた時点の参加者
+    pub snapshot: HashSet<UserId>,
+    pub notice_channel_id: ChannelId,
+    pub notice_message_id: MessageId,
+    /// 参加人数を確認して開始する日時 (延期すると遅くなります)
+    pub start_at: Timestamp,
+    /// 主催者が延期した合計の分数
+    pub snoozed_minutes: u64,
+}
+
+/// サーバー設定の (1 回の延期の分数, 延期できる合計の分数)
+pub fn snooze_limits(data: &Data, guild_id: Option<GuildId>) -> (u64, u64) {
+    let settings = guild_id.map(|guild_id| data.storage.guild_settings(guild_id));
+    let minutes = settings
+        .as_ref()
+        .and_then(|settings| settings.snooze_minutes)
+        .unwrap_or(DEFAULT_SNOOZE_MINUTES);
+    let max_minutes = settings
+        .as_ref()
+        .and_then(|settings| settings.max_snooze_minutes)
+        .unwrap_or(DEFAULT_MAX_SNOOZE_MINUTES);
+    (minutes, max_minutes)
+}
+
+/// さらに `minutes` 分延期した場合の延期の合計 (`max_minutes` を超える場合は `None`)
+pub fn next_snooze(snoozed_minutes: u64, minutes: u64, max_minutes: u64) -> Option<u64> {
+    let total = snoozed_minutes.checked_add(minutes)?;
+    (total <= max_minutes).then_some(total)
+}
+
+/// カウントダウンのお知らせに付ける延期ボタン (これ以上延期できない場合は付けません)
+fn snooze_components(
+    config: &RecruitConfig,
+    message_id: MessageId,
+    snoozed_minutes: u64,
+    (minutes, max_minutes): (u64, u64),
+) -> Vec<CreateActionRow> {
+    if next_snooze(snoozed_minutes, minutes, max_minutes).is_none() {
+        return Vec::new();
+    }
+    let button = CreateButton::new(format!("{SNOOZE_PREFIX}{message_id}"))
+        .label(config.language().text(Text::SnoozeButton { minutes }))
+        .style(ButtonStyle::Secondary);
+    vec![CreateActionRow::Buttons(vec![button])]
+}
+
+/// 参加人数が揃った募集のカウントダウンを始めます (すでにカウントダウン中なら何もしません)
+pub async fn start_countdown(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &RecruitConfig,
+    message: &Message,
+    guild_id: Option<GuildId>,
+    user_ids: HashSet<UserId>,
+    delay_minutes: u64,
+) -> Result<(), Error> {
+    if data
+        .pending_starts
+        .lock()
+        .unwrap()
+        .contains_key(&message.id)
+    {
+        return Ok(());
+    }
+
+    let delay = Duration::from_secs(delay_minutes.saturating_mul(60));
+    let start_at =
+        Timestamp::from_unix_timestamp(Timestamp::now().unix_timestamp() + delay.as_secs() as i64)?;
+    let policy = NotificationPolicy::new(config);
+    let content = config.language().text(Text::CountdownStarted {
+        game_title: &config.game_title,
+        start_at: start_at.unix_timestamp(),
+    });
+    let components = snooze_components(config, message.id, 0, snooze_limits(data, guild_id));
+    let notice = api
+        .send_message(
+            message.channel_id,
+            policy
+                .message(&content, &Mentions::none())
+                .components(components),
+        )
+        .await?;
+    let mut snapshot: Vec<UserId> = user_ids.iter().copied().collect();
+    snapshot.sort();
+    data.pending_starts.lock().unwrap().insert(
+        message.id,
+        PendingStart {
+            snapshot: user_ids,
+            notice_channel_id: notice.channel_id,
+            notice_message_id: notice.id,
+            start_at,
+            snoozed_minutes: 0,
+        },
+    );
+
+    data.scheduler.enqueue(
+        &*data.storage,
+        start_at,
+        Job::FinishCountdown {
+            channel_id: message.channel_id,
+            message_id: message.id,
+            guild_id,
+            snapshot,
+            notice_channel_id: notice.channel_id,
+            notice_message_id: notice.id,
+            snoozed_minutes: 0,
+        },
+    );
+    Ok(())
+}
+
+/// カウントダウンの延期ボタンかどうか
+pub fn is_snooze_button(interaction: &ComponentInteraction) -> bool {
+    interaction.data.custom_id.starts_with(SNOOZE_PREFIX)
+}
+
+/// 主催者が延期ボタンを押したら, 参加人数を確認する日時を遅らせてお知らせします
+///
+/// 延期した後も, 参加人数は遅らせた日時に確認し直します (参加者が減っていれば開始しません)。
+pub async fn handle_snooze_button(
+    ctx: &Context,
+    data: &Data,
+    interaction: &ComponentInteraction,
+) -> Result<(), Error> {
+    let Some(message_id) = interaction
+        .data
+        .custom_id
+        .strip_prefix(SNOOZE_PREFIX)
+        .and_then(|id| id.parse().ok())
+        .map(MessageId::new)
+    else {
+        return Ok(());
+    };
+    let guild_id = interaction.guild_id;
+    let _guard = data.lock_message(message_id).await;
+    let pending = data
+        .pending_starts
+        .lock()
+        .unwrap()
+        .get(&message_id)
+        .map(|pending| {
+            (
+                pending.snapshot.clone(),
+                pending.start_at,
+                pending.snoozed_minutes,
+            )
+        });
+    let Some((snapshot, start_at, snoozed_minutes)) = pending else {
+        let language = i18n::guild_language(data, guild_id);
+        return respond(ctx, interaction, &language.text(Text::SnoozeClosed)).await;
+    };
+    // カウントダウンのお知らせは募集メッセージと同じチャンネルに送っています
+    let channel_id = interaction.channel_id;
+    let message = channel_id.message(ctx, message_id).await?;
+    let mut config = config_storage::load(data, &message)?.config;
+    i18n::resolve_language(data, &mut config, guild_id);
+    let language = config.language();
+
+    if config.organizer != Some(interaction.user.id) {
+        return respond(ctx, interaction, &language.text(Text::SnoozeNotOrganizer)).await;
+    }
+    let limits = snooze_limits(data, guild_id);
+    let (minutes, max_minutes) = limits;
+    let Some(snoozed_minutes) = next_snooze(snoozed_minutes, minutes, max_minutes) else {
+        let content = language.text(Text::SnoozeLimit { max_minutes });
+        return respond(ctx, interaction, &content).await;
+    };
+    let start_at = Timestamp::from_unix_timestamp(
+        start_at
+            .unix_timestamp()
+            .saturating_add(minutes.saturating_mul(60).min(i64::MAX as u64) as i64),
+    )?;
+
+    data.storage.cancel_jobs(
+        &|job| matches!(job, Job::FinishCountdown { message_id: id, .. } if *id == message_id),
+    )?;
+    let mut sorted_snapshot: Vec<UserId> = snapshot.iter().copied().collect();
+    sorted_snapshot.sort();
+    let notice = interaction.message.id;
+    data.scheduler.enqueue(
+        &*data.storage,
+        start_at,
+        Job::FinishCountdown {
+            channel_id,
+            message_id,
+            guild_id,
+            snapshot: sorted_snapshot,
+            notice_channel_id: channel_id,
+            notice_message_id: notice,
+            snoozed_minutes,
+        },
+    );
+    if let Some(pending) = data.pending_starts.lock().unwrap().get_mut(&message_id) {
+        pending.start_at = start_at;
+        pending.snoozed_minutes = snoozed_minutes;
+    }
+
+    interaction
+        .create_response(ctx, CreateInteractionResponse::Acknowledge)
+        .await?;
+    let policy = NotificationPolicy::new(&config);
+    let content = language.text(Text::CountdownStarted {
+        game_title: &config.game_title,
+        start_at: start_at.unix_timestamp(),
+    });
+    let components = snooze_components(&config, message_id, snoozed_minutes, limits);
+    channel_id
+        .edit_message(ctx, notice, policy.edit(&content).components(components))
+        .await?;
+    let content = language.text(Text::Snoozed {
+        game_title: &config.game_title,
+        minutes,
+        start_at: start_at.unix_timestamp(),
+    });
+    policy
+        .send(
+            ctx,
+            data,
+            channel_id,
+            policy.message(&content, &Mentions::none()),
+        )
+        .await?;
+    Ok(())
+}
+
+async fn respond(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    content: &str,
+) -> Result<(), Error> {
+    interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+/// カウントダウンを取り消します (ベルで即時開始した場合など)
+pub async fn cancel_countdown(api: &impl DiscordApi, data: &Data, message_id: MessageId) {
+    let pending = data.pending_starts.lock().unwrap().remove(&message_id);
+    if let Err(err) = data.storage.cancel_jobs(
+        &|job| matches!(job, Job::FinishCountdown { message_id: id, .. } if *id == message_id),
+    ) {
+        eprintln!("Failed to cancel countdown job: {err}");
+    }
+    if let Some(pending) = pending {
+        let _ = api
+            .delete_message(pending.notice_channel_id, pending.notice_message_id)
+            .await;
+    }
+}
+
+pub async fn finish_countdown(
+    ctx: &Context,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+) -> Result<(), Error> {
+    let _guard = data.lock_message(message_id).await;
+    let Some(pending) = data.pending_starts.lock().unwrap().remove(&message_id) else {
+        return Ok(());
+    };
+    let _ = pending
+        .notice_channel_id
+        .delete_message(ctx, pending.notice_message_id)
+        .await;
+
+    let message = channel_id.message(ctx, message_id).await?;
+    let mut config = config_storage::load(data, &message)?.config;
+    i18n::resolve_language(data, &mut config, guild_id);
+    let user_ids = collect_participants(ctx, data, &message, &config, guild_id).await?;
+    let policy = NotificationPolicy::new(&config);
+
+    if config.required_players <= user_ids.len() {
+        let participants = participant_order::ordered(data, message_id, &user_ids);
+        return send_start_notification(
+            ctx,
+            data,
+            &config,
+            &policy,
+            &message,
+            guild_id,
+            participants,
+        )
+        .await;
+    }
+
+    let withdrawn = withdrawn_users(&pending.snapshot, &user_ids);
+    // 抜けた参加者が多い大きなサーバーでは, 表示名を 1 人ずつ取得せずにメンションで表示します
+    let strategy = match guild_id {
+        Some(guild_id) => member_work::select(
+            data,
+            guild_id,
+            "Countdown withdrawal notice",
+            member_work::uncached(ctx, guild_id, &withdrawn).len(),
+        ),
+        None => MemberStrategy::PerMember,
+    };
+    let mut withdrawn_names = Vec::new();
+    for user_id in withdrawn {
+        withdrawn_names.push(if strategy.shows_display_names() {
+            display_name(ctx, guild_id, user_id).await
+        } else {
+            user_id.mention().to_string()
+        });
+    }
+    let content = render_withdrawal_notice(
+        config.language(),
+        &config.game_title,
+        &withdrawn_names,
+        user_ids.len(),
+        config.required_players,
+    );
+    policy
+        .send(
+            ctx,
+            data,
+            channel_id,
+            policy.message(&content, &Mentions::none()),
+        )
+        .await?;
+    Ok(())
+}
+
+/// カウントダウン開始時にいて, 現在はいない参加者
+pub fn withdrawn_users(snapshot: &HashSet<UserId>, current: &HashSet<UserId>) -> Vec<UserId> {
+    let mut withdrawn: Vec<UserId> = snapshot.difference(current).copied().collect();
+    withdrawn.sort();
+    withdrawn
+}
+
+pub fn render_withdrawal_notice(
+    language: Language,
+    game_title: &str,
+    withdrawn_names: &[String],
+    count: usize,
+    required_players: usize,
+) -> String {
+    if withdrawn_names.is_empty() {
+        language.text(Text::CountdownAborted {
+            game_title,
+            count,
+            required_players,
+        })
+    } else {
+        language.text(Text::CountdownWithdrawn {
+            names: &withdrawn_names.join(", "),
+            game_title,
+            count,
+            required_players,
+        })
+    }
+}
+
+async fn display_name(ctx: &Context, guild_id: Option<GuildId>, user_id: UserId) -> String {
+    if let Some(guild_id) = guild_id
+        && let Ok(member) = guild_id.member(ctx, user_id).await
+    {
+        return member.display_name().to_string();
+    }
+    match user_id.to_user(ctx).await {
+        Ok(user) => user.display_name().to_string(),
+        Err(_) => user_id.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn users(ids: &[u64]) -> HashSet<UserId> {
+        ids.iter().copied().map(UserId::new).collect()
+    }
+
+    #[test]
+    fn withdrawn_users_are_sorted_and_newcomers_are_ignored() {
+        assert_eq!(
+            withdrawn_users(&users(&[3, 1, 2]), &users(&[2, 4])),
+            [UserId::new(1), UserId::new(3)]
+        );
+        assert!(withdrawn_users(&users(&[1, 2]), &users(&[1, 2, 3])).is_empty());
+    }
+
+    #[test]
+    fn withdrawal_notice_names_who_left() {
+        assert_eq!(
+            render_withdrawal_notice(
+                Language::Ja,
+                "Apex",
+                &["Alice".to_string(), "Bob".to_string()],
+                1,
+                3
+            ),
+            "Alice, Bob が参加を取り消したため Apex の開始を中止しました (現在 1/3人)"
+        );
+        // 名前が分からない場合は, 減ったことだけを知らせます
+        assert_eq!(
+            render_withdrawal_notice(Language::Ja, "Apex", &[], 2, 3),
+            "参加者が減ったため Apex の開始を中止しました (現在 2/3人)"
+        );
+    }
+
+    #[test]
+    fn snoozes_stop_at_the_limit() {
+        assert_eq!(next_snooze(0, 5, 15), Some(5));
+        assert_eq!(next_snooze(10, 5, 15), Some(15));
+        assert_eq!(next_snooze(15, 5, 15), None);
+        assert_eq!(next_snooze(u64::MAX, 5, u64::MAX), None);
+    }
+}
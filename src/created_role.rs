@@ -0,0 +1,93 @@
+use poise::serenity_prelude::*;
+
+use crate::Error;
+
+/// Discord のロール名の上限の文字数
+pub const MAX_ROLE_NAME_CHARS: usize = 100;
+/// 1 つのサーバーに作成できるロールの上限
+pub const MAX_GUILD_ROLES: usize = 250;
+/// `/settings role_prefix` の上限の文字数
+pub const MAX_ROLE_PREFIX_CHARS: usize = 10;
+
+/// 募集のために作成するロールの名前
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleName {
+    pub name: String,
+    /// 上限を超えたため省略したかどうか
+    pub truncated: bool,
+}
+
+/// サーバー設定の接頭辞とゲーム名から, 作成するロールの名前を決めます
+///
+/// 前後の空白を取り除き, 上限を超える場合は末尾を「…」にして省略します。
+pub fn role_name(prefix: Option<&str>, game_title: &str) -> RoleName {
+    let name = format!("{}{}", prefix.unwrap_or_default(), game_title.trim());
+    let name = name.trim();
+    if name.chars().count() <= MAX_ROLE_NAME_CHARS {
+        return RoleName {
+            name: name.to_string(),
+            truncated: false,
+        };
+    }
+    let truncated: String = name.chars().take(MAX_ROLE_NAME_CHARS - 1).collect();
+    RoleName {
+        name: format!("{}…", truncated.trim_end()),
+        truncated: true,
+    }
+}
+
+/// 同じ名前のロールがあれば, 作成せずに使います (連携アプリのロールは使いません)
+pub fn find_reusable<'a>(roles: impl IntoIterator<Item = &'a Role>, name: &str) -> Option<RoleId> {
+    roles
+        .into_iter()
+        .find(|role| !role.managed && role.name == name)
+        .map(|role| role.id)
+}
+
+/// サーバーのロールを, キャッシュにあればキャッシュから, なければ API から取得します
+pub async fn guild_roles(ctx: &Context, guild_id: GuildId) -> Result<Vec<Role>, Error> {
+    let cached = ctx
+        .cache
+        .guild(guild_id)
+        .map(|guild| guild.roles.values().cloned().collect());
+    match cached {
+        Some(roles) => Ok(roles),
+        None => Ok(guild_id.roles(ctx).await?.into_values().collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn role(id: u64, name: &str, managed: bool) -> Role {
+        let mut role = Role::default();
+        role.id = RoleId::new(id);
+        role.name = name.to_string();
+        role.managed = managed;
+        role
+    }
+
+    #[test]
+    fn long_role_names_are_truncated() {
+        assert_eq!(
+            role_name(Some("募集: "), " Apex "),
+            RoleName {
+                name: "募集: Apex".to_string(),
+                truncated: false,
+            }
+        );
+        let name = role_name(None, &"A".repeat(MAX_ROLE_NAME_CHARS + 1));
+        assert!(name.truncated);
+        assert_eq!(name.name.chars().count(), MAX_ROLE_NAME_CHARS);
+        assert!(name.name.ends_with('…'));
+    }
+
+    #[test]
+    fn roles_of_integrations_are_not_reused() {
+        let roles = [role(1, "Apex", true), role(2, "Apex", false)];
+        assert_eq!(find_reusable(&roles, "Apex"), Some(RoleId::new(2)));
+        assert_eq!(find_reusable(&roles[..1], "Apex"), None);
+        assert_eq!(find_reusable(&roles, "Valorant"), None);
+    }
+}
@@ -0,0 +1,456 @@
+use std::fmt::Write;
+
+use poise::{CreateReply, serenity_prelude::*};
+
+use crate::config_storage;
+use crate::recruit_message::{extract_toml_block, parse_emoji, sanitize_text};
+use crate::{
+    Data, Error, allowed_bot_participants, bot_permissions, fetch_reaction_user_list,
+    is_counted_reactor, voice_channel_participants,
+};
+use crate::{participant_count, sweeper};
+
+const MESSAGE_LIMIT: usize = 2000;
+
+/// 開始通知などに必要な Bot の権限
+const REQUIRED_PERMISSIONS: Permissions = Permissions::VIEW_CHANNEL
+    .union(Permissions::SEND_MESSAGES)
+    .union(Permissions::READ_MESSAGE_HISTORY)
+    .union(Permissions::ADD_REACTIONS)
+    .union(Permissions::MANAGE_MESSAGES);
+
+/// 募集メッセージの内部状態を表示します (管理者向け)
+#[poise::command(
+    slash_command,
+    guild_only,
+    ephemeral,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn debug_recruit(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集メッセージのリンク"] message: Message,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.defer_ephemeral().await?;
+
+    let report = render_report(ctx.serenity_context(), ctx.data(), guild_id, &message).await;
+    let reply = if report.len() + "```\n\n```".len() <= MESSAGE_LIMIT {
+        CreateReply::default().content(format!("```\n{report}\n```"))
+    } else {
+        CreateReply::default()
+            .content(format!(
+                "```\n{}\n```\n全文は添付ファイルを参照してください",
+                truncate(&report, MESSAGE_LIMIT - 100)
+            ))
+            .attachment(CreateAttachment::bytes(
+                report.into_bytes(),
+                "debug_recruit.txt",
+            ))
+    };
+    ctx.send(reply).await?;
+    Ok(())
+}
+
+async fn render_report(ctx: &Context, data: &Data, guild_id: GuildId, message: &Message) -> String {
+    let mut report = String::new();
+    let bot_id = ctx.cache.current_user().id;
+
+    let _ = writeln!(report, "[message]");
+    let _ = writeln!(
+        report,
+        "id: {} (channel {})",
+        message.id, message.channel_id
+    );
+    let _ = writeln!(
+        report,
+        "author: {} ({}){}",
+        message.author.name,
+        message.author.id,
+        if message.author.id == bot_id {
+            ""
+        } else {
+            " - この Bot の投稿ではありません"
+        }
+    );
+    let source = match ctx.cache.message(message.channel_id, message.id) {
+        Some(_) => "cache",
+        None => "REST",
+    };
+    let _ = writeln!(report, "source: {source}");
+
+    let _ = writeln!(report, "\n[config]");
+    let stored = config_storage::is_stored(data, message.id);
+    let _ = writeln!(
+        report,
+        "storage: {}",
+        if stored { "database" } else { "message" }
+    );
+    let recruit_message = match config_storage::load_with_warnings(data, message) {
+        Ok((recruit_message, warnings)) => {
+            let block = if stored {
+                toml::to_string(&recruit_message).unwrap_or_default()
+            } else {
+                extract_toml_block(&message.content)
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let _ = writeln!(report, "{}", block.trim_end());
+            for warning in warnings {
+                let _ = writeln!(report, "warning: {warning}");
+            }
+            Some(recruit_message)
+        }
+        Err(err) => {
+            let _ = writeln!(report, "parse error: {err}");
+            None
+        }
+    };
+
+    let deferred: Vec<String> = data
+        .storage
+        .deferred_participants(message.id)
+        .iter()
+        .map(|user_id| user_id.to_string())
+        .collect();
+    let _ = writeln!(report, "deferred (priority): {}", format_list(&deferred));
+
+    let _ = writeln!(report, "\n[participants] (REST)");
+    let emojis = &data.config.emojis;
+    let builtin = recruit_message.as_ref().map_or(
+        [
+            emojis.participation.as_str(),
+            emojis.silent_participation.as_str(),
+        ],
+        |recruit_message| {
+            [
+                recruit_message.config.participation_emoji(emojis),
+                recruit_message.config.silent_participation_emoji(emojis),
+            ]
+        },
+    );
+    let mut reactions: Vec<(String, ReactionType)> = builtin
+        .iter()
+        .chain(&[emojis.start.as_str()])
+        .map(|emoji| (emoji.to_string(), parse_emoji(emoji)))
+        .collect();
+    if let Some(recruit_message) = &recruit_message {
+        reactions.extend(recruit_message.config.extra_reactions.iter().map(|extra| {
+            let counts = if extra.counts { "" } else { ", not counted" };
+            (
+                format!("{} {}{counts}", extra.emoji, extra.label),
+                extra.reaction_type(),
+            )
+        }));
+    }
+    let allowed_bot_participants = allowed_bot_participants(data, Some(guild_id));
+    for (emoji, reaction_type) in reactions {
+        let is_counted = |user: &User| is_counted_reactor(user, &allowed_bot_participants);
+        match fetch_reaction_user_list(ctx, message, reaction_type, is_counted).await {
+            Ok(users) => {
+                let users: Vec<String> = users
+                    .iter()
+                    .map(|user| format!("{} ({})", user.name, user.id))
+                    .collect();
+                let _ = writeln!(report, "{emoji} ({}): {}", users.len(), format_list(&users));
+            }
+            Err(err) => {
+                let _ = writeln!(report, "{emoji}: 取得に失敗しました: {err}");
+            }
+        }
+    }
+    if let Some(recruit_message) = &recruit_message
+        && let Some(voice_channel_id) = recruit_message.config.count_voice_channel
+    {
+        let users: Vec<String> = voice_channel_participants(
+            ctx,
+            guild_id,
+            voice_channel_id,
+            recruit_message.config.require_undeafened,
+        )
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+        let _ = writeln!(
+            report,
+            "VC {voice_channel_id} (cache) ({}): {}",
+            users.len(),
+            format_list(&users)
+        );
+    }
+
+    let _ = writeln!(report, "\n[in-memory]");
+    match data.pending_starts.lock().unwrap().get(&message.id) {
+        Some(pending) => {
+            let _ = writeln!(
+                report,
+                "countdown: pending (snapshot {}, notice {}, start <t:{}:R>, snoozed {} min)",
+                pending.snapshot.len(),
+                pending.notice_message_id,
+                pending.start_at.unix_timestamp(),
+                pending.snoozed_minutes
+            );
+        }
+        None => {
+            let _ = writeln!(report, "countdown: none");
+        }
+    }
+    let voice_channels: Vec<String> = data
+        .voice_recruits
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, recruits)| recruits.contains(&(message.channel_id, message.id)))
+        .map(|(channel_id, _)| channel_id.to_string())
+        .collect();
+    let _ = writeln!(
+        report,
+        "voice registration: {}",
+        format_list(&voice_channels)
+    );
+    let lock_state = match data.message_locks.lock().unwrap().get(&message.id) {
+        Some(lock) if lock.try_lock().is_err() => "held",
+        Some(_) => "idle",
+        None => "none",
+    };
+    let _ = writeln!(report, "message lock: {lock_state}");
+    let _ = writeln!(
+        report,
+        "cached participant count: {}",
+        participant_count::cached_count(data, message.id)
+            .map_or_else(|| "-".to_string(), |count| count.to_string())
+    );
+    let _ = writeln!(
+        report,
+        "start marker: {}",
+        data.storage.start_marker(message.id).map_or_else(
+            || "-".to_string(),
+            |marker| marker.start_sent_at.to_string()
+        )
+    );
+    let _ = writeln!(
+        report,
+        "start retry: {}",
+        data.storage.start_retry(message.id).map_or_else(
+            || "-".to_string(),
+            |retry| format!("{} failures since {}", retry.failures, retry.decided_at)
+        )
+    );
+    let _ = writeln!(
+        report,
+        "lobbies: {}",
+        data.storage.lobby_placement(message.id).map_or_else(
+            || "-".to_string(),
+            |placement| format!(
+                "{} started, {} placed",
+                placement.lobbies,
+                placement.placed.len()
+            )
+        )
+    );
+    let _ = writeln!(
+        report,
+        "close reason: {}",
+        data.storage.closure(guild_id, message.id).map_or_else(
+            || "-".to_string(),
+            |closure| format!("{} at {}", closure.reason, closure.closed_at)
+        )
+    );
+    let _ = writeln!(
+        report,
+        "voice hold: {}",
+        data.storage.voice_hold(message.id).map_or_else(
+            || "-".to_string(),
+            |hold| format!("{} until {}", hold.voice_channel_id, hold.deadline)
+        )
+    );
+    let _ = writeln!(report, "total: {}", sweeper::state_counts(data));
+    let _ = writeln!(report, "participant counting: {}", data.count_metrics);
+    let _ = writeln!(report, "gateway resumes: {}", data.resume_metrics);
+    let _ = writeln!(report, "background REST: {}", data.rest_budget);
+    let _ = writeln!(report, "running jobs: {}", data.tasks);
+
+    let _ = writeln!(report, "\n[settings]");
+    let settings = data.storage.guild_settings(guild_id);
+    let _ = writeln!(
+        report,
+        "webhook_url: {}",
+        if settings.webhook_url.is_some() {
+            "set"
+        } else {
+            "-"
+        }
+    );
+    let _ = writeln!(
+        report,
+        "organizer_role: {}{}",
+        settings
+            .organizer_role
+            .map_or_else(|| "-".to_string(), |role_id| role_id.to_string()),
+        if settings.organizer_role_for_mentions_only {
+            " (mentions only)"
+        } else {
+            ""
+        }
+    );
+
+    let _ = writeln!(
+        report,
+        "rejection_notice_dm: {}",
+        settings.rejection_notice_dm
+    );
+    let _ = writeln!(
+        report,
+        "role_ping_cooldown_minutes: {}",
+        settings
+            .role_ping_cooldown_minutes
+            .map_or_else(|| "-".to_string(), |minutes| minutes.to_string())
+    );
+    let _ = writeln!(
+        report,
+        "cleanup_after_start_minutes: {}",
+        settings
+            .cleanup_after_start_minutes
+            .map_or_else(|| "-".to_string(), |minutes| minutes.to_string())
+    );
+    let _ = writeln!(report, "language: {}", settings.language.code());
+    let _ = writeln!(report, "config_storage: {}", settings.config_storage.code());
+    let _ = writeln!(report, "deletion_log: {}", settings.deletion_log.code());
+    let _ = writeln!(report, "cleanup_mode: {}", settings.cleanup_mode.code());
+    let _ = writeln!(
+        report,
+        "dedupe_mentions: {}",
+        settings.dedupe_mentions.unwrap_or(true)
+    );
+    let _ = writeln!(report, "ping_on_fail: {}", settings.ping_on_fail);
+    let _ = writeln!(
+        report,
+        "reaction_emoji: {}",
+        settings.reaction_emoji.as_ref().map_or_else(
+            || "-".to_string(),
+            |pair| format!("{} {}", pair.participation, pair.silent_participation)
+        )
+    );
+    let _ = writeln!(
+        report,
+        "member_work_cap: {}",
+        settings
+            .member_work_cap
+            .map_or_else(|| "-".to_string(), |cap| cap.to_string())
+    );
+    let _ = writeln!(
+        report,
+        "timezone: {}",
+        settings.timezone.as_deref().unwrap_or("-")
+    );
+    let _ = writeln!(
+        report,
+        "remind_before_minutes: {}",
+        settings
+            .remind_before_minutes
+            .map_or_else(|| "-".to_string(), |minutes| minutes.to_string())
+    );
+    let _ = writeln!(report, "skip_spammers: {}", settings.skip_spammers);
+    let _ = writeln!(
+        report,
+        "role_name_prefix: {}",
+        settings.role_name_prefix.as_deref().unwrap_or("-")
+    );
+    for (key, days) in [
+        ("min_account_age_days", settings.min_account_age_days),
+        ("min_membership_age_days", settings.min_membership_age_days),
+    ] {
+        let _ = writeln!(
+            report,
+            "{key}: {}",
+            days.map_or_else(|| "-".to_string(), |days| days.to_string())
+        );
+    }
+    let _ = writeln!(
+        report,
+        "quiet_hours: {}",
+        settings
+            .quiet_hours
+            .map_or_else(|| "-".to_string(), |quiet_hours| quiet_hours.to_string())
+    );
+    let _ = writeln!(
+        report,
+        "audit_channel: {}",
+        settings
+            .audit_channel
+            .map_or_else(|| "-".to_string(), |channel_id| channel_id.to_string())
+    );
+    let _ = writeln!(
+        report,
+        "summary_channel: {}",
+        settings
+            .summary_channel
+            .map_or_else(|| "-".to_string(), |channel_id| channel_id.to_string())
+    );
+    let _ = writeln!(
+        report,
+        "mirror_channels: {}",
+        if settings.mirror_channels.is_empty() {
+            "-".to_string()
+        } else {
+            settings
+                .mirror_channels
+                .iter()
+                .map(|(source, target)| format!("{source} -> {target}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+    );
+    let _ = writeln!(
+        report,
+        "error_channel: {}",
+        settings
+            .error_channel
+            .map_or_else(|| "-".to_string(), |channel_id| channel_id.to_string())
+    );
+    let _ = writeln!(report, "error_ping_user: {}", settings.error_ping_user);
+    let allowed_bots: Vec<String> = settings
+        .allowed_bot_participants
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let _ = writeln!(
+        report,
+        "allowed_bot_participants: {}",
+        format_list(&allowed_bots)
+    );
+
+    let _ = writeln!(report, "\n[permissions]");
+    match bot_permissions(ctx, guild_id, message.channel_id) {
+        Some(permissions) => {
+            let _ = writeln!(report, "bot: {permissions}");
+            let missing = REQUIRED_PERMISSIONS - permissions;
+            if !missing.is_empty() && !permissions.administrator() {
+                let _ = writeln!(report, "missing: {missing}");
+            }
+        }
+        None => {
+            let _ = writeln!(report, "bot: キャッシュにないため計算できません");
+        }
+    }
+
+    sanitize_text(report.trim_end())
+}
+
+fn format_list(items: &[String]) -> String {
+    if items.is_empty() {
+        "-".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+fn truncate(text: &str, max_len: usize) -> &str {
+    if text.len() <= max_len {
+        return text;
+    }
+    let mut end = max_len;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
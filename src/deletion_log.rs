@@ -0,0 +1,335 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::notification;
+use crate::{Data, Error, send_ephemeral};
+
+const DEFAULT_LOG_PATH: &str = "deletion_log.jsonl";
+/// ファイルをローテーションする大きさ
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+/// 残しておくローテーション済みのファイルの数 (`.1` が最も新しい)
+const ROTATED_FILES: usize = 3;
+/// 管理者向けのチャンネルと `/deletion_log recent` に表示する本文の最大の文字数
+const MAX_PREVIEW_CHARS: usize = 80;
+const DEFAULT_RECENT_ENTRIES: usize = 5;
+
+/// ファイルへの書き込みとローテーションを同時に行わないためのロック
+static LOG_FILE: Mutex<()> = Mutex::new(());
+
+/// Bot が削除したメッセージの記録先
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, poise::ChoiceParameter,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionLogDestination {
+    #[default]
+    #[name = "記録しない"]
+    Off,
+    /// サーバー設定 `audit_channel` のチャンネル
+    #[name = "管理者向けチャンネル"]
+    AuditChannel,
+    /// `JOINBELL_DELETION_LOG_PATH` の JSON Lines のファイル
+    #[name = "ファイル"]
+    File,
+}
+
+impl DeletionLogDestination {
+    pub fn code(self) -> &'static str {
+        match self {
+            DeletionLogDestination::Off => "off",
+            DeletionLogDestination::AuditChannel => "audit_channel",
+            DeletionLogDestination::File => "file",
+        }
+    }
+}
+
+/// Bot がメッセージを削除する理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionReason {
+    /// `delete_after_minutes` が過ぎた通知
+    Notice,
+    /// `cleanup_after_start_minutes` が過ぎた, 開始した募集メッセージ
+    RecruitCleanup,
+}
+
+impl DeletionReason {
+    fn label(self) -> &'static str {
+        match self {
+            DeletionReason::Notice => "通知の自動削除",
+            DeletionReason::RecruitCleanup => "開始した募集の削除",
+        }
+    }
+}
+
+/// 削除したメッセージの記録
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeletionLogEntry {
+    pub guild_id: Option<GuildId>,
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    /// 送信したときの本文 (記録し始める前に予約した削除にはありません)
+    pub content: Option<String>,
+    pub reason: DeletionReason,
+    pub deleted_at: Timestamp,
+}
+
+impl DeletionLogEntry {
+    fn summary(&self) -> String {
+        let content = self
+            .content
+            .as_deref()
+            .map_or_else(|| "(本文の記録なし)".to_string(), preview);
+        format!(
+            "<t:{}:f> {} `{}` ({}): {content}",
+            self.deleted_at.unix_timestamp(),
+            self.channel_id.mention(),
+            self.message_id,
+            self.reason.label()
+        )
+    }
+}
+
+fn preview(content: &str) -> String {
+    let content = content.replace('\n', " ");
+    if content.chars().count() <= MAX_PREVIEW_CHARS {
+        return content;
+    }
+    let truncated: String = content.chars().take(MAX_PREVIEW_CHARS).collect();
+    format!("{truncated}…")
+}
+
+fn log_path() -> PathBuf {
+    std::env::var("JOINBELL_DELETION_LOG_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_LOG_PATH))
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+/// `path` が `MAX_LOG_BYTES` 以上なら, 古いファイルを 1 つずつずらしてから新しいファイルにします
+fn rotate_if_full(path: &Path) -> std::io::Result<()> {
+    match fs::metadata(path) {
+        Ok(metadata) if MAX_LOG_BYTES <= metadata.len() => {}
+        Ok(_) => return Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    }
+    for index in (1..ROTATED_FILES).rev() {
+        let from = rotated_path(path, index);
+        if from.exists() {
+            fs::rename(&from, rotated_path(path, index + 1))?;
+        }
+    }
+    fs::rename(path, rotated_path(path, 1))
+}
+
+fn append_to_file(path: &Path, entry: &DeletionLogEntry) -> Result<(), Error> {
+    let _lock = LOG_FILE.lock().unwrap();
+    rotate_if_full(path)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// 新しい順に並んだ, `guild_id` の記録の最新 `limit` 件
+fn read_recent(path: &Path, guild_id: GuildId, limit: usize) -> Vec<DeletionLogEntry> {
+    let _lock = LOG_FILE.lock().unwrap();
+    let mut entries = Vec::new();
+    let paths = std::iter::once(path.to_path_buf())
+        .chain((1..=ROTATED_FILES).map(|index| rotated_path(path, index)));
+    for path in paths {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        entries.extend(
+            content
+                .lines()
+                .rev()
+                .filter_map(|line| serde_json::from_str::<DeletionLogEntry>(line).ok())
+                .filter(|entry| entry.guild_id == Some(guild_id)),
+        );
+        if limit <= entries.len() {
+            break;
+        }
+    }
+    entries.truncate(limit);
+    entries
+}
+
+/// 削除する前に, サーバー設定 `deletion_log` の記録先に記録します
+///
+/// 記録できなかった場合もログを出力して削除を続けます。
+pub async fn record(
+    ctx: &Context,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    content: Option<String>,
+    reason: DeletionReason,
+) {
+    let guild_id = match channel_id.to_channel(ctx).await {
+        Ok(channel) => channel.guild().map(|channel| channel.guild_id),
+        Err(err) => {
+            eprintln!("Failed to fetch channel of deleted message: {err}");
+            None
+        }
+    };
+    let Some(guild_id) = guild_id else {
+        return;
+    };
+    let settings = data.storage.guild_settings(guild_id);
+    let entry = DeletionLogEntry {
+        guild_id: Some(guild_id),
+        channel_id,
+        message_id,
+        content,
+        reason,
+        deleted_at: Timestamp::now(),
+    };
+    match settings.deletion_log {
+        DeletionLogDestination::Off => {}
+        DeletionLogDestination::AuditChannel => {
+            let Some(audit_channel) = settings.audit_channel else {
+                return;
+            };
+            let message = notification::plain_message(format!("🗑️ {}", entry.summary()));
+            if let Err(err) = audit_channel.send_message(ctx, message).await {
+                eprintln!("Failed to send deletion log: {err}");
+            }
+        }
+        DeletionLogDestination::File => {
+            if let Err(err) = append_to_file(&log_path(), &entry) {
+                eprintln!("Failed to write deletion log: {err}");
+            }
+        }
+    }
+}
+
+/// Bot が削除したメッセージの記録を表示します
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "MANAGE_GUILD",
+    subcommands("deletion_log_recent")
+)]
+pub async fn deletion_log(_ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Bot が最近削除したメッセージを表示します
+#[poise::command(slash_command, guild_only, rename = "recent")]
+async fn deletion_log_recent(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "表示する件数 (既定: 5)"]
+    #[min = 1]
+    #[max = 10]
+    count: Option<usize>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let destination = ctx.data().storage.guild_settings(guild_id).deletion_log;
+    let entries = read_recent(
+        &log_path(),
+        guild_id,
+        count.unwrap_or(DEFAULT_RECENT_ENTRIES),
+    );
+    if entries.is_empty() {
+        let content = match destination {
+            DeletionLogDestination::Off => {
+                "削除したメッセージを記録していません (`/channel_settings deletion_log` で設定できます)"
+            }
+            DeletionLogDestination::AuditChannel => {
+                "削除したメッセージの記録は管理者向けのチャンネルに送っています"
+            }
+            DeletionLogDestination::File => "削除したメッセージの記録はありません",
+        };
+        send_ephemeral(ctx, content).await?;
+        return Ok(());
+    }
+    let lines: Vec<String> = entries.iter().map(DeletionLogEntry::summary).collect();
+    send_ephemeral(ctx, &lines.join("\n")).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD_ID: GuildId = GuildId::new(1);
+
+    fn entry(guild_id: GuildId, message_id: u64) -> DeletionLogEntry {
+        DeletionLogEntry {
+            guild_id: Some(guild_id),
+            channel_id: ChannelId::new(2),
+            message_id: MessageId::new(message_id),
+            content: Some("募集\nApex".to_string()),
+            reason: DeletionReason::Notice,
+            deleted_at: Timestamp::from_unix_timestamp(1_700_000_000).unwrap(),
+        }
+    }
+
+    /// テストごとの一時ファイル
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("joinbell-{}-{name}", std::process::id()));
+        for index in 0..=ROTATED_FILES {
+            let _ = fs::remove_file(rotated_path(&path, index));
+        }
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn previews_fit_on_one_line() {
+        assert_eq!(preview("募集\nApex"), "募集 Apex");
+        let long = "A".repeat(MAX_PREVIEW_CHARS + 1);
+        assert_eq!(
+            preview(&long),
+            format!("{}…", "A".repeat(MAX_PREVIEW_CHARS))
+        );
+        assert!(
+            entry(GUILD_ID, 3)
+                .summary()
+                .ends_with("(通知の自動削除): 募集 Apex")
+        );
+    }
+
+    #[test]
+    fn recent_entries_are_read_back_newest_first() {
+        let path = temp_path("deletion_log.jsonl");
+        for message_id in [3, 4] {
+            append_to_file(&path, &entry(GUILD_ID, message_id)).unwrap();
+        }
+        append_to_file(&path, &entry(GuildId::new(9), 5)).unwrap();
+        let recent = read_recent(&path, GUILD_ID, DEFAULT_RECENT_ENTRIES);
+        assert_eq!(recent, [entry(GUILD_ID, 4), entry(GUILD_ID, 3)]);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn full_files_are_rotated() {
+        let path = temp_path("rotate.jsonl");
+        assert_eq!(
+            rotated_path(&path, 1).file_name().unwrap(),
+            format!("joinbell-{}-rotate.jsonl.1", std::process::id()).as_str()
+        );
+        // 上限に満たないファイルはそのまま追記します
+        fs::write(&path, "old\n").unwrap();
+        rotate_if_full(&path).unwrap();
+        assert!(!rotated_path(&path, 1).exists());
+
+        fs::write(&path, vec![b'a'; MAX_LOG_BYTES as usize]).unwrap();
+        rotate_if_full(&path).unwrap();
+        assert!(!path.exists());
+        assert!(rotated_path(&path, 1).exists());
+        let _ = fs::remove_file(rotated_path(&path, 1));
+    }
+}
@@ -0,0 +1,119 @@
+use poise::serenity_prelude::*;
+
+use crate::config_storage;
+use crate::recruit_message::{MAX_DESCRIPTION_CHARS, sanitize_description};
+use crate::{
+    Data, Error, can_manage_recruit, parse_bot_recruit_message, recruit_edit, send_ephemeral,
+};
+
+/// Discord のメッセージの本文の上限
+const MAX_MESSAGE_CHARS: usize = 2000;
+
+/// 入力した説明をメンションにならないように直します (空の説明は `None` にします)
+pub fn normalize(description: Option<String>) -> Result<Option<String>, &'static str> {
+    let description = description
+        .map(|description| sanitize_description(&description))
+        .filter(|description| !description.is_empty());
+    if description
+        .as_ref()
+        .is_some_and(|description| MAX_DESCRIPTION_CHARS < description.chars().count())
+    {
+        return Err("説明は 500 文字以内で入力してください。");
+    }
+    Ok(description)
+}
+
+/// 募集に説明を付けます (省略すると説明を消します)
+#[poise::command(slash_command, guild_only, rename = "edit")]
+pub async fn describe_recruit(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集メッセージのリンク"] message: Message,
+    #[description = "募集の説明 (ランク帯など, 500 文字まで)"]
+    #[max_length = 500]
+    description: Option<String>,
+) -> Result<(), Error> {
+    let _guard = ctx.data().lock_message(message.id).await;
+    let mut recruit_message =
+        match parse_bot_recruit_message(ctx.serenity_context(), ctx.data(), &message) {
+            Some(recruit_message) => recruit_message,
+            None => {
+                send_ephemeral(ctx, "指定されたメッセージは募集メッセージではありません。").await?;
+                return Ok(());
+            }
+        };
+
+    if !can_manage_recruit(ctx, &recruit_message.config) {
+        send_ephemeral(
+            ctx,
+            "主催者またはメッセージの管理権限を持つメンバーのみ説明を変更できます。",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let description = match normalize(description) {
+        Ok(description) => description,
+        Err(err) => {
+            send_ephemeral(ctx, err).await?;
+            return Ok(());
+        }
+    };
+    let cleared = description.is_none();
+    recruit_message.config.description = description;
+    if MAX_MESSAGE_CHARS
+        < config_storage::render(ctx.data(), message.id, &recruit_message)
+            .chars()
+            .count()
+    {
+        send_ephemeral(
+            ctx,
+            "募集メッセージが長くなりすぎるため, 説明を短くしてください。",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let description = recruit_message.config.description;
+    recruit_edit::edit_recruit_message(
+        ctx.serenity_context(),
+        ctx.data(),
+        message.channel_id,
+        message.id,
+        |recruit_message| {
+            recruit_message.config.description = description.clone();
+            Some(())
+        },
+    )
+    .await?;
+
+    let reply = if cleared {
+        "説明を消しました"
+    } else {
+        "説明を変更しました"
+    };
+    send_ephemeral(ctx, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptions_are_sanitized_and_limited() {
+        assert_eq!(normalize(None), Ok(None));
+        assert_eq!(normalize(Some("  \n ".to_string())), Ok(None));
+        let normalized = normalize(Some("ランク回します\n@everyone `ゴールド以上`".to_string()))
+            .unwrap()
+            .unwrap();
+        assert!(!normalized.contains("@everyone"), "{normalized}");
+        assert!(!normalized.contains('`'), "{normalized}");
+        assert!(normalized.starts_with("ランク回します\n"), "{normalized}");
+        assert_eq!(
+            normalize(Some("あ".repeat(MAX_DESCRIPTION_CHARS)))
+                .map(|d| d.map(|d| d.chars().count())),
+            Ok(Some(MAX_DESCRIPTION_CHARS))
+        );
+        assert!(normalize(Some("あ".repeat(MAX_DESCRIPTION_CHARS + 1))).is_err());
+    }
+}
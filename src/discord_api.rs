@@ -0,0 +1,293 @@
+use poise::serenity_prelude::{self as serenity, *};
+
+use crate::member_work::MEMBERS_PAGE_SIZE;
+use crate::thread;
+
+/// 募集のリアクションの処理と開始通知で使う Discord の API
+///
+/// 本番では `Context` を使います。Discord に接続せずに処理を確かめられるよう,
+/// リアクションの追加から開始通知, リアクションの付け直しまでの処理はこのトレイトを経由して呼び出します。
+pub trait DiscordApi: Sync {
+    /// Bot のユーザー ID
+    fn current_user_id(&self) -> UserId;
+
+    /// Gateway から受け取ったサーバーやメンバーのキャッシュ (接続していない場合は `None`)
+    fn cache(&self) -> Option<&Cache>;
+
+    /// serenity の `Context` (Gateway に接続していない場合は `None`)
+    ///
+    /// 募集のリアクション以外のイベントの処理は `Context` を使うため, `None` の場合は処理しません。
+    fn serenity_context(&self) -> Option<&Context>;
+
+    /// `after` より後の, `reaction_type` でリアクションしたユーザーを最大 100 人取得します
+    fn reaction_users(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        reaction_type: ReactionType,
+        after: Option<UserId>,
+    ) -> impl Future<Output = serenity::Result<Vec<User>>> + Send;
+
+    fn send_message(
+        &self,
+        channel_id: ChannelId,
+        message: CreateMessage,
+    ) -> impl Future<Output = serenity::Result<Message>> + Send;
+
+    /// メッセージを取得します (キャッシュにあればキャッシュから)
+    fn message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> impl Future<Output = serenity::Result<Message>> + Send;
+
+    /// メッセージを書き換えます (アーカイブされたスレッドで解除できなかった場合は `None`)
+    fn edit_message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        message: EditMessage,
+    ) -> impl Future<Output = serenity::Result<Option<Message>>> + Send;
+
+    fn create_reaction(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        reaction_type: ReactionType,
+    ) -> impl Future<Output = serenity::Result<()>> + Send;
+
+    /// `user_id` のリアクションを削除します (`None` の場合は Bot のリアクション)
+    fn delete_reaction(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        user_id: Option<UserId>,
+        reaction_type: ReactionType,
+    ) -> impl Future<Output = serenity::Result<()>> + Send;
+
+    /// メッセージのリアクションをすべて削除します
+    fn delete_reactions(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> impl Future<Output = serenity::Result<()>> + Send;
+
+    fn delete_message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> impl Future<Output = serenity::Result<()>> + Send;
+
+    fn direct_message(
+        &self,
+        user_id: UserId,
+        message: CreateMessage,
+    ) -> impl Future<Output = serenity::Result<Message>> + Send;
+
+    /// スレッドのアーカイブを解除します
+    fn unarchive_thread(
+        &self,
+        channel_id: ChannelId,
+    ) -> impl Future<Output = serenity::Result<()>> + Send;
+
+    fn channel(
+        &self,
+        channel_id: ChannelId,
+    ) -> impl Future<Output = serenity::Result<Channel>> + Send;
+
+    fn member(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> impl Future<Output = serenity::Result<Member>> + Send;
+
+    /// `after` より後のサーバーのメンバーを, ユーザー ID 順に最大 1000 人取得します
+    fn guild_members(
+        &self,
+        guild_id: GuildId,
+        after: Option<UserId>,
+    ) -> impl Future<Output = serenity::Result<Vec<Member>>> + Send;
+
+    fn add_role(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+    ) -> impl Future<Output = serenity::Result<()>> + Send;
+
+    fn create_role(
+        &self,
+        guild_id: GuildId,
+        role: EditRole<'_>,
+    ) -> impl Future<Output = serenity::Result<Role>> + Send;
+
+    fn delete_role(
+        &self,
+        guild_id: GuildId,
+        role_id: RoleId,
+    ) -> impl Future<Output = serenity::Result<()>> + Send;
+}
+
+/// リアクションしたユーザーのリアクションを削除します
+pub async fn delete_user_reaction(
+    api: &impl DiscordApi,
+    reaction: &Reaction,
+) -> serenity::Result<()> {
+    api.delete_reaction(
+        reaction.channel_id,
+        reaction.message_id,
+        reaction.user_id,
+        reaction.emoji.clone(),
+    )
+    .await
+}
+
+impl DiscordApi for Context {
+    fn current_user_id(&self) -> UserId {
+        self.cache.current_user().id
+    }
+
+    fn cache(&self) -> Option<&Cache> {
+        Some(&self.cache)
+    }
+
+    fn serenity_context(&self) -> Option<&Context> {
+        Some(self)
+    }
+
+    async fn reaction_users(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        reaction_type: ReactionType,
+        after: Option<UserId>,
+    ) -> serenity::Result<Vec<User>> {
+        channel_id
+            .reaction_users(self, message_id, reaction_type, Some(100), after)
+            .await
+    }
+
+    async fn send_message(
+        &self,
+        channel_id: ChannelId,
+        message: CreateMessage,
+    ) -> serenity::Result<Message> {
+        channel_id.send_message(self, message).await
+    }
+
+    async fn message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> serenity::Result<Message> {
+        let message = channel_id.message(self, message_id).await?;
+        // 起動直後などに本文のない部分的なメッセージがキャッシュにある場合は, REST API から取得し直します
+        if message.content.is_empty() {
+            return self.http.get_message(channel_id, message_id).await;
+        }
+        Ok(message)
+    }
+
+    async fn edit_message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        message: EditMessage,
+    ) -> serenity::Result<Option<Message>> {
+        thread::retry_if_archived(self, channel_id, || {
+            channel_id.edit_message(self, message_id, message.clone())
+        })
+        .await
+    }
+
+    async fn create_reaction(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        reaction_type: ReactionType,
+    ) -> serenity::Result<()> {
+        channel_id
+            .create_reaction(self, message_id, reaction_type)
+            .await
+    }
+
+    async fn delete_reaction(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        user_id: Option<UserId>,
+        reaction_type: ReactionType,
+    ) -> serenity::Result<()> {
+        channel_id
+            .delete_reaction(self, message_id, user_id, reaction_type)
+            .await
+    }
+
+    async fn delete_reactions(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> serenity::Result<()> {
+        channel_id.delete_reactions(self, message_id).await
+    }
+
+    async fn delete_message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> serenity::Result<()> {
+        channel_id.delete_message(self, message_id).await
+    }
+
+    async fn direct_message(
+        &self,
+        user_id: UserId,
+        message: CreateMessage,
+    ) -> serenity::Result<Message> {
+        user_id.direct_message(self, message).await
+    }
+
+    async fn unarchive_thread(&self, channel_id: ChannelId) -> serenity::Result<()> {
+        channel_id
+            .edit_thread(self, EditThread::new().archived(false))
+            .await?;
+        Ok(())
+    }
+
+    async fn channel(&self, channel_id: ChannelId) -> serenity::Result<Channel> {
+        channel_id.to_channel(self).await
+    }
+
+    async fn member(&self, guild_id: GuildId, user_id: UserId) -> serenity::Result<Member> {
+        guild_id.member(self, user_id).await
+    }
+
+    async fn guild_members(
+        &self,
+        guild_id: GuildId,
+        after: Option<UserId>,
+    ) -> serenity::Result<Vec<Member>> {
+        guild_id
+            .members(self, Some(MEMBERS_PAGE_SIZE as u64), after)
+            .await
+    }
+
+    async fn add_role(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+    ) -> serenity::Result<()> {
+        self.http
+            .add_member_role(guild_id, user_id, role_id, None)
+            .await
+    }
+
+    async fn create_role(&self, guild_id: GuildId, role: EditRole<'_>) -> serenity::Result<Role> {
+        guild_id.create_role(self, role).await
+    }
+
+    async fn delete_role(&self, guild_id: GuildId, role_id: RoleId) -> serenity::Result<()> {
+        guild_id.delete_role(self, role_id).await
+    }
+}
@@ -0,0 +1,150 @@
+use poise::serenity_prelude::{self as serenity, HttpError};
+
+use crate::Error;
+
+pub const UNKNOWN_CHANNEL: isize = 10003;
+pub const UNKNOWN_MEMBER: isize = 10007;
+pub const UNKNOWN_MESSAGE: isize = 10008;
+const MAX_GUILD_ROLES: isize = 30005;
+const MISSING_ACCESS: isize = 50001;
+pub const MISSING_PERMISSIONS: isize = 50013;
+
+/// 削除されていて見つからなかった Discord のリソースの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityKind {
+    Channel,
+    Message,
+}
+
+/// Unknown Channel (10003) / Unknown Message (10008) のエラーなら, 見つからなかったリソースの種類を返します
+pub fn is_unknown_entity(err: &serenity::Error) -> Option<EntityKind> {
+    let serenity::Error::Http(HttpError::UnsuccessfulRequest(response)) = err else {
+        return None;
+    };
+    match response.error.code {
+        UNKNOWN_CHANNEL => Some(EntityKind::Channel),
+        UNKNOWN_MESSAGE => Some(EntityKind::Message),
+        _ => None,
+    }
+}
+
+/// ロールの作成に失敗した理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoleErrorKind {
+    /// Bot にロールの管理権限がないか, Bot のロールより上のロールを操作しようとしました
+    MissingPermissions,
+    /// サーバーのロールの数が上限に達しています
+    TooManyRoles,
+}
+
+impl RoleErrorKind {
+    /// 実行者に表示する説明
+    pub fn message(self) -> &'static str {
+        match self {
+            RoleErrorKind::MissingPermissions => {
+                "Bot に「ロールの管理」の権限がないため, ロールを作成できませんでした。Bot のロールの権限と順番を確認してください。"
+            }
+            RoleErrorKind::TooManyRoles => {
+                "サーバーのロールが上限 (250 個) に達しているため, ロールを作成できませんでした。使っていないロールを削除してから再度お試しください。"
+            }
+        }
+    }
+}
+
+/// ロールの操作のエラーなら, 失敗した理由を返します
+pub fn role_error_kind(err: &serenity::Error) -> Option<RoleErrorKind> {
+    let serenity::Error::Http(HttpError::UnsuccessfulRequest(response)) = err else {
+        return None;
+    };
+    match response.error.code {
+        MISSING_ACCESS | MISSING_PERMISSIONS => Some(RoleErrorKind::MissingPermissions),
+        MAX_GUILD_ROLES => Some(RoleErrorKind::TooManyRoles),
+        _ => None,
+    }
+}
+
+/// Missing Access (50001) / Missing Permissions (50013) のエラー (Bot の権限が足りない) かどうか
+pub fn is_missing_permissions(err: &Error) -> bool {
+    matches!(
+        err.downcast_ref::<serenity::Error>(),
+        Some(serenity::Error::Http(HttpError::UnsuccessfulRequest(response)))
+            if matches!(response.error.code, MISSING_ACCESS | MISSING_PERMISSIONS)
+    )
+}
+
+/// Unknown Member (10007) のエラー (サーバーにいないユーザー) かどうか
+pub fn is_unknown_member(err: &serenity::Error) -> bool {
+    matches!(
+        err,
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response))
+            if response.error.code == UNKNOWN_MEMBER
+    )
+}
+
+/// `is_unknown_entity` を, 他の処理のエラーと混ざった `Error` に対して判定します
+pub fn unknown_entity(err: &Error) -> Option<EntityKind> {
+    err.downcast_ref::<serenity::Error>()
+        .and_then(is_unknown_entity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_discord::MockError;
+
+    async fn error(status: u16, code: isize) -> serenity::Error {
+        MockError { status, code }.into_error().await
+    }
+
+    #[tokio::test]
+    async fn deleted_channels_and_messages_are_recognized() {
+        assert_eq!(
+            is_unknown_entity(&error(404, UNKNOWN_CHANNEL).await),
+            Some(EntityKind::Channel)
+        );
+        assert_eq!(
+            is_unknown_entity(&error(404, UNKNOWN_MESSAGE).await),
+            Some(EntityKind::Message)
+        );
+        assert_eq!(
+            is_unknown_entity(&error(403, MISSING_PERMISSIONS).await),
+            None
+        );
+
+        let err: Error = error(404, UNKNOWN_MESSAGE).await.into();
+        assert_eq!(unknown_entity(&err), Some(EntityKind::Message));
+        assert_eq!(unknown_entity(&"Unknown Message".into()), None);
+    }
+
+    #[tokio::test]
+    async fn departed_members_are_recognized() {
+        assert!(is_unknown_member(&error(404, UNKNOWN_MEMBER).await));
+        assert!(!is_unknown_member(&error(404, UNKNOWN_MESSAGE).await));
+    }
+
+    #[tokio::test]
+    async fn role_errors_are_explained() {
+        assert_eq!(
+            role_error_kind(&error(403, MISSING_ACCESS).await),
+            Some(RoleErrorKind::MissingPermissions)
+        );
+        assert_eq!(
+            role_error_kind(&error(400, MAX_GUILD_ROLES).await),
+            Some(RoleErrorKind::TooManyRoles)
+        );
+        assert_eq!(role_error_kind(&error(404, UNKNOWN_CHANNEL).await), None);
+    }
+
+    #[tokio::test]
+    async fn missing_permissions_are_recognized() {
+        assert!(is_missing_permissions(
+            &error(403, MISSING_ACCESS).await.into()
+        ));
+        assert!(is_missing_permissions(
+            &error(403, MISSING_PERMISSIONS).await.into()
+        ));
+        assert!(!is_missing_permissions(
+            &error(404, UNKNOWN_MESSAGE).await.into()
+        ));
+    }
+}
@@ -0,0 +1,181 @@
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config_storage;
+use crate::discord_api::DiscordApi;
+use crate::i18n::Text;
+use crate::notification::{Mentions, NotificationPolicy};
+use crate::recruit_log::{self, RecruitEventKind};
+use crate::recruit_message::RecruitConfig;
+use crate::{Data, Error};
+
+/// 開始すると締め切る募集の, 開始したときの参加者
+///
+/// 再起動しても 🙅 のリアクションを処理できるよう, TOML ファイルに保存します。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StartedParticipants {
+    pub channel_id: ChannelId,
+    pub participants: Vec<UserId>,
+    /// 先着 `max_players` 人に入らなかった参加者 (先着順, 抜けた参加者の代わりに繰り上げます)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub waitlist: Vec<UserId>,
+    /// 🙅 で抜けた参加者
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dropped: Vec<UserId>,
+}
+
+impl StartedParticipants {
+    /// `user_id` を抜けた参加者にし, 繰り上げる参加者を返します
+    ///
+    /// 参加者でない (すでに抜けた場合を含む) ユーザーは `None` を返し, 何も変えません。
+    pub fn drop_out(&mut self, user_id: UserId) -> Option<Option<UserId>> {
+        let position = self.participants.iter().position(|&id| id == user_id)?;
+        self.participants.remove(position);
+        self.dropped.push(user_id);
+        if self.waitlist.is_empty() {
+            return Some(None);
+        }
+        let replacement = self.waitlist.remove(0);
+        self.participants.push(replacement);
+        Some(Some(replacement))
+    }
+}
+
+pub fn is_drop_out_reaction(data: &Data, emoji: &ReactionType) -> bool {
+    matches!(emoji, ReactionType::Unicode(emoji) if *emoji == data.config.emojis.drop_out)
+}
+
+/// 開始すると締め切る募集で, 開始したときの参加者を記録して 🙅 のリアクションを付けます
+pub async fn record_started(
+    api: &impl DiscordApi,
+    data: &Data,
+    message: &Message,
+    participants: Vec<UserId>,
+    waitlist: Vec<UserId>,
+) -> Result<(), Error> {
+    let channel_id = message.channel_id;
+    data.storage.set_started_participants(
+        message.id,
+        StartedParticipants {
+            channel_id,
+            participants,
+            waitlist,
+            dropped: Vec::new(),
+        },
+    )?;
+    api.create_reaction(
+        channel_id,
+        message.id,
+        ReactionType::Unicode(data.config.emojis.drop_out.clone()),
+    )
+    .await?;
+    Ok(())
+}
+
+/// 開始した募集の参加者が 🙅 を付けたら主催者に知らせ, 待っている参加者がいれば繰り上げます
+///
+/// 開始していない募集や, 参加者でないユーザーの 🙅 は無視します。
+pub async fn handle_drop_out_reaction(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &RecruitConfig,
+    reaction: &Reaction,
+    message: &Message,
+) -> Result<(), Error> {
+    let Some(user_id) = reaction.user_id else {
+        return Ok(());
+    };
+    let _guard = data.lock_message(message.id).await;
+    let Some(mut started) = data.storage.started_participants(message.id) else {
+        return Ok(());
+    };
+    let Some(replacement) = started.drop_out(user_id) else {
+        return Ok(());
+    };
+    let participants = started.participants.clone();
+    data.storage.set_started_participants(message.id, started)?;
+    recruit_log::record(
+        data,
+        message.id,
+        Some(config),
+        RecruitEventKind::DroppedOut { user_id },
+        participants,
+    );
+
+    let policy = NotificationPolicy::new(config);
+    let language = config.language();
+    // 複数のゲームから選ぶ募集は, 開始したゲームの名前で知らせます
+    let winner = config_storage::load(data, message)?.state.winner;
+    let game_title = winner.as_deref().unwrap_or(&config.game_title);
+    let dropped = user_id.mention().to_string();
+    let channel_id = message.channel_id;
+    if let Some(organizer) = config.organizer {
+        let content = language.text(Text::DroppedOut {
+            user: &dropped,
+            game_title,
+        });
+        let link = message.id.link(channel_id, reaction.guild_id);
+        policy
+            .send_dm_or_channel(api, data, organizer, channel_id, &content, &link)
+            .await?;
+    }
+    if let Some(replacement) = replacement {
+        let content = language.text(Text::Replacement {
+            user: &replacement.mention().to_string(),
+            dropped: &dropped,
+            game_title,
+        });
+        policy
+            .send(
+                api,
+                data,
+                channel_id,
+                policy.message(&content, &Mentions::user(replacement)),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn users(ids: &[u64]) -> Vec<UserId> {
+        ids.iter().copied().map(UserId::new).collect()
+    }
+
+    #[test]
+    fn dropped_participants_are_replaced_from_the_waitlist() {
+        let mut started = StartedParticipants {
+            channel_id: ChannelId::new(1),
+            participants: users(&[11, 12]),
+            waitlist: users(&[13]),
+            dropped: Vec::new(),
+        };
+        assert_eq!(
+            started.drop_out(UserId::new(11)),
+            Some(Some(UserId::new(13)))
+        );
+        assert_eq!(started.drop_out(UserId::new(12)), Some(None));
+        // 抜けた参加者や参加していないユーザーは, 何も変えません
+        assert_eq!(started.drop_out(UserId::new(11)), None);
+        assert_eq!(started.drop_out(UserId::new(14)), None);
+        assert_eq!(started.participants, users(&[13]));
+        assert!(started.waitlist.is_empty());
+        assert_eq!(started.dropped, users(&[11, 12]));
+    }
+
+    #[test]
+    fn only_the_configured_emoji_drops_out() {
+        let data = Data::for_test();
+        assert!(is_drop_out_reaction(
+            &data,
+            &ReactionType::Unicode("🙅".to_string())
+        ));
+        assert!(!is_drop_out_reaction(
+            &data,
+            &ReactionType::Unicode("✋".to_string())
+        ));
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use poise::serenity_prelude::*;
+
+use crate::Data;
+
+/// 募集ごとに, 参加用のリアクション (✋ / 🤚) を処理したユーザー
+pub type ProcessedReactors = HashMap<MessageId, HashMap<UserId, ProcessedReactor>>;
+
+/// 参加用のリアクションを処理したユーザーの記録
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessedReactor {
+    /// ✋ の参加通知を送った場合は, そのメッセージ (チャンネル, メッセージ)
+    pub notice: Option<(ChannelId, MessageId)>,
+}
+
+/// 同じユーザーの参加用のリアクションをどう処理するか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReactorAction {
+    /// 最初のリアクションとして, 参加通知 (✋ の場合) とロールの付与をします
+    First,
+    /// すでに処理したユーザーなので, 参加通知もロールの付与もしません
+    Duplicate,
+    /// ✋ の参加通知を送った後に 🤚 が付いたため, 参加通知なしの参加として通知を削除します
+    RetractNotice(ChannelId, MessageId),
+}
+
+/// ✋ と 🤚 の両方でリアクションしたユーザーは, 参加通知なしの参加として扱います
+pub fn decide(previous: Option<&ProcessedReactor>, silent: bool) -> ReactorAction {
+    match previous {
+        None => ReactorAction::First,
+        Some(ProcessedReactor {
+            notice: Some((channel_id, message_id)),
+        }) if silent => ReactorAction::RetractNotice(*channel_id, *message_id),
+        Some(_) => ReactorAction::Duplicate,
+    }
+}
+
+/// `user_id` の参加用のリアクションをどう処理するか決め, 処理したユーザーとして記録します
+pub fn process(data: &Data, message_id: MessageId, user_id: UserId, silent: bool) -> ReactorAction {
+    let mut processed = data.processed_reactors.lock().unwrap();
+    let reactors = processed.entry(message_id).or_default();
+    let action = decide(reactors.get(&user_id), silent);
+    let reactor = reactors
+        .entry(user_id)
+        .or_insert(ProcessedReactor { notice: None });
+    if let ReactorAction::RetractNotice(..) = action {
+        reactor.notice = None;
+    }
+    action
+}
+
+/// 送った参加通知を記録します (後から 🤚 が付いたときに削除するため)
+pub fn record_notice(data: &Data, message_id: MessageId, user_id: UserId, notice: &Message) {
+    if let Some(reactor) = data
+        .processed_reactors
+        .lock()
+        .unwrap()
+        .get_mut(&message_id)
+        .and_then(|reactors| reactors.get_mut(&user_id))
+    {
+        reactor.notice = Some((notice.channel_id, notice.id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE_ID: MessageId = MessageId::new(1);
+    const USER_ID: UserId = UserId::new(2);
+
+    #[test]
+    fn second_reaction_only_retracts_the_notice() {
+        let notified = ProcessedReactor {
+            notice: Some((ChannelId::new(3), MessageId::new(4))),
+        };
+        assert_eq!(decide(None, false), ReactorAction::First);
+        assert_eq!(decide(Some(&notified), false), ReactorAction::Duplicate);
+        assert_eq!(
+            decide(Some(&notified), true),
+            ReactorAction::RetractNotice(ChannelId::new(3), MessageId::new(4))
+        );
+        let silent = ProcessedReactor { notice: None };
+        assert_eq!(decide(Some(&silent), true), ReactorAction::Duplicate);
+    }
+
+    #[test]
+    fn processed_reactors_are_remembered() {
+        let data = Data::for_test();
+        assert_eq!(
+            process(&data, MESSAGE_ID, USER_ID, true),
+            ReactorAction::First
+        );
+        assert_eq!(
+            process(&data, MESSAGE_ID, USER_ID, false),
+            ReactorAction::Duplicate
+        );
+        // 別の募集では, 最初のリアクションとして処理します
+        assert_eq!(
+            process(&data, MessageId::new(5), USER_ID, false),
+            ReactorAction::First
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use poise::serenity_prelude::*;
+
+pub use crate::Data;
+#[cfg(feature = "api")]
+use crate::api;
+pub use crate::discord_api::DiscordApi;
+use crate::notification::Mentions;
+use crate::reaction_debounce::Admission;
+pub use crate::reaction_emoji::ReactionEmojis;
+#[cfg(feature = "sqlite")]
+pub use crate::sqlite_storage::SqliteStorage;
+pub use crate::storage::Storage;
+pub use crate::storage_backend::{FileStorage, MemoryStorage, from_env as storage_from_env};
+use crate::{
+    Error, capabilities, command_locale, commands, handle_gateway_event, member_left, moderate,
+    process_reaction_add, retry_held_reactions, scheduler, start_marker, summary, sweeper,
+};
+
+/// 募集の処理の設定
+///
+/// `EngineConfig::new()` の既定値から, 変えたい項目だけを指定します。
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use joinbell::engine::{EngineConfig, ReactionEmojis};
+///
+/// let config = EngineConfig::new()
+///     .emojis(ReactionEmojis {
+///         participation: "👍".to_string(),
+///         ..ReactionEmojis::default()
+///     })
+///     .state_horizon(Duration::from_secs(6 * 60 * 60));
+/// ```
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+    pub(crate) emojis: ReactionEmojis,
+    pub(crate) intents: GatewayIntents,
+    pub(crate) application_flags: ApplicationFlags,
+    pub(crate) state_horizon: Duration,
+    pub(crate) rejection_notice_interval: Duration,
+    pub(crate) verification_ttl: Duration,
+    pub(crate) cached_count_ttl: Duration,
+    pub(crate) debounce_window: Duration,
+    pub(crate) startup_grace: Duration,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            emojis: ReactionEmojis::default(),
+            intents: GatewayIntents::non_privileged(),
+            application_flags: ApplicationFlags::empty(),
+            state_horizon: Duration::from_secs(sweeper::DEFAULT_STATE_HORIZON_HOURS * 60 * 60),
+            rejection_notice_interval: Duration::from_secs(60 * 60),
+            verification_ttl: Duration::from_secs(24 * 60 * 60),
+            cached_count_ttl: Duration::from_secs(10 * 60),
+            debounce_window: Duration::from_secs(2),
+            startup_grace: Duration::from_secs(30),
+        }
+    }
+}
+
+impl EngineConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 環境変数 (`JOINBELL_STATE_HORIZON_HOURS`, `JOINBELL_GUILD_MEMBERS_INTENT`,
+    /// `JOINBELL_MESSAGE_CONTENT_INTENT`) を反映した設定
+    pub fn from_env() -> Self {
+        let mut intents = GatewayIntents::non_privileged();
+        if member_left::guild_members_intent_enabled() {
+            intents |= GatewayIntents::GUILD_MEMBERS;
+        }
+        if capabilities::message_content_intent_enabled() {
+            intents |= GatewayIntents::MESSAGE_CONTENT;
+        }
+        Self::new()
+            .intents(intents)
+            .state_horizon(sweeper::state_horizon_from_env())
+    }
+
+    /// 接続に使う Gateway Intents
+    pub fn gateway_intents(&self) -> GatewayIntents {
+        self.intents
+    }
+
+    /// 募集のリアクションに使う絵文字
+    pub fn emojis(mut self, emojis: ReactionEmojis) -> Self {
+        self.emojis = emojis;
+        self
+    }
+
+    /// 接続に使う Gateway Intents (既定: `GatewayIntents::non_privileged()`)
+    pub fn intents(mut self, intents: GatewayIntents) -> Self {
+        self.intents = intents;
+        self
+    }
+
+    /// Bot のアプリケーションのフラグ (接続したときの `Ready` の値を指定します)
+    pub fn application_flags(mut self, application_flags: ApplicationFlags) -> Self {
+        self.application_flags = application_flags;
+        self
+    }
+
+    /// 最後にリアクションなどを処理してから, 募集の状態をメモリに残しておく時間 (既定: 24 時間)
+    pub fn state_horizon(mut self, state_horizon: Duration) -> Self {
+        self.state_horizon = state_horizon;
+        self
+    }
+
+    /// 同じ募集について同じユーザーに, リアクションを数えなかった理由を通知する間隔 (既定: 1 時間)
+    pub fn rejection_notice_interval(mut self, rejection_notice_interval: Duration) -> Self {
+        self.rejection_notice_interval = rejection_notice_interval;
+        self
+    }
+
+    /// 確認を通ったユーザーを, 確認し直さずに参加者として数える時間 (既定: 24 時間)
+    pub fn verification_ttl(mut self, verification_ttl: Duration) -> Self {
+        self.verification_ttl = verification_ttl;
+        self
+    }
+
+    /// キャッシュした参加人数を使う期間 (既定: 10 分)
+    ///
+    /// 取りこぼしたリアクションがあっても, この期間が過ぎれば数え直します。
+    pub fn cached_count_ttl(mut self, cached_count_ttl: Duration) -> Self {
+        self.cached_count_ttl = cached_count_ttl;
+        self
+    }
+
+    /// 同じユーザーの同じリアクションの操作をまとめる時間 (既定: 2 秒)
+    pub fn debounce_window(mut self, debounce_window: Duration) -> Self {
+        self.debounce_window = debounce_window;
+        self
+    }
+
+    /// 起動してからキャッシュの準備ができるまで, 募集か判定できなかったリアクションを預かる時間 (既定: 30 秒)
+    pub fn startup_grace(mut self, startup_grace: Duration) -> Self {
+        self.startup_grace = startup_grace;
+        self
+    }
+}
+
+/// ほかの Bot に組み込める, 募集の処理
+///
+/// `Storage` に保存し, `DiscordApi` で Discord を操作します。serenity で動かす場合は `Context` を,
+/// Discord に接続せずに動かす場合は `MockDiscord` を使います。
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use joinbell::engine::{Engine, EngineConfig, MemoryStorage};
+/// use joinbell::mock_discord::{self, MockDiscord};
+/// use poise::serenity_prelude::{ChannelId, FullEvent, GuildId, MessageId};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), joinbell::Error> {
+/// let engine = Engine::new(
+///     Arc::new(MemoryStorage::default()),
+///     MockDiscord::new(),
+///     EngineConfig::new(),
+/// );
+/// let guild_id = GuildId::new(1);
+/// let message = mock_discord::message(
+///     ChannelId::new(2),
+///     MessageId::new(3),
+///     "```toml\ngame_title = \"Apex\"\nrequired_players = 2\n```",
+/// );
+/// engine.api().insert_message(message.clone());
+///
+/// for user_id in [11, 12] {
+///     engine.api().insert_member(mock_discord::member(guild_id, user_id));
+///     engine.api().add_reaction(message.id, "✋", user_id);
+///     let add_reaction = mock_discord::reaction(&message, guild_id, user_id, "✋");
+///     engine.handle_event(&FullEvent::ReactionAdd { add_reaction }).await?;
+/// }
+///
+/// let sent = engine.api().sent_contents();
+/// assert!(sent.last().unwrap().starts_with("<@11> <@12> が Apex を開始します"));
+/// # Ok(())
+/// # }
+/// ```
+pub struct Engine<A = Context> {
+    api: A,
+    data: Data,
+}
+
+impl<A: DiscordApi> Engine<A> {
+    pub fn new(storage: Arc<dyn Storage>, api: A, config: EngineConfig) -> Self {
+        Self {
+            api,
+            data: Data::new(storage, config),
+        }
+    }
+
+    pub fn api(&self) -> &A {
+        &self.api
+    }
+
+    /// Bot の状態 (`attach` したフレームワークの `Data` として渡します)
+    pub fn data(&self) -> &Data {
+        &self.data
+    }
+
+    /// Discord から受け取ったイベントを処理します (`attach` したフレームワークと同じ処理です)
+    pub async fn handle_event(&self, event: &FullEvent) -> Result<(), Error> {
+        dispatch_event(&self.api, &self.data, event).await
+    }
+}
+
+impl Engine<Context> {
+    /// 予約した処理の実行や今日の募集の一覧の書き換えなど, バックグラウンドの処理を開始します
+    ///
+    /// フレームワークの `setup` で, 接続した `Context` から `Engine` を作った後に呼び出します。
+    pub fn spawn_tasks(&self) {
+        let ctx = &self.api;
+        let data = &self.data;
+        {
+            let ctx = ctx.clone();
+            let data = data.clone();
+            tokio::spawn(async move {
+                start_marker::resume_interrupted_starts(&ctx, &data).await;
+                moderate::resume_bulk_closes(&ctx, &data).await;
+            });
+        }
+        {
+            // キャッシュの準備ができた通知が来なくても, 預かったリアクションを処理します
+            let ctx = ctx.clone();
+            let data = data.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(data.config.startup_grace).await;
+                retry_held_reactions(&ctx, &data).await;
+            });
+        }
+        {
+            // 連打で保留したリアクションの追加を, まとめる時間が過ぎてから処理します
+            let ctx = ctx.clone();
+            let data = data.clone();
+            tokio::spawn(async move {
+                loop {
+                    for reaction in data.reaction_debouncer.next_batch().await {
+                        let ctx = ctx.clone();
+                        let data = data.clone();
+                        tokio::spawn(async move {
+                            if let Err(err) = process_reaction_add(&ctx, &data, &reaction).await {
+                                eprintln!("Failed to handle debounced reaction: {err}");
+                            }
+                        });
+                    }
+                }
+            });
+        }
+        #[cfg(feature = "api")]
+        api::spawn_from_env(ctx.clone(), data.clone());
+        scheduler::spawn_worker(ctx.clone(), data.clone());
+        sweeper::spawn_sweeper(data.clone(), data.config.state_horizon);
+        summary::spawn_updater(ctx.clone(), data.clone());
+    }
+}
+
+/// フレームワークの設定に, 募集のコマンドとイベントの処理を加えます
+///
+/// コマンドはサーバーの言語に合わせて翻訳します。コマンドへの返信はメンションを送りません。
+pub fn attach(options: &mut poise::FrameworkOptions<Data, Error>) {
+    let mut commands = commands();
+    command_locale::localize(&mut commands);
+    options.commands.extend(commands);
+    // poise の既定ではユーザーへのメンションを送ります
+    options.allowed_mentions = Some(Mentions::none().allowed_mentions());
+    options.event_handler =
+        |ctx, event, _framework, data| Box::pin(dispatch_event(ctx, data, event));
+}
+
+/// イベントを処理します
+///
+/// 募集のリアクションは `api` で処理し, それ以外のイベントは `api` が serenity の `Context` の場合だけ処理します。
+async fn dispatch_event<A: DiscordApi>(
+    api: &A,
+    data: &Data,
+    event: &FullEvent,
+) -> Result<(), Error> {
+    match event {
+        FullEvent::ReactionAdd { add_reaction } => {
+            // スーパーリアクションの連打などは, まとめる時間が過ぎてから最後の状態だけを処理します
+            match data.reaction_debouncer.admit(add_reaction) {
+                Admission::Process => process_reaction_add(api, data, add_reaction).await?,
+                Admission::Queued => {}
+            }
+        }
+        FullEvent::ReactionRemove { removed_reaction } => {
+            data.reaction_debouncer.cancel(removed_reaction);
+        }
+        event => {
+            if let Some(ctx) = api.serenity_context() {
+                handle_gateway_event(ctx, data, event).await?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use poise::serenity_prelude::*;
+
+use crate::close_reason;
+use crate::discord_api::DiscordApi;
+use crate::discord_error::is_unknown_entity;
+use crate::i18n::{self, Language, Text};
+use crate::notification::{Mentions, NotificationKind, NotificationPolicy};
+use crate::recruit_message::{RecruitConfig, render_mentions};
+use crate::scheduler::Job;
+use crate::{
+    Data, Error, collect_participants, config_storage, countdown, quiet_hours, recruit_edit,
+    roll_call, send_ephemeral, task_registry, voice_gate,
+};
+
+/// `start_time` のある募集について, 開始予定の日時に人数が揃っているかの確認を予約します
+pub fn schedule_expiry(
+    data: &Data,
+    config: &RecruitConfig,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+) {
+    let Some(start_time) = config.start_time else {
+        return;
+    };
+    data.scheduler.enqueue(
+        &*data.storage,
+        start_time,
+        Job::ExpireRecruit {
+            channel_id,
+            message_id,
+            guild_id,
+        },
+    );
+}
+
+/// 人数が揃わなかった募集のお知らせ (参加者がいなければ参加者の行を省きます)
+pub fn render_expired_notice(
+    language: Language,
+    game_title: &str,
+    participants: &[UserId],
+    required_players: usize,
+) -> String {
+    let mut content = language.text(Text::RecruitExpired {
+        game_title,
+        count: participants.len(),
+        required_players,
+    });
+    if !participants.is_empty() {
+        content += &format!(
+            "\n{}",
+            language.text(Text::RecruitExpiredParticipants {
+                mentions: &render_mentions(participants),
+            })
+        );
+    }
+    content
+}
+
+/// お知らせでメンションする参加者 (サーバー設定 `ping_on_fail` のときだけメンションします)
+pub fn expired_notice_mentions(ping_on_fail: bool, participants: &[UserId]) -> Mentions {
+    if ping_on_fail {
+        Mentions::users(participants.iter().copied())
+    } else {
+        Mentions::none()
+    }
+}
+
+/// 開始予定の日時になっても人数が揃っていない募集を, 不成立として締め切ります
+///
+/// その時点の参加者を数え直して記録し, 募集メッセージに不成立になったことを表示して,
+/// チャンネルと主催者の DM に参加していた人と人数を知らせます。
+/// 人数が揃って締め切った募集, キャンセルした募集, 受付を終了した募集では何もしません。
+pub async fn expire_recruit(
+    api: &impl DiscordApi,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+) -> Result<(), Error> {
+    let _guard = data.lock_message(message_id).await;
+    let message = match api.message(channel_id, message_id).await {
+        Ok(message) => message,
+        Err(err) if is_unknown_entity(&err).is_some() => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    let recruit_message = config_storage::load(data, &message)?;
+    let state = &recruit_message.state;
+    if state.cancelled || state.started || state.closed_entries.is_some() {
+        return Ok(());
+    }
+    let mut config = recruit_message.config.clone();
+    i18n::resolve_language(data, &mut config, guild_id);
+    let mut participants: Vec<UserId> =
+        collect_participants(api, data, &message, &config, guild_id)
+            .await?
+            .into_iter()
+            .collect();
+    participants.sort();
+    // `:bell:` を押さないまま予定の日時を迎えた募集も, 人数が揃っていれば締め切りません
+    if config.required_players <= participants.len() {
+        return Ok(());
+    }
+
+    let edited =
+        recruit_edit::edit_recruit_message(api, data, channel_id, message_id, |recruit_message| {
+            let state = &recruit_message.state;
+            if state.cancelled || state.started || state.closed_entries.is_some() {
+                return None;
+            }
+            recruit_message.state.cancelled = true;
+            recruit_message.state.expired = true;
+            recruit_message.state.participant_count = Some(participants.len());
+            Some(())
+        })
+        .await?;
+    if edited.is_none() {
+        return Ok(());
+    }
+    close_reason::close_expired(data, message_id, &config, participants.clone());
+    countdown::cancel_countdown(api, data, message_id).await;
+    roll_call::cancel_roll_call(api, data, message_id).await;
+    voice_gate::release(data, message_id);
+    task_registry::cancel_all(data, message_id);
+    data.participant_counts.lock().unwrap().remove(&message_id);
+
+    let language = config.language();
+    let content = render_expired_notice(
+        language,
+        &config.game_title,
+        &participants,
+        config.required_players,
+    );
+    let ping_on_fail =
+        guild_id.is_some_and(|guild_id| data.storage.guild_settings(guild_id).ping_on_fail);
+    let quiet_hours = quiet_hours::active(data, guild_id, &config, Timestamp::now());
+    let policy = NotificationPolicy::new(&config).with_quiet_hours(quiet_hours.map(|_| language));
+    if let Err(err) = policy
+        .notify(
+            api,
+            data,
+            NotificationKind::Notice,
+            channel_id,
+            &content,
+            &expired_notice_mentions(ping_on_fail, &participants),
+        )
+        .await
+    {
+        eprintln!("Failed to send expired notice of {message_id}: {err}");
+    }
+    if let Some(organizer) = config.organizer {
+        let dm_content = format!("{content}\n{}", message.link());
+        if let Err(err) = api
+            .direct_message(organizer, policy.message(&dm_content, &Mentions::none()))
+            .await
+        {
+            eprintln!("Failed to send expired notice to organizer {organizer}: {err}");
+        }
+    }
+    Ok(())
+}
+
+/// 不成立の募集のお知らせで, 参加者をメンションするか設定します
+#[poise::command(slash_command, guild_only, rename = "ping_on_fail")]
+pub async fn settings_ping_on_fail(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "参加していた人をメンションするかどうか (既定: いいえ)"] enabled: bool,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.ping_on_fail = enabled;
+        })?;
+    let content = if enabled {
+        "人数が揃わなかった募集のお知らせで、参加していた人をメンションします"
+    } else {
+        "人数が揃わなかった募集のお知らせでは、参加していた人をメンションせずに表示します"
+    };
+    send_ephemeral(ctx, content).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expired_notice_lists_participants() {
+        let participants = [UserId::new(11), UserId::new(12)];
+        let content = render_expired_notice(Language::Ja, "Apex", &participants, 3);
+        assert!(
+            content.ends_with("(2/3)\n参加していた人: <@11> <@12>"),
+            "{content}"
+        );
+        let content = render_expired_notice(Language::En, "Apex", &[], 3);
+        assert!(!content.contains('\n'), "{content}");
+    }
+
+    #[test]
+    fn participants_are_pinged_only_when_configured() {
+        let participants = [UserId::new(11)];
+        assert_eq!(
+            expired_notice_mentions(true, &participants),
+            Mentions::users(participants)
+        );
+        assert_eq!(
+            expired_notice_mentions(false, &participants),
+            Mentions::none()
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::recruit_message::sanitize_text;
+use crate::{Data, Error, send_ephemeral};
+
+const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+
+/// ゲーム名を比較用に正規化します
+///
+/// 全角・半角の違い (NFKC), 大文字・小文字の違い, 前後と連続する空白の違いを無視します。
+pub fn normalize(title: &str) -> String {
+    let normalized: String = title.nfkc().collect::<String>().to_lowercase();
+    normalized.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// サーバーごとのゲーム名の別名 (正規化した別名 → 正式なゲーム名)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GameAliases(BTreeMap<String, String>);
+
+impl GameAliases {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 別名 `alias` を正式なゲーム名 `canonical` に対応付けます
+    ///
+    /// `canonical` 自体が別名の場合はその正式なゲーム名に, `alias` を正式なゲーム名としていた別名は
+    /// `canonical` に付け替えるため, 別名が連鎖することはありません。
+    pub fn insert(&mut self, canonical: &str, alias: &str) -> Result<(), String> {
+        let key = normalize(alias);
+        if key.is_empty() || normalize(canonical).is_empty() {
+            return Err("ゲーム名と別名を入力してください".to_string());
+        }
+        let canonical = self.canonical(canonical).trim().to_string();
+        if normalize(&canonical) == key {
+            return Err(format!("{alias} は {canonical} と同じゲーム名です"));
+        }
+        for target in self.0.values_mut() {
+            if normalize(target) == key {
+                *target = canonical.clone();
+            }
+        }
+        self.0.insert(key, canonical);
+        Ok(())
+    }
+
+    /// 別名を削除し, 対応付けていた正式なゲーム名を返します
+    pub fn remove(&mut self, alias: &str) -> Option<String> {
+        self.0.remove(&normalize(alias))
+    }
+
+    /// 別名であれば正式なゲーム名を, そうでなければ `title` をそのまま返します
+    pub fn canonical<'a>(&'a self, title: &'a str) -> &'a str {
+        self.0.get(&normalize(title)).map_or(title, String::as_str)
+    }
+
+    /// 別名を考慮して同じゲームかどうか判定します
+    pub fn is_same_game(&self, a: &str, b: &str) -> bool {
+        normalize(self.canonical(a)) == normalize(self.canonical(b))
+    }
+
+    /// (正規化した別名, 正式なゲーム名) の一覧
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(alias, canonical)| (alias.as_str(), canonical.as_str()))
+    }
+
+    /// 手動で編集した設定ファイルなどの, 正規化されていない別名を正規化します
+    pub fn normalized(self) -> Self {
+        let mut aliases = Self::default();
+        for (alias, canonical) in self.0 {
+            let _ = aliases.insert(&canonical, &alias);
+        }
+        aliases
+    }
+}
+
+/// ゲーム名の入力候補として, 別名と募集の記録から正式なゲーム名を返します
+///
+/// 入力中の文字列が正式なゲーム名か別名に含まれるものを候補にします。
+pub async fn autocomplete_game_title(
+    ctx: poise::Context<'_, Data, Error>,
+    partial: &str,
+) -> impl Iterator<Item = String> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Vec::new().into_iter();
+    };
+    let storage = &ctx.data().storage;
+    let aliases = storage.guild_settings(guild_id).game_aliases;
+    let partial = normalize(partial);
+
+    let mut candidates: Vec<String> = Vec::new();
+    let mut add = |title: &str| {
+        let title = aliases.canonical(title);
+        if !candidates
+            .iter()
+            .any(|candidate| aliases.is_same_game(candidate, title))
+        {
+            candidates.push(title.to_string());
+        }
+    };
+    for record in storage.recruit_history(guild_id).iter().rev() {
+        add(&record.game_title);
+    }
+    for (_, canonical) in aliases.iter() {
+        add(canonical);
+    }
+
+    let matches: Vec<String> = candidates
+        .into_iter()
+        .filter(|candidate| {
+            normalize(candidate).contains(&partial)
+                || aliases.iter().any(|(alias, canonical)| {
+                    alias.contains(&partial) && aliases.is_same_game(canonical, candidate)
+                })
+        })
+        .take(MAX_AUTOCOMPLETE_CHOICES)
+        .collect();
+    matches.into_iter()
+}
+
+/// ゲーム名の別名を管理します
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "MANAGE_GUILD",
+    subcommands("alias_add", "alias_list", "alias_remove")
+)]
+pub async fn alias(_ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// ゲーム名の別名を追加します
+#[poise::command(slash_command, guild_only, rename = "add")]
+async fn alias_add(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "正式なゲーム名"]
+    #[autocomplete = "autocomplete_game_title"]
+    canonical: String,
+    #[description = "同じゲームとして扱う別名"] alias: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let canonical = sanitize_text(&canonical);
+    let alias = sanitize_text(&alias);
+    let mut result = Ok(());
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            result = settings.game_aliases.insert(&canonical, &alias);
+        })?;
+    let content = match result {
+        Ok(()) => format!("{alias} を {canonical} の別名にしました"),
+        Err(err) => err,
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// ゲーム名の別名の一覧を表示します
+#[poise::command(slash_command, guild_only, rename = "list")]
+async fn alias_list(ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let aliases = ctx.data().storage.guild_settings(guild_id).game_aliases;
+    if aliases.is_empty() {
+        send_ephemeral(ctx, "ゲーム名の別名はありません").await?;
+        return Ok(());
+    }
+    let mut groups: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (alias, canonical) in aliases.iter() {
+        groups.entry(canonical).or_default().push(alias);
+    }
+    let lines: Vec<String> = groups
+        .into_iter()
+        .map(|(canonical, aliases)| format!("{canonical}: {}", aliases.join(", ")))
+        .collect();
+    send_ephemeral(ctx, &lines.join("\n")).await?;
+    Ok(())
+}
+
+/// ゲーム名の別名を削除します
+#[poise::command(slash_command, guild_only, rename = "remove")]
+async fn alias_remove(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "削除する別名"] alias: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let mut removed = None;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            removed = settings.game_aliases.remove(&alias);
+        })?;
+    let content = match removed {
+        Some(canonical) => format!(
+            "{canonical} の別名 {} を削除しました",
+            sanitize_text(&alias)
+        ),
+        None => format!("{} という別名はありません", sanitize_text(&alias)),
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn width_case_and_spaces_are_ignored() {
+        assert_eq!(normalize("  ＡＰＥＸ   Legends "), "apex legends");
+    }
+
+    #[test]
+    fn aliases_point_to_the_canonical_title() {
+        let mut aliases = GameAliases::default();
+        aliases.insert("Apex Legends", "apex").unwrap();
+        // 別名の別名は, 正式なゲーム名に対応付けます
+        aliases.insert("APEX", "エペ").unwrap();
+        assert_eq!(aliases.canonical("エペ"), "Apex Legends");
+        assert_eq!(aliases.canonical("Valorant"), "Valorant");
+        assert!(aliases.is_same_game("エペ", "apex legends"));
+        assert!(!aliases.is_same_game("エペ", "Valorant"));
+        assert!(
+            aliases
+                .insert("Apex Legends", "ａｐｅｘ ｌｅｇｅｎｄｓ")
+                .is_err()
+        );
+        assert!(aliases.insert("Apex Legends", " ").is_err());
+
+        // 正式なゲーム名を別名にすると, その別名も付け替えます
+        aliases.insert("Apex Mobile", "Apex Legends").unwrap();
+        assert_eq!(aliases.canonical("エペ"), "Apex Mobile");
+        assert_eq!(aliases.remove("エペ").as_deref(), Some("Apex Mobile"));
+    }
+
+    #[test]
+    fn hand_edited_aliases_are_normalized() {
+        let aliases: GameAliases =
+            serde_json::from_str(r#"{"ＡＰＥＸ ": "Apex Legends"}"#).unwrap();
+        let aliases = aliases.normalized();
+        assert_eq!(
+            aliases.iter().collect::<Vec<_>>(),
+            [("apex", "Apex Legends")]
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game_alias::{self, GameAliases, normalize};
+use crate::recruit_message::{MAX_PLAYERS, sanitize_text};
+use crate::{Data, Error, send_ephemeral};
+
+/// `/settings game add` で登録したゲームの人数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GamePlayers {
+    /// 登録したときのゲーム名
+    pub title: String,
+    /// 開始に必要な人数
+    pub players: usize,
+    /// 参加できる最大人数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_players: Option<usize>,
+}
+
+impl GamePlayers {
+    /// 募集の人数として自然な上限 (`max_players` がなければ `players`)
+    pub fn upper(&self) -> usize {
+        self.max_players.unwrap_or(self.players)
+    }
+}
+
+/// サーバーごとのゲームの人数 (正規化した正式なゲーム名 → 人数)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GamePresets(BTreeMap<String, GamePlayers>);
+
+impl GamePresets {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 別名を正式なゲーム名にしてから人数を登録します (同じゲームの登録は上書きします)
+    pub fn insert(
+        &mut self,
+        aliases: &GameAliases,
+        title: &str,
+        players: usize,
+        max_players: Option<usize>,
+    ) -> Result<(), String> {
+        let title = aliases.canonical(title).trim();
+        let key = normalize(title);
+        if key.is_empty() {
+            return Err("ゲーム名を入力してください".to_string());
+        }
+        if players == 0 || MAX_PLAYERS < players {
+            return Err(format!(
+                "players は 1 以上 {MAX_PLAYERS} 以下を指定してください"
+            ));
+        }
+        if max_players.is_some_and(|max_players| max_players < players || MAX_PLAYERS < max_players)
+        {
+            return Err(format!(
+                "max_players は players 以上 {MAX_PLAYERS} 以下を指定してください"
+            ));
+        }
+        self.0.insert(
+            key,
+            GamePlayers {
+                title: title.to_string(),
+                players,
+                max_players,
+            },
+        );
+        Ok(())
+    }
+
+    /// 登録を削除し, 削除した登録を返します
+    pub fn remove(&mut self, aliases: &GameAliases, title: &str) -> Option<GamePlayers> {
+        self.0.remove(&normalize(aliases.canonical(title)))
+    }
+
+    /// 別名を考慮して, ゲーム名の登録を探します
+    pub fn find(&self, aliases: &GameAliases, title: &str) -> Option<&GamePlayers> {
+        self.0.get(&normalize(aliases.canonical(title)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GamePlayers> {
+        self.0.values()
+    }
+
+    /// 手動で編集した設定ファイルなどの, 正規化されていないゲーム名を正規化します
+    pub fn normalized(self) -> Self {
+        Self(
+            self.0
+                .into_values()
+                .map(|players| (normalize(&players.title), players))
+                .collect(),
+        )
+    }
+}
+
+/// `/recruit create` の人数を決めます
+///
+/// 指定した人数を優先し, `required_players` を省略した場合は登録した人数を使います
+/// (`max_players` も省略していれば登録した最大人数を使います)。どちらもなければエラーを返します。
+pub fn resolve_players(
+    required_players: Option<usize>,
+    max_players: Option<usize>,
+    registered: Option<&GamePlayers>,
+) -> Result<(usize, Option<usize>), String> {
+    match (required_players, registered) {
+        (Some(required_players), _) => Ok((required_players, max_players)),
+        (None, Some(registered)) => Ok((
+            registered.players,
+            max_players.or(registered.max_players),
+        )),
+        (None, None) => Err(
+            "required_players を指定してください (/settings game add でゲームの人数を登録すると省略できます)"
+                .to_string(),
+        ),
+    }
+}
+
+/// 指定した人数が登録した人数から大きく外れている場合に, 確認のための説明を返します
+///
+/// 登録した最大人数 (なければ開始に必要な人数) を超える人数を, 打ち間違いとみなします。
+pub fn mismatch(
+    registered: &GamePlayers,
+    required_players: usize,
+    max_players: Option<usize>,
+) -> Option<String> {
+    let upper = registered.upper();
+    let requested = max_players
+        .unwrap_or(required_players)
+        .max(required_players);
+    if requested <= upper {
+        return None;
+    }
+    let expected = match registered.max_players {
+        Some(max_players) => format!("{}〜{max_players}人", registered.players),
+        None => format!("{}人", registered.players),
+    };
+    Some(format!(
+        "{} は {expected} で登録されていますが、{requested}人で募集しようとしています。",
+        registered.title
+    ))
+}
+
+/// ゲームの人数の登録を管理します
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "game",
+    subcommands("game_add", "game_list", "game_remove")
+)]
+pub async fn settings_game(_ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// ゲームの人数を登録します (`/recruit create` で人数を省略できます)
+#[poise::command(slash_command, guild_only, rename = "add")]
+async fn game_add(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "ゲーム名 (別名は正式なゲーム名で登録します)"]
+    #[autocomplete = "game_alias::autocomplete_game_title"]
+    title: String,
+    #[description = "開始に必要な人数"] players: usize,
+    #[description = "参加できる最大人数"] max: Option<usize>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let title = sanitize_text(&title);
+    let mut result = Ok(());
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            result = settings
+                .game_players
+                .insert(&settings.game_aliases, &title, players, max);
+        })?;
+    let content = match result {
+        Ok(()) => match max {
+            Some(max) => format!("{title} を {players}〜{max}人のゲームとして登録しました"),
+            None => format!("{title} を {players}人のゲームとして登録しました"),
+        },
+        Err(err) => err,
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// 登録したゲームの人数の一覧を表示します
+#[poise::command(slash_command, guild_only, rename = "list")]
+async fn game_list(ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let presets = ctx.data().storage.guild_settings(guild_id).game_players;
+    if presets.is_empty() {
+        send_ephemeral(ctx, "人数を登録したゲームはありません").await?;
+        return Ok(());
+    }
+    let lines: Vec<String> = presets
+        .iter()
+        .map(|players| match players.max_players {
+            Some(max_players) => format!("{}: {}〜{max_players}人", players.title, players.players),
+            None => format!("{}: {}人", players.title, players.players),
+        })
+        .collect();
+    send_ephemeral(ctx, &lines.join("\n")).await?;
+    Ok(())
+}
+
+/// ゲームの人数の登録を削除します
+#[poise::command(slash_command, guild_only, rename = "remove")]
+async fn game_remove(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "削除するゲーム名"]
+    #[autocomplete = "game_alias::autocomplete_game_title"]
+    title: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let mut removed = None;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            removed = settings.game_players.remove(&settings.game_aliases, &title);
+        })?;
+    let content = match removed {
+        Some(players) => format!("{} の人数の登録を削除しました", players.title),
+        None => format!("{} の人数は登録されていません", sanitize_text(&title)),
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn squad() -> GamePlayers {
+        GamePlayers {
+            title: "Apex Legends".to_string(),
+            players: 3,
+            max_players: Some(4),
+        }
+    }
+
+    #[test]
+    fn presets_are_found_by_alias() {
+        let mut aliases = GameAliases::default();
+        aliases.insert("Apex Legends", "エペ").unwrap();
+        let mut presets = GamePresets::default();
+        presets.insert(&aliases, "エペ", 3, Some(4)).unwrap();
+        assert_eq!(presets.find(&aliases, "apex legends"), Some(&squad()));
+        assert!(presets.insert(&aliases, "Apex", 0, None).is_err());
+        assert!(presets.insert(&aliases, "Apex", 3, Some(2)).is_err());
+        assert_eq!(presets.remove(&aliases, "エペ"), Some(squad()));
+        assert!(presets.is_empty());
+    }
+
+    #[test]
+    fn registered_players_fill_omitted_counts() {
+        assert_eq!(
+            resolve_players(Some(2), None, Some(&squad())),
+            Ok((2, None))
+        );
+        assert_eq!(
+            resolve_players(None, None, Some(&squad())),
+            Ok((3, Some(4)))
+        );
+        assert_eq!(
+            resolve_players(None, Some(5), Some(&squad())),
+            Ok((3, Some(5)))
+        );
+        assert!(resolve_players(None, None, None).is_err());
+    }
+
+    #[test]
+    fn counts_over_the_registered_maximum_are_confirmed() {
+        assert_eq!(mismatch(&squad(), 3, Some(4)), None);
+        assert_eq!(
+            mismatch(&squad(), 30, None).as_deref(),
+            Some("Apex Legends は 3〜4人 で登録されていますが、30人で募集しようとしています。")
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use poise::serenity_prelude::*;
+
+use crate::discord_error::is_unknown_entity;
+use crate::{
+    Data, Error, begin_start, collect_participants, config_storage, i18n, participant_count,
+};
+
+/// 再接続 (Resume) と, その後の数え直しで見つかったリアクションの統計
+#[derive(Debug, Default)]
+pub struct ResumeMetrics {
+    /// 再接続した回数
+    resumes: AtomicUsize,
+    /// 再接続の後に数え直して増えた参加者の数
+    recovered: AtomicUsize,
+}
+
+impl fmt::Display for ResumeMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "resumes={} recovered={}",
+            self.resumes.load(Ordering::Relaxed),
+            self.recovered.load(Ordering::Relaxed)
+        )
+    }
+}
+
+/// `shard_count` 個のシャードのうち, `shard_id` のシャードが受け持つサーバー
+///
+/// Discord はサーバーの ID の上位ビット (作成日時) をシャードの数で割った余りでシャードを決めます。
+pub fn guilds_in_shard(
+    guild_ids: impl IntoIterator<Item = GuildId>,
+    shard_id: u32,
+    shard_count: u32,
+) -> Vec<GuildId> {
+    let shard_count = u64::from(shard_count.max(1));
+    guild_ids
+        .into_iter()
+        .filter(|guild_id| (guild_id.get() >> 22) % shard_count == u64::from(shard_id))
+        .collect()
+}
+
+/// キャッシュした参加人数と数え直した参加人数から, 取りこぼしていた参加者の数を返します
+///
+/// キャッシュした人数はリアクションを外しても減らさないため, 数え直した人数が少ない場合は 0 とします。
+pub fn recovered_count(cached: usize, counted: usize) -> usize {
+    counted.saturating_sub(cached)
+}
+
+/// シャードが再接続したときに, そのシャードのサーバーの受付中の募集を数え直します
+///
+/// 再接続している間に追加されたリアクションのイベントは届かないことがあるため,
+/// 参加人数のキャッシュの有効期間が過ぎるのを待たずに数え直し, 人数が揃っていれば開始します。
+/// REST API の呼び出しを抑えるため, この起動中に参加人数を数えた募集だけを対象にします。
+pub fn handle_resume(ctx: &Context, data: &Data) {
+    data.resume_metrics.resumes.fetch_add(1, Ordering::Relaxed);
+    let guild_ids = guilds_in_shard(ctx.cache.guilds(), ctx.shard_id.0, ctx.cache.shard_count());
+    eprintln!(
+        "Shard {} resumed, recounting open recruits in {} guilds",
+        ctx.shard_id,
+        guild_ids.len()
+    );
+    let ctx = ctx.clone();
+    let data = data.clone();
+    tokio::spawn(async move {
+        let mut recovered = 0;
+        for guild_id in guild_ids {
+            for (message_id, channel_id) in data.storage.recruit_locations_in_guild(guild_id) {
+                let Some(cached) = participant_count::last_count(&data, message_id) else {
+                    continue;
+                };
+                let result = data
+                    .rest_budget
+                    .run(recount(
+                        &ctx, &data, guild_id, channel_id, message_id, cached,
+                    ))
+                    .await;
+                match result {
+                    Ok(count) => recovered += count,
+                    Err(err) => eprintln!("Failed to recount {message_id} after resume: {err}"),
+                }
+            }
+        }
+        data.resume_metrics
+            .recovered
+            .fetch_add(recovered, Ordering::Relaxed);
+        eprintln!("Recovered {recovered} participants after resume");
+    });
+}
+
+/// 募集の参加者を数え直し, 取りこぼしていた参加者の数を返します
+async fn recount(
+    ctx: &Context,
+    data: &Data,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    cached: usize,
+) -> Result<usize, Error> {
+    let _guard = data.lock_message(message_id).await;
+    let message = match channel_id.message(ctx, message_id).await {
+        Ok(message) => message,
+        Err(err) if is_unknown_entity(&err).is_some() => {
+            data.forget_deleted_message(message_id);
+            return Ok(0);
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let recruit_message = config_storage::load(data, &message)?;
+    let state = &recruit_message.state;
+    let mut config = recruit_message.config;
+    // 複数のゲームの募集と枠のある募集は選択肢ごとのリアクションで開始するため, 数え直しません
+    if state.cancelled
+        || state.closed_entries.is_some()
+        || (state.started && (config.closes_on_start() || config.start_time.is_some()))
+        || config.has_option_reactions()
+    {
+        return Ok(0);
+    }
+    i18n::resolve_language(data, &mut config, Some(guild_id));
+    let user_ids = collect_participants(ctx, data, &message, &config, Some(guild_id)).await?;
+    participant_count::store_count(data, message_id, user_ids.len());
+    let recovered = recovered_count(cached, user_ids.len());
+    if recovered == 0 {
+        return Ok(0);
+    }
+    eprintln!("Recovered {recovered} participants of {message_id} after resume");
+    // 取りこぼしたリアクションで開始人数に届いた場合だけ開始します (届いていた募集は開始済みです)
+    if cached < config.required_players && config.required_players <= user_ids.len() {
+        begin_start(ctx, data, &config, &message, Some(guild_id), user_ids).await?;
+    }
+    Ok(recovered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guilds_are_assigned_to_shards_by_id() {
+        let guild_ids: Vec<GuildId> = (0..4).map(|shard| GuildId::new(shard << 22 | 1)).collect();
+        assert_eq!(
+            guilds_in_shard(guild_ids.clone(), 1, 2),
+            [guild_ids[1], guild_ids[3]]
+        );
+        // シャードの数が分からない場合は, 1 つのシャードとします
+        assert_eq!(guilds_in_shard(guild_ids.clone(), 0, 0), guild_ids);
+    }
+
+    #[test]
+    fn only_added_participants_are_recovered() {
+        assert_eq!(recovered_count(2, 5), 3);
+        assert_eq!(recovered_count(5, 2), 0);
+    }
+}
@@ -0,0 +1,2254 @@
+use poise::serenity_prelude::GuildId;
+use serde::{Deserialize, Serialize};
+
+use crate::Data;
+use crate::recruit_message::RecruitConfig;
+
+/// 募集メッセージと, 募集についての通知に使う言語
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, poise::ChoiceParameter,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    #[name = "日本語"]
+    Ja,
+    #[name = "English"]
+    En,
+}
+
+/// 募集メッセージと, 募集についての通知の文言
+#[derive(Debug, Clone, Copy)]
+pub enum Text<'a> {
+    /// 「(3/5, あと2人)」の形式の参加人数
+    Progress {
+        count: usize,
+        required_players: usize,
+    },
+    Joined {
+        user: &'a str,
+        game_title: &'a str,
+        progress: &'a str,
+    },
+    /// 枠のある募集で, 枠に参加したときの通知 (`progress` はその枠の人数)
+    JoinedSlot {
+        user: &'a str,
+        game_title: &'a str,
+        slot: &'a str,
+        progress: &'a str,
+    },
+    Starting {
+        mentions: &'a str,
+        game_title: &'a str,
+    },
+    Gathered {
+        mentions: &'a str,
+        game_title: &'a str,
+        start_time: &'a str,
+    },
+    /// 主催者を見出しにした埋め込みに入れる, メンションを含まない `Starting`
+    StartingEmbed {
+        game_title: &'a str,
+    },
+    /// 主催者を見出しにした埋め込みに入れる, メンションを含まない `Gathered`
+    GatheredEmbed {
+        game_title: &'a str,
+        start_time: &'a str,
+    },
+    RolePingCoolingDown,
+    /// サイレント時間帯にメンションせずに送った通知に添える文
+    QuietHoursNote,
+    /// サイレント時間帯が終わってから送る, 人数が揃った開始予定の日時がある募集のお知らせ
+    DeferredRolePing {
+        game_title: &'a str,
+        start_time: &'a str,
+        link: &'a str,
+    },
+    /// `cleanup_mode = "stub"` で, 返信された通知を削除する代わりに書き換える本文
+    CleanedUpNotice,
+    /// `/recruit poll` の投票のメッセージ
+    TimePoll {
+        game_title: &'a str,
+        required_players: usize,
+        candidates: &'a str,
+        deadline: i64,
+    },
+    TimePollCandidate {
+        number: usize,
+        start_time: &'a str,
+        votes: usize,
+    },
+    TimePollClosed,
+    TimePollDecided {
+        start_time: &'a str,
+        link: &'a str,
+    },
+    TimePollNoVotes,
+    /// 投票で決まった募集の説明
+    TimePollVoters {
+        mentions: &'a str,
+    },
+    /// 開始予定の日時までに人数が揃わなかった募集のお知らせ
+    RecruitExpired {
+        game_title: &'a str,
+        count: usize,
+        required_players: usize,
+    },
+    /// 人数が揃わなかった募集にリアクションしていた参加者
+    RecruitExpiredParticipants {
+        mentions: &'a str,
+    },
+    /// 募集開始 (繰り返しの募集では前回の開始) から人数が揃うまでの時間
+    FilledIn {
+        seconds: i64,
+    },
+    ConfigReadFailed,
+    RoleAssignFailed,
+    /// サーバー設定 `error_channel` に送る, 誰のどの募集のエラーかを添えたエラー
+    ErrorReport {
+        user: &'a str,
+        link: &'a str,
+        error: &'a str,
+    },
+    OneMoreNeeded {
+        game_title: &'a str,
+    },
+    OverCapacity {
+        game_title: &'a str,
+    },
+    EntriesClosed {
+        game_title: &'a str,
+    },
+    AccountTooNew {
+        game_title: &'a str,
+        days: u64,
+    },
+    MembershipTooNew {
+        game_title: &'a str,
+        days: u64,
+    },
+    MembershipPending {
+        game_title: &'a str,
+    },
+    FlaggedSpammer {
+        game_title: &'a str,
+    },
+    /// 開始通知に添える, 定員より多く集まったことのお知らせ
+    Oversubscribed {
+        capacity: usize,
+        reacted: usize,
+    },
+    Substitutes {
+        mentions: &'a str,
+    },
+    LobbyStarting {
+        number: usize,
+        mentions: &'a str,
+        game_title: &'a str,
+    },
+    Checking,
+    CountdownStarted {
+        game_title: &'a str,
+        start_at: i64,
+    },
+    SnoozeButton {
+        minutes: u64,
+    },
+    Snoozed {
+        game_title: &'a str,
+        minutes: u64,
+        start_at: i64,
+    },
+    SnoozeNotOrganizer,
+    SnoozeLimit {
+        max_minutes: u64,
+    },
+    SnoozeClosed,
+    PriorityDeferred {
+        user: &'a str,
+        role: &'a str,
+        until: i64,
+    },
+    PriorityWindowEnded {
+        game_title: &'a str,
+    },
+    CountdownAborted {
+        game_title: &'a str,
+        count: usize,
+        required_players: usize,
+    },
+    CountdownWithdrawn {
+        names: &'a str,
+        game_title: &'a str,
+        count: usize,
+        required_players: usize,
+    },
+    StartingSoon {
+        game_title: &'a str,
+    },
+    RollCall {
+        mentions: &'a str,
+        game_title: &'a str,
+        deadline: i64,
+    },
+    RollCallButton,
+    RollCallConfirmed {
+        count: usize,
+        required_players: usize,
+    },
+    RollCallNotTarget,
+    RollCallClosed,
+    RollCallFailed {
+        game_title: &'a str,
+        count: usize,
+        required_players: usize,
+    },
+    DroppedOut {
+        user: &'a str,
+        game_title: &'a str,
+    },
+    Replacement {
+        user: &'a str,
+        dropped: &'a str,
+        game_title: &'a str,
+    },
+    LegendHeader {
+        game_title: &'a str,
+    },
+    LegendMultiGameHeader,
+    LegendCategory {
+        category: &'a str,
+    },
+    LegendGameOption {
+        emoji: &'a str,
+        game_title: &'a str,
+        required_players: usize,
+    },
+    LegendMultiGameRule,
+    LegendSlotHeader {
+        game_title: &'a str,
+    },
+    LegendSlot {
+        emoji: &'a str,
+        name: &'a str,
+        count: usize,
+    },
+    LegendSlotRule,
+    LegendParticipation {
+        emoji: &'a str,
+    },
+    LegendSilentParticipation {
+        emoji: &'a str,
+    },
+    LegendStart {
+        emoji: &'a str,
+    },
+    /// ボタンで参加を受け付ける募集の説明
+    LegendButtonsHeader {
+        game_title: &'a str,
+    },
+    LegendButtons,
+    /// 応募が必要な募集 (`/recruit apply`) の説明
+    LegendApplication {
+        question: &'a str,
+    },
+    ButtonJoin,
+    ButtonLeave,
+    LegendAutoAssignRole {
+        role: &'a str,
+    },
+    LegendPresetParticipants {
+        mentions: &'a str,
+    },
+    LegendMaxPlayers {
+        max_players: usize,
+    },
+    LegendStrictOrder,
+    LegendMultiLobby {
+        required_players: usize,
+    },
+    LegendStartTime {
+        start_time: &'a str,
+    },
+    LegendRemindBefore {
+        minutes: u64,
+    },
+    LegendRollCall {
+        minutes: u64,
+    },
+    LegendPriority {
+        role: &'a str,
+        until: i64,
+    },
+    LegendPriorityEnded,
+    LegendStartDelay {
+        minutes: u64,
+    },
+    LegendVoiceChannel {
+        channel: &'a str,
+    },
+    LegendVoiceRequired {
+        channel: &'a str,
+        minutes: u64,
+    },
+    /// VC にいる参加者が足りず, 開始を保留したときの呼びかけ
+    VoiceHold {
+        mentions: &'a str,
+        channel: &'a str,
+        required_players: usize,
+        deadline: i64,
+    },
+    VoiceHoldExpired {
+        game_title: &'a str,
+        channel: &'a str,
+    },
+    /// 開始通知に添える, VC にいなかったため見送った参加者
+    VoiceLeftOut {
+        mentions: &'a str,
+    },
+    LegendExtra {
+        emoji: &'a str,
+        label: &'a str,
+        counts: bool,
+    },
+    LegendSimulation,
+    StateParticipantCount {
+        count: usize,
+        required_players: usize,
+    },
+    StateDeadline {
+        deadline: i64,
+    },
+    StateCancelled,
+    StateExpired,
+    StateEntriesClosed {
+        count: usize,
+        participants: &'a str,
+    },
+    StateStartedWith {
+        winner: &'a str,
+    },
+    StateClosedScheduled,
+    StateStarted,
+}
+
+impl Language {
+    /// スラッシュコマンドの説明をこの言語で表示する Discord のロケール
+    pub fn discord_locales(self) -> &'static [&'static str] {
+        match self {
+            Language::Ja => &["ja"],
+            Language::En => &["en-US", "en-GB"],
+        }
+    }
+
+    /// スラッシュコマンドとパラメーターの説明 (日本語は `#[description]` を使うため `None`)
+    ///
+    /// キーはサブコマンドを空白で区切ったコマンドの名前で, パラメーターは `コマンド.パラメーター` とします。
+    pub fn command_description(self, key: &str) -> Option<(&'static str, &'static str)> {
+        self.command_descriptions()
+            .iter()
+            .find(|(entry, _)| *entry == key)
+            .copied()
+    }
+
+    /// 説明を用意したスラッシュコマンドとパラメーターのキー
+    pub fn command_description_keys(self) -> impl Iterator<Item = &'static str> {
+        self.command_descriptions().iter().map(|&(key, _)| key)
+    }
+
+    fn command_descriptions(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::Ja => &[],
+            Language::En => EN_COMMAND_DESCRIPTIONS,
+        }
+    }
+
+    /// パラメーターの選択肢の名前 (`name` は日本語の名前)
+    pub fn choice_name(self, name: &str) -> Option<&'static str> {
+        match self {
+            Language::Ja => None,
+            Language::En => EN_CHOICE_NAMES
+                .iter()
+                .find(|(entry, _)| *entry == name)
+                .map(|&(_, en)| en),
+        }
+    }
+
+    /// 募集設定やサーバー設定に書き込む言語のコード
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::Ja => "ja",
+            Language::En => "en",
+        }
+    }
+
+    pub fn text(self, text: Text) -> String {
+        match self {
+            Language::Ja => ja(text),
+            Language::En => en(text),
+        }
+    }
+}
+
+/// 秒数を (日, 時間, 分) に分けます (秒は切り捨てます)
+fn split_duration(seconds: i64) -> (i64, i64, i64) {
+    let minutes = seconds / 60;
+    (minutes / (24 * 60), minutes / 60 % 24, minutes % 60)
+}
+
+fn ja(text: Text) -> String {
+    match text {
+        Text::Progress {
+            count,
+            required_players,
+        } if count < required_players => format!(
+            "({count}/{required_players}, あと{}人)",
+            required_players - count
+        ),
+        Text::Progress {
+            count,
+            required_players,
+        } => format!("({count}/{required_players})"),
+        Text::Joined {
+            user,
+            game_title,
+            progress,
+        } => format!("{user} が {game_title} に参加しました {progress}"),
+        Text::JoinedSlot {
+            user,
+            game_title,
+            slot,
+            progress,
+        } => format!("{user} が {game_title} の {slot} に参加しました {progress}"),
+        Text::Starting {
+            mentions,
+            game_title,
+        } => format!("{mentions} が {game_title} を開始します"),
+        Text::Gathered {
+            mentions,
+            game_title,
+            start_time,
+        } => format!("{mentions} で {game_title} の参加者が揃いました (開始予定: {start_time})"),
+        Text::StartingEmbed { game_title } => format!("{game_title} を開始します"),
+        Text::GatheredEmbed {
+            game_title,
+            start_time,
+        } => format!("{game_title} の参加者が揃いました (開始予定: {start_time})"),
+        Text::RolePingCoolingDown => " (ロール通知はクールダウン中)".to_string(),
+        Text::QuietHoursNote => "(サイレント時間帯のため通知なし)".to_string(),
+        Text::DeferredRolePing {
+            game_title,
+            start_time,
+            link,
+        } => format!("{game_title} の参加者が揃いました (開始予定: {start_time}) {link}"),
+        Text::CleanedUpNotice => "(通知は整理されました)".to_string(),
+        Text::TimePoll {
+            game_title,
+            required_players,
+            candidates,
+            deadline,
+        } => format!(
+            "🗳️ {game_title} ({required_players}人) をいつ遊ぶか投票してください。都合のよい日時の番号を押してください (複数選べます、もう一度押すと取り消します)\n{candidates}\n<t:{deadline}:R> に締め切り、最も票が多い日時で募集します"
+        ),
+        Text::TimePollCandidate {
+            number,
+            start_time,
+            votes,
+        } => format!("{number}. {start_time} … {votes}票"),
+        Text::TimePollClosed => "この投票は締め切られました".to_string(),
+        Text::TimePollDecided { start_time, link } => {
+            format!("投票の結果、{start_time} に決まりました {link}")
+        }
+        Text::TimePollNoVotes => "投票がなかったため、募集しませんでした".to_string(),
+        Text::TimePollVoters { mentions } => format!("日時の投票に参加した人: {mentions}"),
+        Text::RecruitExpired {
+            game_title,
+            count,
+            required_players,
+        } => format!(
+            "{game_title} の募集は開始予定の日時までに人数が揃わなかったため、不成立になりました ({count}/{required_players})"
+        ),
+        Text::RecruitExpiredParticipants { mentions } => {
+            format!("参加していた人: {mentions}")
+        }
+        Text::FilledIn { seconds } if seconds < 60 => {
+            "募集開始から1分以内に集まりました".to_string()
+        }
+        Text::FilledIn { seconds } => {
+            let (days, hours, minutes) = split_duration(seconds);
+            let elapsed = match (days, hours) {
+                (0, 0) => format!("{minutes}分"),
+                (0, _) => format!("{hours}時間{minutes}分"),
+                _ => format!("{days}日{hours}時間"),
+            };
+            format!("募集開始から{elapsed}で集まりました")
+        }
+        Text::ConfigReadFailed => {
+            "募集設定の読み取りに失敗しました。募集メッセージを作り直してください。".to_string()
+        }
+        Text::RoleAssignFailed => {
+            "ロールの付与に失敗しました。権限を確認してください。".to_string()
+        }
+        Text::ErrorReport { user, link, error } => {
+            format!("{user} さんへのエラー ({link}): {error}")
+        }
+        Text::OneMoreNeeded { game_title } => format!("「{game_title}」あと1人です"),
+        Text::OverCapacity { game_title } => {
+            format!("{game_title} は定員に達しているため参加できません")
+        }
+        Text::EntriesClosed { game_title } => {
+            format!("{game_title} は受付を終了したため参加できません")
+        }
+        Text::AccountTooNew { game_title, days } => {
+            format!("{game_title} はアカウントの作成から{days}日経たないと参加できません")
+        }
+        Text::MembershipTooNew { game_title, days } => {
+            format!("{game_title} はサーバーへの参加から{days}日経たないと参加できません")
+        }
+        Text::MembershipPending { game_title } => {
+            format!("{game_title} はサーバーのメンバー確認を済ませてから参加してください")
+        }
+        Text::FlaggedSpammer { game_title } => format!(
+            "Discord によってスパムの疑いがあるとされたアカウントは {game_title} に参加できません"
+        ),
+        Text::Oversubscribed { capacity, reacted } => {
+            format!("必要人数{capacity}人に対して{reacted}人が反応しています")
+        }
+        Text::Substitutes { mentions } => format!("補欠: {mentions}"),
+        Text::LobbyStarting {
+            number,
+            mentions,
+            game_title,
+        } => format!("ロビー{number}: {mentions} が {game_title} を開始します"),
+        Text::Checking => "確認中…".to_string(),
+        Text::CountdownStarted {
+            game_title,
+            start_at,
+        } => format!("{game_title} の参加者が揃いました。<t:{start_at}:R> に開始します"),
+        Text::SnoozeButton { minutes } => format!("{minutes}分延期"),
+        Text::Snoozed {
+            game_title,
+            minutes,
+            start_at,
+        } => {
+            format!("{game_title} の開始を {minutes} 分延期しました。<t:{start_at}:R> に開始します")
+        }
+        Text::SnoozeNotOrganizer => "開始を延期できるのは主催者だけです".to_string(),
+        Text::SnoozeLimit { max_minutes } => {
+            format!("これ以上は延期できません (合計 {max_minutes} 分まで)")
+        }
+        Text::SnoozeClosed => "この募集のカウントダウンは終了しています".to_string(),
+        Text::PriorityDeferred { user, role, until } => format!(
+            "{user} 優先期間中は {role} のメンバーが先に参加できます。<t:{until}:R> から参加者として数えます"
+        ),
+        Text::PriorityWindowEnded { game_title } => {
+            format!("{game_title} の優先期間が終わりました。誰でも参加できます")
+        }
+        Text::CountdownAborted {
+            game_title,
+            count,
+            required_players,
+        } => format!(
+            "参加者が減ったため {game_title} の開始を中止しました (現在 {count}/{required_players}人)"
+        ),
+        Text::CountdownWithdrawn {
+            names,
+            game_title,
+            count,
+            required_players,
+        } => format!(
+            "{names} が参加を取り消したため {game_title} の開始を中止しました (現在 {count}/{required_players}人)"
+        ),
+        Text::StartingSoon { game_title } => format!("まもなく「{game_title}」が始まります"),
+        Text::RollCall {
+            mentions,
+            game_title,
+            deadline,
+        } => format!(
+            "{mentions}\n{game_title} の参加者が揃いました。参加できる方は <t:{deadline}:R> までに ✅ を押してください"
+        ),
+        Text::RollCallButton => "参加できます".to_string(),
+        Text::RollCallConfirmed {
+            count,
+            required_players,
+        } => format!("参加を確認しました (現在 {count}/{required_players}人)"),
+        Text::RollCallNotTarget => "点呼の対象は人数が揃った時点の参加者だけです".to_string(),
+        Text::RollCallClosed => "この点呼は終了しています".to_string(),
+        Text::RollCallFailed {
+            game_title,
+            count,
+            required_players,
+        } => format!(
+            "{game_title} の確認が揃いませんでした (確認 {count}/{required_players}人)。募集は引き続き受け付けます"
+        ),
+        Text::DroppedOut { user, game_title } => {
+            format!("{user} さんが {game_title} に参加できなくなりました")
+        }
+        Text::Replacement {
+            user,
+            dropped,
+            game_title,
+        } => format!("{user} {dropped} さんが抜けたため, {game_title} に繰り上げで参加できます"),
+        Text::LegendHeader { game_title } => {
+            format!("このメッセージにリアクションをつけると {game_title} に参加できます")
+        }
+        Text::LegendMultiGameHeader => {
+            "このメッセージにリアクションをつけて参加するゲームを選べます (複数選べます)"
+                .to_string()
+        }
+        Text::LegendCategory { category } => format!("カテゴリ: {category}"),
+        Text::LegendGameOption {
+            emoji,
+            game_title,
+            required_players,
+        } => format!("{emoji}: {game_title} ({required_players}人で開始)"),
+        Text::LegendMultiGameRule => {
+            "最初に人数が揃ったゲームで開始し, 募集を締め切ります".to_string()
+        }
+        Text::LegendSlotHeader { game_title } => {
+            format!("このメッセージに枠のリアクションをつけると {game_title} に参加できます (1 人 1 枠まで)")
+        }
+        Text::LegendSlot { emoji, name, count } => format!("{emoji}: {name} ({count}人)"),
+        Text::LegendSlotRule => {
+            "すべての枠が埋まると開始し, 募集を締め切ります (枠を変えるときは, 今の枠のリアクションを外してから付けてください)"
+                .to_string()
+        }
+        Text::LegendParticipation { emoji } => format!("{emoji}: 参加"),
+        Text::LegendSilentParticipation { emoji } => format!("{emoji}: 参加通知なしで参加"),
+        Text::LegendStart { emoji } => format!("{emoji}: 人数が揃っていなくても開始"),
+        Text::LegendButtonsHeader { game_title } => {
+            format!("下のボタンを押すと {game_title} に参加できます")
+        }
+        Text::LegendButtons => "「参加をやめる」で参加を取り消せます".to_string(),
+        Text::LegendApplication { question } => {
+            format!("「参加する」で「{question}」に答えて応募し、主催者が承認すると参加者になります")
+        }
+        Text::ButtonJoin => "参加する".to_string(),
+        Text::ButtonLeave => "参加をやめる".to_string(),
+        Text::LegendAutoAssignRole { role } => format!("リアクションすると {role} が付与されます"),
+        Text::LegendMaxPlayers { max_players } => format!("定員: {max_players}人"),
+        Text::LegendStrictOrder => "リアクションした順に先着で参加者を決めます".to_string(),
+        Text::LegendMultiLobby { required_players } => {
+            format!("{required_players}人集まるごとにロビーを分けて開始します")
+        }
+        Text::LegendPresetParticipants { mentions } => format!("参加済み: {mentions}"),
+        Text::LegendStartTime { start_time } => format!("開始予定: {start_time}"),
+        Text::LegendRemindBefore { minutes } => {
+            format!("人数が揃ったら, 開始予定の {minutes} 分前に参加者へ DM でお知らせします")
+        }
+        Text::LegendRollCall { minutes } => {
+            format!("人数が揃ったら参加者の点呼をとり, {minutes} 分以内に確認が揃えば開始します")
+        }
+        Text::LegendPriority { role, until } => {
+            format!("<t:{until}:R> までは {role} のメンバーが優先して参加できます")
+        }
+        Text::LegendPriorityEnded => "優先期間は終わりました。誰でも参加できます".to_string(),
+        Text::LegendStartDelay { minutes } => {
+            format!("人数が揃ってから {minutes} 分後に開始します")
+        }
+        Text::LegendVoiceChannel { channel } => {
+            format!("{channel} に接続中のメンバーも参加者に数えます")
+        }
+        Text::LegendVoiceRequired { channel, minutes } => format!(
+            "開始時に {channel} にいる参加者だけで開始します (足りなければ{minutes}分待ちます)"
+        ),
+        Text::VoiceHold {
+            mentions,
+            channel,
+            required_players,
+            deadline,
+        } => format!(
+            "{mentions} {channel} に入ってください。<t:{deadline}:R> までに VC にいる参加者が {required_players} 人揃えば開始します"
+        ),
+        Text::VoiceHoldExpired {
+            game_title,
+            channel,
+        } => format!("{channel} に参加者が揃わなかったため、{game_title} の開始を見送りました"),
+        Text::VoiceLeftOut { mentions } => {
+            format!("VCに入っていなかったため今回は見送り: {mentions}")
+        }
+        Text::LegendExtra {
+            emoji,
+            label,
+            counts: true,
+        } => format!("{emoji}: {label}"),
+        Text::LegendExtra {
+            emoji,
+            label,
+            counts: false,
+        } => format!("{emoji}: {label} (人数に数えません)"),
+        Text::LegendSimulation => {
+            "これは動作確認用の募集です (通知ではメンションしません)".to_string()
+        }
+        Text::StateParticipantCount {
+            count,
+            required_players,
+        } => format!("参加人数: {count}/{required_players}"),
+        Text::StateDeadline { deadline } => format!("締切: <t:{deadline}:f>"),
+        Text::StateCancelled => "この募集はキャンセルされました".to_string(),
+        Text::StateExpired => {
+            "この募集は開始予定の日時までに人数が揃わず、不成立になりました".to_string()
+        }
+        Text::StateEntriesClosed {
+            count,
+            participants,
+        } => format!("受付終了 (参加者 {count} 人) {participants}"),
+        Text::StateStartedWith { winner } => format!("この募集は {winner} で開始しました"),
+        Text::StateClosedScheduled => "人数が揃ったため, この募集は締め切りました".to_string(),
+        Text::StateStarted => "この募集は開始しました".to_string(),
+    }
+}
+
+fn en(text: Text) -> String {
+    match text {
+        Text::Progress {
+            count,
+            required_players,
+        } if count < required_players => format!(
+            "({count}/{required_players}, {} more needed)",
+            required_players - count
+        ),
+        Text::Progress {
+            count,
+            required_players,
+        } => format!("({count}/{required_players})"),
+        Text::Joined {
+            user,
+            game_title,
+            progress,
+        } => format!("{user} joined {game_title} {progress}"),
+        Text::JoinedSlot {
+            user,
+            game_title,
+            slot,
+            progress,
+        } => format!("{user} joined {game_title} as {slot} {progress}"),
+        Text::Starting {
+            mentions,
+            game_title,
+        } => format!("{mentions} are starting {game_title}"),
+        Text::Gathered {
+            mentions,
+            game_title,
+            start_time,
+        } => format!(
+            "{mentions}: enough players have joined {game_title} (scheduled start: {start_time})"
+        ),
+        Text::StartingEmbed { game_title } => format!("Starting {game_title}"),
+        Text::GatheredEmbed {
+            game_title,
+            start_time,
+        } => format!("Enough players have joined {game_title} (scheduled start: {start_time})"),
+        Text::RolePingCoolingDown => " (role ping is on cooldown)".to_string(),
+        Text::QuietHoursNote => "(sent without notifications during quiet hours)".to_string(),
+        Text::DeferredRolePing {
+            game_title,
+            start_time,
+            link,
+        } => format!(
+            "Enough players have joined {game_title} (scheduled start: {start_time}) {link}"
+        ),
+        Text::CleanedUpNotice => "(This notice was cleaned up)".to_string(),
+        Text::TimePoll {
+            game_title,
+            required_players,
+            candidates,
+            deadline,
+        } => format!(
+            "🗳️ When should we play {game_title} ({required_players} players)? Press the numbers of the times that work for you (you can pick several; press again to undo)\n{candidates}\nVoting closes <t:{deadline}:R> and the recruit is posted for the time with the most votes"
+        ),
+        Text::TimePollCandidate {
+            number,
+            start_time,
+            votes,
+        } => format!("{number}. {start_time} … {votes} votes"),
+        Text::TimePollClosed => "This poll is closed".to_string(),
+        Text::TimePollDecided { start_time, link } => {
+            format!("The poll picked {start_time} {link}")
+        }
+        Text::TimePollNoVotes => "Nobody voted, so no recruit was posted".to_string(),
+        Text::TimePollVoters { mentions } => format!("Voted in the time poll: {mentions}"),
+        Text::RecruitExpired {
+            game_title,
+            count,
+            required_players,
+        } => format!(
+            "The recruit for {game_title} did not fill by its start time and is off ({count}/{required_players})"
+        ),
+        Text::RecruitExpiredParticipants { mentions } => format!("Had joined: {mentions}"),
+        Text::FilledIn { seconds } if seconds < 60 => "Filled within a minute".to_string(),
+        Text::FilledIn { seconds } => {
+            let (days, hours, minutes) = split_duration(seconds);
+            let elapsed = match (days, hours) {
+                (0, 0) => format!("{minutes}m"),
+                (0, _) => format!("{hours}h {minutes}m"),
+                _ => format!("{days}d {hours}h"),
+            };
+            format!("Filled in {elapsed}")
+        }
+        Text::ConfigReadFailed => {
+            "Failed to read the recruit settings. Please create the recruit again.".to_string()
+        }
+        Text::RoleAssignFailed => {
+            "Failed to assign the role. Please check the bot's permissions.".to_string()
+        }
+        Text::ErrorReport { user, link, error } => format!("Error for {user} ({link}): {error}"),
+        Text::OneMoreNeeded { game_title } => format!("\"{game_title}\" needs one more player"),
+        Text::OverCapacity { game_title } => {
+            format!("{game_title} is full, so you cannot join")
+        }
+        Text::EntriesClosed { game_title } => {
+            format!("{game_title} is closed for entries, so you cannot join")
+        }
+        Text::AccountTooNew { game_title, days } => format!(
+            "Your account must be at least {days} days old to join {game_title}"
+        ),
+        Text::MembershipTooNew { game_title, days } => format!(
+            "You must have been in this server for at least {days} days to join {game_title}"
+        ),
+        Text::MembershipPending { game_title } => format!(
+            "Complete this server's membership screening before joining {game_title}"
+        ),
+        Text::FlaggedSpammer { game_title } => format!(
+            "Accounts flagged by Discord as likely spammers cannot join {game_title}"
+        ),
+        Text::Oversubscribed { capacity, reacted } => {
+            format!("{reacted} players reacted for {capacity} spots")
+        }
+        Text::Substitutes { mentions } => format!("Substitutes: {mentions}"),
+        Text::LobbyStarting {
+            number,
+            mentions,
+            game_title,
+        } => format!("Lobby {number}: {mentions} are starting {game_title}"),
+        Text::Checking => "Checking…".to_string(),
+        Text::CountdownStarted {
+            game_title,
+            start_at,
+        } => format!("Enough players have joined {game_title}. Starting <t:{start_at}:R>"),
+        Text::SnoozeButton { minutes } => format!("Snooze {minutes} min"),
+        Text::Snoozed {
+            game_title,
+            minutes,
+            start_at,
+        } => format!("{game_title} was snoozed by {minutes} min. Starting <t:{start_at}:R>"),
+        Text::SnoozeNotOrganizer => "Only the organizer can snooze the start".to_string(),
+        Text::SnoozeLimit { max_minutes } => {
+            format!("The start cannot be snoozed any further (up to {max_minutes} min in total)")
+        }
+        Text::SnoozeClosed => "The countdown for this recruit is over".to_string(),
+        Text::PriorityDeferred { user, role, until } => format!(
+            "{user} Members of {role} get priority for now. You will be counted <t:{until}:R>"
+        ),
+        Text::PriorityWindowEnded { game_title } => {
+            format!("The priority window for {game_title} is over. Anyone can join")
+        }
+        Text::CountdownAborted {
+            game_title,
+            count,
+            required_players,
+        } => format!(
+            "Cancelled the start of {game_title} because players left (now {count}/{required_players})"
+        ),
+        Text::CountdownWithdrawn {
+            names,
+            game_title,
+            count,
+            required_players,
+        } => format!(
+            "Cancelled the start of {game_title} because {names} left (now {count}/{required_players})"
+        ),
+        Text::StartingSoon { game_title } => format!("\"{game_title}\" is starting soon"),
+        Text::RollCall {
+            mentions,
+            game_title,
+            deadline,
+        } => format!(
+            "{mentions}\nEnough players have joined {game_title}. If you can play, press ✅ <t:{deadline}:R>"
+        ),
+        Text::RollCallButton => "I'm in".to_string(),
+        Text::RollCallConfirmed {
+            count,
+            required_players,
+        } => format!("You are confirmed (now {count}/{required_players})"),
+        Text::RollCallNotTarget => {
+            "Only players who had joined when the roll call started can confirm".to_string()
+        }
+        Text::RollCallClosed => "This roll call has ended".to_string(),
+        Text::RollCallFailed {
+            game_title,
+            count,
+            required_players,
+        } => format!(
+            "Not enough players confirmed {game_title} (confirmed {count}/{required_players}). The recruit stays open"
+        ),
+        Text::DroppedOut { user, game_title } => {
+            format!("{user} can no longer join {game_title}")
+        }
+        Text::Replacement {
+            user,
+            dropped,
+            game_title,
+        } => format!("{user} {dropped} dropped out, so you are now in for {game_title}"),
+        Text::LegendHeader { game_title } => {
+            format!("React to this message to join {game_title}")
+        }
+        Text::LegendMultiGameHeader => {
+            "React to this message to choose the games you want to play (you can choose more than one)"
+                .to_string()
+        }
+        Text::LegendCategory { category } => format!("Category: {category}"),
+        Text::LegendGameOption {
+            emoji,
+            game_title,
+            required_players,
+        } => format!("{emoji}: {game_title} (starts with {required_players})"),
+        Text::LegendMultiGameRule => {
+            "Starts with the first game that has enough players, then closes".to_string()
+        }
+        Text::LegendSlotHeader { game_title } => {
+            format!("React to this message with a slot to join {game_title} (one slot per person)")
+        }
+        Text::LegendSlot { emoji, name, count } => format!("{emoji}: {name} ({count})"),
+        Text::LegendSlotRule => {
+            "Starts when every slot is full, then closes (to switch slots, remove your current reaction first)"
+                .to_string()
+        }
+        Text::LegendParticipation { emoji } => format!("{emoji}: Join"),
+        Text::LegendSilentParticipation { emoji } => {
+            format!("{emoji}: Join without a notification")
+        }
+        Text::LegendStart { emoji } => format!("{emoji}: Start even if not enough players"),
+        Text::LegendButtonsHeader { game_title } => {
+            format!("Press the button below to join {game_title}")
+        }
+        Text::LegendButtons => "Press Leave to drop out".to_string(),
+        Text::LegendApplication { question } => {
+            format!("Press Join to answer \"{question}\" and apply. You join once the organizer accepts")
+        }
+        Text::ButtonJoin => "Join".to_string(),
+        Text::ButtonLeave => "Leave".to_string(),
+        Text::LegendAutoAssignRole { role } => format!("Reacting gives you {role}"),
+        Text::LegendMaxPlayers { max_players } => format!("Capacity: {max_players}"),
+        Text::LegendStrictOrder => "Players are chosen first come, first served".to_string(),
+        Text::LegendMultiLobby { required_players } => {
+            format!("A new lobby starts every {required_players} players")
+        }
+        Text::LegendPresetParticipants { mentions } => format!("Already in: {mentions}"),
+        Text::LegendStartTime { start_time } => format!("Scheduled start: {start_time}"),
+        Text::LegendRemindBefore { minutes } => format!(
+            "Once enough players join, they get a DM {minutes} minutes before the scheduled start"
+        ),
+        Text::LegendRollCall { minutes } => format!(
+            "Once enough players join, they are asked to confirm within {minutes} minutes before the start"
+        ),
+        Text::LegendPriority { role, until } => {
+            format!("Members of {role} get priority until <t:{until}:R>")
+        }
+        Text::LegendPriorityEnded => "The priority window is over. Anyone can join".to_string(),
+        Text::LegendStartDelay { minutes } => {
+            format!("Starts {minutes} minutes after enough players join")
+        }
+        Text::LegendVoiceChannel { channel } => {
+            format!("Members connected to {channel} also count as players")
+        }
+        Text::LegendVoiceRequired { channel, minutes } => format!(
+            "Only players in {channel} at start time play (waits up to {minutes} min if short)"
+        ),
+        Text::VoiceHold {
+            mentions,
+            channel,
+            required_players,
+            deadline,
+        } => format!(
+            "{mentions} please join {channel}. The game starts if {required_players} players are in voice by <t:{deadline}:R>"
+        ),
+        Text::VoiceHoldExpired {
+            game_title,
+            channel,
+        } => format!("Not enough players joined {channel}, so {game_title} did not start"),
+        Text::VoiceLeftOut { mentions } => {
+            format!("Not in voice, so sitting this one out: {mentions}")
+        }
+        Text::LegendExtra {
+            emoji,
+            label,
+            counts: true,
+        } => format!("{emoji}: {label}"),
+        Text::LegendExtra {
+            emoji,
+            label,
+            counts: false,
+        } => format!("{emoji}: {label} (not counted)"),
+        Text::LegendSimulation => {
+            "This is a test recruit (notifications do not mention anyone)".to_string()
+        }
+        Text::StateParticipantCount {
+            count,
+            required_players,
+        } => format!("Players: {count}/{required_players}"),
+        Text::StateDeadline { deadline } => format!("Deadline: <t:{deadline}:f>"),
+        Text::StateCancelled => "This recruit was cancelled".to_string(),
+        Text::StateExpired => "This recruit did not fill by its start time".to_string(),
+        Text::StateEntriesClosed {
+            count,
+            participants,
+        } => format!("Entries closed ({count} participants) {participants}"),
+        Text::StateStartedWith { winner } => format!("This recruit started with {winner}"),
+        Text::StateClosedScheduled => {
+            "This recruit is closed because enough players have joined".to_string()
+        }
+        Text::StateStarted => "This recruit has started".to_string(),
+    }
+}
+
+/// コマンドとパラメーターの英語の説明
+///
+/// 日本語の説明は各コマンドの `#[description]` (ドキュメントコメント) を既定値とします。
+/// キーはサブコマンドを空白で区切ったコマンドの名前で, パラメーターは `コマンド.パラメーター` とします。
+const EN_COMMAND_DESCRIPTIONS: &[(&str, &str)] = &[
+    ("recruit", "Create and manage recruits"),
+    ("recruit create", "Post a recruit"),
+    ("recruit create.game_title", "Game to recruit for"),
+    (
+        "recruit create.required_players",
+        "Players needed to start (optional for games set in /settings game)",
+    ),
+    ("recruit create.max_players", "Maximum number of players"),
+    (
+        "recruit create.enforce_cap",
+        "Remove reactions over the maximum",
+    ),
+    ("recruit create.mention_role", "Role to mention on start"),
+    (
+        "recruit create.mention_role2",
+        "Second role to mention on start",
+    ),
+    (
+        "recruit create.mention_role3",
+        "Third role to mention on start",
+    ),
+    (
+        "recruit create.create_role",
+        "Create a role for this recruit",
+    ),
+    (
+        "recruit create.auto_assign_role_on_reaction",
+        "Give the role to members who react",
+    ),
+    (
+        "recruit create.notify_on_reaction",
+        "Send a notice when someone joins",
+    ),
+    (
+        "recruit create.delete_after_minutes",
+        "Minutes before notices are deleted",
+    ),
+    (
+        "recruit create.start_delay_minutes",
+        "Minutes to wait after enough players join",
+    ),
+    (
+        "recruit create.extra_reactions",
+        "Extra reactions (e.g. 👀=Watch, 🎥=Stream=count)",
+    ),
+    (
+        "recruit create.cleanup_after_start_minutes",
+        "Minutes after start before the recruit is deleted",
+    ),
+    (
+        "recruit create.notify_organizer_when_close",
+        "Notify the organizer when one more player is needed",
+    ),
+    (
+        "recruit create.strict_order",
+        "Accept the first max_players reactions in order",
+    ),
+    (
+        "recruit create.category",
+        "Category of the recruit (uses its defaults)",
+    ),
+    (
+        "recruit create.start_time",
+        "Scheduled start (e.g. 21:00, 2026-10-20 21:00); closes when full",
+    ),
+    (
+        "recruit create.language",
+        "Language of the recruit (default: server language)",
+    ),
+    (
+        "recruit create.description",
+        "Description of the recruit (e.g. rank range, up to 500 characters)",
+    ),
+    (
+        "recruit create.override_quiet_hours",
+        "Mention even during quiet hours (needs Manage Server)",
+    ),
+    (
+        "recruit create.preset_users",
+        "Members already in (up to 5 @mentions separated by spaces)",
+    ),
+    (
+        "recruit create.roll_call",
+        "Ask players to confirm before the start",
+    ),
+    (
+        "recruit create.priority_role",
+        "Role that can join during the priority window",
+    ),
+    (
+        "recruit create.priority_window",
+        "Length of the priority window (e.g. 30m, default: 30m)",
+    ),
+    (
+        "recruit voice",
+        "Post a recruit that also counts members in a voice channel",
+    ),
+    ("recruit voice.game_title", "Game to recruit for"),
+    (
+        "recruit voice.count_voice_channel",
+        "Voice channel whose members count as participants",
+    ),
+    (
+        "recruit voice.required_players",
+        "Players needed to start (optional for games set in /settings game)",
+    ),
+    (
+        "recruit voice.require_undeafened",
+        "Do not count deafened members in the voice channel",
+    ),
+    ("recruit voice.max_players", "Maximum number of players"),
+    ("recruit voice.mention_role", "Role to mention on start"),
+    (
+        "recruit voice.notify_on_reaction",
+        "Send a notice when someone joins",
+    ),
+    (
+        "recruit voice.start_delay_minutes",
+        "Minutes to wait after enough players join",
+    ),
+    (
+        "recruit voice.category",
+        "Category of the recruit (uses its defaults)",
+    ),
+    (
+        "recruit voice.start_time",
+        "Scheduled start (e.g. 21:00, 2026-10-20 21:00); closes when full",
+    ),
+    (
+        "recruit voice.language",
+        "Language of the recruit (default: server language)",
+    ),
+    (
+        "recruit test",
+        "Post a test recruit that mentions no one (admins only)",
+    ),
+    ("recruit test.game_title", "Game to recruit for"),
+    (
+        "recruit test.required_players",
+        "Players needed to start (optional for games set in /settings game)",
+    ),
+    ("recruit test.max_players", "Maximum number of players"),
+    (
+        "recruit test.mention_role",
+        "Role to mention on start (not actually mentioned)",
+    ),
+    (
+        "recruit test.start_delay_minutes",
+        "Minutes to wait after enough players join",
+    ),
+    (
+        "recruit test.category",
+        "Category of the recruit (uses its defaults)",
+    ),
+    (
+        "recruit test.start_time",
+        "Scheduled start (e.g. 21:00, 2026-10-20 21:00); closes when full",
+    ),
+    (
+        "recruit multi",
+        "Post a recruit for several games that starts whichever fills first",
+    ),
+    ("recruit multi.game_title", "First game"),
+    ("recruit multi.game_title_2", "Second game"),
+    (
+        "recruit multi.required_players_2",
+        "Players needed for the second game",
+    ),
+    (
+        "recruit multi.required_players",
+        "Players needed for the first game (optional for games set in /settings game)",
+    ),
+    ("recruit multi.game_title_3", "Third game"),
+    (
+        "recruit multi.required_players_3",
+        "Players needed for the third game",
+    ),
+    ("recruit multi.mention_role", "Role to mention on start"),
+    (
+        "recruit multi.mention_role2",
+        "Second role to mention on start",
+    ),
+    (
+        "recruit multi.notify_on_reaction",
+        "Send a notice when someone joins",
+    ),
+    (
+        "recruit multi.delete_after_minutes",
+        "Minutes before notices are deleted",
+    ),
+    (
+        "recruit multi.category",
+        "Category of the recruit (uses its defaults)",
+    ),
+    (
+        "recruit multi.language",
+        "Language of the recruit (default: server language)",
+    ),
+    (
+        "recruit multi.description",
+        "Description of the recruit (e.g. rank range, up to 500 characters)",
+    ),
+    ("transfer_recruit", "Change the organizer of a recruit"),
+    ("transfer_recruit.message", "Link to the recruit message"),
+    ("transfer_recruit.new_organizer", "New organizer"),
+    (
+        "recruit edit",
+        "Add a description to a recruit (omit to remove it)",
+    ),
+    ("recruit edit.message", "Link to the recruit message"),
+    (
+        "recruit edit.description",
+        "Description (rank, etc., up to 500 characters)",
+    ),
+    (
+        "close_entries",
+        "Close entries and keep the final participants on the message",
+    ),
+    ("close_entries.message", "Link to the recruit message"),
+    (
+        "close_entries.remove_late_reactions",
+        "Remove reactions added after entries close",
+    ),
+    ("close_entries.pin", "Pin the recruit message"),
+    (
+        "recruit participants",
+        "Register members already in the recruit (omit all to clear)",
+    ),
+    (
+        "recruit participants.message",
+        "Link to the recruit message",
+    ),
+    (
+        "recruit participants.preset_user",
+        "Member to register as a participant",
+    ),
+    ("recruit participants.preset_user2", "Second member"),
+    ("recruit participants.preset_user3", "Third member"),
+    ("recruit participants.preset_user4", "Fourth member"),
+    ("recruit participants.preset_user5", "Fifth member"),
+    ("join", "Join a recruit in this channel"),
+    (
+        "join.message",
+        "Link to the recruit message (searches this channel if omitted)",
+    ),
+    (
+        "remind",
+        "Remind participants by DM that the game starts soon",
+    ),
+    (
+        "remind.message",
+        "Link to the recruit message (searches this channel if omitted)",
+    ),
+    (
+        "recruit status",
+        "Show the participants and state of a recruit",
+    ),
+    (
+        "recruit status.message",
+        "Link to the recruit message (searches this channel if omitted)",
+    ),
+    ("recruit cancel", "Cancel a recruit"),
+    (
+        "recruit cancel.message",
+        "Link to the recruit message (searches this channel if omitted)",
+    ),
+    (
+        "recruit list",
+        "Show open recruits in this server, newest first",
+    ),
+    (
+        "recruit poll",
+        "Vote on a start time, then post the recruit",
+    ),
+    ("recruit poll.game_title", "Game to recruit for"),
+    (
+        "recruit poll.time1",
+        "Candidate time (e.g. 21:00, tomorrow 9pm)",
+    ),
+    ("recruit poll.time2", "Second candidate time"),
+    ("recruit poll.time3", "Third candidate time"),
+    ("recruit poll.time4", "Fourth candidate time"),
+    ("recruit poll.time5", "Fifth candidate time"),
+    (
+        "recruit poll.required_players",
+        "Players needed to start (optional for games set in /settings game)",
+    ),
+    ("recruit poll.max_players", "Maximum number of players"),
+    (
+        "recruit poll.voting_minutes",
+        "Time until voting closes (default: 60 minutes)",
+    ),
+    (
+        "recruit slots",
+        "Recruit by role slots such as tank, healer and DPS",
+    ),
+    ("recruit slots.game_title", "Game to recruit for"),
+    ("recruit slots.slot_1", "Slot name and size (e.g. Tank:1)"),
+    ("recruit slots.slot_2", "Second slot (e.g. Healer:2)"),
+    ("recruit slots.slot_3", "Third slot (e.g. DPS:5)"),
+    ("recruit slots.slot_4", "Fourth slot"),
+    ("recruit slots.slot_5", "Fifth slot"),
+    ("recruit slots.mention_role", "Role to mention on start"),
+    (
+        "recruit apply",
+        "Recruit players who apply with an answer and are accepted by the organizer",
+    ),
+    ("recruit apply.game_title", "Game to recruit for"),
+    ("recruit apply.required_players", "Players needed to start"),
+    (
+        "recruit apply.question",
+        "Question applicants answer (up to 45 characters)",
+    ),
+    ("recruit apply.max_players", "Maximum number of players"),
+    ("recruit apply.mention_role", "Role to mention on start"),
+    (
+        "recruit apply.assign_role",
+        "Whether to give mention_role to accepted players",
+    ),
+    ("deletion_log", "Show messages deleted by the bot"),
+    (
+        "deletion_log recent",
+        "Show messages recently deleted by the bot",
+    ),
+    (
+        "deletion_log recent.count",
+        "Number of entries (default: 5)",
+    ),
+    (
+        "override_quiet_hours",
+        "Mention even during quiet hours for an urgent recruit",
+    ),
+    (
+        "override_quiet_hours.message",
+        "Link to the recruit message",
+    ),
+    (
+        "override_quiet_hours.enabled",
+        "Ignore quiet hours (default: yes)",
+    ),
+    ("settings", "Change the settings of this server"),
+    (
+        "channel_settings",
+        "Change the channels this server uses for logs, summaries and mirrors",
+    ),
+    (
+        "settings webhook_url",
+        "Set the webhook URL for start notifications (omit to clear)",
+    ),
+    (
+        "settings webhook_url.url",
+        "URL to POST start notifications to (https only)",
+    ),
+    (
+        "settings organizer_role",
+        "Set the role required to recruit (omit to clear)",
+    ),
+    (
+        "settings organizer_role.role",
+        "Role required to create recruits",
+    ),
+    (
+        "settings organizer_role.mentions_only",
+        "Require the role only for recruits that mention roles",
+    ),
+    (
+        "settings rejection_notice",
+        "Set where to explain uncounted reactions",
+    ),
+    (
+        "settings rejection_notice.dm",
+        "Send the explanation by DM instead of in the channel",
+    ),
+    (
+        "settings role_ping_cooldown",
+        "Set the minimum interval between role mentions (omit to clear)",
+    ),
+    (
+        "settings role_ping_cooldown.minutes",
+        "Minutes before the same role can be mentioned again",
+    ),
+    (
+        "settings allowed_bot",
+        "Add or remove bots counted as participants",
+    ),
+    ("settings allowed_bot.bot", "Target bot"),
+    (
+        "settings allowed_bot.allowed",
+        "Count the bot as a participant",
+    ),
+    (
+        "settings cleanup_after_start",
+        "Set when started recruit messages are deleted",
+    ),
+    (
+        "settings cleanup_after_start.minutes",
+        "Minutes after start before the recruit is deleted",
+    ),
+    (
+        "settings remind_before",
+        "Set when to remind about scheduled starts (omit to clear)",
+    ),
+    (
+        "settings remind_before.minutes",
+        "Minutes before the start to DM participants",
+    ),
+    (
+        "settings snooze",
+        "Set how long organizers can postpone a countdown",
+    ),
+    (
+        "settings snooze.minutes",
+        "Minutes added per postponement (default: 15)",
+    ),
+    (
+        "settings snooze.max_minutes",
+        "Total minutes that can be added (default: 60, 0 disables)",
+    ),
+    (
+        "channel_settings audit_channel",
+        "Set the channel for admin notices (omit to clear)",
+    ),
+    (
+        "channel_settings audit_channel.channel",
+        "Channel for notices such as failed start notifications",
+    ),
+    (
+        "channel_settings error_messages",
+        "Set how errors for participants are sent (omit to reset)",
+    ),
+    (
+        "channel_settings error_messages.channel",
+        "Channel to send errors to instead of the recruit channel",
+    ),
+    (
+        "channel_settings error_messages.ping_user",
+        "Mention the user in errors in the recruit channel (default: false)",
+    ),
+    ("settings game", "Manage player counts of games"),
+    (
+        "settings game add",
+        "Register a game's player count (lets /recruit omit it)",
+    ),
+    (
+        "settings game add.title",
+        "Game title (aliases use the official title)",
+    ),
+    ("settings game add.players", "Players needed to start"),
+    ("settings game add.max", "Maximum number of players"),
+    ("settings game list", "List registered player counts"),
+    ("settings game remove", "Remove a registered player count"),
+    ("settings game remove.title", "Game title to remove"),
+    (
+        "settings cleanup_mode",
+        "Set how auto-deleted notices that were replied to are handled",
+    ),
+    (
+        "settings cleanup_mode.mode",
+        "Delete replied notices or replace them with a stub",
+    ),
+    (
+        "settings dedupe_mentions",
+        "Set whether participants pinged via a role are also mentioned",
+    ),
+    (
+        "settings dedupe_mentions.enabled",
+        "Show participants with the role by name only (default: true)",
+    ),
+    (
+        "settings ping_on_fail",
+        "Set whether to ping participants when a recruit does not fill",
+    ),
+    (
+        "settings ping_on_fail.enabled",
+        "Mention the participants in the notice (default: false)",
+    ),
+    (
+        "settings emoji",
+        "Manage the participation emoji of new recruits",
+    ),
+    ("settings emoji set", "Set the join and silent join emoji"),
+    (
+        "settings emoji set.join",
+        "Emoji to join (e.g. ✅, :name:, <:name:id>)",
+    ),
+    (
+        "settings emoji set.silent",
+        "Emoji to join without notifications",
+    ),
+    ("settings emoji clear", "Go back to ✋ and 🤚"),
+    (
+        "settings member_work_cap",
+        "Limit fetching members one by one (for large servers)",
+    ),
+    (
+        "settings member_work_cap.size",
+        "Max members to fetch one by one (default: 50)",
+    ),
+    (
+        "settings timezone",
+        "Manage the time zone for time input and quiet hours",
+    ),
+    ("settings timezone set", "Set the time zone of this server"),
+    (
+        "settings timezone set.timezone",
+        "IANA time zone name (e.g. Asia/Tokyo, Europe/Berlin)",
+    ),
+    (
+        "settings timezone clear",
+        "Go back to the default time zone",
+    ),
+    (
+        "settings role_prefix",
+        "Set the prefix for names of created roles",
+    ),
+    (
+        "settings role_prefix.prefix",
+        "Prefix for names of created roles (e.g. 🎮)",
+    ),
+    (
+        "settings quiet_hours",
+        "Set quiet hours without mentions (omit to clear)",
+    ),
+    (
+        "settings quiet_hours.start",
+        "Start of quiet hours (e.g. 23:00)",
+    ),
+    (
+        "settings quiet_hours.end",
+        "End of quiet hours (e.g. 07:00)",
+    ),
+    (
+        "settings min_age",
+        "Set how old accounts must be to count as participants",
+    ),
+    (
+        "settings min_age.account_days",
+        "Minimum days since the account was created",
+    ),
+    (
+        "settings min_age.membership_days",
+        "Minimum days since joining this server",
+    ),
+    (
+        "settings skip_spammers",
+        "Set whether to skip accounts flagged as spammers",
+    ),
+    (
+        "settings skip_spammers.enabled",
+        "Do not count accounts Discord flagged as likely spammers (default: false)",
+    ),
+    (
+        "settings language",
+        "Set the default language of recruits and notices",
+    ),
+    (
+        "settings language.language",
+        "Language of recruits and notices",
+    ),
+    (
+        "settings config_storage",
+        "Set where settings of new recruits are stored",
+    ),
+    (
+        "settings config_storage.config_storage",
+        "Where recruit settings are stored",
+    ),
+    (
+        "notify_me",
+        "Set your notifications in this server (omit to show)",
+    ),
+    (
+        "notify_me.join_announce",
+        "Announce when you join a recruit",
+    ),
+    ("notify_me.start_ping", "Mention you in start notifications"),
+    (
+        "notify_me.reset",
+        "Clear your settings and follow your reaction",
+    ),
+    (
+        "channel_settings summary_channel",
+        "Manage the pinned summary of today's recruits",
+    ),
+    (
+        "channel_settings summary_channel set",
+        "Set the channel for today's recruit summary",
+    ),
+    (
+        "channel_settings summary_channel set.channel",
+        "Channel to post and pin the summary in",
+    ),
+    (
+        "channel_settings summary_channel clear",
+        "Stop the summary and delete its message",
+    ),
+    (
+        "channel_settings deletion_log",
+        "Set where messages deleted by the bot are logged",
+    ),
+    (
+        "channel_settings deletion_log.destination",
+        "Where deleted messages are logged",
+    ),
+    (
+        "settings category",
+        "Manage recruit categories and their defaults",
+    ),
+    (
+        "settings category add",
+        "Add a category (replaces one with the same name)",
+    ),
+    ("settings category add.name", "Category name"),
+    (
+        "settings category add.notify_on_reaction",
+        "Default for sending join notices",
+    ),
+    (
+        "settings category add.auto_assign_role_on_reaction",
+        "Default for giving the role on reaction",
+    ),
+    (
+        "settings category add.delete_after_minutes",
+        "Default minutes before notices are deleted",
+    ),
+    (
+        "settings category add.cleanup_after_start_minutes",
+        "Default minutes after start before the recruit is deleted",
+    ),
+    (
+        "settings category add.remind_before_minutes",
+        "Default minutes before the start to DM participants",
+    ),
+    (
+        "settings category add.language",
+        "Default language of recruits and notices",
+    ),
+    (
+        "settings category add.roll_call",
+        "Default for taking a roll call before starting",
+    ),
+    (
+        "settings category add.roll_call_minutes",
+        "Default minutes until the roll call closes",
+    ),
+    (
+        "settings category add.priority_role",
+        "Role that can join first after the recruit is posted",
+    ),
+    (
+        "settings category add.priority_window_minutes",
+        "Minutes the priority role can join first (default: 30)",
+    ),
+    (
+        "settings category add.over_mention",
+        "Default for who to mention when more players join than needed",
+    ),
+    (
+        "settings category add.multi_lobby",
+        "Default for starting a separate lobby every required_players",
+    ),
+    ("settings category list", "List recruit categories"),
+    ("settings category remove", "Remove a recruit category"),
+    ("settings category remove.name", "Category to remove"),
+    (
+        "channel_settings channel_role",
+        "Manage default roles `/recruit create` mentions per channel",
+    ),
+    (
+        "channel_settings channel_role set",
+        "Set the role to mention for recruits in a channel",
+    ),
+    (
+        "channel_settings channel_role set.channel",
+        "Channel to recruit in",
+    ),
+    (
+        "channel_settings channel_role set.role",
+        "Role to mention when none is given",
+    ),
+    (
+        "channel_settings channel_role list",
+        "List the roles mentioned per channel",
+    ),
+    (
+        "channel_settings channel_role remove",
+        "Clear the role mentioned in a channel",
+    ),
+    (
+        "channel_settings channel_role remove.channel",
+        "Channel to clear",
+    ),
+    (
+        "channel_settings mirror",
+        "Manage read-only mirrors of recruits in another channel",
+    ),
+    (
+        "channel_settings mirror add",
+        "Mirror recruits of a channel into another channel",
+    ),
+    (
+        "channel_settings mirror add.source",
+        "Channel to recruit in",
+    ),
+    (
+        "channel_settings mirror add.target",
+        "Channel to post mirrors in",
+    ),
+    (
+        "channel_settings mirror list",
+        "List the channels recruits are mirrored from",
+    ),
+    (
+        "channel_settings mirror remove",
+        "Stop mirroring recruits of a channel",
+    ),
+    (
+        "channel_settings mirror remove.source",
+        "Channel to stop mirroring",
+    ),
+    (
+        "settings export",
+        "Export the server settings as a TOML file",
+    ),
+    ("settings import", "Import exported server settings"),
+    ("settings import.file", "TOML file from /settings export"),
+    ("join_menu", "Join one of the open recruits in this channel"),
+    (
+        "repair_recruit",
+        "Repair the bot's reactions and text on a recruit",
+    ),
+    ("repair_recruit.message", "Link to the recruit message"),
+    (
+        "adopt_recruits",
+        "Register recruits posted by older versions",
+    ),
+    (
+        "adopt_recruits.channel",
+        "Channel to scan (default: this one)",
+    ),
+    (
+        "adopt_recruits.all_channels",
+        "Scan every text channel in the server",
+    ),
+    (
+        "adopt_recruits.depth",
+        "Messages to scan per channel (default: 200)",
+    ),
+    ("moderate_recruits", "Close many open recruits at once"),
+    (
+        "moderate_recruits close_all",
+        "Close every open recruit in a channel",
+    ),
+    (
+        "moderate_recruits close_all.channel",
+        "Channel whose recruits to close",
+    ),
+    (
+        "moderate_recruits close_older_than",
+        "Close open recruits posted longer ago than a duration",
+    ),
+    (
+        "moderate_recruits close_older_than.duration",
+        "Time since posting (e.g. 2d, 12h)",
+    ),
+    (
+        "require_voice",
+        "Start only with players in a voice channel (unset to clear)",
+    ),
+    ("require_voice.message", "Link to the recruit message"),
+    (
+        "require_voice.channel",
+        "Voice channel players must be in at start time",
+    ),
+    (
+        "require_voice.grace_minutes",
+        "Minutes to wait for players to join (default: 10)",
+    ),
+    ("alias", "Manage aliases of game titles"),
+    ("alias add", "Add an alias of a game title"),
+    ("alias add.canonical", "Official game title"),
+    ("alias add.alias", "Alias treated as the same game"),
+    ("alias list", "List aliases of game titles"),
+    ("alias remove", "Remove an alias of a game title"),
+    ("alias remove.alias", "Alias to remove"),
+    ("leaderboard", "Show who joined recruits the most"),
+    ("leaderboard.game_title", "Game to count"),
+    ("leaderboard.period", "Period to count (default: all)"),
+    ("leaderboard.category", "Category of recruits to count"),
+    (
+        "recruit_log",
+        "Show how the participants of a recruit changed (for admins)",
+    ),
+    ("recruit_log.message", "Link or ID of the recruit message"),
+    (
+        "debug_recruit",
+        "Show the internal state of a recruit (for admins)",
+    ),
+    ("debug_recruit.message", "Link to the recruit message"),
+];
+
+/// パラメーターの選択肢の英語の名前 (日本語の名前をキーとします。英数字だけの名前はそのまま表示します)
+const EN_CHOICE_NAMES: &[(&str, &str)] = &[
+    ("日本語", "Japanese"),
+    ("先着の定員まで", "First players up to the limit"),
+    ("全員", "Everyone"),
+    ("募集メッセージ", "Recruit message"),
+    ("保存ファイル", "Data file"),
+    ("記録しない", "Off"),
+    ("管理者向けチャンネル", "Audit channel"),
+    ("ファイル", "File"),
+    ("削除する", "Delete"),
+    ("返信された通知は書き換える", "Shorten notices with replies"),
+];
+
+/// 募集設定に言語がない (言語を指定できるようになる前の) 募集に, サーバーの言語を設定します
+pub fn resolve_language(data: &Data, config: &mut RecruitConfig, guild_id: Option<GuildId>) {
+    if config.language.is_some() {
+        return;
+    }
+    config.language = Some(guild_language(data, guild_id));
+}
+
+/// `/settings language` で設定したサーバーの言語
+pub fn guild_language(data: &Data, guild_id: Option<GuildId>) -> Language {
+    guild_id
+        .map(|guild_id| data.storage.guild_settings(guild_id).language)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_time_is_rounded_to_the_largest_units() {
+        let filled_in = |language: Language, seconds| language.text(Text::FilledIn { seconds });
+        assert_eq!(
+            filled_in(Language::Ja, 59),
+            "募集開始から1分以内に集まりました"
+        );
+        assert_eq!(
+            filled_in(Language::Ja, 3 * 60 * 60 + 5 * 60),
+            "募集開始から3時間5分で集まりました"
+        );
+        assert_eq!(
+            filled_in(Language::En, 2 * 24 * 60 * 60 + 60 * 60 + 59),
+            "Filled in 2d 1h"
+        );
+        assert_eq!(filled_in(Language::En, 5 * 60), "Filled in 5m");
+    }
+
+    /// すべての種類の文言 (種類を増やしたら `variant_index` と一緒に加えます)
+    fn every_text() -> Vec<Text<'static>> {
+        vec![
+            Text::Progress {
+                count: 2,
+                required_players: 2,
+            },
+            Text::Joined {
+                user: "user",
+                game_title: "game_title",
+                progress: "progress",
+            },
+            Text::JoinedSlot {
+                user: "user",
+                game_title: "game_title",
+                slot: "slot",
+                progress: "progress",
+            },
+            Text::Starting {
+                mentions: "mentions",
+                game_title: "game_title",
+            },
+            Text::Gathered {
+                mentions: "mentions",
+                game_title: "game_title",
+                start_time: "start_time",
+            },
+            Text::StartingEmbed {
+                game_title: "game_title",
+            },
+            Text::GatheredEmbed {
+                game_title: "game_title",
+                start_time: "start_time",
+            },
+            Text::RolePingCoolingDown,
+            Text::QuietHoursNote,
+            Text::DeferredRolePing {
+                game_title: "game_title",
+                start_time: "start_time",
+                link: "link",
+            },
+            Text::CleanedUpNotice,
+            Text::TimePoll {
+                game_title: "game_title",
+                required_players: 2,
+                candidates: "candidates",
+                deadline: 1_700_000_000,
+            },
+            Text::TimePollCandidate {
+                number: 2,
+                start_time: "start_time",
+                votes: 2,
+            },
+            Text::TimePollClosed,
+            Text::TimePollDecided {
+                start_time: "start_time",
+                link: "link",
+            },
+            Text::TimePollNoVotes,
+            Text::TimePollVoters {
+                mentions: "mentions",
+            },
+            Text::RecruitExpired {
+                game_title: "game_title",
+                count: 2,
+                required_players: 2,
+            },
+            Text::RecruitExpiredParticipants {
+                mentions: "mentions",
+            },
+            Text::FilledIn {
+                seconds: 1_700_000_000,
+            },
+            Text::ConfigReadFailed,
+            Text::RoleAssignFailed,
+            Text::ErrorReport {
+                user: "user",
+                link: "link",
+                error: "error",
+            },
+            Text::OneMoreNeeded {
+                game_title: "game_title",
+            },
+            Text::OverCapacity {
+                game_title: "game_title",
+            },
+            Text::EntriesClosed {
+                game_title: "game_title",
+            },
+            Text::AccountTooNew {
+                game_title: "game_title",
+                days: 5,
+            },
+            Text::MembershipTooNew {
+                game_title: "game_title",
+                days: 5,
+            },
+            Text::MembershipPending {
+                game_title: "game_title",
+            },
+            Text::FlaggedSpammer {
+                game_title: "game_title",
+            },
+            Text::Oversubscribed {
+                capacity: 2,
+                reacted: 2,
+            },
+            Text::Substitutes {
+                mentions: "mentions",
+            },
+            Text::LobbyStarting {
+                number: 2,
+                mentions: "mentions",
+                game_title: "game_title",
+            },
+            Text::Checking,
+            Text::CountdownStarted {
+                game_title: "game_title",
+                start_at: 1_700_000_000,
+            },
+            Text::SnoozeButton { minutes: 5 },
+            Text::Snoozed {
+                game_title: "game_title",
+                minutes: 5,
+                start_at: 1_700_000_000,
+            },
+            Text::SnoozeNotOrganizer,
+            Text::SnoozeLimit { max_minutes: 5 },
+            Text::SnoozeClosed,
+            Text::PriorityDeferred {
+                user: "user",
+                role: "role",
+                until: 1_700_000_000,
+            },
+            Text::PriorityWindowEnded {
+                game_title: "game_title",
+            },
+            Text::CountdownAborted {
+                game_title: "game_title",
+                count: 2,
+                required_players: 2,
+            },
+            Text::CountdownWithdrawn {
+                names: "names",
+                game_title: "game_title",
+                count: 2,
+                required_players: 2,
+            },
+            Text::StartingSoon {
+                game_title: "game_title",
+            },
+            Text::RollCall {
+                mentions: "mentions",
+                game_title: "game_title",
+                deadline: 1_700_000_000,
+            },
+            Text::RollCallButton,
+            Text::RollCallConfirmed {
+                count: 2,
+                required_players: 2,
+            },
+            Text::RollCallNotTarget,
+            Text::RollCallClosed,
+            Text::RollCallFailed {
+                game_title: "game_title",
+                count: 2,
+                required_players: 2,
+            },
+            Text::DroppedOut {
+                user: "user",
+                game_title: "game_title",
+            },
+            Text::Replacement {
+                user: "user",
+                dropped: "dropped",
+                game_title: "game_title",
+            },
+            Text::LegendHeader {
+                game_title: "game_title",
+            },
+            Text::LegendMultiGameHeader,
+            Text::LegendCategory {
+                category: "category",
+            },
+            Text::LegendGameOption {
+                emoji: "emoji",
+                game_title: "game_title",
+                required_players: 2,
+            },
+            Text::LegendMultiGameRule,
+            Text::LegendSlotHeader {
+                game_title: "game_title",
+            },
+            Text::LegendSlot {
+                emoji: "emoji",
+                name: "name",
+                count: 2,
+            },
+            Text::LegendSlotRule,
+            Text::LegendParticipation { emoji: "emoji" },
+            Text::LegendSilentParticipation { emoji: "emoji" },
+            Text::LegendStart { emoji: "emoji" },
+            Text::LegendButtonsHeader {
+                game_title: "game_title",
+            },
+            Text::LegendButtons,
+            Text::LegendApplication {
+                question: "question",
+            },
+            Text::ButtonJoin,
+            Text::ButtonLeave,
+            Text::LegendAutoAssignRole { role: "role" },
+            Text::LegendPresetParticipants {
+                mentions: "mentions",
+            },
+            Text::LegendMaxPlayers { max_players: 2 },
+            Text::LegendStrictOrder,
+            Text::LegendMultiLobby {
+                required_players: 2,
+            },
+            Text::LegendStartTime {
+                start_time: "start_time",
+            },
+            Text::LegendRemindBefore { minutes: 5 },
+            Text::LegendRollCall { minutes: 5 },
+            Text::LegendPriority {
+                role: "role",
+                until: 1_700_000_000,
+            },
+            Text::LegendPriorityEnded,
+            Text::LegendStartDelay { minutes: 5 },
+            Text::LegendVoiceChannel { channel: "channel" },
+            Text::LegendVoiceRequired {
+                channel: "channel",
+                minutes: 5,
+            },
+            Text::VoiceHold {
+                mentions: "mentions",
+                channel: "channel",
+                required_players: 2,
+                deadline: 1_700_000_000,
+            },
+            Text::VoiceHoldExpired {
+                game_title: "game_title",
+                channel: "channel",
+            },
+            Text::VoiceLeftOut {
+                mentions: "mentions",
+            },
+            Text::LegendExtra {
+                emoji: "emoji",
+                label: "label",
+                counts: true,
+            },
+            Text::LegendSimulation,
+            Text::StateParticipantCount {
+                count: 2,
+                required_players: 2,
+            },
+            Text::StateDeadline {
+                deadline: 1_700_000_000,
+            },
+            Text::StateCancelled,
+            Text::StateExpired,
+            Text::StateEntriesClosed {
+                count: 2,
+                participants: "participants",
+            },
+            Text::StateStartedWith { winner: "winner" },
+            Text::StateClosedScheduled,
+            Text::StateStarted,
+        ]
+    }
+
+    /// 文言の種類の番号 (種類を増やすとコンパイルエラーになり, `every_text` への追加を忘れません)
+    fn variant_index(text: &Text) -> usize {
+        match text {
+            Text::Progress { .. } => 0,
+            Text::Joined { .. } => 1,
+            Text::JoinedSlot { .. } => 2,
+            Text::Starting { .. } => 3,
+            Text::Gathered { .. } => 4,
+            Text::StartingEmbed { .. } => 5,
+            Text::GatheredEmbed { .. } => 6,
+            Text::RolePingCoolingDown => 7,
+            Text::QuietHoursNote => 8,
+            Text::DeferredRolePing { .. } => 9,
+            Text::CleanedUpNotice => 10,
+            Text::TimePoll { .. } => 11,
+            Text::TimePollCandidate { .. } => 12,
+            Text::TimePollClosed => 13,
+            Text::TimePollDecided { .. } => 14,
+            Text::TimePollNoVotes => 15,
+            Text::TimePollVoters { .. } => 16,
+            Text::RecruitExpired { .. } => 17,
+            Text::RecruitExpiredParticipants { .. } => 18,
+            Text::FilledIn { .. } => 19,
+            Text::ConfigReadFailed => 20,
+            Text::RoleAssignFailed => 21,
+            Text::ErrorReport { .. } => 22,
+            Text::OneMoreNeeded { .. } => 23,
+            Text::OverCapacity { .. } => 24,
+            Text::EntriesClosed { .. } => 25,
+            Text::AccountTooNew { .. } => 26,
+            Text::MembershipTooNew { .. } => 27,
+            Text::MembershipPending { .. } => 28,
+            Text::FlaggedSpammer { .. } => 29,
+            Text::Oversubscribed { .. } => 30,
+            Text::Substitutes { .. } => 31,
+            Text::LobbyStarting { .. } => 32,
+            Text::Checking => 33,
+            Text::CountdownStarted { .. } => 34,
+            Text::SnoozeButton { .. } => 35,
+            Text::Snoozed { .. } => 36,
+            Text::SnoozeNotOrganizer => 37,
+            Text::SnoozeLimit { .. } => 38,
+            Text::SnoozeClosed => 39,
+            Text::PriorityDeferred { .. } => 40,
+            Text::PriorityWindowEnded { .. } => 41,
+            Text::CountdownAborted { .. } => 42,
+            Text::CountdownWithdrawn { .. } => 43,
+            Text::StartingSoon { .. } => 44,
+            Text::RollCall { .. } => 45,
+            Text::RollCallButton => 46,
+            Text::RollCallConfirmed { .. } => 47,
+            Text::RollCallNotTarget => 48,
+            Text::RollCallClosed => 49,
+            Text::RollCallFailed { .. } => 50,
+            Text::DroppedOut { .. } => 51,
+            Text::Replacement { .. } => 52,
+            Text::LegendHeader { .. } => 53,
+            Text::LegendMultiGameHeader => 54,
+            Text::LegendCategory { .. } => 55,
+            Text::LegendGameOption { .. } => 56,
+            Text::LegendMultiGameRule => 57,
+            Text::LegendSlotHeader { .. } => 58,
+            Text::LegendSlot { .. } => 59,
+            Text::LegendSlotRule => 60,
+            Text::LegendParticipation { .. } => 61,
+            Text::LegendSilentParticipation { .. } => 62,
+            Text::LegendStart { .. } => 63,
+            Text::LegendButtonsHeader { .. } => 64,
+            Text::LegendButtons => 65,
+            Text::LegendApplication { .. } => 66,
+            Text::ButtonJoin => 67,
+            Text::ButtonLeave => 68,
+            Text::LegendAutoAssignRole { .. } => 69,
+            Text::LegendPresetParticipants { .. } => 70,
+            Text::LegendMaxPlayers { .. } => 71,
+            Text::LegendStrictOrder => 72,
+            Text::LegendMultiLobby { .. } => 73,
+            Text::LegendStartTime { .. } => 74,
+            Text::LegendRemindBefore { .. } => 75,
+            Text::LegendRollCall { .. } => 76,
+            Text::LegendPriority { .. } => 77,
+            Text::LegendPriorityEnded => 78,
+            Text::LegendStartDelay { .. } => 79,
+            Text::LegendVoiceChannel { .. } => 80,
+            Text::LegendVoiceRequired { .. } => 81,
+            Text::VoiceHold { .. } => 82,
+            Text::VoiceHoldExpired { .. } => 83,
+            Text::VoiceLeftOut { .. } => 84,
+            Text::LegendExtra { .. } => 85,
+            Text::LegendSimulation => 86,
+            Text::StateParticipantCount { .. } => 87,
+            Text::StateDeadline { .. } => 88,
+            Text::StateCancelled => 89,
+            Text::StateExpired => 90,
+            Text::StateEntriesClosed { .. } => 91,
+            Text::StateStartedWith { .. } => 92,
+            Text::StateClosedScheduled => 93,
+            Text::StateStarted => 94,
+        }
+    }
+
+    #[test]
+    fn every_text_is_complete_in_both_languages() {
+        let texts = every_text();
+        let indices: Vec<usize> = texts.iter().map(variant_index).collect();
+        assert_eq!(indices, (0..95).collect::<Vec<_>>());
+        for language in [Language::Ja, Language::En] {
+            for text in &texts {
+                let rendered = language.text(*text);
+                assert!(!rendered.trim().is_empty(), "{language:?}: {text:?}");
+                // 埋め込み忘れの {…} が残っていません
+                assert!(
+                    !rendered.contains('{') && !rendered.contains('}'),
+                    "{language:?}: {rendered}"
+                );
+            }
+        }
+    }
+}
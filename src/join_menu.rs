@@ -0,0 +1,369 @@
+use poise::{CreateReply, serenity_prelude::*};
+use tokio::time::Duration;
+
+use crate::discord_error::is_unknown_entity;
+use crate::i18n::{self, Language};
+use crate::notification::NotificationPolicy;
+use crate::notify_prefs;
+use crate::priority;
+use crate::recruit_message::{RecruitConfig, RecruitMessage};
+use crate::recruit_picker;
+use crate::{
+    Data, Error, assign_role_to_member, begin_start, collect_participants, format_progress,
+    notify_organizer_close, parse_bot_recruit_message, participant_age, participant_count,
+    participant_order, remember_recruit_location, send_participation_notification,
+};
+
+const MENU_TIMEOUT: Duration = Duration::from_secs(120);
+/// Discord のセレクトメニューに表示できる選択肢の上限
+const MAX_MENU_OPTIONS: usize = 25;
+const MAX_LABEL_CHARS: usize = 100;
+
+/// このチャンネルの受付中の募集から選んで参加します
+#[poise::command(slash_command, guild_only, ephemeral, rename = "join_menu")]
+pub async fn join_menu(ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let serenity_ctx = ctx.serenity_context();
+
+    // メッセージは新しい順に取得されるため, 先頭から 25 件までを選択肢にします
+    let messages = ctx
+        .channel_id()
+        .messages(ctx, GetMessages::new().limit(100))
+        .await?;
+    let mut options = Vec::new();
+    for message in &messages {
+        if MAX_MENU_OPTIONS <= options.len() {
+            break;
+        }
+        let Some(recruit_message) = parse_bot_recruit_message(serenity_ctx, ctx.data(), message)
+        else {
+            continue;
+        };
+        if !is_open(&recruit_message) {
+            continue;
+        }
+        let config = &recruit_message.config;
+        let count = collect_participants(serenity_ctx, ctx.data(), message, config, Some(guild_id))
+            .await?
+            .len();
+        let label = format!(
+            "{} ({count}/{})",
+            truncate_chars(&config.game_title, MAX_LABEL_CHARS - 16),
+            config.required_players
+        );
+        let mut option = CreateSelectMenuOption::new(label, message.id.to_string());
+        if let Some(description) = &config.description {
+            let description = description.split_whitespace().collect::<Vec<_>>().join(" ");
+            option = option.description(truncate_chars(&description, MAX_LABEL_CHARS - 1));
+        }
+        options.push(option);
+    }
+    if options.is_empty() {
+        ctx.send(
+            CreateReply::default()
+                .content("このチャンネルに受付中の募集はありません")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let custom_id = format!("{}:join", ctx.id());
+    let reply = ctx
+        .send(
+            CreateReply::default()
+                .content("参加する募集を選んでください")
+                .ephemeral(true)
+                .components(vec![CreateActionRow::SelectMenu(
+                    CreateSelectMenu::new(&custom_id, CreateSelectMenuKind::String { options })
+                        .placeholder("募集を選択"),
+                )]),
+        )
+        .await?;
+
+    let interaction = ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .filter(move |interaction| interaction.data.custom_id == custom_id)
+        .timeout(MENU_TIMEOUT)
+        .await;
+    let Some(interaction) = interaction else {
+        reply
+            .edit(
+                ctx,
+                CreateReply::default()
+                    .content("時間内に選択されなかったため終了しました")
+                    .components(Vec::new()),
+            )
+            .await?;
+        return Ok(());
+    };
+    let selected = match &interaction.data.kind {
+        ComponentInteractionDataKind::StringSelect { values } => values
+            .first()
+            .and_then(|value| value.parse::<u64>().ok())
+            .filter(|&id| id != 0)
+            .map(MessageId::new),
+        _ => None,
+    };
+    let content = match selected {
+        Some(message_id) => {
+            join(
+                serenity_ctx,
+                ctx.data(),
+                guild_id,
+                ctx.channel_id(),
+                message_id,
+                ctx.author().id,
+            )
+            .await?
+        }
+        None => "選択した募集を読み取れませんでした".to_string(),
+    };
+    interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .components(Vec::new()),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+/// このチャンネルの募集に参加します
+///
+/// `message` を省略すると, 受付中の募集が 1 つならそれに参加し, 複数あればメニューから選びます。
+#[poise::command(slash_command, guild_only, ephemeral, rename = "join")]
+pub async fn join_command(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集メッセージのリンク (省略するとこのチャンネルから探します)"]
+    message: Option<Message>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    recruit_picker::with_recruit(ctx, message, move |message| async move {
+        join(
+            ctx.serenity_context(),
+            ctx.data(),
+            guild_id,
+            message.channel_id,
+            message.id,
+            ctx.author().id,
+        )
+        .await
+    })
+    .await
+}
+
+/// 開始もキャンセルもされていない, 🤚 で参加する募集 (複数のゲームの募集と枠のある募集を除く) かどうか
+pub fn is_open(recruit_message: &RecruitMessage) -> bool {
+    let state = &recruit_message.state;
+    !state.started
+        && !state.cancelled
+        && state.closed_entries.is_none()
+        && !recruit_message.config.has_option_reactions()
+}
+
+pub fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((index, _)) => format!("{}…", &text[..index]),
+        None => text.to_string(),
+    }
+}
+
+/// メニューで選ばれた募集にユーザーを参加させ, 実行者に表示する結果を返します
+///
+/// メニューを表示してから選ぶまでに締め切られた募集には参加させません。
+pub async fn join(
+    ctx: &Context,
+    data: &Data,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    user_id: UserId,
+) -> Result<String, Error> {
+    let _guard = data.lock_message(message_id).await;
+    let message = match channel_id.message(ctx, message_id).await {
+        Ok(message) => message,
+        Err(err) if is_unknown_entity(&err).is_some() => {
+            return Ok("選択した募集は削除されたため参加できません".to_string());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let Some(mut recruit_message) = parse_bot_recruit_message(ctx, data, &message) else {
+        return Ok("選択したメッセージは募集ではありません".to_string());
+    };
+    if !is_open(&recruit_message) {
+        return Ok(
+            "選択した募集はメニューを表示した後に締め切られたため参加できません".to_string(),
+        );
+    }
+    remember_recruit_location(data, Some(guild_id), &message);
+    i18n::resolve_language(data, &mut recruit_message.config, Some(guild_id));
+    let config = &recruit_message.config;
+    if config.requires_application() {
+        return Ok(format!(
+            "{} は応募が必要なため、募集メッセージの「参加する」から応募してください",
+            config.game_title
+        ));
+    }
+    // メニューからの参加は後回しにできないため, 優先期間が終わるまで受け付けません
+    if let (Some(role_id), Some(until)) = (config.priority_role, config.priority_until)
+        && priority::is_window_open(Some(until), Timestamp::now())
+        && !guild_id
+            .member(ctx, user_id)
+            .await
+            .is_ok_and(|member| member.roles.contains(&role_id))
+    {
+        return Ok(format!(
+            "{} は優先期間中のため、<t:{}:R> から参加できます",
+            config.game_title,
+            until.unix_timestamp()
+        ));
+    }
+
+    if let Some(reason) = participant_age::rejection(ctx, data, guild_id, user_id, None).await {
+        return Ok(reason.message(config));
+    }
+
+    let content = match admit(ctx, data, guild_id, &message, config, user_id).await? {
+        Admission::AlreadyJoined => format!("すでに {} に参加しています", config.game_title),
+        Admission::Full => format!("{} は定員に達しています", config.game_title),
+        Admission::Joined {
+            participants,
+            role_failed,
+        } => {
+            let mut content = format!(
+                "{} に参加しました {}",
+                config.game_title,
+                format_progress(Language::Ja, participants, config.required_players)
+            );
+            if role_failed {
+                content += "\nロールの付与に失敗しました";
+            }
+            content
+        }
+    };
+    Ok(content)
+}
+
+/// 参加者を追加した結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    AlreadyJoined,
+    Full,
+    Joined {
+        /// 追加した後の参加者の人数
+        participants: usize,
+        role_failed: bool,
+    },
+}
+
+/// 締め切られていない募集にユーザーを参加者として追加し, 通知とロールの付与を行い, 人数が揃えば開始します
+///
+/// 呼び出す側で募集メッセージをロックし, 参加できるユーザーかどうかを確かめます。
+pub async fn admit(
+    ctx: &Context,
+    data: &Data,
+    guild_id: GuildId,
+    message: &Message,
+    config: &RecruitConfig,
+    user_id: UserId,
+) -> Result<Admission, Error> {
+    let channel_id = message.channel_id;
+    let mut user_ids = collect_participants(ctx, data, message, config, Some(guild_id)).await?;
+    if user_ids.contains(&user_id) {
+        return Ok(Admission::AlreadyJoined);
+    }
+    if config
+        .max_players
+        .is_some_and(|max_players| max_players <= user_ids.len())
+    {
+        return Ok(Admission::Full);
+    }
+    data.storage.add_menu_participant(message.id, user_id)?;
+    user_ids.insert(user_id);
+    participant_count::store_count(data, message.id, user_ids.len());
+    if config.strict_order {
+        participant_order::record_reactor(data, message.id, Some(user_id), &user_ids)?;
+    }
+
+    let policy = NotificationPolicy::new(config);
+    if config.notify_on_reaction
+        && notify_prefs::resolve(false, data.storage.notify_prefs(guild_id, user_id)).join_announce
+    {
+        send_participation_notification(
+            ctx,
+            data,
+            config,
+            &policy,
+            channel_id,
+            user_id,
+            user_ids.len(),
+        )
+        .await?;
+    }
+    if config.notify_organizer_when_close
+        && user_ids.len() + 1 == config.required_players
+        && let Some(organizer) = config.organizer
+    {
+        notify_organizer_close(
+            ctx,
+            data,
+            config,
+            &policy,
+            message,
+            Some(guild_id),
+            organizer,
+        )
+        .await?;
+    }
+    let mut role_failed = false;
+    if config.auto_assign_role_on_reaction
+        && policy.has_side_effects()
+        && let Some(&role_id) = config.mention_roles.first()
+        && let Err(err) = assign_role_to_member(ctx, guild_id, user_id, role_id).await
+    {
+        eprintln!("Failed to assign role: {err}");
+        role_failed = true;
+    }
+
+    let participants = user_ids.len();
+    if config.required_players <= participants {
+        begin_start(ctx, data, config, message, Some(guild_id), user_ids).await?;
+    }
+    Ok(Admission::Joined {
+        participants,
+        role_failed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recruit_message::ClosedEntries;
+
+    #[test]
+    fn only_open_recruits_are_listed() {
+        let mut recruit_message = RecruitMessage::new(RecruitConfig::for_test("Apex", 3));
+        assert!(is_open(&recruit_message));
+        recruit_message.state.closed_entries = Some(ClosedEntries {
+            participants: Vec::new(),
+            remove_late_reactions: false,
+        });
+        assert!(!is_open(&recruit_message));
+
+        let mut recruit_message = RecruitMessage::new(RecruitConfig::for_test("Apex", 3));
+        recruit_message.state.started = true;
+        assert!(!is_open(&recruit_message));
+    }
+
+    #[test]
+    fn long_labels_are_truncated_by_chars() {
+        assert_eq!(truncate_chars("Apex", 4), "Apex");
+        // 全角の文字も 1 文字として数えます
+        assert_eq!(truncate_chars("エーペックス", 3), "エーペ…");
+    }
+}
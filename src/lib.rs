@@ -0,0 +1,3929 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use poise::CreateReply;
+use poise::serenity_prelude::{self as serenity, *};
+
+mod adopt;
+#[cfg(feature = "api")]
+mod api;
+mod application;
+mod cancel;
+mod capabilities;
+mod category;
+mod channel_check;
+mod channel_role;
+pub mod cli;
+mod close_entries;
+mod close_reason;
+mod command_locale;
+mod config_storage;
+mod confirm;
+mod countdown;
+mod created_role;
+mod debug;
+mod deletion_log;
+mod description;
+mod discord_api;
+mod discord_error;
+mod drop_out;
+mod duplicate_reaction;
+pub mod engine;
+mod expiry;
+mod game_alias;
+mod game_preset;
+mod gateway_resume;
+mod i18n;
+mod join_menu;
+mod lobby;
+mod member_left;
+mod member_work;
+mod mention_dedupe;
+mod message_deletion;
+mod mirror;
+pub mod mock_discord;
+mod moderate;
+mod multi_game;
+mod notice_cleanup;
+mod notification;
+mod notify_prefs;
+mod participant_age;
+mod participant_count;
+mod participant_order;
+mod participation_buttons;
+mod preset;
+mod priority;
+mod quiet_hours;
+mod reaction_debounce;
+mod reaction_emoji;
+mod reaction_resolve;
+mod recruit_actions;
+mod recruit_command;
+mod recruit_edit;
+mod recruit_log;
+mod recruit_message;
+mod recruit_picker;
+mod recruit_status;
+mod rejection;
+mod reminder;
+mod repair;
+mod rest_budget;
+mod roll_call;
+mod scheduler;
+mod settings_file;
+mod slots;
+#[cfg(feature = "sqlite")]
+mod sqlite_storage;
+mod start_marker;
+mod start_retry;
+mod startup_gate;
+mod stats;
+mod storage;
+mod storage_backend;
+mod summary;
+mod sweeper;
+mod task_registry;
+mod thread;
+mod time_input;
+mod time_poll;
+mod timezone;
+mod voice_gate;
+#[cfg(feature = "webhook")]
+mod webhook;
+
+use capabilities::{Capabilities, MESSAGE_CONTENT_REQUIRED};
+use category::CategoryDefaults;
+use channel_check::ChannelSuitability;
+use close_reason::CloseReason;
+use config_storage::ConfigStorage;
+use countdown::PendingStart;
+use deletion_log::DeletionLogDestination;
+use discord_api::DiscordApi;
+use duplicate_reaction::{ProcessedReactors, ReactorAction};
+use engine::EngineConfig;
+use gateway_resume::ResumeMetrics;
+use i18n::{Language, Text};
+use notification::{Mentions, NotificationKind, NotificationPolicy, OrganizerAuthor};
+use notify_prefs::{NotifyPrefs, StartLabels};
+use participant_age::EligibilityPolicy;
+use participant_count::{CachedCount, CountMetrics};
+use participant_order::StartSelection;
+use quiet_hours::{QuietHours, TimeOfDay};
+use reaction_debounce::ReactionDebouncer;
+use reaction_resolve::ResolutionMetrics;
+use recruit_log::RecruitEventKind;
+use recruit_message::{
+    GameOption, OverMention, ParticipationMode, RecruitConfig, RecruitMessage, RecruitReaction,
+    is_same_emoji, parse_emoji, parse_extra_reactions, sanitize_description, sanitize_text,
+};
+use rejection::{RejectionReason, TtlCache};
+use rest_budget::RestBudget;
+use scheduler::{Job, Scheduler};
+use start_marker::{StartDecision, StartMarker};
+use startup_gate::StartupGate;
+use storage::{RecruitLocation, RecruitRecord, Storage};
+use summary::SummaryBoard;
+use task_registry::TaskRegistry;
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+type Shared<T> = Arc<Mutex<T>>;
+
+const DEFAULT_DELETE_AFTER_MINUTES: u64 = 60;
+
+/// Bot の状態 (poise のコマンドやイベントの処理に渡します)
+#[derive(Clone)]
+pub struct Data {
+    storage: Arc<dyn Storage>,
+    /// 同じ募集メッセージへのリアクションを順番に処理するためのロック
+    message_locks: Shared<HashMap<MessageId, Arc<tokio::sync::Mutex<()>>>>,
+    /// 募集メッセージを最後に処理した時刻 (古い状態の削除に使用)
+    last_touched: Shared<HashMap<MessageId, Instant>>,
+    /// 参加人数に数えるボイスチャンネルごとの募集メッセージ (チャンネル, メッセージ)
+    voice_recruits: Shared<HashMap<ChannelId, HashSet<(ChannelId, MessageId)>>>,
+    /// 開始までのカウントダウン中の募集
+    pending_starts: Shared<HashMap<MessageId, PendingStart>>,
+    /// リアクションを数えなかった理由を通知した (募集メッセージ, ユーザー)
+    rejection_notices: Shared<TtlCache<(MessageId, UserId)>>,
+    /// アカウントとサーバーへの参加の新しさの確認を通った (サーバー, ユーザー, 確認した条件)
+    verified_participants: Shared<TtlCache<(GuildId, UserId, EligibilityPolicy)>>,
+    /// 「あと1人」を主催者に通知した募集 (開始すると再び通知します)
+    close_notices: Shared<HashSet<MessageId>>,
+    /// 参加人数を数え直さずに済むかの判定に使う, 募集ごとの参加人数
+    participant_counts: Shared<HashMap<MessageId, CachedCount>>,
+    /// ✋ と 🤚 の両方でリアクションしたユーザーを一度だけ処理するための, 募集ごとの記録 (開始すると消します)
+    processed_reactors: Shared<ProcessedReactors>,
+    count_metrics: Arc<CountMetrics>,
+    /// シャードの再接続と, その後の数え直しの統計
+    resume_metrics: Arc<ResumeMetrics>,
+    /// `user_id` や `guild_id` がないリアクションのイベントを補った回数
+    reaction_resolutions: Arc<ResolutionMetrics>,
+    /// バックグラウンドの処理が同時に呼び出す REST API の上限
+    rest_budget: Arc<RestBudget>,
+    /// 書き換えを待っている, 今日の募集の一覧
+    summary_board: Arc<SummaryBoard>,
+    scheduler: Arc<Scheduler>,
+    /// 起動直後に募集か判定できなかったリアクション
+    startup_gate: Arc<StartupGate>,
+    /// 予約した処理のうち, 募集ごとに実行中のタスク
+    tasks: Arc<TaskRegistry>,
+    /// 同じユーザーが短い間に繰り返したリアクションの操作
+    reaction_debouncer: Arc<ReactionDebouncer>,
+    /// 起動時に判定した, Bot が使える Discord の機能
+    capabilities: Capabilities,
+    config: Arc<EngineConfig>,
+}
+
+impl Data {
+    fn new(storage: Arc<dyn Storage>, config: EngineConfig) -> Self {
+        Data {
+            storage,
+            message_locks: Default::default(),
+            last_touched: Default::default(),
+            voice_recruits: Default::default(),
+            pending_starts: Default::default(),
+            rejection_notices: Arc::new(Mutex::new(TtlCache::new(
+                config.rejection_notice_interval,
+            ))),
+            verified_participants: Arc::new(Mutex::new(TtlCache::new(config.verification_ttl))),
+            close_notices: Default::default(),
+            processed_reactors: Default::default(),
+            participant_counts: Default::default(),
+            count_metrics: Default::default(),
+            resume_metrics: Default::default(),
+            reaction_resolutions: Default::default(),
+            rest_budget: Arc::new(RestBudget::from_env()),
+            summary_board: Default::default(),
+            scheduler: Arc::new(Scheduler::new()),
+            startup_gate: Arc::new(StartupGate::new(config.startup_grace)),
+            tasks: Default::default(),
+            reaction_debouncer: Arc::new(ReactionDebouncer::new(config.debounce_window)),
+            capabilities: Capabilities::detect(config.intents, config.application_flags),
+            config: Arc::new(config),
+        }
+    }
+
+    async fn lock_message(&self, message_id: MessageId) -> tokio::sync::OwnedMutexGuard<()> {
+        self.last_touched
+            .lock()
+            .unwrap()
+            .insert(message_id, Instant::now());
+        let lock = self
+            .message_locks
+            .lock()
+            .unwrap()
+            .entry(message_id)
+            .or_default()
+            .clone();
+        lock.lock_owned().await
+    }
+
+    /// 削除された募集メッセージについて, 保存している参加者の順番も含めて状態を削除します
+    fn forget_deleted_message(&self, message_id: MessageId) {
+        // 場所の記録を消す前に, 一覧から除くよう予約し, 終わっていない募集なら削除されたことを記録します
+        summary::mark_message(self, message_id);
+        let stored = self.storage.recruit_message(message_id);
+        close_reason::close_recruit(
+            self,
+            message_id,
+            stored
+                .as_ref()
+                .map(|recruit_message| &recruit_message.config),
+            CloseReason::Deleted,
+        );
+        self.forget_message(message_id);
+        if let Err(err) = self.storage.remove_participant_order(message_id) {
+            eprintln!("Failed to remove participant order: {err}");
+        }
+        if let Err(err) = self.storage.remove_menu_participants(message_id) {
+            eprintln!("Failed to remove menu participants: {err}");
+        }
+        if let Err(err) = self.storage.clear_start_marker(message_id) {
+            eprintln!("Failed to clear start marker: {err}");
+        }
+        if let Err(err) = self.storage.remove_recruit_location(message_id) {
+            eprintln!("Failed to remove recruit location: {err}");
+        }
+        if let Err(err) = self.storage.remove_roll_call(message_id) {
+            eprintln!("Failed to remove roll call: {err}");
+        }
+        if let Err(err) = self.storage.remove_recruit_message(message_id) {
+            eprintln!("Failed to remove stored recruit config: {err}");
+        }
+        if let Err(err) = self.storage.remove_rearmed_at(message_id) {
+            eprintln!("Failed to remove rearm time: {err}");
+        }
+        if let Err(err) = self.storage.remove_started_participants(message_id) {
+            eprintln!("Failed to remove started participants: {err}");
+        }
+        if let Err(err) = self.storage.remove_deferred_participants(message_id) {
+            eprintln!("Failed to remove deferred participants: {err}");
+        }
+        if let Err(err) = self.storage.remove_start_retry(message_id) {
+            eprintln!("Failed to remove start retry: {err}");
+        }
+        voice_gate::release(self, message_id);
+        if let Err(err) = self.storage.remove_applications(message_id) {
+            eprintln!("Failed to remove applications: {err}");
+        }
+        if let Err(err) = self.storage.remove_time_poll(message_id) {
+            eprintln!("Failed to remove time poll: {err}");
+        }
+        if let Err(err) = self.storage.remove_lobby_placement(message_id) {
+            eprintln!("Failed to remove lobby placement: {err}");
+        }
+        task_registry::cancel_all(self, message_id);
+    }
+
+    /// Bot が参加しなくなったサーバーについて, 保存している設定や記録とメモリ上の状態を削除します
+    fn purge_guild(&self, guild_id: GuildId) {
+        match self.storage.purge_guild(guild_id) {
+            Ok(summary) => {
+                for &message_id in &summary.messages {
+                    self.forget_message(message_id);
+                }
+                eprintln!("Purged state of guild {guild_id}: {summary}");
+            }
+            Err(err) => eprintln!("Failed to purge state of guild {guild_id}: {err}"),
+        }
+    }
+
+    /// 削除されたチャンネルについて, 募集の記録とメモリ上の状態を削除します
+    fn purge_channel(&self, channel_id: ChannelId) {
+        self.voice_recruits
+            .lock()
+            .unwrap()
+            .retain(|&voice_channel_id, recruits| {
+                recruits.retain(|&(recruit_channel_id, _)| recruit_channel_id != channel_id);
+                voice_channel_id != channel_id && !recruits.is_empty()
+            });
+        match self.storage.purge_channel(channel_id) {
+            Ok(summary) => {
+                for &message_id in &summary.messages {
+                    self.forget_message(message_id);
+                    // ほかのチャンネルに残したミラーを, 削除された募集として書き換えます
+                    summary::mark_message(self, message_id);
+                }
+                if !summary.is_empty() {
+                    eprintln!("Purged state of channel {channel_id}: {summary}");
+                }
+            }
+            Err(err) => eprintln!("Failed to purge state of channel {channel_id}: {err}"),
+        }
+    }
+
+    /// 募集メッセージについて, 保存している記録かメモリ上の状態があるかどうか
+    fn is_tracked_message(&self, message_id: MessageId) -> bool {
+        self.storage.has_message_records(message_id)
+            || self.last_touched.lock().unwrap().contains_key(&message_id)
+            || self
+                .pending_starts
+                .lock()
+                .unwrap()
+                .contains_key(&message_id)
+            || self
+                .participant_counts
+                .lock()
+                .unwrap()
+                .contains_key(&message_id)
+    }
+
+    /// 募集メッセージに関するメモリ上の状態を削除します
+    fn forget_message(&self, message_id: MessageId) {
+        self.last_touched.lock().unwrap().remove(&message_id);
+        let mut message_locks = self.message_locks.lock().unwrap();
+        if message_locks
+            .get(&message_id)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            message_locks.remove(&message_id);
+        }
+        drop(message_locks);
+        self.voice_recruits.lock().unwrap().retain(|_, recruits| {
+            recruits.retain(|&(_, recruit_message_id)| recruit_message_id != message_id);
+            !recruits.is_empty()
+        });
+        self.pending_starts.lock().unwrap().remove(&message_id);
+        if let Err(err) = self.storage.cancel_jobs(
+            &|job| matches!(job, Job::FinishCountdown { message_id: id, .. } if *id == message_id),
+        ) {
+            eprintln!("Failed to cancel countdown job: {err}");
+        }
+        self.rejection_notices
+            .lock()
+            .unwrap()
+            .retain(|&(notice_message_id, _)| notice_message_id != message_id);
+        self.close_notices.lock().unwrap().remove(&message_id);
+        self.participant_counts.lock().unwrap().remove(&message_id);
+        self.processed_reactors.lock().unwrap().remove(&message_id);
+    }
+}
+
+#[cfg(test)]
+impl Data {
+    /// メモリだけに保存する, 既定の機能の Bot の状態
+    fn for_test() -> Self {
+        let storage = Arc::new(storage_backend::MemoryStorage::default());
+        Data::new(storage, EngineConfig::new())
+    }
+}
+
+/// 募集のリアクション以外のイベントを処理します (`engine::attach` のイベントの処理から呼び出します)
+async fn handle_gateway_event(ctx: &Context, data: &Data, event: &FullEvent) -> Result<(), Error> {
+    match event {
+        FullEvent::ReactionRemoveAll {
+            channel_id,
+            removed_from_message_id,
+        } => {
+            match repair::handle_reaction_remove_all(
+                ctx,
+                data,
+                *channel_id,
+                *removed_from_message_id,
+            )
+            .await
+            {
+                Err(err) if discord_error::unknown_entity(&err).is_some() => {
+                    data.forget_deleted_message(*removed_from_message_id);
+                }
+                result => result?,
+            }
+        }
+        FullEvent::CacheReady { .. } => {
+            retry_held_reactions(ctx, data).await;
+        }
+        FullEvent::Resume { .. } => {
+            gateway_resume::handle_resume(ctx, data);
+        }
+        FullEvent::MessageDelete {
+            deleted_message_id, ..
+        } => {
+            data.forget_deleted_message(*deleted_message_id);
+            message_deletion::drop_scheduled_deletions(data, &[*deleted_message_id]);
+        }
+        // ほかの Bot がチャンネルを掃除した場合など, まとめて削除されたメッセージ
+        FullEvent::MessageDeleteBulk {
+            channel_id,
+            multiple_deleted_messages_ids,
+            ..
+        } => {
+            message_deletion::handle_bulk_delete(data, *channel_id, multiple_deleted_messages_ids);
+        }
+        // 障害でサーバーが一時的に使えなくなった場合 (unavailable) は削除しません
+        FullEvent::GuildDelete { incomplete, .. } if !incomplete.unavailable => {
+            data.purge_guild(incomplete.id);
+        }
+        FullEvent::GuildMemberRemoval { guild_id, user, .. } => {
+            member_left::handle_member_removal(ctx, data, *guild_id, user.id).await?;
+        }
+        FullEvent::ChannelDelete { channel, .. } => {
+            data.purge_channel(channel.id);
+        }
+        FullEvent::ThreadDelete { thread, .. } => {
+            data.purge_channel(thread.id);
+        }
+        FullEvent::VoiceStateUpdate { old, new } => {
+            handle_voice_state_update(ctx, data, old.as_ref(), new).await?;
+        }
+        FullEvent::InteractionCreate {
+            interaction: Interaction::Component(interaction),
+        } if roll_call::is_roll_call_button(interaction) => {
+            roll_call::handle_roll_call_button(ctx, data, interaction).await?;
+        }
+        FullEvent::InteractionCreate {
+            interaction: Interaction::Component(interaction),
+        } if countdown::is_snooze_button(interaction) => {
+            countdown::handle_snooze_button(ctx, data, interaction).await?;
+        }
+        FullEvent::InteractionCreate {
+            interaction: Interaction::Component(interaction),
+        } if recruit_actions::is_recruit_action_button(interaction) => {
+            recruit_actions::handle_recruit_action_button(ctx, data, interaction).await?;
+        }
+        FullEvent::InteractionCreate {
+            interaction: Interaction::Component(interaction),
+        } if participation_buttons::is_participation_button(interaction) => {
+            participation_buttons::handle_participation_button(ctx, data, interaction).await?;
+        }
+        FullEvent::InteractionCreate {
+            interaction: Interaction::Component(interaction),
+        } if application::is_review_button(interaction) => {
+            application::handle_review_button(ctx, data, interaction).await?;
+        }
+        FullEvent::InteractionCreate {
+            interaction: Interaction::Modal(interaction),
+        } if application::is_application_form(interaction) => {
+            application::handle_form(ctx, data, interaction).await?;
+        }
+        FullEvent::InteractionCreate {
+            interaction: Interaction::Component(interaction),
+        } if time_poll::is_time_poll_button(interaction) => {
+            time_poll::handle_time_poll_button(ctx, data, interaction).await?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// 登録するコマンド
+fn commands() -> Vec<poise::Command<Data, Error>> {
+    vec![
+        recruit_command::recruit(),
+        import_recruit(),
+        transfer_recruit(),
+        close_entries::close_entries(),
+        quiet_hours::override_quiet_hours(),
+        deletion_log::deletion_log(),
+        settings(),
+        channel_settings(),
+        join_menu::join_menu(),
+        join_menu::join_command(),
+        reminder::remind(),
+        notify_prefs::notify_me(),
+        repair::repair_recruit_command(),
+        adopt::adopt_recruits(),
+        moderate::moderate_recruits(),
+        voice_gate::require_voice(),
+        game_alias::alias(),
+        stats::leaderboard(),
+        recruit_log::recruit_log(),
+        debug::debug_recruit(),
+    ]
+}
+
+/// 募集を作成します
+#[allow(clippy::too_many_arguments)]
+#[poise::command(slash_command, guild_only, rename = "create")]
+async fn recruit_create(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集するゲーム名"]
+    #[autocomplete = "game_alias::autocomplete_game_title"]
+    game_title: String,
+    #[description = "開始に必要な人数 (/settings game で人数を登録したゲームは省略できます)"]
+    required_players: Option<usize>,
+    #[description = "参加できる最大人数"] max_players: Option<usize>,
+    #[description = "定員を超えたリアクションを削除するかどうか"] enforce_cap: Option<bool>,
+    #[description = "開始時にメンションするロール"] mention_role: Option<Role>,
+    #[description = "開始時にメンションする2つ目のロール"] mention_role2: Option<Role>,
+    #[description = "開始時にメンションする3つ目のロール"] mention_role3: Option<Role>,
+    #[description = "ロールを作成するかどうか"] create_role: Option<bool>,
+    #[description = "リアクション追加時にロールを自動付与するかどうか"]
+    auto_assign_role_on_reaction: Option<bool>,
+    #[description = "リアクション追加時に参加通知を送るかどうか"] notify_on_reaction: Option<bool>,
+    #[description = "通知メッセージを削除するまでの時間 (例: 30分, 2h)"]
+    delete_after_minutes: Option<String>,
+    #[description = "人数が揃ってから開始するまでの時間 (例: 5分)"] start_delay_minutes: Option<
+        String,
+    >,
+    #[description = "追加のリアクション (例: 👀=観戦, 🎥=フィルのみ=count)"]
+    extra_reactions: Option<String>,
+    #[description = "開始後に募集メッセージを削除するまでの時間 (例: 1時間)"]
+    cleanup_after_start_minutes: Option<String>,
+    #[description = "あと1人になったら主催者に通知するかどうか"]
+    notify_organizer_when_close: Option<bool>,
+    #[description = "リアクションした順に先着 max_players 人を参加者にするかどうか"]
+    strict_order: Option<bool>,
+    #[description = "募集のカテゴリ (カテゴリの既定値を使います)"]
+    #[autocomplete = "category::autocomplete_category"]
+    category: Option<String>,
+    #[description = "開始予定の日時 (例: 21:00, 明日21時, 2時間後)。人数が揃うと締め切ります"]
+    start_time: Option<String>,
+    #[description = "募集メッセージと通知の言語 (既定: サーバーの言語)"] language: Option<Language>,
+    #[description = "募集の説明 (ランク帯など, 500 文字まで)"]
+    #[max_length = 500]
+    description: Option<String>,
+    #[description = "サイレント時間帯でもメンションするかどうか (サーバーの管理権限が必要)"]
+    override_quiet_hours: Option<bool>,
+    #[description = "参加が決まっているメンバー (@メンバー を空白区切りで 5 人まで)"]
+    preset_users: Option<String>,
+    #[description = "人数が揃ったら開始前に参加者の点呼をとるかどうか"] roll_call: Option<bool>,
+    #[description = "優先期間に参加できるロール"] priority_role: Option<Role>,
+    #[description = "優先期間の長さ (例: 30分, 既定: 30分)"] priority_window: Option<String>,
+) -> Result<(), Error> {
+    create_recruit(
+        ctx,
+        CreateOptions {
+            game_title,
+            required_players,
+            max_players,
+            enforce_cap,
+            mention_roles: [mention_role, mention_role2, mention_role3],
+            create_role,
+            auto_assign_role_on_reaction,
+            notify_on_reaction,
+            delete_after_minutes,
+            start_delay_minutes,
+            extra_reactions,
+            cleanup_after_start_minutes,
+            notify_organizer_when_close,
+            strict_order,
+            category,
+            start_time,
+            language,
+            description,
+            override_quiet_hours,
+            preset_users,
+            roll_call,
+            priority_role,
+            priority_window,
+            ..CreateOptions::default()
+        },
+    )
+    .await
+}
+
+/// ボイスチャンネルの接続中のメンバーも数える募集を作成します
+#[allow(clippy::too_many_arguments)]
+#[poise::command(slash_command, guild_only, rename = "voice")]
+async fn recruit_voice(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集するゲーム名"]
+    #[autocomplete = "game_alias::autocomplete_game_title"]
+    game_title: String,
+    #[description = "接続中のメンバーを参加者として数えるボイスチャンネル"]
+    #[channel_types("Voice", "Stage")]
+    count_voice_channel: GuildChannel,
+    #[description = "開始に必要な人数 (/settings game で人数を登録したゲームは省略できます)"]
+    required_players: Option<usize>,
+    #[description = "スピーカーミュート中のメンバーをボイスチャンネルの参加者に数えないかどうか"]
+    require_undeafened: Option<bool>,
+    #[description = "参加できる最大人数"] max_players: Option<usize>,
+    #[description = "開始時にメンションするロール"] mention_role: Option<Role>,
+    #[description = "リアクション追加時に参加通知を送るかどうか"] notify_on_reaction: Option<bool>,
+    #[description = "人数が揃ってから開始するまでの時間 (例: 5分)"] start_delay_minutes: Option<
+        String,
+    >,
+    #[description = "募集のカテゴリ (カテゴリの既定値を使います)"]
+    #[autocomplete = "category::autocomplete_category"]
+    category: Option<String>,
+    #[description = "開始予定の日時 (例: 21:00, 明日21時, 2時間後)。人数が揃うと締め切ります"]
+    start_time: Option<String>,
+    #[description = "募集メッセージと通知の言語 (既定: サーバーの言語)"] language: Option<Language>,
+) -> Result<(), Error> {
+    create_recruit(
+        ctx,
+        CreateOptions {
+            game_title,
+            required_players,
+            max_players,
+            mention_roles: [mention_role, None, None],
+            notify_on_reaction,
+            start_delay_minutes,
+            count_voice_channel: Some(count_voice_channel),
+            require_undeafened,
+            category,
+            start_time,
+            language,
+            ..CreateOptions::default()
+        },
+    )
+    .await
+}
+
+/// 通知でメンションしない動作確認用の募集を作成します (管理者のみ)
+///
+/// 通知は「【テスト】」付きで送って 5 分後に削除し, ロールの作成・付与や記録は行いません。
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    rename = "test"
+)]
+#[allow(clippy::too_many_arguments)]
+async fn recruit_test(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集するゲーム名"]
+    #[autocomplete = "game_alias::autocomplete_game_title"]
+    game_title: String,
+    #[description = "開始に必要な人数 (/settings game で人数を登録したゲームは省略できます)"]
+    required_players: Option<usize>,
+    #[description = "参加できる最大人数"] max_players: Option<usize>,
+    #[description = "開始時にメンションするロール (メンションはしません)"] mention_role: Option<
+        Role,
+    >,
+    #[description = "人数が揃ってから開始するまでの時間 (例: 5分)"] start_delay_minutes: Option<
+        String,
+    >,
+    #[description = "募集のカテゴリ (カテゴリの既定値を使います)"]
+    #[autocomplete = "category::autocomplete_category"]
+    category: Option<String>,
+    #[description = "開始予定の日時 (例: 21:00, 明日21時, 2時間後)。人数が揃うと締め切ります"]
+    start_time: Option<String>,
+) -> Result<(), Error> {
+    create_recruit(
+        ctx,
+        CreateOptions {
+            game_title,
+            required_players,
+            max_players,
+            mention_roles: [mention_role, None, None],
+            start_delay_minutes,
+            simulate: true,
+            category,
+            start_time,
+            ..CreateOptions::default()
+        },
+    )
+    .await
+}
+
+/// `/recruit create` と, 募集の種類ごとの作成のサブコマンドで指定した内容
+#[derive(Default)]
+struct CreateOptions {
+    game_title: String,
+    required_players: Option<usize>,
+    max_players: Option<usize>,
+    enforce_cap: Option<bool>,
+    mention_roles: [Option<Role>; 3],
+    create_role: Option<bool>,
+    auto_assign_role_on_reaction: Option<bool>,
+    notify_on_reaction: Option<bool>,
+    delete_after_minutes: Option<String>,
+    start_delay_minutes: Option<String>,
+    count_voice_channel: Option<GuildChannel>,
+    require_undeafened: Option<bool>,
+    extra_reactions: Option<String>,
+    simulate: bool,
+    cleanup_after_start_minutes: Option<String>,
+    notify_organizer_when_close: Option<bool>,
+    strict_order: Option<bool>,
+    /// 複数のゲームの募集の, 2 つ目と 3 つ目のゲーム名と人数
+    other_games: [(Option<String>, Option<usize>); 2],
+    category: Option<String>,
+    start_time: Option<String>,
+    language: Option<Language>,
+    description: Option<String>,
+    override_quiet_hours: Option<bool>,
+    preset_users: Option<String>,
+    roll_call: Option<bool>,
+    priority_role: Option<Role>,
+    priority_window: Option<String>,
+}
+
+/// 募集を作成します
+async fn create_recruit(
+    ctx: poise::Context<'_, Data, Error>,
+    options: CreateOptions,
+) -> Result<(), Error> {
+    let CreateOptions {
+        game_title,
+        required_players,
+        max_players,
+        enforce_cap,
+        mention_roles: [mention_role, mention_role2, mention_role3],
+        create_role,
+        auto_assign_role_on_reaction,
+        notify_on_reaction,
+        delete_after_minutes,
+        start_delay_minutes,
+        count_voice_channel,
+        require_undeafened,
+        extra_reactions,
+        simulate,
+        cleanup_after_start_minutes,
+        notify_organizer_when_close,
+        strict_order,
+        other_games:
+            [
+                (game_title_2, required_players_2),
+                (game_title_3, required_players_3),
+            ],
+        category,
+        start_time,
+        language,
+        description,
+        override_quiet_hours,
+        preset_users,
+        roll_call,
+        priority_role,
+        priority_window,
+    } = options;
+    // 人数を登録したゲームは, 省略した人数を登録から補い, 大きく外れた人数を確認します
+    let registered_players = ctx.guild_id().and_then(|guild_id| {
+        let settings = ctx.data().storage.guild_settings(guild_id);
+        settings
+            .game_players
+            .find(&settings.game_aliases, &game_title)
+            .cloned()
+    });
+    let (required_players, max_players) = match game_preset::resolve_players(
+        required_players,
+        max_players,
+        registered_players.as_ref(),
+    ) {
+        Ok(players) => players,
+        Err(err) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+    };
+    if required_players == 0 {
+        ctx.say("required_players は 1 以上を指定してください。")
+            .await?;
+        return Ok(());
+    }
+    if max_players.is_some_and(|max_players| max_players < required_players) {
+        ctx.say("max_players は required_players 以上を指定してください。")
+            .await?;
+        return Ok(());
+    }
+    let minutes = (|| {
+        let parse = |name, input: Option<String>, min| {
+            time_input::parse_optional_minutes(name, input.as_deref(), min)
+        };
+        Ok::<_, String>((
+            parse("delete_after_minutes", delete_after_minutes, 1)?,
+            parse("start_delay_minutes", start_delay_minutes, 1)?,
+            parse(
+                "cleanup_after_start_minutes",
+                cleanup_after_start_minutes,
+                0,
+            )?,
+            parse("priority_window", priority_window, 1)?,
+        ))
+    })();
+    let (
+        delete_after_minutes,
+        start_delay_minutes,
+        cleanup_after_start_minutes,
+        priority_window_minutes,
+    ) = match minutes {
+        Ok(minutes) => minutes,
+        Err(err) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+    };
+    let start_time = match start_time.as_deref().map(|input| {
+        time_input::parse_start_time(
+            "start_time",
+            input,
+            Timestamp::now(),
+            timezone::guild_timezone(ctx.data(), ctx.guild_id()),
+        )
+    }) {
+        Some(Ok(start_time)) => Some(start_time),
+        Some(Err(err)) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+        None => None,
+    };
+    // 参加のリアクションは投稿したときのサーバーの設定を募集ごとに保存します
+    let reaction_emoji = ctx
+        .guild_id()
+        .and_then(|guild_id| ctx.data().storage.guild_settings(guild_id).reaction_emoji);
+    let extra_reactions = match parse_extra_reactions(
+        extra_reactions.as_deref().unwrap_or_default(),
+        reaction_emoji.as_ref(),
+        &ctx.data().config.emojis,
+    ) {
+        Ok(extra_reactions) => extra_reactions,
+        Err(err) => {
+            send_ephemeral(ctx, &format!("extra_reactions が不正です: {err}")).await?;
+            return Ok(());
+        }
+    };
+
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let member_permissions = author_permissions(ctx).unwrap_or_default();
+    let simulation = simulate;
+    if simulation && !member_permissions.administrator() {
+        send_ephemeral(ctx, "動作確認用の募集は管理者のみ作成できます。").await?;
+        return Ok(());
+    }
+    let override_quiet_hours = override_quiet_hours.unwrap_or(false);
+    if override_quiet_hours && !member_permissions.manage_guild() {
+        send_ephemeral(
+            ctx,
+            "サイレント時間帯を無視する募集は, サーバーの管理権限を持つメンバーのみ作成できます。",
+        )
+        .await?;
+        return Ok(());
+    }
+    let description = match description::normalize(description) {
+        Ok(description) => description,
+        Err(err) => {
+            send_ephemeral(ctx, err).await?;
+            return Ok(());
+        }
+    };
+    // 動作確認用の募集ではロールを作成しません
+    let create_role = create_role.unwrap_or(false) && !simulation;
+    let settings = ctx.data().storage.guild_settings(guild_id);
+    let (category, category_defaults) = match category.as_deref() {
+        Some(name) => match category::find(&settings.categories, name) {
+            Some((name, defaults)) => (Some(name.to_string()), defaults.clone()),
+            None => {
+                send_ephemeral(
+                    ctx,
+                    &format!(
+                        "{} というカテゴリはありません。/settings category add で追加してください。",
+                        sanitize_text(name)
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+        },
+        None => (None, CategoryDefaults::default()),
+    };
+    let member_roles = ctx
+        .author_member()
+        .await
+        .map(|member| member.roles.clone())
+        .unwrap_or_default();
+    let explicit_roles: Vec<RoleId> = [&mention_role, &mention_role2, &mention_role3]
+        .into_iter()
+        .flatten()
+        .map(|role| role.id)
+        .collect();
+    let channel_role = channel_role::resolve(
+        &explicit_roles,
+        create_role,
+        settings.channel_roles.get(&ctx.channel_id()).copied(),
+    );
+    let mentions_role = !explicit_roles.is_empty() || create_role || channel_role.is_some();
+    if !settings.can_create_recruit(&member_roles, member_permissions, mentions_role) {
+        send_ephemeral(
+            ctx,
+            "募集を作成する権限がありません。主催者ロールを持つメンバーのみ作成できます。",
+        )
+        .await?;
+        return Ok(());
+    }
+    let preset_participants = match preset::resolve_preset_users(
+        ctx.serenity_context(),
+        preset_users.as_deref().unwrap_or_default(),
+        required_players,
+        max_players,
+    )
+    .await?
+    {
+        Ok(user_ids) => user_ids,
+        Err(err) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+    };
+
+    // 後から Bot がリアクションを読めないプライベートスレッドには投稿しません
+    let channel = ctx.guild_channel().await;
+    if let Some(channel) = &channel
+        && channel.kind == ChannelType::PrivateThread
+        && channel.member.is_none()
+        && !bot_permissions(ctx.serenity_context(), guild_id, channel.id)
+            .is_some_and(|permissions| permissions.manage_threads())
+    {
+        send_ephemeral(
+            ctx,
+            "Bot が参加していないプライベートスレッドでは募集を作成できません。",
+        )
+        .await?;
+        return Ok(());
+    }
+    if let Some(channel) = &channel {
+        match channel_check::check_recruit_channel(ctx, guild_id, channel).await? {
+            ChannelSuitability::Suitable => {}
+            ChannelSuitability::Refuse(reason) => {
+                send_ephemeral(ctx, reason).await?;
+                return Ok(());
+            }
+            ChannelSuitability::Warn(warnings) => {
+                let content = format!(
+                    "{}\nこのチャンネルに募集を投稿しますか?",
+                    warnings.join("\n")
+                );
+                if !confirm::confirm(ctx, &content, "投稿する").await? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    if let Some(mismatch) = registered_players
+        .as_ref()
+        .and_then(|registered| game_preset::mismatch(registered, required_players, max_players))
+    {
+        let content = format!(
+            "{mismatch}
+この人数で募集を投稿しますか?"
+        );
+        if !confirm::confirm(ctx, &content, "投稿する").await? {
+            return Ok(());
+        }
+    }
+
+    let game_options = match multi_game::build_game_options(
+        GameOption {
+            game_title: sanitize_text(&game_title),
+            required_players,
+        },
+        [
+            (
+                game_title_2.as_deref().map(sanitize_text),
+                required_players_2,
+            ),
+            (
+                game_title_3.as_deref().map(sanitize_text),
+                required_players_3,
+            ),
+        ],
+    ) {
+        Ok(game_options) => game_options,
+        Err(err) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+    };
+    if !game_options.is_empty()
+        && (max_players.is_some()
+            || start_delay_minutes.is_some()
+            || count_voice_channel.is_some()
+            || strict_order == Some(true)
+            || notify_organizer_when_close == Some(true)
+            || start_time.is_some())
+    {
+        send_ephemeral(
+            ctx,
+            "複数のゲームの募集では max_players, start_delay_minutes, count_voice_channel, strict_order, notify_organizer_when_close, start_time は指定できません。",
+        )
+        .await?;
+        return Ok(());
+    }
+    // 複数のゲームの募集では, ゲーム名を並べたものを募集のゲーム名とします
+    let (game_title, required_players) = if game_options.is_empty() {
+        (sanitize_text(&game_title), required_players)
+    } else {
+        let titles: Vec<&str> = game_options
+            .iter()
+            .map(|option| option.game_title.as_str())
+            .collect();
+        let required_players = game_options
+            .iter()
+            .map(|option| option.required_players)
+            .min()
+            .unwrap_or(required_players);
+        (titles.join(" / "), required_players)
+    };
+    let mut mention_role_ids = Vec::new();
+    let mut created_role_id = None;
+    let mut role_notes = Vec::new();
+    match mention_role {
+        Some(ref role) => mention_role_ids.push(role.id),
+        None if create_role => {
+            let role_name =
+                created_role::role_name(settings.role_name_prefix.as_deref(), &game_title);
+            let roles = created_role::guild_roles(ctx.serenity_context(), guild_id).await?;
+            if let Some(role_id) = created_role::find_reusable(&roles, &role_name.name) {
+                role_notes.push(format!("既存のロール {} を使います", role_id.mention()));
+                mention_role_ids.push(role_id);
+            } else if created_role::MAX_GUILD_ROLES <= roles.len() {
+                send_ephemeral(ctx, discord_error::RoleErrorKind::TooManyRoles.message()).await?;
+                return Ok(());
+            } else {
+                let role_id =
+                    match create_recruit_role(ctx.serenity_context(), guild_id, &role_name.name)
+                        .await?
+                    {
+                        Ok(role_id) => role_id,
+                        Err(kind) => {
+                            send_ephemeral(ctx, kind.message()).await?;
+                            return Ok(());
+                        }
+                    };
+                if role_name.truncated {
+                    role_notes.push(format!(
+                        "ゲーム名が長いため, ロール名を {} 文字までに省略しました",
+                        created_role::MAX_ROLE_NAME_CHARS
+                    ));
+                }
+                created_role_id = Some(role_id);
+                mention_role_ids.push(role_id);
+            }
+        }
+        None => mention_role_ids.extend(channel_role),
+    }
+    for role in [mention_role2, mention_role3].into_iter().flatten() {
+        if !mention_role_ids.contains(&role.id) {
+            mention_role_ids.push(role.id);
+        }
+    }
+
+    let notify_on_reaction = category::resolve(
+        notify_on_reaction,
+        category_defaults.notify_on_reaction,
+        None,
+        true,
+    );
+    let auto_assign_role_on_reaction = category::resolve(
+        auto_assign_role_on_reaction,
+        category_defaults.auto_assign_role_on_reaction,
+        None,
+        create_role,
+    ) && !mention_role_ids.is_empty();
+    let count_voice_channel_id = count_voice_channel.as_ref().map(|channel| channel.id);
+    let require_undeafened =
+        require_undeafened.unwrap_or(false) && count_voice_channel_id.is_some();
+
+    // 複数のゲームの募集は, 最初に人数が揃った選択肢ですぐに締め切るため点呼をとりません
+    let roll_call_minutes =
+        (category::resolve(roll_call, category_defaults.roll_call, None, false)
+            && game_options.is_empty())
+        .then(|| {
+            category_defaults
+                .roll_call_minutes
+                .unwrap_or(roll_call::DEFAULT_ROLL_CALL_MINUTES)
+        });
+    // 優先期間は優先するロールを指定した, 1 つのゲームの募集でのみ使います
+    let priority_role = priority_role
+        .map(|role| role.id)
+        .or(category_defaults.priority_role)
+        .filter(|_| game_options.is_empty());
+    let priority_until = priority_role.map(|_| {
+        scheduler::fire_at_after_minutes(category::resolve(
+            priority_window_minutes,
+            category_defaults.priority_window_minutes,
+            None,
+            priority::DEFAULT_PRIORITY_WINDOW_MINUTES,
+        ))
+    });
+    // 開始すると締め切る募集では, 次のロビーを待つ参加者を受け付けられません
+    let multi_lobby = category_defaults.multi_lobby == Some(true)
+        && game_options.is_empty()
+        && start_time.is_none();
+
+    let config = RecruitConfig {
+        game_title,
+        required_players,
+        max_players,
+        enforce_cap: enforce_cap.unwrap_or(false) && max_players.is_some(),
+        organizer: Some(ctx.author().id),
+        description,
+        mention_roles: mention_role_ids,
+        notify_on_reaction,
+        auto_assign_role_on_reaction,
+        delete_after_minutes: category::resolve(
+            delete_after_minutes,
+            category_defaults.delete_after_minutes,
+            None,
+            DEFAULT_DELETE_AFTER_MINUTES,
+        ),
+        start_delay_minutes,
+        count_voice_channel: count_voice_channel_id,
+        require_undeafened,
+        simulation,
+        extra_reactions,
+        // どこにも指定がなければ, 開始した募集メッセージは削除しません
+        cleanup_after_start_minutes: category::resolve(
+            cleanup_after_start_minutes.map(Some),
+            category_defaults.cleanup_after_start_minutes.map(Some),
+            settings.cleanup_after_start_minutes.map(Some),
+            None,
+        ),
+        notify_organizer_when_close: notify_organizer_when_close.unwrap_or(false),
+        strict_order: strict_order.unwrap_or(false),
+        game_options,
+        category,
+        start_time,
+        // リマインドは開始予定の日時がある募集でのみ使います
+        remind_before_minutes: start_time.and(category::resolve(
+            None,
+            category_defaults.remind_before_minutes.map(Some),
+            settings.remind_before_minutes.map(Some),
+            None,
+        )),
+        language: Some(category::resolve(
+            language,
+            category_defaults.language,
+            Some(settings.language),
+            Language::default(),
+        )),
+        roll_call_minutes,
+        priority_role,
+        priority_until,
+        over_mention: category_defaults.over_mention.unwrap_or_default(),
+        multi_lobby,
+        override_quiet_hours,
+        preset_participants: preset_participants.clone(),
+        participation_mode: ParticipationMode::default(),
+        require_voice_at_start: None,
+        voice_grace_minutes: None,
+        slots: Vec::new(),
+        reaction_emoji,
+        application_question: None,
+    };
+    let option_reactions = config.has_option_reactions();
+    let posted = match post_recruit_or_delete_role(
+        ctx.serenity_context(),
+        ctx.data(),
+        guild_id,
+        ctx.channel_id(),
+        config,
+        created_role_id,
+    )
+    .await?
+    {
+        Ok(posted) => posted,
+        Err(content) => {
+            send_ephemeral(ctx, &content).await?;
+            return Ok(());
+        }
+    };
+
+    // 登録した参加者は, 先着順でもリアクションした参加者より先に並べます
+    if !preset_participants.is_empty()
+        && let Err(err) = ctx
+            .data()
+            .storage
+            .set_participant_order(posted.id, preset_participants.clone())
+    {
+        eprintln!("Failed to save participant order: {err}");
+    }
+
+    let content = if simulation {
+        "動作確認用の募集メッセージを投稿しました。通知はメンションせずに送信し, 5 分後に削除します"
+    } else {
+        "募集メッセージを投稿しました"
+    };
+    let mut content = format!(
+        "{content} {} {}",
+        format_progress(Language::Ja, preset_participants.len(), required_players),
+        posted.link()
+    );
+    if let Some(role_id) = channel_role {
+        content += &format!(
+            "\nこのチャンネルの設定により {} をメンションします",
+            role_id.mention()
+        );
+    }
+    for note in role_notes {
+        content += &format!("\n{note}");
+    }
+    ctx.send(
+        CreateReply::default()
+            .content(content)
+            .ephemeral(true)
+            .components(recruit_actions::components(
+                &posted,
+                ctx.author().id,
+                option_reactions,
+            )),
+    )
+    .await?;
+    Ok(())
+}
+
+/// `create_role` の募集のために, メンションできるロール `name` を作成します
+///
+/// 権限がないなど, 作成できなかった理由を実行者に説明できる場合は `Ok(Err(_))` を返します。
+async fn create_recruit_role(
+    api: &impl DiscordApi,
+    guild_id: GuildId,
+    name: &str,
+) -> Result<Result<RoleId, discord_error::RoleErrorKind>, Error> {
+    match api
+        .create_role(guild_id, EditRole::new().name(name).mentionable(true))
+        .await
+    {
+        Ok(role) => Ok(Ok(role.id)),
+        Err(err) => match discord_error::role_error_kind(&err) {
+            Some(kind) => {
+                eprintln!("Failed to create role: {err}");
+                Ok(Err(kind))
+            }
+            None => Err(err.into()),
+        },
+    }
+}
+
+/// 募集メッセージを投稿します
+///
+/// 投稿に失敗した場合は, 募集のために作成したロール `created_role_id` が残らないよう削除し,
+/// 実行者に表示する説明を `Ok(Err(_))` で返します。
+async fn post_recruit_or_delete_role<A: DiscordApi>(
+    api: &A,
+    data: &Data,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    config: RecruitConfig,
+    created_role_id: Option<RoleId>,
+) -> Result<Result<Message, String>, Error> {
+    let err = match post_recruit_message(
+        api,
+        data,
+        Some(guild_id),
+        channel_id,
+        config,
+        None,
+        &Mentions::none(),
+    )
+    .await
+    {
+        Ok(posted) => return Ok(Ok(posted)),
+        Err(err) => err,
+    };
+    let Some(role_id) = created_role_id else {
+        return Err(err);
+    };
+    eprintln!("Failed to post recruit message: {err}");
+    let content = match api.delete_role(guild_id, role_id).await {
+        Ok(()) => format!("募集の作成に失敗したため作成したロールを削除しました: {err}"),
+        Err(delete_err) => {
+            eprintln!("Failed to delete role {role_id} after error: {delete_err}");
+            format!("募集の作成に失敗しました (作成したロールの削除にも失敗しました): {err}")
+        }
+    };
+    Ok(Err(content))
+}
+
+#[derive(Debug, poise::Modal)]
+#[name = "募集化する"]
+struct ImportRecruitModal {
+    #[name = "ゲーム名"]
+    #[max_length = 100]
+    game_title: String,
+    #[name = "開始に必要な人数"]
+    #[max_length = 3]
+    required_players: String,
+    #[name = "参加できる最大人数 (任意)"]
+    #[max_length = 3]
+    max_players: Option<String>,
+    #[name = "通知メッセージを削除するまでの分数 (任意)"]
+    #[max_length = 5]
+    delete_after_minutes: Option<String>,
+}
+
+/// 既存のメッセージを元に募集を作成します
+#[poise::command(context_menu_command = "募集化する", guild_only)]
+async fn import_recruit(
+    ctx: poise::ApplicationContext<'_, Data, Error>,
+    message: Message,
+) -> Result<(), Error> {
+    if message.author.bot {
+        send_ephemeral(ctx.into(), "Bot のメッセージは募集にできません。").await?;
+        return Ok(());
+    }
+    // 本文を読み取れないメッセージを, 説明のない募集にしてしまわないようにします
+    let Some(content) = ctx.data.capabilities.foreign_content(&message) else {
+        send_ephemeral(
+            ctx.into(),
+            &format!("{MESSAGE_CONTENT_REQUIRED} (元のメッセージの本文を読み取れませんでした)"),
+        )
+        .await?;
+        return Ok(());
+    };
+    let description = Some(sanitize_description(content)).filter(|text| !text.is_empty());
+
+    let Some(modal) = poise::execute_modal(ctx, None::<ImportRecruitModal>, None).await? else {
+        return Ok(());
+    };
+
+    let parse_count = |value: &str| value.trim().parse::<usize>().ok().filter(|&n| 0 < n);
+    let Some(required_players) = parse_count(&modal.required_players) else {
+        send_ephemeral(
+            ctx.into(),
+            "開始に必要な人数は 1 以上の数字で入力してください。",
+        )
+        .await?;
+        return Ok(());
+    };
+    let max_players = match modal.max_players.as_deref().map(parse_count) {
+        None => None,
+        Some(Some(max_players)) if required_players <= max_players => Some(max_players),
+        Some(_) => {
+            send_ephemeral(
+                ctx.into(),
+                "参加できる最大人数は開始に必要な人数以上の数字で入力してください。",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let delete_after_minutes = match modal.delete_after_minutes.as_deref() {
+        None => DEFAULT_DELETE_AFTER_MINUTES,
+        Some(value) => match value.trim().parse::<u64>() {
+            Ok(minutes) if 0 < minutes => minutes,
+            _ => {
+                send_ephemeral(
+                    ctx.into(),
+                    "通知メッセージを削除するまでの分数は 1 以上の数字で入力してください。",
+                )
+                .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    let config = RecruitConfig {
+        game_title: sanitize_text(modal.game_title.trim()),
+        required_players,
+        max_players,
+        enforce_cap: false,
+        organizer: Some(message.author.id),
+        description,
+        mention_roles: Vec::new(),
+        notify_on_reaction: true,
+        auto_assign_role_on_reaction: false,
+        delete_after_minutes,
+        start_delay_minutes: None,
+        count_voice_channel: None,
+        require_undeafened: false,
+        simulation: false,
+        extra_reactions: Vec::new(),
+        cleanup_after_start_minutes: ctx.guild_id().and_then(|guild_id| {
+            ctx.data()
+                .storage
+                .guild_settings(guild_id)
+                .cleanup_after_start_minutes
+        }),
+        notify_organizer_when_close: false,
+        strict_order: false,
+        game_options: Vec::new(),
+        category: None,
+        start_time: None,
+        remind_before_minutes: None,
+        language: Some(i18n::guild_language(ctx.data(), ctx.guild_id())),
+        roll_call_minutes: None,
+        priority_role: None,
+        priority_until: None,
+        over_mention: OverMention::default(),
+        multi_lobby: false,
+        override_quiet_hours: false,
+        preset_participants: Vec::new(),
+        participation_mode: ParticipationMode::default(),
+        require_voice_at_start: None,
+        voice_grace_minutes: None,
+        slots: Vec::new(),
+        reaction_emoji: ctx
+            .guild_id()
+            .and_then(|guild_id| ctx.data().storage.guild_settings(guild_id).reaction_emoji),
+        application_question: None,
+    };
+    post_recruit_message(
+        ctx.serenity_context(),
+        ctx.data(),
+        ctx.guild_id(),
+        message.channel_id,
+        config,
+        Some(&message),
+        &Mentions::none(),
+    )
+    .await?;
+
+    let content = format!(
+        "募集メッセージを投稿しました {}",
+        format_progress(Language::Ja, 0, required_players)
+    );
+    send_ephemeral(ctx.into(), &content).await?;
+    Ok(())
+}
+
+/// 募集メッセージを投稿し, 参加用のリアクションを付与します
+///
+/// `reply_to` を指定した場合はそのメッセージへの返信として, 返信先へのメンションなしで投稿します。
+/// メンションは `mentions` にだけ送ります (説明文のメンションや `auto_assign_role_on_reaction` のロールには送りません)。
+/// Bot がチャンネルでリアクションを付けられない場合は, 1 つのゲームの募集ならボタンで参加を受け付けます。
+/// サーバーの `config_storage` が `database` の場合は, 募集設定を保存ファイルに保存して toml ブロックを表示しません。
+async fn post_recruit_message<A: DiscordApi>(
+    api: &A,
+    data: &Data,
+    guild_id: Option<GuildId>,
+    channel_id: ChannelId,
+    config: RecruitConfig,
+    reply_to: Option<&Message>,
+    mentions: &Mentions,
+) -> Result<Message, Error> {
+    // 読み取るときと同じ値で投稿します
+    let (mut config, _) = config.validate();
+    // 権限がないと分かっているチャンネルでは, 初めからボタンで参加を受け付けます
+    let cannot_react = !config.has_option_reactions()
+        && guild_id
+            .zip(api.serenity_context())
+            .and_then(|(guild_id, ctx)| bot_permissions(ctx, guild_id, channel_id))
+            .is_some_and(|permissions| {
+                !(permissions.add_reactions() && permissions.read_message_history())
+            });
+    if cannot_react {
+        config.participation_mode = ParticipationMode::Buttons;
+    }
+    let recruit_message = RecruitMessage::new(config.clone());
+    let use_database = guild_id.is_some_and(|guild_id| {
+        data.storage.guild_settings(guild_id).config_storage == ConfigStorage::Database
+    });
+    let content = if use_database {
+        recruit_message.render_without_config(&data.config.emojis)
+    } else {
+        recruit_message.render(&data.config.emojis)
+    };
+    let mut create_message = CreateMessage::new()
+        .content(content)
+        .allowed_mentions(mentions.allowed_mentions());
+    if config.uses_buttons() {
+        create_message = create_message.components(participation_buttons::components(&config));
+    }
+    if let Some(reply_to) = reply_to {
+        create_message = create_message
+            .reference_message(reply_to)
+            .allowed_mentions(mentions.allowed_mentions().replied_user(false));
+    }
+
+    let mut message = api.send_message(channel_id, create_message).await?;
+    // リアクションを付ける前に保存し, 付けている間のリアクションも募集として扱います
+    if use_database
+        && let Err(err) = data
+            .storage
+            .set_recruit_message(message.id, recruit_message)
+    {
+        if let Err(delete_err) = api.delete_message(message.channel_id, message.id).await {
+            eprintln!("Failed to delete recruit message after error: {delete_err}");
+        }
+        return Err(err);
+    }
+    if cannot_react {
+        participation_buttons::report_fallback(api, data, guild_id, &message, true).await;
+    } else if let Err(err) = add_recruit_reactions(api, data, &message, &config).await {
+        if config.has_option_reactions() || !discord_error::is_missing_permissions(&err) {
+            data.forget_deleted_message(message.id);
+            // リアクションのない募集メッセージは完了できないため削除します
+            if let Err(delete_err) = api.delete_message(message.channel_id, message.id).await {
+                eprintln!("Failed to delete recruit message after error: {delete_err}");
+            }
+            return Err(err);
+        }
+        eprintln!("Failed to add recruit reactions: {err}");
+        let buttons = match participation_buttons::switch_to_buttons(
+            api,
+            data,
+            &mut config,
+            &message,
+        )
+        .await
+        {
+            Ok(edited) => {
+                message = edited;
+                true
+            }
+            // ボタンも付けられない場合も, メニューからの参加は受け付けられます
+            Err(edit_err) => {
+                eprintln!("Failed to switch recruit to buttons: {edit_err}");
+                // 案内を送れなくても募集は受け付けているため, 募集メッセージは残します
+                if let Err(err) =
+                    participation_buttons::send_join_command_notice(api, &message).await
+                {
+                    eprintln!("Failed to send /join notice: {err}");
+                }
+                false
+            }
+        };
+        participation_buttons::report_fallback(api, data, guild_id, &message, buttons).await;
+    }
+
+    if let Some(voice_channel_id) = config.count_voice_channel {
+        register_voice_recruit(data, voice_channel_id, message.channel_id, message.id);
+    }
+    remember_recruit_location(data, guild_id, &message);
+    expiry::schedule_expiry(data, &config, message.channel_id, message.id, guild_id);
+    summary::mark_message(data, message.id);
+    mirror::create(api, data, guild_id, &message, &config).await;
+    if let Some(priority_until) = config.priority_until {
+        data.scheduler.enqueue(
+            &*data.storage,
+            priority_until,
+            Job::EndPriorityWindow {
+                channel_id: message.channel_id,
+                message_id: message.id,
+                guild_id,
+            },
+        );
+    }
+    Ok(message)
+}
+
+/// サーバーやチャンネルの削除時に記録を消せるよう, 募集メッセージの場所を保存します
+fn remember_recruit_location(data: &Data, guild_id: Option<GuildId>, message: &Message) {
+    let location = RecruitLocation {
+        guild_id,
+        channel_id: message.channel_id,
+    };
+    if let Err(err) = data.storage.remember_recruit_location(message.id, location) {
+        eprintln!("Failed to save recruit location: {err}");
+    }
+}
+
+async fn add_recruit_reactions(
+    api: &impl DiscordApi,
+    data: &Data,
+    message: &Message,
+    config: &RecruitConfig,
+) -> Result<(), Error> {
+    for reaction_type in config.reaction_types(&data.config.emojis) {
+        api.create_reaction(message.channel_id, message.id, reaction_type)
+            .await?;
+    }
+    Ok(())
+}
+
+/// 募集の主催者を変更します
+#[poise::command(slash_command, guild_only)]
+async fn transfer_recruit(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集メッセージのリンク"] message: Message,
+    #[description = "新しい主催者"] new_organizer: User,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+
+    let recruit_message =
+        match parse_bot_recruit_message(ctx.serenity_context(), ctx.data(), &message) {
+            Some(recruit_message) => recruit_message,
+            None => {
+                send_ephemeral(ctx, "指定されたメッセージは募集メッセージではありません。").await?;
+                return Ok(());
+            }
+        };
+
+    if !can_manage_recruit(ctx, &recruit_message.config) {
+        send_ephemeral(
+            ctx,
+            "主催者またはメッセージの管理権限を持つメンバーのみ主催者を変更できます。",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    if new_organizer.bot {
+        send_ephemeral(ctx, "Bot を主催者にすることはできません。").await?;
+        return Ok(());
+    }
+    if guild_id.member(ctx, new_organizer.id).await.is_err() {
+        send_ephemeral(
+            ctx,
+            "サーバーに参加していないユーザーは主催者にできません。",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let edited = {
+        let _guard = ctx.data().lock_message(message.id).await;
+        recruit_edit::edit_recruit_message(
+            ctx.serenity_context(),
+            ctx.data(),
+            message.channel_id,
+            message.id,
+            |recruit_message| {
+                recruit_message.config.organizer = Some(new_organizer.id);
+                Some(())
+            },
+        )
+        .await?
+    };
+    let Some((recruit_message, ())) = edited else {
+        return Ok(());
+    };
+    let config = recruit_message.config;
+
+    let policy = NotificationPolicy::new(&config);
+    let content = format!(
+        "{} の主催者が {} に変更されました",
+        config.game_title,
+        new_organizer.mention()
+    );
+    policy
+        .send(
+            ctx.serenity_context(),
+            ctx.data(),
+            message.channel_id,
+            policy.message(&content, &Mentions::user(new_organizer.id)),
+        )
+        .await?;
+
+    send_ephemeral(ctx, "主催者を変更しました").await?;
+    Ok(())
+}
+
+/// サーバーごとの設定を変更します
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "MANAGE_GUILD",
+    subcommands(
+        "settings_webhook_url",
+        "settings_organizer_role",
+        "settings_rejection_notice",
+        "settings_role_ping_cooldown",
+        "settings_allowed_bot",
+        "settings_cleanup_after_start",
+        "settings_remind_before",
+        "settings_snooze",
+        "settings_role_prefix",
+        "settings_quiet_hours",
+        "settings_min_age",
+        "participant_age::settings_skip_spammers",
+        "settings_language",
+        "settings_config_storage",
+        "game_preset::settings_game",
+        "notice_cleanup::settings_cleanup_mode",
+        "mention_dedupe::settings_dedupe_mentions",
+        "expiry::settings_ping_on_fail",
+        "reaction_emoji::settings_emoji",
+        "member_work::settings_member_work_cap",
+        "timezone::settings_timezone",
+        "category::settings_category",
+        "settings_file::settings_export",
+        "settings_file::settings_import"
+    )
+)]
+async fn settings(_ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// チャンネルに関わるサーバーの設定を変更します
+///
+/// Discord のコマンドのサブコマンドは 25 個までのため, `/settings` から分けています。
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "channel_settings",
+    default_member_permissions = "MANAGE_GUILD",
+    subcommands(
+        "settings_audit_channel",
+        "settings_error_messages",
+        "settings_deletion_log",
+        "summary::settings_summary_channel",
+        "channel_role::settings_channel_role",
+        "mirror::settings_mirror"
+    )
+)]
+async fn channel_settings(_ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// 開始通知を送る Webhook の URL を設定します (未指定で解除)
+#[poise::command(slash_command, guild_only, rename = "webhook_url")]
+async fn settings_webhook_url(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "開始通知を POST する URL (https のみ)"] url: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+
+    #[cfg(feature = "webhook")]
+    if let Some(url) = &url
+        && let Err(err) = webhook::validate_webhook_url(url)
+    {
+        ctx.send(
+            CreateReply::default()
+                .content(format!("URL が不正です: {err}"))
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+    #[cfg(not(feature = "webhook"))]
+    if url.is_some() {
+        ctx.send(
+            CreateReply::default()
+                .content("この Bot は Webhook 機能を有効にしてビルドされていません。")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let content = match &url {
+        Some(_) => "Webhook の URL を設定しました",
+        None => "Webhook の URL を解除しました",
+    };
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| settings.webhook_url = url)?;
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// 募集を作成できる主催者ロールを設定します (未指定で解除)
+#[poise::command(slash_command, guild_only, rename = "organizer_role")]
+async fn settings_organizer_role(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集の作成に必要なロール"] role: Option<Role>,
+    #[description = "ロールへのメンションを含む募集にのみ主催者ロールを要求するかどうか"]
+    mentions_only: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let content = match &role {
+        Some(role) if mentions_only.unwrap_or(false) => format!(
+            "ロールへのメンションを含む募集の作成を {} に制限しました",
+            role.name
+        ),
+        Some(role) => format!("募集の作成を {} に制限しました", role.name),
+        None => "主催者ロールを解除しました".to_string(),
+    };
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.organizer_role = role.map(|role| role.id);
+            settings.organizer_role_for_mentions_only =
+                settings.organizer_role.is_some() && mentions_only.unwrap_or(false);
+        })?;
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// リアクションを参加者に数えなかった理由の通知先を設定します
+#[poise::command(slash_command, guild_only, rename = "rejection_notice")]
+async fn settings_rejection_notice(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "チャンネルではなく DM で通知するかどうか"] dm: bool,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| settings.rejection_notice_dm = dm)?;
+    let content = if dm {
+        "参加できなかった理由を DM で通知します"
+    } else {
+        "参加できなかった理由をチャンネルで通知します"
+    };
+    send_ephemeral(ctx, content).await?;
+    Ok(())
+}
+
+/// ロールへのメンションの最短の間隔を設定します (未指定で解除)
+#[poise::command(slash_command, guild_only, rename = "role_ping_cooldown")]
+async fn settings_role_ping_cooldown(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "同じロールを再びメンションするまでの時間 (例: 30分, 2h)"] minutes: Option<
+        String,
+    >,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let minutes = match time_input::parse_optional_minutes("minutes", minutes.as_deref(), 1) {
+        Ok(minutes) => minutes,
+        Err(err) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+    };
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.role_ping_cooldown_minutes = minutes
+        })?;
+    let content = match minutes {
+        Some(minutes) => {
+            format!("同じロールへの開始通知のメンションを {minutes} 分に 1 回までにしました")
+        }
+        None => "ロール通知のクールダウンを解除しました".to_string(),
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// 参加者として数える Bot を追加・削除します
+#[poise::command(slash_command, guild_only, rename = "allowed_bot")]
+async fn settings_allowed_bot(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "対象の Bot"] bot: User,
+    #[description = "参加者として数えるかどうか"] allowed: bool,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    if !bot.bot {
+        send_ephemeral(ctx, "Bot のアカウントを指定してください").await?;
+        return Ok(());
+    }
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings
+                .allowed_bot_participants
+                .retain(|&user_id| user_id != bot.id);
+            if allowed {
+                settings.allowed_bot_participants.push(bot.id);
+            }
+        })?;
+    let content = if allowed {
+        format!("{} のリアクションを参加者として数えます", bot.name)
+    } else {
+        format!("{} のリアクションを参加者として数えません", bot.name)
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// 開始した募集メッセージを削除するまでの分数を設定します
+#[poise::command(slash_command, guild_only, rename = "cleanup_after_start")]
+async fn settings_cleanup_after_start(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "開始後に募集メッセージを削除するまでの時間 (例: 1時間)"] minutes: Option<
+        String,
+    >,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let minutes = match time_input::parse_optional_minutes("minutes", minutes.as_deref(), 0) {
+        Ok(minutes) => minutes,
+        Err(err) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+    };
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.cleanup_after_start_minutes = minutes
+        })?;
+    let content = match minutes {
+        Some(minutes) => format!("開始した募集メッセージを {minutes} 分後に削除します"),
+        None => "開始した募集メッセージを削除しません".to_string(),
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// 募集メッセージと通知の言語の既定値を設定します
+#[poise::command(slash_command, guild_only, rename = "language")]
+async fn settings_language(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集メッセージと通知の言語"] language: Language,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| settings.language = language)?;
+    let content = format!(
+        "募集メッセージと通知の言語の既定値を {} にしました",
+        language.code()
+    );
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// 新しく投稿する募集の設定の保存先を設定します
+///
+/// 保存ファイルを選ぶと, 募集メッセージに toml ブロックを表示しません。
+/// 変更前に投稿した募集は, 引き続き投稿したときの保存先から読み取ります。
+#[poise::command(slash_command, guild_only, rename = "config_storage")]
+async fn settings_config_storage(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集設定の保存先"] config_storage: ConfigStorage,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.config_storage = config_storage
+        })?;
+    let content = format!(
+        "新しく投稿する募集の設定の保存先を {} にしました",
+        config_storage.code()
+    );
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// Bot が削除したメッセージの記録先を設定します
+///
+/// 通知の自動削除と開始した募集の削除で, 削除する前に本文などを記録します。
+#[poise::command(slash_command, guild_only, rename = "deletion_log")]
+async fn settings_deletion_log(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "削除したメッセージの記録先"] destination: DeletionLogDestination,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let storage = &ctx.data().storage;
+    storage.update_guild_settings(guild_id, |settings| settings.deletion_log = destination)?;
+    let has_audit_channel = storage.guild_settings(guild_id).audit_channel.is_some();
+    let content = match destination {
+        DeletionLogDestination::Off => "削除したメッセージを記録しません".to_string(),
+        DeletionLogDestination::AuditChannel if !has_audit_channel => {
+            "削除したメッセージを管理者向けのチャンネルに記録します (`/channel_settings audit_channel` でチャンネルを設定してください)".to_string()
+        }
+        destination => format!(
+            "削除したメッセージを {} に記録します",
+            destination.code()
+        ),
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// 開始予定のリマインドを送る時間を設定します (未指定で解除)
+#[poise::command(slash_command, guild_only, rename = "remind_before")]
+async fn settings_remind_before(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "開始予定のどれだけ前に参加者へ DM でお知らせするか (例: 30分)"]
+    minutes: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let minutes = match time_input::parse_optional_minutes("minutes", minutes.as_deref(), 1) {
+        Ok(minutes) => minutes,
+        Err(err) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+    };
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.remind_before_minutes = minutes
+        })?;
+    let content = match minutes {
+        Some(minutes) => format!("開始予定の {minutes} 分前に参加者へ DM でお知らせします"),
+        None => "開始予定のリマインドを解除しました".to_string(),
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// 作成するロールの名前の先頭に付ける文字を設定します
+#[poise::command(slash_command, guild_only, rename = "role_prefix")]
+async fn settings_role_prefix(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "作成するロールの名前の先頭に付ける文字 (例: 🎮)"]
+    #[max_length = 10]
+    prefix: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let prefix = prefix.filter(|prefix| !prefix.trim().is_empty());
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.role_name_prefix = prefix.clone()
+        })?;
+    let content = match prefix {
+        Some(prefix) => format!("作成するロールの名前の先頭に「{prefix}」を付けます"),
+        None => "作成するロールの名前の先頭に付ける文字を解除しました".to_string(),
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// メンションしないサイレント時間帯を設定します (未指定で解除)
+///
+/// 時刻はサーバーのタイムゾーン (`/settings timezone`) で扱います。
+#[poise::command(slash_command, guild_only, rename = "quiet_hours")]
+async fn settings_quiet_hours(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "サイレント時間帯の開始 (例: 23:00)"] start: Option<String>,
+    #[description = "サイレント時間帯の終了 (例: 07:00)"] end: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let quiet_hours = match (start, end) {
+        (Some(start), Some(end)) => {
+            let (start, end) = match (TimeOfDay::parse(&start), TimeOfDay::parse(&end)) {
+                (Ok(start), Ok(end)) => (start, end),
+                (Err(err), _) | (_, Err(err)) => {
+                    send_ephemeral(ctx, &err.to_string()).await?;
+                    return Ok(());
+                }
+            };
+            if start == end {
+                send_ephemeral(ctx, "開始と終了には異なる時刻を指定してください").await?;
+                return Ok(());
+            }
+            Some(QuietHours { start, end })
+        }
+        (None, None) => None,
+        _ => {
+            send_ephemeral(ctx, "開始と終了の両方を指定してください").await?;
+            return Ok(());
+        }
+    };
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| settings.quiet_hours = quiet_hours)?;
+    let content = match quiet_hours {
+        Some(quiet_hours) => {
+            format!("{quiet_hours} は開始通知などをメンションせずに送ります")
+        }
+        None => "サイレント時間帯を解除しました".to_string(),
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// 参加者として数えるアカウントの条件を設定します (未指定で解除)
+///
+/// 条件を満たさないユーザーのリアクションは数えず, ロールも付与しません。
+#[poise::command(slash_command, guild_only, rename = "min_age")]
+async fn settings_min_age(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "アカウントの作成から経った最短の日数"]
+    #[min = 1]
+    account_days: Option<u64>,
+    #[description = "サーバーへの参加から経った最短の日数"]
+    #[min = 1]
+    membership_days: Option<u64>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.min_account_age_days = account_days;
+            settings.min_membership_age_days = membership_days;
+        })?;
+    let mut conditions = Vec::new();
+    if let Some(days) = account_days {
+        conditions.push(format!("アカウントの作成から {days} 日"));
+    }
+    if let Some(days) = membership_days {
+        conditions.push(format!("サーバーへの参加から {days} 日"));
+    }
+    let content = if conditions.is_empty() {
+        "参加者のアカウントの新しさの条件を解除しました".to_string()
+    } else {
+        format!(
+            "{} 以上経ったユーザーだけを参加者として数えます",
+            conditions.join("、")
+        )
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// 管理者向けのお知らせを送るチャンネルを設定します (未指定で解除)
+#[poise::command(slash_command, guild_only, rename = "audit_channel")]
+async fn settings_audit_channel(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "開始通知を送れなかったことなどを知らせるチャンネル"]
+    #[channel_types("Text")]
+    channel: Option<GuildChannel>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let channel_id = channel.map(|channel| channel.id);
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| settings.audit_channel = channel_id)?;
+    let content = match channel_id {
+        Some(channel_id) => format!("管理者向けのお知らせを {} に送ります", channel_id.mention()),
+        None => "管理者向けのお知らせのチャンネルを解除しました".to_string(),
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// 参加したユーザーへのエラーの送り方を設定します (未指定で既定に戻す)
+#[poise::command(slash_command, guild_only, rename = "error_messages")]
+async fn settings_error_messages(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集のチャンネルの代わりにエラーを送るチャンネル"]
+    #[channel_types("Text")]
+    channel: Option<GuildChannel>,
+    #[description = "募集のチャンネルのエラーでユーザーをメンションするか (既定: false)"]
+    ping_user: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let channel_id = channel.map(|channel| channel.id);
+    let ping_user = ping_user.unwrap_or(false);
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.error_channel = channel_id;
+            settings.error_ping_user = ping_user;
+        })?;
+    let content = match channel_id {
+        Some(channel_id) => format!(
+            "エラーを {} に送ります (募集のチャンネルには送りません)",
+            channel_id.mention()
+        ),
+        None if ping_user => {
+            "エラーを募集のチャンネルに送り、ユーザーをメンションします (10分後に削除します)"
+                .to_string()
+        }
+        None => "エラーを募集のチャンネルに送り、ユーザーを名前で呼びかけます (10分後に削除します)"
+            .to_string(),
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// カウントダウン中に主催者が開始を延期できる時間を設定します
+#[poise::command(slash_command, guild_only, rename = "snooze")]
+async fn settings_snooze(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "1 回の延期で遅らせる時間 (既定: 15分)"] minutes: Option<String>,
+    #[description = "延期できる合計の時間 (既定: 1時間、0 で延期できません)"] max_minutes: Option<
+        String,
+    >,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let (minutes, max_minutes) = match (
+        time_input::parse_optional_minutes("minutes", minutes.as_deref(), 1),
+        time_input::parse_optional_minutes("max_minutes", max_minutes.as_deref(), 0),
+    ) {
+        (Ok(minutes), Ok(max_minutes)) => (minutes, max_minutes),
+        (Err(err), _) | (_, Err(err)) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+    };
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.snooze_minutes = minutes;
+            settings.max_snooze_minutes = max_minutes;
+        })?;
+    let (minutes, max_minutes) = countdown::snooze_limits(ctx.data(), Some(guild_id));
+    let content = if max_minutes == 0 {
+        "カウントダウン中の開始の延期を無効にしました".to_string()
+    } else {
+        format!(
+            "カウントダウン中に主催者が 1 回 {minutes} 分、合計 {max_minutes} 分まで開始を延期できます"
+        )
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// リアクションの追加を処理します
+async fn process_reaction_add(
+    api: &impl DiscordApi,
+    data: &Data,
+    reaction: &Reaction,
+) -> Result<(), Error> {
+    match handle_reaction_add(api, data, reaction).await {
+        // 処理中に募集メッセージやチャンネルが削除された場合は, 募集がなくなったものとして扱います
+        Err(err) if discord_error::unknown_entity(&err).is_some() => {
+            data.forget_deleted_message(reaction.message_id);
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// 起動直後に預かったリアクションを処理し直します
+async fn retry_held_reactions(ctx: &Context, data: &Data) {
+    for reaction in data.startup_gate.mark_ready() {
+        if let Err(err) = handle_reaction_add(ctx, data, &reaction).await {
+            eprintln!("Failed to retry reaction held at startup: {err}");
+        }
+    }
+}
+
+async fn handle_reaction_add(
+    api: &impl DiscordApi,
+    data: &Data,
+    reaction: &Reaction,
+) -> Result<(), Error> {
+    let bot_id = api.current_user_id();
+    // ミラーは募集の本文を含むため, 内容によらず募集として扱いません
+    if data.storage.is_mirror_message(reaction.message_id) {
+        return Ok(());
+    }
+    // 記録済みの募集はメッセージの内容によらず募集として扱います
+    let known = data.storage.has_recruit_location(reaction.message_id)
+        || config_storage::is_stored(data, reaction.message_id);
+    // 追加のリアクションは募集ごとに異なるため, Bot のメッセージへのリアクションをすべて確認します
+    if !known
+        && !is_builtin_recruit_reaction(data, reaction.guild_id, &reaction.emoji)
+        && reaction.message_author_id != Some(bot_id)
+    {
+        return Ok(());
+    }
+
+    if reaction.user_id == Some(bot_id) {
+        return Ok(());
+    }
+
+    let message = api
+        .message(reaction.channel_id, reaction.message_id)
+        .await?;
+    // 保存ファイルに募集設定がない場合は, toml ブロックのある (従来の) 募集メッセージだけを読み取ります
+    let is_recruit = known
+        || (message.author.id == bot_id
+            && (config_storage::is_stored(data, message.id)
+                || message.content.contains("```toml")));
+    if !is_recruit {
+        // 起動直後は, キャッシュの準備ができてから判定し直します
+        data.startup_gate.hold(reaction);
+        return Ok(());
+    }
+    // まれに user_id や guild_id のないイベントが届くため, ユーザーごとの処理の前に補います
+    let resolved = reaction_resolve::resolve(api, data, reaction, &message).await;
+    let reaction = &resolved;
+
+    let mut config = match config_storage::load(data, &message) {
+        Ok(recruit_message) => recruit_message.config,
+        Err(_) if !is_builtin_recruit_reaction(data, reaction.guild_id, &reaction.emoji) => {
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("Failed to parse config: {err}");
+            let language = i18n::guild_language(data, reaction.guild_id);
+            send_error_message(api, data, reaction, language).await?;
+            return Ok(());
+        }
+    };
+    i18n::resolve_language(data, &mut config, reaction.guild_id);
+    if drop_out::is_drop_out_reaction(data, &reaction.emoji) {
+        return drop_out::handle_drop_out_reaction(api, data, &config, reaction, &message).await;
+    }
+    let Some(recruit_reaction) = config.recruit_reaction(&reaction.emoji, &data.config.emojis)
+    else {
+        return Ok(());
+    };
+    if let RecruitReaction::GameOption(index) = recruit_reaction {
+        return multi_game::handle_option_reaction(api, data, reaction, message.id, index).await;
+    }
+    if let RecruitReaction::Slot(index) = recruit_reaction {
+        return slots::handle_slot_reaction(api, data, reaction, message.id, index).await;
+    }
+    if !recruit_reaction.counts() {
+        return Ok(());
+    }
+
+    if config.required_players == 0 {
+        send_error_message(api, data, reaction, config.language()).await?;
+        return Ok(());
+    }
+    if config.delete_after_minutes == 0 {
+        send_error_message(api, data, reaction, config.language()).await?;
+        return Ok(());
+    }
+
+    let policy = NotificationPolicy::new(&config);
+    let _guard = data.lock_message(message.id).await;
+    // 場所を記録する前に投稿された募集も, リアクションがあれば記録します
+    remember_recruit_location(data, reaction.guild_id, &message);
+
+    // 開始通知を送った後にリアクションを付け直せていない募集は, 付け直しだけをやり直します
+    let marker = data.storage.start_marker(message.id);
+    if !config.has_option_reactions()
+        && start_marker::decide(marker.as_ref(), false) == StartDecision::Rearm
+    {
+        return start_marker::rearm(api, data, &config, message.channel_id, message.id).await;
+    }
+    // キャンセルした募集と, 開始予定の日時がある募集で人数が揃って締め切った後のリアクションでは開始しません
+    let state = config_storage::load(data, &message)
+        .map(|recruit| recruit.state)
+        .unwrap_or_default();
+    if state.cancelled || (config.start_time.is_some() && state.started) {
+        return Ok(());
+    }
+    // 受付を終了した募集では, 確定した参加者以外を数えません
+    if let Some(closed) = &state.closed_entries {
+        if let Some(user_id) = reaction.user_id
+            && !closed.participants.contains(&user_id)
+        {
+            if closed.remove_late_reactions
+                && let Err(err) = discord_api::delete_user_reaction(api, reaction).await
+            {
+                eprintln!("Failed to remove reaction after entries closed: {err}");
+            }
+            rejection::notify_rejection(
+                api,
+                data,
+                &config,
+                &policy,
+                reaction,
+                user_id,
+                RejectionReason::EntriesClosed,
+            )
+            .await?;
+        }
+        return Ok(());
+    }
+
+    // ロビーに入った参加者のリアクションは, 次のロビーの参加として扱いません
+    if config.multi_lobby
+        && let Some(user_id) = reaction.user_id
+        && lobby::is_placed(data, message.id, user_id)
+    {
+        return Ok(());
+    }
+
+    // 作成やサーバーへの参加が新しいアカウントのリアクションは数えず, ロールも付与しません
+    if let (Some(guild_id), Some(user_id)) = (reaction.guild_id, reaction.user_id)
+        && let Some(reason) =
+            participant_age::rejection(api, data, guild_id, user_id, reaction.member.as_ref()).await
+    {
+        rejection::notify_rejection(api, data, &config, &policy, reaction, user_id, reason).await?;
+        return Ok(());
+    }
+
+    // 優先期間中は, 優先するロールを持たないユーザーを後回しにして数えません
+    if let Some(role_id) = config.priority_role
+        && let Some(user_id) = reaction.user_id
+        && priority::is_window_open(config.priority_until, Timestamp::now())
+        && !priority::has_role(api, reaction, role_id).await
+    {
+        return priority::defer_participant(
+            api,
+            data,
+            &config,
+            &policy,
+            message.id,
+            message.channel_id,
+            user_id,
+        )
+        .await;
+    }
+
+    if let Some(voice_channel_id) = config.count_voice_channel {
+        register_voice_recruit(data, voice_channel_id, message.channel_id, message.id);
+    }
+
+    if participant_count::can_skip_full_count(
+        &config,
+        recruit_reaction,
+        participant_count::cached_count(data, message.id),
+    ) {
+        participant_count::record_skipped(data, message.id);
+        if let Some(user_id) = reaction.user_id {
+            participant_order::append_reactor(data, message.id, user_id)?;
+        }
+        if reactor_action(data, &message, reaction, recruit_reaction) == ReactorAction::First {
+            assign_participant_role(api, data, &config, &policy, reaction).await?;
+        }
+        return Ok(());
+    }
+
+    let mut user_ids = participant_count::collect_participants_with_feedback(
+        api,
+        data,
+        &message,
+        &config,
+        reaction.guild_id,
+    )
+    .await?;
+    // REST API の結果に反映される前でも, リアクションしたユーザーを 1 人として数えます
+    if let Some(user_id) = reaction.user_id
+        && reaction.member.as_ref().is_none_or(|member| {
+            is_counted_reactor(
+                &member.user,
+                &allowed_bot_participants(data, reaction.guild_id),
+            )
+        })
+    {
+        user_ids.insert(user_id);
+    }
+    participant_count::store_count(data, message.id, user_ids.len());
+
+    // 開始通知で先着の参加者を選べるよう, どの募集でも参加した順を記録します
+    let order = participant_order::record_reactor(data, message.id, reaction.user_id, &user_ids)?;
+    let is_over_capacity = |user_id| {
+        if config.strict_order {
+            participant_order::is_over_slots(&order, config.max_players, user_id)
+        } else {
+            is_over_capacity(&config, &user_ids, user_id)
+        }
+    };
+    if let Some(user_id) = reaction.user_id
+        && is_over_capacity(user_id)
+    {
+        if config.enforce_cap
+            && let Err(err) = discord_api::delete_user_reaction(api, reaction).await
+        {
+            eprintln!("Failed to remove reaction over capacity: {err}");
+        }
+        recruit_log::record(
+            data,
+            message.id,
+            Some(&config),
+            RecruitEventKind::OverCapacity { user_id },
+            order.iter().copied().filter(|&id| id != user_id).collect(),
+        );
+        rejection::notify_rejection(
+            api,
+            data,
+            &config,
+            &policy,
+            reaction,
+            user_id,
+            RejectionReason::OverCapacity,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let action = reactor_action(data, &message, reaction, recruit_reaction);
+    // `/notify_me` で保存した設定は, ✋ と 🤚 のどちらで参加したかより優先します
+    let stored_prefs = match (reaction.guild_id, reaction.user_id) {
+        (Some(guild_id), Some(user_id)) => data.storage.notify_prefs(guild_id, user_id),
+        _ => NotifyPrefs::default(),
+    };
+    let prefs = notify_prefs::resolve(
+        recruit_reaction == RecruitReaction::SilentParticipation,
+        stored_prefs,
+    );
+    if let ReactorAction::RetractNotice(channel_id, notice_id) = action
+        && !prefs.join_announce
+        && let Err(err) = api.delete_message(channel_id, notice_id).await
+    {
+        eprintln!("Failed to delete participation notice: {err}");
+    }
+    if config.notify_on_reaction
+        && matches!(
+            recruit_reaction,
+            RecruitReaction::Participation | RecruitReaction::SilentParticipation
+        )
+        && prefs.join_announce
+        && action == ReactorAction::First
+        && let Some(user_id) = reaction.user_id
+        && !config.preset_participants.contains(&user_id)
+    {
+        let notice = send_participation_notification(
+            api,
+            data,
+            &config,
+            &policy,
+            reaction.channel_id,
+            user_id,
+            user_ids.len(),
+        )
+        .await?;
+        duplicate_reaction::record_notice(data, message.id, user_id, &notice);
+    }
+
+    if config.notify_organizer_when_close
+        && recruit_reaction != RecruitReaction::Start
+        && user_ids.len() + 1 == config.required_players
+        && let Some(organizer) = config.organizer
+    {
+        notify_organizer_close(
+            api,
+            data,
+            &config,
+            &policy,
+            &message,
+            reaction.guild_id,
+            organizer,
+        )
+        .await?;
+    }
+
+    if action == ReactorAction::First {
+        assign_participant_role(api, data, &config, &policy, reaction).await?;
+    }
+
+    if recruit_reaction == RecruitReaction::Start {
+        countdown::cancel_countdown(api, data, message.id).await;
+        roll_call::cancel_roll_call(api, data, message.id).await;
+        send_start_notification(
+            api,
+            data,
+            &config,
+            &policy,
+            &message,
+            reaction.guild_id,
+            order,
+        )
+        .await?;
+    } else if config.required_players <= user_ids.len() {
+        begin_start(api, data, &config, &message, reaction.guild_id, user_ids).await?;
+    }
+
+    Ok(())
+}
+
+/// ✋ と 🤚 のリアクションについて, 同じユーザーの 2 つ目のリアクションかどうかを判定します
+///
+/// それ以外のリアクションは毎回 `ReactorAction::First` として処理します。
+fn reactor_action(
+    data: &Data,
+    message: &Message,
+    reaction: &Reaction,
+    recruit_reaction: RecruitReaction<'_>,
+) -> ReactorAction {
+    let silent = match recruit_reaction {
+        RecruitReaction::Participation => false,
+        RecruitReaction::SilentParticipation => true,
+        _ => return ReactorAction::First,
+    };
+    match reaction.user_id {
+        Some(user_id) => duplicate_reaction::process(data, message.id, user_id, silent),
+        None => ReactorAction::First,
+    }
+}
+
+/// 参加人数が揃った募集を開始します
+///
+/// `roll_call_minutes` があれば点呼を, `start_delay_minutes` があればカウントダウンを挟みます。
+async fn begin_start(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &RecruitConfig,
+    message: &Message,
+    guild_id: Option<GuildId>,
+    user_ids: HashSet<UserId>,
+) -> Result<(), Error> {
+    if let Some(minutes) = config.roll_call_minutes {
+        return roll_call::start_roll_call(api, data, config, message, guild_id, user_ids, minutes)
+            .await;
+    }
+    match config.start_delay_minutes {
+        Some(delay_minutes) => {
+            countdown::start_countdown(
+                api,
+                data,
+                config,
+                message,
+                guild_id,
+                user_ids,
+                delay_minutes,
+            )
+            .await
+        }
+        None => {
+            let policy = NotificationPolicy::new(config);
+            let participants = participant_order::ordered(data, message.id, &user_ids);
+            send_start_notification(api, data, config, &policy, message, guild_id, participants)
+                .await
+        }
+    }
+}
+
+async fn handle_voice_state_update(
+    ctx: &Context,
+    data: &Data,
+    old: Option<&VoiceState>,
+    new: &VoiceState,
+) -> Result<(), Error> {
+    let (Some(guild_id), Some(voice_channel_id)) = (new.guild_id, new.channel_id) else {
+        return Ok(());
+    };
+    let was_deafened = old.is_some_and(|old| old.deaf || old.self_deaf);
+    let is_deafened = new.deaf || new.self_deaf;
+    let moved_in = old.and_then(|old| old.channel_id) != Some(voice_channel_id);
+    if !moved_in && (was_deafened == is_deafened) {
+        return Ok(());
+    }
+    if !is_counted_voice_state(ctx, new, false) {
+        return Ok(());
+    }
+    voice_gate::handle_voice_join(ctx, data, voice_channel_id).await;
+
+    let recruits: Vec<_> = {
+        let voice_recruits = data.voice_recruits.lock().unwrap();
+        match voice_recruits.get(&voice_channel_id) {
+            Some(recruits) => recruits.iter().copied().collect(),
+            None => return Ok(()),
+        }
+    };
+
+    for (channel_id, message_id) in recruits {
+        let _guard = data.lock_message(message_id).await;
+        let message = match channel_id.message(ctx, message_id).await {
+            Ok(message) => message,
+            Err(err) if discord_error::is_unknown_entity(&err).is_some() => {
+                data.forget_deleted_message(message_id);
+                continue;
+            }
+            Err(err) => {
+                eprintln!("Failed to fetch recruit message: {err}");
+                unregister_voice_recruit(data, voice_channel_id, channel_id, message_id);
+                continue;
+            }
+        };
+        let mut config = match config_storage::load(data, &message) {
+            Ok(recruit_message)
+                if recruit_message.config.count_voice_channel == Some(voice_channel_id) =>
+            {
+                recruit_message.config
+            }
+            _ => {
+                unregister_voice_recruit(data, voice_channel_id, channel_id, message_id);
+                continue;
+            }
+        };
+        if !moved_in && !config.require_undeafened {
+            continue;
+        }
+        i18n::resolve_language(data, &mut config, Some(guild_id));
+        if !is_counted_voice_state(ctx, new, config.require_undeafened) {
+            continue;
+        }
+
+        let allowed_bot_participants = allowed_bot_participants(data, Some(guild_id));
+        let mut user_ids =
+            collect_reaction_participants(ctx, data, &message, &config, &allowed_bot_participants)
+                .await?;
+        if user_ids.contains(&new.user_id) {
+            continue;
+        }
+        user_ids.extend(voice_channel_participants(
+            ctx,
+            guild_id,
+            voice_channel_id,
+            config.require_undeafened,
+        ));
+
+        if user_ids.len() == config.required_players {
+            begin_start(ctx, data, &config, &message, Some(guild_id), user_ids).await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn register_voice_recruit(
+    data: &Data,
+    voice_channel_id: ChannelId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) {
+    data.voice_recruits
+        .lock()
+        .unwrap()
+        .entry(voice_channel_id)
+        .or_default()
+        .insert((channel_id, message_id));
+}
+
+fn unregister_voice_recruit(
+    data: &Data,
+    voice_channel_id: ChannelId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) {
+    let mut voice_recruits = data.voice_recruits.lock().unwrap();
+    if let Some(recruits) = voice_recruits.get_mut(&voice_channel_id) {
+        recruits.remove(&(channel_id, message_id));
+        if recruits.is_empty() {
+            voice_recruits.remove(&voice_channel_id);
+        }
+    }
+}
+
+/// 募集設定を読み取る前に, 参加・開始用のリアクションかどうかを判定します
+///
+/// 募集ごとの絵文字は募集設定を読み取るまで分からないため, 組み込みの絵文字に加えてサーバーの `/settings emoji` の絵文字も含めます。
+/// 募集設定を読み取った後は `RecruitConfig::recruit_reaction` で募集ごとの絵文字から判定します。
+fn is_builtin_recruit_reaction(
+    data: &Data,
+    guild_id: Option<GuildId>,
+    reaction: &ReactionType,
+) -> bool {
+    let emojis = &data.config.emojis;
+    if [
+        &emojis.participation,
+        &emojis.silent_participation,
+        &emojis.start,
+    ]
+    .iter()
+    .any(|emoji| reaction.unicode_eq(emoji))
+    {
+        return true;
+    }
+    guild_id
+        .and_then(|guild_id| data.storage.guild_settings(guild_id).reaction_emoji)
+        .is_some_and(|pair| {
+            [pair.participation, pair.silent_participation]
+                .iter()
+                .any(|emoji| is_same_emoji(reaction, &parse_emoji(emoji)))
+        })
+}
+
+fn parse_bot_recruit_message(
+    api: &impl DiscordApi,
+    data: &Data,
+    message: &Message,
+) -> Option<RecruitMessage> {
+    if message.author.id != api.current_user_id() {
+        return None;
+    }
+    config_storage::load(data, message).ok()
+}
+
+async fn send_ephemeral(ctx: poise::Context<'_, Data, Error>, content: &str) -> Result<(), Error> {
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+fn author_permissions(ctx: poise::Context<'_, Data, Error>) -> Option<Permissions> {
+    match ctx {
+        poise::Context::Application(ctx) => ctx.interaction.member.as_ref()?.permissions,
+        poise::Context::Prefix(_) => None,
+    }
+}
+
+/// 実行者が募集の主催者か, メッセージの管理権限を持つかどうか
+fn can_manage_recruit(ctx: poise::Context<'_, Data, Error>, config: &RecruitConfig) -> bool {
+    config.organizer == Some(ctx.author().id)
+        || author_permissions(ctx).is_some_and(|permissions| permissions.manage_messages())
+}
+
+/// キャッシュから Bot のチャンネル権限を計算します (スレッドの場合は親チャンネルの権限)
+fn bot_permissions(ctx: &Context, guild_id: GuildId, channel_id: ChannelId) -> Option<Permissions> {
+    let guild = ctx.cache.guild(guild_id)?;
+    let member = guild.members.get(&ctx.cache.current_user().id)?;
+    let channel = match guild.channels.get(&channel_id) {
+        Some(channel) => channel,
+        None => {
+            let thread = guild
+                .threads
+                .iter()
+                .find(|thread| thread.id == channel_id)?;
+            guild.channels.get(&thread.parent_id?)?
+        }
+    };
+    Some(guild.user_permissions_in(channel, member))
+}
+
+/// 定員に達している募集へ新しく参加しようとしているかどうか
+fn is_over_capacity(config: &RecruitConfig, user_ids: &HashSet<UserId>, user_id: UserId) -> bool {
+    config
+        .max_players
+        .is_some_and(|max_players| max_players < user_ids.len() && user_ids.contains(&user_id))
+}
+
+/// 参加者があと 1 人になったことを主催者に通知します (開始するまでは 1 回のみ)
+async fn notify_organizer_close(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &RecruitConfig,
+    policy: &NotificationPolicy,
+    message: &Message,
+    guild_id: Option<GuildId>,
+    organizer: UserId,
+) -> Result<(), Error> {
+    if !data.close_notices.lock().unwrap().insert(message.id) {
+        return Ok(());
+    }
+    let content = config.language().text(Text::OneMoreNeeded {
+        game_title: &config.game_title,
+    });
+    let link = message.id.link(message.channel_id, guild_id);
+    policy
+        .send_dm_or_channel(api, data, organizer, message.channel_id, &content, &link)
+        .await
+}
+
+async fn send_error_message(
+    api: &impl DiscordApi,
+    data: &Data,
+    reaction: &Reaction,
+    language: Language,
+) -> Result<(), Error> {
+    let error = language.text(Text::ConfigReadFailed);
+    notification::notify_error(api, data, reaction, language, &error).await?;
+    Ok(())
+}
+
+async fn send_participation_notification(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &RecruitConfig,
+    policy: &NotificationPolicy,
+    channel_id: ChannelId,
+    user_id: UserId,
+    count: usize,
+) -> Result<Message, Error> {
+    let language = config.language();
+    let content = language.text(Text::Joined {
+        user: &user_id.mention().to_string(),
+        game_title: &config.game_title,
+        progress: &format_progress(language, count, config.required_players),
+    });
+    let notice = policy
+        .notify(
+            api,
+            data,
+            NotificationKind::Participation,
+            channel_id,
+            &content,
+            &Mentions::user(user_id),
+        )
+        .await?;
+    Ok(notice)
+}
+
+/// 参加人数と開始に必要な人数を「(3/5, あと2人)」の形式で表します
+fn format_progress(language: Language, count: usize, required_players: usize) -> String {
+    language.text(Text::Progress {
+        count,
+        required_players,
+    })
+}
+
+async fn send_start_notification(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &RecruitConfig,
+    policy: &NotificationPolicy,
+    message: &Message,
+    guild_id: Option<GuildId>,
+    participants: Vec<UserId>,
+) -> Result<(), Error> {
+    // サイレント時間帯はメンションせずに送ります
+    let quiet_hours = quiet_hours::active(data, guild_id, config, Timestamp::now());
+    let policy = &policy.with_quiet_hours(quiet_hours.map(|_| config.language()));
+    if config.multi_lobby {
+        return lobby::start_lobbies(api, data, config, policy, message, guild_id, participants)
+            .await;
+    }
+    let marker = data.storage.start_marker(message.id);
+    match start_marker::decide(marker.as_ref(), true) {
+        StartDecision::Send => {}
+        StartDecision::Rearm => {
+            return start_marker::rearm(api, data, config, message.channel_id, message.id).await;
+        }
+        StartDecision::Wait => return Ok(()),
+    }
+
+    let reacted = participants.len();
+    // サーバーを抜けたユーザーはメンションしても ID のまま表示されるため, 先着を選ぶ前に除きます
+    let participants = match guild_id {
+        Some(guild_id) => member_left::retain_members(api, data, guild_id, participants).await,
+        None => participants,
+    };
+    // 開始時に VC にいることを求める募集は, VC にいない参加者を見送るか, 揃うまで開始を保留します
+    let Some((participants, left_out)) =
+        voice_gate::check(api, data, config, policy, message, guild_id, participants).await?
+    else {
+        return Ok(());
+    };
+    // 枠のある募集は, 枠ごとに参加した順で埋めた参加者で開始し, 枠ごとにまとめてメンションします
+    let slot_fill = if config.has_slots() {
+        let reactors = slots::fetch_slot_reactors(api, data, message, config, guild_id).await?;
+        Some(slots::fill(&config.slots, &reactors, &participants))
+    } else {
+        None
+    };
+    let participants = match &slot_fill {
+        Some(slot_fill) => slot_fill.participants(),
+        None => participants,
+    };
+    let StartSelection {
+        starters: user_ids,
+        substitutes: waitlist,
+    } = participant_order::select_starters(participants, config);
+
+    let allowed_bot_participants = allowed_bot_participants(data, guild_id);
+    let mut extra_groups = Vec::new();
+    let mut extra_users = Vec::new();
+    for extra in &config.extra_reactions {
+        let extra_user_ids = fetch_reaction_users(
+            api,
+            message,
+            extra.reaction_type(),
+            &allowed_bot_participants,
+        )
+        .await?;
+        if extra_user_ids.is_empty() {
+            continue;
+        }
+        extra_users.extend(extra_user_ids.iter().copied());
+        extra_groups.push((&extra.label, extra_user_ids));
+    }
+
+    // 動作確認用の募集とサイレント時間帯はロールをメンションしないため, クールダウンを消費しません
+    let role_ping_claim = match guild_id {
+        Some(guild_id) if policy.has_side_effects() && !policy.is_quiet() => Some((
+            guild_id,
+            data.storage
+                .claim_role_pings(guild_id, &config.mention_roles, Timestamp::now())?,
+        )),
+        _ => None,
+    };
+    let (role_ids, cooling_down) = match &role_ping_claim {
+        Some((_, claim)) => (claim.allowed.as_slice(), !claim.cooling_down.is_empty()),
+        None => (config.mention_roles.as_slice(), false),
+    };
+    let role_mentions: Vec<String> = role_ids
+        .iter()
+        .map(|role_id| role_id.mention().to_string() + "\n")
+        .collect();
+    // `/notify_me` で開始通知のメンションを断ったユーザーは, メンションせずに表示名だけを表示します
+    let notified_users: Vec<UserId> = user_ids.iter().chain(&extra_users).copied().collect();
+    let mentioned_users = notify_prefs::start_pinged_users(data, guild_id, &notified_users);
+    // ロールのメンションで通知される参加者も, 重ねて通知しないよう名前だけを表示します
+    let mentioned_users = match &role_ping_claim {
+        Some((guild_id, claim)) => mention_dedupe::apply(
+            api,
+            data,
+            *guild_id,
+            &claim.allowed,
+            &user_ids,
+            mentioned_users,
+        ),
+        None => mentioned_users,
+    };
+    // 補欠と VC にいなかった参加者にもメンションは送りません
+    let mut unpinged_users = Vec::new();
+    for &user_id in notified_users.iter().chain(&waitlist).chain(&left_out) {
+        if !mentioned_users.contains(&user_id) && !unpinged_users.contains(&user_id) {
+            unpinged_users.push(user_id);
+        }
+    }
+    let names = match guild_id {
+        Some(guild_id) => {
+            member_work::display_names(api, data, guild_id, "Start notification", &unpinged_users)
+                .await
+        }
+        None => HashMap::new(),
+    };
+    let labels = StartLabels::new(&mentioned_users, names);
+    // 主催者がサーバーにいる場合は, 主催者を見出しにした埋め込みに説明文を入れ, 本文はメンションだけにします
+    let organizer = match (guild_id, config.organizer) {
+        (Some(guild_id), Some(organizer)) => OrganizerAuthor::fetch(api, guild_id, organizer).await,
+        _ => None,
+    };
+    let language = config.language();
+    let mentions = match &slot_fill {
+        Some(slot_fill) => slots::render_groups(&config.slots, slot_fill, &labels),
+        None => labels.join(&user_ids),
+    };
+    let start_time = config.start_time.map(reminder::format_start_time);
+    let game_title = &config.game_title;
+    let mut content = language.text(match (&start_time, organizer.is_some()) {
+        (Some(start_time), false) => Text::Gathered {
+            mentions: &mentions,
+            game_title,
+            start_time,
+        },
+        (Some(start_time), true) => Text::GatheredEmbed {
+            game_title,
+            start_time,
+        },
+        (None, false) => Text::Starting {
+            mentions: &mentions,
+            game_title,
+        },
+        (None, true) => Text::StartingEmbed { game_title },
+    });
+    if cooling_down {
+        content += &language.text(Text::RolePingCoolingDown);
+    }
+    if !waitlist.is_empty() {
+        content += &format!(
+            "\n{}\n{}",
+            language.text(Text::Oversubscribed {
+                capacity: user_ids.len(),
+                reacted,
+            }),
+            language.text(Text::Substitutes {
+                mentions: &labels.join(&waitlist),
+            })
+        );
+    }
+    // :bell: で人数が揃う前に開始した場合は, 集まるまでの時間を表示しません
+    if config.required_players <= user_ids.len()
+        && let Some(seconds) = start_marker::fill_elapsed(
+            message.timestamp,
+            data.storage.rearmed_at(message.id),
+            Timestamp::now(),
+        )
+    {
+        content += &format!("\n{}", language.text(Text::FilledIn { seconds }));
+    }
+    for (label, extra_user_ids) in &extra_groups {
+        content += &format!("\n{label}: {}", labels.join(extra_user_ids));
+    }
+    if !left_out.is_empty() {
+        content += &format!(
+            "\n{}",
+            language.text(Text::VoiceLeftOut {
+                mentions: &labels.join(&left_out),
+            })
+        );
+    }
+    let (content, embed) = match &organizer {
+        Some(organizer) => {
+            let mut mentions = vec![mentions];
+            mentions.extend(
+                extra_users
+                    .iter()
+                    .filter(|user_id| !user_ids.contains(user_id))
+                    .map(|&user_id| labels.label(user_id)),
+            );
+            (
+                role_mentions.concat() + &mentions.join(" "),
+                Some(notification::organizer_embed(organizer, &content)),
+            )
+        }
+        None => (role_mentions.concat() + &content, None),
+    };
+
+    let channel_id = message.channel_id;
+    // 送信中に停止しても再起動後に送り直さないよう, 送信する前に開始済みの印を保存します
+    data.storage.set_start_marker(
+        message.id,
+        StartMarker {
+            channel_id,
+            start_sent_at: Timestamp::now(),
+        },
+    )?;
+    // 本文のロールとユーザーのうち, ここで決めた相手だけにメンションを送ります
+    // (ゲーム名や説明文に含まれるメンションは送りません)
+    let mentions = Mentions::users(mentioned_users).with_roles(role_ids.iter().copied());
+    let create_message = || {
+        let create_message = policy.create_message(NotificationKind::Start, &content, &mentions);
+        match &embed {
+            Some(embed) => create_message.embed(embed.clone()),
+            None => create_message,
+        }
+    };
+    let sent = thread::retry_if_archived(api, channel_id, || {
+        api.send_message(channel_id, create_message())
+    })
+    .await;
+    let start_message = match sent {
+        Ok(Some(start_message)) => start_message,
+        result => {
+            if let Some((guild_id, claim)) = &role_ping_claim
+                && let Err(err) = data.storage.release_role_pings(*guild_id, claim)
+            {
+                eprintln!("Failed to release role pings: {err}");
+            }
+            if let Err(err) = data.storage.clear_start_marker(message.id) {
+                eprintln!("Failed to clear start marker: {err}");
+            }
+            // 開始を決めた時点は失われないよう, 少し待ってから送り直します
+            if let Err(err) = &result {
+                start_retry::record_failure(api, data, channel_id, message.id, guild_id, err).await;
+            }
+            return result.map(|_| ()).map_err(Into::into);
+        }
+    };
+    start_retry::record_success(data, message.id);
+
+    policy.schedule_delete(data, &start_message);
+    let started_participants = config.closes_on_start().then(|| user_ids.clone());
+    data.close_notices.lock().unwrap().remove(&message.id);
+    data.participant_counts.lock().unwrap().remove(&message.id);
+    data.processed_reactors.lock().unwrap().remove(&message.id);
+    if let Err(err) = data.storage.remove_participant_order(message.id) {
+        eprintln!("Failed to remove participant order: {err}");
+    }
+    if config.start_time.is_some() {
+        // 開始予定の日時がある募集は締め切り, リマインドするときに参加者を数え直せるよう
+        // リアクションと /join_menu の参加者を残します
+        close_scheduled_recruit(api, data, message).await?;
+        reminder::schedule_reminder(data, config, channel_id, message.id, guild_id);
+        // サイレント時間帯に送らなかったロールへのメンションは, 時間帯が終わってから送ります
+        if let Some(quiet_hours) = quiet_hours
+            && policy.has_side_effects()
+            && !config.mention_roles.is_empty()
+        {
+            quiet_hours::defer_role_ping(data, quiet_hours, channel_id, message.id, guild_id);
+        }
+    } else if let Err(err) = data.storage.remove_menu_participants(message.id) {
+        eprintln!("Failed to remove menu participants: {err}");
+    }
+
+    if let Some(guild_id) = guild_id
+        && policy.has_side_effects()
+    {
+        let record = RecruitRecord {
+            game_title: config.game_title.clone(),
+            category: config.category.clone(),
+            channel_id,
+            message_id: message.id,
+            started_at: Timestamp::now(),
+            participants: user_ids.clone(),
+            required_players: Some(config.required_players),
+            reacted_count: Some(reacted),
+        };
+        if let Err(err) = data.storage.record_recruit(guild_id, record) {
+            eprintln!("Failed to record recruit history: {err}");
+        }
+    }
+    close_reason::close_recruit(data, message.id, Some(config), CloseReason::Started);
+    recruit_log::record(
+        data,
+        message.id,
+        Some(config),
+        RecruitEventKind::Started,
+        user_ids.clone(),
+    );
+
+    #[cfg(feature = "webhook")]
+    if let Some(guild_id) = guild_id
+        && policy.has_side_effects()
+        && let Some(url) = data.storage.guild_settings(guild_id).webhook_url
+    {
+        let names =
+            member_work::display_names(api, data, guild_id, "Start webhook", &user_ids).await;
+        let payload = webhook::StartPayload::new(
+            config.game_title.clone(),
+            guild_id,
+            channel_id,
+            message.id,
+            &user_ids,
+            &names,
+            Timestamp::now(),
+        );
+        webhook::spawn_start_webhook(url, payload);
+    }
+    // 募集の記録を書き込んでから削除を予約します
+    if let Some(minutes) = config.cleanup_after_start_minutes {
+        data.scheduler.enqueue(
+            &*data.storage,
+            scheduler::fire_at_after_minutes(minutes),
+            Job::DeleteRecruitMessage {
+                channel_id,
+                message_id: message.id,
+            },
+        );
+    }
+    start_marker::rearm(api, data, config, channel_id, message.id).await?;
+    // 付け直しでリアクションを消す募集があるため, 付け直した後に 🙅 を付けます
+    if let Some(participants) = started_participants {
+        drop_out::record_started(api, data, message, participants, waitlist).await?;
+    }
+    Ok(())
+}
+
+/// 人数が揃った, 開始予定の日時がある募集を締め切ったことを募集メッセージに表示します
+async fn close_scheduled_recruit(
+    api: &impl DiscordApi,
+    data: &Data,
+    message: &Message,
+) -> Result<(), Error> {
+    recruit_edit::edit_recruit_message(
+        api,
+        data,
+        message.channel_id,
+        message.id,
+        |recruit_message| {
+            recruit_message.state.started = true;
+            Some(())
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+/// 開始した募集のリアクションを Bot のものだけに戻します
+async fn reset_recruit_reactions(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &RecruitConfig,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Result<(), serenity::Error> {
+    // 開始予定の日時がある募集は, リマインドする参加者を数え直すためリアクションを残します
+    if config.start_time.is_some() {
+        return Ok(());
+    }
+    api.delete_reactions(channel_id, message_id).await?;
+    // 複数のゲームから選ぶ募集と枠のある募集は開始すると締め切るため, リアクションを付け直しません
+    if config.has_option_reactions() {
+        return Ok(());
+    }
+    for reaction_type in config.reaction_types(&data.config.emojis) {
+        api.create_reaction(channel_id, message_id, reaction_type)
+            .await?;
+    }
+    Ok(())
+}
+
+/// `auto_assign_role_on_reaction` の募集で, リアクションしたユーザーに 1 つ目のロールを付与します
+async fn assign_participant_role(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &RecruitConfig,
+    policy: &NotificationPolicy,
+    reaction: &Reaction,
+) -> Result<(), Error> {
+    if config.auto_assign_role_on_reaction
+        && policy.has_side_effects()
+        && let Some(&role_id) = config.mention_roles.first()
+        && let Err(err) = assign_role_if_missing(api, reaction, role_id).await
+    {
+        eprintln!("Failed to assign role: {err}");
+        send_role_assign_error(api, data, reaction, config.language()).await?;
+    }
+    Ok(())
+}
+
+async fn assign_role_if_missing(
+    api: &impl DiscordApi,
+    reaction: &Reaction,
+    role_id: RoleId,
+) -> Result<(), Error> {
+    let (Some(guild_id), Some(user_id)) = (reaction.guild_id, reaction.user_id) else {
+        return Ok(());
+    };
+    assign_role_to_member(api, guild_id, user_id, role_id).await
+}
+
+async fn assign_role_to_member(
+    api: &impl DiscordApi,
+    guild_id: GuildId,
+    user_id: UserId,
+    role_id: RoleId,
+) -> Result<(), Error> {
+    let member = api.member(guild_id, user_id).await?;
+    if member.roles.contains(&role_id) {
+        return Ok(());
+    }
+    api.add_role(guild_id, user_id, role_id).await?;
+    Ok(())
+}
+
+async fn send_role_assign_error(
+    api: &impl DiscordApi,
+    data: &Data,
+    reaction: &Reaction,
+    language: Language,
+) -> Result<(), Error> {
+    let error = language.text(Text::RoleAssignFailed);
+    notification::notify_error(api, data, reaction, language, &error).await?;
+    Ok(())
+}
+
+async fn collect_participants(
+    api: &impl DiscordApi,
+    data: &Data,
+    message: &Message,
+    config: &RecruitConfig,
+    guild_id: Option<GuildId>,
+) -> Result<HashSet<UserId>, Error> {
+    let allowed_bot_participants = allowed_bot_participants(data, guild_id);
+    let mut user_ids =
+        collect_reaction_participants(api, data, message, config, &allowed_bot_participants)
+            .await?;
+    user_ids.extend(data.storage.menu_participants(message.id));
+    // 事前に登録した参加者は, リアクションしていなくても数えます (リアクションしても 1 人として数えます)
+    user_ids.extend(config.preset_participants.iter().copied());
+    // 作成やサーバーへの参加が新しいアカウントは, 人数に数えません
+    participant_age::retain_eligible(api, data, guild_id, &mut user_ids).await;
+    // ロビーに入った参加者は, 次のロビーの人数に数えません
+    if config.multi_lobby {
+        lobby::exclude_placed(data, message.id, &mut user_ids);
+    }
+    if priority::is_window_open(config.priority_until, Timestamp::now()) {
+        priority::exclude_deferred(
+            &mut user_ids,
+            &data.storage.deferred_participants(message.id),
+        );
+    }
+    if let (Some(guild_id), Some(voice_channel_id)) = (guild_id, config.count_voice_channel) {
+        user_ids.extend(voice_channel_participants(
+            api,
+            guild_id,
+            voice_channel_id,
+            config.require_undeafened,
+        ));
+    }
+    Ok(user_ids)
+}
+
+async fn collect_reaction_participants(
+    api: &impl DiscordApi,
+    data: &Data,
+    message: &Message,
+    config: &RecruitConfig,
+    allowed_bot_participants: &[UserId],
+) -> Result<HashSet<UserId>, Error> {
+    let mut user_ids = HashSet::new();
+    for reaction_type in config.counted_reaction_types(&data.config.emojis) {
+        user_ids.extend(
+            fetch_reaction_users(api, message, reaction_type, allowed_bot_participants).await?,
+        );
+    }
+    Ok(user_ids)
+}
+
+fn voice_channel_participants(
+    api: &impl DiscordApi,
+    guild_id: GuildId,
+    voice_channel_id: ChannelId,
+    require_undeafened: bool,
+) -> Vec<UserId> {
+    let Some(guild) = api.cache().and_then(|cache| cache.guild(guild_id)) else {
+        return Vec::new();
+    };
+    guild
+        .voice_states
+        .values()
+        .filter(|state| state.channel_id == Some(voice_channel_id))
+        .filter(|state| is_counted_voice_state(api, state, require_undeafened))
+        .map(|state| state.user_id)
+        .collect()
+}
+
+fn is_counted_voice_state(
+    api: &impl DiscordApi,
+    state: &VoiceState,
+    require_undeafened: bool,
+) -> bool {
+    if require_undeafened && (state.deaf || state.self_deaf) {
+        return false;
+    }
+    let is_bot = match &state.member {
+        Some(member) => member.user.bot,
+        None => api
+            .cache()
+            .and_then(|cache| cache.user(state.user_id))
+            .is_some_and(|user| user.bot),
+    };
+    !is_bot
+}
+
+async fn fetch_reaction_users(
+    api: &impl DiscordApi,
+    message: &Message,
+    reaction_type: ReactionType,
+    allowed_bot_participants: &[UserId],
+) -> Result<Vec<UserId>, Error> {
+    Ok(
+        fetch_reaction_user_list(api, message, reaction_type, |user| {
+            is_counted_reactor(user, allowed_bot_participants)
+        })
+        .await?
+        .into_iter()
+        .map(|user| user.id)
+        .collect(),
+    )
+}
+
+/// Bot 以外のユーザーと, サーバー設定で参加を許可した Bot を参加者として数えます
+fn is_counted_reactor(user: &User, allowed_bot_participants: &[UserId]) -> bool {
+    !user.bot || allowed_bot_participants.contains(&user.id)
+}
+
+fn allowed_bot_participants(data: &Data, guild_id: Option<GuildId>) -> Vec<UserId> {
+    guild_id
+        .map(|guild_id| {
+            data.storage
+                .guild_settings(guild_id)
+                .allowed_bot_participants
+        })
+        .unwrap_or_default()
+}
+
+/// リアクションをつけたユーザーのうち `is_counted` を満たすユーザーを REST API から取得します
+async fn fetch_reaction_user_list(
+    api: &impl DiscordApi,
+    message: &Message,
+    reaction_type: ReactionType,
+    is_counted: impl Fn(&User) -> bool,
+) -> Result<Vec<User>, Error> {
+    let mut users = Vec::new();
+    let mut after = None;
+
+    loop {
+        let chunk = api
+            .reaction_users(message.channel_id, message.id, reaction_type.clone(), after)
+            .await?;
+        let chunk_len = chunk.len();
+        after = chunk.last().map(|user| user.id);
+        users.extend(chunk.into_iter().filter(|user| is_counted(user)));
+        if chunk_len < 100 {
+            break;
+        }
+    }
+
+    Ok(users)
+}
+
+fn schedule_delete_message(data: &Data, message: &Message, delete_after_minutes: u64) {
+    data.scheduler.enqueue(
+        &*data.storage,
+        scheduler::fire_at_after_minutes(delete_after_minutes),
+        Job::DeleteMessage {
+            channel_id: message.channel_id,
+            message_id: message.id,
+            content: Some(message.content.clone()),
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_discord::{self, Call, MockDiscord, MockError, SentEmbed};
+
+    const GUILD_ID: GuildId = GuildId::new(10);
+    const CHANNEL_ID: ChannelId = ChannelId::new(20);
+    const RECRUIT_ID: MessageId = MessageId::new(30);
+    const ROLE_ID: RoleId = RoleId::new(40);
+
+    /// Discord に登録できないコマンドの理由
+    fn check_command(
+        command: &poise::Command<Data, Error>,
+        depth: usize,
+        errors: &mut Vec<String>,
+    ) {
+        let name = &command.qualified_name;
+        if command.context_menu_action.is_some() {
+            return;
+        }
+        if !(1..=32).contains(&command.name.chars().count()) {
+            errors.push(format!("{name}: name must be 1-32 characters"));
+        }
+        let description = command.description.as_deref().unwrap_or("");
+        if !(1..=100).contains(&description.chars().count()) {
+            errors.push(format!("{name}: description must be 1-100 characters"));
+        }
+        if 25 < command.subcommands.len() + command.parameters.len() {
+            errors.push(format!(
+                "{name}: {} options (at most 25)",
+                command.subcommands.len() + command.parameters.len()
+            ));
+        }
+        if !command.subcommands.is_empty() && 2 <= depth {
+            errors.push(format!("{name}: subcommand groups cannot be nested"));
+        }
+        let mut seen_optional = false;
+        for parameter in &command.parameters {
+            let parameter_name = format!("{name}.{}", parameter.name);
+            if !(1..=32).contains(&parameter.name.chars().count()) {
+                errors.push(format!("{parameter_name}: name must be 1-32 characters"));
+            }
+            let description = parameter.description.as_deref().unwrap_or("");
+            if !(1..=100).contains(&description.chars().count()) {
+                errors.push(format!(
+                    "{parameter_name}: description must be 1-100 characters"
+                ));
+            }
+            if 25 < parameter.choices.len() {
+                errors.push(format!("{parameter_name}: at most 25 choices"));
+            }
+            if parameter.required && seen_optional {
+                errors.push(format!(
+                    "{parameter_name}: required options must come before optional ones"
+                ));
+            }
+            seen_optional |= !parameter.required;
+        }
+        for subcommand in &command.subcommands {
+            check_command(subcommand, depth + 1, errors);
+        }
+    }
+
+    /// コマンドの名前, 説明, パラメーターとその選択肢の文字数の合計
+    fn total_characters(command: &poise::Command<Data, Error>) -> usize {
+        let own = command.name.chars().count()
+            + command.description.as_deref().unwrap_or("").chars().count();
+        let parameters: usize = command
+            .parameters
+            .iter()
+            .map(|parameter| {
+                parameter.name.chars().count()
+                    + parameter
+                        .description
+                        .as_deref()
+                        .unwrap_or("")
+                        .chars()
+                        .count()
+                    + parameter
+                        .choices
+                        .iter()
+                        .map(|choice| choice.name.chars().count())
+                        .sum::<usize>()
+            })
+            .sum();
+        own + parameters
+            + command
+                .subcommands
+                .iter()
+                .map(total_characters)
+                .sum::<usize>()
+    }
+
+    /// `config` の募集メッセージを投稿し, `members` をサーバーのメンバーにします
+    fn post_recruit(discord: &MockDiscord, content: &str, members: &[u64]) -> Message {
+        let message = mock_discord::message(CHANNEL_ID, RECRUIT_ID, content);
+        discord.insert_message(message.clone());
+        for &user_id in members {
+            discord.insert_member(mock_discord::member(GUILD_ID, user_id));
+        }
+        message
+    }
+
+    /// `user_id` が `emoji` でリアクションしたイベントを処理します
+    async fn react(
+        discord: &MockDiscord,
+        data: &Data,
+        message: &Message,
+        user_id: u64,
+        emoji: &str,
+    ) {
+        discord.add_reaction(message.id, emoji, user_id);
+        let reaction = mock_discord::reaction(message, GUILD_ID, user_id, emoji);
+        handle_reaction_add(discord, data, &reaction).await.unwrap();
+    }
+
+    fn joined(user_id: u64, count: usize, required_players: usize) -> String {
+        Language::Ja.text(Text::Joined {
+            user: &UserId::new(user_id).mention().to_string(),
+            game_title: "Apex",
+            progress: &format_progress(Language::Ja, count, required_players),
+        })
+    }
+
+    fn create_reaction(emoji: &str) -> Call {
+        Call::CreateReaction {
+            message_id: RECRUIT_ID,
+            reaction: emoji.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn recruit_starts_when_the_last_player_joins() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        let config = RecruitConfig::for_test("Apex", 2);
+        let message = post_recruit(
+            &discord,
+            &RecruitMessage::new(config).render(&data.config.emojis),
+            &[11, 12],
+        );
+
+        react(&discord, &data, &message, 11, "✋").await;
+        assert_eq!(discord.sent_contents(), [joined(11, 1, 2)]);
+
+        react(&discord, &data, &message, 12, "✋").await;
+        let sent = discord.sent_contents();
+        assert_eq!(sent[..2], [joined(11, 1, 2), joined(12, 2, 2)]);
+        assert_eq!(sent.len(), 3);
+        assert!(
+            sent[2].starts_with("<@11> <@12> が Apex を開始します"),
+            "{}",
+            sent[2]
+        );
+        assert_eq!(data.storage.start_marker(RECRUIT_ID), None);
+    }
+
+    #[tokio::test]
+    async fn silent_participation_is_counted_without_a_notice() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        let config = RecruitConfig::for_test("Apex", 3);
+        let message = post_recruit(
+            &discord,
+            &RecruitMessage::new(config).render(&data.config.emojis),
+            &[11, 12, 13],
+        );
+
+        react(&discord, &data, &message, 11, "✋").await;
+        react(&discord, &data, &message, 12, "🤚").await;
+        assert_eq!(discord.sent_contents(), [joined(11, 1, 3)]);
+
+        react(&discord, &data, &message, 13, "✋").await;
+        let sent = discord.sent_contents();
+        assert_eq!(sent[..2], [joined(11, 1, 3), joined(13, 3, 3)]);
+        assert!(
+            sent[2].starts_with("<@11> <@12> <@13> が Apex を開始します"),
+            "{}",
+            sent[2]
+        );
+    }
+
+    #[tokio::test]
+    async fn unreadable_config_is_reported_to_the_reactor() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        let message = post_recruit(&discord, "```toml\nrequired_players = \n```", &[11]);
+
+        react(&discord, &data, &message, 11, "✋").await;
+        let error = Language::Ja.text(Text::ConfigReadFailed);
+        // エラーはメンションせず, 表示名を宛名にして送ります
+        assert_eq!(discord.sent_contents(), [format!("user11 {error}")]);
+        assert!(
+            discord
+                .calls()
+                .iter()
+                .all(|call| !matches!(call, Call::DeleteReactions { .. } | Call::AddRole { .. }))
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_role_assignment_is_reported() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        let mut config = RecruitConfig::for_test("Apex", 3);
+        config.mention_roles = vec![ROLE_ID];
+        config.auto_assign_role_on_reaction = true;
+        let message = post_recruit(
+            &discord,
+            &RecruitMessage::new(config).render(&data.config.emojis),
+            &[11],
+        );
+        let mut member = mock_discord::member(GUILD_ID, 12);
+        member.roles.push(ROLE_ID);
+        discord.insert_member(member);
+        discord.fail("add_role", MockError::MISSING_PERMISSIONS);
+
+        react(&discord, &data, &message, 11, "✋").await;
+        assert!(discord.calls().contains(&Call::AddRole {
+            user_id: UserId::new(11),
+            role_id: ROLE_ID,
+        }));
+        let error = Language::Ja.text(Text::RoleAssignFailed);
+        assert_eq!(discord.sent_contents()[1], format!("user11 {error}"));
+
+        // ロールを持っているメンバーには付与しません
+        react(&discord, &data, &message, 12, "✋").await;
+        let add_roles = discord
+            .calls()
+            .into_iter()
+            .filter(|call| matches!(call, Call::AddRole { .. }))
+            .count();
+        assert_eq!(add_roles, 1);
+    }
+
+    #[tokio::test]
+    async fn started_recruit_resets_reactions_for_the_next_round() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        let config = RecruitConfig::for_test("Apex", 2);
+        let message = post_recruit(
+            &discord,
+            &RecruitMessage::new(config).render(&data.config.emojis),
+            &[11, 12, 13],
+        );
+
+        react(&discord, &data, &message, 11, "✋").await;
+        react(&discord, &data, &message, 12, "✋").await;
+        let calls = discord.calls();
+        let start = calls
+            .iter()
+            .rposition(|call| matches!(call, Call::SendMessage { .. }))
+            .unwrap();
+        assert_eq!(
+            calls[start + 1..],
+            [
+                Call::DeleteReactions {
+                    message_id: RECRUIT_ID
+                },
+                create_reaction("✋"),
+                create_reaction("🤚"),
+                create_reaction("🔔"),
+            ]
+        );
+
+        // 付け直した後のリアクションは, 次の募集の 1 人目として数えます
+        react(&discord, &data, &message, 13, "✋").await;
+        assert_eq!(discord.sent_contents().last(), Some(&joined(13, 1, 2)));
+    }
+
+    #[test]
+    fn progress_shows_the_remaining_spots() {
+        assert_eq!(format_progress(Language::Ja, 1, 3), "(1/3, あと2人)");
+        assert_eq!(format_progress(Language::Ja, 3, 3), "(3/3)");
+        assert_eq!(format_progress(Language::Ja, 4, 3), "(4/3)");
+        assert_eq!(format_progress(Language::En, 2, 3), "(2/3, 1 more needed)");
+    }
+
+    #[test]
+    fn only_allowed_bots_are_counted() {
+        let mut user = User::default();
+        user.id = UserId::new(1);
+        assert!(is_counted_reactor(&user, &[]));
+        user.bot = true;
+        assert!(!is_counted_reactor(&user, &[]));
+        assert!(!is_counted_reactor(&user, &[UserId::new(2)]));
+        assert!(is_counted_reactor(&user, &[UserId::new(1)]));
+    }
+
+    #[test]
+    fn commands_fit_discord_limits() {
+        let commands = commands();
+        let mut errors = Vec::new();
+        for command in &commands {
+            check_command(command, 0, &mut errors);
+            if command.context_menu_action.is_none() && 4000 < total_characters(command) {
+                errors.push(format!(
+                    "{}: {} characters in total (at most 4000)",
+                    command.name,
+                    total_characters(command)
+                ));
+            }
+        }
+        assert!(100 >= commands.len(), "at most 100 global commands");
+        assert!(errors.is_empty(), "{}", errors.join("\n"));
+    }
+
+    /// `create_role` の募集として, ロールを作成してから募集メッセージを投稿します
+    async fn post_with_new_role(
+        discord: &MockDiscord,
+        data: &Data,
+    ) -> (RoleId, Result<Message, String>) {
+        let role_id = create_recruit_role(discord, GUILD_ID, "Apex")
+            .await
+            .unwrap()
+            .unwrap();
+        let mut config = RecruitConfig::for_test("Apex", 3);
+        config.mention_roles = vec![role_id];
+        config.auto_assign_role_on_reaction = true;
+        let posted =
+            post_recruit_or_delete_role(discord, data, GUILD_ID, CHANNEL_ID, config, Some(role_id))
+                .await
+                .unwrap();
+        (role_id, posted)
+    }
+
+    /// 削除したメッセージとロールの呼び出し
+    fn cleanups(discord: &MockDiscord) -> Vec<Call> {
+        discord
+            .calls()
+            .into_iter()
+            .filter(|call| matches!(call, Call::DeleteMessage { .. } | Call::DeleteRole { .. }))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn role_creation_failure_is_explained_before_posting() {
+        let discord = MockDiscord::new();
+        discord.fail("create_role", MockError::MISSING_PERMISSIONS);
+
+        let created = create_recruit_role(&discord, GUILD_ID, "Apex")
+            .await
+            .unwrap();
+        assert_eq!(
+            created,
+            Err(discord_error::RoleErrorKind::MissingPermissions)
+        );
+        assert_eq!(
+            discord.calls(),
+            [Call::CreateRole {
+                name: Some("Apex".to_string())
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn created_role_is_deleted_when_the_recruit_cannot_be_sent() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        discord.fail("send_message", MockError::MISSING_PERMISSIONS);
+
+        let (role_id, posted) = post_with_new_role(&discord, &data).await;
+        let content = posted.unwrap_err();
+        assert!(content.starts_with("募集の作成に失敗したため作成したロールを削除しました"));
+        assert_eq!(cleanups(&discord), [Call::DeleteRole { role_id }]);
+    }
+
+    #[tokio::test]
+    async fn recruit_and_role_are_deleted_when_reactions_fail() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        discord.fail_after("create_reaction", 1, MockError::UNKNOWN_MESSAGE);
+
+        let (role_id, posted) = post_with_new_role(&discord, &data).await;
+        assert!(posted.is_err());
+        let message_id = discord
+            .calls()
+            .into_iter()
+            .find_map(|call| match call {
+                Call::CreateReaction { message_id, .. } => Some(message_id),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            cleanups(&discord),
+            [
+                Call::DeleteMessage { message_id },
+                Call::DeleteRole { role_id }
+            ]
+        );
+        // 削除した募集の場所は記録しません
+        assert!(data.storage.recruit_location(message_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn recruit_switches_to_buttons_without_the_reaction_permission() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        discord.fail("create_reaction", MockError::MISSING_PERMISSIONS);
+
+        let (_, posted) = post_with_new_role(&discord, &data).await;
+        let message = posted.unwrap();
+        assert!(cleanups(&discord).is_empty());
+        assert!(discord.calls().contains(&Call::EditMessage {
+            message_id: message.id,
+            content: Some(message.content.clone()),
+        }));
+        let recruit = RecruitMessage::parse(&message.content).unwrap();
+        assert_eq!(
+            recruit.config.participation_mode,
+            ParticipationMode::Buttons
+        );
+        assert!(data.storage.recruit_location(message.id).is_some());
+    }
+
+    #[tokio::test]
+    async fn recruit_is_kept_when_neither_buttons_nor_the_join_notice_can_be_sent() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        discord.fail("create_reaction", MockError::MISSING_PERMISSIONS);
+        discord.fail("edit_message", MockError::MISSING_PERMISSIONS);
+
+        let (_, posted) = post_with_new_role(&discord, &data).await;
+        assert!(posted.is_ok());
+        assert!(cleanups(&discord).is_empty());
+        assert!(discord.sent_contents()[1].contains("`/join` で参加してください"));
+
+        // 案内も送れない場合も, `/join` での参加を受け付けるため募集メッセージとロールは残します
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        discord.fail("create_reaction", MockError::MISSING_PERMISSIONS);
+        discord.fail("edit_message", MockError::MISSING_PERMISSIONS);
+        discord.fail_after("send_message", 1, MockError::MISSING_PERMISSIONS);
+
+        let (_, posted) = post_with_new_role(&discord, &data).await;
+        let message_id = posted.unwrap().id;
+        assert!(cleanups(&discord).is_empty());
+        assert!(data.storage.recruit_location(message_id).is_some());
+    }
+
+    /// 最後に送ったメッセージの本文と埋め込み
+    fn last_sent(discord: &MockDiscord) -> (String, Vec<SentEmbed>) {
+        discord
+            .calls()
+            .into_iter()
+            .rev()
+            .find_map(|call| match call {
+                Call::SendMessage {
+                    content, embeds, ..
+                } => Some((content, embeds)),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn start_notification_keeps_mentions_in_the_content() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        let mut config = RecruitConfig::for_test("Apex", 2);
+        config.organizer = Some(UserId::new(13));
+        let message = post_recruit(
+            &discord,
+            &RecruitMessage::new(config).render(&data.config.emojis),
+            &[11, 12, 13],
+        );
+
+        react(&discord, &data, &message, 11, "✋").await;
+        react(&discord, &data, &message, 12, "✋").await;
+        let (content, embeds) = last_sent(&discord);
+        // メンションは埋め込みの中では通知されないため, 本文に残します
+        assert_eq!(content, "<@11> <@12>");
+        assert_eq!(embeds.len(), 1);
+        assert_eq!(embeds[0].author.as_deref(), Some("user13"));
+        let description = embeds[0].description.as_deref().unwrap();
+        assert!(
+            description.starts_with("Apex を開始します"),
+            "{description}"
+        );
+        assert!(!description.contains("<@11>"), "{description}");
+    }
+
+    #[tokio::test]
+    async fn start_notification_without_the_organizer_has_no_embed() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        let mut config = RecruitConfig::for_test("Apex", 2);
+        // 主催者はサーバーを抜けています
+        config.organizer = Some(UserId::new(13));
+        let message = post_recruit(
+            &discord,
+            &RecruitMessage::new(config).render(&data.config.emojis),
+            &[11, 12],
+        );
+
+        react(&discord, &data, &message, 11, "✋").await;
+        react(&discord, &data, &message, 12, "✋").await;
+        let (content, embeds) = last_sent(&discord);
+        assert!(
+            content.starts_with("<@11> <@12> が Apex を開始します"),
+            "{content}"
+        );
+        assert!(embeds.is_empty());
+    }
+}
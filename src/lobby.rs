@@ -0,0 +1,250 @@
+use std::collections::{HashMap, HashSet};
+
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::close_reason::{self, CloseReason};
+use crate::discord_api::DiscordApi;
+use crate::i18n::Text;
+use crate::notification::{Mentions, NotificationKind, NotificationPolicy};
+use crate::notify_prefs::StartLabels;
+use crate::recruit_log::{self, RecruitEventKind};
+use crate::recruit_message::RecruitConfig;
+use crate::storage::RecruitRecord;
+use crate::{Data, Error, member_left, member_work, mention_dedupe, notify_prefs, thread};
+
+/// `multi_lobby` の募集で, ロビーに入った参加者
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LobbyPlacement {
+    pub channel_id: ChannelId,
+    /// 開始したロビーの数 (次のロビーの番号に使います)
+    pub lobbies: usize,
+    /// ロビーに入った参加者 (再び人数に数えたり, メンションしたりしないようにします)
+    pub placed: Vec<UserId>,
+}
+
+/// 参加した順の参加者を `lobby_size` 人ずつのロビーに分け, (ロビー, 残りの参加者) を返します
+///
+/// 1 つのロビーにも満たない場合 (`:bell:` で開始した場合) は, 全員を 1 つのロビーにします。
+pub fn partition(mut order: Vec<UserId>, lobby_size: usize) -> (Vec<Vec<UserId>>, Vec<UserId>) {
+    if order.is_empty() {
+        return (Vec::new(), Vec::new());
+    }
+    if order.len() < lobby_size || lobby_size == 0 {
+        return (vec![order], Vec::new());
+    }
+    let remainder = order.split_off(order.len() / lobby_size * lobby_size);
+    let lobbies = order
+        .chunks(lobby_size)
+        .map(|lobby| lobby.to_vec())
+        .collect();
+    (lobbies, remainder)
+}
+
+/// すでにロビーに入った参加者を `user_ids` から除きます
+pub fn exclude_placed(data: &Data, message_id: MessageId, user_ids: &mut HashSet<UserId>) {
+    if let Some(placement) = data.storage.lobby_placement(message_id) {
+        for user_id in &placement.placed {
+            user_ids.remove(user_id);
+        }
+    }
+}
+
+/// `user_id` がすでにロビーに入ったかどうか
+pub fn is_placed(data: &Data, message_id: MessageId, user_id: UserId) -> bool {
+    data.storage
+        .lobby_placement(message_id)
+        .is_some_and(|placement| placement.placed.contains(&user_id))
+}
+
+/// 参加者をロビーに分けて開始通知を送ります
+///
+/// 募集は締め切らず, リアクションも付け直しません。ロビーに入らなかった参加者はそのまま次のロビーを待ちます。
+pub async fn start_lobbies(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &RecruitConfig,
+    policy: &NotificationPolicy,
+    message: &Message,
+    guild_id: Option<GuildId>,
+    participants: Vec<UserId>,
+) -> Result<(), Error> {
+    let channel_id = message.channel_id;
+    let mut placement = data
+        .storage
+        .lobby_placement(message.id)
+        .unwrap_or(LobbyPlacement {
+            channel_id,
+            lobbies: 0,
+            placed: Vec::new(),
+        });
+    let participants: Vec<UserId> = participants
+        .into_iter()
+        .filter(|user_id| !placement.placed.contains(user_id))
+        .collect();
+    let participants = match guild_id {
+        Some(guild_id) => member_left::retain_members(api, data, guild_id, participants).await,
+        None => participants,
+    };
+    let (lobbies, _remainder) = partition(participants, config.required_players);
+    if lobbies.is_empty() {
+        return Ok(());
+    }
+
+    // ロールへのメンションは最初のロビーを開始するときだけ送ります
+    let role_ping_claim = match guild_id {
+        Some(guild_id)
+            if policy.has_side_effects() && !policy.is_quiet() && placement.lobbies == 0 =>
+        {
+            Some((
+                guild_id,
+                data.storage
+                    .claim_role_pings(guild_id, &config.mention_roles, Timestamp::now())?,
+            ))
+        }
+        _ => None,
+    };
+    let role_ids = match &role_ping_claim {
+        Some((_, claim)) => claim.allowed.as_slice(),
+        None if placement.lobbies == 0 => config.mention_roles.as_slice(),
+        None => &[],
+    };
+    // `/notify_me` で開始通知のメンションを断ったユーザーは, メンションせずに表示名だけを表示します
+    let placed_users: Vec<UserId> = lobbies.iter().flatten().copied().collect();
+    let mentioned_users = notify_prefs::start_pinged_users(data, guild_id, &placed_users);
+    // ロールのメンションで通知される参加者も, 重ねて通知しないよう名前だけを表示します
+    let mentioned_users = match &role_ping_claim {
+        Some((guild_id, claim)) => mention_dedupe::apply(
+            api,
+            data,
+            *guild_id,
+            &claim.allowed,
+            &placed_users,
+            mentioned_users,
+        ),
+        None => mentioned_users,
+    };
+    let unpinged_users: Vec<UserId> = placed_users
+        .iter()
+        .copied()
+        .filter(|user_id| !mentioned_users.contains(user_id))
+        .collect();
+    let names = match guild_id {
+        Some(guild_id) => {
+            member_work::display_names(api, data, guild_id, "Lobby start", &unpinged_users).await
+        }
+        None => HashMap::new(),
+    };
+    let labels = StartLabels::new(&mentioned_users, names);
+
+    let language = config.language();
+    let mut lines: Vec<String> = role_ids
+        .iter()
+        .map(|role_id| role_id.mention().to_string())
+        .collect();
+    for (index, lobby) in lobbies.iter().enumerate() {
+        lines.push(language.text(Text::LobbyStarting {
+            number: placement.lobbies + index + 1,
+            mentions: &labels.join(lobby),
+            game_title: &config.game_title,
+        }));
+    }
+    let content = lines.join("\n");
+
+    let mentions = Mentions::users(mentioned_users).with_roles(role_ids.iter().copied());
+    let sent = thread::retry_if_archived(api, channel_id, || {
+        api.send_message(
+            channel_id,
+            policy.create_message(NotificationKind::Start, &content, &mentions),
+        )
+    })
+    .await;
+    let start_message = match sent {
+        Ok(Some(start_message)) => start_message,
+        result => {
+            if let Some((guild_id, claim)) = &role_ping_claim
+                && let Err(err) = data.storage.release_role_pings(*guild_id, claim)
+            {
+                eprintln!("Failed to release role pings: {err}");
+            }
+            return result.map(|_| ()).map_err(Into::into);
+        }
+    };
+    policy.schedule_delete(data, &start_message);
+
+    placement.lobbies += lobbies.len();
+    placement.placed.extend(lobbies.iter().flatten().copied());
+    data.storage.set_lobby_placement(message.id, placement)?;
+    data.close_notices.lock().unwrap().remove(&message.id);
+    data.participant_counts.lock().unwrap().remove(&message.id);
+
+    if let Some(guild_id) = guild_id
+        && policy.has_side_effects()
+    {
+        for lobby in &lobbies {
+            let record = RecruitRecord {
+                game_title: config.game_title.clone(),
+                category: config.category.clone(),
+                channel_id,
+                message_id: message.id,
+                started_at: Timestamp::now(),
+                participants: lobby.clone(),
+                required_players: Some(config.required_players),
+                reacted_count: Some(lobby.len()),
+            };
+            if let Err(err) = data.storage.record_recruit(guild_id, record) {
+                eprintln!("Failed to record recruit history: {err}");
+            }
+        }
+    }
+    close_reason::close_recruit(data, message.id, Some(config), CloseReason::Started);
+    recruit_log::record(
+        data,
+        message.id,
+        Some(config),
+        RecruitEventKind::Started,
+        lobbies.concat(),
+    );
+
+    #[cfg(feature = "webhook")]
+    if let Some(guild_id) = guild_id
+        && policy.has_side_effects()
+        && let Some(url) = data.storage.guild_settings(guild_id).webhook_url
+    {
+        let names =
+            member_work::display_names(api, data, guild_id, "Start webhook", &placed_users).await;
+        for lobby in lobbies {
+            let payload = crate::webhook::StartPayload::new(
+                config.game_title.clone(),
+                guild_id,
+                channel_id,
+                message.id,
+                &lobby,
+                &names,
+                Timestamp::now(),
+            );
+            crate::webhook::spawn_start_webhook(url.clone(), payload);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn users(ids: &[u64]) -> Vec<UserId> {
+        ids.iter().copied().map(UserId::new).collect()
+    }
+
+    #[test]
+    fn participants_are_split_into_full_lobbies() {
+        assert_eq!(
+            partition(users(&[1, 2, 3, 4, 5]), 2),
+            (vec![users(&[1, 2]), users(&[3, 4])], users(&[5]))
+        );
+        // 1 つのロビーに満たない場合は, 全員を 1 つのロビーにします
+        assert_eq!(partition(users(&[1]), 2), (vec![users(&[1])], Vec::new()));
+        assert_eq!(partition(Vec::new(), 2), (Vec::new(), Vec::new()));
+    }
+}
@@ -0,0 +1,177 @@
+use poise::serenity_prelude::*;
+
+use crate::config_storage;
+use crate::discord_api::DiscordApi;
+use crate::discord_error::is_unknown_member;
+use crate::member_work::{self, MemberStrategy};
+use crate::recruit_log::{self, RecruitEventKind};
+use crate::{Data, Error};
+
+/// `JOINBELL_GUILD_MEMBERS_INTENT` が `1` または `true` なら, サーバーを抜けたメンバーのイベントを受け取ります
+///
+/// Server Members Intent は Developer Portal で有効にしていないと接続できないため, 既定では受け取りません。
+pub fn guild_members_intent_enabled() -> bool {
+    std::env::var("JOINBELL_GUILD_MEMBERS_INTENT")
+        .is_ok_and(|value| matches!(value.trim(), "1" | "true"))
+}
+
+/// 参加者がサーバーのメンバーかどうかの確認結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Membership {
+    Member,
+    /// サーバーを抜けた (Unknown Member が返った)
+    Departed,
+    /// 取得に失敗して分からなかった
+    Unknown,
+}
+
+/// 参加者として残すかどうか
+///
+/// 確認に失敗したユーザーは, 参加者から漏らさないよう残します。
+pub fn keeps_participant(membership: Membership) -> bool {
+    membership != Membership::Departed
+}
+
+/// キャッシュを優先し, なければ REST API でメンバーかどうかを確認します
+async fn membership(api: &impl DiscordApi, guild_id: GuildId, user_id: UserId) -> Membership {
+    if api
+        .cache()
+        .and_then(|cache| cache.guild(guild_id))
+        .is_some_and(|guild| guild.members.contains_key(&user_id))
+    {
+        return Membership::Member;
+    }
+    match api.member(guild_id, user_id).await {
+        Ok(_) => Membership::Member,
+        Err(err) if is_unknown_member(&err) => Membership::Departed,
+        Err(err) => {
+            eprintln!("Failed to check membership of {user_id}: {err}");
+            Membership::Unknown
+        }
+    }
+}
+
+/// サーバーを抜けたユーザーを参加者から除きます (開始通知でメンションする前に使います)
+///
+/// キャッシュにない参加者が `member_work_cap` を超える場合は, メンバーの一覧からまとめて確認するか,
+/// 一覧を取得できなければ確認を省いて全員を残します。
+pub async fn retain_members(
+    api: &impl DiscordApi,
+    data: &Data,
+    guild_id: GuildId,
+    user_ids: Vec<UserId>,
+) -> Vec<UserId> {
+    let candidates = member_work::uncached(api, guild_id, &user_ids);
+    let strategy = member_work::select(data, guild_id, "Membership check", candidates.len());
+    let batch = match strategy {
+        MemberStrategy::PerMember => None,
+        MemberStrategy::Batched => {
+            Some(member_work::fetch_members(api, guild_id, &candidates).await)
+        }
+        MemberStrategy::CacheOnly => return user_ids,
+    };
+    let mut members = Vec::with_capacity(user_ids.len());
+    for user_id in user_ids {
+        let membership = match &batch {
+            Some(batch) if candidates.contains(&user_id) => batch.membership(user_id),
+            _ => membership(api, guild_id, user_id).await,
+        };
+        if keeps_participant(membership) {
+            members.push(user_id);
+        }
+    }
+    members
+}
+
+/// サーバーを抜けたユーザーを, そのサーバーの募集の参加者の記録から除き, リアクションを削除します
+///
+/// リアクションを削除するのは, 参加者として記録していた募集だけです。
+/// メッセージの管理権限がない場合などはログを出力して続けます。
+pub async fn handle_member_removal(
+    ctx: &Context,
+    data: &Data,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<(), Error> {
+    for (message_id, channel_id) in data.storage.recruit_locations_in_guild(guild_id) {
+        let _guard = data.lock_message(message_id).await;
+        let recorded = data.storage.remove_participant(message_id, user_id)?;
+        let processed = data
+            .processed_reactors
+            .lock()
+            .unwrap()
+            .get_mut(&message_id)
+            .is_some_and(|reactors| reactors.remove(&user_id).is_some());
+        if !recorded && !processed {
+            continue;
+        }
+        if recorded {
+            recruit_log::record(
+                data,
+                message_id,
+                None,
+                RecruitEventKind::MemberLeft { user_id },
+                data.storage.participant_order(message_id),
+            );
+        }
+        data.participant_counts.lock().unwrap().remove(&message_id);
+        data.rest_budget
+            .run(remove_reactions(ctx, data, channel_id, message_id, user_id))
+            .await;
+    }
+    Ok(())
+}
+
+/// 抜けたメンバーの, 募集メッセージの参加のリアクションを削除します
+async fn remove_reactions(
+    ctx: &Context,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    user_id: UserId,
+) {
+    let message = match channel_id.message(ctx, message_id).await {
+        Ok(message) => message,
+        Err(err) => {
+            eprintln!("Failed to fetch recruit {message_id} for departed member: {err}");
+            return;
+        }
+    };
+    let Ok(recruit_message) = config_storage::load(data, &message) else {
+        return;
+    };
+    for reaction_type in recruit_message.config.reaction_types(&data.config.emojis) {
+        if let Err(err) = channel_id
+            .delete_reaction(ctx, message_id, Some(user_id), reaction_type)
+            .await
+        {
+            eprintln!("Failed to remove reaction of departed member: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_discord::{self, MockDiscord, MockError};
+
+    const GUILD_ID: GuildId = GuildId::new(1);
+
+    #[tokio::test]
+    async fn only_departed_members_are_dropped() {
+        let api = MockDiscord::new();
+        api.insert_member(mock_discord::member(GUILD_ID, 11));
+        let member = membership(&api, GUILD_ID, UserId::new(11)).await;
+        let departed = membership(&api, GUILD_ID, UserId::new(12)).await;
+        assert_eq!(member, Membership::Member);
+        assert_eq!(departed, Membership::Departed);
+
+        // 確認に失敗したユーザーは残します
+        api.fail("member", MockError::MISSING_PERMISSIONS);
+        let unknown = membership(&api, GUILD_ID, UserId::new(12)).await;
+        assert_eq!(unknown, Membership::Unknown);
+        assert!(keeps_participant(member));
+        assert!(!keeps_participant(departed));
+        assert!(keeps_participant(unknown));
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::{HashMap, HashSet};
+
+use poise::serenity_prelude::*;
+
+use crate::discord_api::DiscordApi;
+use crate::member_left::{self, Membership};
+use crate::{Data, Error, send_ephemeral};
+
+/// 取得するメンバーがこの人数を超えたら, 1 人ずつ取得するのをやめます (`member_work_cap` の既定値)
+pub const DEFAULT_MEMBER_WORK_CAP: usize = 50;
+pub const MAX_MEMBER_WORK_CAP: usize = 1000;
+/// メンバーの一覧を 1 回に取得する人数 (Discord の上限)
+pub const MEMBERS_PAGE_SIZE: usize = 1000;
+/// メンバーの一覧をまとめて取得するときに, 1 回の処理で取得するページ数の上限
+const MAX_MEMBER_PAGES: usize = 20;
+
+/// 参加者のメンバーの情報の集め方 (処理ごとに 1 度だけ選びます)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberStrategy {
+    /// キャッシュになければ 1 人ずつ REST API で取得し, 表示名で表示します
+    PerMember,
+    /// サーバーのメンバーの一覧をページごとにまとめて取得し, 表示名の代わりにメンションで表示します
+    Batched,
+    /// メンバーの一覧を取得できない (Server Members Intent を使わない) ため, キャッシュだけを使います
+    ///
+    /// 省いても参加者から漏れない確認 (サーバーを抜けたかどうかなど) は行いません。
+    CacheOnly,
+}
+
+impl MemberStrategy {
+    /// キャッシュにないメンバーの人数 `candidates` と上限 `cap` から選びます
+    pub fn choose(candidates: usize, cap: usize, members_intent: bool) -> Self {
+        if candidates <= cap {
+            MemberStrategy::PerMember
+        } else if members_intent {
+            MemberStrategy::Batched
+        } else {
+            MemberStrategy::CacheOnly
+        }
+    }
+
+    /// ユーザーを表示名で表示するかどうか (しない場合はメンションで表示します)
+    pub fn shows_display_names(self) -> bool {
+        self == MemberStrategy::PerMember
+    }
+}
+
+/// サーバーの上限に従って集め方を選び, 1 人ずつ取得しない場合はログに出力します
+///
+/// `operation` はログに出力する処理の名前です。
+pub fn select(
+    data: &Data,
+    guild_id: GuildId,
+    operation: &str,
+    candidates: usize,
+) -> MemberStrategy {
+    let cap = data
+        .storage
+        .guild_settings(guild_id)
+        .member_work_cap
+        .unwrap_or(DEFAULT_MEMBER_WORK_CAP);
+    let strategy =
+        MemberStrategy::choose(candidates, cap, member_left::guild_members_intent_enabled());
+    if strategy != MemberStrategy::PerMember {
+        eprintln!(
+            "{operation} in {guild_id}: {candidates} uncached members exceed the cap of {cap}, using {strategy:?}"
+        );
+    }
+    strategy
+}
+
+/// キャッシュにあるメンバー
+pub fn cached_member(api: &impl DiscordApi, guild_id: GuildId, user_id: UserId) -> Option<Member> {
+    api.cache()?.guild(guild_id)?.members.get(&user_id).cloned()
+}
+
+/// キャッシュにないユーザー
+pub fn uncached(api: &impl DiscordApi, guild_id: GuildId, user_ids: &[UserId]) -> Vec<UserId> {
+    let Some(guild) = api.cache().and_then(|cache| cache.guild(guild_id)) else {
+        return user_ids.to_vec();
+    };
+    user_ids
+        .iter()
+        .copied()
+        .filter(|user_id| !guild.members.contains_key(user_id))
+        .collect()
+}
+
+/// メンバーの一覧からまとめて取得したメンバー
+#[derive(Debug, Default)]
+pub struct MemberBatch {
+    members: HashMap<UserId, Member>,
+    /// 一覧を確認し終えた最後のユーザー ID (これ以下で見つからなかったユーザーはメンバーではありません)
+    scanned_through: u64,
+    /// 一覧を最後まで確認したかどうか
+    reached_end: bool,
+}
+
+impl MemberBatch {
+    pub fn member(&self, user_id: UserId) -> Option<&Member> {
+        self.members.get(&user_id)
+    }
+
+    /// 一覧を確認できた範囲で, メンバーかどうかを返します
+    pub fn membership(&self, user_id: UserId) -> Membership {
+        if self.members.contains_key(&user_id) {
+            Membership::Member
+        } else if self.reached_end || user_id.get() <= self.scanned_through {
+            Membership::Departed
+        } else {
+            Membership::Unknown
+        }
+    }
+}
+
+/// `user_ids` のメンバーを, サーバーのメンバーの一覧からまとめて取得します
+///
+/// 一覧はユーザー ID 順に返るため, 最も小さい ID の直前から取得を始め, 全員が見つかるか最も大きい ID を
+/// 過ぎたところでやめます。取得に失敗した場合とページ数の上限に達した場合は, それまでの結果を返します。
+pub async fn fetch_members(
+    api: &impl DiscordApi,
+    guild_id: GuildId,
+    user_ids: &[UserId],
+) -> MemberBatch {
+    let mut wanted: HashSet<UserId> = user_ids.iter().copied().collect();
+    let (Some(first), Some(last)) = (user_ids.iter().min(), user_ids.iter().max()) else {
+        return MemberBatch {
+            reached_end: true,
+            ..MemberBatch::default()
+        };
+    };
+    let last = last.get();
+    let mut batch = MemberBatch {
+        scanned_through: first.get() - 1,
+        ..MemberBatch::default()
+    };
+    for _ in 0..MAX_MEMBER_PAGES {
+        let after = (batch.scanned_through != 0).then(|| UserId::new(batch.scanned_through));
+        let page = match api.guild_members(guild_id, after).await {
+            Ok(page) => page,
+            Err(err) => {
+                eprintln!("Failed to fetch members of {guild_id}: {err}");
+                return batch;
+            }
+        };
+        let full = page.len() == MEMBERS_PAGE_SIZE;
+        for member in page {
+            let user_id = member.user.id;
+            batch.scanned_through = batch.scanned_through.max(user_id.get());
+            if wanted.remove(&user_id) {
+                batch.members.insert(user_id, member);
+            }
+        }
+        if !full {
+            batch.reached_end = true;
+            return batch;
+        }
+        if wanted.is_empty() || last <= batch.scanned_through {
+            return batch;
+        }
+    }
+    eprintln!("Stopped fetching members of {guild_id} after {MAX_MEMBER_PAGES} pages");
+    batch
+}
+
+/// ユーザーの表示名を調べます (調べられなかったユーザーは含めません)
+///
+/// キャッシュにないユーザーが `member_work_cap` を超える場合は, メンバーの一覧からまとめて取得するか,
+/// 一覧を取得できなければキャッシュにあるユーザーだけを調べます。`operation` はログに出力する処理の名前です。
+pub async fn display_names(
+    api: &impl DiscordApi,
+    data: &Data,
+    guild_id: GuildId,
+    operation: &str,
+    user_ids: &[UserId],
+) -> HashMap<UserId, String> {
+    let candidates = uncached(api, guild_id, user_ids);
+    let strategy = select(data, guild_id, operation, candidates.len());
+    let batch = match strategy {
+        MemberStrategy::Batched => fetch_members(api, guild_id, &candidates).await,
+        MemberStrategy::PerMember | MemberStrategy::CacheOnly => MemberBatch::default(),
+    };
+    let mut names = HashMap::new();
+    for &user_id in user_ids {
+        let member = match cached_member(api, guild_id, user_id) {
+            Some(member) => Some(member),
+            None if strategy == MemberStrategy::PerMember => {
+                api.member(guild_id, user_id).await.ok()
+            }
+            None => batch.member(user_id).cloned(),
+        };
+        if let Some(member) = member {
+            names.insert(user_id, member.display_name().to_string());
+        }
+    }
+    names
+}
+
+/// メンバーを 1 人ずつ取得する人数の上限を設定します
+///
+/// 大きなサーバーで, REST API の呼び出しが増えすぎないようにします。
+#[poise::command(slash_command, guild_only, rename = "member_work_cap")]
+pub async fn settings_member_work_cap(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "1 人ずつ取得する人数の上限 (既定: 50)"]
+    #[min = 1]
+    #[max = 1000]
+    size: Option<usize>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.member_work_cap = size;
+        })?;
+    let size = size.unwrap_or(DEFAULT_MEMBER_WORK_CAP);
+    let content = format!(
+        "キャッシュにない参加者が {size} 人を超えたら、メンバーの情報を 1 人ずつ取得せず、名前の代わりにメンションで表示します"
+    );
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_discord::MockDiscord;
+
+    const GUILD_ID: GuildId = GuildId::new(1);
+
+    #[test]
+    fn members_over_the_cap_are_fetched_in_batches() {
+        assert_eq!(
+            MemberStrategy::choose(50, 50, false),
+            MemberStrategy::PerMember
+        );
+        assert_eq!(
+            MemberStrategy::choose(51, 50, true),
+            MemberStrategy::Batched
+        );
+        assert_eq!(
+            MemberStrategy::choose(51, 50, false),
+            MemberStrategy::CacheOnly
+        );
+        assert!(MemberStrategy::PerMember.shows_display_names());
+        assert!(!MemberStrategy::Batched.shows_display_names());
+    }
+
+    #[test]
+    fn the_cap_is_read_from_the_guild_settings() {
+        let data = Data::for_test();
+        data.storage
+            .update_guild_settings(GUILD_ID, |settings| settings.member_work_cap = Some(100))
+            .unwrap();
+        assert_eq!(
+            select(&data, GUILD_ID, "Test", 100),
+            MemberStrategy::PerMember
+        );
+        // キャッシュを使わない場合は, 全員を取得します
+        let user_ids = [UserId::new(11), UserId::new(12)];
+        assert_eq!(uncached(&MockDiscord::new(), GUILD_ID, &user_ids), user_ids);
+    }
+}
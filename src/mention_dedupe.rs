@@ -0,0 +1,145 @@
+use std::collections::{HashMap, HashSet};
+
+use poise::serenity_prelude::*;
+
+use crate::discord_api::DiscordApi;
+use crate::{Data, Error, send_ephemeral};
+
+/// 参加者ごとの, 持っているロール
+pub type MemberRoles = HashMap<UserId, Vec<RoleId>>;
+
+/// ロールのメンションで通知される参加者を, 開始通知でメンションを送るユーザーから除きます
+///
+/// `role_ids` は開始通知でメンションするロールです。サーバー設定 `dedupe_mentions` が無効な場合と,
+/// ロールをメンションしない場合は除きません。除いた参加者は開始通知に名前だけを表示します。
+pub fn apply(
+    api: &impl DiscordApi,
+    data: &Data,
+    guild_id: GuildId,
+    role_ids: &[RoleId],
+    participants: &[UserId],
+    mentioned_users: Vec<UserId>,
+) -> Vec<UserId> {
+    let enabled = data
+        .storage
+        .guild_settings(guild_id)
+        .dedupe_mentions
+        .unwrap_or(true);
+    if !enabled || role_ids.is_empty() {
+        return mentioned_users;
+    }
+    let Some((mentionable, member_roles)) = cached_roles(api, guild_id, role_ids, participants)
+    else {
+        return mentioned_users;
+    };
+    let covered = covered_by_roles(&mentionable, &member_roles);
+    dedupe(mentioned_users, &covered)
+}
+
+/// `role_ids` のうちメンションできる設定のロールと, キャッシュにある参加者のロール
+///
+/// サーバーがキャッシュになければ `None` を返します。
+fn cached_roles(
+    api: &impl DiscordApi,
+    guild_id: GuildId,
+    role_ids: &[RoleId],
+    participants: &[UserId],
+) -> Option<(Vec<RoleId>, MemberRoles)> {
+    let guild = api.cache()?.guild(guild_id)?;
+    // メンションできないロールは, Bot の権限によっては通知されないため除きます
+    let mentionable = role_ids
+        .iter()
+        .copied()
+        .filter(|role_id| {
+            guild
+                .roles
+                .get(role_id)
+                .is_some_and(|role| role.mentionable)
+        })
+        .collect();
+    let member_roles = participants
+        .iter()
+        .filter_map(|user_id| {
+            let member = guild.members.get(user_id)?;
+            Some((*user_id, member.roles.clone()))
+        })
+        .collect();
+    Some((mentionable, member_roles))
+}
+
+/// 参加者のうち, `role_ids` のいずれかのロールを持っていてロールのメンションで通知される参加者
+///
+/// `member_roles` はロールが分かる参加者だけを含みます。ロールが分からない参加者は含めません (個別にメンションします)。
+pub fn covered_by_roles(role_ids: &[RoleId], member_roles: &MemberRoles) -> HashSet<UserId> {
+    member_roles
+        .iter()
+        .filter(|(_, roles)| roles.iter().any(|role_id| role_ids.contains(role_id)))
+        .map(|(user_id, _)| *user_id)
+        .collect()
+}
+
+/// メンションを送るユーザーから, ロールのメンションで通知されるユーザーを除きます
+pub fn dedupe(mentioned_users: Vec<UserId>, covered: &HashSet<UserId>) -> Vec<UserId> {
+    mentioned_users
+        .into_iter()
+        .filter(|user_id| !covered.contains(user_id))
+        .collect()
+}
+
+/// ロールで通知される参加者を, 開始通知で名前だけにするか設定します
+#[poise::command(slash_command, guild_only, rename = "dedupe_mentions")]
+pub async fn settings_dedupe_mentions(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "ロールを持つ参加者は名前だけにするかどうか (既定: はい)"] enabled: bool,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.dedupe_mentions = Some(enabled);
+        })?;
+    let content = if enabled {
+        "開始通知でメンションするロールを持つ参加者は、個別にメンションせず名前で表示します"
+    } else {
+        "開始通知でメンションするロールを持つ参加者も、個別にメンションします"
+    };
+    send_ephemeral(ctx, content).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify_prefs::StartLabels;
+
+    const ROLE: RoleId = RoleId::new(10);
+    const OTHER_ROLE: RoleId = RoleId::new(11);
+
+    #[test]
+    fn only_role_holders_are_covered() {
+        let member_roles = HashMap::from([
+            (UserId::new(1), vec![ROLE]),
+            (UserId::new(2), vec![OTHER_ROLE]),
+            (UserId::new(3), vec![OTHER_ROLE, ROLE]),
+            (UserId::new(4), Vec::new()),
+        ]);
+        let covered = covered_by_roles(&[ROLE], &member_roles);
+        assert_eq!(covered, HashSet::from([UserId::new(1), UserId::new(3)]));
+        assert!(covered_by_roles(&[], &member_roles).is_empty());
+    }
+
+    #[test]
+    fn covered_users_are_named_and_others_keep_mentions() {
+        let participants: Vec<UserId> = (1..=3).map(UserId::new).collect();
+        // ロールが分からない参加者 (3) はメンションします
+        let member_roles =
+            HashMap::from([(UserId::new(1), vec![ROLE]), (UserId::new(2), Vec::new())]);
+        let covered = covered_by_roles(&[ROLE], &member_roles);
+        let mentioned = dedupe(participants.clone(), &covered);
+        assert_eq!(mentioned, vec![UserId::new(2), UserId::new(3)]);
+
+        let names = HashMap::from([(UserId::new(1), "Alice".to_string())]);
+        let labels = StartLabels::new(&mentioned, names);
+        assert_eq!(labels.join(&participants), "@Alice <@2> <@3>");
+    }
+}
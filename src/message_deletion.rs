@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+
+use poise::serenity_prelude::*;
+
+use crate::Data;
+use crate::scheduler::ScheduledJob;
+
+/// まとめて削除されたメッセージのうち, Bot が記録を持つもの
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DeletedTargets {
+    /// 記録のある募集メッセージ (削除された募集として終了し, 状態と予約を消します)
+    pub recruits: Vec<MessageId>,
+    /// 削除を予約していたメッセージ (予約を黙って取り消します)
+    pub scheduled_deletions: Vec<MessageId>,
+}
+
+impl DeletedTargets {
+    pub fn is_empty(&self) -> bool {
+        self.recruits.is_empty() && self.scheduled_deletions.is_empty()
+    }
+}
+
+/// 削除されたメッセージ `deleted` を, 記録のある募集と削除を予約したメッセージに分けます
+///
+/// 同じメッセージが両方に入ることもあります (開始した後に削除を予約した募集メッセージなど)。
+pub fn classify(
+    deleted: &[MessageId],
+    is_recruit: impl Fn(MessageId) -> bool,
+    jobs: &[ScheduledJob],
+) -> DeletedTargets {
+    let deleted: HashSet<MessageId> = deleted.iter().copied().collect();
+    let mut recruits: Vec<MessageId> = deleted
+        .iter()
+        .copied()
+        .filter(|&message_id| is_recruit(message_id))
+        .collect();
+    recruits.sort();
+    let mut scheduled_deletions: Vec<MessageId> = jobs
+        .iter()
+        .filter_map(|job| job.payload.deleted_message_id())
+        .filter(|message_id| deleted.contains(message_id))
+        .collect();
+    scheduled_deletions.sort();
+    scheduled_deletions.dedup();
+    DeletedTargets {
+        recruits,
+        scheduled_deletions,
+    }
+}
+
+/// 削除されたメッセージの削除の予約を取り消します
+///
+/// 削除済みのメッセージを削除しようとしてエラーを出力し続けないようにします。
+pub fn drop_scheduled_deletions(data: &Data, deleted: &[MessageId]) {
+    if let Err(err) = data.storage.cancel_jobs(&|job| {
+        job.deleted_message_id()
+            .is_some_and(|message_id| deleted.contains(&message_id))
+    }) {
+        eprintln!("Failed to cancel deletion jobs of deleted messages: {err}");
+    }
+}
+
+/// まとめて削除されたメッセージのうち, 募集は削除された募集として終了し, 削除の予約は取り消します
+///
+/// 記録のないメッセージ (ほかのユーザーのメッセージなど) は何もしません。
+pub fn handle_bulk_delete(data: &Data, channel_id: ChannelId, deleted: &[MessageId]) {
+    let targets = classify(
+        deleted,
+        |message_id| data.is_tracked_message(message_id),
+        &data.storage.jobs(),
+    );
+    if targets.is_empty() {
+        return;
+    }
+    eprintln!(
+        "{} messages were bulk deleted in {channel_id}: closing {} recruits, dropping {} scheduled deletions",
+        deleted.len(),
+        targets.recruits.len(),
+        targets.scheduled_deletions.len()
+    );
+    if !targets.scheduled_deletions.is_empty() {
+        drop_scheduled_deletions(data, &targets.scheduled_deletions);
+    }
+    for &message_id in &targets.recruits {
+        data.forget_deleted_message(message_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::Job;
+
+    const CHANNEL_ID: ChannelId = ChannelId::new(1);
+
+    fn messages(ids: &[u64]) -> Vec<MessageId> {
+        ids.iter().map(|&id| MessageId::new(id)).collect()
+    }
+
+    fn deletion(id: u64, message_id: u64) -> ScheduledJob {
+        ScheduledJob {
+            id,
+            fire_at: Timestamp::from_unix_timestamp(1_700_000_000).unwrap(),
+            payload: Job::DeleteMessage {
+                channel_id: CHANNEL_ID,
+                message_id: MessageId::new(message_id),
+                content: None,
+            },
+            attempts: 0,
+            leased: false,
+        }
+    }
+
+    #[test]
+    fn deleted_messages_are_split_into_recruits_and_scheduled_deletions() {
+        let jobs = [deletion(1, 12), deletion(2, 12), deletion(3, 20)];
+        let targets = classify(&messages(&[13, 12, 11]), |id| id.get() != 12, &jobs);
+        assert_eq!(
+            targets,
+            DeletedTargets {
+                recruits: messages(&[11, 13]),
+                // 同じメッセージの予約が重なっていても 1 つにまとめます
+                scheduled_deletions: messages(&[12]),
+            }
+        );
+        assert!(!targets.is_empty());
+    }
+
+    #[test]
+    fn untracked_messages_are_ignored() {
+        let targets = classify(&messages(&[11]), |_| false, &[deletion(1, 20)]);
+        assert!(targets.is_empty());
+    }
+}
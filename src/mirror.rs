@@ -0,0 +1,326 @@
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::close_reason::CloseReason;
+use crate::discord_api::DiscordApi;
+use crate::discord_error::{EntityKind, is_unknown_entity};
+use crate::recruit_message::RecruitConfig;
+use crate::summary::{self, SummaryEntry};
+use crate::{Data, Error, bot_permissions, notification, participant_count, send_ephemeral};
+
+/// `/channel_settings mirror` のチャンネルに投稿した, 募集の読み取り専用のミラー
+///
+/// 募集メッセージが削除されても終わった表示にできるよう, 最後に表示した募集の内容を保存します。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MirrorMessage {
+    pub guild_id: GuildId,
+    /// ミラーを投稿したチャンネル
+    pub channel_id: ChannelId,
+    /// ミラーのメッセージ
+    pub message_id: MessageId,
+    /// 最後に表示した募集の内容
+    pub entry: SummaryEntry,
+    /// 募集が終わった理由 (終わった表示に書き換えたら記録を消します)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closed: Option<CloseReason>,
+}
+
+/// ミラーの本文
+///
+/// 参加はできないため, リアクションやボタンは付けずに募集メッセージへのリンクを表示します。
+/// 削除された募集にはリンクを表示しません。
+pub fn render(guild_id: GuildId, entry: &SummaryEntry, closed: Option<CloseReason>) -> String {
+    let count = entry
+        .count
+        .map_or_else(|| "?".to_string(), |count| count.to_string());
+    let link = entry.message_id.link(entry.channel_id, Some(guild_id));
+    let Some(reason) = closed else {
+        let mut line = format!(
+            "🔭 **{}** {count}/{}",
+            entry.game_title, entry.required_players
+        );
+        if let Some(start_time) = entry.start_time {
+            line += &format!(" (<t:{}:f> 開始予定)", start_time.unix_timestamp());
+        }
+        return format!("{line}\n参加は募集から: {link}");
+    };
+    let line = format!(
+        "🔒 ~~**{}**~~ {count}/{} この募集は終了しました ({reason})",
+        entry.game_title, entry.required_players
+    );
+    if reason == CloseReason::Deleted {
+        line
+    } else {
+        format!("{line}\n{link}")
+    }
+}
+
+/// 募集を投稿したチャンネルにミラーの設定があれば, ミラーを投稿します
+///
+/// 動作確認用の募集はミラーしません。投稿できなくても募集の作成は続けます。
+pub async fn create(
+    api: &impl DiscordApi,
+    data: &Data,
+    guild_id: Option<GuildId>,
+    message: &Message,
+    config: &RecruitConfig,
+) {
+    let Some(guild_id) = guild_id else {
+        return;
+    };
+    if config.simulation {
+        return;
+    }
+    let Some(channel_id) = data
+        .storage
+        .guild_settings(guild_id)
+        .mirror_channels
+        .get(&message.channel_id)
+        .copied()
+    else {
+        return;
+    };
+    let entry = SummaryEntry {
+        channel_id: message.channel_id,
+        message_id: message.id,
+        game_title: config.game_title.clone(),
+        count: participant_count::last_count(data, message.id),
+        required_players: config.required_players,
+        start_time: config.start_time,
+    };
+    let content = render(guild_id, &entry, None);
+    let posted = match api
+        .send_message(channel_id, notification::plain_message(content))
+        .await
+    {
+        Ok(posted) => posted,
+        Err(err) => {
+            eprintln!("Failed to post mirror of recruit {}: {err}", message.id);
+            return;
+        }
+    };
+    let mirror = MirrorMessage {
+        guild_id,
+        channel_id,
+        message_id: posted.id,
+        entry,
+        closed: None,
+    };
+    if let Err(err) = data.storage.set_mirror(message.id, Some(mirror)) {
+        eprintln!("Failed to save mirror of recruit {}: {err}", message.id);
+    }
+}
+
+/// 募集が終わったことをミラーに記録し, 終わった表示への書き換えを予約します
+pub fn close(data: &Data, message_id: MessageId, reason: CloseReason) {
+    match data.storage.update_mirror(message_id, |mirror| {
+        mirror.closed.get_or_insert(reason);
+    }) {
+        Ok(Some(())) => data.summary_board.mark_mirror(message_id),
+        Ok(None) => {}
+        Err(err) => eprintln!("Failed to close mirror of recruit {message_id}: {err}"),
+    }
+}
+
+/// ミラーを募集の状態に書き換えます
+///
+/// 終わった表示に書き換えたら記録を消します。ミラーのメッセージが削除されていた場合は,
+/// 受付中なら投稿し直し, 終わった募集なら記録を消します。
+pub async fn sync(ctx: &Context, data: &Data, message_id: MessageId) -> Result<(), Error> {
+    let Some(mirror) = data.storage.mirror(message_id) else {
+        return Ok(());
+    };
+    let mirror = match mirror.closed {
+        Some(_) => mirror,
+        None => {
+            // 読み取れない場合は, 終わったことが記録されるまで前の表示のままにします
+            let Some(mut entry) =
+                summary::open_entry(ctx, data, mirror.entry.channel_id, message_id).await
+            else {
+                return Ok(());
+            };
+            let updated = data.storage.update_mirror(message_id, |mirror| {
+                // 数え直しの前は, 前に数えた人数を表示します
+                entry.count = entry.count.or(mirror.entry.count);
+                mirror.entry = entry;
+                mirror.clone()
+            })?;
+            match updated {
+                Some(mirror) => mirror,
+                None => return Ok(()),
+            }
+        }
+    };
+    let content = render(mirror.guild_id, &mirror.entry, mirror.closed);
+    match mirror
+        .channel_id
+        .edit_message(ctx, mirror.message_id, EditMessage::new().content(&content))
+        .await
+    {
+        Ok(_) => {}
+        Err(err)
+            if mirror.closed.is_none() && is_unknown_entity(&err) == Some(EntityKind::Message) =>
+        {
+            eprintln!("Mirror of recruit {message_id} was deleted, posting it again");
+            let posted = mirror
+                .channel_id
+                .send_message(ctx, notification::plain_message(content))
+                .await?;
+            data.storage
+                .update_mirror(message_id, |mirror| mirror.message_id = posted.id)?;
+            return Ok(());
+        }
+        Err(err) if is_unknown_entity(&err).is_some() => {}
+        Err(err) => return Err(err.into()),
+    }
+    if mirror.closed.is_some() {
+        data.storage.set_mirror(message_id, None)?;
+    }
+    Ok(())
+}
+
+/// 募集を読み取り専用で別のチャンネルにミラーする設定を管理します
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "mirror",
+    subcommands("mirror_add", "mirror_list", "mirror_remove")
+)]
+pub async fn settings_mirror(_ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// チャンネルの募集を別のチャンネルにミラーします
+///
+/// ミラーはメンションせずに投稿し, 参加人数と終わったことを募集に合わせて書き換えます。
+/// 設定した後に投稿した募集をミラーします。
+#[poise::command(slash_command, guild_only, rename = "add")]
+async fn mirror_add(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集するチャンネル"]
+    #[channel_types("Text", "News", "Voice")]
+    source: GuildChannel,
+    #[description = "ミラーを投稿するチャンネル"]
+    #[channel_types("Text", "News")]
+    target: GuildChannel,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    if source.id == target.id {
+        send_ephemeral(
+            ctx,
+            "募集するチャンネルと異なるチャンネルを指定してください",
+        )
+        .await?;
+        return Ok(());
+    }
+    if bot_permissions(ctx.serenity_context(), guild_id, target.id)
+        .is_some_and(|permissions| !(permissions.view_channel() && permissions.send_messages()))
+    {
+        let content = format!(
+            "Bot が {} にメッセージを送信できません。チャンネルの権限を確認してください",
+            target.mention()
+        );
+        send_ephemeral(ctx, &content).await?;
+        return Ok(());
+    }
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.mirror_channels.insert(source.id, target.id);
+        })?;
+    let content = format!(
+        "{} の募集を {} にミラーします",
+        source.mention(),
+        target.mention()
+    );
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// 募集をミラーするチャンネルの一覧を表示します
+#[poise::command(slash_command, guild_only, rename = "list")]
+async fn mirror_list(ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let mirror_channels = ctx.data().storage.guild_settings(guild_id).mirror_channels;
+    if mirror_channels.is_empty() {
+        send_ephemeral(ctx, "ミラーは設定されていません").await?;
+        return Ok(());
+    }
+    let lines: Vec<String> = mirror_channels
+        .iter()
+        .map(|(source, target)| format!("{} → {}", source.mention(), target.mention()))
+        .collect();
+    send_ephemeral(ctx, &lines.join("\n")).await?;
+    Ok(())
+}
+
+/// チャンネルの募集のミラーをやめます
+///
+/// 投稿済みのミラーは, 募集が終わるまで書き換えます。
+#[poise::command(slash_command, guild_only, rename = "remove")]
+async fn mirror_remove(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "ミラーをやめるチャンネル"] source: GuildChannel,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let mut removed = false;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            removed = settings.mirror_channels.remove(&source.id).is_some();
+        })?;
+    let content = if removed {
+        format!("{} の募集のミラーをやめました", source.mention())
+    } else {
+        format!("{} にはミラーが設定されていません", source.mention())
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD_ID: GuildId = GuildId::new(1);
+    const LINK: &str = "https://discord.com/channels/1/2/3";
+
+    fn entry(count: Option<usize>, start_time: Option<i64>) -> SummaryEntry {
+        SummaryEntry {
+            channel_id: ChannelId::new(2),
+            message_id: MessageId::new(3),
+            game_title: "Apex".to_string(),
+            count,
+            required_players: 3,
+            start_time: start_time.map(|seconds| Timestamp::from_unix_timestamp(seconds).unwrap()),
+        }
+    }
+
+    #[test]
+    fn open_recruits_link_to_the_recruit() {
+        assert_eq!(
+            render(GUILD_ID, &entry(Some(2), Some(1_700_000_000)), None),
+            format!("🔭 **Apex** 2/3 (<t:1700000000:f> 開始予定)\n参加は募集から: {LINK}")
+        );
+        // まだ数えていない募集は人数を ? にします
+        assert_eq!(
+            render(GUILD_ID, &entry(None, None), None),
+            format!("🔭 **Apex** ?/3\n参加は募集から: {LINK}")
+        );
+    }
+
+    #[test]
+    fn closed_recruits_are_struck_through() {
+        let closed = render(GUILD_ID, &entry(Some(3), None), Some(CloseReason::Started));
+        assert_eq!(
+            closed,
+            format!(
+                "🔒 ~~**Apex**~~ 3/3 この募集は終了しました ({})\n{LINK}",
+                CloseReason::Started
+            )
+        );
+        // 削除された募集へのリンクは表示しません
+        let deleted = render(GUILD_ID, &entry(Some(3), None), Some(CloseReason::Deleted));
+        assert!(!deleted.contains(LINK));
+    }
+}
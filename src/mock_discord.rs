@@ -0,0 +1,577 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use poise::serenity_prelude::{self as serenity, *};
+
+use crate::discord_api::DiscordApi;
+use crate::discord_error::{MISSING_PERMISSIONS, UNKNOWN_CHANNEL, UNKNOWN_MEMBER, UNKNOWN_MESSAGE};
+
+/// `MockDiscord` が受けた呼び出し
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Call {
+    ReactionUsers {
+        message_id: MessageId,
+        reaction: String,
+    },
+    SendMessage {
+        channel_id: ChannelId,
+        content: String,
+        embeds: Vec<SentEmbed>,
+    },
+    Message {
+        message_id: MessageId,
+    },
+    EditMessage {
+        message_id: MessageId,
+        content: Option<String>,
+    },
+    CreateReaction {
+        message_id: MessageId,
+        reaction: String,
+    },
+    DeleteReaction {
+        message_id: MessageId,
+        user_id: Option<UserId>,
+        reaction: String,
+    },
+    DeleteReactions {
+        message_id: MessageId,
+    },
+    DeleteMessage {
+        message_id: MessageId,
+    },
+    DirectMessage {
+        user_id: UserId,
+        content: String,
+    },
+    UnarchiveThread {
+        channel_id: ChannelId,
+    },
+    Channel {
+        channel_id: ChannelId,
+    },
+    Member {
+        user_id: UserId,
+    },
+    GuildMembers {
+        after: Option<UserId>,
+    },
+    AddRole {
+        user_id: UserId,
+        role_id: RoleId,
+    },
+    CreateRole {
+        name: Option<String>,
+    },
+    DeleteRole {
+        role_id: RoleId,
+    },
+}
+
+/// 送ったメッセージの埋め込みの, 見出しの名前と説明文
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentEmbed {
+    pub author: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Discord の API が返すエラー (HTTP のステータスと Discord のエラーコード)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MockError {
+    pub status: u16,
+    pub code: isize,
+}
+
+impl MockError {
+    pub const UNKNOWN_MESSAGE: MockError = MockError {
+        status: 404,
+        code: UNKNOWN_MESSAGE,
+    };
+    pub const MISSING_PERMISSIONS: MockError = MockError {
+        status: 403,
+        code: MISSING_PERMISSIONS,
+    };
+
+    /// serenity のエラーにします
+    pub async fn into_error(self) -> serenity::Error {
+        let body = serde_json::json!({ "code": self.code, "message": "mock error" }).to_string();
+        let response = ::http::Response::builder()
+            .status(self.status)
+            .body(body)
+            .unwrap();
+        let response =
+            ErrorResponse::from_response(response.into(), LightMethod::Get.reqwest_method()).await;
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response))
+    }
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    calls: Vec<Call>,
+    messages: HashMap<MessageId, Message>,
+    /// 募集メッセージとリアクションの絵文字ごとの, リアクションしたユーザー (リアクションした順)
+    reactions: HashMap<(MessageId, String), Vec<User>>,
+    members: HashMap<UserId, Member>,
+    /// 呼び出しの種類ごとに, 成功させる残りの回数とその後の呼び出しで返すエラー
+    failures: HashMap<&'static str, (usize, MockError)>,
+    /// 送ったメッセージや作成したロールに付ける, 最後の ID
+    next_id: u64,
+}
+
+/// Discord に接続せずに, 呼び出しを記録して用意したデータを返す `DiscordApi`
+#[derive(Debug, Clone, Default)]
+pub struct MockDiscord {
+    state: Arc<Mutex<MockState>>,
+}
+
+/// テスト用のユーザー (`BOT_ID` なら Bot)
+pub fn user(id: u64) -> User {
+    let mut user = User::default();
+    user.id = UserId::new(id);
+    user.name = format!("user{id}");
+    user.bot = user.id == BOT_ID;
+    user
+}
+
+/// テスト用のメンバー
+pub fn member(guild_id: GuildId, id: u64) -> Member {
+    let mut member = Member::default();
+    member.guild_id = guild_id;
+    member.user = user(id);
+    member
+}
+
+/// `MockDiscord` の Bot のユーザー ID
+pub const BOT_ID: UserId = UserId::new(1);
+
+/// テスト用の, Bot が送ったメッセージ
+pub fn message(channel_id: ChannelId, message_id: MessageId, content: &str) -> Message {
+    let mut message = Message::default();
+    message.id = message_id;
+    message.channel_id = channel_id;
+    message.author = user(BOT_ID.get());
+    message.content = content.to_string();
+    message.timestamp = message_id.created_at();
+    message
+}
+
+/// テスト用の, `user_id` が `emoji` で `message` に付けたリアクションのイベント
+pub fn reaction(message: &Message, guild_id: GuildId, user_id: u64, emoji: &str) -> Reaction {
+    serde_json::from_value(serde_json::json!({
+        "user_id": user_id.to_string(),
+        "channel_id": message.channel_id.to_string(),
+        "message_id": message.id.to_string(),
+        "guild_id": guild_id.to_string(),
+        "emoji": { "id": null, "name": emoji },
+        "message_author_id": message.author.id.to_string(),
+        "burst": false,
+        "type": 0,
+    }))
+    .unwrap()
+}
+
+impl MockDiscord {
+    pub fn new() -> Self {
+        let mock = Self::default();
+        mock.state.lock().unwrap().next_id = 9_000_000_000_000_000;
+        mock
+    }
+
+    /// 受けた呼び出し (古い順)
+    pub fn calls(&self) -> Vec<Call> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// 送ったメッセージの本文 (古い順)
+    pub fn sent_contents(&self) -> Vec<String> {
+        self.calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                Call::SendMessage { content, .. } => Some(content),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn insert_message(&self, message: Message) {
+        self.state
+            .lock()
+            .unwrap()
+            .messages
+            .insert(message.id, message);
+    }
+
+    /// `user_id` が `emoji` でリアクションしたことにします
+    pub fn add_reaction(&self, message_id: MessageId, emoji: &str, user_id: u64) {
+        let mut state = self.state.lock().unwrap();
+        let users = state
+            .reactions
+            .entry((message_id, emoji.to_string()))
+            .or_default();
+        if users.iter().all(|user| user.id.get() != user_id) {
+            users.push(user(user_id));
+        }
+    }
+
+    pub fn insert_member(&self, member: Member) {
+        self.state
+            .lock()
+            .unwrap()
+            .members
+            .insert(member.user.id, member);
+    }
+
+    /// 呼び出し `kind` (`DiscordApi` のメソッド名) が `err` で失敗するようにします
+    pub fn fail(&self, kind: &'static str, err: MockError) {
+        self.fail_after(kind, 0, err);
+    }
+
+    /// 呼び出し `kind` が `successes` 回成功した後, `err` で失敗するようにします
+    pub fn fail_after(&self, kind: &'static str, successes: usize, err: MockError) {
+        self.state
+            .lock()
+            .unwrap()
+            .failures
+            .insert(kind, (successes, err));
+    }
+
+    /// 呼び出しを記録し, 失敗させる呼び出しならエラーを返します
+    async fn record(&self, kind: &'static str, call: Call) -> serenity::Result<()> {
+        let failure = {
+            let mut state = self.state.lock().unwrap();
+            state.calls.push(call);
+            match state.failures.get_mut(kind) {
+                Some((0, err)) => Some(*err),
+                Some((successes, _)) => {
+                    *successes -= 1;
+                    None
+                }
+                None => None,
+            }
+        };
+        match failure {
+            Some(failure) => Err(failure.into_error().await),
+            None => Ok(()),
+        }
+    }
+
+    async fn unknown_message() -> serenity::Error {
+        MockError::UNKNOWN_MESSAGE.into_error().await
+    }
+}
+
+/// 送信または書き換えの内容の本文
+fn content_of(builder: &impl serde::Serialize) -> Option<String> {
+    let value = serde_json::to_value(builder).ok()?;
+    value.get("content")?.as_str().map(str::to_string)
+}
+
+/// 送信する内容の埋め込み
+fn embeds_of(builder: &impl serde::Serialize) -> Vec<SentEmbed> {
+    let text = |value: &serde_json::Value| value.as_str().map(str::to_string);
+    let Ok(value) = serde_json::to_value(builder) else {
+        return Vec::new();
+    };
+    value["embeds"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|embed| SentEmbed {
+            author: text(&embed["author"]["name"]),
+            description: text(&embed["description"]),
+        })
+        .collect()
+}
+
+impl DiscordApi for MockDiscord {
+    fn current_user_id(&self) -> UserId {
+        BOT_ID
+    }
+
+    /// Gateway に接続しないため, キャッシュはありません
+    fn cache(&self) -> Option<&Cache> {
+        None
+    }
+
+    fn serenity_context(&self) -> Option<&Context> {
+        None
+    }
+
+    async fn reaction_users(
+        &self,
+        _channel_id: ChannelId,
+        message_id: MessageId,
+        reaction_type: ReactionType,
+        after: Option<UserId>,
+    ) -> serenity::Result<Vec<User>> {
+        let reaction = reaction_type.to_string();
+        self.record(
+            "reaction_users",
+            Call::ReactionUsers {
+                message_id,
+                reaction: reaction.clone(),
+            },
+        )
+        .await?;
+        let state = self.state.lock().unwrap();
+        let mut users = state
+            .reactions
+            .get(&(message_id, reaction))
+            .cloned()
+            .unwrap_or_default();
+        // Discord と同じく, ユーザー ID 順に返します
+        users.sort_by_key(|user| user.id);
+        Ok(users
+            .into_iter()
+            .filter(|user| after.is_none_or(|after| after < user.id))
+            .take(100)
+            .collect())
+    }
+
+    async fn send_message(
+        &self,
+        channel_id: ChannelId,
+        message: CreateMessage,
+    ) -> serenity::Result<Message> {
+        let content = content_of(&message).unwrap_or_default();
+        self.record(
+            "send_message",
+            Call::SendMessage {
+                channel_id,
+                content: content.clone(),
+                embeds: embeds_of(&message),
+            },
+        )
+        .await?;
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let sent = self::message(channel_id, MessageId::new(state.next_id), &content);
+        state.messages.insert(sent.id, sent.clone());
+        Ok(sent)
+    }
+
+    async fn message(
+        &self,
+        _channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> serenity::Result<Message> {
+        self.record("message", Call::Message { message_id }).await?;
+        let found = self
+            .state
+            .lock()
+            .unwrap()
+            .messages
+            .get(&message_id)
+            .cloned();
+        match found {
+            Some(message) => Ok(message),
+            None => Err(Self::unknown_message().await),
+        }
+    }
+
+    async fn edit_message(
+        &self,
+        _channel_id: ChannelId,
+        message_id: MessageId,
+        message: EditMessage,
+    ) -> serenity::Result<Option<Message>> {
+        let content = content_of(&message);
+        self.record(
+            "edit_message",
+            Call::EditMessage {
+                message_id,
+                content: content.clone(),
+            },
+        )
+        .await?;
+        let edited = {
+            let mut state = self.state.lock().unwrap();
+            state.messages.get_mut(&message_id).map(|edited| {
+                if let Some(content) = content {
+                    edited.content = content;
+                }
+                edited.clone()
+            })
+        };
+        match edited {
+            Some(edited) => Ok(Some(edited)),
+            None => Err(Self::unknown_message().await),
+        }
+    }
+
+    async fn create_reaction(
+        &self,
+        _channel_id: ChannelId,
+        message_id: MessageId,
+        reaction_type: ReactionType,
+    ) -> serenity::Result<()> {
+        let reaction = reaction_type.to_string();
+        self.record(
+            "create_reaction",
+            Call::CreateReaction {
+                message_id,
+                reaction: reaction.clone(),
+            },
+        )
+        .await?;
+        self.add_reaction(message_id, &reaction, BOT_ID.get());
+        Ok(())
+    }
+
+    async fn delete_reaction(
+        &self,
+        _channel_id: ChannelId,
+        message_id: MessageId,
+        user_id: Option<UserId>,
+        reaction_type: ReactionType,
+    ) -> serenity::Result<()> {
+        let reaction = reaction_type.to_string();
+        self.record(
+            "delete_reaction",
+            Call::DeleteReaction {
+                message_id,
+                user_id,
+                reaction: reaction.clone(),
+            },
+        )
+        .await?;
+        let user_id = user_id.unwrap_or(BOT_ID);
+        if let Some(users) = self
+            .state
+            .lock()
+            .unwrap()
+            .reactions
+            .get_mut(&(message_id, reaction))
+        {
+            users.retain(|user| user.id != user_id);
+        }
+        Ok(())
+    }
+
+    async fn delete_reactions(
+        &self,
+        _channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> serenity::Result<()> {
+        self.record("delete_reactions", Call::DeleteReactions { message_id })
+            .await?;
+        self.state
+            .lock()
+            .unwrap()
+            .reactions
+            .retain(|(reacted, _), _| *reacted != message_id);
+        Ok(())
+    }
+
+    async fn delete_message(
+        &self,
+        _channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> serenity::Result<()> {
+        self.record("delete_message", Call::DeleteMessage { message_id })
+            .await?;
+        self.state.lock().unwrap().messages.remove(&message_id);
+        Ok(())
+    }
+
+    async fn direct_message(
+        &self,
+        user_id: UserId,
+        message: CreateMessage,
+    ) -> serenity::Result<Message> {
+        let content = content_of(&message).unwrap_or_default();
+        self.record(
+            "direct_message",
+            Call::DirectMessage {
+                user_id,
+                content: content.clone(),
+            },
+        )
+        .await?;
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        Ok(self::message(
+            ChannelId::new(user_id.get()),
+            MessageId::new(state.next_id),
+            &content,
+        ))
+    }
+
+    async fn unarchive_thread(&self, channel_id: ChannelId) -> serenity::Result<()> {
+        self.record("unarchive_thread", Call::UnarchiveThread { channel_id })
+            .await
+    }
+
+    async fn channel(&self, channel_id: ChannelId) -> serenity::Result<Channel> {
+        self.record("channel", Call::Channel { channel_id }).await?;
+        Err(MockError {
+            status: 404,
+            code: UNKNOWN_CHANNEL,
+        }
+        .into_error()
+        .await)
+    }
+
+    async fn member(&self, _guild_id: GuildId, user_id: UserId) -> serenity::Result<Member> {
+        self.record("member", Call::Member { user_id }).await?;
+        let found = self.state.lock().unwrap().members.get(&user_id).cloned();
+        match found {
+            Some(member) => Ok(member),
+            None => Err(MockError {
+                status: 404,
+                code: UNKNOWN_MEMBER,
+            }
+            .into_error()
+            .await),
+        }
+    }
+
+    async fn guild_members(
+        &self,
+        _guild_id: GuildId,
+        after: Option<UserId>,
+    ) -> serenity::Result<Vec<Member>> {
+        self.record("guild_members", Call::GuildMembers { after })
+            .await?;
+        let mut members: Vec<Member> = self
+            .state
+            .lock()
+            .unwrap()
+            .members
+            .values()
+            .filter(|member| after.is_none_or(|after| after < member.user.id))
+            .cloned()
+            .collect();
+        members.sort_by_key(|member| member.user.id);
+        Ok(members)
+    }
+
+    async fn add_role(
+        &self,
+        _guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+    ) -> serenity::Result<()> {
+        self.record("add_role", Call::AddRole { user_id, role_id })
+            .await
+    }
+
+    async fn create_role(&self, _guild_id: GuildId, role: EditRole<'_>) -> serenity::Result<Role> {
+        let name = serde_json::to_value(&role)
+            .ok()
+            .and_then(|value| value.get("name")?.as_str().map(str::to_string));
+        self.record("create_role", Call::CreateRole { name: name.clone() })
+            .await?;
+        let mut created = Role::default();
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        created.id = RoleId::new(state.next_id);
+        created.name = name.unwrap_or_default();
+        Ok(created)
+    }
+
+    async fn delete_role(&self, _guild_id: GuildId, role_id: RoleId) -> serenity::Result<()> {
+        self.record("delete_role", Call::DeleteRole { role_id })
+            .await
+    }
+}
@@ -0,0 +1,333 @@
+use poise::serenity_prelude::*;
+use tokio::task::JoinSet;
+
+use crate::close_reason::CloseReason;
+use crate::discord_api::DiscordApi;
+use crate::discord_error::unknown_entity;
+use crate::join_menu::truncate_chars;
+use crate::summary::{self, SummaryEntry};
+use crate::{Data, Error, cancel, confirm, send_ephemeral, time_input};
+
+/// 確認に表示する, 終了する募集のゲーム名の件数
+const PREVIEW_TITLES: usize = 5;
+/// 結果に表示する, 終了できなかった募集の件数
+const MAX_LISTED_FAILURES: usize = 10;
+const MAX_REASON_CHARS: usize = 80;
+
+/// まとめて終了する募集の選び方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// チャンネルのすべての受付中の募集
+    Channel(ChannelId),
+    /// `before` より前に投稿された受付中の募集
+    PostedBefore(Timestamp),
+}
+
+impl Selection {
+    pub fn matches(self, message_id: MessageId, channel_id: ChannelId) -> bool {
+        match self {
+            Selection::Channel(target) => channel_id == target,
+            Selection::PostedBefore(before) => {
+                message_id.created_at().unix_timestamp() < before.unix_timestamp()
+            }
+        }
+    }
+}
+
+/// 1 件の募集を終了した結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Closed,
+    /// 確認してから終了するまでに, 開始・キャンセル・削除された
+    AlreadyClosed,
+    Failed(String),
+}
+
+/// まとめて終了した結果
+#[derive(Debug, Default)]
+pub struct BulkReport {
+    pub closed: usize,
+    pub already_closed: usize,
+    /// 終了できなかった募集と, その理由
+    pub failures: Vec<(MessageId, ChannelId, String)>,
+}
+
+impl BulkReport {
+    fn add(&mut self, message_id: MessageId, channel_id: ChannelId, outcome: Outcome) {
+        match outcome {
+            Outcome::Closed => self.closed += 1,
+            Outcome::AlreadyClosed => self.already_closed += 1,
+            Outcome::Failed(reason) => self.failures.push((message_id, channel_id, reason)),
+        }
+    }
+
+    pub fn render(&self, guild_id: GuildId) -> String {
+        let mut lines = vec![format!("{} 件の募集を終了しました", self.closed)];
+        if 0 < self.already_closed {
+            lines.push(format!(
+                "すでに開始・キャンセル・削除されていた募集: {} 件",
+                self.already_closed
+            ));
+        }
+        if !self.failures.is_empty() {
+            lines.push(format!("終了できなかった募集: {} 件", self.failures.len()));
+            let mut failures = self.failures.clone();
+            failures.sort();
+            for (message_id, channel_id, reason) in failures.iter().take(MAX_LISTED_FAILURES) {
+                lines.push(format!(
+                    "- {} {reason}",
+                    message_id.link(*channel_id, Some(guild_id))
+                ));
+            }
+            if MAX_LISTED_FAILURES < failures.len() {
+                lines.push(format!("他{}件", failures.len() - MAX_LISTED_FAILURES));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// 終了する前に表示する確認 (件数と, 古い順に最初の数件のゲーム名)
+pub fn render_confirmation(target: &str, entries: &[SummaryEntry]) -> String {
+    let mut entries: Vec<&SummaryEntry> = entries.iter().collect();
+    entries.sort_by_key(|entry| entry.message_id);
+    let mut lines = vec![format!(
+        "{target}受付中の募集 {} 件を終了します。よろしいですか？",
+        entries.len()
+    )];
+    for entry in entries.iter().take(PREVIEW_TITLES) {
+        lines.push(format!("- **{}**", entry.game_title));
+    }
+    if PREVIEW_TITLES < entries.len() {
+        lines.push(format!("他{}件", entries.len() - PREVIEW_TITLES));
+    }
+    lines.join("\n")
+}
+
+/// 受付中の募集をまとめて終了します
+///
+/// 終了する募集を先に保存ファイルへ保存し, 1 件終えるごとに消すため, 途中で再起動しても
+/// `resume_bulk_closes` で残りだけを終了します。同時に呼び出す REST API は `Data::rest_budget` で抑えます。
+pub async fn close_all<A: DiscordApi + Clone + Send + 'static>(
+    api: &A,
+    data: &Data,
+    targets: Vec<(MessageId, ChannelId)>,
+) -> BulkReport {
+    if let Err(err) = data.storage.add_bulk_closes(&targets) {
+        eprintln!("Failed to save bulk close targets: {err}");
+    }
+    let mut tasks = JoinSet::new();
+    for (message_id, channel_id) in targets {
+        let api = api.clone();
+        let data = data.clone();
+        tasks.spawn(async move {
+            let outcome = data
+                .rest_budget
+                .run(close_one(&api, &data, channel_id, message_id))
+                .await;
+            if let Err(err) = data.storage.remove_bulk_close(message_id) {
+                eprintln!("Failed to remove bulk close target {message_id}: {err}");
+            }
+            (message_id, channel_id, outcome)
+        });
+    }
+    let mut report = BulkReport::default();
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok((message_id, channel_id, outcome)) => {
+                report.add(message_id, channel_id, outcome);
+            }
+            Err(err) => eprintln!("Bulk close task failed: {err}"),
+        }
+    }
+    report
+}
+
+async fn close_one(
+    api: &impl DiscordApi,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Outcome {
+    let result = cancel::cancel_recruit_as(
+        api,
+        data,
+        channel_id,
+        message_id,
+        CloseReason::ModeratorBulk,
+    )
+    .await;
+    match result {
+        Ok(true) => Outcome::Closed,
+        Ok(false) => Outcome::AlreadyClosed,
+        Err(err) if unknown_entity(&err).is_some() => Outcome::AlreadyClosed,
+        Err(err) => {
+            let reason = truncate_chars(&err.to_string().replace('\n', " "), MAX_REASON_CHARS);
+            Outcome::Failed(reason)
+        }
+    }
+}
+
+/// 再起動する前にまとめて終了していた募集の, 残りを終了します
+pub async fn resume_bulk_closes(ctx: &Context, data: &Data) {
+    let targets = data.storage.bulk_closes();
+    if targets.is_empty() {
+        return;
+    }
+    let report = close_all(ctx, data, targets).await;
+    for (message_id, _, reason) in &report.failures {
+        eprintln!("Failed to resume bulk close of {message_id}: {reason}");
+    }
+}
+
+/// 受付中の募集をまとめて終了します (イベントの後片付け用)
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "MANAGE_MESSAGES",
+    subcommands("moderate_close_all", "moderate_close_older_than")
+)]
+pub async fn moderate_recruits(_ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// チャンネルの受付中の募集をすべて終了します
+#[poise::command(slash_command, guild_only, ephemeral, rename = "close_all")]
+async fn moderate_close_all(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集を終了するチャンネル"]
+    #[channel_types("Text")]
+    channel: GuildChannel,
+) -> Result<(), Error> {
+    let target = format!("<#{}> の", channel.id);
+    run(ctx, Selection::Channel(channel.id), &target).await
+}
+
+/// 投稿から指定の時間が過ぎた受付中の募集を終了します
+///
+/// サーバーのすべてのチャンネルの募集が対象です。
+#[poise::command(slash_command, guild_only, ephemeral, rename = "close_older_than")]
+async fn moderate_close_older_than(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "投稿してからの時間 (例: 2d, 12h, 1日)"] duration: String,
+) -> Result<(), Error> {
+    let minutes = match time_input::parse_minutes("duration", &duration, 1) {
+        Ok(minutes) => minutes,
+        Err(err) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+    };
+    let seconds = i64::try_from(minutes.saturating_mul(60)).unwrap_or(i64::MAX);
+    let before =
+        Timestamp::from_unix_timestamp(Timestamp::now().unix_timestamp().saturating_sub(seconds))
+            .map_err(|_| "duration is too long")?;
+    let target = format!("<t:{}:f> より前に投稿された", before.unix_timestamp());
+    run(ctx, Selection::PostedBefore(before), &target).await
+}
+
+async fn run(
+    ctx: poise::Context<'_, Data, Error>,
+    selection: Selection,
+    target: &str,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.defer_ephemeral().await?;
+    let data = ctx.data();
+    let entries = summary::collect_open_entries(
+        ctx.serenity_context(),
+        data,
+        guild_id,
+        |message_id, channel_id| selection.matches(message_id, channel_id),
+    )
+    .await;
+    if entries.is_empty() {
+        send_ephemeral(ctx, &format!("{target}受付中の募集はありません")).await?;
+        return Ok(());
+    }
+    let content = render_confirmation(target, &entries);
+    if !confirm::confirm(ctx, &content, "終了する").await? {
+        return Ok(());
+    }
+    let targets = entries
+        .iter()
+        .map(|entry| (entry.message_id, entry.channel_id))
+        .collect();
+    let report = close_all(ctx.serenity_context(), data, targets).await;
+    if 0 < report.closed {
+        data.summary_board.mark(guild_id);
+    }
+    send_ephemeral(ctx, &report.render(guild_id)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD_ID: GuildId = GuildId::new(1);
+    const CHANNEL_ID: ChannelId = ChannelId::new(2);
+    /// 2023-11-14T22:13:20Z に投稿したメッセージ
+    const MESSAGE_ID: MessageId = MessageId::new(1_174_109_840_998_400_000);
+
+    fn entry(game_title: &str, message_id: u64) -> SummaryEntry {
+        SummaryEntry {
+            channel_id: CHANNEL_ID,
+            message_id: MessageId::new(message_id),
+            game_title: game_title.to_string(),
+            count: None,
+            required_players: 3,
+            start_time: None,
+        }
+    }
+
+    #[test]
+    fn recruits_are_selected_by_channel_or_age() {
+        assert!(Selection::Channel(CHANNEL_ID).matches(MESSAGE_ID, CHANNEL_ID));
+        assert!(!Selection::Channel(CHANNEL_ID).matches(MESSAGE_ID, ChannelId::new(3)));
+        let at = |seconds: i64| Timestamp::from_unix_timestamp(1_700_000_000 + seconds).unwrap();
+        assert!(Selection::PostedBefore(at(1)).matches(MESSAGE_ID, CHANNEL_ID));
+        assert!(!Selection::PostedBefore(at(0)).matches(MESSAGE_ID, CHANNEL_ID));
+    }
+
+    #[test]
+    fn confirmation_previews_the_oldest_titles() {
+        let entries: Vec<SummaryEntry> = (0..PREVIEW_TITLES as u64 + 1)
+            .rev()
+            .map(|index| entry(&format!("Game {index}"), index + 1))
+            .collect();
+        let content = render_confirmation("このチャンネルの", &entries);
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(
+            lines[0],
+            "このチャンネルの受付中の募集 6 件を終了します。よろしいですか？"
+        );
+        assert_eq!(lines[1], "- **Game 0**");
+        assert_eq!(lines.last(), Some(&"他1件"));
+    }
+
+    #[test]
+    fn report_lists_failures_in_order() {
+        let mut report = BulkReport::default();
+        report.add(
+            MessageId::new(4),
+            CHANNEL_ID,
+            Outcome::Failed("b".to_string()),
+        );
+        report.add(
+            MessageId::new(3),
+            CHANNEL_ID,
+            Outcome::Failed("a".to_string()),
+        );
+        report.add(MESSAGE_ID, CHANNEL_ID, Outcome::Closed);
+        report.add(MESSAGE_ID, CHANNEL_ID, Outcome::AlreadyClosed);
+        assert_eq!(
+            report.render(GUILD_ID),
+            "1 件の募集を終了しました\n\
+             すでに開始・キャンセル・削除されていた募集: 1 件\n\
+             終了できなかった募集: 2 件\n\
+             - https://discord.com/channels/1/2/3 a\n\
+             - https://discord.com/channels/1/2/4 b"
+        );
+    }
+}
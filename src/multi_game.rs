@@ -0,0 +1,261 @@
+use std::collections::HashSet;
+
+use poise::serenity_prelude::*;
+
+use crate::config_storage;
+use crate::discord_api::DiscordApi;
+use crate::i18n::{self, Language, Text};
+use crate::notification::{Mentions, NotificationPolicy};
+use crate::recruit_message::{GameOption, RecruitConfig};
+use crate::{
+    Data, Error, allowed_bot_participants, assign_participant_role, fetch_reaction_users,
+    format_progress, is_counted_reactor, participant_age, participant_order, recruit_edit,
+    rejection, send_start_notification,
+};
+
+/// `/recruit create` の `game_title` と `game_title_2` などの組から選択肢を作ります
+///
+/// 2 つ目以降のゲームが指定されていなければ, 1 つのゲームの募集として空の選択肢を返します。
+pub fn build_game_options(
+    first: GameOption,
+    others: [(Option<String>, Option<usize>); 2],
+) -> Result<Vec<GameOption>, String> {
+    let mut options = vec![first];
+    for (index, other) in others.into_iter().enumerate() {
+        let number = index + 2;
+        match other {
+            (None, None) => {}
+            (Some(game_title), Some(required_players)) => {
+                if required_players == 0 {
+                    return Err(format!(
+                        "required_players_{number} は 1 以上を指定してください。"
+                    ));
+                }
+                options.push(GameOption {
+                    game_title,
+                    required_players,
+                });
+            }
+            _ => {
+                return Err(format!(
+                    "game_title_{number} と required_players_{number} は両方指定してください。"
+                ));
+            }
+        }
+    }
+    if options.len() == 1 {
+        return Ok(Vec::new());
+    }
+    Ok(options)
+}
+
+/// 複数のゲームから, 先に人数が揃ったゲームで開始する募集を作成します
+///
+/// ゲームごとに 1️⃣ 2️⃣ 3️⃣ のリアクションで参加し, 最初に人数が揃ったゲームで開始して募集を締め切ります。
+#[allow(clippy::too_many_arguments)]
+#[poise::command(slash_command, guild_only, rename = "multi")]
+pub async fn recruit_multi(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "1つ目のゲーム名"]
+    #[autocomplete = "crate::game_alias::autocomplete_game_title"]
+    game_title: String,
+    #[description = "2つ目のゲーム名"] game_title_2: String,
+    #[description = "2つ目のゲームの開始に必要な人数"] required_players_2: usize,
+    #[description = "1つ目のゲームの開始に必要な人数 (/settings game で登録したゲームは省略できます)"]
+    required_players: Option<usize>,
+    #[description = "3つ目のゲーム名"] game_title_3: Option<String>,
+    #[description = "3つ目のゲームの開始に必要な人数"] required_players_3: Option<usize>,
+    #[description = "開始時にメンションするロール"] mention_role: Option<Role>,
+    #[description = "開始時にメンションする2つ目のロール"] mention_role2: Option<Role>,
+    #[description = "リアクション追加時に参加通知を送るかどうか"] notify_on_reaction: Option<bool>,
+    #[description = "通知メッセージを削除するまでの時間 (例: 30分, 2h)"]
+    delete_after_minutes: Option<String>,
+    #[description = "募集のカテゴリ (カテゴリの既定値を使います)"]
+    #[autocomplete = "crate::category::autocomplete_category"]
+    category: Option<String>,
+    #[description = "募集メッセージと通知の言語 (既定: サーバーの言語)"] language: Option<Language>,
+    #[description = "募集の説明 (ランク帯など, 500 文字まで)"]
+    #[max_length = 500]
+    description: Option<String>,
+) -> Result<(), Error> {
+    crate::create_recruit(
+        ctx,
+        crate::CreateOptions {
+            game_title,
+            required_players,
+            mention_roles: [mention_role, mention_role2, None],
+            notify_on_reaction,
+            delete_after_minutes,
+            other_games: [
+                (Some(game_title_2), Some(required_players_2)),
+                (game_title_3, required_players_3),
+            ],
+            category,
+            language,
+            description,
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// 複数のゲームから選ぶ募集で, 選択肢へのリアクションを処理します
+///
+/// 選択肢ごとに人数を数え, 最初に人数が揃った選択肢のリアクションをしたユーザーだけで開始して募集を締め切ります。
+pub async fn handle_option_reaction(
+    api: &impl DiscordApi,
+    data: &Data,
+    reaction: &Reaction,
+    message_id: MessageId,
+    index: usize,
+) -> Result<(), Error> {
+    let _guard = data.lock_message(message_id).await;
+    // 直前に処理したリアクションで締め切られていないか, 最新の募集メッセージで確認します
+    let message = api.message(reaction.channel_id, message_id).await?;
+    let mut recruit_message = config_storage::load(data, &message)?;
+    if recruit_message.state.started {
+        return Ok(());
+    }
+    i18n::resolve_language(data, &mut recruit_message.config, reaction.guild_id);
+    let config = &recruit_message.config;
+    let Some(option) = config.game_options.get(index) else {
+        return Ok(());
+    };
+    let Some(reaction_type) = config.game_option_reaction_types().get(index).cloned() else {
+        return Ok(());
+    };
+
+    let allowed_bot_participants = allowed_bot_participants(data, reaction.guild_id);
+    let mut user_ids: HashSet<UserId> =
+        fetch_reaction_users(api, &message, reaction_type, &allowed_bot_participants)
+            .await?
+            .into_iter()
+            .collect();
+    participant_age::retain_eligible(api, data, reaction.guild_id, &mut user_ids).await;
+    // REST API の結果に反映される前でも, リアクションしたユーザーを 1 人として数えます
+    if let Some(user_id) = reaction.user_id
+        && reaction
+            .member
+            .as_ref()
+            .is_none_or(|member| is_counted_reactor(&member.user, &allowed_bot_participants))
+    {
+        user_ids.insert(user_id);
+    }
+
+    let policy = NotificationPolicy::new(config);
+    // 作成やサーバーへの参加が新しいアカウントのリアクションは数えず, ロールも付与しません
+    if let (Some(guild_id), Some(user_id)) = (reaction.guild_id, reaction.user_id)
+        && let Some(reason) =
+            participant_age::rejection(api, data, guild_id, user_id, reaction.member.as_ref()).await
+    {
+        return rejection::notify_rejection(api, data, config, &policy, reaction, user_id, reason)
+            .await;
+    }
+    if config.notify_on_reaction
+        && let Some(user_id) = reaction.user_id
+    {
+        let language = config.language();
+        let content = language.text(Text::Joined {
+            user: &user_id.mention().to_string(),
+            game_title: &option.game_title,
+            progress: &format_progress(language, user_ids.len(), option.required_players),
+        });
+        policy
+            .send(
+                api,
+                data,
+                message.channel_id,
+                policy.message(&content, &Mentions::user(user_id)),
+            )
+            .await?;
+    }
+
+    assign_participant_role(api, data, config, &policy, reaction).await?;
+
+    if user_ids.len() < option.required_players {
+        return Ok(());
+    }
+
+    let winner_config = RecruitConfig {
+        game_title: option.game_title.clone(),
+        required_players: option.required_players,
+        ..config.clone()
+    };
+    let edited = recruit_edit::edit_recruit_message(
+        api,
+        data,
+        message.channel_id,
+        message.id,
+        |recruit_message| {
+            if recruit_message.state.started {
+                return None;
+            }
+            recruit_message.state.started = true;
+            recruit_message.state.winner = Some(option.game_title.clone());
+            Some(())
+        },
+    )
+    .await?;
+    if edited.is_none() {
+        return Ok(());
+    }
+    send_start_notification(
+        api,
+        data,
+        &winner_config,
+        &policy,
+        &message,
+        reaction.guild_id,
+        participant_order::ordered(data, message.id, &user_ids),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(game_title: &str, required_players: usize) -> GameOption {
+        GameOption {
+            game_title: game_title.to_string(),
+            required_players,
+        }
+    }
+
+    #[test]
+    fn game_options_need_both_title_and_players() {
+        assert_eq!(
+            build_game_options(option("A", 4), [(None, None), (None, None)]),
+            Ok(Vec::new())
+        );
+        assert_eq!(
+            build_game_options(
+                option("A", 4),
+                [(Some("B".to_string()), Some(2)), (None, None)]
+            ),
+            Ok(vec![option("A", 4), option("B", 2)])
+        );
+        assert_eq!(
+            build_game_options(
+                option("A", 4),
+                [(None, None), (Some("C".to_string()), Some(3))]
+            ),
+            Ok(vec![option("A", 4), option("C", 3)])
+        );
+        assert!(
+            build_game_options(
+                option("A", 4),
+                [(Some("B".to_string()), None), (None, None)]
+            )
+            .is_err()
+        );
+        assert!(build_game_options(option("A", 4), [(None, None), (None, Some(3))]).is_err());
+        assert!(
+            build_game_options(
+                option("A", 4),
+                [(Some("B".to_string()), Some(0)), (None, None)]
+            )
+            .is_err()
+        );
+    }
+}
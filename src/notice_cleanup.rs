@@ -0,0 +1,133 @@
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::deletion_log::{self, DeletionReason};
+use crate::discord_error::is_unknown_entity;
+use crate::i18n::{self, Text};
+use crate::{Data, Error, send_ephemeral, thread};
+
+/// 返信を探す, 通知より後のメッセージの件数
+const REPLY_SCAN_LIMIT: u8 = 100;
+
+/// `delete_after_minutes` が過ぎた通知の片付け方
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, poise::ChoiceParameter,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupMode {
+    /// 通知を削除します
+    #[default]
+    #[name = "削除する"]
+    Delete,
+    /// 返信された通知は本文を短い文に書き換え, 返信されていない通知は削除します
+    #[name = "返信された通知は書き換える"]
+    Stub,
+}
+
+impl CleanupMode {
+    pub fn code(self) -> &'static str {
+        match self {
+            CleanupMode::Delete => "delete",
+            CleanupMode::Stub => "stub",
+        }
+    }
+}
+
+/// 通知より後の直近 `REPLY_SCAN_LIMIT` 件のメッセージに, 通知への返信があるかどうか
+async fn has_reply(ctx: &Context, channel_id: ChannelId, message_id: MessageId) -> bool {
+    let request = GetMessages::new().after(message_id).limit(REPLY_SCAN_LIMIT);
+    match channel_id.messages(ctx, request).await {
+        Ok(messages) => messages.iter().any(|message| {
+            message
+                .message_reference
+                .as_ref()
+                .is_some_and(|reference| reference.message_id == Some(message_id))
+        }),
+        Err(err) => {
+            eprintln!("Failed to fetch replies of {message_id}: {err}");
+            false
+        }
+    }
+}
+
+/// 予約した通知を片付けます
+///
+/// サーバー設定 `cleanup_mode` が `stub` で通知が返信されている場合は, 返信の参照先が残るよう
+/// 本文だけを書き換えます。それ以外の場合は削除し, 削除の記録を残します。
+/// すでに削除されていた通知 (ほかの Bot がまとめて削除した場合など) は片付け済みとします。
+pub async fn clean_up(
+    ctx: &Context,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    content: Option<String>,
+) {
+    let guild_id = match channel_id.to_channel(ctx).await {
+        Ok(channel) => channel.guild().map(|channel| channel.guild_id),
+        Err(_) => None,
+    };
+    let stubs = guild_id.is_some_and(|guild_id| {
+        data.storage.guild_settings(guild_id).cleanup_mode == CleanupMode::Stub
+    });
+    if stubs && has_reply(ctx, channel_id, message_id).await {
+        let stub = i18n::guild_language(data, guild_id).text(Text::CleanedUpNotice);
+        let edited = thread::retry_if_archived(ctx, channel_id, || {
+            channel_id.edit_message(
+                &ctx.http,
+                message_id,
+                EditMessage::new()
+                    .content(&stub)
+                    .embeds(Vec::new())
+                    .components(Vec::new()),
+            )
+        })
+        .await;
+        match edited {
+            Ok(_) => return,
+            Err(err) if is_unknown_entity(&err).is_some() => return,
+            // 書き換えられない場合は, 予約どおり削除します
+            Err(err) => eprintln!("Failed to replace notice {message_id} with a stub: {err}"),
+        }
+    }
+    let deleted = thread::retry_if_archived(ctx, channel_id, || {
+        channel_id.delete_message(&ctx.http, message_id)
+    })
+    .await;
+    match deleted {
+        Ok(Some(())) => {
+            deletion_log::record(
+                ctx,
+                data,
+                channel_id,
+                message_id,
+                content,
+                DeletionReason::Notice,
+            )
+            .await;
+        }
+        // アーカイブを解除できなかった場合と, すでに削除されていた場合
+        Ok(None) => {}
+        Err(err) if is_unknown_entity(&err).is_some() => {}
+        Err(err) => eprintln!("Failed to delete notice {message_id}: {err}"),
+    }
+}
+
+/// 自動削除する通知が返信されていた場合の扱いを設定します
+#[poise::command(slash_command, guild_only, rename = "cleanup_mode")]
+pub async fn settings_cleanup_mode(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "返信された通知を削除するか書き換えるか"] mode: CleanupMode,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| settings.cleanup_mode = mode)?;
+    let content = match mode {
+        CleanupMode::Delete => "自動削除する通知は、返信されていても削除します",
+        CleanupMode::Stub => {
+            "自動削除する通知が返信されていた場合は、削除せずに本文を書き換えて返信先を残します"
+        }
+    };
+    send_ephemeral(ctx, content).await?;
+    Ok(())
+}
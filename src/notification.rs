@@ -0,0 +1,632 @@
+use poise::serenity_prelude::{self as serenity, *};
+
+use tokio::task::JoinSet;
+
+use crate::discord_api::DiscordApi;
+use crate::i18n::{Language, Text};
+use crate::recruit_message::RecruitConfig;
+use crate::{Data, Error, schedule_delete_message};
+
+/// シミュレーションの通知メッセージを削除するまでの分数
+const SIMULATION_DELETE_AFTER_MINUTES: u64 = 5;
+const SIMULATION_PREFIX: &str = "【テスト】";
+/// 募集のチャンネルに送ったエラーを削除するまでの分数
+const ERROR_DELETE_AFTER_MINUTES: u64 = 10;
+
+/// `allowed_mentions` に挙げられるユーザーとロールのそれぞれの最大数
+const MAX_ALLOWED_MENTIONS: usize = 100;
+
+/// 同時に送信する DM の最大数
+const MAX_CONCURRENT_DMS: usize = 4;
+
+/// 募集に関する通知の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// ✋ の参加通知
+    Participation,
+    /// 開始通知
+    Start,
+    /// カウントダウンや点呼などの, 募集に関するその他のお知らせ
+    Notice,
+    /// 募集設定を読み取れなかった場合などの, リアクションしたユーザーへのエラー
+    Error,
+}
+
+/// 開始通知の埋め込みの見出しに表示する主催者
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrganizerAuthor {
+    pub name: String,
+    pub icon_url: String,
+}
+
+impl OrganizerAuthor {
+    /// サーバーのメンバーとしての主催者の表示名とアバター (サーバーを抜けていれば `None`)
+    pub async fn fetch(api: &impl DiscordApi, guild_id: GuildId, user_id: UserId) -> Option<Self> {
+        let member = api.member(guild_id, user_id).await.ok()?;
+        Some(Self {
+            name: member.display_name().to_string(),
+            icon_url: member.face(),
+        })
+    }
+}
+
+/// 主催者を見出しにした開始通知の埋め込み
+///
+/// 埋め込みの中のメンションは通知されないため, メンションは本文に残し, 説明文だけを埋め込みに入れます。
+pub fn organizer_embed(author: &OrganizerAuthor, description: &str) -> CreateEmbed {
+    CreateEmbed::new()
+        .author(CreateEmbedAuthor::new(&author.name).icon_url(&author.icon_url))
+        .description(description)
+}
+
+/// 通知でメンションを送る相手
+///
+/// 本文にはゲーム名や説明文など, 募集を作ったメンバーが書いた文字列が入り, `<@&ロール>` や `@everyone` が紛れ込むことがあります。
+/// 本文の中のメンションからは送る相手を決めず, ここに挙げたユーザーとロールだけにメンションを送ります。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mentions {
+    pub users: Vec<UserId>,
+    pub roles: Vec<RoleId>,
+}
+
+impl Mentions {
+    /// 誰にもメンションを送りません
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// `user_id` だけにメンションを送ります
+    pub fn user(user_id: UserId) -> Self {
+        Self::users([user_id])
+    }
+
+    pub fn users(users: impl IntoIterator<Item = UserId>) -> Self {
+        Self {
+            users: users.into_iter().collect(),
+            roles: Vec::new(),
+        }
+    }
+
+    /// `roles` にもメンションを送ります
+    pub fn with_roles(self, roles: impl IntoIterator<Item = RoleId>) -> Self {
+        Self {
+            roles: roles.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// `@everyone`, `@here` と, 挙げていないユーザーやロールへのメンションを送らない設定
+    ///
+    /// Discord が受け付けるのはそれぞれ `MAX_ALLOWED_MENTIONS` 件までのため, それより後の相手には送りません。
+    pub fn allowed_mentions(&self) -> CreateAllowedMentions {
+        CreateAllowedMentions::new()
+            .everyone(false)
+            .users(self.users.iter().copied().take(MAX_ALLOWED_MENTIONS))
+            .roles(self.roles.iter().copied().take(MAX_ALLOWED_MENTIONS))
+    }
+}
+
+/// 誰にもメンションを送らないメッセージ (監査ログやお知らせなど, 通知の送り方によらないメッセージ)
+pub fn plain_message(content: impl Into<String>) -> CreateMessage {
+    CreateMessage::new()
+        .content(content)
+        .allowed_mentions(Mentions::none().allowed_mentions())
+}
+
+/// 通知の種類ごとの送り方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delivery {
+    /// 送信から削除するまでの分数 (`None` なら削除しません)
+    pub delete_after_minutes: Option<u64>,
+    /// 「【テスト】」を付け, メンションせずに送るかどうか
+    pub simulation: bool,
+}
+
+/// 募集に関する通知メッセージの送り方
+///
+/// シミュレーションの募集では同じ内容の通知をメンションなしで送り, 短時間で削除します。
+/// サイレント時間帯には, エラー以外の通知をメンションなしで送ります。
+#[derive(Debug, Clone, Copy)]
+pub struct NotificationPolicy {
+    pub simulation: bool,
+    pub delete_after_minutes: u64,
+    /// サイレント時間帯なら, メンションしないことを添える言語
+    pub quiet: Option<Language>,
+}
+
+impl NotificationPolicy {
+    pub fn new(config: &RecruitConfig) -> Self {
+        if config.simulation {
+            Self {
+                simulation: true,
+                delete_after_minutes: SIMULATION_DELETE_AFTER_MINUTES,
+                quiet: None,
+            }
+        } else {
+            Self {
+                simulation: false,
+                delete_after_minutes: config.delete_after_minutes,
+                quiet: None,
+            }
+        }
+    }
+
+    /// 募集設定を読み取れなかった場合の, エラーを送るための送り方
+    pub fn unconfigured() -> Self {
+        Self {
+            simulation: false,
+            delete_after_minutes: 0,
+            quiet: None,
+        }
+    }
+
+    /// サイレント時間帯なら, メンションせずに送る送り方にします
+    pub fn with_quiet_hours(self, quiet: Option<Language>) -> Self {
+        Self { quiet, ..self }
+    }
+
+    /// サイレント時間帯のためにメンションしないかどうか
+    pub fn is_quiet(&self) -> bool {
+        !self.simulation && self.quiet.is_some()
+    }
+
+    /// 通知の種類ごとの送り方を決めます
+    ///
+    /// エラーはリアクションしたユーザーが読めるよう, シミュレーションの募集でも「【テスト】」を付けずに送ります。
+    pub fn delivery(&self, kind: NotificationKind) -> Delivery {
+        match kind {
+            NotificationKind::Participation
+            | NotificationKind::Start
+            | NotificationKind::Notice => Delivery {
+                delete_after_minutes: Some(self.delete_after_minutes),
+                simulation: self.simulation,
+            },
+            NotificationKind::Error => Delivery {
+                delete_after_minutes: Some(ERROR_DELETE_AFTER_MINUTES),
+                simulation: false,
+            },
+        }
+    }
+
+    /// ロールの付与や記録など, 通知以外の副作用を伴う処理を行うかどうか
+    pub fn has_side_effects(&self) -> bool {
+        !self.simulation
+    }
+
+    /// 実際にメンションを送る相手
+    ///
+    /// シミュレーションの募集とサイレント時間帯 (エラーを除く) には, 誰にもメンションを送りません。
+    pub fn effective_mentions(&self, kind: NotificationKind, mentions: &Mentions) -> Mentions {
+        if self.delivery(kind).simulation
+            || (self.quiet.is_some() && kind != NotificationKind::Error)
+        {
+            Mentions::none()
+        } else {
+            mentions.clone()
+        }
+    }
+
+    /// お知らせ (`NotificationKind::Notice`) を作ります
+    pub fn message(&self, content: &str, mentions: &Mentions) -> CreateMessage {
+        self.create_message(NotificationKind::Notice, content, mentions)
+    }
+
+    /// `mentions` だけにメンションを送る通知を作ります
+    pub fn create_message(
+        &self,
+        kind: NotificationKind,
+        content: &str,
+        mentions: &Mentions,
+    ) -> CreateMessage {
+        let content = if self.delivery(kind).simulation {
+            format!("{SIMULATION_PREFIX}{content}")
+        } else if let Some(language) = self.quiet
+            && kind != NotificationKind::Error
+        {
+            format!("{content}\n{}", language.text(Text::QuietHoursNote))
+        } else {
+            content.to_string()
+        };
+        CreateMessage::new()
+            .content(content)
+            .allowed_mentions(self.effective_mentions(kind, mentions).allowed_mentions())
+    }
+
+    /// `kind` の送り方で通知を送信し, 削除する種類なら削除を予約します
+    pub async fn notify(
+        &self,
+        api: &impl DiscordApi,
+        data: &Data,
+        kind: NotificationKind,
+        channel_id: ChannelId,
+        content: &str,
+        mentions: &Mentions,
+    ) -> Result<Message, serenity::Error> {
+        let message = api
+            .send_message(channel_id, self.create_message(kind, content, mentions))
+            .await?;
+        if let Some(minutes) = self.delivery(kind).delete_after_minutes {
+            schedule_delete_message(data, &message, minutes);
+        }
+        Ok(message)
+    }
+
+    /// `message` で送った通知メッセージの内容を書き換えます
+    ///
+    /// 書き換えてもメンションは送られないため, 誰にもメンションを送らない設定にします。
+    pub fn edit(&self, content: &str) -> EditMessage {
+        let content = if self.simulation {
+            format!("{SIMULATION_PREFIX}{content}")
+        } else {
+            content.to_string()
+        };
+        EditMessage::new()
+            .content(content)
+            .allowed_mentions(Mentions::none().allowed_mentions())
+    }
+
+    /// 通知メッセージを送信し, `delete_after_minutes` 分後に削除します
+    pub async fn send(
+        &self,
+        api: &impl DiscordApi,
+        data: &Data,
+        channel_id: ChannelId,
+        message: CreateMessage,
+    ) -> Result<Message, serenity::Error> {
+        let message = api.send_message(channel_id, message).await?;
+        self.schedule_delete(data, &message);
+        Ok(message)
+    }
+
+    /// `link` (募集メッセージへのリンク) を付けて DM で通知し, DM を送れない場合はメンション付きでチャンネルに送ります
+    pub async fn send_dm_or_channel(
+        &self,
+        api: &impl DiscordApi,
+        data: &Data,
+        user_id: UserId,
+        channel_id: ChannelId,
+        content: &str,
+        link: &str,
+    ) -> Result<(), Error> {
+        let dm_content = format!("{content}\n{link}");
+        match api
+            .direct_message(user_id, self.message(&dm_content, &Mentions::none()))
+            .await
+        {
+            Ok(_) => return Ok(()),
+            Err(err) => eprintln!("Failed to send notice by DM: {err}"),
+        }
+        self.send_channel_mention(api, data, user_id, channel_id, content)
+            .await
+    }
+
+    pub async fn send_channel_mention(
+        &self,
+        api: &impl DiscordApi,
+        data: &Data,
+        user_id: UserId,
+        channel_id: ChannelId,
+        content: &str,
+    ) -> Result<(), Error> {
+        let content = format!("{} {content}", user_id.mention());
+        self.send(
+            api,
+            data,
+            channel_id,
+            self.message(&content, &Mentions::user(user_id)),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub fn schedule_delete(&self, data: &Data, message: &Message) {
+        schedule_delete_message(data, message, self.delete_after_minutes);
+    }
+}
+
+/// リアクションしたユーザーへのエラーの送り方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorRoute {
+    pub channel_id: ChannelId,
+    /// ユーザーをメンションするかどうか (しない場合は表示名で呼びかけます)
+    pub mention_user: bool,
+    /// 送信から削除するまでの分数 (`None` なら削除しません)
+    pub delete_after_minutes: Option<u64>,
+    /// 管理者向けのチャンネルに送るかどうか (送る場合は誰のどの募集のエラーかを添えます)
+    pub staff: bool,
+}
+
+/// サーバー設定 `error_channel` と `error_ping_user` から, エラーの送り方を決めます
+///
+/// `error_channel` があれば募集のチャンネルには送らず, 管理者が確認できるよう削除せずに残します。
+/// 募集のチャンネルに送るエラーは `ERROR_DELETE_AFTER_MINUTES` 分後に削除し,
+/// `error_ping_user` のときだけメンションします。
+pub fn route_error(
+    recruit_channel: ChannelId,
+    error_channel: Option<ChannelId>,
+    error_ping_user: bool,
+) -> ErrorRoute {
+    match error_channel {
+        Some(channel_id) => ErrorRoute {
+            channel_id,
+            mention_user: false,
+            delete_after_minutes: None,
+            staff: true,
+        },
+        None => ErrorRoute {
+            channel_id: recruit_channel,
+            mention_user: error_ping_user,
+            delete_after_minutes: NotificationPolicy::unconfigured()
+                .delivery(NotificationKind::Error)
+                .delete_after_minutes,
+            staff: false,
+        },
+    }
+}
+
+/// エラーの宛先のユーザーの表示名 (わからなければメンションの文字列)
+async fn reactor_name(api: &impl DiscordApi, reaction: &Reaction, user_id: UserId) -> String {
+    if let Some(member) = &reaction.member {
+        return member.display_name().to_string();
+    }
+    if let Some(guild_id) = reaction.guild_id
+        && let Ok(member) = api.member(guild_id, user_id).await
+    {
+        return member.display_name().to_string();
+    }
+    user_id.mention().to_string()
+}
+
+/// リアクションしたユーザーへのエラーを, サーバー設定に従った送り先と宛名で送ります
+pub async fn notify_error(
+    api: &impl DiscordApi,
+    data: &Data,
+    reaction: &Reaction,
+    language: Language,
+    error: &str,
+) -> Result<Message, serenity::Error> {
+    let settings = reaction
+        .guild_id
+        .map(|guild_id| data.storage.guild_settings(guild_id))
+        .unwrap_or_default();
+    let route = route_error(
+        reaction.channel_id,
+        settings.error_channel,
+        settings.error_ping_user,
+    );
+    let mentions = match reaction.user_id {
+        Some(user_id) if route.mention_user => Mentions::user(user_id),
+        _ => Mentions::none(),
+    };
+    let addressee = match reaction.user_id {
+        Some(user_id) if route.mention_user => Some(user_id.mention().to_string()),
+        Some(user_id) => Some(reactor_name(api, reaction, user_id).await),
+        None => None,
+    };
+    let content = if route.staff {
+        let link = reaction
+            .message_id
+            .link(reaction.channel_id, reaction.guild_id);
+        language.text(Text::ErrorReport {
+            user: addressee.as_deref().unwrap_or("-"),
+            link: &link,
+            error,
+        })
+    } else {
+        match addressee {
+            Some(addressee) => format!("{addressee} {error}"),
+            None => error.to_string(),
+        }
+    };
+    let message = NotificationPolicy::unconfigured().create_message(
+        NotificationKind::Error,
+        &content,
+        &mentions,
+    );
+    let message = api.send_message(route.channel_id, message).await?;
+    if let Some(minutes) = route.delete_after_minutes {
+        schedule_delete_message(data, &message, minutes);
+    }
+    Ok(message)
+}
+
+/// 複数のユーザーに同じ内容の DM を送り, 送れなかったユーザーを返します
+///
+/// API に負荷をかけないよう, 同時に送信する DM は `MAX_CONCURRENT_DMS` 件までにし,
+/// バックグラウンドの処理の REST API の上限 (`Data::rest_budget`) の中で送ります。
+pub async fn send_dms(
+    ctx: &Context,
+    data: &Data,
+    user_ids: &[UserId],
+    content: &str,
+) -> Vec<UserId> {
+    let mut failed = Vec::new();
+    let mut pending = user_ids.iter().copied();
+    let mut tasks = JoinSet::new();
+    loop {
+        while tasks.len() < MAX_CONCURRENT_DMS
+            && let Some(user_id) = pending.next()
+        {
+            let http = ctx.http.clone();
+            let budget = data.rest_budget.clone();
+            let message = plain_message(content);
+            tasks.spawn(async move {
+                let result = budget.run(user_id.direct_message(&http, message)).await;
+                (user_id, result)
+            });
+        }
+        let Some(result) = tasks.join_next().await else {
+            break;
+        };
+        match result {
+            Ok((_, Ok(_))) => {}
+            Ok((user_id, Err(err))) => {
+                eprintln!("Failed to send DM to {user_id}: {err}");
+                failed.push(user_id);
+            }
+            Err(err) => eprintln!("Failed to join DM task: {err}"),
+        }
+    }
+    failed.sort();
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    /// 送信するメッセージの JSON
+    fn body(message: CreateMessage) -> Value {
+        serde_json::to_value(message).unwrap()
+    }
+
+    #[test]
+    fn simulation_is_labelled_and_never_mentions() {
+        let mut config = RecruitConfig::for_test("Apex", 2);
+        config.simulation = true;
+        let policy = NotificationPolicy::new(&config);
+        assert!(!policy.has_side_effects());
+        assert_eq!(
+            policy.delivery(NotificationKind::Start),
+            Delivery {
+                delete_after_minutes: Some(SIMULATION_DELETE_AFTER_MINUTES),
+                simulation: true,
+            }
+        );
+
+        let mentions = Mentions::user(UserId::new(1)).with_roles([RoleId::new(2)]);
+        let message = body(policy.create_message(NotificationKind::Start, "開始", &mentions));
+        assert_eq!(message["content"], "【テスト】開始");
+        assert_eq!(message["allowed_mentions"]["users"], json!([]));
+        assert_eq!(message["allowed_mentions"]["roles"], json!([]));
+
+        let policy = NotificationPolicy::new(&RecruitConfig::for_test("Apex", 2));
+        assert!(policy.has_side_effects());
+        let message = body(policy.create_message(NotificationKind::Start, "開始", &mentions));
+        assert_eq!(message["content"], "開始");
+        assert_eq!(message["allowed_mentions"]["users"], json!(["1"]));
+    }
+
+    #[test]
+    fn errors_are_deleted_sooner_and_never_simulated() {
+        let mut config = RecruitConfig::for_test("Apex", 2);
+        config.delete_after_minutes = 30;
+        let policy = NotificationPolicy::new(&config);
+        for kind in [
+            NotificationKind::Participation,
+            NotificationKind::Start,
+            NotificationKind::Notice,
+        ] {
+            assert_eq!(policy.delivery(kind).delete_after_minutes, Some(30));
+        }
+        assert_eq!(
+            policy
+                .delivery(NotificationKind::Error)
+                .delete_after_minutes,
+            Some(ERROR_DELETE_AFTER_MINUTES)
+        );
+
+        config.simulation = true;
+        let policy = NotificationPolicy::new(&config);
+        assert_eq!(
+            policy.delivery(NotificationKind::Error),
+            Delivery {
+                delete_after_minutes: Some(ERROR_DELETE_AFTER_MINUTES),
+                simulation: false,
+            }
+        );
+        let message = body(policy.create_message(
+            NotificationKind::Error,
+            "失敗しました",
+            &Mentions::user(UserId::new(1)),
+        ));
+        assert_eq!(message["content"], "失敗しました");
+        assert_eq!(message["allowed_mentions"]["users"], json!(["1"]));
+    }
+
+    #[test]
+    fn only_listed_users_and_roles_are_mentioned() {
+        let message = body(plain_message("@everyone <@&2>"));
+        assert_eq!(message["allowed_mentions"]["parse"], json!([]));
+        assert_eq!(message["allowed_mentions"]["users"], json!([]));
+        assert_eq!(message["allowed_mentions"]["roles"], json!([]));
+
+        // Discord が受け付ける件数を超えた相手には送りません
+        let mentions = Mentions::users((1..=MAX_ALLOWED_MENTIONS as u64 + 1).map(UserId::new));
+        let allowed = serde_json::to_value(mentions.allowed_mentions()).unwrap();
+        assert_eq!(
+            allowed["users"].as_array().unwrap().len(),
+            MAX_ALLOWED_MENTIONS
+        );
+    }
+
+    #[test]
+    fn quiet_hours_mention_no_one_except_errors() {
+        let policy = NotificationPolicy::new(&RecruitConfig::for_test("Apex", 2))
+            .with_quiet_hours(Some(Language::Ja));
+        assert!(policy.is_quiet());
+        let mentions = Mentions::user(UserId::new(1));
+        assert_eq!(
+            policy.effective_mentions(NotificationKind::Start, &mentions),
+            Mentions::none()
+        );
+        assert_eq!(
+            policy.effective_mentions(NotificationKind::Error, &mentions),
+            mentions
+        );
+        let message = body(policy.create_message(NotificationKind::Start, "開始", &mentions));
+        assert_eq!(
+            message["content"],
+            format!("開始\n{}", Language::Ja.text(Text::QuietHoursNote))
+        );
+        assert_eq!(message["allowed_mentions"]["users"], json!([]));
+    }
+
+    #[test]
+    fn errors_go_to_the_error_channel_without_mentions() {
+        let recruit_channel = ChannelId::new(1);
+        let error_channel = ChannelId::new(2);
+        // 管理者向けのチャンネルでは, error_ping_user にかかわらずメンションせずに残します
+        for error_ping_user in [false, true] {
+            assert_eq!(
+                route_error(recruit_channel, Some(error_channel), error_ping_user),
+                ErrorRoute {
+                    channel_id: error_channel,
+                    mention_user: false,
+                    delete_after_minutes: None,
+                    staff: true,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn errors_in_the_recruit_channel_are_deleted_and_ping_only_when_asked() {
+        let recruit_channel = ChannelId::new(1);
+        for error_ping_user in [false, true] {
+            assert_eq!(
+                route_error(recruit_channel, None, error_ping_user),
+                ErrorRoute {
+                    channel_id: recruit_channel,
+                    mention_user: error_ping_user,
+                    delete_after_minutes: Some(ERROR_DELETE_AFTER_MINUTES),
+                    staff: false,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn organizer_embed_has_the_organizer_as_author() {
+        let author = OrganizerAuthor {
+            name: "主催者".to_string(),
+            icon_url: "https://cdn.discordapp.com/embed/avatars/0.png".to_string(),
+        };
+        let embed = serde_json::to_value(organizer_embed(&author, "Apex を開始します")).unwrap();
+        assert_eq!(embed["author"]["name"], "主催者");
+        assert_eq!(embed["author"]["icon_url"], author.icon_url);
+        assert_eq!(embed["description"], "Apex を開始します");
+    }
+}
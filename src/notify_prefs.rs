@@ -0,0 +1,207 @@
+use std::collections::{HashMap, HashSet};
+
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{Data, Error, send_ephemeral};
+
+/// `/notify_me` で保存した, サーバーごとのユーザーの通知の設定
+///
+/// 未設定の項目は, 参加したリアクションで決めます。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotifyPrefs {
+    /// 参加したことを参加通知で知らせるかどうか
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub join_announce: Option<bool>,
+    /// 開始通知でメンションするかどうか
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_ping: Option<bool>,
+}
+
+/// 参加したユーザーへの通知の扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolvedPrefs {
+    pub join_announce: bool,
+    pub start_ping: bool,
+}
+
+/// リアクションと保存した設定から, 通知の扱いを決めます
+///
+/// `silent` は 🤚 で参加したかどうかです。保存した設定がある項目はリアクションより優先します。
+pub fn resolve(silent: bool, stored: NotifyPrefs) -> ResolvedPrefs {
+    ResolvedPrefs {
+        join_announce: stored.join_announce.unwrap_or(!silent),
+        start_ping: stored.start_ping.unwrap_or(true),
+    }
+}
+
+/// 開始通知でメンションを送るユーザー (`start_ping = false` のユーザーを除きます)
+pub fn start_pinged_users(
+    data: &Data,
+    guild_id: Option<GuildId>,
+    user_ids: &[UserId],
+) -> Vec<UserId> {
+    let Some(guild_id) = guild_id else {
+        return user_ids.to_vec();
+    };
+    user_ids
+        .iter()
+        .copied()
+        .filter(|&user_id| resolve(false, data.storage.notify_prefs(guild_id, user_id)).start_ping)
+        .collect()
+}
+
+/// 開始通知での参加者の表示
+///
+/// メンションを送るユーザーは `<@ユーザー>` で, それ以外のユーザーはメンションのように `@表示名` で表示します。
+/// 表示名が分からないユーザーは `<@ユーザー>` のまま表示します (メンションは送りません)。
+#[derive(Debug, Clone, Default)]
+pub struct StartLabels {
+    pinged: HashSet<UserId>,
+    names: HashMap<UserId, String>,
+}
+
+impl StartLabels {
+    /// `pinged` はメンションを送るユーザー, `names` はそれ以外のユーザーの表示名です
+    pub fn new(pinged: &[UserId], names: HashMap<UserId, String>) -> Self {
+        StartLabels {
+            pinged: pinged.iter().copied().collect(),
+            names,
+        }
+    }
+
+    pub fn label(&self, user_id: UserId) -> String {
+        match self.names.get(&user_id) {
+            Some(name) if !self.pinged.contains(&user_id) => plain_name(name),
+            _ => user_id.mention().to_string(),
+        }
+    }
+
+    /// ユーザーを空白で区切って表示します
+    pub fn join(&self, user_ids: &[UserId]) -> String {
+        user_ids
+            .iter()
+            .map(|&user_id| self.label(user_id))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// 表示名を `@表示名` で表示します (Markdown やメンションとして解釈されないようにします)
+pub fn plain_name(name: &str) -> String {
+    let mut plain = String::from("@");
+    for c in name.chars() {
+        if matches!(
+            c,
+            '\\' | '*' | '_' | '~' | '`' | '|' | '<' | '>' | '[' | ']' | '#'
+        ) {
+            plain.push('\\');
+        }
+        plain.push(c);
+    }
+    plain
+}
+
+fn describe(value: Option<bool>, default: &str) -> String {
+    match value {
+        Some(true) => "する".to_string(),
+        Some(false) => "しない".to_string(),
+        None => format!("未設定 ({default})"),
+    }
+}
+
+/// このサーバーでの自分への通知を設定します (未指定で表示)
+///
+/// 設定した項目は, 参加したリアクション (✋ / 🤚) より優先します。
+#[poise::command(slash_command, guild_only, ephemeral, rename = "notify_me")]
+pub async fn notify_me(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "参加したときに参加通知で知らせるか"] join_announce: Option<bool>,
+    #[description = "開始通知でメンションするか"] start_ping: Option<bool>,
+    #[description = "設定を消してリアクションで決めるようにする"] reset: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let user_id = ctx.author().id;
+    let storage = &ctx.data().storage;
+    let mut prefs = if reset == Some(true) {
+        NotifyPrefs::default()
+    } else {
+        storage.notify_prefs(guild_id, user_id)
+    };
+    if join_announce.is_some() {
+        prefs.join_announce = join_announce;
+    }
+    if start_ping.is_some() {
+        prefs.start_ping = start_ping;
+    }
+    storage.set_notify_prefs(guild_id, user_id, prefs)?;
+    let content = [
+        format!(
+            "参加通知: {}",
+            describe(prefs.join_announce, "✋ なら知らせ、🤚 なら知らせない")
+        ),
+        format!(
+            "開始通知のメンション: {}",
+            describe(prefs.start_ping, "メンションする")
+        ),
+    ]
+    .join("\n");
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reaction_decides_unset_prefs() {
+        let prefs = resolve(false, NotifyPrefs::default());
+        assert!(prefs.join_announce && prefs.start_ping);
+        let prefs = resolve(true, NotifyPrefs::default());
+        assert!(!prefs.join_announce && prefs.start_ping);
+    }
+
+    #[test]
+    fn stored_prefs_win_over_reaction() {
+        let stored = NotifyPrefs {
+            join_announce: Some(true),
+            start_ping: Some(false),
+        };
+        for silent in [false, true] {
+            let prefs = resolve(silent, stored);
+            assert!(prefs.join_announce);
+            assert!(!prefs.start_ping);
+        }
+        let stored = NotifyPrefs {
+            join_announce: Some(false),
+            start_ping: None,
+        };
+        assert_eq!(
+            resolve(false, stored),
+            ResolvedPrefs {
+                join_announce: false,
+                start_ping: true,
+            }
+        );
+    }
+
+    #[test]
+    fn unpinged_users_are_rendered_as_names() {
+        let (pinged, quiet, unknown) = (UserId::new(1), UserId::new(2), UserId::new(3));
+        let names = HashMap::from([(pinged, "Alice".to_string()), (quiet, "Bob".to_string())]);
+        let labels = StartLabels::new(&[pinged], names);
+        assert_eq!(labels.label(pinged), "<@1>");
+        assert_eq!(labels.label(quiet), "@Bob");
+        // 表示名が分からなければメンションの形のまま表示します
+        assert_eq!(labels.label(unknown), "<@3>");
+        assert_eq!(labels.join(&[pinged, quiet, unknown]), "<@1> @Bob <@3>");
+    }
+
+    #[test]
+    fn plain_name_escapes_markdown() {
+        assert_eq!(plain_name("たろう"), "@たろう");
+        assert_eq!(plain_name("**bold**_x_"), "@\\*\\*bold\\*\\*\\_x\\_");
+        assert_eq!(plain_name("<@123>"), "@\\<@123\\>");
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::HashSet;
+use std::time::Instant;
+
+use poise::serenity_prelude::*;
+
+use crate::discord_api::DiscordApi;
+use crate::discord_error::is_unknown_member;
+use crate::member_work::{self, MemberBatch, MemberStrategy};
+use crate::rejection::RejectionReason;
+use crate::storage::GuildSettings;
+use crate::{Data, Error, send_ephemeral};
+
+/// Discord の ID (snowflake) の時刻の基準 (2015-01-01T00:00:00Z, Unix 時間のミリ秒)
+const DISCORD_EPOCH_MILLIS: u64 = 1_420_070_400_000;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Discord の ID (snowflake) が作られた日時 (Unix 時間の秒)
+///
+/// ユーザーの ID ならアカウントを作成した日時です。
+pub fn snowflake_timestamp(id: u64) -> i64 {
+    (((id >> 22) + DISCORD_EPOCH_MILLIS) / 1000) as i64
+}
+
+/// メンバー確認 (オンボーディング) を有効にしたサーバーの `features`
+const MEMBER_VERIFICATION_GATE: &str = "MEMBER_VERIFICATION_GATE_ENABLED";
+
+/// 参加者として数えるアカウントの条件 (アカウントとサーバーへの参加の新しさ, メンバー確認, スパムの疑い)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct EligibilityPolicy {
+    pub min_account_age_days: Option<u64>,
+    pub min_membership_age_days: Option<u64>,
+    /// メンバー確認を済ませていないメンバーを数えないかどうか (メンバー確認を有効にしたサーバーのみ)
+    pub skip_pending: bool,
+    /// Discord がスパムの疑いがあるとしたユーザーを数えないかどうか (サーバー設定 `skip_spammers`)
+    pub skip_spammers: bool,
+}
+
+/// 条件の判定に使うメンバーとユーザーの情報
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemberFacts {
+    /// サーバーに参加した日時 (Unix 時間の秒, 分からなければ `None`)
+    pub joined_at: Option<i64>,
+    /// メンバー確認を済ませていないかどうか
+    pub pending: bool,
+    /// ユーザーの公開フラグ (分からなければ `None`)
+    pub public_flags: Option<UserPublicFlags>,
+}
+
+impl MemberFacts {
+    pub fn from_member(member: &Member) -> Self {
+        Self {
+            joined_at: member.joined_at.map(|joined_at| joined_at.unix_timestamp()),
+            pending: member.pending,
+            public_flags: member.user.public_flags,
+        }
+    }
+}
+
+impl EligibilityPolicy {
+    /// サーバー設定と, サーバーでメンバー確認が有効か (`screening`) から条件を作ります
+    pub fn from_settings(settings: &GuildSettings, screening: bool) -> Self {
+        Self {
+            min_account_age_days: settings.min_account_age_days,
+            min_membership_age_days: settings.min_membership_age_days,
+            skip_pending: screening,
+            skip_spammers: settings.skip_spammers,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min_account_age_days.is_none() && !self.needs_member()
+    }
+
+    /// 判定にメンバーの情報 (`MemberFacts`) が必要かどうか
+    pub fn needs_member(&self) -> bool {
+        self.min_membership_age_days.is_some() || self.skip_pending || self.skip_spammers
+    }
+
+    /// 条件を満たさないユーザーについて, 数えない理由を返します
+    ///
+    /// メンバーの情報 `member` が分からない場合は, メンバー確認, スパムの疑い, サーバーへの参加の新しさを確認しません。
+    pub fn evaluate(
+        &self,
+        user_id: UserId,
+        member: Option<MemberFacts>,
+        now: i64,
+    ) -> Option<RejectionReason> {
+        let member = member.unwrap_or_default();
+        if self.skip_pending && member.pending {
+            return Some(RejectionReason::MembershipPending);
+        }
+        if self.skip_spammers
+            && member
+                .public_flags
+                .is_some_and(|flags| flags.contains(UserPublicFlags::SPAMMER))
+        {
+            return Some(RejectionReason::FlaggedSpammer);
+        }
+        let is_younger = |since: i64, days: u64| {
+            let days = i64::try_from(days).unwrap_or(i64::MAX);
+            now.saturating_sub(since) < days.saturating_mul(SECONDS_PER_DAY)
+        };
+        if let Some(days) = self.min_account_age_days
+            && is_younger(snowflake_timestamp(user_id.get()), days)
+        {
+            return Some(RejectionReason::AccountTooNew { days });
+        }
+        if let (Some(days), Some(joined_at)) = (self.min_membership_age_days, member.joined_at)
+            && is_younger(joined_at, days)
+        {
+            return Some(RejectionReason::MembershipTooNew { days });
+        }
+        None
+    }
+}
+
+/// サーバーの条件を求めます (キャッシュにないサーバーはメンバー確認が有効とみなします)
+fn guild_policy(api: &impl DiscordApi, data: &Data, guild_id: GuildId) -> EligibilityPolicy {
+    let screening = api
+        .cache()
+        .and_then(|cache| cache.guild(guild_id))
+        .is_none_or(|guild| {
+            guild
+                .features
+                .iter()
+                .any(|feature| feature == MEMBER_VERIFICATION_GATE)
+        });
+    EligibilityPolicy::from_settings(&data.storage.guild_settings(guild_id), screening)
+}
+
+/// リアクションやメニューから参加したユーザーを参加者として数えない理由を返します
+///
+/// メンバーの情報はイベントに含まれるメンバー `member` を使い, なければキャッシュと REST API から取得します。
+pub async fn rejection(
+    api: &impl DiscordApi,
+    data: &Data,
+    guild_id: GuildId,
+    user_id: UserId,
+    member: Option<&Member>,
+) -> Option<RejectionReason> {
+    check(
+        api,
+        data,
+        guild_id,
+        user_id,
+        member,
+        MemberStrategy::PerMember,
+    )
+    .await
+}
+
+/// `strategy` が `PerMember` でなければ, `member` もキャッシュもないメンバーを REST API で取得せずに判定します
+async fn check(
+    api: &impl DiscordApi,
+    data: &Data,
+    guild_id: GuildId,
+    user_id: UserId,
+    member: Option<&Member>,
+    strategy: MemberStrategy,
+) -> Option<RejectionReason> {
+    let policy = guild_policy(api, data, guild_id);
+    if policy.is_empty() {
+        return None;
+    }
+    let now = Instant::now();
+    let key = (guild_id, user_id, policy);
+    if data
+        .verified_participants
+        .lock()
+        .unwrap()
+        .contains(&key, now)
+    {
+        return None;
+    }
+    let facts = match member {
+        Some(member) => Some(MemberFacts::from_member(member)),
+        None if policy.needs_member() && strategy == MemberStrategy::PerMember => {
+            member_facts(api, guild_id, user_id).await
+        }
+        None if policy.needs_member() => cached_facts(api, guild_id, user_id),
+        None => None,
+    };
+    let reason = policy.evaluate(user_id, facts, Timestamp::now().unix_timestamp());
+    // メンバーの情報を確認できなかったユーザーは, 次の確認でもう一度取得します
+    if reason.is_none() && (facts.is_some() || !policy.needs_member()) {
+        data.verified_participants
+            .lock()
+            .unwrap()
+            .insert_if_expired(key, now);
+    }
+    reason
+}
+
+/// 条件を満たさないユーザーを参加者から除きます
+pub async fn retain_eligible(
+    api: &impl DiscordApi,
+    data: &Data,
+    guild_id: Option<GuildId>,
+    user_ids: &mut HashSet<UserId>,
+) {
+    let Some(guild_id) = guild_id else {
+        return;
+    };
+    let policy = guild_policy(api, data, guild_id);
+    if policy.is_empty() {
+        return;
+    }
+    // 確認済みでもキャッシュにもないメンバーだけを, 取得する候補として数えます
+    let unverified: Vec<UserId> = if policy.needs_member() {
+        let now = Instant::now();
+        let verified = data.verified_participants.lock().unwrap();
+        user_ids
+            .iter()
+            .copied()
+            .filter(|&user_id| !verified.contains(&(guild_id, user_id, policy), now))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let candidates = member_work::uncached(api, guild_id, &unverified);
+    let strategy = member_work::select(data, guild_id, "Eligibility check", candidates.len());
+    let batch = match strategy {
+        MemberStrategy::Batched => member_work::fetch_members(api, guild_id, &candidates).await,
+        MemberStrategy::PerMember | MemberStrategy::CacheOnly => MemberBatch::default(),
+    };
+    let mut rejected = Vec::new();
+    for &user_id in user_ids.iter() {
+        if check(
+            api,
+            data,
+            guild_id,
+            user_id,
+            batch.member(user_id),
+            strategy,
+        )
+        .await
+        .is_some()
+        {
+            rejected.push(user_id);
+        }
+    }
+    for user_id in rejected {
+        user_ids.remove(&user_id);
+    }
+}
+
+/// キャッシュを優先し, なければ REST API でメンバーの情報を取得します
+async fn member_facts(
+    api: &impl DiscordApi,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Option<MemberFacts> {
+    let cached = cached_facts(api, guild_id, user_id);
+    if cached.is_some() {
+        return cached;
+    }
+    match api.member(guild_id, user_id).await {
+        Ok(member) => Some(MemberFacts::from_member(&member)),
+        Err(err) if is_unknown_member(&err) => None,
+        Err(err) => {
+            eprintln!("Failed to fetch member {user_id}: {err}");
+            None
+        }
+    }
+}
+
+fn cached_facts(api: &impl DiscordApi, guild_id: GuildId, user_id: UserId) -> Option<MemberFacts> {
+    api.cache()
+        .and_then(|cache| cache.guild(guild_id))
+        .and_then(|guild| guild.members.get(&user_id).map(MemberFacts::from_member))
+}
+
+/// スパムの疑いがあるアカウントを参加者に数えないか設定します
+#[poise::command(slash_command, guild_only, rename = "skip_spammers")]
+pub async fn settings_skip_spammers(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "スパムの疑いがあるアカウントを数えないかどうか (既定: いいえ)"] enabled: bool,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.skip_spammers = enabled;
+        })?;
+    let content = if enabled {
+        "Discord によってスパムの疑いがあるとされたアカウントを、参加者として数えません"
+    } else {
+        "スパムの疑いがあるとされたアカウントも、参加者として数えます"
+    };
+    send_ephemeral(ctx, content).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2016-04-30T11:18:25Z に作成したアカウント
+    const USER_ID: UserId = UserId::new(175_928_847_299_117_063);
+    const CREATED_AT: i64 = 1_462_015_105;
+
+    #[test]
+    fn snowflakes_tell_when_accounts_were_created() {
+        assert_eq!(snowflake_timestamp(USER_ID.get()), CREATED_AT);
+    }
+
+    #[test]
+    fn new_accounts_and_memberships_are_rejected() {
+        let policy = EligibilityPolicy {
+            min_account_age_days: Some(7),
+            min_membership_age_days: Some(1),
+            ..EligibilityPolicy::default()
+        };
+        let week = 7 * SECONDS_PER_DAY;
+        assert_eq!(
+            policy.evaluate(USER_ID, None, CREATED_AT + week - 1),
+            Some(RejectionReason::AccountTooNew { days: 7 })
+        );
+        assert_eq!(policy.evaluate(USER_ID, None, CREATED_AT + week), None);
+
+        let now = CREATED_AT + 2 * week;
+        let member = |joined_at| MemberFacts {
+            joined_at: Some(joined_at),
+            ..MemberFacts::default()
+        };
+        assert_eq!(
+            policy.evaluate(USER_ID, Some(member(now - 60)), now),
+            Some(RejectionReason::MembershipTooNew { days: 1 })
+        );
+        assert_eq!(
+            policy.evaluate(USER_ID, Some(member(now - SECONDS_PER_DAY)), now),
+            None
+        );
+        assert!(EligibilityPolicy::default().is_empty());
+        assert!(policy.needs_member());
+    }
+
+    #[test]
+    fn pending_and_spam_flagged_members_are_rejected() {
+        let policy = EligibilityPolicy {
+            skip_pending: true,
+            skip_spammers: true,
+            ..EligibilityPolicy::default()
+        };
+        let pending = MemberFacts {
+            pending: true,
+            ..MemberFacts::default()
+        };
+        let spammer = MemberFacts {
+            public_flags: Some(UserPublicFlags::SPAMMER),
+            ..MemberFacts::default()
+        };
+        assert_eq!(
+            policy.evaluate(USER_ID, Some(pending), CREATED_AT),
+            Some(RejectionReason::MembershipPending)
+        );
+        assert_eq!(
+            policy.evaluate(USER_ID, Some(spammer), CREATED_AT),
+            Some(RejectionReason::FlaggedSpammer)
+        );
+        // メンバーの情報が分からない場合は数えます
+        assert_eq!(policy.evaluate(USER_ID, None, CREATED_AT), None);
+        assert_eq!(
+            EligibilityPolicy::default().evaluate(USER_ID, Some(pending), CREATED_AT),
+            None
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use poise::serenity_prelude::*;
+use tokio::time::{Duration, sleep};
+
+use crate::discord_api::DiscordApi;
+use crate::i18n::Text;
+use crate::notification;
+use crate::recruit_message::{RecruitConfig, RecruitReaction};
+use crate::{Data, Error, collect_participants, summary};
+
+/// 参加者の集計がこの時間を超えたら「確認中…」を表示します
+const SLOW_COUNT_THRESHOLD: Duration = Duration::from_secs(5);
+
+/// 最後に REST API で数えた参加人数と, その後にキャッシュで数えたリアクションの数の合計
+#[derive(Debug, Clone, Copy)]
+pub struct CachedCount {
+    count: usize,
+    counted_at: Instant,
+}
+
+/// 参加者の集計の統計
+#[derive(Debug, Default)]
+pub struct CountMetrics {
+    /// キャッシュした参加人数で済ませた回数
+    skipped: AtomicUsize,
+    /// 集計に `SLOW_COUNT_THRESHOLD` 以上かかった回数
+    slow: AtomicUsize,
+}
+
+impl fmt::Display for CountMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "skipped={} slow={}",
+            self.skipped.load(Ordering::Relaxed),
+            self.slow.load(Ordering::Relaxed)
+        )
+    }
+}
+
+/// キャッシュした参加人数から, 今回のリアクションで人数を数え直す必要がないか判定します
+///
+/// リアクションを外しても参加人数のキャッシュは減らさないため, キャッシュは実際の人数以上になります。
+/// そのうえで今回のリアクションを加えても開始・「あと1人」の通知・定員超過のいずれにも届かず,
+/// 参加通知で正確な人数を表示する必要もない場合にのみ true を返します。
+pub fn can_skip_full_count(
+    config: &RecruitConfig,
+    recruit_reaction: RecruitReaction<'_>,
+    cached: Option<usize>,
+) -> bool {
+    let Some(cached) = cached else {
+        return false;
+    };
+    let counts_as_participation = match recruit_reaction {
+        RecruitReaction::Participation => !config.notify_on_reaction,
+        RecruitReaction::SilentParticipation => true,
+        RecruitReaction::Extra(extra) => extra.counts,
+        RecruitReaction::Start | RecruitReaction::GameOption(_) | RecruitReaction::Slot(_) => false,
+    };
+    // ボイスチャンネルの参加者はリアクションと関係なく増え, 先着順は毎回記録する必要があります
+    if !counts_as_participation
+        || config.has_option_reactions()
+        || config.strict_order
+        || config.count_voice_channel.is_some()
+    {
+        return false;
+    }
+    let estimated = cached + 1;
+    estimated < config.required_players.saturating_sub(1)
+        && config
+            .max_players
+            .is_none_or(|max_players| estimated <= max_players)
+}
+
+/// 有効期間内のキャッシュした参加人数
+pub fn cached_count(data: &Data, message_id: MessageId) -> Option<usize> {
+    data.participant_counts
+        .lock()
+        .unwrap()
+        .get(&message_id)
+        .filter(|cached| cached.counted_at.elapsed() < data.config.cached_count_ttl)
+        .map(|cached| cached.count)
+}
+
+/// 有効期間にかかわらず, 最後にキャッシュした参加人数 (今日の募集の一覧に表示します)
+pub fn last_count(data: &Data, message_id: MessageId) -> Option<usize> {
+    data.participant_counts
+        .lock()
+        .unwrap()
+        .get(&message_id)
+        .map(|cached| cached.count)
+}
+
+/// REST API で数えた参加人数をキャッシュします
+pub fn store_count(data: &Data, message_id: MessageId, count: usize) {
+    data.participant_counts.lock().unwrap().insert(
+        message_id,
+        CachedCount {
+            count,
+            counted_at: Instant::now(),
+        },
+    );
+    summary::mark_message(data, message_id);
+}
+
+/// 数え直しを省いたリアクションの分だけキャッシュした参加人数を増やします
+pub fn record_skipped(data: &Data, message_id: MessageId) {
+    if let Some(cached) = data.participant_counts.lock().unwrap().get_mut(&message_id) {
+        cached.count += 1;
+    }
+    summary::mark_message(data, message_id);
+    data.count_metrics.skipped.fetch_add(1, Ordering::Relaxed);
+}
+
+/// 参加者を数え, 時間がかかっている間は募集メッセージに「確認中…」と返信します
+///
+/// 返信は集計が終わると削除します。
+pub async fn collect_participants_with_feedback(
+    api: &impl DiscordApi,
+    data: &Data,
+    message: &Message,
+    config: &RecruitConfig,
+    guild_id: Option<GuildId>,
+) -> Result<HashSet<UserId>, Error> {
+    let started_at = Instant::now();
+    let count = collect_participants(api, data, message, config, guild_id);
+    tokio::pin!(count);
+    tokio::select! {
+        result = &mut count => return result,
+        _ = sleep(SLOW_COUNT_THRESHOLD) => {}
+    }
+
+    data.count_metrics.slow.fetch_add(1, Ordering::Relaxed);
+    let notice = api
+        .send_message(
+            message.channel_id,
+            notification::plain_message(config.language().text(Text::Checking))
+                .reference_message(message),
+        )
+        .await;
+    let result = count.await;
+    eprintln!(
+        "Counting participants of {} took {:.1}s",
+        message.id,
+        started_at.elapsed().as_secs_f64()
+    );
+    match notice {
+        Ok(notice) => {
+            if let Err(err) = api.delete_message(notice.channel_id, notice.id).await {
+                eprintln!("Failed to delete checking notice: {err}");
+            }
+        }
+        Err(err) => eprintln!("Failed to send checking notice: {err}"),
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recruit_message::{ExtraReaction, GameOption};
+
+    /// 参加通知を送らない, 5 人で開始する募集
+    fn config() -> RecruitConfig {
+        let mut config = RecruitConfig::for_test("Apex", 5);
+        config.notify_on_reaction = false;
+        config
+    }
+
+    fn can_skip(config: &RecruitConfig, cached: usize) -> bool {
+        can_skip_full_count(config, RecruitReaction::Participation, Some(cached))
+    }
+
+    #[test]
+    fn count_is_skipped_only_far_from_the_start() {
+        let config = config();
+        // 今回のリアクションで 3 人 (開始まで 2 人) なら数え直しません
+        assert!(can_skip(&config, 2));
+        // 「あと1人」の通知や開始に届く場合は数え直します
+        assert!(!can_skip(&config, 3));
+        assert!(!can_skip(&config, 4));
+        assert!(!can_skip(&config, 10));
+        assert!(!can_skip_full_count(
+            &config,
+            RecruitReaction::Participation,
+            None
+        ));
+
+        let mut small = config.clone();
+        small.required_players = 2;
+        assert!(!can_skip(&small, 0));
+    }
+
+    #[test]
+    fn count_is_skipped_only_within_max_players() {
+        let mut config = config();
+        config.max_players = Some(3);
+        assert!(can_skip(&config, 2));
+        config.max_players = Some(2);
+        assert!(!can_skip(&config, 2));
+    }
+
+    #[test]
+    fn recruits_that_need_exact_counts_are_always_recounted() {
+        let mut notified = config();
+        notified.notify_on_reaction = true;
+        assert!(!can_skip(&notified, 0));
+        // 参加通知を送らないリアクションは数え直しません
+        assert!(can_skip_full_count(
+            &notified,
+            RecruitReaction::SilentParticipation,
+            Some(0)
+        ));
+
+        let mut strict_order = config();
+        strict_order.strict_order = true;
+        assert!(!can_skip(&strict_order, 0));
+
+        let mut voice = config();
+        voice.count_voice_channel = Some(ChannelId::new(1));
+        assert!(!can_skip(&voice, 0));
+
+        let mut multi_game = config();
+        multi_game.game_options = vec![
+            GameOption {
+                game_title: "Apex".to_string(),
+                required_players: 5,
+            },
+            GameOption {
+                game_title: "Valorant".to_string(),
+                required_players: 5,
+            },
+        ];
+        assert!(!can_skip(&multi_game, 0));
+        assert!(!can_skip_full_count(
+            &multi_game,
+            RecruitReaction::GameOption(0),
+            Some(0)
+        ));
+    }
+
+    #[test]
+    fn only_counted_reactions_are_skipped() {
+        let config = config();
+        let counted = ExtraReaction {
+            emoji: "🎮".to_string(),
+            label: "補欠".to_string(),
+            counts: true,
+        };
+        let watching = ExtraReaction {
+            counts: false,
+            ..counted.clone()
+        };
+        assert!(can_skip_full_count(
+            &config,
+            RecruitReaction::Extra(&counted),
+            Some(0)
+        ));
+        assert!(!can_skip_full_count(
+            &config,
+            RecruitReaction::Extra(&watching),
+            Some(0)
+        ));
+        assert!(!can_skip_full_count(
+            &config,
+            RecruitReaction::Start,
+            Some(0)
+        ));
+    }
+}
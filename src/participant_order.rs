@@ -0,0 +1,253 @@
+use std::collections::HashSet;
+
+use poise::serenity_prelude::{MessageId, UserId};
+
+use crate::recruit_message::{OverMention, RecruitConfig};
+use crate::{Data, Error};
+
+/// リアクションを処理した順に参加者を記録します
+///
+/// 募集メッセージごとのロックを取ってから呼び出してください。
+/// 記録済みの順番を保ったまま, `current` (現在の参加者) にいない参加者を除き,
+/// 記録されていない参加者を末尾に加えた順番を保存して返します。
+pub fn record_reactor(
+    data: &Data,
+    message_id: MessageId,
+    user_id: Option<UserId>,
+    current: &HashSet<UserId>,
+) -> Result<Vec<UserId>, Error> {
+    let previous = data.storage.participant_order(message_id);
+    let mut persisted = previous.clone();
+    if let Some(user_id) = user_id
+        && current.contains(&user_id)
+        && !persisted.contains(&user_id)
+    {
+        persisted.push(user_id);
+    }
+    let order = reconcile(&persisted, current);
+    if order != previous {
+        data.storage
+            .set_participant_order(message_id, order.clone())?;
+    }
+    Ok(order)
+}
+
+/// 参加者を数え直さずに受け付けたリアクションのユーザーを, 記録した順番の末尾に加えます
+///
+/// 募集メッセージごとのロックを取ってから呼び出してください。
+/// 後で数え直したときに, 数え直さなかった参加者が後から参加した参加者の後ろに並ばないようにします。
+pub fn append_reactor(data: &Data, message_id: MessageId, user_id: UserId) -> Result<(), Error> {
+    let mut order = data.storage.participant_order(message_id);
+    if append(&mut order, user_id) {
+        data.storage.set_participant_order(message_id, order)?;
+    }
+    Ok(())
+}
+
+/// `user_id` が記録されていなければ末尾に加え, 加えたかどうかを返します
+fn append(order: &mut Vec<UserId>, user_id: UserId) -> bool {
+    if order.contains(&user_id) {
+        return false;
+    }
+    order.push(user_id);
+    true
+}
+
+/// 現在の参加者を, 記録した順番に並べます (記録は更新しません)
+pub fn ordered(data: &Data, message_id: MessageId, current: &HashSet<UserId>) -> Vec<UserId> {
+    reconcile(&data.storage.participant_order(message_id), current)
+}
+
+/// 記録済みの順番に, 記録から漏れた参加者をユーザー ID 順に加えます
+///
+/// REST API から取得したリアクションの順番は追加された順ではないため,
+/// 記録から漏れた参加者は常に記録済みの参加者の後ろに並べます。
+pub fn reconcile(persisted: &[UserId], current: &HashSet<UserId>) -> Vec<UserId> {
+    let mut order: Vec<UserId> = Vec::with_capacity(current.len());
+    for &user_id in persisted {
+        if current.contains(&user_id) && !order.contains(&user_id) {
+            order.push(user_id);
+        }
+    }
+    let mut missed: Vec<UserId> = current
+        .iter()
+        .filter(|user_id| !order.contains(user_id))
+        .copied()
+        .collect();
+    missed.sort();
+    order.extend(missed);
+    order
+}
+
+/// 参加者のうち先着 `max_players` 人に入っていないかどうか
+pub fn is_over_slots(order: &[UserId], max_players: Option<usize>, user_id: UserId) -> bool {
+    let Some(max_players) = max_players else {
+        return false;
+    };
+    order
+        .iter()
+        .position(|&id| id == user_id)
+        .is_some_and(|position| max_players <= position)
+}
+
+/// 開始通知でメンションする参加者と補欠
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StartSelection {
+    pub starters: Vec<UserId>,
+    pub substitutes: Vec<UserId>,
+}
+
+/// 参加した順の参加者を, 開始する参加者と補欠に分けます
+///
+/// 定員は `max_players` (なければ開始に必要な人数) で, 先着で定員までを開始し残りを補欠とします。
+/// `over_mention = all` の募集は全員を開始しますが, `strict_order` の募集は `max_players` を超えた分を補欠とします。
+pub fn select_starters(mut order: Vec<UserId>, config: &RecruitConfig) -> StartSelection {
+    let capacity = match config.over_mention {
+        OverMention::First => Some(config.max_players.unwrap_or(config.required_players)),
+        OverMention::All if config.strict_order => config.max_players,
+        OverMention::All => None,
+    };
+    let substitutes = match capacity {
+        Some(capacity) if capacity < order.len() => order.split_off(capacity),
+        _ => Vec::new(),
+    };
+    StartSelection {
+        starters: order,
+        substitutes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    fn users(ids: &[u64]) -> Vec<UserId> {
+        ids.iter().copied().map(UserId::new).collect()
+    }
+
+    fn set(ids: &[u64]) -> HashSet<UserId> {
+        users(ids).into_iter().collect()
+    }
+
+    #[test]
+    fn skipped_reactors_keep_their_place() {
+        // u2 と u3 は数え直さずに受け付け, u4 で数え直す
+        let mut persisted = users(&[1]);
+        assert!(append(&mut persisted, UserId::new(2)));
+        assert!(append(&mut persisted, UserId::new(3)));
+        assert!(!append(&mut persisted, UserId::new(2)));
+        assert!(append(&mut persisted, UserId::new(4)));
+        let order = reconcile(&persisted, &set(&[1, 2, 3, 4]));
+        assert_eq!(order, users(&[1, 2, 3, 4]));
+
+        let config = RecruitConfig::for_test("Valorant", 3);
+        let selection = select_starters(order, &config);
+        assert_eq!(selection.starters, users(&[1, 2, 3]));
+        assert_eq!(selection.substitutes, users(&[4]));
+    }
+
+    #[test]
+    fn select_starters_exact_fill() {
+        let config = RecruitConfig::for_test("Valorant", 3);
+        let selection = select_starters(users(&[5, 1, 9]), &config);
+        assert_eq!(selection.starters, users(&[5, 1, 9]));
+        assert!(selection.substitutes.is_empty());
+    }
+
+    #[test]
+    fn select_starters_overfill() {
+        let config = RecruitConfig::for_test("Valorant", 5);
+        let order = users(&[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        let selection = select_starters(order.clone(), &config);
+        assert_eq!(selection.starters, users(&[1, 2, 3, 4, 5]));
+        assert_eq!(selection.substitutes, users(&[6, 7, 8, 9]));
+
+        let config = RecruitConfig {
+            max_players: Some(6),
+            ..RecruitConfig::for_test("Valorant", 5)
+        };
+        let selection = select_starters(order.clone(), &config);
+        assert_eq!(selection.starters.len(), 6);
+        assert_eq!(selection.substitutes, users(&[7, 8, 9]));
+
+        let config = RecruitConfig {
+            over_mention: OverMention::All,
+            ..RecruitConfig::for_test("Valorant", 5)
+        };
+        let selection = select_starters(order, &config);
+        assert_eq!(selection.starters.len(), 9);
+        assert!(selection.substitutes.is_empty());
+    }
+
+    #[test]
+    fn select_starters_underfill_after_reconcile() {
+        // 記録した参加者のうち 1 人がリアクションを外していた
+        let order = reconcile(&users(&[1, 2, 3]), &set(&[1, 3]));
+        let config = RecruitConfig::for_test("Valorant", 3);
+        let selection = select_starters(order, &config);
+        assert_eq!(selection.starters, users(&[1, 3]));
+        assert!(selection.substitutes.is_empty());
+    }
+
+    #[test]
+    fn is_over_slots_uses_position() {
+        let order = users(&[1, 2, 3]);
+        assert!(!is_over_slots(&order, Some(2), UserId::new(2)));
+        assert!(is_over_slots(&order, Some(2), UserId::new(3)));
+        assert!(!is_over_slots(&order, None, UserId::new(3)));
+        assert!(!is_over_slots(&order, Some(2), UserId::new(4)));
+    }
+
+    #[tokio::test]
+    async fn interleaved_reactions_keep_processing_order() {
+        let data = Data::for_test();
+        let message_id = MessageId::new(1);
+        let reactors = Arc::new(Mutex::new(HashSet::new()));
+        let processed = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        // 同じユーザーのリアクションが重なっても, 処理した順に 1 度だけ記録します
+        for id in [5, 3, 8, 3, 1, 5, 9, 2] {
+            let data = data.clone();
+            let reactors = reactors.clone();
+            let processed = processed.clone();
+            tasks.push(tokio::spawn(async move {
+                let user_id = UserId::new(id);
+                let _lock = data.lock_message(message_id).await;
+                tokio::task::yield_now().await;
+                let current = {
+                    let mut reactors = reactors.lock().unwrap();
+                    reactors.insert(user_id);
+                    reactors.clone()
+                };
+                let mut processed = processed.lock().unwrap();
+                if !processed.contains(&user_id) {
+                    processed.push(user_id);
+                }
+                record_reactor(&data, message_id, Some(user_id), &current).unwrap();
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+        let processed = processed.lock().unwrap().clone();
+        assert_eq!(data.storage.participant_order(message_id), processed);
+
+        // 数え直しで見つかった参加者は, 記録済みの参加者の後ろにユーザー ID 順で加えます
+        let mut current = reactors.lock().unwrap().clone();
+        current.extend(users(&[7, 4]));
+        let order = record_reactor(&data, message_id, None, &current).unwrap();
+        let mut expected = processed.clone();
+        expected.extend(users(&[4, 7]));
+        assert_eq!(order, expected);
+        // 何度数え直しても順番は変わりません
+        assert_eq!(
+            record_reactor(&data, message_id, None, &current).unwrap(),
+            expected
+        );
+        assert_eq!(ordered(&data, message_id, &current), expected);
+        let unique: HashSet<UserId> = order.iter().copied().collect();
+        assert_eq!(unique.len(), order.len());
+    }
+}
@@ -0,0 +1,258 @@
+use poise::serenity_prelude::*;
+
+use crate::application::{self, ApplicationStatus};
+use crate::discord_api::DiscordApi;
+use crate::discord_error::is_unknown_entity;
+use crate::i18n::{self, Language, Text};
+use crate::join_menu;
+use crate::notification;
+use crate::recruit_message::{ParticipationMode, RecruitConfig};
+use crate::{
+    Data, Error, collect_participants, config_storage, format_progress, parse_bot_recruit_message,
+    participant_count, recruit_edit,
+};
+
+const BUTTON_PREFIX: &str = "recruit_button:";
+
+/// 募集メッセージの参加のボタンの操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Join,
+    Leave,
+}
+
+impl Action {
+    fn custom_id(self) -> String {
+        let code = match self {
+            Action::Join => "join",
+            Action::Leave => "leave",
+        };
+        format!("{BUTTON_PREFIX}{code}")
+    }
+
+    fn parse(custom_id: &str) -> Option<Self> {
+        match custom_id.strip_prefix(BUTTON_PREFIX)? {
+            "join" => Some(Action::Join),
+            "leave" => Some(Action::Leave),
+            _ => None,
+        }
+    }
+}
+
+/// ボタンで参加を受け付ける募集メッセージに付けるボタン
+///
+/// 操作する募集はボタンを押したメッセージなので, custom_id には含めません。
+pub fn components(config: &RecruitConfig) -> Vec<CreateActionRow> {
+    let language = config.language();
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(Action::Join.custom_id())
+            .label(language.text(Text::ButtonJoin))
+            .style(ButtonStyle::Primary),
+        CreateButton::new(Action::Leave.custom_id())
+            .label(language.text(Text::ButtonLeave))
+            .style(ButtonStyle::Secondary),
+    ])]
+}
+
+/// 募集メッセージの参加のボタンかどうか
+pub fn is_participation_button(interaction: &ComponentInteraction) -> bool {
+    interaction.data.custom_id.starts_with(BUTTON_PREFIX)
+}
+
+/// 参加のボタンを押したユーザーを, 募集に参加させるか参加をやめさせます
+pub async fn handle_participation_button(
+    ctx: &Context,
+    data: &Data,
+    interaction: &ComponentInteraction,
+) -> Result<(), Error> {
+    let (Some(action), Some(guild_id)) = (
+        Action::parse(&interaction.data.custom_id),
+        interaction.guild_id,
+    ) else {
+        return Ok(());
+    };
+    // 応募が必要な募集は, 参加させる代わりに質問に答えるフォームを表示します
+    if action == Action::Join
+        && let Some(recruit_message) = parse_bot_recruit_message(ctx, data, &interaction.message)
+        && recruit_message.config.requires_application()
+        && join_menu::is_open(&recruit_message)
+    {
+        return application::open_form(ctx, data, interaction, &recruit_message.config).await;
+    }
+    interaction.defer_ephemeral(ctx).await?;
+    let channel_id = interaction.channel_id;
+    let message_id = interaction.message.id;
+    let user_id = interaction.user.id;
+    let content = match action {
+        Action::Join => {
+            join_menu::join(ctx, data, guild_id, channel_id, message_id, user_id).await?
+        }
+        Action::Leave => leave(ctx, data, guild_id, channel_id, message_id, user_id).await?,
+    };
+    interaction
+        .create_followup(
+            ctx,
+            CreateInteractionResponseFollowup::new()
+                .content(content)
+                .ephemeral(true),
+        )
+        .await?;
+    Ok(())
+}
+
+/// ボタンで参加したユーザーを参加者から除き, 実行者に表示する結果を返します
+async fn leave(
+    ctx: &Context,
+    data: &Data,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    user_id: UserId,
+) -> Result<String, Error> {
+    let _guard = data.lock_message(message_id).await;
+    let message = match channel_id.message(ctx, message_id).await {
+        Ok(message) => message,
+        Err(err) if is_unknown_entity(&err).is_some() => {
+            return Ok("募集メッセージは削除されています".to_string());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let Some(mut recruit_message) = parse_bot_recruit_message(ctx, data, &message) else {
+        return Ok("このメッセージは募集ではありません".to_string());
+    };
+    if !join_menu::is_open(&recruit_message) {
+        return Ok("この募集はすでに開始または締め切られています".to_string());
+    }
+    i18n::resolve_language(data, &mut recruit_message.config, Some(guild_id));
+    let config = &recruit_message.config;
+    let withdrawn = if config.requires_application() {
+        application::withdraw(ctx, data, message.id, user_id).await?
+    } else {
+        None
+    };
+    if !data.storage.remove_participant(message.id, user_id)? {
+        if withdrawn == Some(ApplicationStatus::Pending) {
+            return Ok(format!("{} への応募を取り下げました", config.game_title));
+        }
+        if config.preset_participants.contains(&user_id) {
+            return Ok(format!(
+                "主催者が {} の参加者として登録しているため、やめる場合は主催者に伝えてください",
+                config.game_title
+            ));
+        }
+        return Ok(format!("{} に参加していません", config.game_title));
+    }
+    let user_ids = collect_participants(ctx, data, &message, config, Some(guild_id)).await?;
+    participant_count::store_count(data, message.id, user_ids.len());
+    Ok(format!(
+        "{} の参加をやめました {}",
+        config.game_title,
+        format_progress(Language::Ja, user_ids.len(), config.required_players)
+    ))
+}
+
+/// Bot がリアクションを付けられなかった募集メッセージを, ボタンで参加を受け付ける募集に書き換えます
+///
+/// `config` は書き換えた後の募集設定になります。
+pub async fn switch_to_buttons(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &mut RecruitConfig,
+    message: &Message,
+) -> Result<Message, Error> {
+    let edited = recruit_edit::read_modify_write(
+        api,
+        message.channel_id,
+        message.id,
+        |message| {
+            let mut recruit_message = config_storage::load(data, message)?;
+            recruit_message.config.participation_mode = ParticipationMode::Buttons;
+            Ok(Some(recruit_message))
+        },
+        |recruit_message| {
+            let content = config_storage::store(data, message.id, recruit_message)?;
+            Ok(EditMessage::new()
+                .content(content)
+                .components(components(&recruit_message.config)))
+        },
+    )
+    .await?;
+    let Some((recruit_message, Some(message))) = edited else {
+        return Err("recruit message is in an archived thread".into());
+    };
+    *config = recruit_message.config;
+    Ok(message)
+}
+
+/// ボタンにも切り替えられなかった募集メッセージに, `/join` での参加を案内します
+pub async fn send_join_command_notice(
+    api: &impl DiscordApi,
+    message: &Message,
+) -> Result<(), Error> {
+    let content = format!(
+        "Bot がこのチャンネルでリアクションを付けられないため、`/join` で参加してください: {}",
+        message.link()
+    );
+    api.send_message(message.channel_id, notification::plain_message(content))
+        .await?;
+    Ok(())
+}
+
+/// リアクションの代わりにボタンか `/join` で参加を受け付けることを, サーバー設定 `audit_channel` に知らせます
+pub async fn report_fallback(
+    api: &impl DiscordApi,
+    data: &Data,
+    guild_id: Option<GuildId>,
+    message: &Message,
+    buttons: bool,
+) {
+    let method = if buttons { "ボタン" } else { "`/join`" };
+    eprintln!(
+        "Cannot add reactions in channel {}, accepting participation by {}",
+        message.channel_id,
+        if buttons { "buttons" } else { "/join" }
+    );
+    let Some(audit_channel) =
+        guild_id.and_then(|guild_id| data.storage.guild_settings(guild_id).audit_channel)
+    else {
+        return;
+    };
+    let content = format!(
+        "⚠️ Bot に {} でリアクションを付ける権限がないため、{} の参加を{method}で受け付けます。Bot のチャンネルの権限を確認してください",
+        message.channel_id.mention(),
+        message.link()
+    );
+    if let Err(err) = api
+        .send_message(audit_channel, notification::plain_message(content))
+        .await
+    {
+        eprintln!("Failed to send audit alert: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn button_ids_are_read_back() {
+        for action in [Action::Join, Action::Leave] {
+            assert_eq!(Action::parse(&action.custom_id()), Some(action));
+        }
+        assert_eq!(Action::parse("recruit_button:cancel"), None);
+        assert_eq!(Action::parse("join"), None);
+    }
+
+    #[test]
+    fn buttons_are_labelled_in_the_recruit_language() {
+        let components = components(&RecruitConfig::for_test("Apex", 3));
+        let value = serde_json::to_value(&components).unwrap();
+        let buttons = value[0]["components"].as_array().unwrap();
+        let custom_ids: Vec<&str> = buttons
+            .iter()
+            .map(|button| button["custom_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(custom_ids, ["recruit_button:join", "recruit_button:leave"]);
+        assert_eq!(buttons[0]["label"], Language::Ja.text(Text::ButtonJoin));
+    }
+}
@@ -0,0 +1,284 @@
+use poise::serenity_prelude::{self as serenity, *};
+
+use crate::{
+    Data, Error, begin_start, can_manage_recruit, collect_participants, i18n,
+    parse_bot_recruit_message, participant_count, recruit_edit, send_ephemeral,
+};
+
+/// 事前に登録できる参加者の人数
+pub const MAX_PRESET_PARTICIPANTS: usize = 5;
+
+/// 登録する参加者 (同じユーザーは 1 人にまとめ, Bot と最大人数を超える登録はエラーにします)
+pub fn preset_user_ids(
+    users: impl IntoIterator<Item = User>,
+    max_players: Option<usize>,
+) -> Result<Vec<UserId>, &'static str> {
+    let mut user_ids = Vec::new();
+    for user in users {
+        if user.bot {
+            return Err("Bot は参加者として登録できません。");
+        }
+        if !user_ids.contains(&user.id) {
+            user_ids.push(user.id);
+        }
+    }
+    if max_players.is_some_and(|max_players| max_players < user_ids.len()) {
+        return Err("最大人数より多くの参加者は登録できません。");
+    }
+    Ok(user_ids)
+}
+
+/// 登録した参加者を, 先着順でもリアクションした参加者より先に並べます
+fn order_with_presets(users: &[UserId], order: Vec<UserId>) -> Vec<UserId> {
+    let mut ordered = users.to_vec();
+    ordered.extend(order.into_iter().filter(|user_id| !users.contains(user_id)));
+    ordered
+}
+
+/// `/recruit create` の `preset_users` (`<@123> <@456>` のようなメンションか ID を空白やカンマで区切ったもの) を読み取ります
+pub fn parse_user_mentions(input: &str) -> Result<Vec<UserId>, String> {
+    let mut user_ids = Vec::new();
+    for word in input
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '、')
+        .filter(|word| !word.is_empty())
+    {
+        let id = word
+            .strip_prefix("<@")
+            .and_then(|rest| rest.strip_suffix('>'))
+            .map(|rest| rest.strip_prefix('!').unwrap_or(rest))
+            .unwrap_or(word);
+        let Some(user_id) = id
+            .parse::<u64>()
+            .ok()
+            .filter(|&id| id != 0)
+            .map(UserId::new)
+        else {
+            return Err(format!(
+                "preset_users の `{word}` はメンバーではありません。`@メンバー` を空白で区切って指定してください。"
+            ));
+        };
+        if !user_ids.contains(&user_id) {
+            user_ids.push(user_id);
+        }
+    }
+    if MAX_PRESET_PARTICIPANTS < user_ids.len() {
+        return Err(format!(
+            "preset_users は {MAX_PRESET_PARTICIPANTS} 人までです。"
+        ));
+    }
+    Ok(user_ids)
+}
+
+/// `/recruit create` で登録する参加者
+///
+/// 開始に必要な人数に届く登録は, 募集する必要がないためエラーにします。
+pub async fn resolve_preset_users(
+    ctx: &Context,
+    input: &str,
+    required_players: usize,
+    max_players: Option<usize>,
+) -> Result<Result<Vec<UserId>, String>, Error> {
+    let user_ids = match parse_user_mentions(input) {
+        Ok(user_ids) => user_ids,
+        Err(err) => return Ok(Err(err)),
+    };
+    let mut users = Vec::new();
+    for user_id in user_ids {
+        match user_id.to_user(ctx).await {
+            Ok(user) => users.push(user),
+            Err(serenity::Error::Http(HttpError::UnsuccessfulRequest(response)))
+                if response.status_code.as_u16() == 404 =>
+            {
+                return Ok(Err(format!(
+                    "preset_users の {} が見つかりません。",
+                    user_id.mention()
+                )));
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+    let user_ids = match preset_user_ids(users, max_players) {
+        Ok(user_ids) => user_ids,
+        Err(err) => return Ok(Err(err.to_string())),
+    };
+    if required_players <= user_ids.len() {
+        return Ok(Err(
+            "preset_users は開始に必要な人数より少なく指定してください。".to_string(),
+        ));
+    }
+    Ok(Ok(user_ids))
+}
+
+/// 参加が決まっているメンバーを登録します (すべて省略で解除)
+#[poise::command(slash_command, guild_only, rename = "participants")]
+pub async fn preset_participants(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集メッセージのリンク"] message: Message,
+    #[description = "参加者として登録するメンバー"] preset_user: Option<User>,
+    #[description = "2 人目"] preset_user2: Option<User>,
+    #[description = "3 人目"] preset_user3: Option<User>,
+    #[description = "4 人目"] preset_user4: Option<User>,
+    #[description = "5 人目"] preset_user5: Option<User>,
+) -> Result<(), Error> {
+    let data = ctx.data();
+    let serenity_ctx = ctx.serenity_context();
+    let _guard = data.lock_message(message.id).await;
+    let Some(recruit_message) = parse_bot_recruit_message(serenity_ctx, data, &message) else {
+        send_ephemeral(ctx, "指定されたメッセージは募集メッセージではありません。").await?;
+        return Ok(());
+    };
+
+    if !can_manage_recruit(ctx, &recruit_message.config) {
+        send_ephemeral(
+            ctx,
+            "主催者またはメッセージの管理権限を持つメンバーのみ参加者を登録できます。",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let state = &recruit_message.state;
+    if state.cancelled
+        || (state.started && recruit_message.config.closes_on_start())
+        || state.closed_entries.is_some()
+    {
+        send_ephemeral(ctx, "この募集はすでに終了しています。").await?;
+        return Ok(());
+    }
+    // 複数のゲームの募集と枠のある募集は選択肢ごとのリアクションで数えるため, 事前に登録できません
+    if recruit_message.config.has_option_reactions() {
+        send_ephemeral(
+            ctx,
+            "複数のゲームの募集と枠のある募集には参加者を事前に登録できません。",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let users = match preset_user_ids(
+        [
+            preset_user,
+            preset_user2,
+            preset_user3,
+            preset_user4,
+            preset_user5,
+        ]
+        .into_iter()
+        .flatten(),
+        recruit_message.config.max_players,
+    ) {
+        Ok(users) => users,
+        Err(err) => {
+            send_ephemeral(ctx, err).await?;
+            return Ok(());
+        }
+    };
+
+    let edited = recruit_edit::edit_recruit_message(
+        serenity_ctx,
+        data,
+        message.channel_id,
+        message.id,
+        |recruit_message| {
+            let state = &recruit_message.state;
+            if state.cancelled
+                || (state.started && recruit_message.config.closes_on_start())
+                || state.closed_entries.is_some()
+            {
+                return None;
+            }
+            recruit_message.config.preset_participants = users.clone();
+            Some(())
+        },
+    )
+    .await?;
+    let Some((recruit_message, ())) = edited else {
+        send_ephemeral(ctx, "この募集はすでに終了しています。").await?;
+        return Ok(());
+    };
+
+    let order = order_with_presets(&users, data.storage.participant_order(message.id));
+    data.storage.set_participant_order(message.id, order)?;
+
+    let mut config = recruit_message.config;
+    i18n::resolve_language(data, &mut config, ctx.guild_id());
+    let user_ids =
+        collect_participants(serenity_ctx, data, &message, &config, ctx.guild_id()).await?;
+    participant_count::store_count(data, message.id, user_ids.len());
+    let reply = if users.is_empty() {
+        "事前に登録した参加者を解除しました".to_string()
+    } else {
+        format!(
+            "{} 人を参加者として登録しました (参加者 {} 人)",
+            users.len(),
+            user_ids.len()
+        )
+    };
+    send_ephemeral(ctx, &reply).await?;
+
+    if config.required_players <= user_ids.len() {
+        begin_start(
+            serenity_ctx,
+            data,
+            &config,
+            &message,
+            ctx.guild_id(),
+            user_ids,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: u64, bot: bool) -> User {
+        let mut user = User::default();
+        user.id = UserId::new(id);
+        user.bot = bot;
+        user
+    }
+
+    fn ids(ids: &[u64]) -> Vec<UserId> {
+        ids.iter().copied().map(UserId::new).collect()
+    }
+
+    #[test]
+    fn preset_users_are_deduplicated_and_checked() {
+        assert_eq!(
+            preset_user_ids([user(1, false), user(2, false), user(1, false)], None),
+            Ok(ids(&[1, 2]))
+        );
+        assert!(preset_user_ids([user(1, false), user(2, true)], None).is_err());
+        assert!(preset_user_ids([user(1, false), user(2, false)], Some(1)).is_err());
+        assert_eq!(preset_user_ids([user(1, false)], Some(1)), Ok(ids(&[1])));
+    }
+
+    #[test]
+    fn mentions_are_parsed_in_order() {
+        assert_eq!(parse_user_mentions(""), Ok(Vec::new()));
+        assert_eq!(
+            parse_user_mentions("<@12> <@!34>,56、<@12>"),
+            Ok(ids(&[12, 34, 56]))
+        );
+        assert!(parse_user_mentions("<@12> @someone").is_err());
+        assert!(parse_user_mentions("<@&12>").is_err());
+        assert!(parse_user_mentions("0").is_err());
+        assert!(parse_user_mentions("1 2 3 4 5 6").is_err());
+        assert_eq!(
+            parse_user_mentions("1 2 3 4 5 5").map(|ids| ids.len()),
+            Ok(5)
+        );
+    }
+
+    #[test]
+    fn presets_come_before_reactors() {
+        assert_eq!(
+            order_with_presets(&ids(&[3, 1]), ids(&[1, 2, 4])),
+            ids(&[3, 1, 2, 4])
+        );
+        assert_eq!(order_with_presets(&[], ids(&[2, 1])), ids(&[2, 1]));
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashSet;
+
+use poise::serenity_prelude::*;
+
+use crate::discord_api::DiscordApi;
+use crate::i18n::{self, Text};
+use crate::notification::{Mentions, NotificationPolicy};
+use crate::recruit_message::RecruitConfig;
+use crate::{
+    Data, Error, begin_start, collect_participants, participant_count, participant_order,
+    recruit_edit,
+};
+
+/// カテゴリの `priority_window_minutes` を指定しなかった場合の優先期間の分数
+pub const DEFAULT_PRIORITY_WINDOW_MINUTES: u64 = 30;
+
+/// `now` が優先期間中かどうか
+pub fn is_window_open(priority_until: Option<Timestamp>, now: Timestamp) -> bool {
+    priority_until.is_some_and(|until| now.unix_timestamp() < until.unix_timestamp())
+}
+
+/// 後回しにした参加者を除きます
+pub fn exclude_deferred(user_ids: &mut HashSet<UserId>, deferred: &[UserId]) {
+    for user_id in deferred {
+        user_ids.remove(user_id);
+    }
+}
+
+/// 優先期間が終わったときの参加者の順番 (後回しにした参加者をリアクションした順に末尾へ加えます)
+pub fn release_order(order: &[UserId], deferred: &[UserId]) -> Vec<UserId> {
+    let mut released = order.to_vec();
+    for &user_id in deferred {
+        if !released.contains(&user_id) {
+            released.push(user_id);
+        }
+    }
+    released
+}
+
+/// リアクションしたユーザーが `role_id` を持っているか (確認できない場合は持っていないものとします)
+pub async fn has_role(api: &impl DiscordApi, reaction: &Reaction, role_id: RoleId) -> bool {
+    if let Some(member) = &reaction.member {
+        return member.roles.contains(&role_id);
+    }
+    let (Some(guild_id), Some(user_id)) = (reaction.guild_id, reaction.user_id) else {
+        return false;
+    };
+    match api.member(guild_id, user_id).await {
+        Ok(member) => member.roles.contains(&role_id),
+        Err(err) => {
+            eprintln!("Failed to check priority role: {err}");
+            false
+        }
+    }
+}
+
+/// 優先期間中のリアクションを後回しの参加者として記録し, 初回だけお知らせします
+///
+/// リアクションは残し, 優先期間が終わったときに参加者として数えます。
+pub async fn defer_participant(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &RecruitConfig,
+    policy: &NotificationPolicy,
+    message_id: MessageId,
+    channel_id: ChannelId,
+    user_id: UserId,
+) -> Result<(), Error> {
+    if !data.storage.add_deferred_participant(message_id, user_id)? {
+        return Ok(());
+    }
+    let (Some(role_id), Some(until)) = (config.priority_role, config.priority_until) else {
+        return Ok(());
+    };
+    let content = config.language().text(Text::PriorityDeferred {
+        user: &user_id.mention().to_string(),
+        role: &role_id.mention().to_string(),
+        until: until.unix_timestamp(),
+    });
+    policy
+        .send(
+            api,
+            data,
+            channel_id,
+            policy.message(&content, &Mentions::user(user_id)),
+        )
+        .await?;
+    Ok(())
+}
+
+/// 優先期間が終わった募集を全員に公開し, 後回しにした参加者を数えて人数が揃っていれば開始します
+pub async fn end_priority_window(
+    ctx: &Context,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+) -> Result<(), Error> {
+    let _guard = data.lock_message(message_id).await;
+    let deferred = data.storage.deferred_participants(message_id);
+    let edited =
+        recruit_edit::edit_recruit_message(ctx, data, channel_id, message_id, |recruit_message| {
+            let state = &recruit_message.state;
+            if state.cancelled
+                || (state.started && recruit_message.config.closes_on_start())
+                || state.closed_entries.is_some()
+            {
+                return None;
+            }
+            recruit_message.config.priority_until = None;
+            Some(())
+        })
+        .await?;
+    data.storage.remove_deferred_participants(message_id)?;
+    let Some((recruit_message, ())) = edited else {
+        return Ok(());
+    };
+    let message = channel_id.message(ctx, message_id).await?;
+
+    let mut config = recruit_message.config;
+    i18n::resolve_language(data, &mut config, guild_id);
+    if config.strict_order {
+        let order = release_order(&data.storage.participant_order(message_id), &deferred);
+        data.storage.set_participant_order(message_id, order)?;
+    }
+    let policy = NotificationPolicy::new(&config);
+    let content = config.language().text(Text::PriorityWindowEnded {
+        game_title: &config.game_title,
+    });
+    policy
+        .send(
+            ctx,
+            data,
+            channel_id,
+            policy.message(&content, &Mentions::none()),
+        )
+        .await?;
+
+    // 後回しにした参加者ですぐに人数が揃う場合があるため, 数え直します
+    let user_ids = collect_participants(ctx, data, &message, &config, guild_id).await?;
+    participant_count::store_count(data, message_id, user_ids.len());
+    if config.strict_order {
+        participant_order::record_reactor(data, message_id, None, &user_ids)?;
+    }
+    if config.required_players <= user_ids.len() {
+        begin_start(ctx, data, &config, &message, guild_id, user_ids).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn users(ids: &[u64]) -> Vec<UserId> {
+        ids.iter().copied().map(UserId::new).collect()
+    }
+
+    #[test]
+    fn window_closes_at_its_end() {
+        let at = |seconds: i64| Timestamp::from_unix_timestamp(1_700_000_000 + seconds).unwrap();
+        assert!(is_window_open(Some(at(60)), at(59)));
+        assert!(!is_window_open(Some(at(60)), at(60)));
+        assert!(!is_window_open(None, at(0)));
+    }
+
+    #[test]
+    fn deferred_participants_join_after_the_window() {
+        let mut user_ids: HashSet<UserId> = users(&[11, 12, 13]).into_iter().collect();
+        exclude_deferred(&mut user_ids, &users(&[12, 14]));
+        assert_eq!(user_ids, users(&[11, 13]).into_iter().collect());
+
+        // 後回しにした参加者は, すでに並んでいなければ末尾に加えます
+        assert_eq!(
+            release_order(&users(&[11, 13]), &users(&[14, 11, 12])),
+            users(&[11, 13, 14, 12])
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use std::fmt;
+
+use chrono_tz::Tz;
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::discord_error::is_unknown_entity;
+use crate::i18n::{self, Text};
+use crate::notification::{Mentions, NotificationKind, NotificationPolicy};
+use crate::recruit_message::RecruitConfig;
+use crate::scheduler::Job;
+use crate::{
+    Data, Error, parse_bot_recruit_message, recruit_edit, reminder, send_ephemeral, thread,
+    timezone,
+};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// 1 日のうちの時刻 (0 時からの分数, 設定ファイルでは `HH:MM`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(u32);
+
+impl TimeOfDay {
+    /// `HH:MM` を読み取ります
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let invalid = || format!("時刻は HH:MM の形式で指定してください: {input}");
+        let (hour, minute) = input.trim().split_once(':').ok_or_else(invalid)?;
+        let hour: u32 = hour.parse().map_err(|_| invalid())?;
+        let minute: u32 = minute.parse().map_err(|_| invalid())?;
+        if 24 <= hour || 60 <= minute {
+            return Err(invalid().into());
+        }
+        Ok(Self(hour * 60 + minute))
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        time.to_string()
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.0 / 60, self.0 % 60)
+    }
+}
+
+/// 開始通知などでメンションしないサイレント時間帯
+///
+/// 時刻はサーバーのタイムゾーンで扱います。`end` が `start` より前なら日付をまたぎます。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
+}
+
+/// `minute` (0 時からの分数) が `start` から `end` まで (`end` は含みません) に入るかどうか
+///
+/// `end` が `start` より前なら日付をまたぐ時間帯とし, 同じなら空の時間帯とします。
+pub fn is_within(start: u32, end: u32, minute: u32) -> bool {
+    if start <= end {
+        start <= minute && minute < end
+    } else {
+        start <= minute || minute < end
+    }
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: Timestamp, tz: Tz) -> bool {
+        is_within(self.start.0, self.end.0, local_minute(time, tz))
+    }
+
+    /// `time` より後で, 最初にサイレント時間帯が終わる日時
+    ///
+    /// 夏時間の切り替えをまたぐ場合も, `tz` で `end` の時刻になる日時とします。
+    pub fn end_after(&self, time: Timestamp, tz: Tz) -> Timestamp {
+        let local = timezone::to_local(tz, time.unix_timestamp());
+        let day_start = local.div_euclid(SECONDS_PER_DAY) * SECONDS_PER_DAY;
+        let end = i64::from(self.end.0) * 60;
+        let mut local_end = day_start + end;
+        if local_end <= local {
+            local_end += SECONDS_PER_DAY;
+        }
+        let mut seconds = timezone::to_utc(tz, local_end);
+        // 2 回ある時刻の早い方がすでに過ぎている場合は, 次の日の終わりとします
+        if seconds <= time.unix_timestamp() {
+            seconds = timezone::to_utc(tz, local_end + SECONDS_PER_DAY);
+        }
+        Timestamp::from_unix_timestamp(seconds).unwrap_or(time)
+    }
+}
+
+impl fmt::Display for QuietHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+fn local_minute(time: Timestamp, tz: Tz) -> u32 {
+    let local = timezone::to_local(tz, time.unix_timestamp());
+    (local.div_euclid(60).rem_euclid(i64::from(MINUTES_PER_DAY))) as u32
+}
+
+/// `now` がサーバーのサイレント時間帯なら, その時間帯を返します
+///
+/// `override_quiet_hours` を指定した募集では常に `None` を返します。
+pub fn active(
+    data: &Data,
+    guild_id: Option<GuildId>,
+    config: &RecruitConfig,
+    now: Timestamp,
+) -> Option<QuietHours> {
+    if config.override_quiet_hours {
+        return None;
+    }
+    let tz = timezone::guild_timezone(data, guild_id);
+    data.storage
+        .guild_settings(guild_id?)
+        .quiet_hours
+        .filter(|quiet_hours| quiet_hours.contains(now, tz))
+}
+
+/// サイレント時間帯に人数が揃った開始予定の日時がある募集で, ロールへのメンションを時間帯の終わりに予約します
+pub fn defer_role_ping(
+    data: &Data,
+    quiet_hours: QuietHours,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+) {
+    data.scheduler.enqueue(
+        &*data.storage,
+        quiet_hours.end_after(Timestamp::now(), timezone::guild_timezone(data, guild_id)),
+        Job::DeferredRolePing {
+            channel_id,
+            message_id,
+            guild_id,
+        },
+    );
+}
+
+/// サイレント時間帯に送らなかったロールへのメンションを送ります
+///
+/// 募集メッセージが削除された場合とキャンセルされた場合は送りません。
+pub async fn send_deferred_role_ping(
+    ctx: &Context,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+) -> Result<(), Error> {
+    let message = match channel_id.message(ctx, message_id).await {
+        Ok(message) => message,
+        Err(err) if is_unknown_entity(&err).is_some() => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    let Some(mut recruit_message) = parse_bot_recruit_message(ctx, data, &message) else {
+        return Ok(());
+    };
+    if recruit_message.state.cancelled {
+        return Ok(());
+    }
+    i18n::resolve_language(data, &mut recruit_message.config, guild_id);
+    let config = &recruit_message.config;
+    let policy = NotificationPolicy::new(config);
+    let (Some(guild_id), Some(start_time)) = (guild_id, config.start_time) else {
+        return Ok(());
+    };
+    let claim = data
+        .storage
+        .claim_role_pings(guild_id, &config.mention_roles, Timestamp::now())?;
+    if claim.allowed.is_empty() {
+        return Ok(());
+    }
+    let mut lines: Vec<String> = claim
+        .allowed
+        .iter()
+        .map(|role_id| role_id.mention().to_string())
+        .collect();
+    lines.push(config.language().text(Text::DeferredRolePing {
+        game_title: &config.game_title,
+        start_time: &reminder::format_start_time(start_time),
+        link: &message.link(),
+    }));
+    let content = lines.join("\n");
+    let sent = thread::retry_if_archived(ctx, channel_id, || {
+        channel_id.send_message(
+            ctx,
+            policy.create_message(
+                NotificationKind::Start,
+                &content,
+                &Mentions::none().with_roles(claim.allowed.iter().copied()),
+            ),
+        )
+    })
+    .await;
+    match sent {
+        Ok(Some(sent)) => {
+            policy.schedule_delete(data, &sent);
+            Ok(())
+        }
+        result => {
+            if let Err(err) = data.storage.release_role_pings(guild_id, &claim) {
+                eprintln!("Failed to release role pings: {err}");
+            }
+            result.map(|_| ()).map_err(Into::into)
+        }
+    }
+}
+
+/// 緊急の募集で, サーバーのサイレント時間帯を無視してメンションします
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "MANAGE_GUILD",
+    rename = "override_quiet_hours"
+)]
+pub async fn override_quiet_hours(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集メッセージのリンク"] message: Message,
+    #[description = "サイレント時間帯を無視する (既定: はい)"] enabled: Option<bool>,
+) -> Result<(), Error> {
+    let data = ctx.data();
+    let _guard = data.lock_message(message.id).await;
+    if parse_bot_recruit_message(ctx.serenity_context(), data, &message).is_none() {
+        send_ephemeral(ctx, "指定されたメッセージは募集メッセージではありません。").await?;
+        return Ok(());
+    }
+    let enabled = enabled.unwrap_or(true);
+    recruit_edit::edit_recruit_message(
+        ctx.serenity_context(),
+        data,
+        message.channel_id,
+        message.id,
+        |recruit_message| {
+            recruit_message.config.override_quiet_hours = enabled;
+            Some(())
+        },
+    )
+    .await?;
+    let reply = if enabled {
+        "この募集はサイレント時間帯でもメンションします"
+    } else {
+        "この募集はサイレント時間帯にメンションしません"
+    };
+    send_ephemeral(ctx, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiet_hours(start: &str, end: &str) -> QuietHours {
+        QuietHours {
+            start: TimeOfDay::parse(start).unwrap(),
+            end: TimeOfDay::parse(end).unwrap(),
+        }
+    }
+
+    #[test]
+    fn times_of_day_are_read_as_hh_mm() {
+        assert_eq!(TimeOfDay::parse(" 7:05 ").unwrap().to_string(), "07:05");
+        assert!(TimeOfDay::parse("24:00").is_err());
+        assert!(TimeOfDay::parse("12:60").is_err());
+        assert!(TimeOfDay::parse("1200").is_err());
+    }
+
+    #[test]
+    fn quiet_hours_may_cross_midnight() {
+        assert!(is_within(60, 120, 60));
+        assert!(!is_within(60, 120, 120));
+        assert!(is_within(23 * 60, 8 * 60, 0));
+        assert!(!is_within(23 * 60, 8 * 60, 12 * 60));
+        // 開始と終了が同じ時間帯は空です
+        assert!(!is_within(60, 60, 60));
+    }
+
+    #[test]
+    fn quiet_hours_end_in_the_server_timezone() {
+        // 2023-11-15 07:13:20 (日本時間)
+        let time = Timestamp::from_unix_timestamp(1_700_000_000).unwrap();
+        let quiet_hours = quiet_hours("23:00", "08:00");
+        assert!(quiet_hours.contains(time, Tz::Asia__Tokyo));
+        assert!(!quiet_hours.contains(time, Tz::UTC));
+        assert_eq!(
+            quiet_hours
+                .end_after(time, Tz::Asia__Tokyo)
+                .unix_timestamp(),
+            1_700_002_800
+        );
+        assert_eq!(quiet_hours.to_string(), "23:00-08:00");
+    }
+}
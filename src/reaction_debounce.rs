@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use poise::serenity_prelude::{MessageId, Reaction, ReactionType, UserId};
+use tokio::sync::Notify;
+
+/// リアクションの追加を受け取ったときの処理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    /// すぐに処理します
+    Process,
+    /// 直前にも操作があったため, まとめて後で処理します
+    Queued,
+}
+
+/// キーごとに, 短い間に続いた操作を最後の状態だけにまとめます
+///
+/// 最初の追加はすぐに処理し, `window` 以内に続いた追加は最初に保留した時刻から `window` 後に 1 回だけ処理します。
+/// 保留中に取り消された追加は処理しません。
+#[derive(Debug)]
+pub struct Debouncer<K, V> {
+    window: Duration,
+    /// 最後に操作があった時刻
+    last_seen: HashMap<K, Instant>,
+    /// 保留中の追加と, 処理する時刻
+    pending: HashMap<K, (V, Instant)>,
+}
+
+impl<K: Eq + Hash + Clone, V> Debouncer<K, V> {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_seen: HashMap::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// 追加を受け取ります
+    pub fn add(&mut self, key: K, value: V, now: Instant) -> Admission {
+        let recent = self
+            .last_seen
+            .insert(key.clone(), now)
+            .is_some_and(|last| now.duration_since(last) < self.window);
+        if let Some((pending, _)) = self.pending.get_mut(&key) {
+            // 処理する時刻は延ばさず, 最後の追加だけを残します
+            *pending = value;
+            return Admission::Queued;
+        }
+        if !recent {
+            return Admission::Process;
+        }
+        self.pending.insert(key, (value, now + self.window));
+        Admission::Queued
+    }
+
+    /// 取り消しを受け取り, 保留中の追加を処理しないようにします
+    pub fn remove(&mut self, key: K, now: Instant) {
+        self.pending.remove(&key);
+        self.last_seen.insert(key, now);
+    }
+
+    /// 処理する時刻になった追加を取り出します
+    pub fn take_due(&mut self, now: Instant) -> Vec<V> {
+        let due: Vec<K> = self
+            .pending
+            .iter()
+            .filter(|(_, (_, due_at))| *due_at <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        let values = due
+            .into_iter()
+            .filter_map(|key| self.pending.remove(&key))
+            .map(|(value, _)| value)
+            .collect();
+        let window = self.window;
+        let pending = &self.pending;
+        self.last_seen
+            .retain(|key, last| now.duration_since(*last) < window || pending.contains_key(key));
+        values
+    }
+
+    /// 次に処理する時刻
+    pub fn next_due(&self) -> Option<Instant> {
+        self.pending.values().map(|(_, due_at)| *due_at).min()
+    }
+}
+
+type ReactionKey = (MessageId, UserId, ReactionType);
+
+/// リアクションの連打をまとめるための, 共有する状態
+///
+/// 絵文字ごとに意味が異なるため, 募集メッセージとユーザーと絵文字の組ごとにまとめます。
+pub struct ReactionDebouncer {
+    debouncer: Mutex<Debouncer<ReactionKey, Reaction>>,
+    wake: Notify,
+}
+
+fn key(reaction: &Reaction) -> Option<ReactionKey> {
+    Some((
+        reaction.message_id,
+        reaction.user_id?,
+        reaction.emoji.clone(),
+    ))
+}
+
+impl ReactionDebouncer {
+    /// 同じユーザーの同じリアクションの操作を `window` の間まとめます
+    pub fn new(window: Duration) -> Self {
+        Self {
+            debouncer: Mutex::new(Debouncer::new(window)),
+            wake: Notify::new(),
+        }
+    }
+
+    /// リアクションの追加をすぐに処理するかどうか (後で処理する場合は保留します)
+    pub fn admit(&self, reaction: &Reaction) -> Admission {
+        let Some(key) = key(reaction) else {
+            return Admission::Process;
+        };
+        let admission = self
+            .debouncer
+            .lock()
+            .unwrap()
+            .add(key, reaction.clone(), Instant::now());
+        if admission == Admission::Queued {
+            self.wake.notify_one();
+        }
+        admission
+    }
+
+    /// リアクションが外されたら, 保留中の追加を取り消します
+    pub fn cancel(&self, reaction: &Reaction) {
+        if let Some(key) = key(reaction) {
+            self.debouncer.lock().unwrap().remove(key, Instant::now());
+        }
+    }
+
+    /// 処理する時刻になるまで待ち, 保留していた追加を取り出します
+    pub async fn next_batch(&self) -> Vec<Reaction> {
+        loop {
+            let next_due = {
+                let mut debouncer = self.debouncer.lock().unwrap();
+                let due = debouncer.take_due(Instant::now());
+                if !due.is_empty() {
+                    return due;
+                }
+                debouncer.next_due()
+            };
+            match next_due {
+                Some(due_at) => {
+                    tokio::select! {
+                        _ = tokio::time::sleep_until(due_at.into()) => {}
+                        _ = self.wake.notified() => {}
+                    }
+                }
+                None => self.wake.notified().await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(2);
+
+    #[test]
+    fn repeated_additions_are_processed_once_after_the_window() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(WINDOW);
+        assert_eq!(debouncer.add("a", 1, start), Admission::Process);
+        assert_eq!(debouncer.add("a", 2, start), Admission::Queued);
+        // 保留した後の追加は, 処理する時刻を延ばさずに最後の値だけを残します
+        assert_eq!(
+            debouncer.add("a", 3, start + Duration::from_secs(1)),
+            Admission::Queued
+        );
+        assert_eq!(debouncer.add("b", 4, start), Admission::Process);
+        assert_eq!(debouncer.next_due(), Some(start + WINDOW));
+        assert!(
+            debouncer
+                .take_due(start + Duration::from_secs(1))
+                .is_empty()
+        );
+        assert_eq!(debouncer.take_due(start + WINDOW), [3]);
+        assert_eq!(debouncer.next_due(), None);
+
+        // 間が空いた追加は, すぐに処理します
+        assert_eq!(
+            debouncer.add("a", 5, start + WINDOW * 3),
+            Admission::Process
+        );
+    }
+
+    #[test]
+    fn cancelled_additions_are_not_processed() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(WINDOW);
+        assert_eq!(debouncer.add("a", 1, start), Admission::Process);
+        assert_eq!(debouncer.add("a", 2, start), Admission::Queued);
+        debouncer.remove("a", start);
+        assert!(debouncer.take_due(start + WINDOW).is_empty());
+        // 取り消した直後の追加も, まとめる時間の間は保留します
+        debouncer.remove("b", start);
+        assert_eq!(debouncer.add("b", 3, start), Admission::Queued);
+    }
+}
@@ -0,0 +1,377 @@
+use poise::serenity_prelude::*;
+
+use crate::recruit_message::{
+    EmojiPair, GAME_OPTION_EMOJIS, SLOT_EMOJIS, is_same_emoji, parse_emoji,
+};
+use crate::{Data, Error, send_ephemeral};
+
+/// カスタム絵文字の名前の長さ (Discord の制限)
+const MIN_NAME_CHARS: usize = 2;
+const MAX_NAME_CHARS: usize = 32;
+/// 肌の色や ZWJ でつないだ絵文字を含めた, Unicode の絵文字の最大文字数
+const MAX_UNICODE_CHARS: usize = 16;
+const KEYCAP: char = '\u{20E3}';
+
+/// Bot が募集のリアクションに使う組み込みの絵文字 (`EngineConfig::emojis` で変えられます)
+///
+/// 参加と通知なしで参加の絵文字は, サーバーの `/settings emoji` や募集ごとの設定があればそちらを使います。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReactionEmojis {
+    /// 参加
+    pub participation: String,
+    /// 通知なしで参加
+    pub silent_participation: String,
+    /// 今すぐ開始
+    pub start: String,
+    /// 開始後に参加できなくなったことを知らせる
+    pub drop_out: String,
+}
+
+impl Default for ReactionEmojis {
+    fn default() -> Self {
+        Self {
+            participation: "✋".to_string(),
+            silent_participation: "🤚".to_string(),
+            start: "🔔".to_string(),
+            drop_out: "🙅".to_string(),
+        }
+    }
+}
+
+/// `/settings emoji` に入力された絵文字
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmojiInput {
+    /// Unicode の絵文字
+    Unicode(String),
+    /// `<:name:id>` または `<a:name:id>` のカスタム絵文字
+    Custom {
+        animated: bool,
+        id: EmojiId,
+        name: String,
+    },
+    /// `:name:` のカスタム絵文字の名前 (サーバーの絵文字から探します)
+    Name(String),
+}
+
+impl EmojiInput {
+    /// 入力された絵文字を読み取ります
+    ///
+    /// 前後の空白は無視します。カスタム絵文字は `<:name:id>`, `<a:name:id>`, `:name:` のいずれか,
+    /// それ以外は 1 つの Unicode の絵文字 (肌の色, ZWJ でつないだもの, 国旗, キーキャップを含む) のみ受け付けます。
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err("絵文字を指定してください".to_string());
+        }
+        if let Some(inner) = input.strip_prefix('<') {
+            let inner = inner
+                .strip_suffix('>')
+                .ok_or_else(|| format!("`{input}` はカスタム絵文字の形式ではありません"))?;
+            let mut parts = inner.split(':');
+            let animated = match parts.next() {
+                Some("") => false,
+                Some("a") => true,
+                _ => return Err(format!("`{input}` はカスタム絵文字の形式ではありません")),
+            };
+            let (Some(name), Some(id), None) = (parts.next(), parts.next(), parts.next()) else {
+                return Err(format!("`{input}` はカスタム絵文字の形式ではありません"));
+            };
+            let id = id
+                .parse::<u64>()
+                .ok()
+                .filter(|&id| id != 0)
+                .ok_or_else(|| format!("`{input}` の絵文字の ID が不正です"))?;
+            return Ok(EmojiInput::Custom {
+                animated,
+                id: EmojiId::new(id),
+                name: validate_name(name)?,
+            });
+        }
+        if let Some(name) = input
+            .strip_prefix(':')
+            .and_then(|name| name.strip_suffix(':'))
+        {
+            return Ok(EmojiInput::Name(validate_name(name)?));
+        }
+        if is_unicode_emoji(input) {
+            return Ok(EmojiInput::Unicode(input.to_string()));
+        }
+        Err(format!(
+            "`{input}` は絵文字として使えません (Unicode の絵文字, `:名前:`, `<:名前:ID>` のいずれかを指定してください)"
+        ))
+    }
+
+    /// `:name:` をサーバーの絵文字から探し, 募集設定に保存する形式にします
+    pub fn resolve(self, guild_emojis: &[Emoji]) -> Result<String, String> {
+        match self {
+            EmojiInput::Unicode(emoji) => Ok(emoji),
+            EmojiInput::Custom { animated, id, name } => Ok(format_custom(animated, id, &name)),
+            EmojiInput::Name(name) => guild_emojis
+                .iter()
+                .find(|emoji| emoji.name == name)
+                .map(|emoji| format_custom(emoji.animated, emoji.id, &emoji.name))
+                .ok_or_else(|| format!("このサーバーに :{name}: という絵文字はありません")),
+        }
+    }
+}
+
+fn format_custom(animated: bool, id: EmojiId, name: &str) -> String {
+    let prefix = if animated { "a" } else { "" };
+    format!("<{prefix}:{name}:{id}>")
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let chars = name.chars().count();
+    if !(MIN_NAME_CHARS..=MAX_NAME_CHARS).contains(&chars)
+        || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(format!(
+            "`{name}` はカスタム絵文字の名前として使えません ({MIN_NAME_CHARS}〜{MAX_NAME_CHARS} 文字の英数字と _)"
+        ));
+    }
+    Ok(name.to_string())
+}
+
+/// 絵文字に使われる記号の範囲
+fn is_emoji_char(c: char) -> bool {
+    matches!(c,
+        '\u{00A9}' | '\u{00AE}' | '\u{203C}' | '\u{2049}' | '\u{2122}' | '\u{2139}'
+        | '\u{2194}'..='\u{21AA}' | '\u{231A}'..='\u{23FF}' | '\u{24C2}'
+        | '\u{25AA}'..='\u{27BF}' | '\u{2934}' | '\u{2935}' | '\u{2B05}'..='\u{2B55}'
+        | '\u{3030}' | '\u{303D}' | '\u{3297}' | '\u{3299}'
+        | '\u{1F000}'..='\u{1FAFF}')
+}
+
+/// 絵文字を組み合わせるときにだけ使う文字 (異体字セレクタ, ZWJ, タグ)
+fn is_emoji_modifier(c: char) -> bool {
+    matches!(
+        c,
+        '\u{FE0F}' | '\u{200D}' | KEYCAP | '\u{E0020}'..='\u{E007F}'
+    )
+}
+
+/// 1 つの Unicode の絵文字として扱える文字列かどうか
+///
+/// 文字がすべて絵文字の記号か組み合わせ用の文字で, 少なくとも 1 つ絵文字の記号を含むものとします。
+/// `1️⃣` のようなキーキャップに限り, 数字と `#`, `*` を受け付けます。
+fn is_unicode_emoji(input: &str) -> bool {
+    let keycap = input.ends_with(KEYCAP);
+    let chars: Vec<char> = input.chars().collect();
+    if MAX_UNICODE_CHARS < chars.len() {
+        return false;
+    }
+    let keycap_base = |index: usize, c: char| {
+        keycap && index == 0 && (c.is_ascii_digit() || c == '#' || c == '*')
+    };
+    chars
+        .iter()
+        .enumerate()
+        .all(|(index, &c)| is_emoji_char(c) || is_emoji_modifier(c) || keycap_base(index, c))
+        && chars
+            .iter()
+            .enumerate()
+            .any(|(index, &c)| is_emoji_char(c) || keycap_base(index, c))
+}
+
+/// サーバーの既定の絵文字として使えるか, 形式とほかのリアクションとの重複を確認します
+///
+/// Bot が実際にリアクションを付けられるかは確認しません (`/settings emoji set` で確認します)。
+pub fn check_pair(pair: &EmojiPair, emojis: &ReactionEmojis) -> Result<(), String> {
+    for emoji in [&pair.participation, &pair.silent_participation] {
+        if matches!(EmojiInput::parse(emoji)?, EmojiInput::Name(_)) {
+            return Err(format!("{emoji} は `<:名前:ID>` の形式で指定してください"));
+        }
+    }
+    let participation = parse_emoji(&pair.participation);
+    let silent_participation = parse_emoji(&pair.silent_participation);
+    if is_same_emoji(&participation, &silent_participation) {
+        return Err("参加と通知なしで参加には異なる絵文字を指定してください".to_string());
+    }
+    let reserved = [emojis.start.as_str(), emojis.drop_out.as_str()];
+    let reserved = reserved
+        .iter()
+        .chain(&GAME_OPTION_EMOJIS)
+        .chain(&SLOT_EMOJIS)
+        .map(|emoji| parse_emoji(emoji));
+    for reserved in reserved {
+        for (input, emoji) in [
+            (&pair.participation, &participation),
+            (&pair.silent_participation, &silent_participation),
+        ] {
+            if is_same_emoji(emoji, &reserved) {
+                return Err(format!("{input} はほかのリアクションで使われています"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Bot がカスタム絵文字を使えるか, 確認用のメッセージに実際にリアクションを付けて確かめます
+///
+/// 確認用のメッセージはリアクションごとすぐに削除します。
+async fn check_usable(ctx: &Context, channel_id: ChannelId, emojis: &[&str]) -> Result<(), String> {
+    let message = channel_id
+        .say(ctx, "絵文字を確認しています…")
+        .await
+        .map_err(|err| {
+            eprintln!("Failed to post emoji check message: {err}");
+            "このチャンネルに確認用のメッセージを投稿できませんでした。Bot の権限を確認してください"
+                .to_string()
+        })?;
+    let mut result = Ok(());
+    for emoji in emojis {
+        if let Err(err) = message.react(ctx, parse_emoji(emoji)).await {
+            eprintln!("Failed to react with {emoji}: {err}");
+            result = Err(format!(
+                "Bot は {emoji} を使えません。Bot が参加しているサーバーの絵文字を指定してください"
+            ));
+            break;
+        }
+    }
+    if let Err(err) = message.delete(ctx).await {
+        eprintln!("Failed to delete emoji check message: {err}");
+    }
+    result
+}
+
+/// 新しい募集の参加のリアクションの絵文字を管理します
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "emoji",
+    subcommands("emoji_set", "emoji_clear")
+)]
+pub async fn settings_emoji(_ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// 参加と通知なしで参加のリアクションの絵文字を設定します
+#[poise::command(slash_command, guild_only, rename = "set")]
+async fn emoji_set(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "参加の絵文字 (例: ✅, :name:, <:name:id>)"] join: String,
+    #[description = "通知なしで参加の絵文字"] silent: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.defer_ephemeral().await?;
+    let guild_emojis = guild_id.emojis(ctx).await?;
+    let resolve = |name: &str, input: &str| {
+        EmojiInput::parse(input)
+            .and_then(|emoji| emoji.resolve(&guild_emojis))
+            .map_err(|err| format!("{name}: {err}"))
+    };
+    let pair = match (resolve("join", &join), resolve("silent", &silent)) {
+        (Ok(participation), Ok(silent_participation)) => EmojiPair {
+            participation,
+            silent_participation,
+        },
+        (Err(err), _) | (_, Err(err)) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+    };
+    if let Err(err) = check_pair(&pair, &ctx.data().config.emojis) {
+        send_ephemeral(ctx, &err).await?;
+        return Ok(());
+    }
+    let custom: Vec<&str> = [&pair.participation, &pair.silent_participation]
+        .into_iter()
+        .filter(|emoji| matches!(parse_emoji(emoji), ReactionType::Custom { .. }))
+        .map(String::as_str)
+        .collect();
+    if !custom.is_empty()
+        && let Err(err) = check_usable(ctx.serenity_context(), ctx.channel_id(), &custom).await
+    {
+        send_ephemeral(ctx, &err).await?;
+        return Ok(());
+    }
+    let content = format!(
+        "これから作成する募集では、{} で参加、{} で通知なしで参加します (投稿済みの募集はそのままです)",
+        pair.participation, pair.silent_participation
+    );
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.reaction_emoji = Some(pair);
+        })?;
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// 参加のリアクションの絵文字を ✋ と 🤚 に戻します
+#[poise::command(slash_command, guild_only, ephemeral, rename = "clear")]
+async fn emoji_clear(ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.reaction_emoji = None;
+        })?;
+    send_ephemeral(
+        ctx,
+        "これから作成する募集では、✋ で参加、🤚 で通知なしで参加します",
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(participation: &str, silent_participation: &str) -> EmojiPair {
+        EmojiPair {
+            participation: participation.to_string(),
+            silent_participation: silent_participation.to_string(),
+        }
+    }
+
+    #[test]
+    fn single_emojis_and_custom_emojis_are_accepted() {
+        for emoji in ["👍", "👍🏽", "👨‍👩‍👧", "🇯🇵", "1️⃣", "❤️"] {
+            assert_eq!(
+                EmojiInput::parse(emoji),
+                Ok(EmojiInput::Unicode(emoji.to_string())),
+                "{emoji}"
+            );
+        }
+        assert_eq!(
+            EmojiInput::parse(" <a:party_parrot:123> "),
+            Ok(EmojiInput::Custom {
+                animated: true,
+                id: EmojiId::new(123),
+                name: "party_parrot".to_string(),
+            })
+        );
+        assert_eq!(
+            EmojiInput::parse(":join:"),
+            Ok(EmojiInput::Name("join".to_string()))
+        );
+        for input in ["", "a", "1", "参加", "<:join:0>", "<:j:1>", ":参加:"] {
+            assert!(EmojiInput::parse(input).is_err(), "{input}");
+        }
+        assert!(EmojiInput::parse(&"👍".repeat(MAX_UNICODE_CHARS + 1)).is_err());
+    }
+
+    #[test]
+    fn custom_emojis_are_stored_as_mentions() {
+        let custom = EmojiInput::parse("<:join:123>").unwrap();
+        assert_eq!(custom.resolve(&[]), Ok("<:join:123>".to_string()));
+        // サーバーにない名前は使えません
+        assert!(EmojiInput::Name("join".to_string()).resolve(&[]).is_err());
+    }
+
+    #[test]
+    fn pairs_must_not_reuse_other_reactions() {
+        let emojis = ReactionEmojis::default();
+        assert!(check_pair(&pair("👍", "<:quiet:123>"), &emojis).is_ok());
+        assert!(check_pair(&pair("👍", "👍"), &emojis).is_err());
+        assert!(check_pair(&pair("👍", ":quiet:"), &emojis).is_err());
+        for reserved in ["🔔", "🙅", GAME_OPTION_EMOJIS[0], SLOT_EMOJIS[0]] {
+            assert!(
+                check_pair(&pair("👍", reserved), &emojis).is_err(),
+                "{reserved}"
+            );
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use poise::serenity_prelude::*;
+
+use crate::discord_api::DiscordApi;
+use crate::{Data, fetch_reaction_user_list};
+
+/// リアクションのイベントの項目を, どこから得るか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldSource<T> {
+    /// イベントに含まれている
+    Present(T),
+    /// イベントに含まれるメンバーの情報から得られる
+    FromMember(T),
+    /// REST API から取得し直す必要がある
+    Fetch,
+}
+
+impl<T: Copy> FieldSource<T> {
+    pub fn value(self) -> Option<T> {
+        match self {
+            FieldSource::Present(value) | FieldSource::FromMember(value) => Some(value),
+            FieldSource::Fetch => None,
+        }
+    }
+}
+
+/// リアクションのイベントに足りない項目を補う方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolutionPlan {
+    pub user: FieldSource<UserId>,
+    pub guild: FieldSource<GuildId>,
+}
+
+impl ResolutionPlan {
+    /// イベントの項目をそのまま使えるかどうか
+    pub fn is_complete(&self) -> bool {
+        matches!(self.user, FieldSource::Present(_))
+            && matches!(self.guild, FieldSource::Present(_))
+    }
+}
+
+/// `user_id` と `guild_id` がないイベントについて, 補う方法を決めます
+///
+/// サーバーのメッセージへのリアクションならメンバーの情報から補い, それもなければ取得し直します。
+pub fn plan(
+    user_id: Option<UserId>,
+    guild_id: Option<GuildId>,
+    member: Option<&Member>,
+) -> ResolutionPlan {
+    let user = match (user_id, member) {
+        (Some(user_id), _) => FieldSource::Present(user_id),
+        (None, Some(member)) => FieldSource::FromMember(member.user.id),
+        (None, None) => FieldSource::Fetch,
+    };
+    let guild = match (guild_id, member) {
+        (Some(guild_id), _) => FieldSource::Present(guild_id),
+        (None, Some(member)) => FieldSource::FromMember(member.guild_id),
+        (None, None) => FieldSource::Fetch,
+    };
+    ResolutionPlan { user, guild }
+}
+
+/// 項目が足りないリアクションのイベントを補った回数
+#[derive(Debug, Default)]
+pub struct ResolutionMetrics {
+    /// 補えた回数
+    resolved: AtomicUsize,
+    /// 補えずにユーザーごとの処理を省いた回数
+    unresolved: AtomicUsize,
+}
+
+impl fmt::Display for ResolutionMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "resolved={} unresolved={}",
+            self.resolved.load(Ordering::Relaxed),
+            self.unresolved.load(Ordering::Relaxed)
+        )
+    }
+}
+
+/// `user_id` や `guild_id` がないリアクションのイベントを補ったものを返します
+///
+/// ユーザーは, そのリアクションをつけたユーザーのうち参加した順に記録されていないユーザーが 1 人だけの場合に補います。
+/// サーバーはチャンネルから取得し, DM のチャンネルなら `None` のままにします。
+pub async fn resolve(
+    api: &impl DiscordApi,
+    data: &Data,
+    reaction: &Reaction,
+    message: &Message,
+) -> Reaction {
+    let plan = plan(
+        reaction.user_id,
+        reaction.guild_id,
+        reaction.member.as_ref(),
+    );
+    if plan.is_complete() {
+        return reaction.clone();
+    }
+    let mut resolved = reaction.clone();
+    resolved.user_id = match plan.user {
+        FieldSource::Fetch => fetch_new_reactor(api, data, reaction, message).await,
+        source => source.value(),
+    };
+    resolved.guild_id = match plan.guild {
+        FieldSource::Fetch => fetch_guild_id(api, message).await,
+        source => source.value(),
+    };
+
+    let metrics = &data.reaction_resolutions;
+    if resolved.user_id.is_some() {
+        metrics.resolved.fetch_add(1, Ordering::Relaxed);
+    } else {
+        metrics.unresolved.fetch_add(1, Ordering::Relaxed);
+    }
+    eprintln!(
+        "Resolved partial reaction on {} (user: {:?}, guild: {:?}) ({metrics})",
+        reaction.message_id, plan.user, plan.guild
+    );
+    resolved
+}
+
+/// リアクションをつけたユーザーのうち, 参加した順に記録されていないただ 1 人のユーザー
+async fn fetch_new_reactor(
+    api: &impl DiscordApi,
+    data: &Data,
+    reaction: &Reaction,
+    message: &Message,
+) -> Option<UserId> {
+    let bot_id = api.current_user_id();
+    let users = match fetch_reaction_user_list(api, message, reaction.emoji.clone(), |user| {
+        user.id != bot_id
+    })
+    .await
+    {
+        Ok(users) => users,
+        Err(err) => {
+            eprintln!("Failed to fetch reaction users to resolve reactor: {err}");
+            return None;
+        }
+    };
+    let recorded = data.storage.participant_order(message.id);
+    let mut candidates = users
+        .into_iter()
+        .map(|user| user.id)
+        .filter(|user_id| !recorded.contains(user_id));
+    match (candidates.next(), candidates.next()) {
+        (Some(user_id), None) => Some(user_id),
+        _ => None,
+    }
+}
+
+async fn fetch_guild_id(api: &impl DiscordApi, message: &Message) -> Option<GuildId> {
+    if message.guild_id.is_some() {
+        return message.guild_id;
+    }
+    match api.channel(message.channel_id).await {
+        Ok(channel) => channel.guild().map(|channel| channel.guild_id),
+        Err(err) => {
+            eprintln!("Failed to fetch channel to resolve guild: {err}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_discord;
+
+    const GUILD_ID: GuildId = GuildId::new(1);
+    const USER_ID: UserId = UserId::new(11);
+
+    #[test]
+    fn missing_fields_are_taken_from_the_member() {
+        let complete = plan(Some(USER_ID), Some(GUILD_ID), None);
+        assert!(complete.is_complete());
+
+        let member = mock_discord::member(GUILD_ID, USER_ID.get());
+        let from_member = plan(None, None, Some(&member));
+        assert!(!from_member.is_complete());
+        assert_eq!(from_member.user, FieldSource::FromMember(USER_ID));
+        assert_eq!(from_member.guild.value(), Some(GUILD_ID));
+
+        // メンバーの情報もなければ, 取得し直します
+        let fetch = plan(None, Some(GUILD_ID), None);
+        assert_eq!(fetch.user, FieldSource::Fetch);
+        assert_eq!(fetch.user.value(), None);
+        assert_eq!(fetch.guild, FieldSource::Present(GUILD_ID));
+    }
+}
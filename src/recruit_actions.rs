@@ -0,0 +1,208 @@
+use poise::serenity_prelude::*;
+
+use crate::discord_error::unknown_entity;
+use crate::{Data, Error, cancel, join_menu};
+
+const ACTION_PREFIX: &str = "recruit_action:";
+/// 投稿したときの返信のボタンを受け付ける秒数 (返信のトークンの有効期限)
+const ACTION_LIFETIME_SECONDS: i64 = 15 * 60;
+
+/// 投稿したときの返信のボタンの操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    /// 作成した人も参加します
+    Join,
+    /// 投稿した募集を取り消します
+    Cancel,
+}
+
+impl Action {
+    fn code(self) -> &'static str {
+        match self {
+            Action::Join => "join",
+            Action::Cancel => "cancel",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "join" => Some(Action::Join),
+            "cancel" => Some(Action::Cancel),
+            _ => None,
+        }
+    }
+}
+
+/// ボタンの custom_id に含める, 操作する募集と操作できるユーザー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ActionTarget {
+    action: Action,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    author_id: UserId,
+}
+
+impl ActionTarget {
+    fn custom_id(&self) -> String {
+        format!(
+            "{ACTION_PREFIX}{}:{}:{}:{}",
+            self.action.code(),
+            self.channel_id,
+            self.message_id,
+            self.author_id
+        )
+    }
+
+    fn parse(custom_id: &str) -> Option<Self> {
+        let mut parts = custom_id.strip_prefix(ACTION_PREFIX)?.split(':');
+        let action = Action::from_code(parts.next()?)?;
+        let mut next_id = || parts.next()?.parse::<u64>().ok().filter(|&id| id != 0);
+        Some(Self {
+            action,
+            channel_id: ChannelId::new(next_id()?),
+            message_id: MessageId::new(next_id()?),
+            author_id: UserId::new(next_id()?),
+        })
+    }
+}
+
+/// 募集を投稿したときの返信に付けるボタン
+///
+/// 複数のゲームの募集と枠のある募集は参加するゲームや枠を選ぶ必要があるため, 「自分も参加」は付けません。
+pub fn components(
+    message: &Message,
+    author_id: UserId,
+    option_reactions: bool,
+) -> Vec<CreateActionRow> {
+    let target = |action| ActionTarget {
+        action,
+        channel_id: message.channel_id,
+        message_id: message.id,
+        author_id,
+    };
+    let mut buttons = Vec::new();
+    if !option_reactions {
+        buttons.push(
+            CreateButton::new(target(Action::Join).custom_id())
+                .label("自分も参加")
+                .style(ButtonStyle::Primary),
+        );
+    }
+    buttons.push(
+        CreateButton::new(target(Action::Cancel).custom_id())
+            .label("取り消す")
+            .style(ButtonStyle::Danger),
+    );
+    vec![CreateActionRow::Buttons(buttons)]
+}
+
+/// 投稿したときの返信のボタンかどうか
+pub fn is_recruit_action_button(interaction: &ComponentInteraction) -> bool {
+    interaction.data.custom_id.starts_with(ACTION_PREFIX)
+}
+
+/// 返信を送ってから `now` までに, ボタンを受け付ける時間を過ぎたかどうか
+fn is_expired(sent_at: Timestamp, now: Timestamp) -> bool {
+    ACTION_LIFETIME_SECONDS <= now.unix_timestamp() - sent_at.unix_timestamp()
+}
+
+/// 投稿したときの返信のボタンを押したら, 参加または取り消しをします
+///
+/// 募集を作成した人以外の操作と, 返信から 15 分を過ぎた操作は受け付けません。
+pub async fn handle_recruit_action_button(
+    ctx: &Context,
+    data: &Data,
+    interaction: &ComponentInteraction,
+) -> Result<(), Error> {
+    let Some(target) = ActionTarget::parse(&interaction.data.custom_id) else {
+        return Ok(());
+    };
+    if interaction.user.id != target.author_id {
+        return respond(ctx, interaction, "募集を作成した人だけが操作できます").await;
+    }
+    if is_expired(interaction.message.timestamp, Timestamp::now()) {
+        // 返信のトークンが切れているため, ボタンを押したときのトークンで返信を更新します
+        interaction
+            .create_response(
+                ctx,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!(
+                            "{}\n(ボタンの有効期限が切れました)",
+                            interaction.message.content
+                        ))
+                        .components(Vec::new()),
+                ),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    match target.action {
+        Action::Join => {
+            let Some(guild_id) = interaction.guild_id else {
+                return Ok(());
+            };
+            interaction.defer_ephemeral(ctx).await?;
+            let content = join_menu::join(
+                ctx,
+                data,
+                guild_id,
+                target.channel_id,
+                target.message_id,
+                target.author_id,
+            )
+            .await?;
+            interaction
+                .create_followup(
+                    ctx,
+                    CreateInteractionResponseFollowup::new()
+                        .content(content)
+                        .ephemeral(true),
+                )
+                .await?;
+        }
+        Action::Cancel => {
+            interaction
+                .create_response(ctx, CreateInteractionResponse::Acknowledge)
+                .await?;
+            let content =
+                match cancel::cancel_recruit(ctx, data, target.channel_id, target.message_id).await
+                {
+                    Ok(true) => "募集を取り消しました",
+                    Ok(false) => "この募集はすでに開始または取り消されています",
+                    Err(err) if unknown_entity(&err).is_some() => {
+                        "募集メッセージは削除されています"
+                    }
+                    Err(err) => return Err(err),
+                };
+            interaction
+                .edit_response(
+                    ctx,
+                    EditInteractionResponse::new()
+                        .content(content)
+                        .components(Vec::new()),
+                )
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn respond(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    content: &str,
+) -> Result<(), Error> {
+    interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+    Ok(())
+}
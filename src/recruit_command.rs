@@ -0,0 +1,126 @@
+use poise::serenity_prelude::*;
+
+use crate::discord_error::unknown_entity;
+use crate::summary::{self, SummaryEntry};
+use crate::{
+    Data, Error, can_manage_recruit, cancel, parse_bot_recruit_message, recruit_picker,
+    send_ephemeral,
+};
+
+/// 募集を作成・管理します
+///
+/// Discord ではサブコマンドを持つコマンド自体は実行できないため, 作成は `/recruit create` で行います。
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands(
+        "crate::recruit_create",
+        "crate::recruit_voice",
+        "crate::recruit_test",
+        "crate::multi_game::recruit_multi",
+        "cancel",
+        "crate::description::describe_recruit",
+        "crate::recruit_status::recruit_status",
+        "list",
+        "crate::preset::preset_participants",
+        "crate::time_poll::recruit_poll",
+        "crate::slots::recruit_slots",
+        "crate::application::recruit_apply"
+    )
+)]
+pub async fn recruit(_ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// 募集をキャンセルします
+///
+/// `message` を省略すると, このチャンネルの受付中の募集から探します。
+#[poise::command(slash_command, guild_only, ephemeral, rename = "cancel")]
+async fn cancel(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集メッセージのリンク (省略するとこのチャンネルから探します)"]
+    message: Option<Message>,
+) -> Result<(), Error> {
+    recruit_picker::with_recruit(ctx, message, move |message| async move {
+        let data = ctx.data();
+        let Some(recruit_message) =
+            parse_bot_recruit_message(ctx.serenity_context(), data, &message)
+        else {
+            return Ok("指定されたメッセージは募集メッセージではありません。".to_string());
+        };
+        if !can_manage_recruit(ctx, &recruit_message.config) {
+            return Ok(
+                "主催者またはメッセージの管理権限を持つメンバーのみキャンセルできます。"
+                    .to_string(),
+            );
+        }
+        let content = match cancel::cancel_recruit(
+            ctx.serenity_context(),
+            data,
+            message.channel_id,
+            message.id,
+        )
+        .await
+        {
+            Ok(true) => "募集をキャンセルしました",
+            Ok(false) => "この募集はすでに開始またはキャンセルされています",
+            Err(err) if unknown_entity(&err).is_some() => "募集メッセージは削除されています",
+            Err(err) => return Err(err),
+        };
+        Ok(content.to_string())
+    })
+    .await
+}
+
+/// このサーバーの受付中の募集を, 新しい順に表示します
+#[poise::command(slash_command, guild_only, ephemeral, rename = "list")]
+async fn list(ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.defer_ephemeral().await?;
+    let entries = summary::collect_entries(ctx.serenity_context(), ctx.data(), guild_id).await;
+    send_ephemeral(ctx, &list_content(guild_id, entries)).await?;
+    Ok(())
+}
+
+/// `/recruit list` の表示 (新しい募集から並べます)
+fn list_content(guild_id: GuildId, mut entries: Vec<SummaryEntry>) -> String {
+    if entries.is_empty() {
+        return "受付中の募集はありません".to_string();
+    }
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.message_id));
+    let entries: Vec<&SummaryEntry> = entries.iter().collect();
+    summary::render_entries(guild_id, &entries).join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message_id: u64, game_title: &str, count: Option<usize>) -> SummaryEntry {
+        SummaryEntry {
+            channel_id: ChannelId::new(2),
+            message_id: MessageId::new(message_id),
+            game_title: game_title.to_string(),
+            count,
+            required_players: 4,
+            start_time: None,
+        }
+    }
+
+    #[test]
+    fn list_shows_newest_recruits_first() {
+        let guild_id = GuildId::new(1);
+        assert_eq!(
+            list_content(guild_id, Vec::new()),
+            "受付中の募集はありません"
+        );
+        let content = list_content(
+            guild_id,
+            vec![entry(10, "Old", Some(1)), entry(20, "New", None)],
+        );
+        assert_eq!(
+            content,
+            "- **New** ?/4 https://discord.com/channels/1/2/20\n- **Old** 1/4 https://discord.com/channels/1/2/10"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use poise::serenity_prelude::*;
+
+use crate::config_storage;
+use crate::discord_api::DiscordApi;
+use crate::recruit_message::RecruitMessage;
+use crate::{Data, Error};
+
+/// 読み取ってから書き込むまでに本文が変わっていた場合に, 読み取り直す回数
+const CONFLICT_RETRIES: usize = 1;
+
+/// 最新の本文を読み取って書き換えます
+///
+/// `prepare` で最新のメッセージから書き込む内容を決め (`None` なら書き換えません),
+/// 書き込む直前にもう一度読み取って本文が変わっていなければ, `commit` で作った内容で書き換えます。
+/// 本文が変わっていた場合は, 他の変更を消さないよう `CONFLICT_RETRIES` 回まで読み取りからやり直します。
+/// `commit` は書き込むと決まってから 1 回だけ呼ぶため, 保存ファイルへの保存などの副作用を伴ってもかまいません。
+pub async fn read_modify_write<R>(
+    api: &impl DiscordApi,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    mut prepare: impl FnMut(&Message) -> Result<Option<R>, Error>,
+    commit: impl FnOnce(&R) -> Result<EditMessage, Error>,
+) -> Result<Option<(R, Option<Message>)>, Error> {
+    let mut attempt = 0;
+    let prepared = loop {
+        let message = api.message(channel_id, message_id).await?;
+        let Some(prepared) = prepare(&message)? else {
+            return Ok(None);
+        };
+        if attempt == CONFLICT_RETRIES {
+            break prepared;
+        }
+        let latest = api.message(channel_id, message_id).await?;
+        if latest.content == message.content {
+            break prepared;
+        }
+        eprintln!("Message {message_id} changed while editing, reading it again");
+        attempt += 1;
+    };
+    let edit = commit(&prepared)?;
+    let edited = api.edit_message(channel_id, message_id, edit).await?;
+    Ok(Some((prepared, edited)))
+}
+
+/// 募集メッセージの募集設定と状態を, 最新の内容に `transform` を適用して書き換えます
+///
+/// 募集メッセージを書き換える処理はすべてこの関数を通し, 読み取った後の他の処理の変更を消さないようにします。
+/// 同じ Bot の中で同時に書き換えないよう, 呼び出す前に `Data::lock_message` でロックしてください。
+/// `transform` が `None` を返した場合 (すでに終わった募集など) は書き換えません。
+pub async fn edit_recruit_message<T>(
+    api: &impl DiscordApi,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    mut transform: impl FnMut(&mut RecruitMessage) -> Option<T>,
+) -> Result<Option<(RecruitMessage, T)>, Error> {
+    let edited = read_modify_write(
+        api,
+        channel_id,
+        message_id,
+        |message| {
+            let mut recruit_message = config_storage::load(data, message)?;
+            Ok(transform(&mut recruit_message).map(|value| (recruit_message, value)))
+        },
+        |(recruit_message, _)| {
+            let content = config_storage::store(data, message_id, recruit_message)?;
+            Ok(EditMessage::new().content(content))
+        },
+    )
+    .await?;
+    Ok(edited.map(|(edited, _)| edited))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_discord::{self, MockDiscord};
+
+    const CHANNEL_ID: ChannelId = ChannelId::new(1);
+    const MESSAGE_ID: MessageId = MessageId::new(2);
+
+    #[tokio::test]
+    async fn changes_during_an_edit_are_read_again() {
+        let api = MockDiscord::new();
+        api.insert_message(mock_discord::message(CHANNEL_ID, MESSAGE_ID, "1"));
+        let mut read = Vec::new();
+        let edited = read_modify_write(
+            &api,
+            CHANNEL_ID,
+            MESSAGE_ID,
+            |message| {
+                read.push(message.content.clone());
+                // 最初に読み取った後に, 他の処理が書き換えます
+                if read.len() == 1 {
+                    api.insert_message(mock_discord::message(CHANNEL_ID, MESSAGE_ID, "2"));
+                }
+                Ok(Some(format!("{}+", message.content)))
+            },
+            |content| Ok(EditMessage::new().content(content)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(read, ["1", "2"]);
+        let (content, message) = edited.unwrap();
+        assert_eq!(content, "2+");
+        assert_eq!(message.unwrap().content, "2+");
+
+        // 書き換えない場合は, 書き込みません
+        let skipped = read_modify_write(
+            &api,
+            CHANNEL_ID,
+            MESSAGE_ID,
+            |_| Ok(None::<String>),
+            |_| unreachable!(),
+        )
+        .await
+        .unwrap();
+        assert!(skipped.is_none());
+    }
+}
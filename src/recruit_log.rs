@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+
+use poise::CreateReply;
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::close_reason::CloseReason;
+use crate::member_work;
+use crate::recruit_message::RecruitConfig;
+use crate::{Data, Error, send_ephemeral};
+
+/// 1 つの募集に残す出来事の件数 (超えた分は古いものから消します)
+pub const MAX_EVENTS_PER_RECRUIT: usize = 100;
+/// サーバーごとに記録を残す募集の数 (超えた分は最後の出来事が古い募集から消します)
+pub const MAX_RECRUITS_PER_GUILD: usize = 1000;
+/// 最後の出来事からこの日数が過ぎた募集の記録は消します
+pub const RETENTION_DAYS: i64 = 90;
+const EMBED_DESCRIPTION_LIMIT: usize = 4096;
+
+/// 参加者が変わった出来事
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecruitEventKind {
+    /// 開始通知を送った (ロビーの開始を含みます)
+    Started,
+    /// 開始以外の理由で募集が終わった
+    Closed { reason: CloseReason },
+    /// 定員を超えてリアクションしたため, 参加者として数えなかった
+    OverCapacity { user_id: UserId },
+    /// サーバーを抜けたため, 参加者から除いた
+    MemberLeft { user_id: UserId },
+    /// 開始した後に 🙅 で抜けた
+    DroppedOut { user_id: UserId },
+}
+
+/// 募集の記録の 1 件 (保存した後は書き換えません)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecruitEvent {
+    pub at: Timestamp,
+    pub event: RecruitEventKind,
+    /// 出来事の直後の参加者 (参加した順)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub participants: Vec<UserId>,
+}
+
+/// 出来事を追加し, `MAX_EVENTS_PER_RECRUIT` を超えた分を古いものから消します
+pub fn push_bounded(events: &mut Vec<RecruitEvent>, event: RecruitEvent) {
+    events.push(event);
+    if MAX_EVENTS_PER_RECRUIT < events.len() {
+        events.drain(..events.len() - MAX_EVENTS_PER_RECRUIT);
+    }
+}
+
+/// サーバーの記録から, `RETENTION_DAYS` が過ぎた募集と `MAX_RECRUITS_PER_GUILD` を超えた募集を消します
+pub fn prune(logs: &mut HashMap<MessageId, Vec<RecruitEvent>>, now: Timestamp) {
+    let expires_before = now.unix_timestamp() - RETENTION_DAYS * 24 * 60 * 60;
+    let last_at = |events: &[RecruitEvent]| {
+        events
+            .last()
+            .map_or(i64::MIN, |event| event.at.unix_timestamp())
+    };
+    logs.retain(|_, events| expires_before <= last_at(events));
+    if logs.len() <= MAX_RECRUITS_PER_GUILD {
+        return;
+    }
+    let mut recruits: Vec<(i64, MessageId)> = logs
+        .iter()
+        .map(|(&message_id, events)| (last_at(events), message_id))
+        .collect();
+    recruits.sort_unstable();
+    for (_, message_id) in &recruits[..recruits.len() - MAX_RECRUITS_PER_GUILD] {
+        logs.remove(message_id);
+    }
+}
+
+/// 募集の参加者が変わったことを, その時点の参加者とともに記録します
+///
+/// 場所を保存していない募集と動作確認用の募集は記録しません。
+pub fn record(
+    data: &Data,
+    message_id: MessageId,
+    config: Option<&RecruitConfig>,
+    event: RecruitEventKind,
+    participants: Vec<UserId>,
+) {
+    if config.is_some_and(|config| config.simulation) {
+        return;
+    }
+    let Some(guild_id) = data
+        .storage
+        .recruit_location(message_id)
+        .and_then(|location| location.guild_id)
+    else {
+        return;
+    };
+    let event = RecruitEvent {
+        at: Timestamp::now(),
+        event,
+        participants,
+    };
+    if let Err(err) = data
+        .storage
+        .append_recruit_event(guild_id, message_id, event)
+    {
+        eprintln!("Failed to record recruit event of {message_id}: {err}");
+    }
+}
+
+/// 記録の 1 件を 1 行にします (`name` はユーザーの表示に使います)
+pub fn render_event(event: &RecruitEvent, name: impl Fn(UserId) -> String) -> String {
+    let what = match &event.event {
+        RecruitEventKind::Started => "開始".to_string(),
+        RecruitEventKind::Closed { reason } => format!("終了 ({reason})"),
+        RecruitEventKind::OverCapacity { user_id } => {
+            format!("定員を超えたため数えなかった: {}", name(*user_id))
+        }
+        RecruitEventKind::MemberLeft { user_id } => {
+            format!("サーバーを抜けたため除いた: {}", name(*user_id))
+        }
+        RecruitEventKind::DroppedOut { user_id } => {
+            format!("開始後に抜けた: {}", name(*user_id))
+        }
+    };
+    let participants: Vec<String> = event.participants.iter().map(|&id| name(id)).collect();
+    format!(
+        "{} {what} / 参加者 {} 人: {}",
+        event.at,
+        participants.len(),
+        if participants.is_empty() {
+            "-".to_string()
+        } else {
+            participants.join(", ")
+        }
+    )
+}
+
+/// 記録に出てくるユーザーの表示名を調べます (調べられなければ ID のみ)
+async fn resolve_names(
+    ctx: &Context,
+    data: &Data,
+    guild_id: GuildId,
+    events: &[RecruitEvent],
+) -> HashMap<UserId, String> {
+    let mut user_ids = Vec::new();
+    for event in events {
+        let user_id = match event.event {
+            RecruitEventKind::OverCapacity { user_id }
+            | RecruitEventKind::MemberLeft { user_id }
+            | RecruitEventKind::DroppedOut { user_id } => Some(user_id),
+            RecruitEventKind::Started | RecruitEventKind::Closed { .. } => None,
+        };
+        for &user_id in user_id.iter().chain(&event.participants) {
+            if !user_ids.contains(&user_id) {
+                user_ids.push(user_id);
+            }
+        }
+    }
+    let names = member_work::display_names(ctx, data, guild_id, "Recruit log", &user_ids).await;
+    user_ids
+        .into_iter()
+        .map(|user_id| {
+            let name = match names.get(&user_id) {
+                Some(name) => format!("{name} ({user_id})"),
+                None => user_id.to_string(),
+            };
+            (user_id, name)
+        })
+        .collect()
+}
+
+/// 募集メッセージのリンクまたは ID を読み取ります
+fn parse_message_id(input: &str) -> Option<MessageId> {
+    let input = input.trim();
+    let id = input.rsplit('/').next().unwrap_or(input);
+    id.parse::<u64>()
+        .ok()
+        .filter(|&id| id != 0)
+        .map(MessageId::new)
+}
+
+/// 募集の参加者が変わった記録を古い順に表示します (管理者向け)
+///
+/// 削除された募集も, メッセージのリンクか ID で表示できます。
+#[poise::command(
+    slash_command,
+    guild_only,
+    ephemeral,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn recruit_log(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集メッセージのリンクまたは ID"] message: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let Some(message_id) = parse_message_id(&message) else {
+        send_ephemeral(ctx, "募集メッセージのリンクまたは ID を指定してください").await?;
+        return Ok(());
+    };
+    let events = ctx.data().storage.recruit_events(guild_id, message_id);
+    if events.is_empty() {
+        send_ephemeral(ctx, "この募集の記録はありません").await?;
+        return Ok(());
+    }
+    ctx.defer_ephemeral().await?;
+
+    let names = resolve_names(ctx.serenity_context(), ctx.data(), guild_id, &events).await;
+    let name = |user_id: UserId| {
+        names
+            .get(&user_id)
+            .cloned()
+            .unwrap_or_else(|| user_id.to_string())
+    };
+    let lines: Vec<String> = events
+        .iter()
+        .map(|event| render_event(event, name))
+        .collect();
+    let log = lines.join("\n");
+    let title = format!("募集 {message_id} の記録 ({} 件)", events.len());
+    let reply = if log.chars().count() <= EMBED_DESCRIPTION_LIMIT {
+        CreateReply::default().embed(CreateEmbed::new().title(title).description(log))
+    } else {
+        CreateReply::default()
+            .content(format!("{title}\n全文は添付ファイルを参照してください"))
+            .attachment(CreateAttachment::bytes(
+                format!("{title}\n{log}\n").into_bytes(),
+                "recruit_log.txt",
+            ))
+    };
+    ctx.send(reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(at: i64) -> RecruitEvent {
+        RecruitEvent {
+            at: Timestamp::from_unix_timestamp(at).unwrap(),
+            event: RecruitEventKind::Started,
+            participants: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn push_bounded_keeps_newest_events() {
+        let mut events = Vec::new();
+        for at in 0..MAX_EVENTS_PER_RECRUIT as i64 + 5 {
+            push_bounded(&mut events, event(at));
+        }
+        assert_eq!(events.len(), MAX_EVENTS_PER_RECRUIT);
+        assert_eq!(events[0], event(5));
+    }
+
+    #[test]
+    fn prune_expires_and_caps_recruits() {
+        let day = 24 * 60 * 60;
+        let now = 1_700_000_000;
+        let mut logs = HashMap::from([
+            (
+                MessageId::new(1),
+                vec![event(now - (RETENTION_DAYS + 1) * day)],
+            ),
+            (
+                MessageId::new(2),
+                vec![event(now - (RETENTION_DAYS + 1) * day), event(now - day)],
+            ),
+        ]);
+        prune(&mut logs, Timestamp::from_unix_timestamp(now).unwrap());
+        assert_eq!(logs.keys().collect::<Vec<_>>(), vec![&MessageId::new(2)]);
+
+        let mut logs: HashMap<MessageId, Vec<RecruitEvent>> = (1..=MAX_RECRUITS_PER_GUILD as u64
+            + 2)
+            .map(|id| (MessageId::new(id), vec![event(now - 1000 + id as i64)]))
+            .collect();
+        prune(&mut logs, Timestamp::from_unix_timestamp(now).unwrap());
+        assert_eq!(logs.len(), MAX_RECRUITS_PER_GUILD);
+        assert!(!logs.contains_key(&MessageId::new(1)));
+        assert!(!logs.contains_key(&MessageId::new(2)));
+        assert!(logs.contains_key(&MessageId::new(3)));
+    }
+
+    #[test]
+    fn events_are_rendered_with_participant_names() {
+        let started = RecruitEvent {
+            event: RecruitEventKind::OverCapacity {
+                user_id: UserId::new(13),
+            },
+            participants: vec![UserId::new(11), UserId::new(12)],
+            ..event(0)
+        };
+        let name = |user_id: UserId| format!("user{user_id}");
+        assert!(
+            render_event(&started, name)
+                .ends_with(" 定員を超えたため数えなかった: user13 / 参加者 2 人: user11, user12")
+        );
+        assert!(render_event(&event(0), name).ends_with(" 開始 / 参加者 0 人: -"));
+    }
+
+    #[test]
+    fn message_links_and_ids_are_read() {
+        assert_eq!(
+            parse_message_id(" https://discord.com/channels/1/2/3 "),
+            Some(MessageId::new(3))
+        );
+        assert_eq!(parse_message_id("3"), Some(MessageId::new(3)));
+        assert_eq!(parse_message_id("0"), None);
+        assert_eq!(parse_message_id("募集"), None);
+    }
+}
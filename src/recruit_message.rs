@@ -0,0 +1,1195 @@
+use std::fmt;
+
+use poise::serenity_prelude::{ChannelId, Mentionable, ReactionType, RoleId, Timestamp, UserId};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::DEFAULT_DELETE_AFTER_MINUTES;
+use crate::i18n::{Language, Text};
+use crate::reaction_emoji::ReactionEmojis;
+
+/// 複数のゲームから選ぶ募集で, 選択肢ごとに Bot が付けるリアクション
+pub const GAME_OPTION_EMOJIS: [&str; 3] = ["1️⃣", "2️⃣", "3️⃣"];
+/// 枠のある募集で, 枠ごとに Bot が付けるリアクション
+pub const SLOT_EMOJIS: [&str; 5] = ["🟥", "🟦", "🟩", "🟨", "🟪"];
+/// 募集メッセージから読み取るゲーム名の最大文字数 (超えた分は省略します)
+pub const MAX_GAME_TITLE_CHARS: usize = 100;
+/// 募集メッセージから読み取る `required_players` と `max_players` の上限
+pub const MAX_PLAYERS: usize = 100;
+/// `require_voice_at_start` の募集で, VC に参加者が揃うのを待つ分数の既定値
+pub const DEFAULT_VOICE_GRACE_MINUTES: u64 = 10;
+const UNTITLED_GAME: &str = "(ゲーム名なし)";
+
+/// 募集メッセージの toml ブロックに記述する募集設定
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecruitConfig {
+    pub game_title: String,
+    pub required_players: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_players: Option<usize>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub enforce_cap: bool,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_id"
+    )]
+    pub organizer: Option<UserId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 開始時にメンションするロール (旧形式の `mention_role = <id>` も読み取ります)
+    #[serde(
+        default,
+        alias = "mention_role",
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_ids",
+        deserialize_with = "deserialize_one_or_many"
+    )]
+    pub mention_roles: Vec<RoleId>,
+    #[serde(
+        default = "default_notify_on_reaction",
+        skip_serializing_if = "is_default_notify_on_reaction"
+    )]
+    pub notify_on_reaction: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub auto_assign_role_on_reaction: bool,
+    #[serde(
+        default = "default_delete_after_minutes",
+        skip_serializing_if = "is_default_delete_after_minutes"
+    )]
+    pub delete_after_minutes: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_delay_minutes: Option<u64>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_id"
+    )]
+    pub count_voice_channel: Option<ChannelId>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub require_undeafened: bool,
+    /// 通知でメンションしない動作確認用の募集かどうか
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub simulation: bool,
+    /// 参加・開始以外の追加のリアクション
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_reactions: Vec<ExtraReaction>,
+    /// 開始後に募集メッセージを削除するまでの分数 (ピン留めした募集は削除しません)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cleanup_after_start_minutes: Option<u64>,
+    /// 参加者があと 1 人になったときに `organizer` に通知するかどうか
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub notify_organizer_when_close: bool,
+    /// リアクションを処理した順に先着 `max_players` 人を参加者とするかどうか
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub strict_order: bool,
+    /// 複数のゲームから選ぶ募集の選択肢 (空なら 1 つのゲームの募集)
+    ///
+    /// 選択肢ごとに人数を数え, 最初に人数が揃った選択肢で開始して募集を締め切ります。
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub game_options: Vec<GameOption>,
+    /// `/settings category` で登録した募集のカテゴリ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// 開始予定の日時
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<Timestamp>,
+    /// 人数が揃った募集で, 開始予定の何分前に参加者へ DM でリマインドするか
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remind_before_minutes: Option<u64>,
+    /// 募集メッセージと通知の言語 (未指定の場合はサーバーの言語)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<Language>,
+    /// 人数が揃ったら開始通知の前に参加者の点呼をとる場合の, 点呼の締切までの分数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roll_call_minutes: Option<u64>,
+    /// 優先期間中に先に参加できるロール
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_id"
+    )]
+    pub priority_role: Option<RoleId>,
+    /// 優先期間の終わり (優先期間が終わると取り除きます)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority_until: Option<Timestamp>,
+    /// 定員より多く集まったときに, 開始通知でメンションする参加者
+    #[serde(default, skip_serializing_if = "is_default_over_mention")]
+    pub over_mention: OverMention,
+    /// `required_players` 人集まるごとにロビーを分けて開始し, 募集を続けるかどうか
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub multi_lobby: bool,
+    /// サーバーのサイレント時間帯でもメンションするかどうか (`/override_quiet_hours`)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub override_quiet_hours: bool,
+    /// 主催者が事前に参加者として登録したユーザー (リアクションがなくても数えます)
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_ids"
+    )]
+    pub preset_participants: Vec<UserId>,
+    /// 参加をリアクションとボタンのどちらで受け付けるか
+    #[serde(default, skip_serializing_if = "is_default_participation_mode")]
+    pub participation_mode: ParticipationMode,
+    /// 開始時に入っている参加者だけで開始する VC (`/require_voice`)
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_id"
+    )]
+    pub require_voice_at_start: Option<ChannelId>,
+    /// VC にいる参加者が足りないときに, 揃うのを待つ分数 (未指定なら `DEFAULT_VOICE_GRACE_MINUTES`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice_grace_minutes: Option<u64>,
+    /// 枠のある募集の枠 (空なら枠のない募集)
+    ///
+    /// 参加者は 1 人 1 枠までで, すべての枠が埋まると開始して募集を締め切ります。
+    /// `required_players` は枠の人数の合計です。
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub slots: Vec<Slot>,
+    /// 参加と通知なしで参加のリアクションの絵文字 (未指定なら ✋ と 🤚)
+    ///
+    /// 投稿したときのサーバーの `/settings emoji` を保存するため, 設定を変えても投稿済みの募集はそのまま数えます。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reaction_emoji: Option<EmojiPair>,
+    /// 参加する前に答える質問 (`/recruit apply`)
+    ///
+    /// 質問のある募集はボタンでのみ応募を受け付け, 主催者が承認した応募者だけを参加者として数えます。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub application_question: Option<String>,
+}
+
+/// 参加を受け付ける方法
+///
+/// Bot がチャンネルでリアクションを付けられない場合は, 投稿したときに `Buttons` に切り替えます。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParticipationMode {
+    /// 募集メッセージのリアクションで参加します
+    #[default]
+    Reactions,
+    /// 募集メッセージのボタンで参加します (Bot はリアクションを付けません)
+    Buttons,
+}
+
+/// 定員より多く集まったときに, 開始通知でメンションする参加者
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, poise::ChoiceParameter,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum OverMention {
+    /// 先着で定員までをメンションし, 残りを補欠とします
+    #[default]
+    #[name = "先着の定員まで"]
+    First,
+    /// 集まった全員をメンションします
+    #[name = "全員"]
+    All,
+}
+
+impl OverMention {
+    pub fn code(self) -> &'static str {
+        match self {
+            OverMention::First => "first",
+            OverMention::All => "all",
+        }
+    }
+}
+
+/// 募集設定を読み取るときに直した内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigWarning {
+    /// ゲーム名から見えない文字を取り除きました
+    InvisibleCharacters { field: &'static str },
+    /// ゲーム名が空になったため仮の名前にしました
+    EmptyTitle { field: &'static str },
+    /// ゲーム名が長すぎるため省略しました
+    TitleTruncated { field: &'static str, chars: usize },
+    /// 人数が多すぎるため上限にしました
+    PlayersClamped { field: &'static str, value: usize },
+}
+
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigWarning::InvisibleCharacters { field } => {
+                write!(f, "{field} から見えない文字を取り除きました")
+            }
+            ConfigWarning::EmptyTitle { field } => {
+                write!(f, "{field} が空のため「{UNTITLED_GAME}」としました")
+            }
+            ConfigWarning::TitleTruncated { field, chars } => write!(
+                f,
+                "{field} が長すぎるため省略しました ({chars} 文字, 上限 {MAX_GAME_TITLE_CHARS} 文字)"
+            ),
+            ConfigWarning::PlayersClamped { field, value } => {
+                write!(
+                    f,
+                    "{field} = {value} は多すぎるため {MAX_PLAYERS} にしました"
+                )
+            }
+        }
+    }
+}
+
+impl RecruitConfig {
+    /// 古いメッセージや手動で編集された募集設定を, 通知を壊さない値に直します
+    ///
+    /// ゲーム名の前後の空白と見えない文字を取り除いて長すぎる場合は省略し, 人数は上限に収めます。
+    /// エラーにはせず, 直した内容を返します。
+    pub fn validate(mut self) -> (RecruitConfig, Vec<ConfigWarning>) {
+        let mut warnings = Vec::new();
+        self.game_title = validate_title(&self.game_title, "game_title", &mut warnings);
+        for option in &mut self.game_options {
+            option.game_title =
+                validate_title(&option.game_title, "game_options.game_title", &mut warnings);
+            option.required_players = clamp_players(
+                option.required_players,
+                "game_options.required_players",
+                &mut warnings,
+            );
+        }
+        for slot in &mut self.slots {
+            slot.count = clamp_players(slot.count, "slots.count", &mut warnings);
+        }
+        self.required_players =
+            clamp_players(self.required_players, "required_players", &mut warnings);
+        self.max_players = self
+            .max_players
+            .map(|max_players| clamp_players(max_players, "max_players", &mut warnings));
+        (self, warnings)
+    }
+}
+
+/// 幅のない文字や書字方向の制御文字など, 表示されない文字かどうか
+///
+/// 絵文字の結合に使うゼロ幅接合子 (U+200D) は, 前後が ASCII 以外の文字の場合は残します。
+fn is_invisible(text: &[char], index: usize) -> bool {
+    match text[index] {
+        '\u{200B}' | '\u{200C}' | '\u{2060}' | '\u{FEFF}' | '\u{180E}' | '\u{00AD}' => true,
+        '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' => true,
+        '\u{200D}' => {
+            let joins = |c: Option<&char>| c.is_some_and(|c| !c.is_ascii() && !c.is_whitespace());
+            !(joins(index.checked_sub(1).and_then(|i| text.get(i))) && joins(text.get(index + 1)))
+        }
+        _ => false,
+    }
+}
+
+fn validate_title(title: &str, field: &'static str, warnings: &mut Vec<ConfigWarning>) -> String {
+    let chars: Vec<char> = title.chars().collect();
+    let visible: String = (0..chars.len())
+        .filter(|&index| !is_invisible(&chars, index))
+        .map(|index| chars[index])
+        .collect();
+    if visible.chars().count() != chars.len() {
+        warnings.push(ConfigWarning::InvisibleCharacters { field });
+    }
+    let trimmed = visible.trim();
+    if trimmed.is_empty() {
+        warnings.push(ConfigWarning::EmptyTitle { field });
+        return UNTITLED_GAME.to_string();
+    }
+    let count = trimmed.chars().count();
+    if MAX_GAME_TITLE_CHARS < count {
+        warnings.push(ConfigWarning::TitleTruncated {
+            field,
+            chars: count,
+        });
+        let truncated: String = trimmed.chars().take(MAX_GAME_TITLE_CHARS - 1).collect();
+        return format!("{}…", truncated.trim_end());
+    }
+    trimmed.to_string()
+}
+
+fn clamp_players(value: usize, field: &'static str, warnings: &mut Vec<ConfigWarning>) -> usize {
+    if MAX_PLAYERS < value {
+        warnings.push(ConfigWarning::PlayersClamped { field, value });
+        return MAX_PLAYERS;
+    }
+    value
+}
+
+/// 複数のゲームから選ぶ募集の選択肢
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameOption {
+    pub game_title: String,
+    pub required_players: usize,
+}
+
+/// 枠のある募集の「タンク」などの枠
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Slot {
+    pub name: String,
+    pub count: usize,
+}
+
+/// 参加と通知なしで参加のリアクションの絵文字 (Unicode の絵文字または `<:name:id>`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmojiPair {
+    pub participation: String,
+    pub silent_participation: String,
+}
+
+/// 「観戦」などの区分を表す追加のリアクション
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtraReaction {
+    pub emoji: String,
+    pub label: String,
+    /// `required_players` の人数に数えるかどうか
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub counts: bool,
+}
+
+impl ExtraReaction {
+    pub fn reaction_type(&self) -> ReactionType {
+        parse_emoji(&self.emoji)
+    }
+}
+
+/// 募集メッセージに付けられたリアクションの種類
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecruitReaction<'a> {
+    Participation,
+    SilentParticipation,
+    Start,
+    Extra(&'a ExtraReaction),
+    /// 複数のゲームから選ぶ募集の `game_options` の番号
+    GameOption(usize),
+    /// 枠のある募集の `slots` の番号
+    Slot(usize),
+}
+
+impl RecruitReaction<'_> {
+    /// 参加者として数えるリアクションかどうか
+    pub fn counts(&self) -> bool {
+        match self {
+            RecruitReaction::Extra(extra) => extra.counts,
+            _ => true,
+        }
+    }
+}
+
+impl RecruitConfig {
+    /// 募集メッセージと通知の言語
+    ///
+    /// 言語を指定できるようになる前の募集は, `i18n::resolve_language` でサーバーの言語を設定してから使います。
+    pub fn language(&self) -> Language {
+        self.language.unwrap_or_default()
+    }
+
+    pub fn is_multi_game(&self) -> bool {
+        !self.game_options.is_empty()
+    }
+
+    pub fn has_slots(&self) -> bool {
+        !self.slots.is_empty()
+    }
+
+    /// 参加のリアクションの絵文字
+    pub fn participation_emoji<'a>(&'a self, emojis: &'a ReactionEmojis) -> &'a str {
+        self.reaction_emoji
+            .as_ref()
+            .map_or(&emojis.participation, |pair| &pair.participation)
+    }
+
+    /// 通知なしで参加のリアクションの絵文字
+    pub fn silent_participation_emoji<'a>(&'a self, emojis: &'a ReactionEmojis) -> &'a str {
+        self.reaction_emoji
+            .as_ref()
+            .map_or(&emojis.silent_participation, |pair| {
+                &pair.silent_participation
+            })
+    }
+
+    /// 選択肢ごとのリアクションで参加する募集 (複数のゲームの募集と枠のある募集) かどうか
+    ///
+    /// 🤚 と 🔔 は付けず, 選択肢ごとに数えて開始すると締め切ります。
+    pub fn has_option_reactions(&self) -> bool {
+        self.is_multi_game() || self.has_slots()
+    }
+
+    /// 参加をボタンで受け付ける募集かどうか (選択肢ごとのリアクションで参加する募集はリアクションのみです)
+    pub fn uses_buttons(&self) -> bool {
+        !self.has_option_reactions() && self.participation_mode == ParticipationMode::Buttons
+    }
+
+    /// 参加に主催者の承認が必要な募集かどうか (リアクションでは参加できません)
+    pub fn requires_application(&self) -> bool {
+        self.uses_buttons() && self.application_question.is_some()
+    }
+
+    /// 開始すると締め切る募集かどうか
+    ///
+    /// 開始予定の日時がある募集は人数が揃った時点で締め切り, リアクションを残したまま開始予定を待ちます。
+    pub fn closes_on_start(&self) -> bool {
+        self.has_option_reactions() || self.start_time.is_some()
+    }
+
+    pub fn game_option_reaction_types(&self) -> Vec<ReactionType> {
+        GAME_OPTION_EMOJIS
+            .iter()
+            .take(self.game_options.len())
+            .map(|emoji| parse_emoji(emoji))
+            .collect()
+    }
+
+    pub fn slot_reaction_types(&self) -> Vec<ReactionType> {
+        SLOT_EMOJIS
+            .iter()
+            .take(self.slots.len())
+            .map(|emoji| parse_emoji(emoji))
+            .collect()
+    }
+
+    /// Bot が募集メッセージに付けるリアクション (付ける順)
+    ///
+    /// ボタンで参加を受け付ける募集には付けません。
+    pub fn reaction_types(&self, emojis: &ReactionEmojis) -> Vec<ReactionType> {
+        if self.uses_buttons() {
+            return Vec::new();
+        }
+        if self.has_option_reactions() {
+            let mut reactions = if self.is_multi_game() {
+                self.game_option_reaction_types()
+            } else {
+                self.slot_reaction_types()
+            };
+            reactions.extend(
+                self.extra_reactions
+                    .iter()
+                    .map(ExtraReaction::reaction_type),
+            );
+            return reactions;
+        }
+        let mut reactions = vec![parse_emoji(self.participation_emoji(emojis))];
+        if self.notify_on_reaction {
+            reactions.push(parse_emoji(self.silent_participation_emoji(emojis)));
+        }
+        reactions.push(parse_emoji(&emojis.start));
+        reactions.extend(
+            self.extra_reactions
+                .iter()
+                .map(ExtraReaction::reaction_type),
+        );
+        reactions
+    }
+
+    /// 参加者として数えるリアクション
+    ///
+    /// `notify_on_reaction = false` の募集でも, ユーザーが付けた 🤚 は数えます。
+    pub fn counted_reaction_types(&self, emojis: &ReactionEmojis) -> Vec<ReactionType> {
+        if self.requires_application() {
+            return Vec::new();
+        }
+        if self.is_multi_game() {
+            return self.game_option_reaction_types();
+        }
+        if self.has_slots() {
+            return self.slot_reaction_types();
+        }
+        let mut reactions: Vec<ReactionType> = [
+            self.participation_emoji(emojis),
+            self.silent_participation_emoji(emojis),
+            &emojis.start,
+        ]
+        .iter()
+        .map(|emoji| parse_emoji(emoji))
+        .collect();
+        reactions.extend(
+            self.extra_reactions
+                .iter()
+                .filter(|extra| extra.counts)
+                .map(ExtraReaction::reaction_type),
+        );
+        reactions
+    }
+
+    pub fn recruit_reaction(
+        &self,
+        emoji: &ReactionType,
+        emojis: &ReactionEmojis,
+    ) -> Option<RecruitReaction<'_>> {
+        if self.requires_application() {
+            return None;
+        }
+        if self.is_multi_game() {
+            return self
+                .game_option_reaction_types()
+                .iter()
+                .position(|option| is_same_emoji(emoji, option))
+                .map(RecruitReaction::GameOption);
+        }
+        if self.has_slots() {
+            return self
+                .slot_reaction_types()
+                .iter()
+                .position(|slot| is_same_emoji(emoji, slot))
+                .map(RecruitReaction::Slot);
+        }
+        if is_same_emoji(emoji, &parse_emoji(self.participation_emoji(emojis))) {
+            return Some(RecruitReaction::Participation);
+        }
+        if is_same_emoji(emoji, &parse_emoji(self.silent_participation_emoji(emojis))) {
+            return Some(RecruitReaction::SilentParticipation);
+        }
+        if is_same_emoji(emoji, &parse_emoji(&emojis.start)) {
+            return Some(RecruitReaction::Start);
+        }
+        self.extra_reactions
+            .iter()
+            .find(|extra| is_same_emoji(emoji, &extra.reaction_type()))
+            .map(RecruitReaction::Extra)
+    }
+}
+
+/// 募集メッセージに表示する募集の状態
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecruitState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub participant_count: Option<usize>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub started: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub cancelled: bool,
+    /// 開始予定の日時までに人数が揃わなかった (`cancelled` も `true` にし, キャンセルした募集と同じく扱います)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub expired: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<Timestamp>,
+    /// 複数のゲームから選ぶ募集で開始したゲーム
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub winner: Option<String>,
+    /// `/close_entries` で受付を終了した募集の確定した参加者
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closed_entries: Option<ClosedEntries>,
+}
+
+/// 受付を終了した募集の記録
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClosedEntries {
+    pub participants: Vec<UserId>,
+    /// 受付終了後に付いた参加のリアクションを外すか
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub remove_late_reactions: bool,
+}
+
+/// 募集メッセージの本文
+///
+/// `render` と `parse` は互いに逆変換で, メッセージを編集するときは必ずこの型を経由します。
+/// 募集設定を保存ファイルに保存する募集 (`config_storage = "database"`) は, この型のまま保存します。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecruitMessage {
+    pub config: RecruitConfig,
+    #[serde(default, skip_serializing_if = "is_default_state")]
+    pub state: RecruitState,
+}
+
+#[derive(Deserialize)]
+struct StateTable {
+    #[serde(default)]
+    state: RecruitState,
+}
+
+#[derive(Serialize)]
+struct StateTableRef<'a> {
+    state: &'a RecruitState,
+}
+
+impl RecruitMessage {
+    pub fn new(config: RecruitConfig) -> Self {
+        Self {
+            config,
+            state: RecruitState::default(),
+        }
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        Self::parse_with_warnings(content).map(|(message, _)| message)
+    }
+
+    /// 募集メッセージを読み取り, 募集設定を `RecruitConfig::validate` で直した内容も返します
+    pub fn parse_with_warnings(content: &str) -> Result<(Self, Vec<ConfigWarning>), String> {
+        let block = extract_toml_block(content).ok_or("toml block not found")?;
+        let config: RecruitConfig = toml::from_str(block).map_err(|err| err.to_string())?;
+        let StateTable { state } = toml::from_str(block).map_err(|err| err.to_string())?;
+        let (config, warnings) = config.validate();
+        Ok((Self { config, state }, warnings))
+    }
+
+    pub fn render(&self, emojis: &ReactionEmojis) -> String {
+        format!(
+            "\n{}\n```toml\n{}\n```",
+            self.render_lines(emojis).join("\n"),
+            self.render_toml_block()
+        )
+    }
+
+    /// 募集設定の toml ブロックを含めずに描画します (募集設定を保存ファイルに保存する募集で使います)
+    pub fn render_without_config(&self, emojis: &ReactionEmojis) -> String {
+        self.render_lines(emojis).join("\n")
+    }
+
+    fn render_lines(&self, emojis: &ReactionEmojis) -> Vec<String> {
+        let config = &self.config;
+        let language = config.language();
+
+        let mut lines = if config.is_multi_game() {
+            vec![language.text(Text::LegendMultiGameHeader)]
+        } else if config.has_slots() {
+            vec![language.text(Text::LegendSlotHeader {
+                game_title: &config.game_title,
+            })]
+        } else if config.uses_buttons() {
+            vec![language.text(Text::LegendButtonsHeader {
+                game_title: &config.game_title,
+            })]
+        } else {
+            vec![language.text(Text::LegendHeader {
+                game_title: &config.game_title,
+            })]
+        };
+        if let Some(description) = &config.description {
+            lines.extend(description.lines().map(|line| format!("> {line}")));
+        }
+        if let Some(category) = &config.category {
+            lines.push(language.text(Text::LegendCategory { category }));
+        }
+        if config.is_multi_game() {
+            for (emoji, option) in GAME_OPTION_EMOJIS.iter().zip(&config.game_options) {
+                lines.push(language.text(Text::LegendGameOption {
+                    emoji,
+                    game_title: &option.game_title,
+                    required_players: option.required_players,
+                }));
+            }
+            lines.push(language.text(Text::LegendMultiGameRule));
+        } else if config.has_slots() {
+            for (emoji, slot) in SLOT_EMOJIS.iter().zip(&config.slots) {
+                lines.push(language.text(Text::LegendSlot {
+                    emoji,
+                    name: &slot.name,
+                    count: slot.count,
+                }));
+            }
+            lines.push(language.text(Text::LegendSlotRule));
+        } else if config.uses_buttons() {
+            if let Some(question) = &config.application_question {
+                lines.push(language.text(Text::LegendApplication { question }));
+            }
+            lines.push(language.text(Text::LegendButtons));
+        } else {
+            lines.push(language.text(Text::LegendParticipation {
+                emoji: config.participation_emoji(emojis),
+            }));
+            if config.notify_on_reaction {
+                lines.push(language.text(Text::LegendSilentParticipation {
+                    emoji: config.silent_participation_emoji(emojis),
+                }));
+            }
+            lines.push(language.text(Text::LegendStart {
+                emoji: &emojis.start,
+            }));
+        }
+        if config.auto_assign_role_on_reaction
+            && let Some(role_id) = config.mention_roles.first()
+        {
+            let role = role_id.mention().to_string();
+            lines.push(language.text(Text::LegendAutoAssignRole { role: &role }));
+        }
+        if !config.preset_participants.is_empty() {
+            let mentions: Vec<String> = config
+                .preset_participants
+                .iter()
+                .map(|user_id| user_id.mention().to_string())
+                .collect();
+            lines.push(language.text(Text::LegendPresetParticipants {
+                mentions: &mentions.join(" "),
+            }));
+        }
+        if let Some(max_players) = config.max_players {
+            lines.push(language.text(Text::LegendMaxPlayers { max_players }));
+        }
+        if config.strict_order {
+            lines.push(language.text(Text::LegendStrictOrder));
+        }
+        if config.multi_lobby {
+            lines.push(language.text(Text::LegendMultiLobby {
+                required_players: config.required_players,
+            }));
+        }
+        if let Some(start_time) = config.start_time {
+            let start_time = crate::reminder::format_start_time(start_time);
+            lines.push(language.text(Text::LegendStartTime {
+                start_time: &start_time,
+            }));
+            if let Some(minutes) = config.remind_before_minutes {
+                lines.push(language.text(Text::LegendRemindBefore { minutes }));
+            }
+        }
+        if let Some(minutes) = config.roll_call_minutes {
+            lines.push(language.text(Text::LegendRollCall { minutes }));
+        }
+        if let Some(role_id) = config.priority_role {
+            let role = role_id.mention().to_string();
+            lines.push(match config.priority_until {
+                Some(until) => language.text(Text::LegendPriority {
+                    role: &role,
+                    until: until.unix_timestamp(),
+                }),
+                None => language.text(Text::LegendPriorityEnded),
+            });
+        }
+        if let Some(minutes) = config.start_delay_minutes {
+            lines.push(language.text(Text::LegendStartDelay { minutes }));
+        }
+        if let Some(channel_id) = config.count_voice_channel {
+            let channel = channel_id.mention().to_string();
+            lines.push(language.text(Text::LegendVoiceChannel { channel: &channel }));
+        }
+        if let Some(channel_id) = config.require_voice_at_start {
+            let channel = channel_id.mention().to_string();
+            lines.push(
+                language.text(Text::LegendVoiceRequired {
+                    channel: &channel,
+                    minutes: config
+                        .voice_grace_minutes
+                        .unwrap_or(DEFAULT_VOICE_GRACE_MINUTES),
+                }),
+            );
+        }
+        for extra in &config.extra_reactions {
+            lines.push(language.text(Text::LegendExtra {
+                emoji: &extra.emoji,
+                label: &extra.label,
+                counts: extra.counts,
+            }));
+        }
+        if config.simulation {
+            lines.push(language.text(Text::LegendSimulation));
+        }
+        lines.extend(self.render_state_lines());
+        lines
+    }
+
+    fn render_state_lines(&self) -> Vec<String> {
+        let state = &self.state;
+        let language = self.config.language();
+        let mut lines = Vec::new();
+        if let Some(count) = state.participant_count {
+            lines.push(language.text(Text::StateParticipantCount {
+                count,
+                required_players: self.config.required_players,
+            }));
+        }
+        if let Some(deadline) = state.deadline {
+            lines.push(language.text(Text::StateDeadline {
+                deadline: deadline.unix_timestamp(),
+            }));
+        }
+        if state.expired {
+            lines.push(language.text(Text::StateExpired));
+        } else if state.cancelled {
+            lines.push(language.text(Text::StateCancelled));
+        } else if let Some(closed) = &state.closed_entries {
+            lines.push(language.text(Text::StateEntriesClosed {
+                count: closed.participants.len(),
+                participants: &render_mentions(&closed.participants),
+            }));
+        } else if state.started
+            && let Some(winner) = &state.winner
+        {
+            lines.push(language.text(Text::StateStartedWith { winner }));
+        } else if state.started && self.config.start_time.is_some() {
+            lines.push(language.text(Text::StateClosedScheduled));
+        } else if state.started {
+            lines.push(language.text(Text::StateStarted));
+        }
+        lines
+    }
+
+    fn render_toml_block(&self) -> String {
+        let mut block = toml::to_string(&self.config).expect("RecruitConfig is serializable");
+        if self.state != RecruitState::default() {
+            block.push('\n');
+            block.push_str(
+                &toml::to_string(&StateTableRef { state: &self.state })
+                    .expect("RecruitState is serializable"),
+            );
+        }
+        block.trim_end().to_string()
+    }
+}
+
+/// 参加者のメンションを空白区切りで並べます (受付終了や不成立の募集の参加者の表示)
+pub fn render_mentions(user_ids: &[UserId]) -> String {
+    user_ids
+        .iter()
+        .map(|user_id| user_id.mention().to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 募集の説明の上限の文字数
+pub const MAX_DESCRIPTION_CHARS: usize = 500;
+
+/// 募集の説明から, コードブロックの区切りと `@everyone` / `@here` のメンションを取り除きます
+pub fn sanitize_description(text: &str) -> String {
+    sanitize_text(text.trim())
+        .replace("@everyone", "@\u{200B}everyone")
+        .replace("@here", "@\u{200B}here")
+}
+
+/// 募集メッセージの本文を壊さないよう, ユーザーが入力した文字列からコードブロックの区切りを取り除きます
+pub fn sanitize_text(text: &str) -> String {
+    text.replace('`', "\u{FF40}")
+}
+
+/// `/recruit create` の `extra_reactions` を読み取ります
+///
+/// `絵文字=ラベル` をカンマ区切りで指定し, 人数に数える場合は `絵文字=ラベル=count` とします。
+pub fn parse_extra_reactions(
+    input: &str,
+    reaction_emoji: Option<&EmojiPair>,
+    emojis: &ReactionEmojis,
+) -> Result<Vec<ExtraReaction>, String> {
+    let builtin = reaction_emoji.map_or(
+        [
+            emojis.participation.as_str(),
+            emojis.silent_participation.as_str(),
+        ],
+        |pair| {
+            [
+                pair.participation.as_str(),
+                pair.silent_participation.as_str(),
+            ]
+        },
+    );
+    const MAX_EXTRA_REACTIONS: usize = 5;
+    const MAX_LABEL_LENGTH: usize = 20;
+
+    let mut extra_reactions: Vec<ExtraReaction> = Vec::new();
+    for item in input
+        .split([',', '、'])
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+    {
+        let mut parts = item.split('=').map(str::trim);
+        let emoji = parts.next().unwrap_or_default();
+        let label = parts.next().unwrap_or_default();
+        let counts = match parts.next() {
+            None => false,
+            Some("count") => true,
+            Some(_) => return Err(format!("`{item}` の 3 つ目には count のみ指定できます")),
+        };
+        if emoji.is_empty() || label.is_empty() || parts.next().is_some() {
+            return Err(format!(
+                "`{item}` は `絵文字=ラベル` の形式で指定してください"
+            ));
+        }
+        if emoji.contains(|c: char| c == '`' || c.is_whitespace()) {
+            return Err(format!("`{emoji}` は絵文字として使えません"));
+        }
+        if MAX_LABEL_LENGTH < label.chars().count() {
+            return Err(format!(
+                "ラベルは {MAX_LABEL_LENGTH} 文字以内で指定してください"
+            ));
+        }
+        let reaction_type = parse_emoji(emoji);
+        let is_duplicate = builtin
+            .iter()
+            .chain(&[emojis.start.as_str()])
+            .chain(&GAME_OPTION_EMOJIS)
+            .chain(&SLOT_EMOJIS)
+            .map(|emoji| parse_emoji(emoji))
+            .chain(extra_reactions.iter().map(ExtraReaction::reaction_type))
+            .any(|other| is_same_emoji(&reaction_type, &other));
+        if is_duplicate {
+            return Err(format!("{emoji} はほかのリアクションと重複しています"));
+        }
+        extra_reactions.push(ExtraReaction {
+            emoji: emoji.to_string(),
+            label: sanitize_text(label),
+            counts,
+        });
+    }
+    if MAX_EXTRA_REACTIONS < extra_reactions.len() {
+        return Err(format!(
+            "追加のリアクションは {MAX_EXTRA_REACTIONS} 個まで指定できます"
+        ));
+    }
+    Ok(extra_reactions)
+}
+
+/// Unicode の絵文字またはカスタム絵文字 (`<:name:id>`) を読み取ります
+pub fn parse_emoji(emoji: &str) -> ReactionType {
+    ReactionType::try_from(emoji).unwrap_or_else(|_| ReactionType::Unicode(emoji.to_string()))
+}
+
+/// カスタム絵文字は名前が変わっても同じ絵文字とみなします
+pub fn is_same_emoji(a: &ReactionType, b: &ReactionType) -> bool {
+    match (a, b) {
+        (ReactionType::Custom { id: a, .. }, ReactionType::Custom { id: b, .. }) => a == b,
+        (ReactionType::Unicode(a), ReactionType::Unicode(b)) => a == b,
+        _ => false,
+    }
+}
+
+pub fn extract_toml_block(content: &str) -> Option<&str> {
+    let start_index = content.find("```toml")?;
+    let rest = &content[start_index + "```toml".len()..];
+    let end_index = rest.find("```")?;
+    Some(rest[..end_index].trim())
+}
+
+fn serialize_id<S, T>(id: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Copy + Into<u64>,
+{
+    match id {
+        Some(id) => serializer.serialize_u64((*id).into()),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn serialize_ids<S, T>(ids: &[T], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Copy + Into<u64>,
+{
+    serializer.collect_seq(ids.iter().map(|&id| id.into()))
+}
+
+fn deserialize_one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_default_over_mention(value: &OverMention) -> bool {
+    *value == OverMention::default()
+}
+
+fn is_default_participation_mode(value: &ParticipationMode) -> bool {
+    *value == ParticipationMode::default()
+}
+
+fn is_default_state(state: &RecruitState) -> bool {
+    *state == RecruitState::default()
+}
+
+fn default_notify_on_reaction() -> bool {
+    true
+}
+
+fn is_default_notify_on_reaction(value: &bool) -> bool {
+    *value == default_notify_on_reaction()
+}
+
+fn default_delete_after_minutes() -> u64 {
+    DEFAULT_DELETE_AFTER_MINUTES
+}
+
+fn is_default_delete_after_minutes(value: &u64) -> bool {
+    *value == DEFAULT_DELETE_AFTER_MINUTES
+}
+
+#[cfg(test)]
+impl RecruitConfig {
+    /// ゲーム名と開始に必要な人数のほかは既定値の募集設定
+    pub fn for_test(game_title: &str, required_players: usize) -> Self {
+        toml::from_str(&format!(
+            "game_title = {}\nrequired_players = {required_players}",
+            toml::Value::from(game_title)
+        ))
+        .unwrap()
+    }
+}
+
+/// テストの基準の日時から `seconds` 秒後の日時
+#[cfg(test)]
+pub fn at(seconds: i64) -> Timestamp {
+    Timestamp::from_unix_timestamp(1_700_000_000 + seconds).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recruit_message() -> RecruitMessage {
+        let mut config = RecruitConfig::for_test("Apex Legends", 3);
+        config.max_players = Some(5);
+        config.organizer = Some(UserId::new(1));
+        config.description = Some("ランクに行きます\n初心者歓迎".to_string());
+        config.mention_roles = vec![RoleId::new(2), RoleId::new(3)];
+        config.notify_on_reaction = false;
+        config.start_time = Some(Timestamp::from_unix_timestamp(1_700_000_000).unwrap());
+        config.extra_reactions = vec![ExtraReaction {
+            emoji: "👀".to_string(),
+            label: "様子見".to_string(),
+            counts: false,
+        }];
+        config.language = Some(Language::En);
+        RecruitMessage {
+            config,
+            state: RecruitState {
+                participant_count: Some(2),
+                started: true,
+                ..RecruitState::default()
+            },
+        }
+    }
+
+    #[test]
+    fn parse_reads_back_what_render_wrote() {
+        let emojis = ReactionEmojis::default();
+        for message in [
+            RecruitMessage::new(RecruitConfig::for_test("Apex", 2)),
+            recruit_message(),
+        ] {
+            let (parsed, warnings) =
+                RecruitMessage::parse_with_warnings(&message.render(&emojis)).unwrap();
+            assert_eq!(parsed, message);
+            assert!(warnings.is_empty());
+        }
+    }
+
+    #[test]
+    fn render_without_config_has_no_toml_block() {
+        let content = recruit_message().render_without_config(&ReactionEmojis::default());
+        assert_eq!(extract_toml_block(&content), None);
+        assert!(RecruitMessage::parse(&content).is_err());
+    }
+
+    #[test]
+    fn toml_block_is_found_and_trimmed() {
+        assert_eq!(
+            extract_toml_block("募集\n```toml\n\ngame_title = \"Apex\"\n  \n```\n"),
+            Some("game_title = \"Apex\"")
+        );
+        assert_eq!(extract_toml_block("```toml\n```"), Some(""));
+        assert_eq!(extract_toml_block("```toml\ngame_title = \"Apex\""), None);
+        assert_eq!(extract_toml_block("```\ngame_title = \"Apex\"\n```"), None);
+    }
+
+    #[test]
+    fn extra_reactions_must_not_reuse_other_reactions() {
+        let emojis = ReactionEmojis::default();
+        assert_eq!(
+            parse_extra_reactions("👀=様子見, 🎧=VC=count", None, &emojis).unwrap(),
+            [
+                ExtraReaction {
+                    emoji: "👀".to_string(),
+                    label: "様子見".to_string(),
+                    counts: false,
+                },
+                ExtraReaction {
+                    emoji: "🎧".to_string(),
+                    label: "VC".to_string(),
+                    counts: true,
+                },
+            ]
+        );
+        for input in ["✋=参加", "🔔=開始", "1️⃣=一番", "👀=a, 👀=b"] {
+            assert!(
+                parse_extra_reactions(input, None, &emojis).is_err(),
+                "{input}"
+            );
+        }
+        assert!(parse_extra_reactions("👀", None, &emojis).is_err());
+        assert!(parse_extra_reactions("👀=様子見=yes", None, &emojis).is_err());
+    }
+
+    #[test]
+    fn only_counting_extra_reactions_are_counted() {
+        let emojis = ReactionEmojis::default();
+        let mut config = RecruitConfig::for_test("Apex", 2);
+        config.extra_reactions =
+            parse_extra_reactions("👀=様子見, 🎧=VC=count", None, &emojis).unwrap();
+        let counted = config.counted_reaction_types(&emojis);
+        assert!(counted.contains(&parse_emoji("🎧")));
+        assert!(!counted.contains(&parse_emoji("👀")));
+        assert!(config.reaction_types(&emojis).contains(&parse_emoji("👀")));
+        let Some(RecruitReaction::Extra(extra)) =
+            config.recruit_reaction(&parse_emoji("👀"), &emojis)
+        else {
+            panic!("👀 is not an extra reaction");
+        };
+        assert_eq!(extra.label, "様子見");
+    }
+
+    #[test]
+    fn single_mention_role_of_old_messages_is_read() {
+        let old = RecruitMessage::parse(
+            "```toml\ngame_title = \"Apex\"\nrequired_players = 2\nmention_role = 10\n```",
+        )
+        .unwrap();
+        assert_eq!(old.config.mention_roles, [RoleId::new(10)]);
+
+        let mut config = RecruitConfig::for_test("Apex", 2);
+        config.mention_roles = vec![RoleId::new(10), RoleId::new(11)];
+        let content = RecruitMessage::new(config).render(&ReactionEmojis::default());
+        assert!(content.contains("mention_roles = [10, 11]"), "{content}");
+    }
+
+    #[test]
+    fn invalid_titles_and_counts_are_fixed_with_warnings() {
+        let long_title = "A".repeat(MAX_GAME_TITLE_CHARS + 1);
+        let content = format!(
+            "```toml\ngame_title = \"\u{200B}{long_title}\"\nrequired_players = {}\n```",
+            MAX_PLAYERS + 1
+        );
+        let (message, warnings) = RecruitMessage::parse_with_warnings(&content).unwrap();
+        assert_eq!(
+            message.config.game_title.chars().count(),
+            MAX_GAME_TITLE_CHARS
+        );
+        assert_eq!(message.config.required_players, MAX_PLAYERS);
+        assert_eq!(
+            warnings,
+            [
+                ConfigWarning::InvisibleCharacters {
+                    field: "game_title"
+                },
+                ConfigWarning::TitleTruncated {
+                    field: "game_title",
+                    chars: MAX_GAME_TITLE_CHARS + 1
+                },
+                ConfigWarning::PlayersClamped {
+                    field: "required_players",
+                    value: MAX_PLAYERS + 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn extra_reactions_may_use_the_default_emojis_replaced_by_a_server_pair() {
+        let emojis = ReactionEmojis::default();
+
+        // サーバーの絵文字を使う募集では, ✋ を追加のリアクションにできます
+        let pair = EmojiPair {
+            participation: "👍".to_string(),
+            silent_participation: "👌".to_string(),
+        };
+        assert!(parse_extra_reactions("✋=挙手", Some(&pair), &emojis).is_ok());
+        assert!(parse_extra_reactions("👍=参加", Some(&pair), &emojis).is_err());
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+
+use poise::{CreateReply, serenity_prelude::*};
+use tokio::time::Duration;
+
+use crate::join_menu::truncate_chars;
+use crate::recruit_message::RecruitMessage;
+use crate::{
+    Data, Error, collect_participants, parse_bot_recruit_message, participant_count, send_ephemeral,
+};
+
+const PICKER_TIMEOUT: Duration = Duration::from_secs(60);
+/// 候補を探す, チャンネルの最近のメッセージの件数
+const SCANNED_MESSAGES: u8 = 100;
+/// Discord のセレクトメニューに表示できる選択肢の上限
+const MAX_CANDIDATES: usize = 25;
+const MAX_LABEL_CHARS: usize = 100;
+
+/// 募集メッセージのリンクを省略したコマンドで, 対象になりうる募集
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub message_id: MessageId,
+    pub game_title: String,
+    pub count: usize,
+    pub required_players: usize,
+    /// 開始済みの募集 (開始しても締め切らない募集を含みます)
+    pub started: bool,
+}
+
+/// 候補を新しい順に並べ, 開始済みの募集を除いて, セレクトメニューに表示できる件数までにします
+pub fn rank_candidates(mut candidates: Vec<Candidate>) -> Vec<Candidate> {
+    candidates.retain(|candidate| !candidate.started);
+    candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.message_id));
+    candidates.truncate(MAX_CANDIDATES);
+    candidates
+}
+
+/// キャンセルも受付終了もされていない, 1 つのゲームの募集かどうか
+fn is_candidate(recruit_message: &RecruitMessage) -> bool {
+    let state = &recruit_message.state;
+    !state.cancelled && state.closed_entries.is_none() && !recruit_message.config.is_multi_game()
+}
+
+/// 投稿からの経過時間を `3 時間前` のように表します
+fn format_age(created_at: Timestamp, now: Timestamp) -> String {
+    let minutes = (now.unix_timestamp() - created_at.unix_timestamp()).max(0) / 60;
+    match minutes {
+        0 => "たった今".to_string(),
+        1..60 => format!("{minutes} 分前"),
+        60..1440 => format!("{} 時間前", minutes / 60),
+        _ => format!("{} 日前", minutes / 1440),
+    }
+}
+
+/// コマンドの対象の募集を決めてから `run` を実行し, 返した内容を実行者にのみ表示します
+///
+/// `message` を指定した場合はその募集を対象にします。省略した場合はこのチャンネルの受付中の募集を探し,
+/// 1 つだけならそれを, 複数あればセレクトメニューで選ばれた募集を対象にします。
+/// 1 分以内に選ばれなかった場合は `run` を実行しません。
+pub async fn with_recruit<F, Fut>(
+    ctx: poise::Context<'_, Data, Error>,
+    message: Option<Message>,
+    run: F,
+) -> Result<(), Error>
+where
+    F: FnOnce(Message) -> Fut,
+    Fut: Future<Output = Result<String, Error>>,
+{
+    ctx.defer_ephemeral().await?;
+    let data = ctx.data();
+    let serenity_ctx = ctx.serenity_context();
+    if let Some(message) = message {
+        if parse_bot_recruit_message(serenity_ctx, data, &message).is_none() {
+            send_ephemeral(ctx, "指定されたメッセージは募集メッセージではありません。").await?;
+            return Ok(());
+        }
+        let content = run(message).await?;
+        send_ephemeral(ctx, &content).await?;
+        return Ok(());
+    }
+
+    let mut messages: HashMap<MessageId, Message> = HashMap::new();
+    let mut candidates = Vec::new();
+    for message in ctx
+        .channel_id()
+        .messages(ctx, GetMessages::new().limit(SCANNED_MESSAGES))
+        .await?
+    {
+        let Some(recruit_message) = parse_bot_recruit_message(serenity_ctx, data, &message) else {
+            continue;
+        };
+        if !is_candidate(&recruit_message) {
+            continue;
+        }
+        let config = &recruit_message.config;
+        let started = recruit_message.state.started;
+        let count = match participant_count::cached_count(data, message.id) {
+            Some(count) => count,
+            None if started => 0,
+            None => collect_participants(serenity_ctx, data, &message, config, ctx.guild_id())
+                .await?
+                .len(),
+        };
+        candidates.push(Candidate {
+            message_id: message.id,
+            game_title: config.game_title.clone(),
+            count,
+            required_players: config.required_players,
+            started,
+        });
+        messages.insert(message.id, message);
+    }
+    let candidates = rank_candidates(candidates);
+
+    let (message_id, reply) = match candidates.as_slice() {
+        [] => {
+            send_ephemeral(ctx, "このチャンネルに受付中の募集はありません").await?;
+            return Ok(());
+        }
+        [candidate] => (candidate.message_id, None),
+        _ => match pick(ctx, &candidates).await? {
+            Some((message_id, reply)) => (message_id, Some(reply)),
+            None => return Ok(()),
+        },
+    };
+    let content = match messages.remove(&message_id) {
+        Some(message) => run(message).await?,
+        None => "選択した募集を読み取れませんでした".to_string(),
+    };
+    match reply {
+        Some(reply) => {
+            reply
+                .edit(
+                    ctx,
+                    CreateReply::default()
+                        .content(content)
+                        .components(Vec::new()),
+                )
+                .await?;
+        }
+        None => send_ephemeral(ctx, &content).await?,
+    }
+    Ok(())
+}
+
+/// 候補をセレクトメニューで表示し, 選ばれた募集とメニューのメッセージを返します
+async fn pick<'a>(
+    ctx: poise::Context<'a, Data, Error>,
+    candidates: &[Candidate],
+) -> Result<Option<(MessageId, poise::ReplyHandle<'a>)>, Error> {
+    let now = Timestamp::now();
+    let options = candidates
+        .iter()
+        .map(|candidate| {
+            let label = format!(
+                "{} ({}/{})",
+                truncate_chars(&candidate.game_title, MAX_LABEL_CHARS - 16),
+                candidate.count,
+                candidate.required_players
+            );
+            let age = format_age(candidate.message_id.created_at(), now);
+            CreateSelectMenuOption::new(label, candidate.message_id.to_string())
+                .description(format!("{age}に投稿"))
+        })
+        .collect();
+    let custom_id = format!("{}:pick", ctx.id());
+    let reply = ctx
+        .send(
+            CreateReply::default()
+                .content("このチャンネルに受付中の募集が複数あります。対象の募集を選んでください")
+                .ephemeral(true)
+                .components(vec![CreateActionRow::SelectMenu(
+                    CreateSelectMenu::new(&custom_id, CreateSelectMenuKind::String { options })
+                        .placeholder("募集を選択"),
+                )]),
+        )
+        .await?;
+
+    let interaction = ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .filter(move |interaction| interaction.data.custom_id == custom_id)
+        .timeout(PICKER_TIMEOUT)
+        .await;
+    let Some(interaction) = interaction else {
+        reply
+            .edit(
+                ctx,
+                CreateReply::default()
+                    .content("時間内に選択されなかったため終了しました")
+                    .components(Vec::new()),
+            )
+            .await?;
+        return Ok(None);
+    };
+    // 選んだ後の処理に時間がかかっても応答が切れないよう, 先に応答してからメニューを書き換えます
+    interaction
+        .create_response(ctx, CreateInteractionResponse::Acknowledge)
+        .await?;
+    let selected = match &interaction.data.kind {
+        ComponentInteractionDataKind::StringSelect { values } => values
+            .first()
+            .and_then(|value| value.parse::<u64>().ok())
+            .filter(|&id| id != 0)
+            .map(MessageId::new),
+        _ => None,
+    };
+    match selected {
+        Some(message_id) => Ok(Some((message_id, reply))),
+        None => {
+            reply
+                .edit(
+                    ctx,
+                    CreateReply::default()
+                        .content("選択した募集を読み取れませんでした")
+                        .components(Vec::new()),
+                )
+                .await?;
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(message_id: u64, started: bool) -> Candidate {
+        Candidate {
+            message_id: MessageId::new(message_id),
+            game_title: "Apex".to_string(),
+            count: 1,
+            required_players: 3,
+            started,
+        }
+    }
+
+    #[test]
+    fn newest_open_recruits_come_first() {
+        let ranked = rank_candidates(vec![
+            candidate(1, false),
+            candidate(3, true),
+            candidate(2, false),
+        ]);
+        assert_eq!(ranked, [candidate(2, false), candidate(1, false)]);
+
+        let many = (1..=MAX_CANDIDATES as u64 + 1)
+            .map(|message_id| candidate(message_id, false))
+            .collect();
+        let ranked = rank_candidates(many);
+        assert_eq!(ranked.len(), MAX_CANDIDATES);
+        assert_eq!(
+            ranked[0].message_id,
+            MessageId::new(MAX_CANDIDATES as u64 + 1)
+        );
+    }
+
+    #[test]
+    fn ages_are_shown_in_the_largest_unit() {
+        let at =
+            |minutes: i64| Timestamp::from_unix_timestamp(1_700_000_000 + minutes * 60).unwrap();
+        assert_eq!(format_age(at(0), at(0)), "たった今");
+        assert_eq!(format_age(at(0), at(59)), "59 分前");
+        assert_eq!(format_age(at(0), at(3 * 60)), "3 時間前");
+        assert_eq!(format_age(at(0), at(2 * 1440)), "2 日前");
+        // 時計のずれで未来になった場合も, たった今とします
+        assert_eq!(format_age(at(1), at(0)), "たった今");
+    }
+}
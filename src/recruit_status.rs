@@ -0,0 +1,118 @@
+use poise::serenity_prelude::*;
+
+use crate::i18n;
+use crate::recruit_message::{RecruitConfig, RecruitState};
+use crate::{
+    Data, Error, collect_participants, format_progress, parse_bot_recruit_message,
+    participant_count, recruit_picker, reminder,
+};
+
+/// 募集の参加人数と状態を表示します
+///
+/// `message` を省略すると, このチャンネルの受付中の募集から探します。
+#[poise::command(slash_command, guild_only, ephemeral, rename = "status")]
+pub async fn recruit_status(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集メッセージのリンク (省略するとこのチャンネルから探します)"]
+    message: Option<Message>,
+) -> Result<(), Error> {
+    recruit_picker::with_recruit(ctx, message, move |message| async move {
+        let data = ctx.data();
+        let serenity_ctx = ctx.serenity_context();
+        let Some(mut recruit_message) = parse_bot_recruit_message(serenity_ctx, data, &message)
+        else {
+            return Ok("指定されたメッセージは募集メッセージではありません。".to_string());
+        };
+        i18n::resolve_language(data, &mut recruit_message.config, ctx.guild_id());
+        let config = &recruit_message.config;
+        let state = &recruit_message.state;
+        // 受付を終了した募集は, 確定した参加者を表示します
+        let mut user_ids: Vec<UserId> = match &state.closed_entries {
+            Some(closed) => closed.participants.clone(),
+            None => {
+                let user_ids =
+                    collect_participants(serenity_ctx, data, &message, config, ctx.guild_id())
+                        .await?;
+                participant_count::store_count(data, message.id, user_ids.len());
+                user_ids.into_iter().collect()
+            }
+        };
+        user_ids.sort();
+
+        Ok(render_status(config, state, &user_ids, &message.link()))
+    })
+    .await
+}
+
+/// `/recruit status` の表示 (`user_ids` は表示する参加者)
+fn render_status(
+    config: &RecruitConfig,
+    state: &RecruitState,
+    user_ids: &[UserId],
+    link: &str,
+) -> String {
+    let status = if state.expired {
+        "不成立"
+    } else if state.cancelled {
+        "キャンセル済み"
+    } else if state.closed_entries.is_some() {
+        "受付終了"
+    } else if state.started {
+        "開始済み"
+    } else {
+        "受付中"
+    };
+    let mut lines = vec![format!(
+        "**{}** {} ({status})",
+        config.game_title,
+        format_progress(config.language(), user_ids.len(), config.required_players)
+    )];
+    if let Some(max_players) = config.max_players {
+        lines.push(format!("定員: {max_players} 人"));
+    }
+    if let Some(start_time) = config.start_time {
+        lines.push(format!(
+            "開始予定: {}",
+            reminder::format_start_time(start_time)
+        ));
+    }
+    let participants = if user_ids.is_empty() {
+        "なし".to_string()
+    } else {
+        user_ids
+            .iter()
+            .map(|user_id| user_id.mention().to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    lines.push(format!("参加者: {participants}"));
+    lines.push(link.to_string());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_shows_progress_and_participants() {
+        let mut config = RecruitConfig::for_test("Game", 4);
+        config.max_players = Some(5);
+        let state = RecruitState::default();
+        let link = "https://discord.com/channels/1/2/3";
+        assert_eq!(
+            render_status(&config, &state, &[UserId::new(10), UserId::new(11)], link),
+            format!("**Game** (2/4, あと2人) (受付中)\n定員: 5 人\n参加者: <@10> <@11>\n{link}")
+        );
+
+        let state = RecruitState {
+            cancelled: true,
+            ..RecruitState::default()
+        };
+        config.max_players = None;
+        assert_eq!(
+            render_status(&config, &state, &[], link),
+            format!("**Game** (0/4, あと4人) (キャンセル済み)\n参加者: なし\n{link}")
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Instant;
+
+use poise::serenity_prelude::*;
+use tokio::time::Duration;
+
+use crate::discord_api::DiscordApi;
+use crate::i18n::Text;
+use crate::notification::NotificationPolicy;
+use crate::recruit_message::RecruitConfig;
+use crate::{Data, Error};
+
+/// リアクションを参加者として数えない理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectionReason {
+    OverCapacity,
+    EntriesClosed,
+    /// アカウントの作成から `days` 日経っていない
+    AccountTooNew {
+        days: u64,
+    },
+    /// サーバーへの参加から `days` 日経っていない
+    MembershipTooNew {
+        days: u64,
+    },
+    /// サーバーのメンバー確認 (オンボーディング) を済ませていない
+    MembershipPending,
+    /// Discord がスパムの疑いがあるアカウントとしている
+    FlaggedSpammer,
+}
+
+impl RejectionReason {
+    pub fn message(self, config: &RecruitConfig) -> String {
+        match self {
+            RejectionReason::OverCapacity => config.language().text(Text::OverCapacity {
+                game_title: &config.game_title,
+            }),
+            RejectionReason::EntriesClosed => config.language().text(Text::EntriesClosed {
+                game_title: &config.game_title,
+            }),
+            RejectionReason::AccountTooNew { days } => {
+                config.language().text(Text::AccountTooNew {
+                    game_title: &config.game_title,
+                    days,
+                })
+            }
+            RejectionReason::MembershipTooNew { days } => {
+                config.language().text(Text::MembershipTooNew {
+                    game_title: &config.game_title,
+                    days,
+                })
+            }
+            RejectionReason::MembershipPending => config.language().text(Text::MembershipPending {
+                game_title: &config.game_title,
+            }),
+            RejectionReason::FlaggedSpammer => config.language().text(Text::FlaggedSpammer {
+                game_title: &config.game_title,
+            }),
+        }
+    }
+}
+
+/// 一定時間内に一度だけ処理するためのキーの記録
+pub struct TtlCache<K> {
+    ttl: Duration,
+    entries: HashMap<K, Instant>,
+}
+
+impl<K: Eq + Hash> TtlCache<K> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// `key` が記録されていないか期限切れなら記録して true を返します
+    pub fn insert_if_expired(&mut self, key: K, now: Instant) -> bool {
+        self.sweep(now);
+        if self.entries.contains_key(&key) {
+            return false;
+        }
+        self.entries.insert(key, now);
+        true
+    }
+
+    /// `key` が記録されていて期限切れでないかどうか
+    pub fn contains(&self, key: &K, now: Instant) -> bool {
+        self.entries
+            .get(key)
+            .is_some_and(|inserted_at| now.duration_since(*inserted_at) < self.ttl)
+    }
+
+    /// 期限切れのキーを削除します
+    pub fn sweep(&mut self, now: Instant) {
+        self.entries
+            .retain(|_, inserted_at| now.duration_since(*inserted_at) < self.ttl);
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&K) -> bool) {
+        self.entries.retain(|key, _| keep(key));
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// リアクションが数えられなかった理由を本人に通知します
+///
+/// サーバー設定 `rejection_notice_dm` が有効なら DM で送り, DM を送れない場合はチャンネルに送ります。
+pub async fn notify_rejection(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &RecruitConfig,
+    policy: &NotificationPolicy,
+    reaction: &Reaction,
+    user_id: UserId,
+    reason: RejectionReason,
+) -> Result<(), Error> {
+    let is_first_notice = data
+        .rejection_notices
+        .lock()
+        .unwrap()
+        .insert_if_expired((reaction.message_id, user_id), Instant::now());
+    if !is_first_notice {
+        return Ok(());
+    }
+
+    let content = reason.message(config);
+    let use_dm = reaction
+        .guild_id
+        .is_some_and(|guild_id| data.storage.guild_settings(guild_id).rejection_notice_dm);
+    if use_dm {
+        return policy
+            .send_dm_or_channel(
+                api,
+                data,
+                user_id,
+                reaction.channel_id,
+                &content,
+                &reaction
+                    .message_id
+                    .link(reaction.channel_id, reaction.guild_id),
+            )
+            .await;
+    }
+    policy
+        .send_channel_mention(api, data, user_id, reaction.channel_id, &content)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::Language;
+
+    const TTL: Duration = Duration::from_secs(60 * 60);
+
+    #[test]
+    fn same_key_is_accepted_once_per_ttl() {
+        let start = Instant::now();
+        let mut cache = TtlCache::new(TTL);
+        assert!(cache.insert_if_expired(1, start));
+        assert!(!cache.insert_if_expired(1, start + TTL / 2));
+        assert!(cache.insert_if_expired(2, start + TTL / 2));
+        assert!(cache.contains(&1, start + TTL / 2));
+
+        // 期限が切れたら, もう一度受け付けます
+        assert!(!cache.contains(&1, start + TTL));
+        assert!(cache.insert_if_expired(1, start + TTL));
+        assert!(!cache.insert_if_expired(1, start + TTL * 3 / 2));
+    }
+
+    #[test]
+    fn expired_keys_are_swept() {
+        let start = Instant::now();
+        let mut cache = TtlCache::new(TTL);
+        cache.insert_if_expired(1, start);
+        cache.insert_if_expired(2, start + TTL / 2);
+        cache.sweep(start + TTL);
+        assert_eq!(cache.len(), 1);
+        cache.retain(|&key| key != 2);
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn reasons_are_told_in_the_recruit_language() {
+        let mut config = RecruitConfig::for_test("Apex", 2);
+        assert_eq!(
+            RejectionReason::OverCapacity.message(&config),
+            "Apex は定員に達しているため参加できません"
+        );
+        config.language = Some(Language::En);
+        assert_eq!(
+            RejectionReason::OverCapacity.message(&config),
+            "Apex is full, so you cannot join"
+        );
+    }
+}
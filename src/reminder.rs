@@ -0,0 +1,160 @@
+use poise::serenity_prelude::*;
+
+use crate::discord_error::is_unknown_entity;
+use crate::i18n::{self, Text};
+use crate::notification::{Mentions, NotificationPolicy, send_dms};
+use crate::recruit_message::RecruitConfig;
+use crate::scheduler::Job;
+use crate::{
+    Data, Error, can_manage_recruit, collect_participants, parse_bot_recruit_message, quiet_hours,
+    recruit_picker,
+};
+
+/// 人数が揃った `start_time` のある募集について, 開始予定の `remind_before_minutes` 分前のリマインドを予約します
+///
+/// すでにリマインドする時刻を過ぎている場合は予約しません。
+pub fn schedule_reminder(
+    data: &Data,
+    config: &RecruitConfig,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+) {
+    let (Some(start_time), Some(remind_before_minutes)) =
+        (config.start_time, config.remind_before_minutes)
+    else {
+        return;
+    };
+    let seconds = remind_before_minutes
+        .saturating_mul(60)
+        .min(i64::MAX as u64) as i64;
+    let Ok(fire_at) =
+        Timestamp::from_unix_timestamp(start_time.unix_timestamp().saturating_sub(seconds))
+    else {
+        return;
+    };
+    if fire_at <= Timestamp::now() {
+        return;
+    }
+    data.scheduler.enqueue(
+        &*data.storage,
+        fire_at,
+        Job::RemindParticipants {
+            channel_id,
+            message_id,
+            guild_id,
+        },
+    );
+}
+
+/// 開始予定が近づいたことを, リマインドする時点の参加者に DM で知らせます
+///
+/// DM を送れなかった参加者はまとめてメンションしてチャンネルに知らせます。
+/// 募集メッセージが削除された場合とキャンセルされた場合は知らせません。
+pub async fn remind_participants(
+    ctx: &Context,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+) -> Result<(), Error> {
+    let message = match channel_id.message(ctx, message_id).await {
+        Ok(message) => message,
+        Err(err) if is_unknown_entity(&err).is_some() => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    let Some(mut recruit_message) = parse_bot_recruit_message(ctx, data, &message) else {
+        return Ok(());
+    };
+    if recruit_message.state.cancelled {
+        return Ok(());
+    }
+    i18n::resolve_language(data, &mut recruit_message.config, guild_id);
+    let config = &recruit_message.config;
+    // サイレント時間帯は, DM を送れなかった参加者をメンションせずにチャンネルに知らせます
+    let quiet_hours = quiet_hours::active(data, guild_id, config, Timestamp::now());
+    let policy =
+        NotificationPolicy::new(config).with_quiet_hours(quiet_hours.map(|_| config.language()));
+    // 受付を終了した募集は, 確定した参加者にだけリマインドします
+    let mut user_ids: Vec<UserId> = match &recruit_message.state.closed_entries {
+        Some(closed) => closed.participants.clone(),
+        None => collect_participants(ctx, data, &message, config, guild_id)
+            .await?
+            .into_iter()
+            .collect(),
+    };
+    if user_ids.is_empty() {
+        return Ok(());
+    }
+    user_ids.sort();
+
+    let content = config.language().text(Text::StartingSoon {
+        game_title: &config.game_title,
+    });
+    let dm_content = format!("{content}\n{}", message.link());
+    let failed = send_dms(ctx, data, &user_ids, &dm_content).await;
+    if failed.is_empty() {
+        return Ok(());
+    }
+    let mentions: Vec<String> = failed
+        .iter()
+        .map(|user_id| user_id.mention().to_string())
+        .collect();
+    let content = format!("{} {content}", mentions.join(" "));
+    policy
+        .send(
+            ctx,
+            data,
+            channel_id,
+            policy.message(&content, &Mentions::users(failed)),
+        )
+        .await?;
+    Ok(())
+}
+
+/// 参加者に, 開始が近いことを DM で知らせます
+///
+/// `message` を省略すると, このチャンネルの受付中の募集から探します。
+#[poise::command(slash_command, guild_only, ephemeral, rename = "remind")]
+pub async fn remind(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集メッセージのリンク (省略するとこのチャンネルから探します)"]
+    message: Option<Message>,
+) -> Result<(), Error> {
+    recruit_picker::with_recruit(ctx, message, move |message| async move {
+        let data = ctx.data();
+        let Some(recruit_message) =
+            parse_bot_recruit_message(ctx.serenity_context(), data, &message)
+        else {
+            return Ok("指定されたメッセージは募集メッセージではありません。".to_string());
+        };
+        if !can_manage_recruit(ctx, &recruit_message.config) {
+            return Ok(
+                "主催者またはメッセージの管理権限を持つメンバーのみリマインドできます。"
+                    .to_string(),
+            );
+        }
+        if recruit_message.state.cancelled {
+            return Ok("この募集はキャンセルされています。".to_string());
+        }
+        remind_participants(
+            ctx.serenity_context(),
+            data,
+            message.channel_id,
+            message.id,
+            ctx.guild_id(),
+        )
+        .await?;
+        Ok(format!(
+            "{} の参加者にリマインドを送りました",
+            recruit_message.config.game_title
+        ))
+    })
+    .await
+}
+
+/// 開始予定を Discord のタイムスタンプ記法で表示します
+pub fn format_start_time(start_time: Timestamp) -> String {
+    let seconds = start_time.unix_timestamp();
+    format!("<t:{seconds}:f> (<t:{seconds}:R>)")
+}
@@ -0,0 +1,176 @@
+use poise::serenity_prelude::*;
+
+use crate::config_storage;
+use crate::recruit_message::ConfigWarning;
+use crate::{
+    Data, Error, can_manage_recruit, parse_bot_recruit_message, recruit_edit, send_ephemeral,
+    thread,
+};
+
+/// 募集メッセージを修復した内容
+#[derive(Debug, Default)]
+pub struct RepairReport {
+    /// 付け直した Bot のリアクション
+    pub added_reactions: Vec<ReactionType>,
+    /// 本文を設定から描画し直したかどうか
+    pub rerendered: bool,
+    /// 描画し直すときに直した募集設定
+    pub config_warnings: Vec<ConfigWarning>,
+}
+
+impl RepairReport {
+    pub fn is_empty(&self) -> bool {
+        self.added_reactions.is_empty() && !self.rerendered
+    }
+}
+
+/// 募集メッセージの Bot のリアクションと本文を, 募集設定どおりに戻します
+///
+/// 開始して締め切った複数のゲームの募集とキャンセルした募集には, リアクションを付け直しません。
+/// 募集メッセージでない場合は `None` を返します。
+pub async fn repair_recruit(
+    ctx: &Context,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Result<Option<RepairReport>, Error> {
+    let _guard = data.lock_message(message_id).await;
+    let message = channel_id.message(ctx, message_id).await?;
+    if message.author.id != ctx.cache.current_user().id {
+        return Ok(None);
+    }
+    let Ok((recruit_message, config_warnings)) = config_storage::load_with_warnings(data, &message)
+    else {
+        return Ok(None);
+    };
+    let state = &recruit_message.state;
+    let config = &recruit_message.config;
+
+    let mut report = RepairReport::default();
+    let closed = state.cancelled || (state.started && config.closes_on_start());
+    if !closed {
+        for reaction_type in config.reaction_types(&data.config.emojis) {
+            let present = message.reactions.iter().any(|reaction| {
+                reaction.me && is_same_reaction(&reaction.reaction_type, &reaction_type)
+            });
+            if present {
+                continue;
+            }
+            thread::retry_if_archived(ctx, channel_id, || {
+                channel_id.create_reaction(ctx, message_id, reaction_type.clone())
+            })
+            .await?;
+            report.added_reactions.push(reaction_type);
+        }
+    }
+
+    // Discord は本文の前後の空白を取り除いて保存するため, 空白を除いて比較します
+    let content = config_storage::render(data, message_id, &recruit_message);
+    if message.content.trim() != content.trim() {
+        recruit_edit::edit_recruit_message(ctx, data, channel_id, message_id, |_| Some(())).await?;
+        report.rerendered = true;
+        report.config_warnings = config_warnings;
+    }
+    Ok(Some(report))
+}
+
+/// カスタム絵文字は名前が変わっても同じ絵文字として扱います
+fn is_same_reaction(a: &ReactionType, b: &ReactionType) -> bool {
+    match (a, b) {
+        (ReactionType::Custom { id: a, .. }, ReactionType::Custom { id: b, .. }) => a == b,
+        (ReactionType::Unicode(a), ReactionType::Unicode(b)) => {
+            a.trim_end_matches('\u{FE0F}') == b.trim_end_matches('\u{FE0F}')
+        }
+        _ => false,
+    }
+}
+
+/// 募集メッセージのリアクションがすべて削除されたときに, Bot のリアクションを付け直します
+///
+/// 最近リアクションを処理した募集か, キャッシュにある Bot のメッセージのみ確認します。
+pub async fn handle_reaction_remove_all(
+    ctx: &Context,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Result<(), Error> {
+    let bot_id = ctx.cache.current_user().id;
+    let tracked = data.last_touched.lock().unwrap().contains_key(&message_id)
+        || ctx
+            .cache
+            .message(channel_id, message_id)
+            .is_some_and(|message| message.author.id == bot_id);
+    if !tracked {
+        return Ok(());
+    }
+    if let Some(report) = repair_recruit(ctx, data, channel_id, message_id).await?
+        && !report.is_empty()
+    {
+        eprintln!(
+            "Repaired recruit {message_id} after all reactions were removed ({} reactions)",
+            report.added_reactions.len()
+        );
+    }
+    Ok(())
+}
+
+/// 募集メッセージの Bot のリアクションと本文を修復します
+#[poise::command(slash_command, guild_only, ephemeral, rename = "repair_recruit")]
+pub async fn repair_recruit_command(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集メッセージのリンク"] message: Message,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let Some(recruit_message) =
+        parse_bot_recruit_message(ctx.serenity_context(), ctx.data(), &message)
+    else {
+        send_ephemeral(ctx, "指定されたメッセージは募集メッセージではありません。").await?;
+        return Ok(());
+    };
+    if !can_manage_recruit(ctx, &recruit_message.config) {
+        send_ephemeral(
+            ctx,
+            "主催者またはメッセージの管理権限を持つメンバーのみ募集を修復できます。",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let report = repair_recruit(
+        ctx.serenity_context(),
+        ctx.data(),
+        message.channel_id,
+        message.id,
+    )
+    .await?;
+    let content = match report {
+        None => "指定されたメッセージは募集メッセージではありません。".to_string(),
+        Some(report) if report.is_empty() => "修復が必要な箇所はありませんでした".to_string(),
+        Some(report) => {
+            let mut lines = Vec::new();
+            if !report.added_reactions.is_empty() {
+                let reactions: Vec<String> = report
+                    .added_reactions
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
+                lines.push(format!(
+                    "リアクションを付け直しました: {}",
+                    reactions.join(" ")
+                ));
+            }
+            if report.rerendered {
+                lines.push("募集メッセージの本文を募集設定から作り直しました".to_string());
+                lines.extend(
+                    report
+                        .config_warnings
+                        .iter()
+                        .map(|warning| format!("- {warning}")),
+                );
+            }
+            lines.join("\n")
+        }
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tokio::sync::Semaphore;
+
+const DEFAULT_PERMITS: usize = 8;
+
+/// バックグラウンドの処理が同時に呼び出す REST API の上限
+///
+/// 複数の DM の送信や, 抜けたメンバーのリアクションの削除, 起動時のやり直しなどが一度に REST API を呼び出して,
+/// `/recruit create` やリアクションの処理が遅くならないようにします。利用者の操作への応答はこの上限を使いません。
+#[derive(Debug)]
+pub struct RestBudget {
+    semaphore: Semaphore,
+    permits: usize,
+    /// 上限に達していて, 空きを待っている処理の数
+    waiters: AtomicUsize,
+}
+
+impl RestBudget {
+    pub fn new(permits: usize) -> Self {
+        let permits = permits.max(1);
+        Self {
+            semaphore: Semaphore::new(permits),
+            permits,
+            waiters: AtomicUsize::new(0),
+        }
+    }
+
+    /// `JOINBELL_BACKGROUND_REST_PERMITS` (既定: 8) を上限にします
+    pub fn from_env() -> Self {
+        let permits = std::env::var("JOINBELL_BACKGROUND_REST_PERMITS")
+            .ok()
+            .and_then(|permits| permits.parse().ok())
+            .filter(|&permits| 0 < permits)
+            .unwrap_or(DEFAULT_PERMITS);
+        Self::new(permits)
+    }
+
+    /// 上限に空きができるまで待ってから `operation` を実行します
+    pub async fn run<F: Future>(&self, operation: F) -> F::Output {
+        self.waiters.fetch_add(1, Ordering::Relaxed);
+        let permit = self.semaphore.acquire().await;
+        self.waiters.fetch_sub(1, Ordering::Relaxed);
+        let output = operation.await;
+        drop(permit);
+        output
+    }
+
+    pub fn waiters(&self) -> usize {
+        self.waiters.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Display for RestBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "permits={} in_use={} waiters={}",
+            self.permits,
+            self.permits - self.semaphore.available_permits(),
+            self.waiters()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::task::JoinSet;
+
+    use super::*;
+
+    /// 呼び出した数を数え, `release` に許可が足されるまで応答しない API
+    struct SlowApi {
+        active: AtomicUsize,
+        peak: AtomicUsize,
+        release: Semaphore,
+    }
+
+    impl SlowApi {
+        fn new() -> Self {
+            Self {
+                active: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                release: Semaphore::new(0),
+            }
+        }
+
+        async fn call(&self) {
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(active, Ordering::SeqCst);
+            self.release.acquire().await.unwrap().forget();
+            self.active.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn calls_beyond_the_permits_wait_for_a_free_permit() {
+        let budget = Arc::new(RestBudget::new(2));
+        let api = Arc::new(SlowApi::new());
+        let mut tasks = JoinSet::new();
+        for _ in 0..6 {
+            let budget = budget.clone();
+            let api = api.clone();
+            tasks.spawn(async move { budget.run(api.call()).await });
+        }
+        while budget.waiters() < 4 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(api.active.load(Ordering::SeqCst), 2);
+        assert_eq!(budget.to_string(), "permits=2 in_use=2 waiters=4");
+
+        api.release.add_permits(6);
+        while tasks.join_next().await.is_some() {}
+        assert_eq!(api.peak.load(Ordering::SeqCst), 2);
+        assert_eq!(budget.to_string(), "permits=2 in_use=0 waiters=0");
+    }
+
+    #[test]
+    fn at_least_one_call_is_permitted() {
+        assert_eq!(
+            RestBudget::new(0).to_string(),
+            "permits=1 in_use=0 waiters=0"
+        );
+    }
+}
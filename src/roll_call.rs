@@ -0,0 +1,258 @@
+use std::collections::HashSet;
+
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config_storage;
+use crate::discord_api::DiscordApi;
+use crate::i18n::{self, Text};
+use crate::notification::{Mentions, NotificationPolicy};
+use crate::recruit_message::{RecruitConfig, RecruitMessage};
+use crate::scheduler::{self, Job};
+use crate::{Data, Error, participant_order, send_start_notification};
+
+/// 点呼の ✅ ボタンの custom_id の接頭辞 (後ろに募集メッセージの ID を付けます)
+const ROLL_CALL_PREFIX: &str = "roll_call:";
+/// `roll_call_minutes` を指定しなかった場合の点呼の締切までの分数
+pub const DEFAULT_ROLL_CALL_MINUTES: u64 = 10;
+
+/// 点呼中の募集
+///
+/// 再起動しても締切に結果を確認できるよう, TOML ファイルに保存します。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RollCall {
+    pub channel_id: ChannelId,
+    pub notice_message_id: MessageId,
+    pub deadline: Timestamp,
+    /// 点呼を始めた時点の参加者 (✅ を押せるユーザー)
+    pub participants: Vec<UserId>,
+    /// ✅ を押した参加者
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub confirmed: Vec<UserId>,
+}
+
+/// 人数が揃った募集で, 開始通知の代わりに点呼をとります (すでに点呼中なら何もしません)
+pub async fn start_roll_call(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &RecruitConfig,
+    message: &Message,
+    guild_id: Option<GuildId>,
+    user_ids: HashSet<UserId>,
+    minutes: u64,
+) -> Result<(), Error> {
+    if data.storage.roll_call(message.id).is_some() {
+        return Ok(());
+    }
+
+    let mut participants: Vec<UserId> = user_ids.into_iter().collect();
+    participants.sort();
+    let deadline = scheduler::fire_at_after_minutes(minutes);
+    let mentions: Vec<String> = participants
+        .iter()
+        .map(|user_id| user_id.mention().to_string())
+        .collect();
+    let language = config.language();
+    let content = language.text(Text::RollCall {
+        mentions: &mentions.join(" "),
+        game_title: &config.game_title,
+        deadline: deadline.unix_timestamp(),
+    });
+    let policy = NotificationPolicy::new(config);
+    let channel_id = message.channel_id;
+    let button = CreateButton::new(format!("{ROLL_CALL_PREFIX}{}", message.id))
+        .label(language.text(Text::RollCallButton))
+        .style(ButtonStyle::Success);
+    let notice = api
+        .send_message(
+            channel_id,
+            policy
+                .message(&content, &Mentions::users(participants.iter().copied()))
+                .components(vec![CreateActionRow::Buttons(vec![button])]),
+        )
+        .await?;
+
+    data.storage.set_roll_call(
+        message.id,
+        RollCall {
+            channel_id,
+            notice_message_id: notice.id,
+            deadline,
+            participants,
+            confirmed: Vec::new(),
+        },
+    )?;
+    data.scheduler.enqueue(
+        &*data.storage,
+        deadline,
+        Job::FinishRollCall {
+            channel_id,
+            message_id: message.id,
+            guild_id,
+        },
+    );
+    Ok(())
+}
+
+/// 点呼の ✅ ボタンかどうか
+pub fn is_roll_call_button(interaction: &ComponentInteraction) -> bool {
+    interaction.data.custom_id.starts_with(ROLL_CALL_PREFIX)
+}
+
+/// 点呼の ✅ ボタンを押した参加者を記録し, 人数が揃ったら開始通知を送ります
+pub async fn handle_roll_call_button(
+    ctx: &Context,
+    data: &Data,
+    interaction: &ComponentInteraction,
+) -> Result<(), Error> {
+    let Some(message_id) = interaction
+        .data
+        .custom_id
+        .strip_prefix(ROLL_CALL_PREFIX)
+        .and_then(|id| id.parse().ok())
+        .map(MessageId::new)
+    else {
+        return Ok(());
+    };
+    let _guard = data.lock_message(message_id).await;
+    let Some(mut roll_call) = data.storage.roll_call(message_id) else {
+        let language = i18n::guild_language(data, interaction.guild_id);
+        return respond(ctx, interaction, &language.text(Text::RollCallClosed)).await;
+    };
+    let message = roll_call.channel_id.message(ctx, message_id).await?;
+    let mut recruit_message = config_storage::load(data, &message)?;
+    i18n::resolve_language(data, &mut recruit_message.config, interaction.guild_id);
+    let config = &recruit_message.config;
+    let language = config.language();
+
+    let user_id = interaction.user.id;
+    if !roll_call.participants.contains(&user_id) {
+        return respond(ctx, interaction, &language.text(Text::RollCallNotTarget)).await;
+    }
+    roll_call.confirmed = data.storage.confirm_roll_call(message_id, user_id)?;
+    let content = language.text(Text::RollCallConfirmed {
+        count: roll_call.confirmed.len(),
+        required_players: config.required_players,
+    });
+    respond(ctx, interaction, &content).await?;
+
+    if config.required_players <= roll_call.confirmed.len() {
+        let guild_id = interaction.guild_id;
+        finish(ctx, data, &recruit_message, &message, guild_id, roll_call).await?;
+    }
+    Ok(())
+}
+
+/// 点呼の締切に, ✅ を押した参加者が揃っていれば開始通知を送り, 揃っていなければお知らせします
+pub async fn finish_roll_call(
+    ctx: &Context,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+) -> Result<(), Error> {
+    let _guard = data.lock_message(message_id).await;
+    let Some(roll_call) = data.storage.roll_call(message_id) else {
+        return Ok(());
+    };
+    let message = channel_id.message(ctx, message_id).await?;
+    let mut recruit_message = config_storage::load(data, &message)?;
+    i18n::resolve_language(data, &mut recruit_message.config, guild_id);
+    finish(ctx, data, &recruit_message, &message, guild_id, roll_call).await
+}
+
+async fn finish(
+    ctx: &Context,
+    data: &Data,
+    recruit_message: &RecruitMessage,
+    message: &Message,
+    guild_id: Option<GuildId>,
+    roll_call: RollCall,
+) -> Result<(), Error> {
+    clear(ctx, data, message.id, Some(&roll_call)).await;
+    // 点呼中にキャンセルされた募集や, 締め切られた開始予定のある募集は開始しません
+    let state = &recruit_message.state;
+    let config = &recruit_message.config;
+    if state.cancelled
+        || state.closed_entries.is_some()
+        || (state.started && config.closes_on_start())
+    {
+        return Ok(());
+    }
+    let policy = NotificationPolicy::new(config);
+    let confirmed: HashSet<UserId> = roll_call.confirmed.iter().copied().collect();
+    if config.required_players <= confirmed.len() {
+        let participants = participant_order::ordered(data, message.id, &confirmed);
+        return send_start_notification(
+            ctx,
+            data,
+            config,
+            &policy,
+            message,
+            guild_id,
+            participants,
+        )
+        .await;
+    }
+    // 募集は締め切らず, 再び人数が揃ったら点呼をやり直します
+    let content = config.language().text(Text::RollCallFailed {
+        game_title: &config.game_title,
+        count: confirmed.len(),
+        required_players: config.required_players,
+    });
+    policy
+        .send(
+            ctx,
+            data,
+            message.channel_id,
+            policy.message(&content, &Mentions::none()),
+        )
+        .await?;
+    Ok(())
+}
+
+/// 点呼を取り消します (ベルで即時開始した場合や募集メッセージが削除された場合など)
+pub async fn cancel_roll_call(api: &impl DiscordApi, data: &Data, message_id: MessageId) {
+    let roll_call = data.storage.roll_call(message_id);
+    clear(api, data, message_id, roll_call.as_ref()).await;
+}
+
+/// 点呼の記録と締切の予約を消し, 点呼のお知らせを削除します
+async fn clear(
+    api: &impl DiscordApi,
+    data: &Data,
+    message_id: MessageId,
+    roll_call: Option<&RollCall>,
+) {
+    if let Err(err) = data.storage.remove_roll_call(message_id) {
+        eprintln!("Failed to remove roll call: {err}");
+    }
+    if let Err(err) = data.storage.cancel_jobs(
+        &|job| matches!(job, Job::FinishRollCall { message_id: id, .. } if *id == message_id),
+    ) {
+        eprintln!("Failed to cancel roll call job: {err}");
+    }
+    if let Some(roll_call) = roll_call {
+        let _ = api
+            .delete_message(roll_call.channel_id, roll_call.notice_message_id)
+            .await;
+    }
+}
+
+async fn respond(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    content: &str,
+) -> Result<(), Error> {
+    interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,535 @@
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio::time::{Duration, sleep};
+
+use crate::countdown::{self, PendingStart};
+use crate::deletion_log::{self, DeletionReason};
+use crate::discord_error::{is_unknown_entity, unknown_entity};
+use crate::storage::Storage;
+use crate::{
+    Data, Error, expiry, notice_cleanup, priority, quiet_hours, reminder, roll_call, start_retry,
+    thread, time_poll, voice_gate,
+};
+
+/// 予約した処理の内容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "job_type", rename_all = "snake_case")]
+pub enum Job {
+    DeleteMessage {
+        channel_id: ChannelId,
+        message_id: MessageId,
+        /// 削除の記録に残す, 送信したときの本文
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<String>,
+    },
+    /// 開始した募集メッセージを削除します (ピン留めされている場合は削除しません)
+    DeleteRecruitMessage {
+        channel_id: ChannelId,
+        message_id: MessageId,
+    },
+    /// カウントダウンが終わった募集の参加人数を確認して開始します
+    FinishCountdown {
+        channel_id: ChannelId,
+        message_id: MessageId,
+        guild_id: Option<GuildId>,
+        snapshot: Vec<UserId>,
+        notice_channel_id: ChannelId,
+        notice_message_id: MessageId,
+        /// 主催者が延期した合計の分数
+        #[serde(default, skip_serializing_if = "is_zero")]
+        snoozed_minutes: u64,
+    },
+    /// 開始予定が近づいたことを参加者に知らせます
+    RemindParticipants {
+        channel_id: ChannelId,
+        message_id: MessageId,
+        guild_id: Option<GuildId>,
+    },
+    /// 点呼の締切に, 確認が揃っていれば開始します
+    FinishRollCall {
+        channel_id: ChannelId,
+        message_id: MessageId,
+        guild_id: Option<GuildId>,
+    },
+    /// 優先期間が終わった募集を全員に公開します
+    EndPriorityWindow {
+        channel_id: ChannelId,
+        message_id: MessageId,
+        guild_id: Option<GuildId>,
+    },
+    /// 送れなかった開始通知を送り直します
+    RetryStartNotification {
+        channel_id: ChannelId,
+        message_id: MessageId,
+        guild_id: Option<GuildId>,
+    },
+    /// サイレント時間帯に送らなかったロールへのメンションを送ります
+    DeferredRolePing {
+        channel_id: ChannelId,
+        message_id: MessageId,
+        guild_id: Option<GuildId>,
+    },
+    /// VC にいる参加者を待つ時間が終わった募集を, 開始するか見送ります
+    ExpireVoiceHold {
+        channel_id: ChannelId,
+        message_id: MessageId,
+        guild_id: Option<GuildId>,
+    },
+    /// 日時の投票を締め切り, 最も票が多い日時で募集します
+    FinishTimePoll {
+        channel_id: ChannelId,
+        message_id: MessageId,
+        guild_id: Option<GuildId>,
+    },
+    /// 開始予定の日時に人数が揃っていない募集を, 不成立として締め切ります
+    ExpireRecruit {
+        channel_id: ChannelId,
+        message_id: MessageId,
+        guild_id: Option<GuildId>,
+    },
+}
+
+impl Job {
+    /// 処理の対象のチャンネル
+    pub fn channel_id(&self) -> ChannelId {
+        match self {
+            Job::DeleteMessage { channel_id, .. }
+            | Job::DeleteRecruitMessage { channel_id, .. }
+            | Job::FinishCountdown { channel_id, .. }
+            | Job::RemindParticipants { channel_id, .. }
+            | Job::FinishRollCall { channel_id, .. }
+            | Job::EndPriorityWindow { channel_id, .. }
+            | Job::RetryStartNotification { channel_id, .. }
+            | Job::DeferredRolePing { channel_id, .. }
+            | Job::ExpireVoiceHold { channel_id, .. }
+            | Job::FinishTimePoll { channel_id, .. }
+            | Job::ExpireRecruit { channel_id, .. } => *channel_id,
+        }
+    }
+
+    /// 処理の対象のサーバー (記録していない処理は `None`)
+    pub fn guild_id(&self) -> Option<GuildId> {
+        match self {
+            Job::DeleteMessage { .. } | Job::DeleteRecruitMessage { .. } => None,
+            Job::FinishCountdown { guild_id, .. }
+            | Job::RemindParticipants { guild_id, .. }
+            | Job::FinishRollCall { guild_id, .. }
+            | Job::EndPriorityWindow { guild_id, .. }
+            | Job::RetryStartNotification { guild_id, .. }
+            | Job::DeferredRolePing { guild_id, .. }
+            | Job::ExpireVoiceHold { guild_id, .. }
+            | Job::FinishTimePoll { guild_id, .. }
+            | Job::ExpireRecruit { guild_id, .. } => *guild_id,
+        }
+    }
+
+    /// 処理の対象の募集メッセージ (通知メッセージの削除は `None`, 日時の投票は投票のメッセージ)
+    pub fn recruit_message_id(&self) -> Option<MessageId> {
+        match self {
+            Job::DeleteMessage { .. } => None,
+            Job::DeleteRecruitMessage { message_id, .. }
+            | Job::FinishCountdown { message_id, .. }
+            | Job::RemindParticipants { message_id, .. }
+            | Job::FinishRollCall { message_id, .. }
+            | Job::EndPriorityWindow { message_id, .. }
+            | Job::RetryStartNotification { message_id, .. }
+            | Job::DeferredRolePing { message_id, .. }
+            | Job::ExpireVoiceHold { message_id, .. }
+            | Job::FinishTimePoll { message_id, .. }
+            | Job::ExpireRecruit { message_id, .. } => Some(*message_id),
+        }
+    }
+
+    /// 削除を予約したメッセージ (通知メッセージと開始した募集メッセージ)
+    pub fn deleted_message_id(&self) -> Option<MessageId> {
+        match self {
+            Job::DeleteMessage { message_id, .. }
+            | Job::DeleteRecruitMessage { message_id, .. } => Some(*message_id),
+            _ => None,
+        }
+    }
+
+    /// 処理の種類 (保存ファイルの `job_type` と同じ名前)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Job::DeleteMessage { .. } => "delete_message",
+            Job::DeleteRecruitMessage { .. } => "delete_recruit_message",
+            Job::FinishCountdown { .. } => "finish_countdown",
+            Job::RemindParticipants { .. } => "remind_participants",
+            Job::FinishRollCall { .. } => "finish_roll_call",
+            Job::EndPriorityWindow { .. } => "end_priority_window",
+            Job::RetryStartNotification { .. } => "retry_start_notification",
+            Job::DeferredRolePing { .. } => "deferred_role_ping",
+            Job::ExpireVoiceHold { .. } => "expire_voice_hold",
+            Job::FinishTimePoll { .. } => "finish_time_poll",
+            Job::ExpireRecruit { .. } => "expire_recruit",
+        }
+    }
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// 失敗した処理を実行し直す回数の上限 (最初の実行を含みます)
+pub const MAX_JOB_ATTEMPTS: u32 = 3;
+/// 失敗した処理を実行し直すまでの時間 (実行した回数を掛けます)
+pub const JOB_RETRY_DELAY_SECONDS: i64 = 60;
+
+/// TOML ファイルに保存する予約済みの処理
+///
+/// 実行中の処理も, 終わるまで保存しておきます。実行中に停止した場合は, 再起動後に実行し直します。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledJob {
+    pub id: u64,
+    pub fire_at: Timestamp,
+    pub payload: Job,
+    /// 実行を始めた回数
+    #[serde(default, skip_serializing_if = "is_zero_attempts")]
+    pub attempts: u32,
+    /// 実行中かどうか (保存しないため, 再起動すると実行前に戻ります)
+    #[serde(skip)]
+    pub leased: bool,
+}
+
+fn is_zero_attempts(value: &u32) -> bool {
+    *value == 0
+}
+
+/// 予約した処理を実行時刻に実行します
+///
+/// 予約は TOML ファイルに保存するため, 再起動しても実行時刻に実行されます。
+pub struct Scheduler {
+    wake: Notify,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            wake: Notify::new(),
+        }
+    }
+
+    pub fn enqueue(&self, storage: &dyn Storage, fire_at: Timestamp, job: Job) {
+        if let Err(err) = storage.push_job(fire_at, job) {
+            eprintln!("Failed to save scheduled job: {err}");
+            return;
+        }
+        self.wake.notify_one();
+    }
+}
+
+/// 現在から `minutes` 分後の日時
+pub fn fire_at_after_minutes(minutes: u64) -> Timestamp {
+    let seconds = minutes.saturating_mul(60).min(i64::MAX as u64) as i64;
+    Timestamp::from_unix_timestamp(Timestamp::now().unix_timestamp().saturating_add(seconds))
+        .unwrap_or_else(|_| Timestamp::now())
+}
+
+/// 予約した処理を実行するタスクを開始します
+///
+/// 起動前に予約されたカウントダウンはカウントダウン中の募集として復元し, 実行時刻を過ぎた処理はすぐに実行します。
+pub fn spawn_worker(ctx: Context, data: Data) {
+    for job in data.storage.jobs() {
+        if let Job::FinishCountdown {
+            message_id,
+            snapshot,
+            notice_channel_id,
+            notice_message_id,
+            snoozed_minutes,
+            ..
+        } = job.payload
+        {
+            data.pending_starts.lock().unwrap().insert(
+                message_id,
+                PendingStart {
+                    snapshot: snapshot.into_iter().collect(),
+                    notice_channel_id,
+                    notice_message_id,
+                    start_at: job.fire_at,
+                    snoozed_minutes,
+                },
+            );
+        }
+    }
+
+    tokio::spawn(async move {
+        loop {
+            let now = Timestamp::now();
+            for job in data.storage.lease_due_jobs(now) {
+                let recruit_message_id = job.payload.recruit_message_id();
+                let payload = job.payload.clone();
+                let task = {
+                    let ctx = ctx.clone();
+                    let data = data.clone();
+                    async move {
+                        let result = run(&ctx, &data, job.payload).await;
+                        finish(&data, job.id, result);
+                    }
+                };
+                // 募集の処理は, 募集をキャンセルしたときに中断できるよう記録します
+                match recruit_message_id {
+                    Some(message_id) => data.tasks.spawn(message_id, payload, task),
+                    None => {
+                        tokio::spawn(task);
+                    }
+                }
+            }
+
+            match data.storage.next_fire_at() {
+                Some(fire_at) => {
+                    let seconds = (fire_at.unix_timestamp() - now.unix_timestamp()).max(1);
+                    tokio::select! {
+                        _ = sleep(Duration::from_secs(seconds as u64)) => {}
+                        _ = data.scheduler.wake.notified() => {}
+                    }
+                }
+                None => data.scheduler.wake.notified().await,
+            }
+        }
+    });
+}
+
+/// 実行し終えた処理の予約を消します
+///
+/// 失敗した場合は, `MAX_JOB_ATTEMPTS` 回まで時間を置いて実行し直します。
+fn finish(data: &Data, id: u64, result: Result<(), Error>) {
+    let saved = match result {
+        Ok(()) => data.storage.complete_job(id),
+        Err(err) => {
+            eprintln!("Failed to run scheduled job {id}: {err}");
+            match data.storage.retry_job(id, Timestamp::now()) {
+                Ok(false) => {
+                    eprintln!("Gave up scheduled job {id} after {MAX_JOB_ATTEMPTS} attempts");
+                    Ok(())
+                }
+                result => result.map(|_| ()),
+            }
+        }
+    };
+    if let Err(err) = saved {
+        eprintln!("Failed to save scheduled job {id}: {err}");
+    }
+    data.scheduler.wake.notify_one();
+}
+
+/// 募集メッセージやチャンネルが削除されていた場合は, 募集の記録を消して終わった処理として扱います
+fn forget_if_deleted(
+    data: &Data,
+    message_id: MessageId,
+    result: Result<(), Error>,
+) -> Result<(), Error> {
+    match result {
+        Err(err) if unknown_entity(&err).is_some() => {
+            data.forget_deleted_message(message_id);
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+async fn run(ctx: &Context, data: &Data, job: Job) -> Result<(), Error> {
+    match job {
+        Job::DeleteMessage {
+            channel_id,
+            message_id,
+            content,
+        } => {
+            notice_cleanup::clean_up(ctx, data, channel_id, message_id, content).await;
+            Ok(())
+        }
+        Job::DeleteRecruitMessage {
+            channel_id,
+            message_id,
+        } => {
+            let message = match channel_id.message(ctx, message_id).await {
+                Ok(message) => message,
+                Err(err) if is_unknown_entity(&err).is_some() => return Ok(()),
+                Err(err) => return Err(err.into()),
+            };
+            if message.pinned {
+                return Ok(());
+            }
+            match thread::retry_if_archived(ctx, channel_id, || {
+                channel_id.delete_message(&ctx.http, message_id)
+            })
+            .await
+            {
+                Ok(Some(())) => {
+                    deletion_log::record(
+                        ctx,
+                        data,
+                        channel_id,
+                        message_id,
+                        Some(message.content),
+                        DeletionReason::RecruitCleanup,
+                    )
+                    .await;
+                    Ok(())
+                }
+                Err(err) if is_unknown_entity(&err).is_none() => Err(err.into()),
+                _ => Ok(()),
+            }
+        }
+        Job::FinishCountdown {
+            channel_id,
+            message_id,
+            guild_id,
+            ..
+        } => forget_if_deleted(
+            data,
+            message_id,
+            countdown::finish_countdown(ctx, data, channel_id, message_id, guild_id).await,
+        ),
+        Job::RemindParticipants {
+            channel_id,
+            message_id,
+            guild_id,
+        } => reminder::remind_participants(ctx, data, channel_id, message_id, guild_id).await,
+        Job::FinishRollCall {
+            channel_id,
+            message_id,
+            guild_id,
+        } => forget_if_deleted(
+            data,
+            message_id,
+            roll_call::finish_roll_call(ctx, data, channel_id, message_id, guild_id).await,
+        ),
+        Job::EndPriorityWindow {
+            channel_id,
+            message_id,
+            guild_id,
+        } => forget_if_deleted(
+            data,
+            message_id,
+            priority::end_priority_window(ctx, data, channel_id, message_id, guild_id).await,
+        ),
+        Job::RetryStartNotification {
+            channel_id,
+            message_id,
+            guild_id,
+        } => forget_if_deleted(
+            data,
+            message_id,
+            start_retry::retry_start_notification(ctx, data, channel_id, message_id, guild_id)
+                .await,
+        ),
+        Job::DeferredRolePing {
+            channel_id,
+            message_id,
+            guild_id,
+        } => {
+            quiet_hours::send_deferred_role_ping(ctx, data, channel_id, message_id, guild_id).await
+        }
+        Job::ExpireVoiceHold {
+            channel_id,
+            message_id,
+            guild_id,
+        } => forget_if_deleted(
+            data,
+            message_id,
+            voice_gate::resume(ctx, data, channel_id, message_id, guild_id).await,
+        ),
+        Job::FinishTimePoll {
+            channel_id,
+            message_id,
+            ..
+        } => forget_if_deleted(
+            data,
+            message_id,
+            time_poll::finish_time_poll(ctx, data, channel_id, message_id).await,
+        ),
+        Job::ExpireRecruit {
+            channel_id,
+            message_id,
+            guild_id,
+        } => forget_if_deleted(
+            data,
+            message_id,
+            expiry::expire_recruit(ctx, data, channel_id, message_id, guild_id).await,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recruit_message::at;
+    use crate::storage_backend::MemoryStorage;
+
+    fn countdown_job() -> Job {
+        Job::FinishCountdown {
+            channel_id: ChannelId::new(1),
+            message_id: MessageId::new(2),
+            guild_id: Some(GuildId::new(3)),
+            snapshot: vec![UserId::new(4)],
+            notice_channel_id: ChannelId::new(1),
+            notice_message_id: MessageId::new(5),
+            snoozed_minutes: 0,
+        }
+    }
+
+    #[test]
+    fn countdown_survives_restart_mid_run() {
+        let storage = MemoryStorage::default();
+        storage.push_job(at(60), countdown_job()).unwrap();
+
+        assert!(storage.lease_due_jobs(at(59)).is_empty());
+        assert_eq!(storage.next_fire_at(), Some(at(60)));
+        let due = storage.lease_due_jobs(at(60));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].payload, countdown_job());
+        // 実行中の処理は, 次の実行時刻にも含めず二重に実行しません
+        assert_eq!(storage.next_fire_at(), None);
+        assert!(storage.lease_due_jobs(at(61)).is_empty());
+
+        // 実行し終える前に停止しても, 再起動後に実行し直します
+        let restarted = storage.reopen().unwrap();
+        assert_eq!(restarted.next_fire_at(), Some(at(60)));
+        let due = restarted.lease_due_jobs(at(120));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].attempts, 2);
+
+        restarted.complete_job(due[0].id).unwrap();
+        assert!(restarted.jobs().is_empty());
+        let restarted = restarted.reopen().unwrap();
+        assert!(restarted.jobs().is_empty());
+    }
+
+    #[test]
+    fn failed_job_is_retried_until_the_limit() {
+        let storage = MemoryStorage::default();
+        storage.push_job(at(0), countdown_job()).unwrap();
+
+        let mut now = at(0);
+        for attempt in 1..MAX_JOB_ATTEMPTS {
+            let due = storage.lease_due_jobs(now);
+            assert_eq!(due.len(), 1);
+            assert!(storage.retry_job(due[0].id, now).unwrap());
+            let fire_at = storage.next_fire_at().unwrap();
+            assert_eq!(
+                fire_at.unix_timestamp(),
+                now.unix_timestamp() + JOB_RETRY_DELAY_SECONDS * i64::from(attempt)
+            );
+            assert!(storage.lease_due_jobs(now).is_empty());
+            now = fire_at;
+        }
+        let due = storage.lease_due_jobs(now);
+        assert!(!storage.retry_job(due[0].id, now).unwrap());
+        assert!(storage.jobs().is_empty());
+    }
+
+    #[test]
+    fn job_interrupted_too_often_is_dropped() {
+        let storage = MemoryStorage::default();
+        storage.push_job(at(0), countdown_job()).unwrap();
+        for _ in 0..MAX_JOB_ATTEMPTS {
+            let storage = storage.reopen().unwrap();
+            assert_eq!(storage.lease_due_jobs(at(0)).len(), 1);
+        }
+        let storage = storage.reopen().unwrap();
+        assert!(storage.lease_due_jobs(at(0)).is_empty());
+        assert!(storage.jobs().is_empty());
+    }
+}
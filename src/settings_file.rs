@@ -0,0 +1,284 @@
+use std::collections::{HashMap, HashSet};
+
+use poise::{CreateReply, serenity_prelude::*};
+
+use crate::confirm::confirm;
+use crate::recruit_message::sanitize_text;
+use crate::storage::GuildSettings;
+use crate::{Data, Error, send_ephemeral};
+
+const SETTINGS_FILE_NAME: &str = "joinbell_settings.toml";
+const MAX_SETTINGS_FILE_SIZE: u32 = 64 * 1024;
+
+/// サーバー設定を TOML ファイルに書き出します
+#[poise::command(slash_command, guild_only, ephemeral, rename = "export")]
+pub async fn settings_export(ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let content = ctx.data().storage.guild_settings(guild_id).to_toml()?;
+    ctx.send(
+        CreateReply::default()
+            .content("サーバー設定を書き出しました (Webhook の URL を含むため取り扱いに注意してください)")
+            .attachment(CreateAttachment::bytes(
+                content.into_bytes(),
+                SETTINGS_FILE_NAME,
+            )),
+    )
+    .await?;
+    Ok(())
+}
+
+/// 書き出したサーバー設定を読み込みます
+#[poise::command(slash_command, guild_only, ephemeral, rename = "import")]
+pub async fn settings_import(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "/settings export で書き出した TOML ファイル"] file: Attachment,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    if MAX_SETTINGS_FILE_SIZE < file.size {
+        send_ephemeral(ctx, "設定ファイルが大きすぎます").await?;
+        return Ok(());
+    }
+    let content = match String::from_utf8(file.download().await?) {
+        Ok(content) => content,
+        Err(_) => {
+            send_ephemeral(ctx, "設定ファイルは UTF-8 の TOML で指定してください").await?;
+            return Ok(());
+        }
+    };
+    let mut settings = match GuildSettings::from_toml(&content, &ctx.data().config.emojis) {
+        Ok(settings) => settings,
+        Err(err) => {
+            let content = format!(
+                "設定ファイルが不正です: {}",
+                sanitize_text(&err.to_string())
+            );
+            send_ephemeral(ctx, &content).await?;
+            return Ok(());
+        }
+    };
+    let warnings = resolve_references(ctx, guild_id, &mut settings).await?;
+
+    if !confirm(ctx, &render_preview(&settings, &warnings)?, "適用する").await? {
+        return Ok(());
+    }
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |current| *current = settings)?;
+    send_ephemeral(ctx, "サーバー設定を読み込みました").await?;
+    Ok(())
+}
+
+/// 設定が参照するロール, チャンネル, メンバーのうち, このサーバーに存在するもの
+#[derive(Debug, Default)]
+struct GuildEntities {
+    roles: HashSet<RoleId>,
+    channels: HashSet<ChannelId>,
+    /// `allowed_bot_participants` のうちサーバーにいるユーザーと, Bot かどうか
+    members: HashMap<UserId, bool>,
+}
+
+/// ロールなどの参照がこのサーバーに存在するか確認し, 存在しないものを取り除いて警告を返します
+async fn resolve_references(
+    ctx: poise::Context<'_, Data, Error>,
+    guild_id: GuildId,
+    settings: &mut GuildSettings,
+) -> Result<Vec<String>, Error> {
+    let mut entities = GuildEntities::default();
+    let refers_to_roles = settings.organizer_role.is_some()
+        || !settings.channel_roles.is_empty()
+        || settings
+            .categories
+            .values()
+            .any(|defaults| defaults.priority_role.is_some());
+    if refers_to_roles {
+        entities.roles = guild_id.roles(ctx).await?.into_keys().collect();
+    }
+    let refers_to_channels = settings.audit_channel.is_some()
+        || settings.summary_channel.is_some()
+        || settings.error_channel.is_some()
+        || !settings.mirror_channels.is_empty();
+    if refers_to_channels {
+        entities.channels = guild_id.channels(ctx).await?.into_keys().collect();
+    }
+    for &user_id in &settings.allowed_bot_participants {
+        if let Ok(member) = guild_id.member(ctx, user_id).await {
+            entities.members.insert(user_id, member.user.bot);
+        }
+    }
+    Ok(remove_missing_references(settings, &entities))
+}
+
+/// `entities` にない参照を取り除き, 取り除いた理由の警告を返します
+fn remove_missing_references(
+    settings: &mut GuildSettings,
+    entities: &GuildEntities,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if let Some(role_id) = settings.organizer_role
+        && !entities.roles.contains(&role_id)
+    {
+        warnings.push(format!(
+            "organizer_role のロール {role_id} がこのサーバーにないため解除します"
+        ));
+        settings.organizer_role = None;
+        settings.organizer_role_for_mentions_only = false;
+    }
+
+    for (name, channel) in [
+        ("audit_channel", &mut settings.audit_channel),
+        ("summary_channel", &mut settings.summary_channel),
+        ("error_channel", &mut settings.error_channel),
+    ] {
+        if let Some(channel_id) = *channel
+            && !entities.channels.contains(&channel_id)
+        {
+            warnings.push(format!(
+                "{name} のチャンネル {channel_id} がこのサーバーにないため解除します"
+            ));
+            *channel = None;
+        }
+    }
+
+    settings.mirror_channels.retain(|source, target| {
+        let exists = entities.channels.contains(source) && entities.channels.contains(target);
+        if !exists {
+            warnings.push(format!(
+                "mirror_channels の {source} → {target} のチャンネルがこのサーバーにないため除きます"
+            ));
+        }
+        exists
+    });
+
+    settings.channel_roles.retain(|channel_id, role_id| {
+        let exists = entities.roles.contains(role_id);
+        if !exists {
+            warnings.push(format!(
+                "channel_roles の {channel_id} のロール {role_id} がこのサーバーにないため除きます"
+            ));
+        }
+        exists
+    });
+
+    for (name, defaults) in &mut settings.categories {
+        if let Some(role_id) = defaults.priority_role
+            && !entities.roles.contains(&role_id)
+        {
+            warnings.push(format!(
+                "categories.{name}.priority_role のロール {role_id} がこのサーバーにないため解除します"
+            ));
+            defaults.priority_role = None;
+        }
+    }
+
+    settings
+        .allowed_bot_participants
+        .retain(|user_id| match entities.members.get(user_id) {
+            Some(true) => true,
+            Some(false) => {
+                warnings.push(format!(
+                    "allowed_bot_participants の {user_id} は Bot ではないため除きます"
+                ));
+                false
+            }
+            None => {
+                warnings.push(format!(
+                    "allowed_bot_participants の {user_id} がこのサーバーにいないため除きます"
+                ));
+                false
+            }
+        });
+
+    #[cfg(feature = "webhook")]
+    let invalid_webhook_url = settings
+        .webhook_url
+        .as_deref()
+        .and_then(|url| crate::webhook::validate_webhook_url(url).err());
+    #[cfg(not(feature = "webhook"))]
+    let invalid_webhook_url = settings
+        .webhook_url
+        .as_ref()
+        .map(|_| "この Bot は Webhook 機能を有効にしてビルドされていません".to_string());
+    if let Some(err) = invalid_webhook_url {
+        warnings.push(format!("webhook_url を設定できないため解除します: {err}"));
+        settings.webhook_url = None;
+    }
+
+    warnings
+}
+
+fn render_preview(settings: &GuildSettings, warnings: &[String]) -> Result<String, Error> {
+    #[cfg(feature = "webhook")]
+    let preview = GuildSettings {
+        webhook_url: settings
+            .webhook_url
+            .as_deref()
+            .map(crate::webhook::redact_webhook_url),
+        ..settings.clone()
+    };
+    #[cfg(not(feature = "webhook"))]
+    let preview = settings.clone();
+    let mut content = format!(
+        "次の設定で現在のサーバー設定を置き換えます\n```toml\n{}\n```",
+        sanitize_text(preview.to_toml()?.trim_end())
+    );
+    for warning in warnings {
+        content += &format!("\n⚠️ {warning}");
+    }
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_missing_from_the_server_are_removed_with_warnings() {
+        let mut settings = GuildSettings {
+            organizer_role: Some(RoleId::new(1)),
+            organizer_role_for_mentions_only: true,
+            channel_roles: [(ChannelId::new(10), RoleId::new(2))].into(),
+            audit_channel: Some(ChannelId::new(10)),
+            error_channel: Some(ChannelId::new(11)),
+            mirror_channels: [
+                (ChannelId::new(10), ChannelId::new(12)),
+                (ChannelId::new(12), ChannelId::new(10)),
+            ]
+            .into(),
+            allowed_bot_participants: vec![UserId::new(20), UserId::new(21), UserId::new(22)],
+            ..GuildSettings::default()
+        };
+        let entities = GuildEntities {
+            roles: [RoleId::new(2)].into(),
+            channels: [ChannelId::new(10)].into(),
+            members: [(UserId::new(20), true), (UserId::new(21), false)].into(),
+        };
+
+        let warnings = remove_missing_references(&mut settings, &entities);
+        assert_eq!(
+            warnings,
+            [
+                "organizer_role のロール 1 がこのサーバーにないため解除します",
+                "error_channel のチャンネル 11 がこのサーバーにないため解除します",
+                "mirror_channels の 10 → 12 のチャンネルがこのサーバーにないため除きます",
+                "mirror_channels の 12 → 10 のチャンネルがこのサーバーにないため除きます",
+                "allowed_bot_participants の 21 は Bot ではないため除きます",
+                "allowed_bot_participants の 22 がこのサーバーにいないため除きます",
+            ]
+        );
+        assert_eq!(settings.organizer_role, None);
+        assert!(!settings.organizer_role_for_mentions_only);
+        // サーバーにある参照は残します
+        assert_eq!(settings.audit_channel, Some(ChannelId::new(10)));
+        assert_eq!(settings.channel_roles.len(), 1);
+        assert!(settings.mirror_channels.is_empty());
+        assert_eq!(settings.allowed_bot_participants, [UserId::new(20)]);
+    }
+
+    #[test]
+    fn settings_without_references_need_no_warnings() {
+        let mut settings = GuildSettings::default();
+        let warnings = remove_missing_references(&mut settings, &GuildEntities::default());
+        assert!(warnings.is_empty());
+    }
+}
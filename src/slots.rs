@@ -0,0 +1,470 @@
+use std::collections::HashSet;
+
+use poise::{CreateReply, serenity_prelude::*};
+
+use crate::channel_check::{self, ChannelSuitability};
+use crate::discord_api::{self, DiscordApi};
+use crate::i18n::{self, Language, Text};
+use crate::notification::{Mentions, NotificationPolicy};
+use crate::notify_prefs::StartLabels;
+use crate::recruit_message::{
+    MAX_PLAYERS, OverMention, ParticipationMode, RecruitConfig, SLOT_EMOJIS, Slot, sanitize_text,
+};
+use crate::{
+    DEFAULT_DELETE_AFTER_MINUTES, Data, Error, allowed_bot_participants, assign_participant_role,
+    author_permissions, config_storage, confirm, fetch_reaction_users, format_progress, game_alias,
+    is_counted_reactor, participant_age, participant_order, post_recruit_message, recruit_actions,
+    recruit_edit, rejection, send_ephemeral, send_start_notification,
+};
+
+/// 枠の名前の最大文字数
+const MAX_SLOT_NAME_CHARS: usize = 20;
+
+/// `/recruit slots` の `slot_1` などに指定した `名前:人数` を読み取ります
+pub fn parse_slot(field: &str, input: &str) -> Result<Slot, String> {
+    let Some((name, count)) = input.rsplit_once([':', '：']) else {
+        return Err(format!(
+            "{field} は `名前:人数` の形式で指定してください (例: タンク:1)"
+        ));
+    };
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("{field} の名前を指定してください"));
+    }
+    if MAX_SLOT_NAME_CHARS < name.chars().count() {
+        return Err(format!(
+            "{field} の名前は {MAX_SLOT_NAME_CHARS} 文字以内で指定してください"
+        ));
+    }
+    let count = match count.trim().parse::<usize>() {
+        Ok(count) if (1..=MAX_PLAYERS).contains(&count) => count,
+        _ => {
+            return Err(format!(
+                "{field} の人数は 1 から {MAX_PLAYERS} で指定してください"
+            ));
+        }
+    };
+    Ok(Slot {
+        name: sanitize_text(name),
+        count,
+    })
+}
+
+/// `slot_1` から順に指定された枠を読み取ります (同じ名前の枠は指定できません)
+pub fn build_slots(inputs: [Option<String>; SLOT_EMOJIS.len()]) -> Result<Vec<Slot>, String> {
+    let mut slots: Vec<Slot> = Vec::new();
+    for (index, input) in inputs.into_iter().enumerate() {
+        let Some(input) = input else {
+            continue;
+        };
+        let slot = parse_slot(&format!("slot_{}", index + 1), &input)?;
+        if slots.iter().any(|other| other.name == slot.name) {
+            return Err(format!("枠「{}」が重複しています", slot.name));
+        }
+        slots.push(slot);
+    }
+    let total: usize = slots.iter().map(|slot| slot.count).sum();
+    if MAX_PLAYERS < total {
+        return Err(format!(
+            "枠の人数の合計は {MAX_PLAYERS} 人以内で指定してください"
+        ));
+    }
+    Ok(slots)
+}
+
+/// 枠のある募集の, 枠ごとの参加者
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SlotFill {
+    /// `slots` の順に, 枠に入った参加者 (参加した順)
+    pub members: Vec<Vec<UserId>>,
+    /// 埋まった枠にしかリアクションしていない参加者と, どの枠にもリアクションしていない参加者
+    pub overflow: Vec<UserId>,
+}
+
+impl SlotFill {
+    /// すべての枠が埋まったかどうか
+    pub fn is_full(&self, slots: &[Slot]) -> bool {
+        slots
+            .iter()
+            .enumerate()
+            .all(|(index, slot)| slot.count <= self.members.get(index).map_or(0, Vec::len))
+    }
+
+    /// 枠の順に並べた参加者と, その後ろに枠に入れなかった参加者
+    ///
+    /// 先着で枠の人数の合計までを開始し, 残りを補欠とする `send_start_notification` にそのまま渡せます。
+    pub fn participants(&self) -> Vec<UserId> {
+        self.members
+            .iter()
+            .flatten()
+            .chain(&self.overflow)
+            .copied()
+            .collect()
+    }
+}
+
+/// 参加した順の `order` の参加者を, 枠ごとのリアクション `reactors` から枠に割り当てます
+///
+/// 参加者は 1 人 1 枠までで, リアクションした枠のうち番号が小さく空きのある枠に入れます。
+pub fn fill(slots: &[Slot], reactors: &[HashSet<UserId>], order: &[UserId]) -> SlotFill {
+    let mut fill = SlotFill {
+        members: vec![Vec::new(); slots.len()],
+        overflow: Vec::new(),
+    };
+    for &user_id in order {
+        let slot_index = slots.iter().enumerate().position(|(index, slot)| {
+            reactors
+                .get(index)
+                .is_some_and(|reactors| reactors.contains(&user_id))
+                && fill.members[index].len() < slot.count
+        });
+        match slot_index {
+            Some(index) => fill.members[index].push(user_id),
+            None => fill.overflow.push(user_id),
+        }
+    }
+    fill
+}
+
+/// `user_id` が `index` 以外の枠にもリアクションしていれば, その枠の番号
+pub fn other_slot(reactors: &[HashSet<UserId>], index: usize, user_id: UserId) -> Option<usize> {
+    reactors
+        .iter()
+        .enumerate()
+        .position(|(other, reactors)| other != index && reactors.contains(&user_id))
+}
+
+/// 開始通知で, 参加者を枠ごとにまとめたメンション (`タンク: @a / ヒーラー: @b @c`)
+pub fn render_groups(slots: &[Slot], fill: &SlotFill, labels: &StartLabels) -> String {
+    slots
+        .iter()
+        .zip(&fill.members)
+        .filter(|(_, members)| !members.is_empty())
+        .map(|(slot, members)| format!("{}: {}", slot.name, labels.join(members)))
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+/// 枠ごとにリアクションしたユーザーを取得します (新しいアカウントは数えません)
+pub async fn fetch_slot_reactors(
+    api: &impl DiscordApi,
+    data: &Data,
+    message: &Message,
+    config: &RecruitConfig,
+    guild_id: Option<GuildId>,
+) -> Result<Vec<HashSet<UserId>>, Error> {
+    let allowed_bot_participants = allowed_bot_participants(data, guild_id);
+    let mut reactors = Vec::new();
+    for reaction_type in config.slot_reaction_types() {
+        let user_ids: HashSet<UserId> =
+            fetch_reaction_users(api, message, reaction_type, &allowed_bot_participants)
+                .await?
+                .into_iter()
+                .collect();
+        reactors.push(user_ids);
+    }
+    let mut eligible: HashSet<UserId> = reactors.iter().flatten().copied().collect();
+    participant_age::retain_eligible(api, data, guild_id, &mut eligible).await;
+    for user_ids in &mut reactors {
+        user_ids.retain(|user_id| eligible.contains(user_id));
+    }
+    Ok(reactors)
+}
+
+/// 枠のある募集で, 枠へのリアクションを処理します
+///
+/// ほかの枠にリアクションしているユーザーのリアクションは外し, すべての枠が埋まったら開始して募集を締め切ります。
+pub async fn handle_slot_reaction(
+    api: &impl DiscordApi,
+    data: &Data,
+    reaction: &Reaction,
+    message_id: MessageId,
+    index: usize,
+) -> Result<(), Error> {
+    let _guard = data.lock_message(message_id).await;
+    // 直前に処理したリアクションで締め切られていないか, 最新の募集メッセージで確認します
+    let message = api.message(reaction.channel_id, message_id).await?;
+    let mut recruit_message = config_storage::load(data, &message)?;
+    let state = &recruit_message.state;
+    if state.started || state.cancelled || state.closed_entries.is_some() {
+        return Ok(());
+    }
+    i18n::resolve_language(data, &mut recruit_message.config, reaction.guild_id);
+    let config = &recruit_message.config;
+    let Some(slot) = config.slots.get(index) else {
+        return Ok(());
+    };
+
+    let mut reactors = fetch_slot_reactors(api, data, &message, config, reaction.guild_id).await?;
+    let allowed_bot_participants = allowed_bot_participants(data, reaction.guild_id);
+    // REST API の結果に反映される前でも, リアクションしたユーザーを枠に数えます
+    if let Some(user_id) = reaction.user_id
+        && reaction
+            .member
+            .as_ref()
+            .is_none_or(|member| is_counted_reactor(&member.user, &allowed_bot_participants))
+    {
+        reactors[index].insert(user_id);
+    }
+
+    let policy = NotificationPolicy::new(config);
+    // 作成やサーバーへの参加が新しいアカウントのリアクションは数えず, ロールも付与しません
+    if let (Some(guild_id), Some(user_id)) = (reaction.guild_id, reaction.user_id)
+        && let Some(reason) =
+            participant_age::rejection(api, data, guild_id, user_id, reaction.member.as_ref()).await
+    {
+        return rejection::notify_rejection(api, data, config, &policy, reaction, user_id, reason)
+            .await;
+    }
+    // 1 人 1 枠までのため, ほかの枠に入っているユーザーが付けたリアクションは外します
+    if let Some(user_id) = reaction.user_id
+        && other_slot(&reactors, index, user_id).is_some()
+    {
+        if let Err(err) = discord_api::delete_user_reaction(api, reaction).await {
+            eprintln!("Failed to remove reaction for another slot of {message_id}: {err}");
+        }
+        return Ok(());
+    }
+
+    let current: HashSet<UserId> = reactors.iter().flatten().copied().collect();
+    let order = participant_order::record_reactor(data, message.id, reaction.user_id, &current)?;
+    let slot_fill = fill(&config.slots, &reactors, &order);
+    if config.notify_on_reaction
+        && let Some(user_id) = reaction.user_id
+    {
+        let language = config.language();
+        let content = language.text(Text::JoinedSlot {
+            user: &user_id.mention().to_string(),
+            game_title: &config.game_title,
+            slot: &slot.name,
+            progress: &format_progress(language, slot_fill.members[index].len(), slot.count),
+        });
+        policy
+            .send(
+                api,
+                data,
+                message.channel_id,
+                policy.message(&content, &Mentions::user(user_id)),
+            )
+            .await?;
+    }
+
+    assign_participant_role(api, data, config, &policy, reaction).await?;
+
+    if !slot_fill.is_full(&config.slots) {
+        return Ok(());
+    }
+    let edited = recruit_edit::edit_recruit_message(
+        api,
+        data,
+        message.channel_id,
+        message.id,
+        |recruit_message| {
+            if recruit_message.state.started {
+                return None;
+            }
+            recruit_message.state.started = true;
+            Some(())
+        },
+    )
+    .await?;
+    if edited.is_none() {
+        return Ok(());
+    }
+    send_start_notification(
+        api,
+        data,
+        config,
+        &policy,
+        &message,
+        reaction.guild_id,
+        slot_fill.participants(),
+    )
+    .await
+}
+
+/// 「タンク」「ヒーラー」などの枠ごとに人数を決めて募集します
+///
+/// 参加者は 1 人 1 枠までで, すべての枠が埋まると開始して募集を締め切ります。
+#[allow(clippy::too_many_arguments)]
+#[poise::command(slash_command, guild_only, rename = "slots")]
+pub async fn recruit_slots(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集するゲーム名"]
+    #[autocomplete = "game_alias::autocomplete_game_title"]
+    game_title: String,
+    #[description = "枠の名前と人数 (例: タンク:1)"] slot_1: String,
+    #[description = "2つ目の枠 (例: ヒーラー:2)"] slot_2: Option<String>,
+    #[description = "3つ目の枠 (例: DPS:5)"] slot_3: Option<String>,
+    #[description = "4つ目の枠"] slot_4: Option<String>,
+    #[description = "5つ目の枠"] slot_5: Option<String>,
+    #[description = "開始時にメンションするロール"] mention_role: Option<Role>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let data = ctx.data();
+    let slots = match build_slots([Some(slot_1), slot_2, slot_3, slot_4, slot_5]) {
+        Ok(slots) => slots,
+        Err(err) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+    };
+
+    let settings = data.storage.guild_settings(guild_id);
+    let member_roles = ctx
+        .author_member()
+        .await
+        .map(|member| member.roles.clone())
+        .unwrap_or_default();
+    let member_permissions = author_permissions(ctx).unwrap_or_default();
+    if !settings.can_create_recruit(&member_roles, member_permissions, mention_role.is_some()) {
+        send_ephemeral(
+            ctx,
+            "募集を作成する権限がありません。主催者ロールを持つメンバーのみ作成できます。",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    if let Some(channel) = ctx.guild_channel().await {
+        match channel_check::check_recruit_channel(ctx, guild_id, &channel).await? {
+            ChannelSuitability::Suitable => {}
+            ChannelSuitability::Refuse(reason) => {
+                send_ephemeral(ctx, reason).await?;
+                return Ok(());
+            }
+            ChannelSuitability::Warn(warnings) => {
+                let content = format!(
+                    "{}\nこのチャンネルに募集を投稿しますか?",
+                    warnings.join("\n")
+                );
+                if !confirm::confirm(ctx, &content, "投稿する").await? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    let required_players = slots.iter().map(|slot| slot.count).sum();
+    let config = RecruitConfig {
+        game_title: sanitize_text(game_title.trim()),
+        required_players,
+        max_players: None,
+        enforce_cap: false,
+        organizer: Some(ctx.author().id),
+        description: None,
+        mention_roles: mention_role.map(|role| role.id).into_iter().collect(),
+        notify_on_reaction: true,
+        auto_assign_role_on_reaction: false,
+        delete_after_minutes: DEFAULT_DELETE_AFTER_MINUTES,
+        start_delay_minutes: None,
+        count_voice_channel: None,
+        require_undeafened: false,
+        simulation: false,
+        extra_reactions: Vec::new(),
+        cleanup_after_start_minutes: settings.cleanup_after_start_minutes,
+        notify_organizer_when_close: false,
+        strict_order: false,
+        game_options: Vec::new(),
+        category: None,
+        start_time: None,
+        remind_before_minutes: None,
+        language: Some(settings.language),
+        roll_call_minutes: None,
+        priority_role: None,
+        priority_until: None,
+        over_mention: OverMention::default(),
+        multi_lobby: false,
+        override_quiet_hours: false,
+        preset_participants: Vec::new(),
+        participation_mode: ParticipationMode::default(),
+        require_voice_at_start: None,
+        voice_grace_minutes: None,
+        slots,
+        reaction_emoji: None,
+        application_question: None,
+    };
+    let posted = post_recruit_message(
+        ctx.serenity_context(),
+        data,
+        Some(guild_id),
+        ctx.channel_id(),
+        config,
+        None,
+        &Mentions::none(),
+    )
+    .await?;
+    let content = format!(
+        "募集メッセージを投稿しました {} {}",
+        format_progress(Language::Ja, 0, required_players),
+        posted.link()
+    );
+    ctx.send(
+        CreateReply::default()
+            .content(content)
+            .ephemeral(true)
+            .components(recruit_actions::components(&posted, ctx.author().id, true)),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot(name: &str, count: usize) -> Slot {
+        Slot {
+            name: name.to_string(),
+            count,
+        }
+    }
+
+    fn users(ids: &[u64]) -> Vec<UserId> {
+        ids.iter().copied().map(UserId::new).collect()
+    }
+
+    fn reactors(ids: &[u64]) -> HashSet<UserId> {
+        users(ids).into_iter().collect()
+    }
+
+    #[test]
+    fn slots_are_read_as_name_and_count() {
+        assert_eq!(parse_slot("slot_1", " タンク：1 "), Ok(slot("タンク", 1)));
+        assert!(parse_slot("slot_1", "タンク").is_err());
+        assert!(parse_slot("slot_1", ":1").is_err());
+        assert!(parse_slot("slot_1", "タンク:0").is_err());
+
+        let slots = build_slots([
+            Some("タンク:1".to_string()),
+            None,
+            Some("ヒーラー:2".to_string()),
+            None,
+            None,
+        ]);
+        assert_eq!(slots, Ok(vec![slot("タンク", 1), slot("ヒーラー", 2)]));
+        let duplicated = build_slots([
+            Some("タンク:1".to_string()),
+            Some("タンク:2".to_string()),
+            None,
+            None,
+            None,
+        ]);
+        assert!(duplicated.is_err());
+    }
+
+    #[test]
+    fn participants_fill_the_first_open_slot_they_reacted_to() {
+        let slots = [slot("タンク", 1), slot("ヒーラー", 1)];
+        let slot_reactors = [reactors(&[11, 12]), reactors(&[12, 13])];
+        let fill = fill(&slots, &slot_reactors, &users(&[11, 12, 13, 14]));
+        assert_eq!(fill.members, [users(&[11]), users(&[12])]);
+        // 埋まった枠にしかリアクションしていない参加者は, 補欠にします
+        assert_eq!(fill.overflow, users(&[13, 14]));
+        assert!(fill.is_full(&slots));
+        assert_eq!(fill.participants(), users(&[11, 12, 13, 14]));
+
+        assert_eq!(other_slot(&slot_reactors, 0, UserId::new(12)), Some(1));
+        assert_eq!(other_slot(&slot_reactors, 0, UserId::new(11)), None);
+    }
+}
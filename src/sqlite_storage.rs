@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::{Mutex, mpsc};
+
+use serde_json::{Map, Value};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+
+use crate::Error;
+use crate::storage::{Storage, StorageData, StorageState};
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS records (
+    table_name TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (table_name, key)
+)";
+const SELECT_ROWS: &str = "SELECT table_name, key, value FROM records";
+const UPSERT_ROW: &str = "INSERT INTO records (table_name, key, value) VALUES (?, ?, ?)
+    ON CONFLICT (table_name, key) DO UPDATE SET value = excluded.value";
+const DELETE_ROW: &str = "DELETE FROM records WHERE table_name = ? AND key = ?";
+
+/// `(表, キー)` ごとの, JSON で表した値
+type Rows = BTreeMap<(String, String), String>;
+
+/// 書き込む行 (`None` の行は消します)
+type Changes = Vec<((String, String), Option<String>)>;
+
+struct WriteRequest {
+    changes: Changes,
+    done: mpsc::Sender<Result<(), Error>>,
+}
+
+/// SQLite のデータベースに保存します
+///
+/// サーバーの設定や募集の記録などは, サーバーや募集ごとに 1 行として保存し, 変わった行だけを書き込みます。
+/// sqlx は非同期で動くため, データベースには専用のスレッドから接続します。
+pub struct SqliteStorage {
+    state: StorageState,
+    /// データベースに書き込んである行
+    written: Mutex<Rows>,
+    writer: UnboundedSender<WriteRequest>,
+}
+
+impl SqliteStorage {
+    /// `url` (例: `sqlite://joinbell.db`) のデータベースを開きます (なければ作成します)
+    pub fn open(url: &str) -> Result<Self, Error> {
+        let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+        let (opened, opened_rx) = mpsc::channel();
+        let (writer, requests) = unbounded_channel();
+        std::thread::Builder::new()
+            .name("joinbell-sqlite".to_string())
+            .spawn(move || {
+                let runtime = match tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(runtime) => runtime,
+                    Err(err) => {
+                        let _ = opened.send(Err(err.into()));
+                        return;
+                    }
+                };
+                runtime.block_on(serve(options, opened, requests));
+            })?;
+        let rows = opened_rx.recv().map_err(|_| "SQLite thread stopped")??;
+        Ok(Self {
+            state: StorageState::new(from_rows(&rows)?),
+            written: Mutex::new(rows),
+            writer,
+        })
+    }
+
+    /// 書き込んである行と異なる行を書き込み, 書き込んだかどうかを返します
+    fn write(&self, data: &StorageData) -> Result<bool, Error> {
+        let rows = to_rows(data)?;
+        let mut written = self.written.lock().unwrap();
+        let mut changes: Changes = rows
+            .iter()
+            .filter(|(key, value)| written.get(*key) != Some(*value))
+            .map(|(key, value)| (key.clone(), Some(value.clone())))
+            .collect();
+        changes.extend(
+            written
+                .keys()
+                .filter(|key| !rows.contains_key(*key))
+                .map(|key| (key.clone(), None)),
+        );
+        if changes.is_empty() {
+            return Ok(false);
+        }
+        let (done, result) = mpsc::channel();
+        self.writer
+            .send(WriteRequest { changes, done })
+            .map_err(|_| "SQLite thread stopped")?;
+        result.recv().map_err(|_| "SQLite thread stopped")??;
+        *written = rows;
+        Ok(true)
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn state(&self) -> &StorageState {
+        &self.state
+    }
+
+    fn persist(&self, data: &StorageData) -> Result<(), Error> {
+        self.write(data)?;
+        Ok(())
+    }
+
+    fn migrate(&self) -> Result<bool, Error> {
+        self.write(&self.state.lock())
+    }
+}
+
+/// データベースに接続して保存した行を返し, `SqliteStorage` が閉じられるまで書き込みを受け付けます
+async fn serve(
+    options: SqliteConnectOptions,
+    opened: mpsc::Sender<Result<Rows, Error>>,
+    mut requests: UnboundedReceiver<WriteRequest>,
+) {
+    let pool = match connect(options).await {
+        Ok(pool) => pool,
+        Err(err) => {
+            let _ = opened.send(Err(err));
+            return;
+        }
+    };
+    let _ = opened.send(read_rows(&pool).await);
+    while let Some(request) = requests.recv().await {
+        let _ = request.done.send(write_rows(&pool, &request.changes).await);
+    }
+    pool.close().await;
+}
+
+async fn connect(options: SqliteConnectOptions) -> Result<SqlitePool, Error> {
+    // `sqlite::memory:` は接続ごとに別のデータベースになるため, 1 つの接続を使い続けます
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .min_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(options)
+        .await?;
+    sqlx::query(CREATE_TABLE).execute(&pool).await?;
+    Ok(pool)
+}
+
+async fn read_rows(pool: &SqlitePool) -> Result<Rows, Error> {
+    let rows: Vec<(String, String, String)> = sqlx::query_as(SELECT_ROWS).fetch_all(pool).await?;
+    Ok(rows
+        .into_iter()
+        .map(|(table_name, key, value)| ((table_name, key), value))
+        .collect())
+}
+
+async fn write_rows(pool: &SqlitePool, changes: &Changes) -> Result<(), Error> {
+    let mut transaction = pool.begin().await?;
+    for ((table_name, key), value) in changes {
+        match value {
+            Some(value) => {
+                sqlx::query(UPSERT_ROW)
+                    .bind(table_name.as_str())
+                    .bind(key.as_str())
+                    .bind(value.as_str())
+                    .execute(&mut *transaction)
+                    .await?
+            }
+            None => {
+                sqlx::query(DELETE_ROW)
+                    .bind(table_name.as_str())
+                    .bind(key.as_str())
+                    .execute(&mut *transaction)
+                    .await?
+            }
+        };
+    }
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// 内容を行に分けます
+///
+/// サーバーや募集ごとの表は項目ごとに 1 行, それ以外 (予約した処理の一覧など) は表全体を
+/// キーが空の 1 行にします。
+fn to_rows(data: &StorageData) -> Result<Rows, Error> {
+    let Value::Object(tables) = serde_json::to_value(data)? else {
+        return Err("storage is not serialized as a table".into());
+    };
+    let mut rows = Rows::new();
+    for (table_name, value) in tables {
+        match value {
+            Value::Object(entries) => {
+                for (key, value) in entries {
+                    rows.insert((table_name.clone(), key), value.to_string());
+                }
+            }
+            value => {
+                rows.insert((table_name, String::new()), value.to_string());
+            }
+        }
+    }
+    Ok(rows)
+}
+
+fn from_rows(rows: &Rows) -> Result<StorageData, Error> {
+    let mut tables = Map::new();
+    for ((table_name, key), value) in rows {
+        let value: Value = serde_json::from_str(value)?;
+        if key.is_empty() {
+            tables.insert(table_name.clone(), value);
+        } else if let Value::Object(entries) = tables
+            .entry(table_name.clone())
+            .or_insert_with(|| Value::Object(Map::new()))
+        {
+            entries.insert(key.clone(), value);
+        }
+    }
+    Ok(serde_json::from_value(Value::Object(tables))?)
+}
@@ -0,0 +1,148 @@
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::discord_api::DiscordApi;
+use crate::discord_error::is_unknown_entity;
+use crate::recruit_message::RecruitConfig;
+use crate::{Data, Error, parse_bot_recruit_message, reset_recruit_reactions, thread};
+
+/// 開始通知を送る前に保存する, 開始済みの印
+///
+/// 開始通知を送ってからリアクションを付け直すまでに停止しても, 再起動後に同じ募集の開始通知を
+/// 送り直さないために使います。
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StartMarker {
+    pub channel_id: ChannelId,
+    pub start_sent_at: Timestamp,
+}
+
+/// 開始済みの印と参加人数から決めた, 募集に対して行う処理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartDecision {
+    /// 開始通知を送ります
+    Send,
+    /// 開始通知は送信済みのため送らず, 中断したリアクションの付け直しをやり直します
+    Rearm,
+    /// 人数が揃っていないため何もしません
+    Wait,
+}
+
+/// 開始通知を送るかどうかを決めます
+///
+/// 開始済みの印がある間は人数が揃っていても開始通知を送りません。
+pub fn decide(marker: Option<&StartMarker>, count_met: bool) -> StartDecision {
+    match (marker, count_met) {
+        (Some(_), _) => StartDecision::Rearm,
+        (None, true) => StartDecision::Send,
+        (None, false) => StartDecision::Wait,
+    }
+}
+
+/// 開始済みの募集のリアクションを付け直し, 繰り返し募集できるようにします
+///
+/// 開始済みの印は付け直しが終わってから消すため, 途中で失敗した場合は次の機会にやり直します。
+/// 複数のゲームから選ぶ募集は開始すると締め切るため, 印は募集メッセージを削除するまで残します。
+pub async fn rearm(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &RecruitConfig,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Result<(), Error> {
+    thread::retry_if_archived(api, channel_id, || {
+        reset_recruit_reactions(api, data, config, channel_id, message_id)
+    })
+    .await?;
+    if !config.has_option_reactions() {
+        data.storage.clear_start_marker(message_id)?;
+        // 次に人数が揃うまでの時間は, 付け直した時刻から数えます
+        if config.start_time.is_none() {
+            data.storage.set_rearmed_at(message_id, Timestamp::now())?;
+        }
+    }
+    Ok(())
+}
+
+/// 募集開始 (付け直した募集では付け直した時刻) から `now` までの秒数
+///
+/// 時計のずれで `now` が基準より前になる場合は `None` を返します。
+pub fn fill_elapsed(
+    posted_at: Timestamp,
+    rearmed_at: Option<Timestamp>,
+    now: Timestamp,
+) -> Option<i64> {
+    let baseline = rearmed_at.unwrap_or(posted_at);
+    let seconds = now.unix_timestamp() - baseline.unix_timestamp();
+    (0 <= seconds).then_some(seconds)
+}
+
+/// 停止中に中断した開始処理の, リアクションの付け直しをやり直します
+pub async fn resume_interrupted_starts(ctx: &Context, data: &Data) {
+    for (message_id, marker) in data.storage.start_markers() {
+        let _guard = data.lock_message(message_id).await;
+        let result = data
+            .rest_budget
+            .run(resume_interrupted_start(ctx, data, message_id, marker))
+            .await;
+        if let Err(err) = result {
+            eprintln!("Failed to resume start of {message_id}: {err}");
+        }
+    }
+}
+
+async fn resume_interrupted_start(
+    ctx: &Context,
+    data: &Data,
+    message_id: MessageId,
+    marker: StartMarker,
+) -> Result<(), Error> {
+    let message = match marker.channel_id.message(ctx, message_id).await {
+        Ok(message) => message,
+        Err(err) if is_unknown_entity(&err).is_some() => {
+            data.forget_deleted_message(message_id);
+            return Ok(());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let Some(recruit_message) = parse_bot_recruit_message(ctx, data, &message) else {
+        return data.storage.clear_start_marker(message_id);
+    };
+    if recruit_message.config.has_option_reactions() {
+        return Ok(());
+    }
+    rearm(
+        ctx,
+        data,
+        &recruit_message.config,
+        marker.channel_id,
+        message_id,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_is_sent_once_until_rearmed() {
+        let marker = StartMarker {
+            channel_id: ChannelId::new(1),
+            start_sent_at: Timestamp::from_unix_timestamp(1_700_000_000).unwrap(),
+        };
+        assert_eq!(decide(None, true), StartDecision::Send);
+        assert_eq!(decide(None, false), StartDecision::Wait);
+        // 印がある間は, 人数が揃っていても付け直しだけをやり直します
+        assert_eq!(decide(Some(&marker), true), StartDecision::Rearm);
+        assert_eq!(decide(Some(&marker), false), StartDecision::Rearm);
+    }
+
+    #[test]
+    fn fill_time_counts_from_the_rearm() {
+        let at = |seconds: i64| Timestamp::from_unix_timestamp(1_700_000_000 + seconds).unwrap();
+        assert_eq!(fill_elapsed(at(0), None, at(90)), Some(90));
+        assert_eq!(fill_elapsed(at(0), Some(at(60)), at(90)), Some(30));
+        // 時計のずれで基準より前になった場合は表示しません
+        assert_eq!(fill_elapsed(at(60), None, at(0)), None);
+    }
+}
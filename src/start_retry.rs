@@ -0,0 +1,220 @@
+use poise::serenity_prelude::{self as serenity, *};
+use serde::{Deserialize, Serialize};
+
+use crate::discord_api::DiscordApi;
+use crate::discord_error::is_unknown_entity;
+use crate::notification::{self, NotificationPolicy};
+use crate::scheduler::{self, Job};
+use crate::{
+    Data, Error, collect_participants, config_storage, i18n, participant_order,
+    send_start_notification,
+};
+
+/// 開始通知を送れなかったときに, 送り直すまでの分数 (合計で約 10 分)
+const RETRY_DELAYS_MINUTES: [u64; 3] = [1, 3, 6];
+
+/// 開始を決めたが, まだ開始通知を送れていない募集
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StartRetry {
+    pub channel_id: ChannelId,
+    /// 送れなかった回数
+    pub failures: usize,
+    /// 最初に開始を決めた日時
+    pub decided_at: Timestamp,
+}
+
+/// 開始通知を送れなかった後に行う処理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryStep {
+    /// `delay_minutes` 分後に送り直します
+    Retry { delay_minutes: u64 },
+    /// 送り直すのをあきらめます
+    GiveUp,
+}
+
+/// `failures` 回送れなかった募集の次の処理を決めます
+pub fn next_step(failures: usize) -> RetryStep {
+    match RETRY_DELAYS_MINUTES.get(failures.saturating_sub(1)) {
+        Some(&delay_minutes) => RetryStep::Retry { delay_minutes },
+        None => RetryStep::GiveUp,
+    }
+}
+
+/// 開始通知を送れたので, 送り直しの記録を消します
+pub fn record_success(data: &Data, message_id: MessageId) {
+    if let Err(err) = data.storage.remove_start_retry(message_id) {
+        eprintln!("Failed to remove start retry: {err}");
+    }
+}
+
+/// 開始通知を送れなかったことを記録し, 送り直しを予約します
+///
+/// 送り直す回数を超えた場合は記録を消し, サーバー設定 `audit_channel` に知らせます。
+pub async fn record_failure(
+    api: &impl DiscordApi,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+    err: &serenity::Error,
+) {
+    let (failures, step) = match advance(data, channel_id, message_id, guild_id) {
+        Ok(advanced) => advanced,
+        Err(err) => {
+            eprintln!("Failed to save start retry: {err}");
+            return;
+        }
+    };
+    match step {
+        RetryStep::Retry { delay_minutes } => eprintln!(
+            "Failed to send start notification of {message_id} (attempt {failures}), retrying in {delay_minutes} minutes: {err}"
+        ),
+        RetryStep::GiveUp => {
+            eprintln!(
+                "Gave up sending start notification of {message_id} after {failures} attempts: {err}"
+            );
+            alert_gave_up(api, data, channel_id, message_id, guild_id, failures).await;
+        }
+    }
+}
+
+/// 送れなかった回数を増やし, 次の送り直しを予約するか記録を消します
+fn advance(
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+) -> Result<(usize, RetryStep), Error> {
+    let retry = match data.storage.start_retry(message_id) {
+        Some(retry) => StartRetry {
+            failures: retry.failures + 1,
+            ..retry
+        },
+        None => StartRetry {
+            channel_id,
+            failures: 1,
+            decided_at: Timestamp::now(),
+        },
+    };
+    let step = next_step(retry.failures);
+    // 送り直しを待つ間に別の開始で送れなかった場合も, 予約は 1 つにします
+    data.storage.cancel_jobs(
+        &|job| matches!(job, Job::RetryStartNotification { message_id: id, .. } if *id == message_id),
+    )?;
+    match step {
+        RetryStep::Retry { delay_minutes } => {
+            data.storage.set_start_retry(message_id, retry)?;
+            data.scheduler.enqueue(
+                &*data.storage,
+                scheduler::fire_at_after_minutes(delay_minutes),
+                Job::RetryStartNotification {
+                    channel_id,
+                    message_id,
+                    guild_id,
+                },
+            );
+        }
+        RetryStep::GiveUp => data.storage.remove_start_retry(message_id)?,
+    }
+    Ok((retry.failures, step))
+}
+
+async fn alert_gave_up(
+    api: &impl DiscordApi,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+    failures: usize,
+) {
+    let Some(audit_channel) =
+        guild_id.and_then(|guild_id| data.storage.guild_settings(guild_id).audit_channel)
+    else {
+        return;
+    };
+    let content = format!(
+        "{} の開始通知を {failures} 回送れなかったため, 送り直しをやめました。Bot のチャンネルの権限を確認してください",
+        message_id.link(channel_id, guild_id)
+    );
+    if let Err(err) = api
+        .send_message(audit_channel, notification::plain_message(content))
+        .await
+    {
+        eprintln!("Failed to send audit alert: {err}");
+    }
+}
+
+/// 送れなかった開始通知を送り直します
+///
+/// 募集がキャンセルされた場合, 開始通知を送信済みの場合, 参加者がいなくなった場合は送りません。
+pub async fn retry_start_notification(
+    ctx: &Context,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+) -> Result<(), Error> {
+    let _guard = data.lock_message(message_id).await;
+    if data.storage.start_retry(message_id).is_none() {
+        return Ok(());
+    }
+    let message = match channel_id.message(ctx, message_id).await {
+        Ok(message) => message,
+        Err(err) if is_unknown_entity(&err).is_some() => {
+            data.forget_deleted_message(message_id);
+            return Ok(());
+        }
+        Err(err) => {
+            // 募集メッセージも読めない場合は, 送れなかった場合と同じように数えます
+            record_failure(ctx, data, channel_id, message_id, guild_id, &err).await;
+            return Ok(());
+        }
+    };
+    let recruit_message = config_storage::load(data, &message)?;
+    let mut config = recruit_message.config;
+    if recruit_message.state.cancelled
+        || recruit_message.state.started
+        || recruit_message.state.closed_entries.is_some()
+        || data.storage.start_marker(message_id).is_some()
+    {
+        record_success(data, message_id);
+        return Ok(());
+    }
+    i18n::resolve_language(data, &mut config, guild_id);
+    let user_ids = collect_participants(ctx, data, &message, &config, guild_id).await?;
+    if user_ids.is_empty() {
+        record_success(data, message_id);
+        return Ok(());
+    }
+    let policy = NotificationPolicy::new(&config);
+    let participants = participant_order::ordered(data, message_id, &user_ids);
+    send_start_notification(
+        ctx,
+        data,
+        &config,
+        &policy,
+        &message,
+        guild_id,
+        participants,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_back_off_then_give_up() {
+        let steps: Vec<RetryStep> = (1..=4).map(next_step).collect();
+        assert_eq!(
+            steps,
+            [
+                RetryStep::Retry { delay_minutes: 1 },
+                RetryStep::Retry { delay_minutes: 3 },
+                RetryStep::Retry { delay_minutes: 6 },
+                RetryStep::GiveUp,
+            ]
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use poise::serenity_prelude::Reaction;
+
+/// 預かるリアクションの上限 (超えた分は通常どおり無視します)
+const MAX_PENDING_REACTIONS: usize = 1000;
+
+/// 起動直後のリアクションを, キャッシュの準備ができてから処理し直すための入れ物
+///
+/// 起動直後はゲートウェイからの取得と競合して, 募集メッセージが部分的にしか読めないことがあります。
+pub struct StartupGate {
+    started_at: Instant,
+    /// 起動してからキャッシュの準備ができるまで, 募集か判定できなかったリアクションを預かる時間
+    grace: Duration,
+    ready: AtomicBool,
+    pending: Mutex<Vec<Reaction>>,
+}
+
+/// 募集か判定できなかったリアクションを預かるかどうか
+pub fn should_hold(ready: bool, elapsed: Duration, grace: Duration, pending: usize) -> bool {
+    !ready && elapsed < grace && pending < MAX_PENDING_REACTIONS
+}
+
+impl StartupGate {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            started_at: Instant::now(),
+            grace,
+            ready: AtomicBool::new(false),
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 準備ができる前なら `reaction` を預かり, 預かった場合は `true` を返します
+    pub fn hold(&self, reaction: &Reaction) -> bool {
+        let mut pending = self.pending.lock().unwrap();
+        let hold = should_hold(
+            self.ready.load(Ordering::SeqCst),
+            self.started_at.elapsed(),
+            self.grace,
+            pending.len(),
+        );
+        if hold {
+            pending.push(reaction.clone());
+        }
+        hold
+    }
+
+    /// 準備ができたことを記録し, 預かっていたリアクションを返します (2 回目以降は空です)
+    pub fn mark_ready(&self) -> Vec<Reaction> {
+        let mut pending = self.pending.lock().unwrap();
+        self.ready.store(true, Ordering::SeqCst);
+        std::mem::take(&mut *pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use poise::serenity_prelude::{ChannelId, GuildId, MessageId};
+
+    use super::*;
+    use crate::mock_discord;
+
+    const GRACE: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn reactions_are_held_only_while_warming_up() {
+        assert!(should_hold(false, Duration::ZERO, GRACE, 0));
+        assert!(!should_hold(true, Duration::ZERO, GRACE, 0));
+        assert!(!should_hold(false, GRACE, GRACE, 0));
+        assert!(!should_hold(
+            false,
+            Duration::ZERO,
+            GRACE,
+            MAX_PENDING_REACTIONS
+        ));
+    }
+
+    #[test]
+    fn held_reactions_are_returned_once() {
+        let message = mock_discord::message(ChannelId::new(1), MessageId::new(2), "");
+        let reaction = mock_discord::reaction(&message, GuildId::new(3), 11, "✋");
+        let gate = StartupGate::new(GRACE);
+        assert!(gate.hold(&reaction));
+        assert_eq!(gate.mark_ready().len(), 1);
+        // 準備ができた後は預かりません
+        assert!(!gate.hold(&reaction));
+        assert!(gate.mark_ready().is_empty());
+    }
+}
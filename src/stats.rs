@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+
+use poise::ChoiceParameter;
+use poise::serenity_prelude::*;
+
+use crate::category::autocomplete_category;
+use crate::close_reason::{CloseReason, ClosureRecord};
+use crate::game_alias::{GameAliases, autocomplete_game_title, normalize};
+use crate::storage::RecruitRecord;
+use crate::{Data, Error};
+
+const LEADERBOARD_SIZE: usize = 10;
+const MEDALS: [&str; 3] = ["🥇", "🥈", "🥉"];
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum Period {
+    #[name = "7d"]
+    Week,
+    #[name = "30d"]
+    Month,
+    #[name = "all"]
+    All,
+}
+
+impl Period {
+    fn days(self) -> Option<i64> {
+        match self {
+            Period::Week => Some(7),
+            Period::Month => Some(30),
+            Period::All => None,
+        }
+    }
+}
+
+/// 参加回数の順位表の1行
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaderboardEntry {
+    pub user_id: UserId,
+    pub count: usize,
+    pub first_seen: Timestamp,
+}
+
+/// 開始した募集への参加回数で並べた順位表を作ります
+///
+/// 参加回数が同じ場合は最初に参加したのが早い順に並べます。
+/// ゲーム名は `aliases` の別名を同じゲームとして集計します。
+pub fn rank_participants(
+    records: &[RecruitRecord],
+    aliases: &GameAliases,
+    game_title: Option<&str>,
+    category: Option<&str>,
+    since: Option<Timestamp>,
+) -> Vec<LeaderboardEntry> {
+    let mut entries: HashMap<UserId, LeaderboardEntry> = HashMap::new();
+    let records = records
+        .iter()
+        .filter(|record| {
+            game_title.is_none_or(|title| aliases.is_same_game(&record.game_title, title))
+        })
+        .filter(|record| {
+            category.is_none_or(|category| {
+                record.category.as_deref().is_some_and(|record_category| {
+                    normalize(record_category) == normalize(category)
+                })
+            })
+        })
+        .filter(|record| since.is_none_or(|since| since <= record.started_at));
+    for record in records {
+        for &user_id in &record.participants {
+            let entry = entries.entry(user_id).or_insert(LeaderboardEntry {
+                user_id,
+                count: 0,
+                first_seen: record.started_at,
+            });
+            entry.count += 1;
+            entry.first_seen = entry.first_seen.min(record.started_at);
+        }
+    }
+
+    let mut entries: Vec<LeaderboardEntry> = entries.into_values().collect();
+    entries.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then(a.first_seen.cmp(&b.first_seen))
+            .then(a.user_id.cmp(&b.user_id))
+    });
+    entries
+}
+
+/// 終わった募集の理由ごとの件数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Outcomes {
+    /// 開始または受付を終了した募集
+    pub filled: usize,
+    /// 開始しないまま削除された募集
+    pub unfilled: usize,
+    pub cancelled: usize,
+}
+
+impl Outcomes {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl std::fmt::Display for Outcomes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "成立 {} / 不成立 {} / キャンセル {}",
+            self.filled, self.unfilled, self.cancelled
+        )
+    }
+}
+
+/// 終わった募集を理由ごとに数えます (絞り込みは `rank_participants` と同じです)
+///
+/// ゲーム名のわからない募集は, ゲーム名やカテゴリで絞り込むときには数えません。
+pub fn count_outcomes(
+    records: &[ClosureRecord],
+    aliases: &GameAliases,
+    game_title: Option<&str>,
+    category: Option<&str>,
+    since: Option<Timestamp>,
+) -> Outcomes {
+    let mut outcomes = Outcomes::default();
+    let records = records
+        .iter()
+        .filter(|record| {
+            game_title.is_none_or(|title| {
+                record
+                    .game_title
+                    .as_deref()
+                    .is_some_and(|record_title| aliases.is_same_game(record_title, title))
+            })
+        })
+        .filter(|record| {
+            category.is_none_or(|category| {
+                record.category.as_deref().is_some_and(|record_category| {
+                    normalize(record_category) == normalize(category)
+                })
+            })
+        })
+        .filter(|record| since.is_none_or(|since| since <= record.closed_at));
+    for record in records {
+        match record.reason {
+            reason if reason.is_filled() => outcomes.filled += 1,
+            CloseReason::Cancelled | CloseReason::ModeratorBulk => outcomes.cancelled += 1,
+            _ => outcomes.unfilled += 1,
+        }
+    }
+    outcomes
+}
+
+/// 募集への参加回数の順位を表示します
+#[poise::command(slash_command, guild_only)]
+pub async fn leaderboard(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "集計するゲーム名"]
+    #[autocomplete = "autocomplete_game_title"]
+    game_title: Option<String>,
+    #[description = "集計する期間 (既定: all)"] period: Option<Period>,
+    #[description = "集計する募集のカテゴリ"]
+    #[autocomplete = "autocomplete_category"]
+    category: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let period = period.unwrap_or(Period::All);
+    let since = period.days().and_then(|days| {
+        Timestamp::from_unix_timestamp(Timestamp::now().unix_timestamp() - days * 24 * 60 * 60).ok()
+    });
+
+    let records = ctx.data().storage.recruit_history(guild_id);
+    let aliases = ctx.data().storage.guild_settings(guild_id).game_aliases;
+    let entries = rank_participants(
+        &records,
+        &aliases,
+        game_title.as_deref(),
+        category.as_deref(),
+        since,
+    );
+    let outcomes = count_outcomes(
+        &ctx.data().storage.closure_history(guild_id),
+        &aliases,
+        game_title.as_deref(),
+        category.as_deref(),
+        since,
+    );
+    if entries.is_empty() {
+        let mut content = "まだ開始した募集の記録がありません".to_string();
+        if !outcomes.is_empty() {
+            content += &format!("\n募集の結果: {outcomes}");
+        }
+        ctx.say(content).await?;
+        return Ok(());
+    }
+
+    let lines: Vec<String> = entries
+        .iter()
+        .take(LEADERBOARD_SIZE)
+        .enumerate()
+        .map(|(index, entry)| {
+            let rank = MEDALS
+                .get(index)
+                .map_or_else(|| format!("{}.", index + 1), ToString::to_string);
+            format!("{rank} {} {}回", entry.user_id.mention(), entry.count)
+        })
+        .collect();
+    let own_rank = match entries
+        .iter()
+        .position(|entry| entry.user_id == ctx.author().id)
+    {
+        Some(index) => format!("あなたの順位: {}位 ({}回)", index + 1, entries[index].count),
+        None => "あなたの参加記録はありません".to_string(),
+    };
+
+    let mut title = match &game_title {
+        Some(game_title) => format!(
+            "{} の参加回数ランキング ({})",
+            aliases.canonical(game_title),
+            period.name()
+        ),
+        None => format!("参加回数ランキング ({})", period.name()),
+    };
+    if let Some(category) = &category {
+        title += &format!(" [{category}]");
+    }
+    let mut embed = CreateEmbed::new()
+        .title(title)
+        .description(lines.join("\n"))
+        .footer(CreateEmbedFooter::new(own_rank));
+    if !outcomes.is_empty() {
+        embed = embed.field("募集の結果", outcomes.to_string(), false);
+    }
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recruit_message::at;
+
+    fn record(game_title: &str, started_at: i64, participants: &[u64]) -> RecruitRecord {
+        RecruitRecord {
+            game_title: game_title.to_string(),
+            category: None,
+            channel_id: ChannelId::new(1),
+            message_id: MessageId::new(2),
+            started_at: at(started_at),
+            participants: participants.iter().copied().map(UserId::new).collect(),
+            required_players: None,
+            reacted_count: None,
+        }
+    }
+
+    fn ranking(entries: &[LeaderboardEntry]) -> Vec<(u64, usize)> {
+        entries
+            .iter()
+            .map(|entry| (entry.user_id.get(), entry.count))
+            .collect()
+    }
+
+    #[test]
+    fn participants_are_ranked_by_count_then_first_participation() {
+        let records = [
+            record("Apex", 0, &[3, 1]),
+            record("Valorant", 10, &[2, 1]),
+            record("Apex", 20, &[2, 4]),
+        ];
+        let aliases = GameAliases::default();
+        let entries = rank_participants(&records, &aliases, None, None, None);
+        // 1 と 2 は 2 回ずつで, 先に参加した 1 が上です
+        assert_eq!(ranking(&entries), [(1, 2), (2, 2), (3, 1), (4, 1)]);
+        assert_eq!(entries[1].first_seen, at(10));
+    }
+
+    #[test]
+    fn records_are_filtered_by_game_and_period() {
+        let records = [
+            record("Apex", 0, &[1]),
+            record("Valorant", 10, &[2]),
+            record("Apex", 20, &[2]),
+        ];
+        let aliases = GameAliases::default();
+        let apex = rank_participants(&records, &aliases, Some("Apex"), None, None);
+        assert_eq!(ranking(&apex), [(1, 1), (2, 1)]);
+        let recent = rank_participants(&records, &aliases, None, None, Some(at(10)));
+        assert_eq!(ranking(&recent), [(2, 2)]);
+        assert!(rank_participants(&records, &aliases, Some("LoL"), None, None).is_empty());
+    }
+
+    fn closure(game_title: Option<&str>, reason: CloseReason, closed_at: i64) -> ClosureRecord {
+        ClosureRecord {
+            message_id: MessageId::new(2),
+            channel_id: ChannelId::new(1),
+            game_title: game_title.map(str::to_string),
+            category: None,
+            reason,
+            closed_at: at(closed_at),
+            participants: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn outcomes_are_counted_by_reason() {
+        let records = [
+            closure(Some("Apex"), CloseReason::Started, 0),
+            closure(Some("apex"), CloseReason::EntriesClosed, 10),
+            closure(Some("Apex"), CloseReason::Expired, 20),
+            closure(Some("Valorant"), CloseReason::Cancelled, 30),
+            closure(None, CloseReason::Deleted, 40),
+        ];
+        let aliases = GameAliases::default();
+        let all = count_outcomes(&records, &aliases, None, None, None);
+        assert_eq!(all.to_string(), "成立 2 / 不成立 2 / キャンセル 1");
+
+        // ゲーム名のわからない募集は, ゲーム名で絞り込むと数えません
+        let apex = count_outcomes(&records, &aliases, Some("APEX"), None, Some(at(10)));
+        assert_eq!(apex.to_string(), "成立 1 / 不成立 1 / キャンセル 0");
+        assert!(count_outcomes(&records, &aliases, None, Some("ランク"), None).is_empty());
+        assert!(!CloseReason::Expired.is_filled());
+    }
+}
@@ -0,0 +1,1991 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use poise::serenity_prelude::{
+    ChannelId, GuildId, MessageId, Permissions, RoleId, Timestamp, UserId,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio::time::{Duration, sleep};
+
+use crate::Error;
+use crate::application::Applications;
+use crate::category::CategoryDefaults;
+use crate::close_reason::{CloseReason, ClosureRecord};
+use crate::config_storage::ConfigStorage;
+use crate::created_role::MAX_ROLE_PREFIX_CHARS;
+use crate::deletion_log::DeletionLogDestination;
+use crate::drop_out::StartedParticipants;
+use crate::game_alias::GameAliases;
+use crate::game_preset::GamePresets;
+use crate::i18n::Language;
+use crate::lobby::LobbyPlacement;
+use crate::member_work::MAX_MEMBER_WORK_CAP;
+use crate::mirror::MirrorMessage;
+use crate::notice_cleanup::CleanupMode;
+use crate::notify_prefs::NotifyPrefs;
+use crate::quiet_hours::QuietHours;
+use crate::reaction_emoji::{self, ReactionEmojis};
+use crate::recruit_log::{self, RecruitEvent};
+use crate::recruit_message::{EmojiPair, RecruitMessage};
+use crate::roll_call::RollCall;
+use crate::scheduler::{JOB_RETRY_DELAY_SECONDS, Job, MAX_JOB_ATTEMPTS, ScheduledJob};
+use crate::start_marker::StartMarker;
+use crate::start_retry::StartRetry;
+use crate::summary::SummaryMessage;
+use crate::time_poll::TimePoll;
+use crate::timezone;
+use crate::voice_gate::VoiceHold;
+
+/// 変更してから書き込むまで待つ時間 (この間の変更はまとめて書き込みます)
+const FLUSH_DELAY: Duration = Duration::from_secs(1);
+
+/// サーバーごとに残す, 開始した募集の記録の件数 (超えた分は古いものから消します)
+pub const MAX_HISTORY_PER_GUILD: usize = 10_000;
+
+/// `Storage` の実装がメモリに読み込んだ内容と, 書き込みの状態
+pub struct StorageState {
+    data: Mutex<StorageData>,
+    /// 変更をすぐに書き込まず, `spawn_flusher` のタスクでまとめて書き込むかどうか
+    deferred: AtomicBool,
+    /// 書き込んでいない変更があるかどうか
+    dirty: AtomicBool,
+    flush_wake: Notify,
+    /// 書き込みを 1 つずつ行うためのロック (古い内容で新しい内容を上書きしないようにします)
+    write_lock: Mutex<()>,
+}
+
+/// 保存する表 (サーバーの設定, 募集ごとの記録, 予約した処理など)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageData {
+    #[serde(default)]
+    guilds: HashMap<GuildId, GuildSettings>,
+    /// 開始した募集の記録
+    #[serde(default)]
+    history: HashMap<GuildId, Vec<RecruitRecord>>,
+    /// 終わった募集の理由の記録
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    closures: HashMap<GuildId, Vec<ClosureRecord>>,
+    /// ロールを最後に開始通知でメンションした日時
+    #[serde(default)]
+    role_pings: HashMap<GuildId, HashMap<RoleId, Timestamp>>,
+    /// 予約済みの処理
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    jobs: Vec<ScheduledJob>,
+    #[serde(default)]
+    next_job_id: u64,
+    /// `strict_order` の募集の参加者の順番
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    participant_orders: HashMap<MessageId, Vec<UserId>>,
+    /// `/join_menu` から参加したユーザー (リアクションの代わりに参加者として数える)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    menu_participants: HashMap<MessageId, Vec<UserId>>,
+    /// 開始通知を送った, またはこれから送る募集
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    start_markers: HashMap<MessageId, StartMarker>,
+    /// 募集メッセージを投稿したサーバーとチャンネル
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    recruit_locations: HashMap<MessageId, RecruitLocation>,
+    /// 点呼中の募集
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    roll_calls: HashMap<MessageId, RollCall>,
+    /// `config_storage = "database"` のサーバーに投稿した募集の設定と状態
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    recruit_messages: HashMap<MessageId, RecruitMessage>,
+    /// 開始後にリアクションを付け直した日時 (次に人数が揃うまでの時間の基準)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    rearmed_at: HashMap<MessageId, Timestamp>,
+    /// 開始すると締め切る募集の, 開始したときの参加者 (🙅 で抜けた参加者を知らせるため)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    started_participants: HashMap<MessageId, StartedParticipants>,
+    /// 優先期間中にリアクションした, 優先するロールを持たない参加者 (リアクションした順)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    deferred_participants: HashMap<MessageId, Vec<UserId>>,
+    /// 開始通知を送れず, 送り直しを待っている募集
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    start_retries: HashMap<MessageId, StartRetry>,
+    /// `multi_lobby` の募集で, ロビーに入った参加者
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    lobby_placements: HashMap<MessageId, LobbyPlacement>,
+    /// `/channel_settings summary_channel` のチャンネルに投稿した今日の募集の一覧
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    summary_messages: HashMap<GuildId, SummaryMessage>,
+    /// `/notify_me` で保存した, ユーザーごとの通知の設定
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    notify_prefs: HashMap<GuildId, HashMap<UserId, NotifyPrefs>>,
+    /// VC にいる参加者が揃うのを待って, 開始を保留している募集
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    voice_holds: HashMap<MessageId, VoiceHold>,
+    /// 投票中の, 募集の開始予定の日時の投票
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    time_polls: HashMap<MessageId, TimePoll>,
+    /// `/moderate_recruits` でまとめて終了している途中の募集と, そのチャンネル
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    bulk_closes: HashMap<MessageId, ChannelId>,
+    /// 募集ごとの, 参加者が変わった記録 (古い順)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    recruit_logs: HashMap<GuildId, HashMap<MessageId, Vec<RecruitEvent>>>,
+    /// `/recruit apply` の募集への応募
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    applications: HashMap<MessageId, Applications>,
+    /// 募集ごとの, `/channel_settings mirror` のチャンネルに投稿したミラー
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    mirrors: HashMap<MessageId, MirrorMessage>,
+}
+
+/// 募集メッセージを投稿したサーバーとチャンネル
+///
+/// サーバーやチャンネルが削除されたときに, 募集ごとの記録を消すために使います。
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RecruitLocation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guild_id: Option<GuildId>,
+    pub channel_id: ChannelId,
+}
+
+/// 記録を消す範囲
+#[derive(Debug, Clone, Copy)]
+enum PurgeScope {
+    Guild(GuildId),
+    Channel(ChannelId),
+}
+
+/// `purge_guild` と `purge_channel` で消した記録
+#[derive(Debug, Default)]
+pub struct PurgeSummary {
+    /// 記録を消した募集メッセージ
+    pub messages: Vec<MessageId>,
+    pub jobs: usize,
+    pub history: usize,
+    pub settings: bool,
+    /// 削除されたチャンネルを指していたため解除した, サーバー設定の項目の数
+    pub channel_settings: usize,
+}
+
+impl PurgeSummary {
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+            && self.jobs == 0
+            && self.history == 0
+            && !self.settings
+            && self.channel_settings == 0
+    }
+}
+
+impl fmt::Display for PurgeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} recruits, {} jobs, {} history records, {} channel settings, settings: {}",
+            self.messages.len(),
+            self.jobs,
+            self.history,
+            self.channel_settings,
+            if self.settings { "removed" } else { "-" }
+        )
+    }
+}
+
+impl StorageData {
+    /// `scope` のサーバーまたはチャンネルに関する記録を消します
+    ///
+    /// 記録の種類を追加したときに消し忘れないよう, すべての項目を分解してから消します。
+    /// チャンネルの削除では, 集計に使う開始した募集の記録は残します。
+    fn purge(&mut self, scope: PurgeScope) -> PurgeSummary {
+        let StorageData {
+            guilds,
+            history,
+            closures,
+            role_pings,
+            jobs,
+            next_job_id: _,
+            participant_orders,
+            menu_participants,
+            start_markers,
+            recruit_locations,
+            roll_calls,
+            recruit_messages,
+            rearmed_at,
+            started_participants,
+            deferred_participants,
+            start_retries,
+            lobby_placements,
+            summary_messages,
+            notify_prefs,
+            voice_holds,
+            time_polls,
+            bulk_closes,
+            recruit_logs,
+            applications,
+            mirrors,
+        } = self;
+
+        // チャンネルしか分からない記録のために, サーバーのチャンネルを集めます
+        let channels: HashSet<ChannelId> = match scope {
+            PurgeScope::Channel(channel_id) => HashSet::from([channel_id]),
+            PurgeScope::Guild(guild_id) => recruit_locations
+                .values()
+                .filter(|location| location.guild_id == Some(guild_id))
+                .map(|location| location.channel_id)
+                .chain(
+                    history
+                        .get(&guild_id)
+                        .into_iter()
+                        .flatten()
+                        .map(|record| record.channel_id),
+                )
+                .chain(
+                    jobs.iter()
+                        .filter(|job| job.payload.guild_id() == Some(guild_id))
+                        .map(|job| job.payload.channel_id()),
+                )
+                .collect(),
+        };
+        let in_scope = |guild_id: Option<GuildId>, channel_id: ChannelId| match scope {
+            PurgeScope::Guild(scope_guild_id) if guild_id == Some(scope_guild_id) => true,
+            _ => channels.contains(&channel_id),
+        };
+
+        let mut messages: HashSet<MessageId> = recruit_locations
+            .iter()
+            .filter(|(_, location)| in_scope(location.guild_id, location.channel_id))
+            .map(|(&message_id, _)| message_id)
+            .collect();
+        messages.extend(
+            start_markers
+                .iter()
+                .filter(|(_, marker)| in_scope(None, marker.channel_id))
+                .map(|(&message_id, _)| message_id),
+        );
+        messages.extend(
+            roll_calls
+                .iter()
+                .filter(|(_, roll_call)| in_scope(None, roll_call.channel_id))
+                .map(|(&message_id, _)| message_id),
+        );
+        messages.extend(
+            started_participants
+                .iter()
+                .filter(|(_, started)| in_scope(None, started.channel_id))
+                .map(|(&message_id, _)| message_id),
+        );
+        messages.extend(
+            start_retries
+                .iter()
+                .filter(|(_, retry)| in_scope(None, retry.channel_id))
+                .map(|(&message_id, _)| message_id),
+        );
+        messages.extend(
+            lobby_placements
+                .iter()
+                .filter(|(_, placement)| in_scope(None, placement.channel_id))
+                .map(|(&message_id, _)| message_id),
+        );
+        messages.extend(
+            voice_holds
+                .iter()
+                .filter(|(_, hold)| in_scope(Some(hold.guild_id), hold.channel_id))
+                .map(|(&message_id, _)| message_id),
+        );
+        messages.extend(
+            time_polls
+                .iter()
+                .filter(|(_, poll)| in_scope(Some(poll.guild_id), poll.channel_id))
+                .map(|(&message_id, _)| message_id),
+        );
+        messages.extend(
+            bulk_closes
+                .iter()
+                .filter(|(_, channel_id)| in_scope(None, **channel_id))
+                .map(|(&message_id, _)| message_id),
+        );
+        messages.extend(
+            applications
+                .iter()
+                .filter(|(_, applications)| {
+                    in_scope(Some(applications.guild_id), applications.channel_id)
+                })
+                .map(|(&message_id, _)| message_id),
+        );
+
+        let mut summary = PurgeSummary::default();
+        let job_count = jobs.len();
+        jobs.retain(|job| {
+            let payload = &job.payload;
+            let purge = in_scope(payload.guild_id(), payload.channel_id())
+                || payload
+                    .recruit_message_id()
+                    .is_some_and(|message_id| messages.contains(&message_id));
+            if purge && let Some(message_id) = payload.recruit_message_id() {
+                messages.insert(message_id);
+            }
+            !purge
+        });
+        summary.jobs = job_count - jobs.len();
+
+        participant_orders.retain(|message_id, _| !messages.contains(message_id));
+        menu_participants.retain(|message_id, _| !messages.contains(message_id));
+        start_markers.retain(|message_id, _| !messages.contains(message_id));
+        recruit_locations.retain(|message_id, _| !messages.contains(message_id));
+        roll_calls.retain(|message_id, _| !messages.contains(message_id));
+        recruit_messages.retain(|message_id, _| !messages.contains(message_id));
+        rearmed_at.retain(|message_id, _| !messages.contains(message_id));
+        started_participants.retain(|message_id, _| !messages.contains(message_id));
+        deferred_participants.retain(|message_id, _| !messages.contains(message_id));
+        start_retries.retain(|message_id, _| !messages.contains(message_id));
+        lobby_placements.retain(|message_id, _| !messages.contains(message_id));
+        voice_holds.retain(|message_id, _| !messages.contains(message_id));
+        time_polls.retain(|message_id, _| !messages.contains(message_id));
+        bulk_closes.retain(|message_id, _| !messages.contains(message_id));
+        applications.retain(|message_id, _| !messages.contains(message_id));
+        // 募集の記録だけを消したミラーは, 削除された募集として書き換えられるよう残します
+        mirrors.retain(|message_id, mirror| {
+            if in_scope(Some(mirror.guild_id), mirror.channel_id) {
+                return false;
+            }
+            if messages.contains(message_id) {
+                mirror.closed.get_or_insert(CloseReason::Deleted);
+            }
+            true
+        });
+        summary_messages.retain(|&guild_id, summary| !in_scope(Some(guild_id), summary.channel_id));
+
+        match scope {
+            // 削除されたチャンネルへ通知やミラーを送り続けないよう, 設定からも外します
+            PurgeScope::Channel(channel_id) => {
+                summary.channel_settings = guilds
+                    .values_mut()
+                    .map(|settings| settings.forget_channel(channel_id))
+                    .sum();
+            }
+            PurgeScope::Guild(guild_id) => {
+                summary.settings = guilds.remove(&guild_id).is_some();
+                summary.history = history.remove(&guild_id).map_or(0, |records| records.len());
+                closures.remove(&guild_id);
+                recruit_logs.remove(&guild_id);
+                role_pings.remove(&guild_id);
+                notify_prefs.remove(&guild_id);
+            }
+        }
+
+        summary.messages = messages.into_iter().collect();
+        summary.messages.sort();
+        summary
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GuildSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// 募集を作成できるロール (未設定なら誰でも作成できる)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organizer_role: Option<RoleId>,
+    /// `organizer_role` をロールへのメンションを含む募集にのみ要求するかどうか
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub organizer_role_for_mentions_only: bool,
+    /// リアクションを数えなかった理由をチャンネルではなく DM で通知するかどうか
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rejection_notice_dm: bool,
+    /// 同じロールを開始通知でメンションする最短の間隔 (分)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role_ping_cooldown_minutes: Option<u64>,
+    /// 参加者として数える Bot
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_bot_participants: Vec<UserId>,
+    /// 募集の `cleanup_after_start_minutes` の既定値
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cleanup_after_start_minutes: Option<u64>,
+    /// 開始予定の日時がある募集で, 参加者にリマインドする開始予定の前の分数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remind_before_minutes: Option<u64>,
+    /// カウントダウン中に主催者が 1 回で開始を延期する分数 (未設定なら 15 分)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snooze_minutes: Option<u64>,
+    /// カウントダウン中に延期できる合計の分数 (未設定なら 60 分, 0 なら延期できません)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_snooze_minutes: Option<u64>,
+    /// 募集メッセージと通知の言語の既定値
+    #[serde(default, skip_serializing_if = "is_default_language")]
+    pub language: Language,
+    /// 新しく投稿する募集の設定の保存先
+    #[serde(default, skip_serializing_if = "is_default_config_storage")]
+    pub config_storage: ConfigStorage,
+    /// 同じゲームとして扱うゲーム名の別名
+    #[serde(default, skip_serializing_if = "GameAliases::is_empty")]
+    pub game_aliases: GameAliases,
+    /// `/settings game add` で登録したゲームの人数
+    #[serde(default, skip_serializing_if = "GamePresets::is_empty")]
+    pub game_players: GamePresets,
+    /// 募集のカテゴリごとの既定値
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub categories: BTreeMap<String, CategoryDefaults>,
+    /// ロールの指定がない `/recruit create` でメンションする, チャンネルごとのロール
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub channel_roles: BTreeMap<ChannelId, RoleId>,
+    /// 開始通知を送れなかったことなど, 管理者向けのお知らせを送るチャンネル
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_channel: Option<ChannelId>,
+    /// `/recruit create` で作成するロールの名前の先頭に付ける文字
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role_name_prefix: Option<String>,
+    /// 開始通知などでメンションしないサイレント時間帯
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<QuietHours>,
+    /// 参加者として数える, アカウントの作成から経った最短の日数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_account_age_days: Option<u64>,
+    /// 参加者として数える, サーバーへの参加から経った最短の日数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_membership_age_days: Option<u64>,
+    /// Discord がスパムの疑いがあるとしたユーザーを参加者として数えないかどうか (`/settings skip_spammers`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skip_spammers: bool,
+    /// Bot が予約して削除したメッセージの記録先
+    #[serde(default, skip_serializing_if = "is_default_deletion_log")]
+    pub deletion_log: DeletionLogDestination,
+    /// 今日の募集の一覧を固定するチャンネル
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary_channel: Option<ChannelId>,
+    /// 募集設定を読み取れないなどのエラーを, 募集のチャンネルの代わりに送るチャンネル
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_channel: Option<ChannelId>,
+    /// 募集のチャンネルに送るエラーで, リアクションしたユーザーをメンションするかどうか
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub error_ping_user: bool,
+    /// `delete_after_minutes` が過ぎた通知が返信されていた場合の扱い
+    #[serde(default, skip_serializing_if = "is_default_cleanup_mode")]
+    pub cleanup_mode: CleanupMode,
+    /// 開始通知で, メンションするロールを持つ参加者を名前だけにするかどうか (`/settings dedupe_mentions`, 未設定ならする)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedupe_mentions: Option<bool>,
+    /// 人数が揃わなかった募集のお知らせで, 参加していたメンバーをメンションするかどうか (`/settings ping_on_fail`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ping_on_fail: bool,
+    /// 新しい募集の参加と通知なしで参加のリアクションの絵文字 (`/settings emoji`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reaction_emoji: Option<EmojiPair>,
+    /// メンバーの情報を 1 人ずつ取得する人数の上限 (`/settings member_work_cap`, 未設定なら 50 人)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member_work_cap: Option<usize>,
+    /// 日時の入力とサイレント時間帯などに使う IANA のタイムゾーン名 (`/settings timezone`, 未設定なら既定のタイムゾーン)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// 募集をミラーするチャンネル (募集するチャンネルからミラーを投稿するチャンネル, `/channel_settings mirror`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub mirror_channels: BTreeMap<ChannelId, ChannelId>,
+}
+
+/// 開始した募集の記録
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecruitRecord {
+    pub game_title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub started_at: Timestamp,
+    pub participants: Vec<UserId>,
+    /// 開始に必要な人数 (記録し始める前の募集にはありません)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_players: Option<usize>,
+    /// 開始したときにリアクションなどで参加していた人数 (補欠を含みます)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reacted_count: Option<usize>,
+}
+
+/// クールダウン中でないためメンションできるロール
+#[derive(Debug, Default)]
+pub struct RolePingClaim {
+    pub allowed: Vec<RoleId>,
+    pub cooling_down: Vec<RoleId>,
+    /// メンションできなかった場合に戻すための, 以前にメンションした日時
+    previous: Vec<(RoleId, Option<Timestamp>)>,
+}
+
+/// 設定ファイル (`/settings export`) に書き出す項目
+const GUILD_SETTINGS_KEYS: &[&str] = &[
+    "webhook_url",
+    "organizer_role",
+    "organizer_role_for_mentions_only",
+    "rejection_notice_dm",
+    "role_ping_cooldown_minutes",
+    "allowed_bot_participants",
+    "cleanup_after_start_minutes",
+    "remind_before_minutes",
+    "snooze_minutes",
+    "max_snooze_minutes",
+    "language",
+    "config_storage",
+    "game_aliases",
+    "game_players",
+    "categories",
+    "channel_roles",
+    "audit_channel",
+    "role_name_prefix",
+    "quiet_hours",
+    "min_account_age_days",
+    "min_membership_age_days",
+    "skip_spammers",
+    "deletion_log",
+    "summary_channel",
+    "error_channel",
+    "error_ping_user",
+    "cleanup_mode",
+    "dedupe_mentions",
+    "ping_on_fail",
+    "reaction_emoji",
+    "member_work_cap",
+    "timezone",
+    "mirror_channels",
+];
+
+impl GuildSettings {
+    pub fn to_toml(&self) -> Result<String, Error> {
+        Ok(toml::to_string(self)?)
+    }
+
+    /// `/settings export` で書き出した TOML を読み取ります
+    ///
+    /// 不明な項目や範囲外の値はエラーにします。ロールなどがサーバーに存在するかは確認しません。
+    pub fn from_toml(content: &str, emojis: &ReactionEmojis) -> Result<Self, Error> {
+        let table: toml::Table = toml::from_str(content)?;
+        if let Some(key) = table
+            .keys()
+            .find(|key| !GUILD_SETTINGS_KEYS.contains(&key.as_str()))
+        {
+            return Err(format!("不明な項目があります: {key}").into());
+        }
+        let mut settings: Self = table.try_into()?;
+        if settings.role_ping_cooldown_minutes == Some(0) {
+            return Err("role_ping_cooldown_minutes は 1 以上を指定してください".into());
+        }
+        if settings.remind_before_minutes == Some(0) {
+            return Err("remind_before_minutes は 1 以上を指定してください".into());
+        }
+        if settings
+            .role_name_prefix
+            .as_ref()
+            .is_some_and(|prefix| MAX_ROLE_PREFIX_CHARS < prefix.chars().count())
+        {
+            return Err(format!(
+                "role_name_prefix は {} 文字以内で指定してください",
+                MAX_ROLE_PREFIX_CHARS
+            )
+            .into());
+        }
+        if let Some(quiet_hours) = settings.quiet_hours
+            && quiet_hours.start == quiet_hours.end
+        {
+            return Err("quiet_hours の start と end には異なる時刻を指定してください".into());
+        }
+        if settings.min_account_age_days == Some(0) {
+            return Err("min_account_age_days は 1 以上を指定してください".into());
+        }
+        if settings.min_membership_age_days == Some(0) {
+            return Err("min_membership_age_days は 1 以上を指定してください".into());
+        }
+        if let Some(pair) = &settings.reaction_emoji {
+            reaction_emoji::check_pair(pair, emojis)
+                .map_err(|err| format!("reaction_emoji が不正です: {err}"))?;
+        }
+        if settings
+            .member_work_cap
+            .is_some_and(|cap| !(1..=MAX_MEMBER_WORK_CAP).contains(&cap))
+        {
+            return Err(format!(
+                "member_work_cap は 1 以上 {MAX_MEMBER_WORK_CAP} 以下を指定してください"
+            )
+            .into());
+        }
+        if let Some(name) = &settings.timezone {
+            let tz = timezone::parse(name).map_err(|err| format!("timezone が不正です: {err}"))?;
+            settings.timezone = Some(tz.name().to_string());
+        }
+        if let Some(channel_id) = settings
+            .mirror_channels
+            .iter()
+            .find(|(source, target)| source == target)
+            .map(|(source, _)| source)
+        {
+            return Err(format!(
+                "mirror_channels の {channel_id} には募集するチャンネルと異なるチャンネルを指定してください"
+            )
+            .into());
+        }
+        if settings.snooze_minutes == Some(0) {
+            return Err("snooze_minutes は 1 以上を指定してください".into());
+        }
+        if let Some(name) = settings
+            .categories
+            .iter()
+            .find(|(_, defaults)| defaults.delete_after_minutes == Some(0))
+            .map(|(name, _)| name)
+        {
+            return Err(format!(
+                "categories.{name}.delete_after_minutes は 1 以上を指定してください"
+            )
+            .into());
+        }
+        if let Some(players) = settings.game_players.iter().find(|players| {
+            players.players == 0
+                || players
+                    .max_players
+                    .is_some_and(|max_players| max_players < players.players)
+        }) {
+            return Err(format!(
+                "game_players の {} は players を 1 以上、max_players を players 以上にしてください",
+                players.title
+            )
+            .into());
+        }
+        settings.game_aliases = settings.game_aliases.normalized();
+        settings.game_players = settings.game_players.normalized();
+        Ok(settings)
+    }
+
+    /// 削除されたチャンネル `channel_id` を指す項目を解除し, 解除した項目の数を返します
+    pub fn forget_channel(&mut self, channel_id: ChannelId) -> usize {
+        let mut forgotten = 0;
+        for channel in [
+            &mut self.audit_channel,
+            &mut self.summary_channel,
+            &mut self.error_channel,
+        ] {
+            if *channel == Some(channel_id) {
+                *channel = None;
+                forgotten += 1;
+            }
+        }
+        forgotten += usize::from(self.channel_roles.remove(&channel_id).is_some());
+        let mirrors = self.mirror_channels.len();
+        self.mirror_channels
+            .retain(|&source, &mut target| source != channel_id && target != channel_id);
+        forgotten + mirrors - self.mirror_channels.len()
+    }
+
+    /// 募集を作成してよいか判定します
+    ///
+    /// 管理者は常に作成でき, `organizer_role_for_mentions_only` の場合は
+    /// ロールへのメンションを含まない募集と @everyone へのメンション権限を持つメンバーを許可します。
+    pub fn can_create_recruit(
+        &self,
+        member_roles: &[RoleId],
+        member_permissions: Permissions,
+        mentions_role: bool,
+    ) -> bool {
+        if member_permissions.administrator() {
+            return true;
+        }
+        let Some(organizer_role) = self.organizer_role else {
+            return true;
+        };
+        if self.organizer_role_for_mentions_only
+            && (!mentions_role || member_permissions.mention_everyone())
+        {
+            return true;
+        }
+        member_roles.contains(&organizer_role)
+    }
+}
+
+impl Default for StorageState {
+    fn default() -> Self {
+        Self::new(StorageData::default())
+    }
+}
+
+impl StorageState {
+    pub fn new(data: StorageData) -> Self {
+        Self {
+            data: Mutex::new(data),
+            deferred: AtomicBool::new(false),
+            dirty: AtomicBool::new(false),
+            flush_wake: Notify::new(),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// 読み込んだ内容
+    pub fn lock(&self) -> MutexGuard<'_, StorageData> {
+        self.data.lock().unwrap()
+    }
+}
+
+/// Bot の設定や募集の記録の保存先
+///
+/// 内容はメモリ (`StorageState`) に読み込んでおき, 変更すると `persist` で保存先に書き込みます。
+/// 保存先ごとの実装は `storage_backend::from_env` で選びます。
+pub trait Storage: Send + Sync + 'static {
+    /// 読み込んだ内容と, 書き込みの状態
+    fn state(&self) -> &StorageState;
+
+    /// 内容を保存先に書き込みます
+    fn persist(&self, data: &StorageData) -> Result<(), Error>;
+
+    /// 保存先を現在の形式で書き直し, 書き直したかどうかを返します
+    ///
+    /// 読み込むときに以前の形式の項目 (既定値で補う項目など) を現在の形式に直すため, 書き直すだけで移行できます。
+    fn migrate(&self) -> Result<bool, Error>;
+
+    /// 書き込んでいない変更を書き込みます
+    ///
+    /// 書き込む間も読み書きできるよう, 内容を複製してからロックを外して書き込みます。
+    /// 複製は `write_lock` を持ったまま作るため, 後から作った複製より先に書き込まれることはありません。
+    fn flush(&self) -> Result<(), Error> {
+        let state = self.state();
+        let _writing = state.write_lock.lock().unwrap();
+        if !state.dirty.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let snapshot = state.lock().clone();
+        let result = self.persist(&snapshot);
+        if result.is_err() {
+            state.dirty.store(true, Ordering::Release);
+        }
+        result
+    }
+
+    fn guild_settings(&self, guild_id: GuildId) -> GuildSettings {
+        self.state()
+            .lock()
+            .guilds
+            .get(&guild_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// サーバーの設定を書き換えます (`update_guild_settings` から呼び出します)
+    fn edit_guild_settings(
+        &self,
+        guild_id: GuildId,
+        update: &mut dyn FnMut(&mut GuildSettings),
+    ) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        update(data.guilds.entry(guild_id).or_default());
+        save(self, &data)
+    }
+
+    /// 開始した募集を記録します (サーバーごとに新しい `MAX_HISTORY_PER_GUILD` 件まで残します)
+    fn record_recruit(&self, guild_id: GuildId, record: RecruitRecord) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        let history = data.history.entry(guild_id).or_default();
+        history.push(record);
+        if MAX_HISTORY_PER_GUILD < history.len() {
+            history.drain(..history.len() - MAX_HISTORY_PER_GUILD);
+        }
+        save(self, &data)
+    }
+
+    /// 募集が終わった理由を記録します (すでに記録した募集なら記録せずに `false` を返します)
+    fn record_closure(&self, guild_id: GuildId, record: ClosureRecord) -> Result<bool, Error> {
+        let mut data = self.state().lock();
+        let records = data.closures.entry(guild_id).or_default();
+        if records
+            .iter()
+            .any(|closure| closure.message_id == record.message_id)
+        {
+            return Ok(false);
+        }
+        records.push(record);
+        save(self, &data)?;
+        Ok(true)
+    }
+
+    fn closure(&self, guild_id: GuildId, message_id: MessageId) -> Option<ClosureRecord> {
+        self.state()
+            .lock()
+            .closures
+            .get(&guild_id)?
+            .iter()
+            .find(|closure| closure.message_id == message_id)
+            .cloned()
+    }
+
+    fn closure_history(&self, guild_id: GuildId) -> Vec<ClosureRecord> {
+        self.state()
+            .lock()
+            .closures
+            .get(&guild_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// 募集の記録に出来事を追加します
+    ///
+    /// 募集ごとに `recruit_log::MAX_EVENTS_PER_RECRUIT` 件まで残し, 古くなった募集の記録は消します (`recruit_log::prune`)。
+    fn append_recruit_event(
+        &self,
+        guild_id: GuildId,
+        message_id: MessageId,
+        event: RecruitEvent,
+    ) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        let at = event.at;
+        let logs = data.recruit_logs.entry(guild_id).or_default();
+        recruit_log::push_bounded(logs.entry(message_id).or_default(), event);
+        recruit_log::prune(logs, at);
+        save(self, &data)
+    }
+
+    fn recruit_events(&self, guild_id: GuildId, message_id: MessageId) -> Vec<RecruitEvent> {
+        self.state()
+            .lock()
+            .recruit_logs
+            .get(&guild_id)
+            .and_then(|logs| logs.get(&message_id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn recruit_history(&self, guild_id: GuildId) -> Vec<RecruitRecord> {
+        self.state()
+            .lock()
+            .history
+            .get(&guild_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// クールダウン中でないロールのメンション日時を `now` に更新し, メンションできるロールを返します
+    ///
+    /// 確認と更新は同じロックの中で行うため, 同時に開始した募集が同じロールを二重にメンションすることはありません。
+    fn claim_role_pings(
+        &self,
+        guild_id: GuildId,
+        role_ids: &[RoleId],
+        now: Timestamp,
+    ) -> Result<RolePingClaim, Error> {
+        let mut data = self.state().lock();
+        let Some(cooldown_minutes) = data
+            .guilds
+            .get(&guild_id)
+            .and_then(|settings| settings.role_ping_cooldown_minutes)
+        else {
+            return Ok(RolePingClaim {
+                allowed: role_ids.to_vec(),
+                ..Default::default()
+            });
+        };
+
+        let cooldown_seconds = cooldown_minutes.saturating_mul(60) as i64;
+        let role_pings = data.role_pings.entry(guild_id).or_default();
+        let mut claim = RolePingClaim::default();
+        for &role_id in role_ids {
+            let previous = role_pings.get(&role_id).copied();
+            let is_cooling_down = previous.is_some_and(|pinged_at| {
+                now.unix_timestamp() - pinged_at.unix_timestamp() < cooldown_seconds
+            });
+            if is_cooling_down {
+                claim.cooling_down.push(role_id);
+            } else {
+                role_pings.insert(role_id, now);
+                claim.allowed.push(role_id);
+                claim.previous.push((role_id, previous));
+            }
+        }
+        if !claim.previous.is_empty() {
+            save(self, &data)?;
+        }
+        Ok(claim)
+    }
+
+    /// 開始通知を送れなかった場合に, `claim_role_pings` で更新したメンション日時を戻します
+    fn release_role_pings(&self, guild_id: GuildId, claim: &RolePingClaim) -> Result<(), Error> {
+        if claim.previous.is_empty() {
+            return Ok(());
+        }
+        let mut data = self.state().lock();
+        let role_pings = data.role_pings.entry(guild_id).or_default();
+        for &(role_id, previous) in &claim.previous {
+            match previous {
+                Some(pinged_at) => role_pings.insert(role_id, pinged_at),
+                None => role_pings.remove(&role_id),
+            };
+        }
+        save(self, &data)
+    }
+
+    fn participant_order(&self, message_id: MessageId) -> Vec<UserId> {
+        self.state()
+            .lock()
+            .participant_orders
+            .get(&message_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn set_participant_order(
+        &self,
+        message_id: MessageId,
+        order: Vec<UserId>,
+    ) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if order.is_empty() {
+            data.participant_orders.remove(&message_id);
+        } else {
+            data.participant_orders.insert(message_id, order);
+        }
+        save(self, &data)
+    }
+
+    fn remove_participant_order(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.participant_orders.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    fn menu_participants(&self, message_id: MessageId) -> Vec<UserId> {
+        self.state()
+            .lock()
+            .menu_participants
+            .get(&message_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn add_menu_participant(&self, message_id: MessageId, user_id: UserId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        let participants = data.menu_participants.entry(message_id).or_default();
+        if participants.contains(&user_id) {
+            return Ok(());
+        }
+        participants.push(user_id);
+        save(self, &data)
+    }
+
+    fn remove_menu_participants(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.menu_participants.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    fn start_marker(&self, message_id: MessageId) -> Option<StartMarker> {
+        self.state().lock().start_markers.get(&message_id).copied()
+    }
+
+    fn start_markers(&self) -> Vec<(MessageId, StartMarker)> {
+        self.state()
+            .lock()
+            .start_markers
+            .iter()
+            .map(|(&message_id, &marker)| (message_id, marker))
+            .collect()
+    }
+
+    /// 開始済みの印を保存します (開始通知を送る前に呼び出してください)
+    fn set_start_marker(&self, message_id: MessageId, marker: StartMarker) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        data.start_markers.insert(message_id, marker);
+        save(self, &data)
+    }
+
+    fn clear_start_marker(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.start_markers.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    fn start_retry(&self, message_id: MessageId) -> Option<StartRetry> {
+        self.state().lock().start_retries.get(&message_id).copied()
+    }
+
+    fn set_start_retry(&self, message_id: MessageId, retry: StartRetry) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        data.start_retries.insert(message_id, retry);
+        save(self, &data)
+    }
+
+    fn remove_start_retry(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.start_retries.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    fn voice_hold(&self, message_id: MessageId) -> Option<VoiceHold> {
+        self.state().lock().voice_holds.get(&message_id).copied()
+    }
+
+    /// `voice_channel_id` の VC を待っている募集
+    fn voice_holds_in(&self, voice_channel_id: ChannelId) -> Vec<(MessageId, VoiceHold)> {
+        self.state()
+            .lock()
+            .voice_holds
+            .iter()
+            .filter(|(_, hold)| hold.voice_channel_id == voice_channel_id)
+            .map(|(&message_id, &hold)| (message_id, hold))
+            .collect()
+    }
+
+    fn set_voice_hold(&self, message_id: MessageId, hold: VoiceHold) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        data.voice_holds.insert(message_id, hold);
+        save(self, &data)
+    }
+
+    fn remove_voice_hold(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.voice_holds.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    fn time_poll(&self, message_id: MessageId) -> Option<TimePoll> {
+        self.state().lock().time_polls.get(&message_id).cloned()
+    }
+
+    fn set_time_poll(&self, message_id: MessageId, poll: TimePoll) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        data.time_polls.insert(message_id, poll);
+        save(self, &data)
+    }
+
+    fn remove_time_poll(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.time_polls.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    fn applications(&self, message_id: MessageId) -> Option<Applications> {
+        self.state().lock().applications.get(&message_id).cloned()
+    }
+
+    /// 募集への応募を書き換えます (`update_applications` から呼び出します)
+    fn edit_applications(
+        &self,
+        message_id: MessageId,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        f: &mut dyn FnMut(&mut Applications),
+    ) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        let applications = data
+            .applications
+            .entry(message_id)
+            .or_insert_with(|| Applications::new(guild_id, channel_id));
+        f(applications);
+        if applications.entries.is_empty() {
+            data.applications.remove(&message_id);
+        }
+        save(self, &data)
+    }
+
+    fn remove_applications(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.applications.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    /// まとめて終了している途中の募集 (古い順)
+    fn bulk_closes(&self) -> Vec<(MessageId, ChannelId)> {
+        let mut targets: Vec<(MessageId, ChannelId)> = self
+            .state()
+            .lock()
+            .bulk_closes
+            .iter()
+            .map(|(&message_id, &channel_id)| (message_id, channel_id))
+            .collect();
+        targets.sort();
+        targets
+    }
+
+    /// まとめて終了する募集を, 終了し始める前にまとめて保存します
+    fn add_bulk_closes(&self, targets: &[(MessageId, ChannelId)]) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        data.bulk_closes.extend(targets.iter().copied());
+        save(self, &data)
+    }
+
+    fn remove_bulk_close(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.bulk_closes.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    fn lobby_placement(&self, message_id: MessageId) -> Option<LobbyPlacement> {
+        self.state()
+            .lock()
+            .lobby_placements
+            .get(&message_id)
+            .cloned()
+    }
+
+    fn set_lobby_placement(
+        &self,
+        message_id: MessageId,
+        placement: LobbyPlacement,
+    ) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        data.lobby_placements.insert(message_id, placement);
+        save(self, &data)
+    }
+
+    fn remove_lobby_placement(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.lobby_placements.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    fn summary_message(&self, guild_id: GuildId) -> Option<SummaryMessage> {
+        self.state().lock().summary_messages.get(&guild_id).copied()
+    }
+
+    /// 今日の募集の一覧のメッセージを記録します (`None` で記録を消します)
+    fn set_summary_message(
+        &self,
+        guild_id: GuildId,
+        summary: Option<SummaryMessage>,
+    ) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        let previous = match summary {
+            Some(summary) => data.summary_messages.insert(guild_id, summary),
+            None => data.summary_messages.remove(&guild_id),
+        };
+        if previous == summary {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    /// 募集のミラー
+    fn mirror(&self, message_id: MessageId) -> Option<MirrorMessage> {
+        self.state().lock().mirrors.get(&message_id).cloned()
+    }
+
+    /// 募集のミラーを記録します (`None` で記録を消します)
+    fn set_mirror(
+        &self,
+        message_id: MessageId,
+        mirror: Option<MirrorMessage>,
+    ) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        let previous = match mirror.clone() {
+            Some(mirror) => data.mirrors.insert(message_id, mirror),
+            None => data.mirrors.remove(&message_id),
+        };
+        if previous == mirror {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    /// 募集のミラーを書き換え, ミラーがあったかどうかを返します (`update_mirror` から呼び出します)
+    fn edit_mirror(
+        &self,
+        message_id: MessageId,
+        f: &mut dyn FnMut(&mut MirrorMessage),
+    ) -> Result<bool, Error> {
+        let mut data = self.state().lock();
+        let Some(mirror) = data.mirrors.get_mut(&message_id) else {
+            return Ok(false);
+        };
+        f(mirror);
+        save(self, &data)?;
+        Ok(true)
+    }
+
+    /// `message_id` が Bot の投稿したミラーのメッセージかどうか
+    fn is_mirror_message(&self, message_id: MessageId) -> bool {
+        self.state()
+            .lock()
+            .mirrors
+            .values()
+            .any(|mirror| mirror.message_id == message_id)
+    }
+
+    /// 募集メッセージの場所を記録します (記録済みなら何もしません)
+    fn remember_recruit_location(
+        &self,
+        message_id: MessageId,
+        location: RecruitLocation,
+    ) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.recruit_locations.get(&message_id) == Some(&location) {
+            return Ok(());
+        }
+        data.recruit_locations.insert(message_id, location);
+        save(self, &data)
+    }
+
+    fn recruit_location(&self, message_id: MessageId) -> Option<RecruitLocation> {
+        self.state()
+            .lock()
+            .recruit_locations
+            .get(&message_id)
+            .copied()
+    }
+
+    /// 場所を記録した募集メッセージかどうか
+    fn has_recruit_location(&self, message_id: MessageId) -> bool {
+        self.state()
+            .lock()
+            .recruit_locations
+            .contains_key(&message_id)
+    }
+
+    /// サーバーに投稿した募集メッセージと, そのチャンネル
+    fn recruit_locations_in_guild(&self, guild_id: GuildId) -> Vec<(MessageId, ChannelId)> {
+        let mut locations: Vec<(MessageId, ChannelId)> = self
+            .state()
+            .lock()
+            .recruit_locations
+            .iter()
+            .filter(|(_, location)| location.guild_id == Some(guild_id))
+            .map(|(&message_id, location)| (message_id, location.channel_id))
+            .collect();
+        locations.sort();
+        locations
+    }
+
+    /// サーバーを抜けたユーザーや, 参加をやめたユーザーを, 募集の参加者の順番と `/join_menu` の参加者から除きます
+    ///
+    /// どちらかに記録されていた場合は `true` を返します。
+    fn remove_participant(&self, message_id: MessageId, user_id: UserId) -> Result<bool, Error> {
+        let mut guard = self.state().lock();
+        let data = &mut *guard;
+        let mut removed = false;
+        for participants in [
+            data.participant_orders.get_mut(&message_id),
+            data.menu_participants.get_mut(&message_id),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let len = participants.len();
+            participants.retain(|&id| id != user_id);
+            removed |= participants.len() != len;
+        }
+        if !removed {
+            return Ok(false);
+        }
+        data.participant_orders
+            .retain(|_, participants| !participants.is_empty());
+        data.menu_participants
+            .retain(|_, participants| !participants.is_empty());
+        save(self, data)?;
+        Ok(true)
+    }
+
+    fn remove_recruit_location(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.recruit_locations.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    fn recruit_message(&self, message_id: MessageId) -> Option<RecruitMessage> {
+        self.state()
+            .lock()
+            .recruit_messages
+            .get(&message_id)
+            .cloned()
+    }
+
+    /// 募集の設定と状態を保存します (変わっていなければ何もしません)
+    fn set_recruit_message(
+        &self,
+        message_id: MessageId,
+        recruit_message: RecruitMessage,
+    ) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.recruit_messages.get(&message_id) == Some(&recruit_message) {
+            return Ok(());
+        }
+        data.recruit_messages.insert(message_id, recruit_message);
+        save(self, &data)
+    }
+
+    fn remove_recruit_message(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.recruit_messages.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    fn rearmed_at(&self, message_id: MessageId) -> Option<Timestamp> {
+        self.state().lock().rearmed_at.get(&message_id).copied()
+    }
+
+    fn set_rearmed_at(&self, message_id: MessageId, rearmed_at: Timestamp) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        data.rearmed_at.insert(message_id, rearmed_at);
+        save(self, &data)
+    }
+
+    fn remove_rearmed_at(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.rearmed_at.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    fn deferred_participants(&self, message_id: MessageId) -> Vec<UserId> {
+        self.state()
+            .lock()
+            .deferred_participants
+            .get(&message_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// 後回しにした参加者を記録します (記録済みなら `false` を返します)
+    fn add_deferred_participant(
+        &self,
+        message_id: MessageId,
+        user_id: UserId,
+    ) -> Result<bool, Error> {
+        let mut data = self.state().lock();
+        let deferred = data.deferred_participants.entry(message_id).or_default();
+        if deferred.contains(&user_id) {
+            return Ok(false);
+        }
+        deferred.push(user_id);
+        save(self, &data)?;
+        Ok(true)
+    }
+
+    fn notify_prefs(&self, guild_id: GuildId, user_id: UserId) -> NotifyPrefs {
+        self.state()
+            .lock()
+            .notify_prefs
+            .get(&guild_id)
+            .and_then(|prefs| prefs.get(&user_id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// ユーザーの通知の設定を保存します (すべて未設定なら記録を消します)
+    fn set_notify_prefs(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        prefs: NotifyPrefs,
+    ) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        let guild_prefs = data.notify_prefs.entry(guild_id).or_default();
+        let previous = if prefs == NotifyPrefs::default() {
+            guild_prefs.remove(&user_id)
+        } else {
+            guild_prefs.insert(user_id, prefs)
+        };
+        if guild_prefs.is_empty() {
+            data.notify_prefs.remove(&guild_id);
+        }
+        if previous.unwrap_or_default() == prefs {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    fn remove_deferred_participants(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.deferred_participants.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    fn started_participants(&self, message_id: MessageId) -> Option<StartedParticipants> {
+        self.state()
+            .lock()
+            .started_participants
+            .get(&message_id)
+            .cloned()
+    }
+
+    fn set_started_participants(
+        &self,
+        message_id: MessageId,
+        started: StartedParticipants,
+    ) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        data.started_participants.insert(message_id, started);
+        save(self, &data)
+    }
+
+    fn remove_started_participants(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.started_participants.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    fn roll_call(&self, message_id: MessageId) -> Option<RollCall> {
+        self.state().lock().roll_calls.get(&message_id).cloned()
+    }
+
+    fn set_roll_call(&self, message_id: MessageId, roll_call: RollCall) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        data.roll_calls.insert(message_id, roll_call);
+        save(self, &data)
+    }
+
+    /// 点呼に ✅ を押した参加者を記録し, 確認済みの参加者を返します (点呼中でなければ空)
+    fn confirm_roll_call(
+        &self,
+        message_id: MessageId,
+        user_id: UserId,
+    ) -> Result<Vec<UserId>, Error> {
+        let mut data = self.state().lock();
+        let Some(roll_call) = data.roll_calls.get_mut(&message_id) else {
+            return Ok(Vec::new());
+        };
+        if roll_call.confirmed.contains(&user_id) {
+            return Ok(roll_call.confirmed.clone());
+        }
+        roll_call.confirmed.push(user_id);
+        let confirmed = roll_call.confirmed.clone();
+        save(self, &data)?;
+        Ok(confirmed)
+    }
+
+    fn remove_roll_call(&self, message_id: MessageId) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        if data.roll_calls.remove(&message_id).is_none() {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    /// Bot が参加しなくなったサーバーの設定や記録, 予約した処理をすべて消します
+    fn purge_guild(&self, guild_id: GuildId) -> Result<PurgeSummary, Error> {
+        purge(self, PurgeScope::Guild(guild_id))
+    }
+
+    /// 削除されたチャンネルの募集の記録と予約した処理を消します
+    fn purge_channel(&self, channel_id: ChannelId) -> Result<PurgeSummary, Error> {
+        purge(self, PurgeScope::Channel(channel_id))
+    }
+
+    /// 募集メッセージ `message_id` について, 保存している記録や予約があるかどうか (募集の記録は除きます)
+    fn has_message_records(&self, message_id: MessageId) -> bool {
+        let data = self.state().lock();
+        data.recruit_locations.contains_key(&message_id)
+            || data.recruit_messages.contains_key(&message_id)
+            || data.participant_orders.contains_key(&message_id)
+            || data.menu_participants.contains_key(&message_id)
+            || data.start_markers.contains_key(&message_id)
+            || data.roll_calls.contains_key(&message_id)
+            || data.rearmed_at.contains_key(&message_id)
+            || data.started_participants.contains_key(&message_id)
+            || data.deferred_participants.contains_key(&message_id)
+            || data.start_retries.contains_key(&message_id)
+            || data.lobby_placements.contains_key(&message_id)
+            || data.voice_holds.contains_key(&message_id)
+            || data.time_polls.contains_key(&message_id)
+            || data.applications.contains_key(&message_id)
+            || data
+                .jobs
+                .iter()
+                .any(|job| job.payload.recruit_message_id() == Some(message_id))
+    }
+
+    fn push_job(&self, fire_at: Timestamp, payload: Job) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        let id = data.next_job_id;
+        data.next_job_id += 1;
+        data.jobs.push(ScheduledJob {
+            id,
+            fire_at,
+            payload,
+            attempts: 0,
+            leased: false,
+        });
+        save(self, &data)
+    }
+
+    fn jobs(&self) -> Vec<ScheduledJob> {
+        self.state().lock().jobs.clone()
+    }
+
+    /// 実行時刻を過ぎた処理を実行中にして返します
+    ///
+    /// 予約は実行し終える (`complete_job` / `retry_job`) まで残します。実行中に停止して
+    /// `MAX_JOB_ATTEMPTS` 回実行を始めた処理は, 実行し直さずに消します。
+    fn lease_due_jobs(&self, now: Timestamp) -> Vec<ScheduledJob> {
+        let mut data = self.state().lock();
+        let len = data.jobs.len();
+        data.jobs.retain(|job| {
+            let keep = job.leased || job.attempts < MAX_JOB_ATTEMPTS;
+            if !keep {
+                eprintln!(
+                    "Dropped scheduled job {} after {} interrupted attempts",
+                    job.id, job.attempts
+                );
+            }
+            keep
+        });
+        let mut due = Vec::new();
+        for job in &mut data.jobs {
+            if !job.leased && job.fire_at.unix_timestamp() <= now.unix_timestamp() {
+                job.leased = true;
+                job.attempts += 1;
+                due.push(job.clone());
+            }
+        }
+        if (!due.is_empty() || data.jobs.len() != len)
+            && let Err(err) = save(self, &data)
+        {
+            eprintln!("Failed to save scheduled jobs: {err}");
+        }
+        due
+    }
+
+    /// 実行し終えた処理の予約を消します
+    fn complete_job(&self, id: u64) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        let len = data.jobs.len();
+        data.jobs.retain(|job| job.id != id);
+        if data.jobs.len() == len {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+
+    /// 失敗した処理を, 時間を置いて実行し直すよう予約し直します
+    ///
+    /// `MAX_JOB_ATTEMPTS` 回失敗した処理は予約を消し, false を返します。
+    fn retry_job(&self, id: u64, now: Timestamp) -> Result<bool, Error> {
+        let mut data = self.state().lock();
+        let Some(index) = data.jobs.iter().position(|job| job.id == id) else {
+            return Ok(true);
+        };
+        let job = &mut data.jobs[index];
+        let retried = job.attempts < MAX_JOB_ATTEMPTS;
+        if retried {
+            let delay = JOB_RETRY_DELAY_SECONDS * i64::from(job.attempts);
+            job.fire_at =
+                Timestamp::from_unix_timestamp(now.unix_timestamp() + delay).unwrap_or(now);
+            job.leased = false;
+        } else {
+            data.jobs.remove(index);
+        }
+        save(self, &data)?;
+        Ok(retried)
+    }
+
+    /// 実行中でない処理のうち, 最も早い実行時刻
+    fn next_fire_at(&self) -> Option<Timestamp> {
+        self.state()
+            .lock()
+            .jobs
+            .iter()
+            .filter(|job| !job.leased)
+            .map(|job| job.fire_at)
+            .min_by_key(|fire_at| fire_at.unix_timestamp())
+    }
+
+    /// `remove` が true を返す予約を取り消します
+    fn cancel_jobs(&self, remove: &dyn Fn(&Job) -> bool) -> Result<(), Error> {
+        let mut data = self.state().lock();
+        let len = data.jobs.len();
+        data.jobs.retain(|job| !remove(&job.payload));
+        if data.jobs.len() == len {
+            return Ok(());
+        }
+        save(self, &data)
+    }
+}
+
+impl dyn Storage {
+    /// 変更を `FLUSH_DELAY` ごとにまとめて, 非同期のランタイムの外で書き込むタスクを開始します
+    ///
+    /// 開始するまでは, 変更するたびにすぐ書き込みます。停止する前に `flush` を呼び出してください。
+    pub fn spawn_flusher(self: &Arc<Self>) {
+        self.state().deferred.store(true, Ordering::Release);
+        let storage = self.clone();
+        tokio::spawn(async move {
+            loop {
+                storage.state().flush_wake.notified().await;
+                sleep(FLUSH_DELAY).await;
+                let flushing = storage.clone();
+                match tokio::task::spawn_blocking(move || flushing.flush()).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => {
+                        eprintln!("Failed to save storage: {err}; retrying");
+                        storage.state().flush_wake.notify_one();
+                    }
+                    Err(err) => eprintln!("Failed to save storage: {err}"),
+                }
+            }
+        });
+    }
+
+    pub fn update_guild_settings(
+        &self,
+        guild_id: GuildId,
+        update: impl FnOnce(&mut GuildSettings),
+    ) -> Result<(), Error> {
+        let mut update = Some(update);
+        self.edit_guild_settings(guild_id, &mut |settings| {
+            if let Some(update) = update.take() {
+                update(settings);
+            }
+        })
+    }
+
+    /// 募集への応募を書き換えます (記録がなければ `guild_id` と `channel_id` の募集として作成します)
+    ///
+    /// 応募がなくなった募集の記録は消します。
+    pub fn update_applications<R>(
+        &self,
+        message_id: MessageId,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        f: impl FnOnce(&mut Applications) -> R,
+    ) -> Result<R, Error> {
+        let mut f = Some(f);
+        let mut result = None;
+        self.edit_applications(message_id, guild_id, channel_id, &mut |applications| {
+            result = f.take().map(|f| f(applications));
+        })?;
+        Ok(result.expect("edit_applications calls the closure"))
+    }
+
+    /// 募集のミラーを書き換えます (ミラーがなければ何もせず `None` を返します)
+    pub fn update_mirror<R>(
+        &self,
+        message_id: MessageId,
+        f: impl FnOnce(&mut MirrorMessage) -> R,
+    ) -> Result<Option<R>, Error> {
+        let mut f = Some(f);
+        let mut result = None;
+        self.edit_mirror(message_id, &mut |mirror| {
+            result = f.take().map(|f| f(mirror));
+        })?;
+        Ok(result)
+    }
+}
+
+/// 変更を書き込みます (`spawn_flusher` の後は, 書き込みを予約します)
+fn save<S: Storage + ?Sized>(storage: &S, data: &StorageData) -> Result<(), Error> {
+    let state = storage.state();
+    if state.deferred.load(Ordering::Acquire) {
+        state.dirty.store(true, Ordering::Release);
+        state.flush_wake.notify_one();
+        return Ok(());
+    }
+    storage.persist(data)
+}
+
+fn purge<S: Storage + ?Sized>(storage: &S, scope: PurgeScope) -> Result<PurgeSummary, Error> {
+    let mut data = storage.state().lock();
+    let summary = data.purge(scope);
+    if !summary.is_empty() {
+        save(storage, &data)?;
+    }
+    Ok(summary)
+}
+
+fn is_default_language(language: &Language) -> bool {
+    *language == Language::default()
+}
+
+fn is_default_config_storage(config_storage: &ConfigStorage) -> bool {
+    *config_storage == ConfigStorage::default()
+}
+
+fn is_default_cleanup_mode(cleanup_mode: &CleanupMode) -> bool {
+    *cleanup_mode == CleanupMode::default()
+}
+
+fn is_default_deletion_log(deletion_log: &DeletionLogDestination) -> bool {
+    *deletion_log == DeletionLogDestination::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::quiet_hours::TimeOfDay;
+
+    /// 書き込んだ回数と最後に書き込んだ内容を記録する保存先
+    #[derive(Default)]
+    struct CountingStorage {
+        state: StorageState,
+        writes: AtomicUsize,
+        content: Mutex<Option<String>>,
+    }
+
+    impl CountingStorage {
+        /// 停止して, 最後に書き込んだ内容から読み込み直します
+        fn reopen(&self) -> Self {
+            let content = self.content.lock().unwrap().clone();
+            let data = toml::from_str(content.as_deref().unwrap_or_default()).unwrap();
+            Self {
+                state: StorageState::new(data),
+                writes: AtomicUsize::new(0),
+                content: Mutex::new(content),
+            }
+        }
+    }
+
+    impl Storage for CountingStorage {
+        fn state(&self) -> &StorageState {
+            &self.state
+        }
+
+        fn persist(&self, data: &StorageData) -> Result<(), Error> {
+            self.writes.fetch_add(1, Ordering::SeqCst);
+            *self.content.lock().unwrap() = Some(toml::to_string(data)?);
+            Ok(())
+        }
+
+        fn migrate(&self) -> Result<bool, Error> {
+            Ok(false)
+        }
+    }
+
+    fn record(started_at: i64) -> RecruitRecord {
+        RecruitRecord {
+            game_title: "Apex".to_string(),
+            category: None,
+            channel_id: ChannelId::new(1),
+            message_id: MessageId::new(2),
+            started_at: Timestamp::from_unix_timestamp(started_at).unwrap(),
+            participants: vec![UserId::new(3)],
+            required_players: Some(1),
+            reacted_count: Some(1),
+        }
+    }
+
+    #[test]
+    fn writes_immediately_without_flusher() {
+        let storage = CountingStorage::default();
+        storage.record_recruit(GuildId::new(1), record(0)).unwrap();
+        storage.record_recruit(GuildId::new(1), record(1)).unwrap();
+        assert_eq!(storage.writes.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn flusher_batches_writes() {
+        let counting = Arc::new(CountingStorage::default());
+        let storage: Arc<dyn Storage> = counting.clone();
+        storage.spawn_flusher();
+        for started_at in 0..50 {
+            storage
+                .record_recruit(GuildId::new(1), record(started_at))
+                .unwrap();
+        }
+        assert_eq!(counting.writes.load(Ordering::SeqCst), 0);
+
+        storage.flush().unwrap();
+        assert_eq!(counting.writes.load(Ordering::SeqCst), 1);
+        // 変更がなければ書き込みません
+        storage.flush().unwrap();
+        assert_eq!(counting.writes.load(Ordering::SeqCst), 1);
+
+        let reloaded = counting.reopen();
+        assert_eq!(reloaded.recruit_history(GuildId::new(1)).len(), 50);
+    }
+
+    /// 書き込みを始めたことを知らせ, 知らせを受けるまで書き込みを終えない保存先
+    struct BlockingStorage {
+        state: StorageState,
+        started: Mutex<std::sync::mpsc::Sender<()>>,
+        release: Mutex<std::sync::mpsc::Receiver<()>>,
+    }
+
+    impl Storage for BlockingStorage {
+        fn state(&self) -> &StorageState {
+            &self.state
+        }
+
+        fn persist(&self, _data: &StorageData) -> Result<(), Error> {
+            self.started.lock().unwrap().send(()).unwrap();
+            self.release.lock().unwrap().recv().unwrap();
+            Ok(())
+        }
+
+        fn migrate(&self) -> Result<bool, Error> {
+            Ok(false)
+        }
+    }
+
+    #[test]
+    fn storage_is_usable_while_a_flush_is_writing() {
+        let (started, started_rx) = std::sync::mpsc::channel();
+        let (release, release_rx) = std::sync::mpsc::channel();
+        let storage = Arc::new(BlockingStorage {
+            state: StorageState::default(),
+            started: Mutex::new(started),
+            release: Mutex::new(release_rx),
+        });
+        storage.state.deferred.store(true, Ordering::Release);
+        storage.record_recruit(GuildId::new(1), record(0)).unwrap();
+
+        let flushing = storage.clone();
+        let flush = std::thread::spawn(move || flushing.flush());
+        started_rx.recv().unwrap();
+        // 書き込んでいる間も読み書きでき, 書き込み中の変更は次の書き込みに回します
+        storage.record_recruit(GuildId::new(1), record(1)).unwrap();
+        assert_eq!(storage.recruit_history(GuildId::new(1)).len(), 2);
+        release.send(()).unwrap();
+        flush.join().unwrap().unwrap();
+        assert!(storage.state.dirty.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn history_keeps_newest_records() {
+        let storage = CountingStorage::default();
+        // 1 件ごとに書き込まないよう, まとめて書き込む設定にします
+        storage.state.deferred.store(true, Ordering::Release);
+        for started_at in 0..MAX_HISTORY_PER_GUILD as i64 + 3 {
+            storage
+                .record_recruit(GuildId::new(1), record(started_at))
+                .unwrap();
+        }
+        let history = storage.recruit_history(GuildId::new(1));
+        assert_eq!(history.len(), MAX_HISTORY_PER_GUILD);
+        assert_eq!(history[0].started_at.unix_timestamp(), 3);
+    }
+
+    #[test]
+    fn organizer_role_limits_who_can_create_recruits() {
+        let organizer = RoleId::new(10);
+        let member = Permissions::empty();
+        let everyone = Permissions::MENTION_EVERYONE;
+        assert!(GuildSettings::default().can_create_recruit(&[], member, true));
+
+        let mut settings = GuildSettings {
+            organizer_role: Some(organizer),
+            ..GuildSettings::default()
+        };
+        assert!(settings.can_create_recruit(&[organizer], member, true));
+        assert!(!settings.can_create_recruit(&[], member, false));
+        assert!(settings.can_create_recruit(&[], Permissions::ADMINISTRATOR, true));
+
+        // ロールへのメンションを含む募集だけを制限します
+        settings.organizer_role_for_mentions_only = true;
+        assert!(settings.can_create_recruit(&[], member, false));
+        assert!(!settings.can_create_recruit(&[], member, true));
+        assert!(settings.can_create_recruit(&[], everyone, true));
+    }
+
+    #[test]
+    fn role_pings_are_claimed_once_per_cooldown_and_released_on_failure() {
+        let storage: &dyn Storage = &CountingStorage::default();
+        let guild_id = GuildId::new(1);
+        let [role, other] = [RoleId::new(10), RoleId::new(11)];
+        let at = |minutes: i64| Timestamp::from_unix_timestamp(minutes * 60).unwrap();
+
+        // クールダウンを設定していなければ, いつでもメンションできます
+        let claim = storage.claim_role_pings(guild_id, &[role], at(0)).unwrap();
+        assert_eq!(claim.allowed, [role]);
+        storage
+            .update_guild_settings(guild_id, |settings| {
+                settings.role_ping_cooldown_minutes = Some(30)
+            })
+            .unwrap();
+
+        let claim = storage.claim_role_pings(guild_id, &[role], at(0)).unwrap();
+        assert_eq!(claim.allowed, [role]);
+        let claim = storage
+            .claim_role_pings(guild_id, &[role, other], at(29))
+            .unwrap();
+        assert_eq!(claim.allowed, [other]);
+        assert_eq!(claim.cooling_down, [role]);
+
+        // 開始通知を送れなかった場合は, 前のメンション日時に戻します
+        storage.release_role_pings(guild_id, &claim).unwrap();
+        let claim = storage
+            .claim_role_pings(guild_id, &[other], at(29))
+            .unwrap();
+        assert_eq!(claim.allowed, [other]);
+        let claim = storage.claim_role_pings(guild_id, &[role], at(30)).unwrap();
+        assert_eq!(claim.allowed, [role]);
+    }
+
+    fn exported_settings() -> GuildSettings {
+        GuildSettings {
+            organizer_role: Some(RoleId::new(1)),
+            allowed_bot_participants: vec![UserId::new(2)],
+            remind_before_minutes: Some(10),
+            language: Language::En,
+            channel_roles: BTreeMap::from([(ChannelId::new(3), RoleId::new(4))]),
+            audit_channel: Some(ChannelId::new(5)),
+            role_name_prefix: Some("募集".to_string()),
+            quiet_hours: Some(QuietHours {
+                start: TimeOfDay::parse("23:00").unwrap(),
+                end: TimeOfDay::parse("07:00").unwrap(),
+            }),
+            member_work_cap: Some(100),
+            timezone: Some("Asia/Tokyo".to_string()),
+            mirror_channels: BTreeMap::from([(ChannelId::new(3), ChannelId::new(6))]),
+            ..GuildSettings::default()
+        }
+    }
+
+    #[test]
+    fn exported_settings_are_read_back() {
+        let emojis = ReactionEmojis::default();
+        let exported = exported_settings().to_toml().unwrap();
+        let imported = GuildSettings::from_toml(&exported, &emojis).unwrap();
+        assert_eq!(imported.to_toml().unwrap(), exported);
+        // 既定の設定は空の TOML になります
+        let default = GuildSettings::default().to_toml().unwrap();
+        assert!(GuildSettings::from_toml(&default, &emojis).is_ok());
+    }
+
+    #[test]
+    fn unknown_keys_in_imported_settings_are_rejected() {
+        let emojis = ReactionEmojis::default();
+        let err = GuildSettings::from_toml("langage = \"en\"", &emojis).unwrap_err();
+        assert_eq!(err.to_string(), "不明な項目があります: langage");
+        assert!(GuildSettings::from_toml("language = \"fr\"", &emojis).is_err());
+        // タイムゾーンは正しい名前に直して読み込みます
+        let imported = GuildSettings::from_toml("timezone = \"asia/tokyo\"", &emojis).unwrap();
+        assert_eq!(imported.timezone.as_deref(), Some("Asia/Tokyo"));
+    }
+
+    #[test]
+    fn out_of_range_imported_settings_are_rejected() {
+        let emojis = ReactionEmojis::default();
+        for content in [
+            "role_ping_cooldown_minutes = 0",
+            "remind_before_minutes = 0",
+            "snooze_minutes = 0",
+            "min_account_age_days = 0",
+            "min_membership_age_days = 0",
+            &format!("member_work_cap = {}", MAX_MEMBER_WORK_CAP + 1),
+            &format!(
+                "role_name_prefix = \"{}\"",
+                "a".repeat(MAX_ROLE_PREFIX_CHARS + 1)
+            ),
+            "quiet_hours = { start = \"23:00\", end = \"23:00\" }",
+            "timezone = \"Mars/Olympus\"",
+            "mirror_channels = { \"3\" = 3 }",
+        ] {
+            assert!(
+                GuildSettings::from_toml(content, &emojis).is_err(),
+                "{content}"
+            );
+        }
+        let content = format!("member_work_cap = {MAX_MEMBER_WORK_CAP}");
+        assert!(GuildSettings::from_toml(&content, &emojis).is_ok());
+    }
+}
@@ -0,0 +1,334 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::Error;
+#[cfg(feature = "sqlite")]
+use crate::sqlite_storage::SqliteStorage;
+use crate::storage::{Storage, StorageData, StorageState};
+
+const DEFAULT_DATA_PATH: &str = "joinbell.toml";
+/// 保存ファイルの代わりにメモリだけに保存する `DATABASE_URL` / `JOINBELL_DATA_PATH` の値
+const MEMORY_URL: &str = "memory:";
+const SQLITE_SCHEME: &str = "sqlite:";
+
+/// `DATABASE_URL` から保存先を決めます
+///
+/// - `sqlite:` で始まる場合は SQLite のデータベース (例: `sqlite://joinbell.db`)
+/// - `memory:` の場合はメモリだけ (停止すると内容は消えます, 動作確認用)
+/// - 指定しない場合は `JOINBELL_DATA_PATH` (既定: `joinbell.toml`) の TOML ファイル
+pub fn from_env() -> Result<Arc<dyn Storage>, Error> {
+    match std::env::var("DATABASE_URL") {
+        Ok(url) => open(&url),
+        Err(_) => match std::env::var("JOINBELL_DATA_PATH") {
+            Ok(path) if path == MEMORY_URL => Ok(Arc::new(MemoryStorage::default())),
+            Ok(path) => Ok(Arc::new(FileStorage::open(PathBuf::from(path))?)),
+            Err(_) => Ok(Arc::new(FileStorage::open(PathBuf::from(
+                DEFAULT_DATA_PATH,
+            ))?)),
+        },
+    }
+}
+
+/// `DATABASE_URL` の形式の `url` の保存先を開きます
+pub fn open(url: &str) -> Result<Arc<dyn Storage>, Error> {
+    if url == MEMORY_URL {
+        return Ok(Arc::new(MemoryStorage::default()));
+    }
+    if url.starts_with(SQLITE_SCHEME) {
+        #[cfg(feature = "sqlite")]
+        return Ok(Arc::new(SqliteStorage::open(url)?));
+        #[cfg(not(feature = "sqlite"))]
+        return Err(
+            "DATABASE_URL is sqlite:, but joinbell was built without the sqlite feature".into(),
+        );
+    }
+    Err(format!("Unsupported DATABASE_URL (expected sqlite: or memory:): {url}").into())
+}
+
+/// TOML ファイルに保存します
+///
+/// 書き込み中に停止しても壊れないよう, 一時ファイルに書いてから置き換えます。
+pub struct FileStorage {
+    state: StorageState,
+    path: PathBuf,
+}
+
+impl FileStorage {
+    pub fn open(path: PathBuf) -> Result<Self, Error> {
+        let data = match read_file(&path)? {
+            Some(content) => toml::from_str(&content)?,
+            None => StorageData::default(),
+        };
+        Ok(Self {
+            state: StorageState::new(data),
+            path,
+        })
+    }
+}
+
+impl Storage for FileStorage {
+    fn state(&self) -> &StorageState {
+        &self.state
+    }
+
+    fn persist(&self, data: &StorageData) -> Result<(), Error> {
+        let tmp_path = self.path.with_extension("toml.tmp");
+        std::fs::write(&tmp_path, toml::to_string(data)?)?;
+        std::fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+
+    fn migrate(&self) -> Result<bool, Error> {
+        let data = self.state.lock();
+        let Some(current) = read_file(&self.path)? else {
+            return Ok(false);
+        };
+        if toml::to_string(&*data)? == current {
+            return Ok(false);
+        }
+        self.persist(&data)?;
+        Ok(true)
+    }
+}
+
+fn read_file(path: &PathBuf) -> Result<Option<String>, Error> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// メモリだけに保存します (動作確認やテスト用)
+///
+/// 書き込んだ内容は `reopen` で読み込み直せるため, テストでは再起動した後の状態も確かめられます。
+#[derive(Default)]
+pub struct MemoryStorage {
+    state: StorageState,
+    /// 最後に書き込んだ内容
+    saved: Arc<Mutex<Option<String>>>,
+}
+
+impl MemoryStorage {
+    /// 停止して, 最後に書き込んだ内容から開き直したときの保存先
+    #[cfg(test)]
+    pub fn reopen(&self) -> Result<Self, Error> {
+        let data = match self.saved.lock().unwrap().as_deref() {
+            Some(content) => toml::from_str(content)?,
+            None => StorageData::default(),
+        };
+        Ok(Self {
+            state: StorageState::new(data),
+            saved: self.saved.clone(),
+        })
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn state(&self) -> &StorageState {
+        &self.state
+    }
+
+    fn persist(&self, data: &StorageData) -> Result<(), Error> {
+        *self.saved.lock().unwrap() = Some(toml::to_string(data)?);
+        Ok(())
+    }
+
+    fn migrate(&self) -> Result<bool, Error> {
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use poise::serenity_prelude::{ChannelId, GuildId, MessageId, RoleId, UserId};
+
+    use super::*;
+    use crate::i18n::Language;
+    use crate::recruit_message::at;
+    use crate::scheduler::Job;
+    use crate::storage::{RecruitLocation, RecruitRecord};
+
+    const GUILD_ID: GuildId = GuildId::new(1);
+    const CHANNEL_ID: ChannelId = ChannelId::new(2);
+    const MESSAGE_ID: MessageId = MessageId::new(3);
+
+    fn record(started_at: i64) -> RecruitRecord {
+        RecruitRecord {
+            game_title: "Apex".to_string(),
+            category: None,
+            channel_id: CHANNEL_ID,
+            message_id: MESSAGE_ID,
+            started_at: at(started_at),
+            participants: vec![UserId::new(4)],
+            required_players: Some(1),
+            reacted_count: Some(1),
+        }
+    }
+
+    fn countdown_job() -> Job {
+        Job::FinishCountdown {
+            channel_id: CHANNEL_ID,
+            message_id: MESSAGE_ID,
+            guild_id: Some(GUILD_ID),
+            snapshot: vec![UserId::new(4)],
+            notice_channel_id: CHANNEL_ID,
+            notice_message_id: MessageId::new(5),
+            snoozed_minutes: 0,
+        }
+    }
+
+    fn location() -> RecruitLocation {
+        RecruitLocation {
+            guild_id: Some(GUILD_ID),
+            channel_id: CHANNEL_ID,
+        }
+    }
+
+    /// どの保存先でも, 同じ操作に同じ結果を返すことを確かめます
+    fn check_operations(storage: &dyn Storage) {
+        assert_eq!(storage.guild_settings(GUILD_ID).language, Language::Ja);
+        storage
+            .update_guild_settings(GUILD_ID, |settings| settings.language = Language::En)
+            .unwrap();
+        assert_eq!(storage.guild_settings(GUILD_ID).language, Language::En);
+
+        for started_at in 0..3 {
+            storage
+                .record_recruit(GUILD_ID, record(started_at))
+                .unwrap();
+        }
+        let history = storage.recruit_history(GUILD_ID);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].started_at, at(0));
+
+        storage
+            .remember_recruit_location(MESSAGE_ID, location())
+            .unwrap();
+        assert_eq!(
+            storage.recruit_locations_in_guild(GUILD_ID),
+            vec![(MESSAGE_ID, CHANNEL_ID)]
+        );
+        storage
+            .set_participant_order(MESSAGE_ID, vec![UserId::new(5), UserId::new(4)])
+            .unwrap();
+        assert_eq!(
+            storage.participant_order(MESSAGE_ID),
+            vec![UserId::new(5), UserId::new(4)]
+        );
+
+        storage.push_job(at(60), countdown_job()).unwrap();
+        assert!(storage.lease_due_jobs(at(59)).is_empty());
+        let due = storage.lease_due_jobs(at(60));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].payload, countdown_job());
+        storage.complete_job(due[0].id).unwrap();
+        assert!(storage.jobs().is_empty());
+        storage.push_job(at(120), countdown_job()).unwrap();
+
+        // 削除されたチャンネルを指すサーバー設定は解除します
+        let deleted = ChannelId::new(6);
+        let kept = ChannelId::new(7);
+        storage
+            .update_guild_settings(GUILD_ID, |settings| {
+                settings.audit_channel = Some(deleted);
+                settings.error_channel = Some(deleted);
+                settings.summary_channel = Some(kept);
+                settings.channel_roles.insert(deleted, RoleId::new(8));
+                settings.mirror_channels.insert(kept, deleted);
+                settings.mirror_channels.insert(deleted, kept);
+            })
+            .unwrap();
+        assert_eq!(storage.purge_channel(deleted).unwrap().channel_settings, 5);
+        let settings = storage.guild_settings(GUILD_ID);
+        assert_eq!(settings.audit_channel, None);
+        assert_eq!(settings.error_channel, None);
+        assert_eq!(settings.summary_channel, Some(kept));
+        assert!(settings.channel_roles.is_empty());
+        assert!(settings.mirror_channels.is_empty());
+        assert_eq!(storage.recruit_location(MESSAGE_ID), Some(location()));
+
+        let summary = storage.purge_guild(GUILD_ID).unwrap();
+        assert_eq!(summary.messages, vec![MESSAGE_ID]);
+        assert_eq!(summary.jobs, 1);
+        assert_eq!(storage.guild_settings(GUILD_ID).language, Language::Ja);
+        assert!(storage.recruit_history(GUILD_ID).is_empty());
+        assert!(storage.recruit_location(MESSAGE_ID).is_none());
+        assert!(storage.participant_order(MESSAGE_ID).is_empty());
+        assert!(storage.jobs().is_empty());
+    }
+
+    /// 書き込んだ内容を, 開き直した保存先から読み込めることを確かめます
+    fn check_reopen(storage: &dyn Storage, reopen: impl Fn() -> Arc<dyn Storage>) {
+        storage
+            .update_guild_settings(GUILD_ID, |settings| settings.language = Language::En)
+            .unwrap();
+        storage.record_recruit(GUILD_ID, record(0)).unwrap();
+        storage
+            .remember_recruit_location(MESSAGE_ID, location())
+            .unwrap();
+        storage.push_job(at(60), countdown_job()).unwrap();
+
+        let reopened = reopen();
+        assert_eq!(reopened.guild_settings(GUILD_ID).language, Language::En);
+        assert_eq!(reopened.recruit_history(GUILD_ID).len(), 1);
+        assert_eq!(reopened.recruit_location(MESSAGE_ID), Some(location()));
+        assert_eq!(reopened.jobs().len(), 1);
+
+        // 消した記録は, 開き直しても残りません
+        reopened.remove_recruit_location(MESSAGE_ID).unwrap();
+        let reopened = reopen();
+        assert!(reopened.recruit_location(MESSAGE_ID).is_none());
+        assert_eq!(reopened.jobs().len(), 1);
+    }
+
+    /// テストごとの一時ファイル
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("joinbell-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn memory_storage_conforms() {
+        check_operations(&MemoryStorage::default());
+        let storage = MemoryStorage::default();
+        check_reopen(&storage, || Arc::new(storage.reopen().unwrap()));
+    }
+
+    #[test]
+    fn file_storage_conforms() {
+        let path = temp_path("operations.toml");
+        check_operations(&FileStorage::open(path.clone()).unwrap());
+        let _ = std::fs::remove_file(&path);
+
+        let path = temp_path("reopen.toml");
+        let storage = FileStorage::open(path.clone()).unwrap();
+        check_reopen(&storage, || {
+            Arc::new(FileStorage::open(path.clone()).unwrap())
+        });
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_storage_conforms() {
+        check_operations(&SqliteStorage::open("sqlite::memory:").unwrap());
+
+        let path = temp_path("reopen.db");
+        let url = format!("sqlite://{}", path.display());
+        let storage = SqliteStorage::open(&url).unwrap();
+        check_reopen(&storage, || Arc::new(SqliteStorage::open(&url).unwrap()));
+        // 開き直した内容は現在の形式のため, 書き直す行はありません
+        assert!(!SqliteStorage::open(&url).unwrap().migrate().unwrap());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn database_url_selects_the_backend() {
+        assert!(open(MEMORY_URL).is_ok());
+        assert!(open("postgres://localhost/joinbell").is_err());
+        #[cfg(feature = "sqlite")]
+        assert!(open("sqlite::memory:").is_ok());
+    }
+}
@@ -0,0 +1,447 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use chrono_tz::Tz;
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio::time::{Duration, sleep};
+
+use crate::discord_error::{EntityKind, is_unknown_entity};
+use crate::{Data, Error, parse_bot_recruit_message, participant_count, send_ephemeral, timezone};
+use crate::{mirror, notification};
+
+/// 一覧の書き換えを予約してから, 続く変更をまとめて待つ時間
+const SETTLE_DELAY: Duration = Duration::from_secs(2);
+/// 同じ一覧を書き換える最短の間隔
+const UPDATE_INTERVAL: Duration = Duration::from_secs(10);
+/// 一覧に表示する募集の上限 (超えた分は「他n件」と表示します)
+const MAX_LISTED: usize = 15;
+/// 開始予定が今日の募集を探す, 投稿からの日数
+const LOOKBACK_DAYS: i64 = 7;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// `/channel_settings summary_channel` のチャンネルに投稿した, 今日の募集の一覧のメッセージ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SummaryMessage {
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+}
+
+/// 一覧に表示する, 受付中の募集
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SummaryEntry {
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub game_title: String,
+    /// 最後に数えた参加人数 (まだ数えていない募集にはありません)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    pub required_players: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<Timestamp>,
+}
+
+/// サーバーのタイムゾーン `tz` での, `timestamp` の日付 (1970-01-01 からの日数)
+fn local_day(timestamp: Timestamp, tz: Tz) -> i64 {
+    timezone::to_local(tz, timestamp.unix_timestamp()).div_euclid(SECONDS_PER_DAY)
+}
+
+/// 今日の募集の一覧の本文
+///
+/// 今日投稿した募集と, 開始予定が今日の募集を新しい順に `MAX_LISTED` 件まで表示します。
+pub fn render(guild_id: GuildId, entries: &[SummaryEntry], now: Timestamp, tz: Tz) -> String {
+    let today = local_day(now, tz);
+    let mut entries: Vec<&SummaryEntry> = entries
+        .iter()
+        .filter(|entry| {
+            local_day(entry.message_id.created_at(), tz) == today
+                || entry
+                    .start_time
+                    .is_some_and(|start_time| local_day(start_time, tz) == today)
+        })
+        .collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.message_id));
+
+    let mut lines = vec![format!(
+        "📋 **今日の募集** (<t:{}:t> 時点)",
+        now.unix_timestamp()
+    )];
+    if entries.is_empty() {
+        lines.push("受付中の募集はありません".to_string());
+        return lines.join("\n");
+    }
+    lines.extend(render_entries(guild_id, &entries));
+    lines.join("\n")
+}
+
+/// 募集を 1 件 1 行で `MAX_LISTED` 件まで表し, 超えた分を「他n件」とします
+pub fn render_entries(guild_id: GuildId, entries: &[&SummaryEntry]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for entry in entries.iter().take(MAX_LISTED) {
+        let count = entry
+            .count
+            .map_or_else(|| "?".to_string(), |count| count.to_string());
+        let mut line = format!(
+            "- **{}** {count}/{}",
+            entry.game_title, entry.required_players
+        );
+        if let Some(start_time) = entry.start_time {
+            line += &format!(" (<t:{}:t> 開始予定)", start_time.unix_timestamp());
+        }
+        line += &format!(
+            " {}",
+            entry.message_id.link(entry.channel_id, Some(guild_id))
+        );
+        lines.push(line);
+    }
+    if MAX_LISTED < entries.len() {
+        lines.push(format!("他{}件", entries.len() - MAX_LISTED));
+    }
+    lines
+}
+
+/// 書き換えを待っている一覧のサーバーと, ミラーの募集
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PendingUpdates {
+    pub guilds: HashSet<GuildId>,
+    /// ミラー (`/channel_settings mirror`) を書き換える募集メッセージ
+    pub mirrors: HashSet<MessageId>,
+}
+
+impl PendingUpdates {
+    fn is_empty(&self) -> bool {
+        self.guilds.is_empty() && self.mirrors.is_empty()
+    }
+}
+
+/// 書き換えを待っている一覧とミラー
+#[derive(Debug, Default)]
+pub struct SummaryBoard {
+    pending: Mutex<PendingUpdates>,
+    wake: Notify,
+}
+
+impl SummaryBoard {
+    pub fn mark(&self, guild_id: GuildId) {
+        if self.pending.lock().unwrap().guilds.insert(guild_id) {
+            self.wake.notify_one();
+        }
+    }
+
+    pub fn mark_mirror(&self, message_id: MessageId) {
+        if self.pending.lock().unwrap().mirrors.insert(message_id) {
+            self.wake.notify_one();
+        }
+    }
+
+    /// 書き換えが予約されるまで待ち, `SETTLE_DELAY` の間に続いた予約とまとめて返します
+    async fn next_batch(&self) -> PendingUpdates {
+        loop {
+            if !self.pending.lock().unwrap().is_empty() {
+                sleep(SETTLE_DELAY).await;
+                return std::mem::take(&mut *self.pending.lock().unwrap());
+            }
+            self.wake.notified().await;
+        }
+    }
+}
+
+/// 募集の作成や人数の変化, 開始・締め切りのあとに, その募集のサーバーの一覧とミラーの書き換えを予約します
+pub fn mark_message(data: &Data, message_id: MessageId) {
+    if let Some(guild_id) = data
+        .storage
+        .recruit_location(message_id)
+        .and_then(|location| location.guild_id)
+    {
+        data.summary_board.mark(guild_id);
+    }
+    if data.storage.mirror(message_id).is_some() {
+        data.summary_board.mark_mirror(message_id);
+    }
+}
+
+/// 予約された一覧とミラーを, 同じメッセージにつき `UPDATE_INTERVAL` に 1 回まで書き換えます
+pub fn spawn_updater(ctx: Context, data: Data) {
+    tokio::spawn(async move {
+        loop {
+            let batch = data.summary_board.next_batch().await;
+            for guild_id in batch.guilds {
+                if data
+                    .storage
+                    .guild_settings(guild_id)
+                    .summary_channel
+                    .is_none()
+                {
+                    continue;
+                }
+                if let Err(err) = data.rest_budget.run(update(&ctx, &data, guild_id)).await {
+                    eprintln!("Failed to update recruit summary of guild {guild_id}: {err}");
+                }
+            }
+            for message_id in batch.mirrors {
+                if let Err(err) = data
+                    .rest_budget
+                    .run(mirror::sync(&ctx, &data, message_id))
+                    .await
+                {
+                    eprintln!("Failed to update mirror of recruit {message_id}: {err}");
+                }
+            }
+            sleep(UPDATE_INTERVAL - SETTLE_DELAY).await;
+        }
+    });
+}
+
+/// サーバーの, 直近 `LOOKBACK_DAYS` 日に投稿された受付中の募集
+pub async fn collect_entries(ctx: &Context, data: &Data, guild_id: GuildId) -> Vec<SummaryEntry> {
+    let since = Timestamp::now().unix_timestamp() - LOOKBACK_DAYS * SECONDS_PER_DAY;
+    collect_open_entries(ctx, data, guild_id, |message_id, _| {
+        since <= message_id.created_at().unix_timestamp()
+    })
+    .await
+}
+
+/// サーバーに投稿した募集のうち, `include` が true を返す受付中の募集
+///
+/// 募集メッセージを読み取る前に `include` で絞り込むため, 対象外の募集では REST API を呼び出しません。
+pub async fn collect_open_entries(
+    ctx: &Context,
+    data: &Data,
+    guild_id: GuildId,
+    include: impl Fn(MessageId, ChannelId) -> bool,
+) -> Vec<SummaryEntry> {
+    let mut entries = Vec::new();
+    for (message_id, channel_id) in data.storage.recruit_locations_in_guild(guild_id) {
+        if !include(message_id, channel_id) {
+            continue;
+        }
+        entries.extend(open_entry(ctx, data, channel_id, message_id).await);
+    }
+    entries
+}
+
+/// 受付中の募集を一覧の 1 件として読み取ります (読み取れない募集と受付中でない募集にはありません)
+pub async fn open_entry(
+    ctx: &Context,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Option<SummaryEntry> {
+    let recruit_message = match data.storage.recruit_message(message_id) {
+        Some(recruit_message) => recruit_message,
+        None => {
+            let cached = ctx
+                .cache
+                .message(channel_id, message_id)
+                .map(|message| message.clone());
+            let message = match cached {
+                Some(message) => message,
+                None => channel_id.message(ctx, message_id).await.ok()?,
+            };
+            parse_bot_recruit_message(ctx, data, &message)?
+        }
+    };
+    let config = &recruit_message.config;
+    let state = &recruit_message.state;
+    if state.cancelled
+        || state.closed_entries.is_some()
+        || (state.started && config.closes_on_start())
+    {
+        return None;
+    }
+    Some(SummaryEntry {
+        channel_id,
+        message_id,
+        game_title: config.game_title.clone(),
+        count: participant_count::last_count(data, message_id),
+        required_players: config.required_players,
+        start_time: config.start_time,
+    })
+}
+
+/// 一覧を書き換えます
+///
+/// 一覧のメッセージがない場合や削除されていた場合は投稿し直し, 権限があればピン留めします。
+async fn update(ctx: &Context, data: &Data, guild_id: GuildId) -> Result<SummaryMessage, Error> {
+    let channel_id = data
+        .storage
+        .guild_settings(guild_id)
+        .summary_channel
+        .ok_or("summary_channel is not set")?;
+    let entries = collect_entries(ctx, data, guild_id).await;
+    let content = render(
+        guild_id,
+        &entries,
+        Timestamp::now(),
+        timezone::guild_timezone(data, Some(guild_id)),
+    );
+    if let Some(summary) = data
+        .storage
+        .summary_message(guild_id)
+        .filter(|summary| summary.channel_id == channel_id)
+    {
+        match channel_id
+            .edit_message(
+                ctx,
+                summary.message_id,
+                EditMessage::new().content(&content),
+            )
+            .await
+        {
+            Ok(_) => return Ok(summary),
+            Err(err) if is_unknown_entity(&err) == Some(EntityKind::Message) => {
+                eprintln!("Recruit summary of guild {guild_id} was deleted, posting it again");
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+    let message = channel_id
+        .send_message(ctx, notification::plain_message(content))
+        .await?;
+    if let Err(err) = message.pin(ctx).await {
+        eprintln!("Failed to pin recruit summary: {err}");
+    }
+    let summary = SummaryMessage {
+        channel_id,
+        message_id: message.id,
+    };
+    data.storage.set_summary_message(guild_id, Some(summary))?;
+    Ok(summary)
+}
+
+/// 記録している一覧のメッセージを削除し, 記録を消します
+async fn remove_summary(ctx: &Context, data: &Data, guild_id: GuildId) -> Result<(), Error> {
+    if let Some(summary) = data.storage.summary_message(guild_id) {
+        if let Err(err) = summary
+            .channel_id
+            .delete_message(ctx, summary.message_id)
+            .await
+            && is_unknown_entity(&err).is_none()
+        {
+            eprintln!("Failed to delete recruit summary: {err}");
+        }
+        data.storage.set_summary_message(guild_id, None)?;
+    }
+    Ok(())
+}
+
+/// 今日の募集の一覧を固定するチャンネルを管理します
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "summary_channel",
+    subcommands("summary_channel_set", "summary_channel_clear")
+)]
+pub async fn settings_summary_channel(_ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// 今日の募集の一覧を投稿するチャンネルを設定します
+#[poise::command(slash_command, guild_only, rename = "set")]
+async fn summary_channel_set(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "一覧を投稿してピン留めするチャンネル"]
+    #[channel_types("Text")]
+    channel: GuildChannel,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.defer_ephemeral().await?;
+    let data = ctx.data();
+    let serenity_ctx = ctx.serenity_context();
+    if data
+        .storage
+        .summary_message(guild_id)
+        .is_some_and(|summary| summary.channel_id != channel.id)
+    {
+        remove_summary(serenity_ctx, data, guild_id).await?;
+    }
+    data.storage.update_guild_settings(guild_id, |settings| {
+        settings.summary_channel = Some(channel.id);
+    })?;
+    let content = match update(serenity_ctx, data, guild_id).await {
+        Ok(summary) => format!(
+            "{} に今日の募集の一覧を表示します: {}",
+            channel.mention(),
+            summary.message_id.link(summary.channel_id, Some(guild_id))
+        ),
+        Err(err) => {
+            eprintln!("Failed to post recruit summary: {err}");
+            format!(
+                "{} に一覧を投稿できませんでした。Bot のチャンネルの権限を確認してください",
+                channel.mention()
+            )
+        }
+    };
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// 今日の募集の一覧をやめ, 一覧のメッセージを削除します
+#[poise::command(slash_command, guild_only, rename = "clear")]
+async fn summary_channel_clear(ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let data = ctx.data();
+    data.storage
+        .update_guild_settings(guild_id, |settings| settings.summary_channel = None)?;
+    remove_summary(ctx.serenity_context(), data, guild_id).await?;
+    send_ephemeral(ctx, "今日の募集の一覧を解除しました").await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recruit_message::at;
+
+    const GUILD_ID: GuildId = GuildId::new(1);
+    const CHANNEL_ID: ChannelId = ChannelId::new(2);
+    /// 2023-11-14T22:13:20Z
+    const NOW: i64 = 1_700_000_000;
+
+    /// `posted_at` 秒 (`NOW` から) に投稿した募集
+    fn entry(game_title: &str, posted_at: i64, start_time: Option<i64>) -> SummaryEntry {
+        let millis = (NOW + posted_at) as u64 * 1000 - 1_420_070_400_000;
+        SummaryEntry {
+            channel_id: CHANNEL_ID,
+            message_id: MessageId::new(millis << 22),
+            game_title: game_title.to_string(),
+            count: None,
+            required_players: 3,
+            start_time: start_time.map(at),
+        }
+    }
+
+    #[test]
+    fn only_todays_recruits_are_listed() {
+        let entries = [
+            entry("Apex", -60 * 60, None),
+            entry("Valorant", -60, Some(60 * 60)),
+            entry("Minecraft", -2 * SECONDS_PER_DAY, Some(-60)),
+            entry("Splatoon", -2 * SECONDS_PER_DAY, None),
+        ];
+        let content = render(GUILD_ID, &entries, at(0), Tz::UTC);
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines[0], format!("📋 **今日の募集** (<t:{NOW}:t> 時点)"));
+        // 新しい順に並べ, 開始予定が今日の募集も表示します
+        assert!(lines[1].starts_with("- **Valorant** ?/3 (<t:1700003600:t> 開始予定) https://"));
+        assert!(lines[2].starts_with("- **Apex** ?/3 https://"));
+        assert!(lines[3].starts_with("- **Minecraft**"));
+        assert_eq!(lines.len(), 4);
+
+        // ヘルシンキ (UTC+2) では, 投稿したのは昨日です
+        let content = render(GUILD_ID, &entries[..1], at(0), Tz::Europe__Helsinki);
+        assert!(content.ends_with("受付中の募集はありません"), "{content}");
+    }
+
+    #[test]
+    fn entries_over_the_limit_are_counted() {
+        let entries: Vec<SummaryEntry> = (0..MAX_LISTED as i64 + 2)
+            .map(|index| entry("Apex", -index, None))
+            .collect();
+        let entries: Vec<&SummaryEntry> = entries.iter().collect();
+        let lines = render_entries(GUILD_ID, &entries);
+        assert_eq!(lines.len(), MAX_LISTED + 1);
+        assert_eq!(lines[MAX_LISTED], "他2件");
+    }
+}
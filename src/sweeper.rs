@@ -0,0 +1,169 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+use poise::serenity_prelude::MessageId;
+use tokio::time::{Duration, interval};
+
+use crate::Data;
+
+/// 最後にリアクションなどを処理してから, 募集の状態をメモリに残しておく時間の既定値
+pub(crate) const DEFAULT_STATE_HORIZON_HOURS: u64 = 24;
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// メモリ上に保持している状態の件数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateCounts {
+    pub message_locks: usize,
+    pub last_touched: usize,
+    pub voice_recruits: usize,
+    pub pending_starts: usize,
+    pub rejection_notices: usize,
+    pub verified_participants: usize,
+    pub close_notices: usize,
+    pub processed_reactors: usize,
+}
+
+impl fmt::Display for StateCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "message_locks={} last_touched={} voice_recruits={} pending_starts={} rejection_notices={} verified_participants={} close_notices={} processed_reactors={}",
+            self.message_locks,
+            self.last_touched,
+            self.voice_recruits,
+            self.pending_starts,
+            self.rejection_notices,
+            self.verified_participants,
+            self.close_notices,
+            self.processed_reactors
+        )
+    }
+}
+
+pub fn state_counts(data: &Data) -> StateCounts {
+    StateCounts {
+        message_locks: data.message_locks.lock().unwrap().len(),
+        last_touched: data.last_touched.lock().unwrap().len(),
+        voice_recruits: data
+            .voice_recruits
+            .lock()
+            .unwrap()
+            .values()
+            .map(|recruits| recruits.len())
+            .sum(),
+        pending_starts: data.pending_starts.lock().unwrap().len(),
+        rejection_notices: data.rejection_notices.lock().unwrap().len(),
+        verified_participants: data.verified_participants.lock().unwrap().len(),
+        close_notices: data.close_notices.lock().unwrap().len(),
+        processed_reactors: data.processed_reactors.lock().unwrap().len(),
+    }
+}
+
+/// `JOINBELL_STATE_HORIZON_HOURS` (既定: 24) を読み取ります
+pub fn state_horizon_from_env() -> Duration {
+    let hours = std::env::var("JOINBELL_STATE_HORIZON_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse().ok())
+        .filter(|&hours| 0 < hours)
+        .unwrap_or(DEFAULT_STATE_HORIZON_HOURS);
+    Duration::from_secs(hours * 60 * 60)
+}
+
+/// 古くなった状態を定期的に削除するタスクを開始します
+pub fn spawn_sweeper(data: Data, horizon: Duration) {
+    tokio::spawn(async move {
+        let mut interval = interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            let swept = sweep_stale_state(&data, Instant::now(), horizon);
+            if !swept.is_empty() {
+                eprintln!(
+                    "Swept state of {} recruit messages ({})",
+                    swept.len(),
+                    state_counts(&data)
+                );
+            }
+        }
+    });
+}
+
+/// `horizon` より前に最後に処理した募集メッセージの状態を削除し, 削除したメッセージを返します
+///
+/// カウントダウン中の募集の状態は削除しません。
+pub fn sweep_stale_state(data: &Data, now: Instant, horizon: Duration) -> Vec<MessageId> {
+    let stale: Vec<MessageId> = {
+        let pending_starts = data.pending_starts.lock().unwrap();
+        data.last_touched
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, touched_at)| horizon <= now.duration_since(**touched_at))
+            .filter(|(message_id, _)| !pending_starts.contains_key(message_id))
+            .map(|(&message_id, _)| message_id)
+            .collect()
+    };
+    for &message_id in &stale {
+        data.forget_message(message_id);
+    }
+
+    // 処理中で削除できなかったロックも, 使われなくなった時点で削除します
+    let last_touched = data.last_touched.lock().unwrap();
+    data.message_locks
+        .lock()
+        .unwrap()
+        .retain(|message_id, lock| {
+            1 < Arc::strong_count(lock) || last_touched.contains_key(message_id)
+        });
+    drop(last_touched);
+    data.rejection_notices.lock().unwrap().sweep(now);
+    data.verified_participants.lock().unwrap().sweep(now);
+    stale
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use poise::serenity_prelude::{ChannelId, Timestamp};
+
+    use super::*;
+    use crate::countdown::PendingStart;
+
+    const HORIZON: Duration = Duration::from_secs(60 * 60);
+
+    fn pending_start() -> PendingStart {
+        PendingStart {
+            snapshot: HashSet::new(),
+            notice_channel_id: ChannelId::new(1),
+            notice_message_id: MessageId::new(2),
+            start_at: Timestamp::now(),
+            snoozed_minutes: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn stale_state_is_swept_except_countdowns_and_held_locks() {
+        let data = Data::for_test();
+        let [stale, counting_down, held] = [10, 11, 12].map(MessageId::new);
+        drop(data.lock_message(stale).await);
+        drop(data.lock_message(counting_down).await);
+        let _guard = data.lock_message(held).await;
+        data.pending_starts
+            .lock()
+            .unwrap()
+            .insert(counting_down, pending_start());
+
+        // まだ `horizon` が過ぎていない状態は残します
+        assert!(sweep_stale_state(&data, Instant::now(), HORIZON).is_empty());
+
+        let mut swept = sweep_stale_state(&data, Instant::now() + HORIZON, HORIZON);
+        swept.sort();
+        assert_eq!(swept, [stale, held]);
+        let counts = state_counts(&data);
+        assert_eq!(counts.last_touched, 1);
+        assert_eq!(counts.pending_starts, 1);
+        // 処理中のロックは, 処理が終わるまで残します
+        assert_eq!(counts.message_locks, 2);
+    }
+}
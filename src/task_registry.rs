@@ -0,0 +1,166 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::sync::Mutex;
+
+use poise::serenity_prelude::*;
+use tokio::task::AbortHandle;
+
+use crate::Data;
+use crate::scheduler::Job;
+
+/// 募集ごとに実行中の, 予約した処理のタスク
+///
+/// 予約した処理は実行時刻に保存ファイルから取り出して別のタスクで実行するため,
+/// 取り出した後に募集がキャンセルされても保存ファイルの予約を消すだけでは止まりません。
+/// 実行中のタスクを募集メッセージごとに記録し, まとめて中断できるようにします。
+#[derive(Debug, Default)]
+pub struct TaskRegistry {
+    tasks: Mutex<HashMap<MessageId, Vec<(Job, AbortHandle)>>>,
+}
+
+impl TaskRegistry {
+    /// `job` を実行するタスクを開始し, 募集メッセージ `message_id` のタスクとして記録します
+    pub fn spawn<F>(&self, message_id: MessageId, job: Job, future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handle = tokio::spawn(future).abort_handle();
+        let mut tasks = self.tasks.lock().unwrap();
+        prune(&mut tasks);
+        tasks.entry(message_id).or_default().push((job, handle));
+    }
+
+    /// 募集メッセージ `message_id` の実行中のタスクのうち, `filter` に合うものを中断し, 中断した数を返します
+    ///
+    /// 呼び出したタスク自身は中断しません (予約した処理の中で募集の削除を検知した場合など)。
+    pub fn abort(&self, message_id: MessageId, filter: impl Fn(&Job) -> bool) -> usize {
+        let current = tokio::task::try_id();
+        let mut tasks = self.tasks.lock().unwrap();
+        let Some(entries) = tasks.get_mut(&message_id) else {
+            return 0;
+        };
+        let mut aborted = 0;
+        entries.retain(|(job, handle)| {
+            if handle.is_finished() {
+                return false;
+            }
+            if !filter(job) || current == Some(handle.id()) {
+                return true;
+            }
+            handle.abort();
+            aborted += 1;
+            false
+        });
+        if entries.is_empty() {
+            tasks.remove(&message_id);
+        }
+        aborted
+    }
+
+    /// 処理の種類ごとの, 実行中のタスクの数
+    pub fn live_counts(&self) -> BTreeMap<&'static str, usize> {
+        let mut tasks = self.tasks.lock().unwrap();
+        prune(&mut tasks);
+        let mut counts = BTreeMap::new();
+        for (job, _) in tasks.values().flatten() {
+            *counts.entry(job.kind()).or_default() += 1;
+        }
+        counts
+    }
+}
+
+impl fmt::Display for TaskRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = self.live_counts();
+        if counts.is_empty() {
+            return write!(f, "-");
+        }
+        let counts: Vec<String> = counts
+            .iter()
+            .map(|(kind, count)| format!("{kind}={count}"))
+            .collect();
+        write!(f, "{}", counts.join(" "))
+    }
+}
+
+/// 終わったタスクの記録を削除します
+fn prune(tasks: &mut HashMap<MessageId, Vec<(Job, AbortHandle)>>) {
+    tasks.retain(|_, entries| {
+        entries.retain(|(_, handle)| !handle.is_finished());
+        !entries.is_empty()
+    });
+}
+
+/// 募集メッセージ `message_id` の予約した処理のうち, `filter` に合うものを取り消します
+///
+/// 保存ファイルの予約を削除し, 実行中のタスクも中断します。
+pub fn cancel_matching(data: &Data, message_id: MessageId, filter: impl Fn(&Job) -> bool) {
+    if let Err(err) = data
+        .storage
+        .cancel_jobs(&|job| job.recruit_message_id() == Some(message_id) && filter(job))
+    {
+        eprintln!("Failed to cancel jobs of {message_id}: {err}");
+    }
+    let aborted = data.tasks.abort(message_id, filter);
+    if aborted != 0 {
+        eprintln!("Aborted {aborted} running jobs of {message_id}");
+    }
+}
+
+/// 募集メッセージ `message_id` の予約した処理をすべて取り消します (キャンセルや削除した募集)
+pub fn cancel_all(data: &Data, message_id: MessageId) {
+    cancel_matching(data, message_id, |_| true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANNEL_ID: ChannelId = ChannelId::new(1);
+    const MESSAGE_ID: MessageId = MessageId::new(2);
+
+    fn remind() -> Job {
+        Job::RemindParticipants {
+            channel_id: CHANNEL_ID,
+            message_id: MESSAGE_ID,
+            guild_id: None,
+        }
+    }
+
+    fn roll_call() -> Job {
+        Job::FinishRollCall {
+            channel_id: CHANNEL_ID,
+            message_id: MESSAGE_ID,
+            guild_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn running_jobs_are_aborted_per_recruit() {
+        let registry = TaskRegistry::default();
+        registry.spawn(MESSAGE_ID, remind(), std::future::pending());
+        registry.spawn(MESSAGE_ID, roll_call(), std::future::pending());
+        registry.spawn(MessageId::new(3), remind(), std::future::pending());
+        assert_eq!(
+            registry.to_string(),
+            "finish_roll_call=1 remind_participants=2"
+        );
+
+        let aborted = registry.abort(MESSAGE_ID, |job| matches!(job, Job::FinishRollCall { .. }));
+        assert_eq!(aborted, 1);
+        assert_eq!(registry.abort(MESSAGE_ID, |_| true), 1);
+        assert_eq!(registry.abort(MESSAGE_ID, |_| true), 0);
+        assert_eq!(registry.to_string(), "remind_participants=1");
+    }
+
+    #[tokio::test]
+    async fn finished_jobs_are_not_counted() {
+        let registry = TaskRegistry::default();
+        registry.spawn(MESSAGE_ID, remind(), async {});
+        // 終わるまで待ちます
+        tokio::task::yield_now().await;
+        tokio::task::yield_now().await;
+        assert_eq!(registry.to_string(), "-");
+    }
+}
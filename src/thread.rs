@@ -0,0 +1,37 @@
+use poise::serenity_prelude::{self as serenity, *};
+
+use crate::discord_api::DiscordApi;
+
+/// アーカイブされたスレッドに書き込もうとした場合のエラーコード
+const THREAD_ARCHIVED: isize = 50083;
+
+pub fn is_archived_thread_error(err: &serenity::Error) -> bool {
+    matches!(
+        err,
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response))
+            if response.error.code == THREAD_ARCHIVED
+    )
+}
+
+/// スレッドがアーカイブされていて失敗した場合は, アーカイブを解除してから再試行します
+///
+/// アーカイブを解除できない場合 (スレッドの管理権限がない場合など) はログを出力して `None` を返します。
+pub async fn retry_if_archived<T, F>(
+    api: &impl DiscordApi,
+    channel_id: ChannelId,
+    op: impl Fn() -> F,
+) -> Result<Option<T>, serenity::Error>
+where
+    F: Future<Output = Result<T, serenity::Error>>,
+{
+    match op().await {
+        Err(err) if is_archived_thread_error(&err) => {
+            if let Err(err) = api.unarchive_thread(channel_id).await {
+                eprintln!("Skipped writing to archived thread {channel_id}: {err}");
+                return Ok(None);
+            }
+            op().await.map(Some)
+        }
+        result => result.map(Some),
+    }
+}
@@ -0,0 +1,728 @@
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+use poise::serenity_prelude::Timestamp;
+use tokio::time::Duration;
+
+use crate::timezone;
+
+const MINUTES_PER_HOUR: u64 = 60;
+const MINUTES_PER_DAY: u64 = 24 * MINUTES_PER_HOUR;
+
+/// 時間の長さとして使える形式 (エラーメッセージに表示します)
+pub const DURATION_FORMATS: &str =
+    "`90` (分)、`90m`、`2h`、`1h30m`、`1d`、`30分`、`2時間`、`1時間30分`、`1日`";
+/// 日時として使える形式 (エラーメッセージに表示します)
+pub const START_TIME_FORMATS: &str = "`21:00`、`21時`、`21時30分`、`21時半`、`午後9時`、`明日21時`、`明日20:00`、`10月20日21時`、`2026-10-20 21:00`、`2時間後`";
+
+/// 時間の長さの単位 (長い表記から順に並べます)
+const DURATION_UNITS: [(&[&str], u64); 3] = [
+    (&["days", "day", "日", "d"], MINUTES_PER_DAY),
+    (&["hours", "hour", "時間", "hr", "h"], MINUTES_PER_HOUR),
+    (&["minutes", "minute", "mins", "min", "分", "m"], 1),
+];
+/// 日付を表す言葉と, 今日からの日数 (長い表記から順に並べます)
+const RELATIVE_DAYS: [(&str, u64); 7] = [
+    ("明後日", 2),
+    ("あさって", 2),
+    ("今日", 0),
+    ("きょう", 0),
+    ("明日", 1),
+    ("あした", 1),
+    ("あす", 1),
+];
+
+/// 入力した時間の長さまたは日時
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeInput {
+    /// `2時間` や `30分後` のような, 今からの長さ
+    Duration(Duration),
+    /// `21:00` や `明日21時` のような日時
+    At(Timestamp),
+}
+
+/// 読み取れなかった理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Invalid {
+    Format,
+    BareNumber,
+    AmbiguousDate,
+    AmbiguousMeridiem,
+    MinuteDigits,
+    MinuteWithoutUnit,
+    UnitOrder,
+    Decimal,
+    DateWithoutTime,
+    TimeOutOfRange,
+    DateOutOfRange,
+    TooLarge,
+    NotDuration,
+}
+
+impl Invalid {
+    fn reason(self) -> &'static str {
+        match self {
+            Invalid::Format => "形式が違います",
+            Invalid::BareNumber => "数字だけでは分数か時刻か分からないため、単位を付けてください",
+            Invalid::AmbiguousDate => {
+                "年のない `/` や `-` の日付は月と日の順番が分からないため、`10月20日` のように指定してください"
+            }
+            Invalid::AmbiguousMeridiem => "午前・午後には 0 時から 11 時を指定してください",
+            Invalid::MinuteDigits => "時刻の分は `21:05` のように 2 桁で指定してください",
+            Invalid::MinuteWithoutUnit => "`時` の後の数字には `分` を付けてください",
+            Invalid::UnitOrder => "同じ単位が重なっているか、大きい単位から順に並んでいません",
+            Invalid::Decimal => "小数は使えません",
+            Invalid::DateWithoutTime => "日付だけでは時刻が分かりません",
+            Invalid::TimeOutOfRange => "時刻は 0:00 から 23:59 で指定してください",
+            Invalid::DateOutOfRange => "存在しない日付です",
+            Invalid::TooLarge => "長すぎます",
+            Invalid::NotDuration => "日時ではなく時間の長さを指定してください",
+        }
+    }
+}
+
+/// 全角の数字と記号を半角にし, 英字を小文字にします
+fn normalize(input: &str) -> String {
+    input
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '０'..='９' => char::from_u32(c as u32 - '０' as u32 + '0' as u32).unwrap_or(c),
+            '：' => ':',
+            '／' => '/',
+            '－' => '-',
+            '．' => '.',
+            '　' => ' ',
+            c => c.to_ascii_lowercase(),
+        })
+        .collect()
+}
+
+struct Scanner {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Scanner {
+    fn new(input: &str) -> Self {
+        Self {
+            chars: input.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn is_end(&self) -> bool {
+        self.pos == self.chars.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(' ') {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, word: &str) -> bool {
+        let len = word.chars().count();
+        let matches = self.chars.len() - self.pos >= len
+            && word
+                .chars()
+                .zip(&self.chars[self.pos..])
+                .all(|(a, &b)| a == b);
+        if matches {
+            self.pos += len;
+        }
+        matches
+    }
+
+    /// 数字を読み取り, 値と桁数を返します
+    fn number(&mut self) -> Result<Option<(u64, usize)>, Invalid> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Ok(None);
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        if self.peek() == Some('.') {
+            return Err(Invalid::Decimal);
+        }
+        let value = digits.parse().map_err(|_| Invalid::TooLarge)?;
+        Ok(Some((value, self.pos - start)))
+    }
+}
+
+/// 時間の長さを読み取った結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParsedDuration {
+    /// 単位のない数字だけの入力
+    Bare(u64),
+    Minutes(u64),
+}
+
+/// `1h30m` や `1時間30分` を読み取ります (単位は大きい順に 1 回ずつ)
+fn parse_duration(input: &str) -> Result<ParsedDuration, Invalid> {
+    let mut scanner = Scanner::new(input);
+    let mut total: u64 = 0;
+    let mut last_unit = None;
+    loop {
+        scanner.skip_spaces();
+        if scanner.is_end() {
+            break;
+        }
+        let (value, _) = scanner.number()?.ok_or(Invalid::Format)?;
+        scanner.skip_spaces();
+        let unit = DURATION_UNITS
+            .iter()
+            .position(|(words, _)| words.iter().any(|word| scanner.eat(word)));
+        let Some(unit) = unit else {
+            return if scanner.is_end() && last_unit.is_none() {
+                Ok(ParsedDuration::Bare(value))
+            } else {
+                Err(Invalid::Format)
+            };
+        };
+        if last_unit.is_some_and(|last| unit <= last) {
+            return Err(Invalid::UnitOrder);
+        }
+        last_unit = Some(unit);
+        total = value
+            .checked_mul(DURATION_UNITS[unit].1)
+            .and_then(|minutes| total.checked_add(minutes))
+            .ok_or(Invalid::TooLarge)?;
+    }
+    if last_unit.is_none() {
+        return Err(Invalid::Format);
+    }
+    Ok(ParsedDuration::Minutes(total))
+}
+
+/// 時間の長さではなく日時として読み取る入力かどうか
+fn looks_like_clock(input: &str) -> bool {
+    let chars: Vec<char> = input.chars().collect();
+    let has_clock_hour = chars
+        .iter()
+        .enumerate()
+        .any(|(index, &c)| c == '時' && chars.get(index + 1) != Some(&'間'));
+    has_clock_hour
+        || input.contains([':', '/', '-', '月'])
+        || input.contains("午前")
+        || input.contains("午後")
+        || RELATIVE_DAYS.iter().any(|(word, _)| input.contains(word))
+}
+
+/// 日時のうちの日付
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateInput {
+    /// 今日からの日数
+    Relative(u64),
+    /// 年を省略した場合は `now` 以降で最も近いその日付とします
+    Calendar {
+        year: Option<i32>,
+        month: u32,
+        day: u32,
+    },
+}
+
+/// `明日21時` のような日時を, 日付と時刻に分けて読み取ります
+fn parse_clock(input: &str) -> Result<(Option<DateInput>, NaiveTime), Invalid> {
+    let mut scanner = Scanner::new(input);
+    scanner.skip_spaces();
+    let mut date = RELATIVE_DAYS
+        .iter()
+        .find(|(word, _)| scanner.eat(word))
+        .map(|&(_, days)| DateInput::Relative(days));
+    if date.is_none() {
+        let start = scanner.pos;
+        if let Some((first, digits)) = scanner.number()? {
+            if let Some(separator) = scanner.peek().filter(|&c| c == '-' || c == '/') {
+                if digits != 4 {
+                    return Err(Invalid::AmbiguousDate);
+                }
+                scanner.pos += 1;
+                let (month, _) = scanner.number()?.ok_or(Invalid::Format)?;
+                if !scanner.eat(&separator.to_string()) {
+                    return Err(Invalid::Format);
+                }
+                let (day, _) = scanner.number()?.ok_or(Invalid::Format)?;
+                date = Some(DateInput::Calendar {
+                    year: Some(first as i32),
+                    month: calendar_number(month)?,
+                    day: calendar_number(day)?,
+                });
+            } else if scanner.eat("月") {
+                let (day, _) = scanner.number()?.ok_or(Invalid::Format)?;
+                if !scanner.eat("日") {
+                    return Err(Invalid::Format);
+                }
+                date = Some(DateInput::Calendar {
+                    year: None,
+                    month: calendar_number(first)?,
+                    day: calendar_number(day)?,
+                });
+            } else {
+                scanner.pos = start;
+            }
+        }
+    }
+
+    scanner.skip_spaces();
+    let meridiem = if scanner.eat("午前") {
+        Some(0)
+    } else if scanner.eat("午後") {
+        Some(12)
+    } else {
+        None
+    };
+    let Some((hour, _)) = scanner.number()? else {
+        return Err(if date.is_some() && scanner.is_end() {
+            Invalid::DateWithoutTime
+        } else {
+            Invalid::Format
+        });
+    };
+    let minute = if scanner.eat(":") {
+        match scanner.number()? {
+            Some((minute, 2)) => minute,
+            Some(_) => return Err(Invalid::MinuteDigits),
+            None => return Err(Invalid::Format),
+        }
+    } else if scanner.eat("時") {
+        if scanner.eat("半") {
+            30
+        } else if let Some((minute, _)) = scanner.number()? {
+            if !scanner.eat("分") {
+                return Err(Invalid::MinuteWithoutUnit);
+            }
+            minute
+        } else {
+            0
+        }
+    } else {
+        return Err(Invalid::BareNumber);
+    };
+    scanner.skip_spaces();
+    if !scanner.is_end() {
+        return Err(Invalid::Format);
+    }
+    let hour = match meridiem {
+        Some(_) if 12 <= hour => return Err(Invalid::AmbiguousMeridiem),
+        Some(offset) => hour + offset,
+        None => hour,
+    };
+    let time = u32::try_from(hour)
+        .ok()
+        .zip(u32::try_from(minute).ok())
+        .and_then(|(hour, minute)| NaiveTime::from_hms_opt(hour, minute, 0))
+        .ok_or(Invalid::TimeOutOfRange)?;
+    Ok((date, time))
+}
+
+/// 日付の月や日の数字 (大きすぎる数字は存在しない日付とします)
+fn calendar_number(number: u64) -> Result<u32, Invalid> {
+    u32::try_from(number).map_err(|_| Invalid::DateOutOfRange)
+}
+
+/// 時間の長さまたは日時を読み取ります
+///
+/// 日付のない時刻は `now` 以降で最も近いその時刻とし, 時刻は `tz` のその日時の時差で扱います。
+/// 数字だけの入力は分数か時刻か分からないため読み取りません。
+fn parse_input(input: &str, now: Timestamp, tz: Tz) -> Result<TimeInput, Invalid> {
+    let input = normalize(input);
+    if !looks_like_clock(&input) {
+        let duration = input.strip_suffix('後').unwrap_or(&input);
+        return match parse_duration(duration)? {
+            ParsedDuration::Bare(_) => Err(Invalid::BareNumber),
+            ParsedDuration::Minutes(minutes) => Ok(TimeInput::Duration(Duration::from_secs(
+                minutes.checked_mul(60).ok_or(Invalid::TooLarge)?,
+            ))),
+        };
+    }
+
+    let (date, time) = parse_clock(&input)?;
+    let local_now = DateTime::from_timestamp(now.unix_timestamp(), 0)
+        .ok_or(Invalid::DateOutOfRange)?
+        .with_timezone(&tz)
+        .naive_local();
+    let today = local_now.date();
+    let local = match date {
+        None => {
+            let local = today.and_time(time);
+            if local <= local_now {
+                local
+                    .checked_add_days(Days::new(1))
+                    .ok_or(Invalid::DateOutOfRange)?
+            } else {
+                local
+            }
+        }
+        Some(DateInput::Relative(days)) => today
+            .checked_add_days(Days::new(days))
+            .ok_or(Invalid::DateOutOfRange)?
+            .and_time(time),
+        Some(DateInput::Calendar {
+            year: Some(year),
+            month,
+            day,
+        }) => calendar_date(year, month, day)?.and_time(time),
+        Some(DateInput::Calendar {
+            year: None,
+            month,
+            day,
+        }) => {
+            // 2 月 29 日は, 次にその日がある年とします
+            let mut local = Err(Invalid::DateOutOfRange);
+            for year in today.year()..today.year() + 8 {
+                if let Ok(date) = calendar_date(year, month, day)
+                    && local_now < date.and_time(time)
+                {
+                    local = Ok(date.and_time(time));
+                    break;
+                }
+                if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+                    break;
+                }
+            }
+            local?
+        }
+    };
+    Timestamp::from_unix_timestamp(timezone::to_utc(tz, local.and_utc().timestamp()))
+        .map(TimeInput::At)
+        .map_err(|_| Invalid::DateOutOfRange)
+}
+
+/// 年月日の日付 (存在しない日付はエラーにします)
+fn calendar_date(year: i32, month: u32, day: u32) -> Result<NaiveDate, Invalid> {
+    NaiveDate::from_ymd_opt(year, month, day).ok_or(Invalid::DateOutOfRange)
+}
+
+fn format_error(name: &str, input: &str, invalid: Invalid, formats: &str) -> String {
+    format!(
+        "{name} の `{}` を読み取れません ({})\n使える形式: {formats}",
+        input.trim(),
+        invalid.reason()
+    )
+}
+
+/// `/recruit create` の `start_time` のような日時を読み取ります
+///
+/// `2時間後` のような時間の長さは `now` からの日時とします。過去の日時はエラーにします。
+pub fn parse_start_time(
+    name: &str,
+    input: &str,
+    now: Timestamp,
+    tz: Tz,
+) -> Result<Timestamp, String> {
+    let at = match parse_input(input, now, tz) {
+        Ok(TimeInput::At(at)) => at,
+        Ok(TimeInput::Duration(duration)) => {
+            let seconds = i64::try_from(duration.as_secs()).unwrap_or(i64::MAX);
+            Timestamp::from_unix_timestamp(now.unix_timestamp().saturating_add(seconds))
+                .map_err(|_| format_error(name, input, Invalid::TooLarge, START_TIME_FORMATS))?
+        }
+        Err(invalid) => return Err(format_error(name, input, invalid, START_TIME_FORMATS)),
+    };
+    if at <= now {
+        return Err(format!("{name} には現在より後の日時を指定してください"));
+    }
+    Ok(at)
+}
+
+/// `delete_after_minutes` のような時間の長さを分数で読み取ります
+///
+/// 単位のない数字は分数とします。`min` 分より短い場合はエラーにします。
+pub fn parse_minutes(name: &str, input: &str, min: u64) -> Result<u64, String> {
+    let input_normalized = normalize(input);
+    let parsed = if looks_like_clock(&input_normalized) {
+        Err(Invalid::NotDuration)
+    } else {
+        parse_duration(&input_normalized)
+    };
+    let minutes = match parsed {
+        Ok(ParsedDuration::Bare(minutes) | ParsedDuration::Minutes(minutes)) => minutes,
+        Err(invalid) => return Err(format_error(name, input, invalid, DURATION_FORMATS)),
+    };
+    if minutes < min {
+        return Err(format!("{name} は {min} 分以上を指定してください。"));
+    }
+    Ok(minutes)
+}
+
+/// 省略できるパラメーターの時間の長さを分数で読み取ります
+pub fn parse_optional_minutes(
+    name: &str,
+    input: Option<&str>,
+    min: u64,
+) -> Result<Option<u64>, String> {
+    input
+        .map(|input| parse_minutes(name, input, min))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> Timestamp {
+        Timestamp::parse(rfc3339).unwrap()
+    }
+
+    fn tz(name: &str) -> Tz {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn durations() {
+        let now = at("2026-10-14T03:00:00Z");
+        let cases = [
+            ("2時間", Ok(2 * 60 * 60)),
+            ("30分", Ok(30 * 60)),
+            ("30分後", Ok(30 * 60)),
+            ("1時間30分", Ok(90 * 60)),
+            ("1h30m", Ok(90 * 60)),
+            ("90m", Ok(90 * 60)),
+            ("1日", Ok(24 * 60 * 60)),
+            ("２時間", Ok(2 * 60 * 60)),
+            ("90", Err(Invalid::BareNumber)),
+            ("30m2h", Err(Invalid::UnitOrder)),
+            ("1.5h", Err(Invalid::Decimal)),
+            ("soon", Err(Invalid::Format)),
+        ];
+        for (input, expected) in cases {
+            let expected =
+                expected.map(|seconds| TimeInput::Duration(Duration::from_secs(seconds)));
+            assert_eq!(
+                parse_input(input, now, tz("Asia/Tokyo")),
+                expected,
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn clock_times() {
+        // 2026-10-14 (水) 12:00 JST
+        let now = at("2026-10-14T03:00:00Z");
+        let cases = [
+            ("21:00", Ok("2026-10-14T21:00:00+09:00")),
+            ("２１：００", Ok("2026-10-14T21:00:00+09:00")),
+            ("21時", Ok("2026-10-14T21:00:00+09:00")),
+            ("21時半", Ok("2026-10-14T21:30:00+09:00")),
+            ("21時5分", Ok("2026-10-14T21:05:00+09:00")),
+            ("午後9時", Ok("2026-10-14T21:00:00+09:00")),
+            // 過ぎた時刻と今の時刻は翌日
+            ("9:00", Ok("2026-10-15T09:00:00+09:00")),
+            ("12:00", Ok("2026-10-15T12:00:00+09:00")),
+            ("午前0時", Ok("2026-10-15T00:00:00+09:00")),
+            ("今日9時", Ok("2026-10-14T09:00:00+09:00")),
+            ("明日20:00", Ok("2026-10-15T20:00:00+09:00")),
+            ("あさって21時半", Ok("2026-10-16T21:30:00+09:00")),
+            ("10月20日21時", Ok("2026-10-20T21:00:00+09:00")),
+            // 年のない日付は次にその日付が来る年
+            ("1月5日9時", Ok("2027-01-05T09:00:00+09:00")),
+            ("10月14日9時", Ok("2027-10-14T09:00:00+09:00")),
+            ("2026-10-20 21:00", Ok("2026-10-20T21:00:00+09:00")),
+            ("2026/10/20 21:00", Ok("2026-10-20T21:00:00+09:00")),
+        ];
+        for (input, expected) in cases {
+            let expected = expected.map(|rfc3339| TimeInput::At(at(rfc3339)));
+            assert_eq!(
+                parse_input(input, now, tz("Asia/Tokyo")),
+                expected,
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn month_ends_and_leap_years() {
+        let tokyo = tz("Asia/Tokyo");
+        let cases = [
+            // 月末と年末の翌日
+            (
+                "2026-10-31T23:30:00+09:00",
+                "0:15",
+                Ok("2026-11-01T00:15:00+09:00"),
+            ),
+            (
+                "2026-10-31T23:30:00+09:00",
+                "明日9時",
+                Ok("2026-11-01T09:00:00+09:00"),
+            ),
+            (
+                "2026-12-31T22:00:00+09:00",
+                "明日9時",
+                Ok("2027-01-01T09:00:00+09:00"),
+            ),
+            (
+                "2026-12-31T22:00:00+09:00",
+                "1月1日0時",
+                Ok("2027-01-01T00:00:00+09:00"),
+            ),
+            (
+                "2026-10-14T12:00:00+09:00",
+                "2026-10-31 23:59",
+                Ok("2026-10-31T23:59:00+09:00"),
+            ),
+            (
+                "2026-10-14T12:00:00+09:00",
+                "11月30日21時",
+                Ok("2026-11-30T21:00:00+09:00"),
+            ),
+            (
+                "2026-10-14T12:00:00+09:00",
+                "2026-11-31 10:00",
+                Err(Invalid::DateOutOfRange),
+            ),
+            (
+                "2026-10-14T12:00:00+09:00",
+                "11月31日21時",
+                Err(Invalid::DateOutOfRange),
+            ),
+            (
+                "2026-10-14T12:00:00+09:00",
+                "13月1日21時",
+                Err(Invalid::DateOutOfRange),
+            ),
+            (
+                "2026-10-14T12:00:00+09:00",
+                "10月0日21時",
+                Err(Invalid::DateOutOfRange),
+            ),
+            // うるう年
+            (
+                "2026-10-14T12:00:00+09:00",
+                "2028-02-29 21:00",
+                Ok("2028-02-29T21:00:00+09:00"),
+            ),
+            (
+                "2026-10-14T12:00:00+09:00",
+                "2027-02-29 21:00",
+                Err(Invalid::DateOutOfRange),
+            ),
+            (
+                "2026-10-14T12:00:00+09:00",
+                "2100-02-29 21:00",
+                Err(Invalid::DateOutOfRange),
+            ),
+            (
+                "2026-10-14T12:00:00+09:00",
+                "2月29日21時",
+                Ok("2028-02-29T21:00:00+09:00"),
+            ),
+            (
+                "2028-02-28T22:00:00+09:00",
+                "明日9時",
+                Ok("2028-02-29T09:00:00+09:00"),
+            ),
+            (
+                "2027-02-28T22:00:00+09:00",
+                "明日9時",
+                Ok("2027-03-01T09:00:00+09:00"),
+            ),
+        ];
+        for (now, input, expected) in cases {
+            let expected = expected.map(|rfc3339| TimeInput::At(at(rfc3339)));
+            assert_eq!(
+                parse_input(input, at(now), tokyo),
+                expected,
+                "{now} {input}"
+            );
+        }
+    }
+
+    #[test]
+    fn daylight_saving_time() {
+        let new_york = tz("America/New_York");
+        let cases = [
+            // 2026-03-08 2:00 に 1 時間進みます
+            (
+                "2026-03-07T12:00:00-05:00",
+                "明日12:00",
+                "2026-03-08T12:00:00-04:00",
+            ),
+            // 存在しない 2:30 は切り替え前の時差で読みます (3:30 EDT)
+            (
+                "2026-03-07T12:00:00-05:00",
+                "明日2:30",
+                "2026-03-08T03:30:00-04:00",
+            ),
+            (
+                "2026-03-08T01:00:00-05:00",
+                "2:30",
+                "2026-03-08T03:30:00-04:00",
+            ),
+            // 2026-11-01 2:00 に 1 時間戻ります。2 回ある 1:30 は早い方にします
+            (
+                "2026-10-31T12:00:00-04:00",
+                "明日1:30",
+                "2026-11-01T01:30:00-04:00",
+            ),
+            (
+                "2026-10-31T12:00:00-04:00",
+                "明日12:00",
+                "2026-11-01T12:00:00-05:00",
+            ),
+            (
+                "2026-10-31T12:00:00-04:00",
+                "2026-11-01 21:00",
+                "2026-11-01T21:00:00-05:00",
+            ),
+        ];
+        for (now, input, expected) in cases {
+            assert_eq!(
+                parse_input(input, at(now), new_york),
+                Ok(TimeInput::At(at(expected))),
+                "{now} {input}"
+            );
+        }
+    }
+
+    #[test]
+    fn ambiguous_inputs_are_rejected() {
+        let now = at("2026-10-14T03:00:00Z");
+        let cases = [
+            // 分数か時刻か分からない
+            ("21", Invalid::BareNumber),
+            // 月と日の順番が分からない
+            ("10/20 21:00", Invalid::AmbiguousDate),
+            ("20-10 21:00", Invalid::AmbiguousDate),
+            // 午後 13 時は 13 時か 25 時か分からない
+            ("午後13時", Invalid::AmbiguousMeridiem),
+            ("午前12時", Invalid::AmbiguousMeridiem),
+            ("21:5", Invalid::MinuteDigits),
+            ("21時5", Invalid::MinuteWithoutUnit),
+            ("明日", Invalid::DateWithoutTime),
+            ("10月20日", Invalid::DateWithoutTime),
+            ("24:00", Invalid::TimeOutOfRange),
+            ("21:60", Invalid::TimeOutOfRange),
+            ("明日21時ごろ", Invalid::Format),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                parse_input(input, now, tz("Asia/Tokyo")),
+                Err(expected),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn start_time_must_be_in_the_future() {
+        let now = at("2026-10-14T03:00:00Z");
+        let tokyo = tz("Asia/Tokyo");
+        assert_eq!(
+            parse_start_time("start_time", "2時間後", now, tokyo),
+            Ok(at("2026-10-14T05:00:00Z"))
+        );
+        assert!(parse_start_time("start_time", "2026-10-14 11:00", now, tokyo).is_err());
+        let err = parse_start_time("start_time", "21", now, tokyo).unwrap_err();
+        assert!(err.contains(START_TIME_FORMATS), "{err}");
+        assert_eq!(parse_minutes("bump_after", "90", 10), Ok(90));
+        assert!(parse_minutes("bump_after", "5分", 10).is_err());
+        assert!(parse_minutes("bump_after", "21時", 10).is_err());
+    }
+}
@@ -0,0 +1,574 @@
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::channel_check::{self, ChannelSuitability};
+use crate::discord_api::DiscordApi;
+use crate::i18n::{self, Language, Text};
+use crate::notification::{self, Mentions};
+use crate::recruit_message::{
+    MAX_DESCRIPTION_CHARS, OverMention, ParticipationMode, RecruitConfig, sanitize_text,
+};
+use crate::scheduler::{self, Job};
+use crate::{
+    DEFAULT_DELETE_AFTER_MINUTES, Data, Error, author_permissions, confirm, game_alias,
+    game_preset, post_recruit_message, reminder, send_ephemeral, time_input, timezone,
+};
+
+/// 日時の投票のボタンの custom_id の接頭辞 (後ろに候補の番号を付けます)
+const TIME_POLL_PREFIX: &str = "time_poll:";
+/// `voting_minutes` を指定しなかった場合の投票の締切までの分数
+const DEFAULT_VOTING_MINUTES: u64 = 60;
+
+/// 募集の前に開始予定の日時を決める投票
+///
+/// 投票のメッセージは募集メッセージではないため, 募集設定を持たず保存ファイルにだけ記録します。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimePoll {
+    pub channel_id: ChannelId,
+    pub guild_id: GuildId,
+    pub organizer: UserId,
+    pub game_title: String,
+    pub required_players: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_players: Option<usize>,
+    /// 候補の日時 (早い順)
+    pub candidates: Vec<Timestamp>,
+    /// 候補ごとの, 投票したユーザー (投票した順)
+    pub votes: Vec<Vec<UserId>>,
+    pub deadline: Timestamp,
+}
+
+impl TimePoll {
+    /// `index` 番目の候補への投票を切り替え, 投票した場合は `true`, 取り消した場合は `false` を返します
+    pub fn toggle(&mut self, index: usize, user_id: UserId) -> Option<bool> {
+        let votes = self.votes.get_mut(index)?;
+        match votes.iter().position(|&voter| voter == user_id) {
+            Some(position) => {
+                votes.remove(position);
+                Some(false)
+            }
+            None => {
+                votes.push(user_id);
+                Some(true)
+            }
+        }
+    }
+
+    /// 票が最も多い候補の番号 (同数なら早い日時, 誰も投票していなければ `None`)
+    pub fn winner(&self) -> Option<usize> {
+        self.votes
+            .iter()
+            .enumerate()
+            .filter(|(_, votes)| !votes.is_empty())
+            .max_by_key(|&(index, votes)| (votes.len(), std::cmp::Reverse(index)))
+            .map(|(index, _)| index)
+    }
+
+    /// 投票したユーザー (いずれかの候補に最初に投票した順, 重複なし)
+    pub fn voters(&self) -> Vec<UserId> {
+        let mut voters: Vec<UserId> = Vec::new();
+        for &user_id in self.votes.iter().flatten() {
+            if !voters.contains(&user_id) {
+                voters.push(user_id);
+            }
+        }
+        voters
+    }
+
+    /// 投票のメッセージの本文
+    pub fn render(&self, language: Language) -> String {
+        let candidates: Vec<String> = self
+            .candidates
+            .iter()
+            .zip(&self.votes)
+            .enumerate()
+            .map(|(index, (&candidate, votes))| {
+                language.text(Text::TimePollCandidate {
+                    number: index + 1,
+                    start_time: &reminder::format_start_time(candidate),
+                    votes: votes.len(),
+                })
+            })
+            .collect();
+        language.text(Text::TimePoll {
+            game_title: &self.game_title,
+            required_players: self.required_players,
+            candidates: &candidates.join("\n"),
+            deadline: self.deadline.unix_timestamp(),
+        })
+    }
+}
+
+/// 候補の日時を早い順に並べ, 同じ日時を除きます
+///
+/// 投票の締切より前の候補はエラーにします。
+pub fn sort_candidates(
+    mut candidates: Vec<Timestamp>,
+    deadline: Timestamp,
+) -> Result<Vec<Timestamp>, String> {
+    candidates.sort();
+    candidates.dedup();
+    if candidates.first().is_some_and(|&first| first <= deadline) {
+        return Err("候補の日時は投票の締切より後にしてください".to_string());
+    }
+    Ok(candidates)
+}
+
+fn components(poll: &TimePoll) -> Vec<CreateActionRow> {
+    let buttons = (0..poll.candidates.len())
+        .map(|index| {
+            CreateButton::new(format!("{TIME_POLL_PREFIX}{index}"))
+                .label((index + 1).to_string())
+                .style(ButtonStyle::Secondary)
+        })
+        .collect();
+    vec![CreateActionRow::Buttons(buttons)]
+}
+
+/// 開始予定の日時を投票で決めてから募集します
+///
+/// 締切に最も票が多い日時 (同数なら早い日時) を `start_time` にして募集を投稿します。
+#[allow(clippy::too_many_arguments)]
+#[poise::command(slash_command, guild_only, rename = "poll")]
+pub async fn recruit_poll(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集するゲーム名"]
+    #[autocomplete = "game_alias::autocomplete_game_title"]
+    game_title: String,
+    #[description = "候補の日時 (例: 21:00, 明日21時)"] time1: String,
+    #[description = "2つ目の候補の日時"] time2: Option<String>,
+    #[description = "3つ目の候補の日時"] time3: Option<String>,
+    #[description = "4つ目の候補の日時"] time4: Option<String>,
+    #[description = "5つ目の候補の日時"] time5: Option<String>,
+    #[description = "開始に必要な人数 (/settings game で人数を登録したゲームは省略できます)"]
+    required_players: Option<usize>,
+    #[description = "参加できる最大人数"] max_players: Option<usize>,
+    #[description = "投票の締切までの時間 (既定: 60分)"] voting_minutes: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let data = ctx.data();
+    let settings = data.storage.guild_settings(guild_id);
+    let registered = settings
+        .game_players
+        .find(&settings.game_aliases, &game_title)
+        .cloned();
+    let (required_players, max_players) =
+        match game_preset::resolve_players(required_players, max_players, registered.as_ref()) {
+            Ok(players) => players,
+            Err(err) => {
+                send_ephemeral(ctx, &err).await?;
+                return Ok(());
+            }
+        };
+    if required_players == 0
+        || max_players.is_some_and(|max_players| max_players < required_players)
+    {
+        send_ephemeral(
+            ctx,
+            "required_players は 1 以上、max_players は required_players 以上を指定してください。",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let member_roles = ctx
+        .author_member()
+        .await
+        .map(|member| member.roles.clone())
+        .unwrap_or_default();
+    let member_permissions = author_permissions(ctx).unwrap_or_default();
+    if !settings.can_create_recruit(&member_roles, member_permissions, false) {
+        send_ephemeral(
+            ctx,
+            "募集を作成する権限がありません。主催者ロールを持つメンバーのみ作成できます。",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let parsed = (|| {
+        let voting_minutes =
+            time_input::parse_optional_minutes("voting_minutes", voting_minutes.as_deref(), 1)?
+                .unwrap_or(DEFAULT_VOTING_MINUTES);
+        let deadline = scheduler::fire_at_after_minutes(voting_minutes);
+        let now = Timestamp::now();
+        let tz = timezone::guild_timezone(data, Some(guild_id));
+        let mut candidates = Vec::new();
+        for (name, input) in [
+            ("time1", Some(time1)),
+            ("time2", time2),
+            ("time3", time3),
+            ("time4", time4),
+            ("time5", time5),
+        ] {
+            if let Some(input) = input {
+                candidates.push(time_input::parse_start_time(name, &input, now, tz)?);
+            }
+        }
+        Ok::<_, String>((sort_candidates(candidates, deadline)?, deadline))
+    })();
+    let (candidates, deadline) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+    };
+
+    if let Some(channel) = ctx.guild_channel().await {
+        match channel_check::check_recruit_channel(ctx, guild_id, &channel).await? {
+            ChannelSuitability::Suitable => {}
+            ChannelSuitability::Refuse(reason) => {
+                send_ephemeral(ctx, reason).await?;
+                return Ok(());
+            }
+            ChannelSuitability::Warn(warnings) => {
+                let content = format!(
+                    "{}\nこのチャンネルに投票を投稿しますか?",
+                    warnings.join("\n")
+                );
+                if !confirm::confirm(ctx, &content, "投稿する").await? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    let poll = TimePoll {
+        channel_id: ctx.channel_id(),
+        guild_id,
+        organizer: ctx.author().id,
+        game_title: sanitize_text(game_title.trim()),
+        required_players,
+        max_players,
+        votes: vec![Vec::new(); candidates.len()],
+        candidates,
+        deadline,
+    };
+    let language = settings.language;
+    let message = ctx
+        .channel_id()
+        .send_message(
+            ctx,
+            notification::plain_message(poll.render(language)).components(components(&poll)),
+        )
+        .await?;
+    data.storage.set_time_poll(message.id, poll)?;
+    data.scheduler.enqueue(
+        &*data.storage,
+        deadline,
+        Job::FinishTimePoll {
+            channel_id: message.channel_id,
+            message_id: message.id,
+            guild_id: Some(guild_id),
+        },
+    );
+    send_ephemeral(
+        ctx,
+        &format!(
+            "日時の投票を投稿しました。<t:{}:R> に最も票が多い日時で募集します {}",
+            deadline.unix_timestamp(),
+            message.link()
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+pub fn is_time_poll_button(interaction: &ComponentInteraction) -> bool {
+    interaction.data.custom_id.starts_with(TIME_POLL_PREFIX)
+}
+
+/// 候補のボタンを押したユーザーの投票を切り替え, 投票のメッセージの票数を書き換えます
+pub async fn handle_time_poll_button(
+    ctx: &Context,
+    data: &Data,
+    interaction: &ComponentInteraction,
+) -> Result<(), Error> {
+    let Some(index) = interaction
+        .data
+        .custom_id
+        .strip_prefix(TIME_POLL_PREFIX)
+        .and_then(|index| index.parse::<usize>().ok())
+    else {
+        return Ok(());
+    };
+    let message_id = interaction.message.id;
+    let _guard = data.lock_message(message_id).await;
+    let language = i18n::guild_language(data, interaction.guild_id);
+    let poll = data
+        .storage
+        .time_poll(message_id)
+        .filter(|poll| Timestamp::now() < poll.deadline);
+    let Some(mut poll) = poll else {
+        let response = CreateInteractionResponseMessage::new()
+            .content(language.text(Text::TimePollClosed))
+            .ephemeral(true);
+        interaction
+            .create_response(ctx, CreateInteractionResponse::Message(response))
+            .await?;
+        return Ok(());
+    };
+    if poll.toggle(index, interaction.user.id).is_none() {
+        return Ok(());
+    }
+    data.storage.set_time_poll(message_id, poll.clone())?;
+    let response = CreateInteractionResponseMessage::new().content(poll.render(language));
+    interaction
+        .create_response(ctx, CreateInteractionResponse::UpdateMessage(response))
+        .await?;
+    Ok(())
+}
+
+/// 投票の締切に, 最も票が多い日時で募集を投稿します
+pub async fn finish_time_poll<A: DiscordApi>(
+    api: &A,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Result<(), Error> {
+    let _guard = data.lock_message(message_id).await;
+    let Some(poll) = data.storage.time_poll(message_id) else {
+        return Ok(());
+    };
+    let language = i18n::guild_language(data, Some(poll.guild_id));
+    let result = match poll.winner() {
+        Some(index) => {
+            let start_time = poll.candidates[index];
+            let config = recruit_config(data, &poll, start_time, language);
+            let posted = post_recruit_message(
+                api,
+                data,
+                Some(poll.guild_id),
+                channel_id,
+                config,
+                None,
+                &Mentions::users(poll.voters()),
+            )
+            .await?;
+            language.text(Text::TimePollDecided {
+                start_time: &reminder::format_start_time(start_time),
+                link: &posted.link(),
+            })
+        }
+        None => language.text(Text::TimePollNoVotes),
+    };
+    data.storage.remove_time_poll(message_id)?;
+    let content = format!("{}\n{result}", poll.render(language));
+    api.edit_message(
+        channel_id,
+        message_id,
+        EditMessage::new()
+            .content(&content)
+            .components(Vec::new())
+            .allowed_mentions(CreateAllowedMentions::new()),
+    )
+    .await?;
+    Ok(())
+}
+
+/// 投票で決まった日時の募集の設定
+///
+/// 投票したユーザーは参加者には数えず, 募集の説明でメンションして知らせます。
+fn recruit_config(
+    data: &Data,
+    poll: &TimePoll,
+    start_time: Timestamp,
+    language: Language,
+) -> RecruitConfig {
+    let settings = data.storage.guild_settings(poll.guild_id);
+    let mut mentions = Vec::new();
+    for user_id in poll.voters() {
+        let candidate = [mentions.as_slice(), &[user_id.mention().to_string()]]
+            .concat()
+            .join(" ");
+        let description = language.text(Text::TimePollVoters {
+            mentions: &candidate,
+        });
+        if MAX_DESCRIPTION_CHARS < description.chars().count() {
+            break;
+        }
+        mentions.push(user_id.mention().to_string());
+    }
+    let description = language.text(Text::TimePollVoters {
+        mentions: &mentions.join(" "),
+    });
+    RecruitConfig {
+        game_title: poll.game_title.clone(),
+        required_players: poll.required_players,
+        max_players: poll.max_players,
+        enforce_cap: false,
+        organizer: Some(poll.organizer),
+        description: Some(description),
+        mention_roles: Vec::new(),
+        notify_on_reaction: true,
+        auto_assign_role_on_reaction: false,
+        delete_after_minutes: DEFAULT_DELETE_AFTER_MINUTES,
+        start_delay_minutes: None,
+        count_voice_channel: None,
+        require_undeafened: false,
+        simulation: false,
+        extra_reactions: Vec::new(),
+        cleanup_after_start_minutes: settings.cleanup_after_start_minutes,
+        notify_organizer_when_close: false,
+        strict_order: false,
+        game_options: Vec::new(),
+        category: None,
+        start_time: Some(start_time),
+        remind_before_minutes: settings.remind_before_minutes,
+        language: Some(language),
+        roll_call_minutes: None,
+        priority_role: None,
+        priority_until: None,
+        over_mention: OverMention::default(),
+        multi_lobby: false,
+        override_quiet_hours: false,
+        preset_participants: Vec::new(),
+        participation_mode: ParticipationMode::default(),
+        require_voice_at_start: None,
+        voice_grace_minutes: None,
+        slots: Vec::new(),
+        reaction_emoji: settings.reaction_emoji,
+        application_question: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_discord::{self, Call, MockDiscord};
+    use crate::recruit_message::at;
+
+    const GUILD_ID: GuildId = GuildId::new(10);
+    const CHANNEL_ID: ChannelId = ChannelId::new(20);
+    const POLL_ID: MessageId = MessageId::new(50);
+
+    fn users(ids: &[u64]) -> Vec<UserId> {
+        ids.iter().copied().map(UserId::new).collect()
+    }
+
+    /// 締切の 1 時間後と 2 時間後を候補にした投票
+    fn poll(votes: &[&[u64]]) -> TimePoll {
+        TimePoll {
+            channel_id: CHANNEL_ID,
+            guild_id: GUILD_ID,
+            organizer: UserId::new(2),
+            game_title: "Apex".to_string(),
+            required_players: 3,
+            max_players: None,
+            candidates: vec![at(3600), at(7200)],
+            votes: votes.iter().map(|votes| users(votes)).collect(),
+            deadline: at(0),
+        }
+    }
+
+    /// 投票のメッセージを書き換えた本文
+    fn edited_poll(discord: &MockDiscord) -> String {
+        discord
+            .calls()
+            .into_iter()
+            .find_map(|call| match call {
+                Call::EditMessage {
+                    message_id: POLL_ID,
+                    content,
+                } => content,
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn votes_are_toggled_on_and_off() {
+        let mut poll = poll(&[&[], &[]]);
+        assert_eq!(poll.toggle(1, UserId::new(11)), Some(true));
+        assert_eq!(poll.votes, [users(&[]), users(&[11])]);
+        assert_eq!(poll.toggle(1, UserId::new(11)), Some(false));
+        assert_eq!(poll.votes, [users(&[]), users(&[])]);
+        // 候補にない番号は無視します
+        assert_eq!(poll.toggle(2, UserId::new(11)), None);
+    }
+
+    #[test]
+    fn the_most_voted_candidate_wins_and_ties_go_to_the_earliest() {
+        assert_eq!(poll(&[&[11], &[12, 13]]).winner(), Some(1));
+        assert_eq!(poll(&[&[11, 12], &[13, 14]]).winner(), Some(0));
+        assert_eq!(poll(&[&[], &[11]]).winner(), Some(1));
+    }
+
+    #[test]
+    fn no_votes_means_no_winner() {
+        assert_eq!(poll(&[&[], &[]]).winner(), None);
+    }
+
+    #[test]
+    fn voters_are_listed_once_in_the_order_they_first_voted() {
+        assert_eq!(poll(&[&[12, 11], &[11, 13]]).voters(), users(&[12, 11, 13]));
+    }
+
+    #[test]
+    fn candidates_must_come_after_the_deadline() {
+        assert_eq!(
+            sort_candidates(vec![at(7200), at(3600), at(7200)], at(0)),
+            Ok(vec![at(3600), at(7200)])
+        );
+        assert!(sort_candidates(vec![at(3600), at(0)], at(0)).is_err());
+        assert!(sort_candidates(vec![at(-60)], at(0)).is_err());
+    }
+
+    #[tokio::test]
+    async fn the_winning_time_is_handed_to_a_recruit() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        let poll = poll(&[&[11], &[12, 11]]);
+        discord.insert_message(mock_discord::message(
+            CHANNEL_ID,
+            POLL_ID,
+            &poll.render(Language::Ja),
+        ));
+        data.storage.set_time_poll(POLL_ID, poll).unwrap();
+
+        finish_time_poll(&discord, &data, CHANNEL_ID, POLL_ID)
+            .await
+            .unwrap();
+        let sent = discord.sent_contents();
+        assert_eq!(sent.len(), 1);
+        let recruit = crate::config_storage::load(
+            &data,
+            &mock_discord::message(CHANNEL_ID, POLL_ID, &sent[0]),
+        )
+        .unwrap();
+        assert_eq!(recruit.config.game_title, "Apex");
+        assert_eq!(recruit.config.start_time, Some(at(7200)));
+        assert_eq!(
+            recruit.config.description.as_deref(),
+            Some("日時の投票に参加した人: <@11> <@12>")
+        );
+        let edited = edited_poll(&discord);
+        assert!(
+            edited.contains(&reminder::format_start_time(at(7200))),
+            "{edited}"
+        );
+        assert_eq!(data.storage.time_poll(POLL_ID), None);
+    }
+
+    #[tokio::test]
+    async fn a_poll_without_votes_posts_no_recruit() {
+        let discord = MockDiscord::new();
+        let data = Data::for_test();
+        let poll = poll(&[&[], &[]]);
+        discord.insert_message(mock_discord::message(
+            CHANNEL_ID,
+            POLL_ID,
+            &poll.render(Language::Ja),
+        ));
+        data.storage.set_time_poll(POLL_ID, poll).unwrap();
+
+        finish_time_poll(&discord, &data, CHANNEL_ID, POLL_ID)
+            .await
+            .unwrap();
+        assert!(discord.sent_contents().is_empty());
+        let no_votes = Language::Ja.text(Text::TimePollNoVotes);
+        assert!(edited_poll(&discord).ends_with(&no_votes));
+        assert_eq!(data.storage.time_poll(POLL_ID), None);
+    }
+}
@@ -0,0 +1,208 @@
+use std::sync::OnceLock;
+
+use chrono::{DateTime, LocalResult, Offset, TimeZone};
+use chrono_tz::{TZ_VARIANTS, Tz};
+use poise::serenity_prelude::*;
+
+use crate::{Data, Error, send_ephemeral};
+
+/// `JOINBELL_TIMEZONE` を指定しない場合のタイムゾーン
+pub const DEFAULT_TIMEZONE: Tz = Tz::Asia__Tokyo;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+const MAX_AUTOCOMPLETE: usize = 25;
+
+/// 既定のタイムゾーン (`JOINBELL_TIMEZONE`)
+///
+/// 指定がなく `JOINBELL_UTC_OFFSET_HOURS` がある場合は, その時差の `Etc/GMT` のタイムゾーンとします。
+pub fn default_timezone() -> Tz {
+    static DEFAULT: OnceLock<Tz> = OnceLock::new();
+    *DEFAULT.get_or_init(|| {
+        if let Ok(name) = std::env::var("JOINBELL_TIMEZONE") {
+            match parse(&name) {
+                Ok(tz) => return tz,
+                Err(err) => eprintln!("Ignoring JOINBELL_TIMEZONE: {err}"),
+            }
+        }
+        std::env::var("JOINBELL_UTC_OFFSET_HOURS")
+            .ok()
+            .and_then(|hours| hours.parse().ok())
+            .filter(|hours: &i64| (-12..=14).contains(hours))
+            .and_then(fixed_offset)
+            .unwrap_or(DEFAULT_TIMEZONE)
+    })
+}
+
+/// 時差が `hours` 時間の `Etc/GMT` のタイムゾーン (`Etc/GMT` の符号は時差と逆です)
+fn fixed_offset(hours: i64) -> Option<Tz> {
+    if hours == 0 {
+        return Some(Tz::UTC);
+    }
+    format!("Etc/GMT{:+}", -hours).parse().ok()
+}
+
+/// IANA のタイムゾーン名を読み取ります (大文字と小文字は区別しません)
+pub fn parse(input: &str) -> Result<Tz, String> {
+    let input = input.trim();
+    Tz::from_str_insensitive(input).map_err(|_| {
+        format!("`{input}` はタイムゾーン名ではありません (例: Asia/Tokyo, Europe/Berlin)")
+    })
+}
+
+/// サーバーのタイムゾーン (設定がなければ既定のタイムゾーン)
+pub fn guild_timezone(data: &Data, guild_id: Option<GuildId>) -> Tz {
+    guild_id
+        .and_then(|guild_id| data.storage.guild_settings(guild_id).timezone)
+        .and_then(|name| parse(&name).ok())
+        .unwrap_or_else(default_timezone)
+}
+
+/// UNIX 時刻 `time` の, `tz` での時差 (秒)
+pub fn offset_seconds(tz: Tz, time: i64) -> i64 {
+    DateTime::from_timestamp(time, 0).map_or(0, |utc| {
+        i64::from(
+            tz.offset_from_utc_datetime(&utc.naive_utc())
+                .fix()
+                .local_minus_utc(),
+        )
+    })
+}
+
+/// UNIX 時刻 `time` の, `tz` での日時 (1970-01-01 0:00 からの秒数)
+pub fn to_local(tz: Tz, time: i64) -> i64 {
+    time + offset_seconds(tz, time)
+}
+
+/// `tz` での日時 `local` (1970-01-01 0:00 からの秒数) を UNIX 時刻にします
+///
+/// 夏時間の終わりで 2 回ある日時は早い方とし, 夏時間の始まりで存在しない日時は切り替え前の時差で読みます
+/// (2:30 に切り替わって 1 時間進む場合は 3:30)。
+pub fn to_utc(tz: Tz, local: i64) -> i64 {
+    let Some(naive) = DateTime::from_timestamp(local, 0).map(|local| local.naive_utc()) else {
+        return local;
+    };
+    match tz.from_local_datetime(&naive) {
+        LocalResult::Single(at) | LocalResult::Ambiguous(at, _) => at.timestamp(),
+        // 切り替えは 1 日に 1 回までなので, 1 日前の時差が切り替え前の時差です
+        LocalResult::None => local - offset_seconds(tz, local - SECONDS_PER_DAY),
+    }
+}
+
+/// タイムゾーン名の入力候補
+async fn autocomplete_timezone(
+    _ctx: poise::Context<'_, Data, Error>,
+    partial: &str,
+) -> impl Iterator<Item = String> {
+    let partial = partial.trim().to_lowercase();
+    TZ_VARIANTS
+        .iter()
+        .map(|tz| tz.name())
+        .filter(move |name| name.to_lowercase().contains(&partial))
+        .take(MAX_AUTOCOMPLETE)
+        .map(str::to_string)
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+/// 日時の入力とサイレント時間帯などに使うタイムゾーンを管理します
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "timezone",
+    subcommands("timezone_set", "timezone_clear")
+)]
+pub async fn settings_timezone(_ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// サーバーのタイムゾーンを設定します
+///
+/// 夏時間のあるタイムゾーンでは, 日時ごとにその時点の時差で扱います。
+#[poise::command(slash_command, guild_only, ephemeral, rename = "set")]
+async fn timezone_set(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "IANA のタイムゾーン名 (例: Asia/Tokyo, Europe/Berlin)"]
+    #[autocomplete = "autocomplete_timezone"]
+    timezone: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    let tz = match parse(&timezone) {
+        Ok(tz) => tz,
+        Err(err) => {
+            send_ephemeral(ctx, &err).await?;
+            return Ok(());
+        }
+    };
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.timezone = Some(tz.name().to_string());
+        })?;
+    let content = format!("日時の入力、サイレント時間帯、今日の募集の一覧を {tz} で扱います");
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+/// サーバーのタイムゾーンを既定に戻します
+#[poise::command(slash_command, guild_only, ephemeral, rename = "clear")]
+async fn timezone_clear(ctx: poise::Context<'_, Data, Error>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("guild only")?;
+    ctx.data()
+        .storage
+        .update_guild_settings(guild_id, |settings| {
+            settings.timezone = None;
+        })?;
+    let content = format!("タイムゾーンを既定の {} に戻しました", default_timezone());
+    send_ephemeral(ctx, &content).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-03-10 02:30 (ニューヨークでは夏時間の始まりで存在しない日時)
+    const SPRING_FORWARD: i64 = 1_710_037_800;
+    /// 2024-11-03 01:30 (ニューヨークでは夏時間の終わりで 2 回ある日時)
+    const FALL_BACK: i64 = 1_730_597_400;
+
+    #[test]
+    fn timezone_names_ignore_case() {
+        assert_eq!(parse(" asia/tokyo "), Ok(Tz::Asia__Tokyo));
+        assert!(parse("JST").is_err());
+        assert_eq!(fixed_offset(9), Some(Tz::Etc__GMTMinus9));
+        assert_eq!(fixed_offset(0), Some(Tz::UTC));
+    }
+
+    #[test]
+    fn local_times_round_trip() {
+        let time = 1_700_000_000;
+        assert_eq!(to_local(Tz::Asia__Tokyo, time), time + 9 * 60 * 60);
+        assert_eq!(
+            to_utc(Tz::Asia__Tokyo, to_local(Tz::Asia__Tokyo, time)),
+            time
+        );
+    }
+
+    #[test]
+    fn daylight_saving_gaps_and_overlaps_are_resolved() {
+        let tz = Tz::America__New_York;
+        // 存在しない 2:30 は, 切り替え前の時差で読んで 3:30 (夏時間) とします
+        assert_eq!(to_utc(tz, SPRING_FORWARD), SPRING_FORWARD + 5 * 60 * 60);
+        // 2 回ある 1:30 は, 早い方 (夏時間) とします
+        assert_eq!(to_utc(tz, FALL_BACK), FALL_BACK + 4 * 60 * 60);
+        assert_eq!(offset_seconds(tz, FALL_BACK + 6 * 60 * 60), -5 * 60 * 60);
+    }
+
+    #[test]
+    fn guild_timezone_is_read_from_the_settings() {
+        let data = Data::for_test();
+        let guild_id = GuildId::new(1);
+        data.storage
+            .update_guild_settings(guild_id, |settings| {
+                settings.timezone = Some("Europe/Berlin".to_string())
+            })
+            .unwrap();
+        assert_eq!(guild_timezone(&data, Some(guild_id)), Tz::Europe__Berlin);
+        assert_eq!(guild_timezone(&data, None), default_timezone());
+    }
+}
@@ -0,0 +1,392 @@
+use std::collections::HashSet;
+
+use poise::serenity_prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::discord_api::DiscordApi;
+use crate::discord_error::unknown_entity;
+use crate::i18n::Text;
+use crate::notification::{Mentions, NotificationPolicy};
+use crate::recruit_message::{DEFAULT_VOICE_GRACE_MINUTES, RecruitConfig};
+use crate::scheduler::{self, Job};
+use crate::{
+    Data, Error, begin_start, can_manage_recruit, collect_participants, config_storage, i18n,
+    parse_bot_recruit_message, participant_order, recruit_edit, send_ephemeral,
+    send_start_notification, voice_channel_participants,
+};
+
+/// VC にいる参加者が揃うのを待って, 開始を保留している募集
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoiceHold {
+    pub channel_id: ChannelId,
+    pub guild_id: GuildId,
+    pub voice_channel_id: ChannelId,
+    /// 待つのをやめる日時
+    pub deadline: Timestamp,
+}
+
+/// 開始するときの VC の確認の結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VoiceDecision {
+    /// VC にいる参加者で開始します (`left_out` は VC にいないため見送る参加者)
+    Start {
+        in_voice: Vec<UserId>,
+        left_out: Vec<UserId>,
+    },
+    /// 開始を保留し, VC にいない参加者に呼びかけます
+    Hold { missing: Vec<UserId> },
+    /// 保留したまま待ちます
+    Wait,
+    /// 待つ時間が過ぎたため, 開始を見送ります
+    GiveUp,
+}
+
+/// 参加者 (参加した順) と VC にいるユーザーから, 開始するか保留するかを決めます
+pub fn decide(
+    participants: &[UserId],
+    in_voice: &HashSet<UserId>,
+    required_players: usize,
+    hold: Option<&VoiceHold>,
+    now: Timestamp,
+) -> VoiceDecision {
+    let (present, missing): (Vec<UserId>, Vec<UserId>) = participants
+        .iter()
+        .copied()
+        .partition(|user_id| in_voice.contains(user_id));
+    if required_players <= present.len() {
+        return VoiceDecision::Start {
+            in_voice: present,
+            left_out: missing,
+        };
+    }
+    match hold {
+        None => VoiceDecision::Hold { missing },
+        Some(hold) if hold.deadline <= now => VoiceDecision::GiveUp,
+        Some(_) => VoiceDecision::Wait,
+    }
+}
+
+/// 開始時に VC にいることを求める募集で, 開始する参加者と見送る参加者を返します
+///
+/// VC にいる参加者が足りなければ開始を保留して `None` を返します。
+/// 🔔 で人数が揃う前に開始した場合は確認しません。
+pub async fn check(
+    api: &impl DiscordApi,
+    data: &Data,
+    config: &RecruitConfig,
+    policy: &NotificationPolicy,
+    message: &Message,
+    guild_id: Option<GuildId>,
+    participants: Vec<UserId>,
+) -> Result<Option<(Vec<UserId>, Vec<UserId>)>, Error> {
+    let (Some(guild_id), Some(voice_channel_id)) = (guild_id, config.require_voice_at_start) else {
+        return Ok(Some((participants, Vec::new())));
+    };
+    if participants.len() < config.required_players {
+        return Ok(Some((participants, Vec::new())));
+    }
+    let in_voice: HashSet<UserId> =
+        voice_channel_participants(api, guild_id, voice_channel_id, config.require_undeafened)
+            .into_iter()
+            .collect();
+    let hold = data.storage.voice_hold(message.id);
+    let language = config.language();
+    let channel = voice_channel_id.mention().to_string();
+    match decide(
+        &participants,
+        &in_voice,
+        config.required_players,
+        hold.as_ref(),
+        Timestamp::now(),
+    ) {
+        VoiceDecision::Start { in_voice, left_out } => {
+            if hold.is_some() {
+                release(data, message.id);
+            }
+            Ok(Some((in_voice, left_out)))
+        }
+        VoiceDecision::Hold { missing } => {
+            let minutes = config
+                .voice_grace_minutes
+                .unwrap_or(DEFAULT_VOICE_GRACE_MINUTES);
+            let deadline = scheduler::fire_at_after_minutes(minutes);
+            data.storage.set_voice_hold(
+                message.id,
+                VoiceHold {
+                    channel_id: message.channel_id,
+                    guild_id,
+                    voice_channel_id,
+                    deadline,
+                },
+            )?;
+            data.scheduler.enqueue(
+                &*data.storage,
+                deadline,
+                Job::ExpireVoiceHold {
+                    channel_id: message.channel_id,
+                    message_id: message.id,
+                    guild_id: Some(guild_id),
+                },
+            );
+            let mentions: Vec<String> = missing
+                .iter()
+                .map(|user_id| user_id.mention().to_string())
+                .collect();
+            let content = language.text(Text::VoiceHold {
+                mentions: &mentions.join(" "),
+                channel: &channel,
+                required_players: config.required_players,
+                deadline: deadline.unix_timestamp(),
+            });
+            let mentions = Mentions::users(missing.iter().copied());
+            policy
+                .send(
+                    api,
+                    data,
+                    message.channel_id,
+                    policy.message(&content, &mentions),
+                )
+                .await?;
+            Ok(None)
+        }
+        VoiceDecision::Wait => Ok(None),
+        VoiceDecision::GiveUp => {
+            release(data, message.id);
+            let content = language.text(Text::VoiceHoldExpired {
+                game_title: &config.game_title,
+                channel: &channel,
+            });
+            policy
+                .send(
+                    api,
+                    data,
+                    message.channel_id,
+                    policy.message(&content, &Mentions::none()),
+                )
+                .await?;
+            Ok(None)
+        }
+    }
+}
+
+/// 保留をやめ, 待つ時間の終わりの予約を取り消します
+pub fn release(data: &Data, message_id: MessageId) {
+    if let Err(err) = data.storage.remove_voice_hold(message_id) {
+        eprintln!("Failed to remove voice hold: {err}");
+    }
+    if let Err(err) = data.storage.cancel_jobs(&|job| {
+        matches!(job, Job::ExpireVoiceHold { .. }) && job.recruit_message_id() == Some(message_id)
+    }) {
+        eprintln!("Failed to cancel voice hold job: {err}");
+    }
+}
+
+/// 保留している募集の参加者を数え直し, 開始できるか確かめ直します
+///
+/// 募集が終わっていたり参加者が足りなくなっていたりすれば, 何も送らずに保留をやめます。
+pub async fn resume(
+    ctx: &Context,
+    data: &Data,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    guild_id: Option<GuildId>,
+) -> Result<(), Error> {
+    let _guard = data.lock_message(message_id).await;
+    if data.storage.voice_hold(message_id).is_none() {
+        return Ok(());
+    }
+    let message = channel_id.message(ctx, message_id).await?;
+    let recruit_message = config_storage::load(data, &message)?;
+    let state = &recruit_message.state;
+    let mut config = recruit_message.config;
+    if state.cancelled
+        || state.closed_entries.is_some()
+        || (state.started && config.closes_on_start())
+        || config.require_voice_at_start.is_none()
+        || data.storage.start_marker(message_id).is_some()
+    {
+        release(data, message_id);
+        return Ok(());
+    }
+    i18n::resolve_language(data, &mut config, guild_id);
+    let user_ids = collect_participants(ctx, data, &message, &config, guild_id).await?;
+    if user_ids.len() < config.required_players {
+        release(data, message_id);
+        return Ok(());
+    }
+    let policy = NotificationPolicy::new(&config);
+    let participants = participant_order::ordered(data, message_id, &user_ids);
+    send_start_notification(
+        ctx,
+        data,
+        &config,
+        &policy,
+        &message,
+        guild_id,
+        participants,
+    )
+    .await
+}
+
+/// `voice_channel_id` の VC に誰かが入ったときに, その VC を待っている募集を確かめ直します
+pub async fn handle_voice_join(ctx: &Context, data: &Data, voice_channel_id: ChannelId) {
+    for (message_id, hold) in data.storage.voice_holds_in(voice_channel_id) {
+        match resume(ctx, data, hold.channel_id, message_id, Some(hold.guild_id)).await {
+            Ok(()) => {}
+            Err(err) if unknown_entity(&err).is_some() => data.forget_deleted_message(message_id),
+            Err(err) => eprintln!("Failed to resume voice hold of {message_id}: {err}"),
+        }
+    }
+}
+
+/// 開始時に VC にいる参加者だけで開始します (channel 未指定で解除)
+#[poise::command(slash_command, guild_only, rename = "require_voice")]
+pub async fn require_voice(
+    ctx: poise::Context<'_, Data, Error>,
+    #[description = "募集メッセージのリンク"] message: Message,
+    #[description = "開始時に入っている必要がある VC"]
+    #[channel_types("Voice")]
+    channel: Option<GuildChannel>,
+    #[description = "VC に揃うのを待つ分数 (既定: 10)"]
+    #[min = 1]
+    #[max = 60]
+    grace_minutes: Option<u64>,
+) -> Result<(), Error> {
+    let data = ctx.data();
+    let serenity_ctx = ctx.serenity_context();
+    let _guard = data.lock_message(message.id).await;
+    let Some(recruit_message) = parse_bot_recruit_message(serenity_ctx, data, &message) else {
+        send_ephemeral(ctx, "指定されたメッセージは募集メッセージではありません。").await?;
+        return Ok(());
+    };
+
+    if !can_manage_recruit(ctx, &recruit_message.config) {
+        send_ephemeral(
+            ctx,
+            "主催者またはメッセージの管理権限を持つメンバーのみ設定できます。",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let config = &recruit_message.config;
+    let state = &recruit_message.state;
+    if state.cancelled
+        || (state.started && config.closes_on_start())
+        || state.closed_entries.is_some()
+    {
+        send_ephemeral(ctx, "この募集はすでに終了しています。").await?;
+        return Ok(());
+    }
+    // 開始予定の日時がある募集は人数が揃った時点で締め切るため, 開始時の VC を確かめられません
+    if config.has_option_reactions() || config.multi_lobby || config.start_time.is_some() {
+        send_ephemeral(
+            ctx,
+            "複数のゲームの募集、枠のある募集、ロビーを分ける募集、開始予定の日時がある募集では使えません。",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let voice_channel_id = channel.map(|channel| channel.id);
+    let voice_grace_minutes = grace_minutes
+        .filter(|&minutes| voice_channel_id.is_some() && minutes != DEFAULT_VOICE_GRACE_MINUTES);
+    let edited = recruit_edit::edit_recruit_message(
+        serenity_ctx,
+        data,
+        message.channel_id,
+        message.id,
+        |recruit_message| {
+            let state = &recruit_message.state;
+            if state.cancelled
+                || (state.started && recruit_message.config.closes_on_start())
+                || state.closed_entries.is_some()
+            {
+                return None;
+            }
+            recruit_message.config.require_voice_at_start = voice_channel_id;
+            recruit_message.config.voice_grace_minutes = voice_grace_minutes;
+            Some(())
+        },
+    )
+    .await?;
+    let Some((recruit_message, ())) = edited else {
+        send_ephemeral(ctx, "この募集はすでに終了しています。").await?;
+        return Ok(());
+    };
+
+    let reply = match voice_channel_id {
+        Some(voice_channel_id) => format!(
+            "開始時に {} にいる参加者だけで開始します",
+            voice_channel_id.mention()
+        ),
+        None => "開始時の VC の確認を解除しました".to_string(),
+    };
+    send_ephemeral(ctx, &reply).await?;
+
+    // 保留中の募集は, 新しい設定で確かめ直します
+    if data.storage.voice_hold(message.id).is_some() {
+        release(data, message.id);
+        let mut config = recruit_message.config;
+        i18n::resolve_language(data, &mut config, ctx.guild_id());
+        let user_ids =
+            collect_participants(serenity_ctx, data, &message, &config, ctx.guild_id()).await?;
+        if config.required_players <= user_ids.len() {
+            begin_start(
+                serenity_ctx,
+                data,
+                &config,
+                &message,
+                ctx.guild_id(),
+                user_ids,
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recruit_message::at;
+
+    fn users(ids: &[u64]) -> Vec<UserId> {
+        ids.iter().copied().map(UserId::new).collect()
+    }
+
+    #[test]
+    fn start_waits_for_participants_to_join_voice() {
+        let participants = users(&[11, 12, 13]);
+        let in_voice: HashSet<UserId> = users(&[13, 11]).into_iter().collect();
+        assert_eq!(
+            decide(&participants, &in_voice, 2, None, at(0)),
+            VoiceDecision::Start {
+                in_voice: users(&[11, 13]),
+                left_out: users(&[12]),
+            }
+        );
+        assert_eq!(
+            decide(&participants, &in_voice, 3, None, at(0)),
+            VoiceDecision::Hold {
+                missing: users(&[12])
+            }
+        );
+
+        // 保留した後は, 期限まで待ちます
+        let hold = VoiceHold {
+            channel_id: ChannelId::new(1),
+            guild_id: GuildId::new(2),
+            voice_channel_id: ChannelId::new(3),
+            deadline: at(60),
+        };
+        assert_eq!(
+            decide(&participants, &in_voice, 3, Some(&hold), at(59)),
+            VoiceDecision::Wait
+        );
+        assert_eq!(
+            decide(&participants, &in_voice, 3, Some(&hold), at(60)),
+            VoiceDecision::GiveUp
+        );
+    }
+}
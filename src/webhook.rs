@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use poise::serenity_prelude::{ChannelId, GuildId, MessageId, Timestamp, UserId};
+use reqwest::Url;
+use serde::Serialize;
+use tokio::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .expect("Failed to build webhook client")
+});
+
+/// 開始通知時に Webhook へ送信する内容
+#[derive(Debug, Serialize)]
+pub struct StartPayload {
+    pub game_title: String,
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub participants: Vec<Participant>,
+    pub jump_link: String,
+    pub timestamp: Timestamp,
+}
+
+impl StartPayload {
+    /// 参加者の名前は `names` から, なければユーザー ID にします
+    pub fn new(
+        game_title: String,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        user_ids: &[UserId],
+        names: &HashMap<UserId, String>,
+        timestamp: Timestamp,
+    ) -> Self {
+        let participants = user_ids
+            .iter()
+            .map(|&id| Participant {
+                id,
+                name: names.get(&id).cloned().unwrap_or_else(|| id.to_string()),
+            })
+            .collect();
+        StartPayload {
+            game_title,
+            guild_id,
+            channel_id,
+            message_id,
+            participants,
+            jump_link: message_id.link(channel_id, Some(guild_id)),
+            timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Participant {
+    pub id: UserId,
+    pub name: String,
+}
+
+/// 保存できる Webhook の URL か検証します (https のみ)
+pub fn validate_webhook_url(url: &str) -> Result<(), String> {
+    let url = Url::parse(url).map_err(|err| err.to_string())?;
+    if url.scheme() != "https" {
+        return Err("https の URL を指定してください".to_string());
+    }
+    if url.host_str().is_none() {
+        return Err("ホスト名がありません".to_string());
+    }
+    Ok(())
+}
+
+/// ログに出力できるよう, ホスト名以外を伏せた URL を返します
+pub fn redact_webhook_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(url) => format!(
+            "{}://{}/***",
+            url.scheme(),
+            url.host_str().unwrap_or_default()
+        ),
+        Err(_) => "***".to_string(),
+    }
+}
+
+/// 開始通知の送信を待たないよう, 別タスクで Webhook に送信します
+pub fn spawn_start_webhook(url: String, payload: StartPayload) {
+    tokio::spawn(async move {
+        if let Err(err) = post(&url, &payload).await {
+            eprintln!(
+                "Failed to send webhook to {}: {err}; retrying",
+                redact_webhook_url(&url)
+            );
+            tokio::time::sleep(RETRY_DELAY).await;
+            if let Err(err) = post(&url, &payload).await {
+                eprintln!(
+                    "Failed to send webhook to {}: {err}",
+                    redact_webhook_url(&url)
+                );
+            }
+        }
+    });
+}
+
+async fn post(url: &str, payload: &StartPayload) -> Result<(), reqwest::Error> {
+    CLIENT
+        .post(url)
+        .json(payload)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(reqwest::Error::without_url)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_payload_contract() {
+        let names = HashMap::from([(UserId::new(41), "たろう".to_string())]);
+        let payload = StartPayload::new(
+            "Apex".to_string(),
+            GuildId::new(1),
+            ChannelId::new(2),
+            MessageId::new(3),
+            &[UserId::new(41), UserId::new(42)],
+            &names,
+            Timestamp::from_unix_timestamp(1_700_000_000).unwrap(),
+        );
+        let expected = serde_json::json!({
+            "game_title": "Apex",
+            "guild_id": "1",
+            "channel_id": "2",
+            "message_id": "3",
+            "participants": [
+                { "id": "41", "name": "たろう" },
+                { "id": "42", "name": "42" },
+            ],
+            "jump_link": "https://discord.com/channels/1/2/3",
+            "timestamp": "2023-11-14T22:13:20Z",
+        });
+        assert_eq!(serde_json::to_value(&payload).unwrap(), expected);
+    }
+
+    #[test]
+    fn webhook_url_must_be_https() {
+        assert!(validate_webhook_url("https://example.com/hook").is_ok());
+        assert!(validate_webhook_url("http://example.com/hook").is_err());
+        assert!(validate_webhook_url("not a url").is_err());
+        assert_eq!(
+            redact_webhook_url("https://example.com/hook/secret"),
+            "https://example.com/***"
+        );
+    }
+}